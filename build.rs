@@ -10,11 +10,17 @@
 //! new memory settings.
 //!
 //! The build script also sets the linker flags to tell it which link script to use.
+//!
+//! Finally it passes the commit and build time to `aspeed_ddk::version`.
+//! `ASPEED_GIT_HASH` and `SOURCE_DATE_EPOCH` override them for reproducible
+//! builds.
 
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
@@ -41,4 +47,54 @@ fn main() {
 
     // Set the linker script to the one provided by cortex-m-rt.
     println!("cargo:rustc-link-arg=-Tlink.x");
+
+    version_info();
+}
+
+/// Commit hash and build time for `src/version.rs`; zeros when unknown
+fn version_info() {
+    println!("cargo:rerun-if-env-changed=ASPEED_GIT_HASH");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    track_git_head(Path::new(".git"));
+
+    let git_hash = env::var("ASPEED_GIT_HASH")
+        .ok()
+        .or_else(|| {
+            let out = Command::new("git")
+                .args(["rev-parse", "HEAD"])
+                .output()
+                .ok()?;
+            out.status
+                .success()
+                .then(|| String::from_utf8_lossy(&out.stdout).trim().to_owned())
+        })
+        .unwrap_or_else(|| "0".repeat(40));
+    let timestamp = env::var("SOURCE_DATE_EPOCH").unwrap_or_else(|_| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+            .to_string()
+    });
+    println!("cargo:rustc-env=ASPEED_GIT_HASH={git_hash}");
+    println!("cargo:rustc-env=ASPEED_BUILD_TIMESTAMP={timestamp}");
+}
+
+/// Reruns the build when the commit changes: `HEAD` moves on a checkout,
+/// the branch ref it points to on a commit, and `packed-refs` when git
+/// packs that ref
+fn track_git_head(git_dir: &Path) {
+    let head = git_dir.join("HEAD");
+    let Ok(contents) = fs::read_to_string(&head) else {
+        return;
+    };
+    println!("cargo:rerun-if-changed={}", head.display());
+    // also while the ref is only packed: cargo reruns for a missing path,
+    // which covers the loose ref the next commit creates
+    if let Some(branch) = contents.trim().strip_prefix("ref: ") {
+        println!("cargo:rerun-if-changed={}", git_dir.join(branch).display());
+    }
+    let packed = git_dir.join("packed-refs");
+    if packed.exists() {
+        println!("cargo:rerun-if-changed={}", packed.display());
+    }
 }
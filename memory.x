@@ -38,4 +38,10 @@ SECTIONS
     KEEP(*(.ram_nc));
     __ram_nc_end = .;
   } > RAM_NC
+
+  /* Key material, see keystore.rs. Not initialized at reset */
+  .keystore (NOLOAD) : ALIGN(4)
+  {
+    KEEP(*(.keystore));
+  } > RAM_NC
 }
@@ -1,7 +1,13 @@
 // Licensed under the Apache-2.0 license
 
+use crate::hace_controller::HaceController;
+use crate::hash::Sha256;
+use crate::syscon::{ClockId, SysCon};
 use crate::uart::UartController;
+use embedded_hal::delay::DelayNs;
 use embedded_io::Write;
+use proposed_traits::digest::{DigestInit, DigestOp};
+use proposed_traits::system_control::ClockControl;
 
 pub fn print_array_u32(uart: &mut UartController<'_>, data: &[u32]) {
     let bytes_per_line = 0x4;
@@ -60,3 +66,271 @@ pub fn print_reg_u32(uart: &mut UartController<'_>, reg_base: usize, size: usize
 
     writeln!(uart, "\r").unwrap();
 }
+
+/// Bytes of a console line, excluding the terminating CR
+const LINE_MAX: usize = 64;
+/// Most words one `peek` prints
+const PEEK_MAX: usize = 64;
+
+/// SHA-256 of "abc", FIPS 180-4 example B.1
+const POST_SHA256_ABC: [u8; 32] = [
+    0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23,
+    0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+];
+
+/// A parsed console line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Print `count` words from `addr`
+    Peek {
+        addr: usize,
+        count: usize,
+    },
+    /// Write `value` to the word at `addr`
+    Poke {
+        addr: usize,
+        value: u32,
+    },
+    /// Print the frequencies of the SoC clocks
+    Clocks,
+    /// Probe every 7-bit address on I2C bus `bus` of the console
+    Scan {
+        bus: usize,
+    },
+    /// Run the HACE known-answer test
+    Post,
+    Help,
+    Exit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    UnknownCommand,
+    /// An argument is missing, extra or not a number
+    BadArgument,
+    /// A register address is not word aligned
+    Unaligned,
+}
+
+/// Parses `0x`-prefixed hex or decimal
+fn parse_number(arg: &str) -> Result<usize, ParseError> {
+    let parsed = match arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+    parsed.map_err(|_| ParseError::BadArgument)
+}
+
+fn parse_addr(arg: Option<&str>) -> Result<usize, ParseError> {
+    let addr = parse_number(arg.ok_or(ParseError::BadArgument)?)?;
+    if addr % 4 != 0 {
+        return Err(ParseError::Unaligned);
+    }
+    Ok(addr)
+}
+
+pub fn parse_command(line: &str) -> Result<Command, ParseError> {
+    let mut words = line.split_whitespace();
+    let name = words.next().ok_or(ParseError::Empty)?;
+    let command = match name {
+        "peek" => {
+            let addr = parse_addr(words.next())?;
+            let count = words.next().map_or(Ok(1), parse_number)?;
+            if count == 0 || count > PEEK_MAX {
+                return Err(ParseError::BadArgument);
+            }
+            Command::Peek { addr, count }
+        }
+        "poke" => {
+            let addr = parse_addr(words.next())?;
+            let value = parse_number(words.next().ok_or(ParseError::BadArgument)?)?;
+            let value = u32::try_from(value).map_err(|_| ParseError::BadArgument)?;
+            Command::Poke { addr, value }
+        }
+        "clocks" => Command::Clocks,
+        "scan" => Command::Scan {
+            bus: parse_number(words.next().ok_or(ParseError::BadArgument)?)?,
+        },
+        "post" => Command::Post,
+        "help" => Command::Help,
+        "exit" => Command::Exit,
+        _ => return Err(ParseError::UnknownCommand),
+    };
+    if words.next().is_some() {
+        return Err(ParseError::BadArgument);
+    }
+    Ok(command)
+}
+
+/// An I2C bus the console can scan
+pub trait ProbeBus {
+    /// Whether a device acknowledges `addr`
+    fn probe(&mut self, addr: u8) -> bool;
+}
+
+impl<T: embedded_hal::i2c::I2c> ProbeBus for T {
+    fn probe(&mut self, addr: u8) -> bool {
+        self.read(addr, &mut [0u8]).is_ok()
+    }
+}
+
+/// Drivers the console commands act on
+pub struct ConsoleControllers<'a, 'b> {
+    /// Buses for `scan`, by index
+    pub i2c: &'a mut [&'b mut dyn ProbeBus],
+    /// Engine for `post`
+    pub hace: Option<&'a mut HaceController>,
+}
+
+/// Reads one line, echoing it; backspace edits, CR or LF ends it
+fn read_line<'l>(uart: &mut UartController<'_>, line: &'l mut [u8; LINE_MAX]) -> &'l str {
+    let mut len = 0;
+    loop {
+        match uart.read_byte() {
+            Ok(b'\r' | b'\n') => break,
+            Ok(0x08 | 0x7f) if len > 0 => {
+                len -= 1;
+                uart.write_all(b"\x08 \x08").unwrap();
+            }
+            Ok(b) if (0x20..0x7f).contains(&b) && len < LINE_MAX => {
+                line[len] = b;
+                len += 1;
+                uart.send_byte_fifo(b);
+            }
+            _ => {}
+        }
+    }
+    writeln!(uart, "\r").unwrap();
+    // only printable ASCII was stored
+    core::str::from_utf8(&line[..len]).unwrap_or("")
+}
+
+fn print_clocks<D: DelayNs>(uart: &mut UartController<'_>, syscon: &SysCon<D>) {
+    for (name, clock) in [
+        ("HCLK", ClockId::ClkHCLK),
+        ("PCLK", ClockId::ClkPCLK),
+        ("I3C", ClockId::ClkI3C0),
+    ] {
+        match ClockControl::get_frequency(syscon, &clock) {
+            Ok(hz) => writeln!(uart, "{name}: {hz} Hz\r").unwrap(),
+            Err(e) => writeln!(uart, "{name}: {e:?}\r").unwrap(),
+        }
+    }
+}
+
+fn scan_bus(uart: &mut UartController<'_>, bus: &mut dyn ProbeBus) {
+    let mut found = 0;
+    for addr in 0x08..0x78 {
+        if bus.probe(addr) {
+            writeln!(uart, "  {addr:#04x}\r").unwrap();
+            found += 1;
+        }
+    }
+    writeln!(uart, "{found} devices\r").unwrap();
+}
+
+fn run_post(uart: &mut UartController<'_>, hace: &mut HaceController) {
+    let pass = match hace.init(Sha256) {
+        Ok(mut ctx) => {
+            ctx.update(b"abc").is_ok()
+                && ctx.finalize().is_ok_and(|digest| digest == POST_SHA256_ABC)
+        }
+        Err(_) => false,
+    };
+    let verdict = if pass { "PASS" } else { "FAIL" };
+    writeln!(uart, "SHA-256 KAT: {verdict}\r").unwrap();
+}
+
+/// Interactive peek/poke console for field debugging.
+///
+/// Reads commands from `uart` until `exit`. `peek` and `poke` access any
+/// address, so a wrong one can fault or change the SoC state.
+pub fn console<D: DelayNs>(
+    uart: &mut UartController<'_>,
+    syscon: &mut SysCon<D>,
+    controllers: &mut ConsoleControllers<'_, '_>,
+) {
+    let mut line = [0u8; LINE_MAX];
+    writeln!(uart, "\r\ndebug console, 'help' for commands\r").unwrap();
+    loop {
+        write!(uart, "> ").unwrap();
+        let command = match parse_command(read_line(uart, &mut line)) {
+            Ok(command) => command,
+            Err(ParseError::Empty) => continue,
+            Err(e) => {
+                writeln!(uart, "error: {e:?}\r").unwrap();
+                continue;
+            }
+        };
+        match command {
+            Command::Peek { addr, count } => print_reg_u32(uart, addr, count * 4),
+            Command::Poke { addr, value } => {
+                // SAFETY: none; the operator asked for this write
+                unsafe { core::ptr::write_volatile(addr as *mut u32, value) };
+            }
+            Command::Clocks => print_clocks(uart, syscon),
+            Command::Scan { bus } => match controllers.i2c.get_mut(bus) {
+                Some(bus) => scan_bus(uart, &mut **bus),
+                None => writeln!(uart, "no bus {bus}\r").unwrap(),
+            },
+            Command::Post => match controllers.hace.as_deref_mut() {
+                Some(hace) => run_post(uart, hace),
+                None => writeln!(uart, "no HACE\r").unwrap(),
+            },
+            Command::Help => writeln!(
+                uart,
+                "peek <addr> [words] | poke <addr> <value> | clocks | scan <bus> | post | exit\r"
+            )
+            .unwrap(),
+            Command::Exit => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_command, Command, ParseError};
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            parse_command("peek 0x7e6e2000 4"),
+            Ok(Command::Peek {
+                addr: 0x7e6e_2000,
+                count: 4
+            })
+        );
+        assert_eq!(
+            parse_command("  poke 0x1000  255 "),
+            Ok(Command::Poke {
+                addr: 0x1000,
+                value: 255
+            })
+        );
+        assert_eq!(
+            parse_command("peek 0x10"),
+            Ok(Command::Peek {
+                addr: 0x10,
+                count: 1
+            })
+        );
+        assert_eq!(parse_command("scan 2"), Ok(Command::Scan { bus: 2 }));
+        assert_eq!(parse_command("post"), Ok(Command::Post));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_command("   "), Err(ParseError::Empty));
+        assert_eq!(parse_command("reboot"), Err(ParseError::UnknownCommand));
+        assert_eq!(parse_command("peek 0x12"), Err(ParseError::Unaligned));
+        assert_eq!(parse_command("peek 0x10 0"), Err(ParseError::BadArgument));
+        assert_eq!(parse_command("poke 0x10"), Err(ParseError::BadArgument));
+        assert_eq!(
+            parse_command("poke 0x10 0x100000000"),
+            Err(ParseError::BadArgument)
+        );
+        assert_eq!(parse_command("clocks now"), Err(ParseError::BadArgument));
+        assert_eq!(parse_command("scan zz"), Err(ParseError::BadArgument));
+    }
+}
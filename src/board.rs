@@ -0,0 +1,161 @@
+// Licensed under the Apache-2.0 license
+
+//! One-time acquisition of the SoC peripherals
+//!
+//! [`Board::take`] claims the PAC peripherals once and hands them out
+//! grouped by the driver that is built from them. It takes them through
+//! [`Peripherals::take`], so later calls, and any direct
+//! `Peripherals::take` once the board exists, return `None` and cannot
+//! alias a register block another driver owns.
+
+use crate::gpio::{
+    gpioa, gpiob, gpioc, gpiod, gpioe, gpiof, gpiog, gpioh, gpioi, gpioj, gpiok, gpiol, gpiom,
+    gpion, gpioo, gpiop, gpioq, gpior, gpios, gpiot, gpiou,
+};
+use crate::watchdog::WdtController;
+use ast1060_pac::{
+    Fmc, Hace, I2c, I2c1, I2c10, I2c11, I2c12, I2c13, I2c2, I2c3, I2c4, I2c5, I2c6, I2c7, I2c8,
+    I2c9, Peripherals, Scu, Secure, Spi, Spi1, Uart, Wdt, Wdt1,
+};
+
+/// GPIO ports, not yet split into pins
+pub struct GpioPorts {
+    pub gpioa: gpioa::GPIOA,
+    pub gpiob: gpiob::GPIOB,
+    pub gpioc: gpioc::GPIOC,
+    pub gpiod: gpiod::GPIOD,
+    pub gpioe: gpioe::GPIOE,
+    pub gpiof: gpiof::GPIOF,
+    pub gpiog: gpiog::GPIOG,
+    pub gpioh: gpioh::GPIOH,
+    pub gpioi: gpioi::GPIOI,
+    pub gpioj: gpioj::GPIOJ,
+    pub gpiok: gpiok::GPIOK,
+    pub gpiol: gpiol::GPIOL,
+    pub gpiom: gpiom::GPIOM,
+    pub gpion: gpion::GPION,
+    pub gpioo: gpioo::GPIOO,
+    pub gpiop: gpiop::GPIOP,
+    pub gpioq: gpioq::GPIOQ,
+    pub gpior: gpior::GPIOR,
+    pub gpios: gpios::GPIOS,
+    pub gpiot: gpiot::GPIOT,
+    pub gpiou: gpiou::GPIOU,
+}
+
+/// I2C bus register blocks. `Ast1060I2c` reaches its bus through the
+/// type parameter, so holding the block is what reserves the bus.
+pub struct I2cBuses {
+    pub i2c0: I2c,
+    pub i2c1: I2c1,
+    pub i2c2: I2c2,
+    pub i2c3: I2c3,
+    pub i2c4: I2c4,
+    pub i2c5: I2c5,
+    pub i2c6: I2c6,
+    pub i2c7: I2c7,
+    pub i2c8: I2c8,
+    pub i2c9: I2c9,
+    pub i2c10: I2c10,
+    pub i2c11: I2c11,
+    pub i2c12: I2c12,
+    pub i2c13: I2c13,
+}
+
+/// Peripherals for the crate's drivers, none of them initialized yet
+pub struct Board {
+    /// For `UartController::new`
+    pub uart: Uart,
+    /// For `SysCon::new`
+    pub scu: Scu,
+    /// For `HaceController::new`
+    pub hace: Hace,
+    /// For `AspeedEcdsa::new` and `AspeedRsa::new`
+    pub secure: Secure,
+    pub wdt0: WdtController<Wdt>,
+    pub wdt1: WdtController<Wdt1>,
+    pub gpio: GpioPorts,
+    pub i2c: I2cBuses,
+    /// For `FmcController::new`
+    pub fmc: Fmc,
+    /// For `SpiController::new`
+    pub spi0: Spi,
+    pub spi1: Spi1,
+}
+
+impl Board {
+    /// Claims the peripherals; `None` once they have been claimed, through
+    /// the board or directly from the PAC
+    #[must_use]
+    pub fn take() -> Option<Self> {
+        let p = Peripherals::take()?;
+        // Each GPIO port driver holds the shared GPIO block; they touch
+        // disjoint bit fields of it
+        let gpio = || unsafe { Peripherals::steal() }.gpio;
+        Some(Self {
+            uart: p.uart,
+            scu: p.scu,
+            hace: p.hace,
+            secure: p.secure,
+            wdt0: WdtController::new(),
+            wdt1: WdtController::new(),
+            gpio: GpioPorts {
+                gpioa: gpioa::GPIOA::new(p.gpio),
+                gpiob: gpiob::GPIOB::new(gpio()),
+                gpioc: gpioc::GPIOC::new(gpio()),
+                gpiod: gpiod::GPIOD::new(gpio()),
+                gpioe: gpioe::GPIOE::new(gpio()),
+                gpiof: gpiof::GPIOF::new(gpio()),
+                gpiog: gpiog::GPIOG::new(gpio()),
+                gpioh: gpioh::GPIOH::new(gpio()),
+                gpioi: gpioi::GPIOI::new(gpio()),
+                gpioj: gpioj::GPIOJ::new(gpio()),
+                gpiok: gpiok::GPIOK::new(gpio()),
+                gpiol: gpiol::GPIOL::new(gpio()),
+                gpiom: gpiom::GPIOM::new(gpio()),
+                gpion: gpion::GPION::new(gpio()),
+                gpioo: gpioo::GPIOO::new(gpio()),
+                gpiop: gpiop::GPIOP::new(gpio()),
+                gpioq: gpioq::GPIOQ::new(gpio()),
+                gpior: gpior::GPIOR::new(gpio()),
+                gpios: gpios::GPIOS::new(gpio()),
+                gpiot: gpiot::GPIOT::new(gpio()),
+                gpiou: gpiou::GPIOU::new(gpio()),
+            },
+            i2c: I2cBuses {
+                i2c0: p.i2c,
+                i2c1: p.i2c1,
+                i2c2: p.i2c2,
+                i2c3: p.i2c3,
+                i2c4: p.i2c4,
+                i2c5: p.i2c5,
+                i2c6: p.i2c6,
+                i2c7: p.i2c7,
+                i2c8: p.i2c8,
+                i2c9: p.i2c9,
+                i2c10: p.i2c10,
+                i2c11: p.i2c11,
+                i2c12: p.i2c12,
+                i2c13: p.i2c13,
+            },
+            fmc: p.fmc,
+            spi0: p.spi,
+            spi1: p.spi1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Board;
+
+    #[test]
+    fn test_take_once() {
+        let board = Board::take();
+        assert!(board.is_some());
+        assert!(Board::take().is_none());
+        // dropping the board does not make the peripherals available again
+        drop(board);
+        assert!(Board::take().is_none());
+    }
+}
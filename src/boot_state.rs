@@ -0,0 +1,418 @@
+// Licensed under the Apache-2.0 license
+
+//! Boot attempt counter and anti-rollback version floor
+//!
+//! Both values live in a [`KvStore`], whose records are CRC-checked and
+//! appended rather than overwritten. A write torn by power loss is dropped
+//! on the next mount and the previous value stays in effect, so the
+//! counter never goes backwards past a completed boot and the floor never
+//! drops.
+//!
+//! Early in boot, [`BootCounter::begin_boot`] counts the attempt. Once the
+//! image is known to work, [`BootCounter::record_successful_boot`] clears
+//! the count. When an image keeps failing before that point,
+//! `begin_boot` answers [`BootDecision::Failover`] and the caller switches
+//! to the other image.
+//!
+//! [`BootCounter::check_version`] rejects images older than the floor;
+//! [`BootCounter::raise_min_version`] moves the floor up once a newer image
+//! has proven itself.
+//!
+//! [`BootLoopGuard`] counts consecutive resets by the watchdog, as reported
+//! by [`crate::watchdog::WdtController::reset_by_timeout`], and hands
+//! control to a recovery callback once the streak reaches a threshold.
+
+use crate::kvstore::{KvError, KvStore};
+use crate::spi::norflashblockdevice::BlockAddrUsize;
+use proposed_traits::block_device::BlockDevice;
+
+/// Key of the boot attempt counter
+pub const KEY_BOOT_ATTEMPTS: u16 = 0xB007;
+/// Key of the minimum accepted image version
+pub const KEY_MIN_VERSION: u16 = 0xB008;
+/// Key of the count of consecutive watchdog resets
+pub const KEY_WDT_RESET_STREAK: u16 = 0xB009;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootStateError {
+    Store(KvError),
+    /// A stored value does not have the expected size
+    Corrupt,
+    /// The image is older than the stored minimum version
+    Rollback {
+        version: u32,
+        min: u32,
+    },
+}
+
+impl From<KvError> for BootStateError {
+    fn from(e: KvError) -> Self {
+        Self::Store(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootDecision {
+    /// Boot the current image; this is attempt `attempt`, counted from 1
+    Continue { attempt: u32 },
+    /// The current image failed `attempts` times without reaching
+    /// [`BootCounter::record_successful_boot`]
+    Failover { attempts: u32 },
+}
+
+pub struct BootCounter<'a, D, const MAX_KEYS: usize> {
+    store: &'a mut KvStore<D, MAX_KEYS>,
+    max_attempts: u32,
+}
+
+impl<'a, D, const MAX_KEYS: usize> BootCounter<'a, D, MAX_KEYS>
+where
+    D: BlockDevice<Address = BlockAddrUsize>,
+{
+    /// Fails over once more than `max_attempts` boots in a row have not
+    /// been recorded as successful. The store stays usable for other keys
+    /// once the counter is dropped.
+    pub fn new(store: &'a mut KvStore<D, MAX_KEYS>, max_attempts: u32) -> Self {
+        Self {
+            store,
+            max_attempts,
+        }
+    }
+
+    /// Counts this boot attempt; call before anything that may hang.
+    ///
+    /// The counter is written before the decision is returned, so a boot
+    /// that dies right after still counts.
+    pub fn begin_boot(&mut self) -> Result<BootDecision, BootStateError> {
+        let attempt = self.attempts()?.saturating_add(1);
+        self.write_u32(KEY_BOOT_ATTEMPTS, attempt)?;
+        if attempt > self.max_attempts {
+            Ok(BootDecision::Failover {
+                attempts: attempt - 1,
+            })
+        } else {
+            Ok(BootDecision::Continue { attempt })
+        }
+    }
+
+    /// Clears the attempt counter once the running image works
+    pub fn record_successful_boot(&mut self) -> Result<(), BootStateError> {
+        if self.attempts()? != 0 {
+            self.write_u32(KEY_BOOT_ATTEMPTS, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Boots counted since the last successful one
+    pub fn attempts(&mut self) -> Result<u32, BootStateError> {
+        Ok(self.read_u32(KEY_BOOT_ATTEMPTS)?.unwrap_or(0))
+    }
+
+    /// Oldest image version that may be installed or booted; 0 until set
+    pub fn min_version(&mut self) -> Result<u32, BootStateError> {
+        Ok(self.read_u32(KEY_MIN_VERSION)?.unwrap_or(0))
+    }
+
+    /// Fails with `Rollback` if `version` is below the floor
+    pub fn check_version(&mut self, version: u32) -> Result<(), BootStateError> {
+        let min = self.min_version()?;
+        if version < min {
+            return Err(BootStateError::Rollback { version, min });
+        }
+        Ok(())
+    }
+
+    /// Raises the floor to `version`; a lower version leaves it unchanged
+    pub fn raise_min_version(&mut self, version: u32) -> Result<(), BootStateError> {
+        if version > self.min_version()? {
+            self.write_u32(KEY_MIN_VERSION, version)?;
+        }
+        Ok(())
+    }
+
+    fn read_u32(&mut self, key: u16) -> Result<Option<u32>, BootStateError> {
+        read_u32(self.store, key)
+    }
+
+    fn write_u32(&mut self, key: u16, value: u32) -> Result<(), BootStateError> {
+        write_u32(self.store, key, value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootLoopDecision {
+    /// Boot normally; `streak` watchdog resets in a row preceded this boot
+    Proceed { streak: u32 },
+    /// The recovery callback ran after `streak` watchdog resets in a row
+    Recovery { streak: u32 },
+}
+
+/// Boot-loop detection from consecutive watchdog resets
+pub struct BootLoopGuard<'a, D, const MAX_KEYS: usize> {
+    store: &'a mut KvStore<D, MAX_KEYS>,
+    by_watchdog: bool,
+    counted: bool,
+}
+
+impl<'a, D, const MAX_KEYS: usize> BootLoopGuard<'a, D, MAX_KEYS>
+where
+    D: BlockDevice<Address = BlockAddrUsize>,
+{
+    /// `by_watchdog` is the reset reason of this boot, normally
+    /// `reset_by_timeout()` of the watchdog read before it is started
+    pub fn new(store: &'a mut KvStore<D, MAX_KEYS>, by_watchdog: bool) -> Self {
+        Self {
+            store,
+            by_watchdog,
+            counted: false,
+        }
+    }
+
+    /// Counts this boot into the streak and runs `recovery` with the streak
+    /// once it reaches `threshold`; call early in boot.
+    ///
+    /// A watchdog reset extends the streak and any other reset ends it. The
+    /// boot is counted once, however often this is called. `recovery` is
+    /// where the caller arms the alternate image or enters a minimal mode.
+    pub fn check(
+        &mut self,
+        threshold: u32,
+        recovery: impl FnOnce(u32),
+    ) -> Result<BootLoopDecision, BootStateError> {
+        let mut streak = self.wdt_reset_streak()?;
+        if !self.counted {
+            let counted = if self.by_watchdog {
+                streak.saturating_add(1)
+            } else {
+                0
+            };
+            if counted != streak {
+                write_u32(self.store, KEY_WDT_RESET_STREAK, counted)?;
+            }
+            streak = counted;
+            self.counted = true;
+        }
+        if streak >= threshold {
+            recovery(streak);
+            Ok(BootLoopDecision::Recovery { streak })
+        } else {
+            Ok(BootLoopDecision::Proceed { streak })
+        }
+    }
+
+    /// Watchdog resets in a row, including this boot once it is counted
+    pub fn wdt_reset_streak(&mut self) -> Result<u32, BootStateError> {
+        Ok(read_u32(self.store, KEY_WDT_RESET_STREAK)?.unwrap_or(0))
+    }
+
+    /// Ends the streak once the image has fully come up
+    pub fn record_successful_boot(&mut self) -> Result<(), BootStateError> {
+        if self.wdt_reset_streak()? != 0 {
+            write_u32(self.store, KEY_WDT_RESET_STREAK, 0)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_u32<D, const MAX_KEYS: usize>(
+    store: &mut KvStore<D, MAX_KEYS>,
+    key: u16,
+) -> Result<Option<u32>, BootStateError>
+where
+    D: BlockDevice<Address = BlockAddrUsize>,
+{
+    let mut buf = [0u8; 4];
+    match store.get(key, &mut buf) {
+        Ok(4) => Ok(Some(u32::from_le_bytes(buf))),
+        Ok(_) | Err(KvError::BufferTooSmall(_)) => Err(BootStateError::Corrupt),
+        Err(KvError::NotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_u32<D, const MAX_KEYS: usize>(
+    store: &mut KvStore<D, MAX_KEYS>,
+    key: u16,
+    value: u32,
+) -> Result<(), BootStateError>
+where
+    D: BlockDevice<Address = BlockAddrUsize>,
+{
+    Ok(store.set(key, &value.to_le_bytes())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BootCounter, BootDecision, BootLoopDecision, BootLoopGuard, BootStateError,
+        KEY_BOOT_ATTEMPTS, KEY_MIN_VERSION,
+    };
+    use crate::kvstore::KvStore;
+    use crate::mock_flash::MockFlash;
+
+    type Flash = MockFlash<4, 1024>;
+    type Store = KvStore<Flash, 8>;
+
+    fn mount(flash: Flash) -> Store {
+        Store::mount(flash, 0, 4).unwrap()
+    }
+
+    fn reboot(store: Store) -> Store {
+        mount(store.into_inner())
+    }
+
+    fn counter(store: &mut Store) -> BootCounter<'_, Flash, 8> {
+        BootCounter::new(store, 3)
+    }
+
+    #[test]
+    fn test_counter_lifecycle() {
+        let mut store = mount(Flash::new(128));
+        assert_eq!(counter(&mut store).attempts(), Ok(0));
+        for attempt in 1..=3 {
+            assert_eq!(
+                counter(&mut store).begin_boot(),
+                Ok(BootDecision::Continue { attempt })
+            );
+            store = reboot(store);
+        }
+        // the fourth boot in a row without success fails over
+        assert_eq!(
+            counter(&mut store).begin_boot(),
+            Ok(BootDecision::Failover { attempts: 3 })
+        );
+
+        // the other image comes up and records success
+        store = reboot(store);
+        let mut state = counter(&mut store);
+        assert!(matches!(
+            state.begin_boot(),
+            Ok(BootDecision::Failover { .. })
+        ));
+        state.record_successful_boot().unwrap();
+        store = reboot(store);
+        let mut state = counter(&mut store);
+        assert_eq!(state.attempts(), Ok(0));
+        assert_eq!(
+            state.begin_boot(),
+            Ok(BootDecision::Continue { attempt: 1 })
+        );
+    }
+
+    #[test]
+    fn test_rollback_rejected() {
+        let mut store = mount(Flash::new(128));
+        let mut state = counter(&mut store);
+        assert_eq!(state.check_version(0), Ok(()));
+        state.raise_min_version(7).unwrap();
+        state.raise_min_version(5).unwrap();
+        store = reboot(store);
+        let mut state = counter(&mut store);
+        assert_eq!(state.min_version(), Ok(7));
+        assert_eq!(state.check_version(7), Ok(()));
+        assert_eq!(
+            state.check_version(6),
+            Err(BootStateError::Rollback { version: 6, min: 7 })
+        );
+    }
+
+    #[test]
+    fn test_crafted_metadata() {
+        let mut store = mount(Flash::new(128));
+        store.set(KEY_MIN_VERSION, &9u32.to_le_bytes()).unwrap();
+        store.set(KEY_BOOT_ATTEMPTS, &[1, 0]).unwrap();
+        let mut state = counter(&mut store);
+        assert_eq!(
+            state.check_version(8),
+            Err(BootStateError::Rollback { version: 8, min: 9 })
+        );
+        // a malformed counter is reported instead of being read as zero
+        assert_eq!(state.begin_boot(), Err(BootStateError::Corrupt));
+    }
+
+    #[test]
+    fn test_torn_writes() {
+        // Cut power at every program of a boot, success and floor update
+        for cut in 0..8 {
+            let mut store = mount(Flash::new(128));
+            let mut state = counter(&mut store);
+            state.begin_boot().unwrap();
+            state.raise_min_version(2).unwrap();
+
+            let mut flash = store.into_inner();
+            flash.power_cut_after(cut);
+            let mut store = mount(flash);
+            let mut state = counter(&mut store);
+            let torn = state
+                .begin_boot()
+                .and_then(|_| state.record_successful_boot())
+                .and_then(|()| state.raise_min_version(3))
+                .is_err();
+
+            let mut flash = store.into_inner();
+            flash.clear_faults();
+            let mut store = mount(flash);
+            let mut state = counter(&mut store);
+            // each value is either the old or the new one
+            assert!(matches!(state.attempts(), Ok(0..=2)), "cut {cut}");
+            assert!(matches!(state.min_version(), Ok(2 | 3)), "cut {cut}");
+            if !torn {
+                assert_eq!(state.attempts(), Ok(0));
+                assert_eq!(state.min_version(), Ok(3));
+            }
+            state.begin_boot().unwrap();
+        }
+    }
+
+    /// Boots with the given reset reasons; returns the last decision and
+    /// how often recovery ran
+    fn boot_sequence(flash: Flash, resets: &[bool]) -> (Flash, BootLoopDecision, u32) {
+        let mut flash = flash;
+        let mut decision = BootLoopDecision::Proceed { streak: 0 };
+        let mut recoveries = 0;
+        for &by_watchdog in resets {
+            let mut store = mount(flash);
+            let mut guard = BootLoopGuard::new(&mut store, by_watchdog);
+            decision = guard.check(3, |_| recoveries += 1).unwrap();
+            flash = store.into_inner();
+        }
+        (flash, decision, recoveries)
+    }
+
+    #[test]
+    fn test_wdt_reset_streak() {
+        let (flash, decision, recoveries) = boot_sequence(Flash::new(128), &[true, true]);
+        assert_eq!(decision, BootLoopDecision::Proceed { streak: 2 });
+        assert_eq!(recoveries, 0);
+
+        let (flash, decision, recoveries) = boot_sequence(flash, &[true]);
+        assert_eq!(decision, BootLoopDecision::Recovery { streak: 3 });
+        assert_eq!(recoveries, 1);
+
+        // a power-on or software reset ends the streak
+        let (_, decision, recoveries) = boot_sequence(flash, &[false, true]);
+        assert_eq!(decision, BootLoopDecision::Proceed { streak: 1 });
+        assert_eq!(recoveries, 0);
+    }
+
+    #[test]
+    fn test_streak_cleared_on_success() {
+        let (flash, _, _) = boot_sequence(Flash::new(128), &[true, true]);
+        let mut store = mount(flash);
+        let mut guard = BootLoopGuard::new(&mut store, true);
+        assert_eq!(
+            guard.check(3, |_| {}),
+            Ok(BootLoopDecision::Recovery { streak: 3 })
+        );
+        // counted once per boot
+        assert_eq!(
+            guard.check(5, |_| {}),
+            Ok(BootLoopDecision::Proceed { streak: 3 })
+        );
+        guard.record_successful_boot().unwrap();
+        assert_eq!(guard.wdt_reset_streak(), Ok(0));
+
+        let (_, decision, _) = boot_sequence(store.into_inner(), &[true]);
+        assert_eq!(decision, BootLoopDecision::Proceed { streak: 1 });
+    }
+}
@@ -0,0 +1,205 @@
+// Licensed under the Apache-2.0 license
+
+//! Boot time milestone tracing
+//!
+//! [`mark`] stores the milestone and the DWT cycle counter in a fixed table,
+//! so it can be called before the UART is up. [`report`] prints the table
+//! later, with the time between milestones in microseconds. The cycle
+//! counter is 32 bits wide, so a single gap must stay below one wrap
+//! (about 21 s at 200 MHz).
+
+use crate::uart::UartController;
+use core::ptr::addr_of_mut;
+use cortex_m::peripheral::DWT;
+use embedded_io::Write;
+
+pub const BOOT_TRACE_CAPACITY: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Milestone {
+    Start,
+    UartUp,
+    ClockInit,
+    HacePostDone,
+    HmacTests,
+    EcdsaTests,
+    RsaTests,
+    GpioTests,
+    I2cTests,
+    WdtTests,
+    TimerTests,
+    HostPolicyTests,
+    InterruptTests,
+    SpiTests,
+    Done,
+}
+
+impl Milestone {
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Milestone::Start => "start",
+            Milestone::UartUp => "uart up",
+            Milestone::ClockInit => "clock init",
+            Milestone::HacePostDone => "hace post done",
+            Milestone::HmacTests => "hmac tests",
+            Milestone::EcdsaTests => "ecdsa tests",
+            Milestone::RsaTests => "rsa tests",
+            Milestone::GpioTests => "gpio tests",
+            Milestone::I2cTests => "i2c tests",
+            Milestone::WdtTests => "wdt tests",
+            Milestone::TimerTests => "timer tests",
+            Milestone::HostPolicyTests => "host policy tests",
+            Milestone::InterruptTests => "interrupt tests",
+            Milestone::SpiTests => "spi tests",
+            Milestone::Done => "done",
+        }
+    }
+}
+
+/// Fixed size table of (milestone, cycle count) records
+pub struct BootTrace<const N: usize> {
+    records: [(Milestone, u32); N],
+    len: usize,
+    dropped: usize,
+}
+
+impl<const N: usize> Default for BootTrace<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> BootTrace<N> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            records: [(Milestone::Start, 0); N],
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Records a milestone; once the table is full further marks are counted
+    /// but not stored
+    pub fn record(&mut self, milestone: Milestone, cycles: u32) {
+        if let Some(slot) = self.records.get_mut(self.len) {
+            *slot = (milestone, cycles);
+            self.len += 1;
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[must_use]
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Yields each milestone with the cycles since the previous one and
+    /// since the first one. Counter wraps between records are handled.
+    pub fn deltas(&self) -> impl Iterator<Item = (Milestone, u32, u64)> + '_ {
+        let records = &self.records[..self.len];
+        let mut prev = records.first().map_or(0, |r| r.1);
+        let mut total = 0u64;
+        records.iter().map(move |&(milestone, cycles)| {
+            let delta = cycles.wrapping_sub(prev);
+            prev = cycles;
+            total += u64::from(delta);
+            (milestone, delta, total)
+        })
+    }
+}
+
+/// Converts a cycle count to microseconds at `cpu_hz`
+#[must_use]
+pub fn cycles_to_us(cycles: u64, cpu_hz: u32) -> u64 {
+    cycles * 1_000_000 / u64::from(cpu_hz.max(1))
+}
+
+static mut BOOT_TRACE: BootTrace<BOOT_TRACE_CAPACITY> = BootTrace::new();
+
+/// Starts the DWT cycle counter. Call once, before the first [`mark`].
+pub fn init() {
+    let mut cp = unsafe { cortex_m::Peripherals::steal() };
+    cp.DCB.enable_trace();
+    cp.DWT.set_cycle_count(0);
+    cp.DWT.enable_cycle_counter();
+}
+
+pub fn mark(milestone: Milestone) {
+    let cycles = DWT::cycle_count();
+    cortex_m::interrupt::free(|_| unsafe {
+        (*addr_of_mut!(BOOT_TRACE)).record(milestone, cycles);
+    });
+}
+
+pub fn report(uart: &mut UartController<'_>, cpu_hz: u32) {
+    cortex_m::interrupt::free(|_| {
+        let trace = unsafe { &*addr_of_mut!(BOOT_TRACE) };
+        writeln!(uart, "\r\n####### boot trace #######\r").unwrap();
+        for (milestone, delta, total) in trace.deltas() {
+            writeln!(
+                uart,
+                "{:<18} +{:>8} us  {:>9} us\r",
+                milestone.name(),
+                cycles_to_us(u64::from(delta), cpu_hz),
+                cycles_to_us(total, cpu_hz)
+            )
+            .unwrap();
+        }
+        if trace.dropped() > 0 {
+            writeln!(uart, "{} milestones dropped\r", trace.dropped()).unwrap();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cycles_to_us, BootTrace, Milestone};
+
+    #[test]
+    fn test_deltas() {
+        let mut trace = BootTrace::<3>::new();
+        assert!(trace.is_empty());
+        trace.record(Milestone::Start, 1_000);
+        trace.record(Milestone::UartUp, 201_000);
+        trace.record(Milestone::ClockInit, 601_000);
+        trace.record(Milestone::Done, 700_000);
+        assert_eq!(trace.len(), 3);
+        assert_eq!(trace.dropped(), 1);
+
+        let mut deltas = trace.deltas();
+        assert_eq!(deltas.next(), Some((Milestone::Start, 0, 0)));
+        assert_eq!(deltas.next(), Some((Milestone::UartUp, 200_000, 200_000)));
+        assert_eq!(
+            deltas.next(),
+            Some((Milestone::ClockInit, 400_000, 600_000))
+        );
+        assert_eq!(deltas.next(), None);
+
+        assert_eq!(cycles_to_us(200_000, 200_000_000), 1_000);
+        assert_eq!(cycles_to_us(199, 200_000_000), 0);
+    }
+
+    #[test]
+    fn test_counter_wrap() {
+        let mut trace = BootTrace::<4>::new();
+        trace.record(Milestone::Start, u32::MAX - 99);
+        trace.record(Milestone::UartUp, 100);
+        trace.record(Milestone::Done, 300);
+        let deltas: Vec<_> = trace.deltas().map(|(_, d, t)| (d, t)).collect();
+        assert_eq!(deltas, [(0, 0), (200, 200), (200, 400)]);
+    }
+}
@@ -1,9 +1,14 @@
 // Licensed under the Apache-2.0 license
 
 use crate::uart::UartController;
+use core::cell::Cell;
 use core::ops::{Index, IndexMut};
+use core::time::Duration;
 use embedded_io::Write;
 
+pub mod cache;
+pub mod dma_pool;
+
 pub struct DummyDelay;
 
 impl embedded_hal::delay::DelayNs for DummyDelay {
@@ -77,6 +82,64 @@ impl<const N: usize> IndexMut<usize> for DmaBuffer<N> {
 pub trait Logger {
     fn debug(&mut self, msg: &str);
     fn error(&mut self, msg: &str);
+
+    /// Logs `msg` with key/value fields.
+    ///
+    /// The default renders `msg key=value ...` (see [`write_kv`]) into a
+    /// bounded line and passes it to [`Self::debug`] or [`Self::error`];
+    /// loggers that can emit fields natively should override it.
+    fn log_kv(&mut self, level: LogLevel, msg: &str, fields: &[(&str, LogValue<'_>)]) {
+        let mut buf: heapless::String<128> = heapless::String::new();
+        // A truncated line is still more useful than none
+        let _ = write_kv(&mut buf, msg, fields);
+        match level {
+            LogLevel::Debug => self.debug(buf.as_str()),
+            LogLevel::Error => self.error(buf.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LogLevel {
+    Debug,
+    Error,
+}
+
+/// Value of a structured log field
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LogValue<'a> {
+    U32(u32),
+    I32(i32),
+    Bytes(&'a [u8]),
+    Str(&'a str),
+}
+
+impl core::fmt::Display for LogValue<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::U32(v) => write!(f, "{v:#x}"),
+            Self::I32(v) => write!(f, "{v}"),
+            Self::Bytes(b) => write!(f, "{:?}", HexDump(b)),
+            Self::Str(s) => write!(f, "{s:?}"),
+        }
+    }
+}
+
+/// Renders a structured record as `msg key=value key=value`.
+///
+/// Unsigned values are hex, signed ones decimal, byte slices a bracketed
+/// [`HexDump`] and strings are quoted, so every value is one token a host
+/// tool can split on.
+pub fn write_kv<W: core::fmt::Write>(
+    out: &mut W,
+    msg: &str,
+    fields: &[(&str, LogValue<'_>)],
+) -> core::fmt::Result {
+    out.write_str(msg)?;
+    for (key, value) in fields {
+        write!(out, " {key}={value}")?;
+    }
+    Ok(())
 }
 
 // No-op implementation for production builds
@@ -87,17 +150,18 @@ impl Logger for NoOpLogger {
 }
 
 // UART logger adapter (separate concern)
-pub struct UartLogger<'a> {
-    uart: &'a mut UartController<'a>,
+pub struct UartLogger<'a, 'u> {
+    uart: &'a mut UartController<'u>,
 }
 
-impl<'a> UartLogger<'a> {
-    pub fn new(uart: &'a mut UartController<'a>) -> Self {
+impl<'a, 'u> UartLogger<'a, 'u> {
+    /// Borrows `uart` only for as long as the logger lives
+    pub fn new(uart: &'a mut UartController<'u>) -> Self {
         UartLogger { uart }
     }
 }
 
-impl<'a> Logger for UartLogger<'a> {
+impl Logger for UartLogger<'_, '_> {
     fn debug(&mut self, msg: &str) {
         writeln!(self.uart, "{msg}").ok();
         write!(self.uart, "\r").ok();
@@ -107,3 +171,443 @@ impl<'a> Logger for UartLogger<'a> {
         write!(self.uart, "\r").ok();
     }
 }
+
+/// Extension trait that records a failed `Result` through a [`Logger`]
+/// without consuming or altering it.
+///
+/// Intended for call sites that would otherwise discard errors with
+/// `let _ = ...`, so that failures are at least visible in the log:
+///
+/// ```ignore
+/// let _ = syscon
+///     .enable_clock(ClockId::ClkYCLK as u8)
+///     .log_err(&mut logger, "enable HACE clock");
+/// ```
+pub trait LogErr: Sized {
+    #[must_use]
+    fn log_err<L: Logger>(self, logger: &mut L, context: &str) -> Self;
+}
+
+impl<T, E: core::fmt::Debug> LogErr for Result<T, E> {
+    fn log_err<L: Logger>(self, logger: &mut L, context: &str) -> Self {
+        if let Err(ref e) = self {
+            let mut buf: heapless::String<64> = heapless::String::new();
+            // A truncated message is still more useful than none
+            let _ = core::fmt::Write::write_fmt(&mut buf, format_args!("{context}: {e:?}"));
+            logger.error(buf.as_str());
+        }
+        self
+    }
+}
+
+/// Outcome of a signature verification, encoded as a multi-bit pattern.
+///
+/// `VALID` and `INVALID` are bitwise complements, so neither a single
+/// skipped branch nor a stuck-at-zero/one register can turn one into the
+/// other. Any value that is neither is treated as a fault.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct VerifyDecision(u32);
+
+impl VerifyDecision {
+    pub const VALID: Self = Self(0x5AA5_C33C);
+    pub const INVALID: Self = Self(!0x5AA5_C33C);
+
+    #[must_use]
+    pub fn from_bool(valid: bool) -> Self {
+        if valid {
+            Self::VALID
+        } else {
+            Self::INVALID
+        }
+    }
+
+    /// Compares `a` and `b` front to back, accumulating differences.
+    #[must_use]
+    pub fn compare(a: &[u8], b: &[u8]) -> Self {
+        let mut diff = u8::from(a.len() != b.len());
+        for (x, y) in a.iter().zip(b) {
+            diff |= x ^ y;
+        }
+        Self::from_bool(diff == 0)
+    }
+
+    /// Compares `a` and `b` back to front by counting equal bytes.
+    ///
+    /// Deliberately computed differently from [`VerifyDecision::compare`] so
+    /// that a single glitch cannot corrupt both results the same way.
+    #[must_use]
+    pub fn compare_rev(a: &[u8], b: &[u8]) -> Self {
+        let mut same = 0usize;
+        for (x, y) in a.iter().rev().zip(b.iter().rev()) {
+            same += usize::from(x == y);
+        }
+        Self::from_bool(a.len() == b.len() && same == a.len())
+    }
+
+    /// Cross-checks two independently computed decisions.
+    ///
+    /// Returns `None` if they disagree or either is not a known pattern.
+    #[must_use]
+    pub fn confirm(first: Self, second: Self) -> Option<Self> {
+        if first.0 != second.0 || (first.0 != Self::VALID.0 && first.0 != Self::INVALID.0) {
+            return None;
+        }
+        Some(first)
+    }
+
+    #[must_use]
+    pub fn is_valid(self) -> bool {
+        self.0 == Self::VALID.0
+    }
+
+    #[must_use]
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+/// CRC-32 (IEEE 802.3, reflected) for data stored in flash
+#[must_use]
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in data {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Consistent read of a running down-counter register.
+///
+/// Reads the counter twice and accepts the second value when the two are
+/// no more than `max_drift` ticks apart, going down. A read that catches the
+/// counter while its halves are being updated, or while it reloads, fails
+/// that check and the pair is retried. After `attempts` failed pairs the
+/// last value read is returned.
+pub fn read_down_counter(mut read: impl FnMut() -> u32, max_drift: u32, attempts: usize) -> u32 {
+    let mut last = read();
+    for _ in 0..attempts {
+        let next = read();
+        if last.wrapping_sub(next) <= max_drift {
+            return next;
+        }
+        last = next;
+    }
+    last
+}
+
+/// Free-running 32-bit tick counter that [`Deadline`]s are measured against
+#[derive(Copy, Clone)]
+pub struct TickClock {
+    pub now: fn() -> u32,
+    pub ticks_per_us: u32,
+}
+
+impl TickClock {
+    /// DWT cycle counter at `cpu_hz`; it must already be running, e.g. via
+    /// `boot_trace::init`
+    #[must_use]
+    pub fn dwt(cpu_hz: u32) -> Self {
+        Self {
+            now: cortex_m::peripheral::DWT::cycle_count,
+            ticks_per_us: (cpu_hz / 1_000_000).max(1),
+        }
+    }
+}
+
+/// Returned by [`Deadline::checkpoint`] once the deadline has passed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Expired;
+
+/// Expiry point shared by the steps of a bounded operation.
+///
+/// The deadline is a tick budget from its creation, so counter wraps are
+/// handled as long as the budget and the gaps between checks stay below one
+/// wrap of the clock (about 21 s for the DWT at 200 MHz). Once seen expired
+/// it stays expired.
+pub struct Deadline {
+    clock: TickClock,
+    start: u32,
+    budget: u32,
+    expired: Cell<bool>,
+}
+
+impl Deadline {
+    #[must_use]
+    pub fn new(clock: TickClock, timeout: Duration) -> Self {
+        Self::starting_at((clock.now)(), clock, timeout, u32::MAX)
+    }
+
+    fn starting_at(start: u32, clock: TickClock, timeout: Duration, limit: u32) -> Self {
+        let ticks = timeout
+            .as_micros()
+            .saturating_mul(u128::from(clock.ticks_per_us));
+        Self {
+            clock,
+            start,
+            budget: u32::try_from(ticks).unwrap_or(u32::MAX).min(limit),
+            expired: Cell::new(false),
+        }
+    }
+
+    /// Deadline for a nested step: expires after `timeout` or together with
+    /// `self`, whichever comes first
+    #[must_use]
+    pub fn child(&self, timeout: Duration) -> Self {
+        let now = (self.clock.now)();
+        let left = self.ticks_left(now);
+        Self::starting_at(now, self.clock, timeout, left)
+    }
+
+    fn ticks_left(&self, now: u32) -> u32 {
+        if self.expired.get() {
+            return 0;
+        }
+        let left = self.budget.saturating_sub(now.wrapping_sub(self.start));
+        if left == 0 {
+            self.expired.set(true);
+        }
+        left
+    }
+
+    #[must_use]
+    pub fn is_expired(&self) -> bool {
+        self.ticks_left((self.clock.now)()) == 0
+    }
+
+    /// Cheap check for use inside polling loops
+    pub fn checkpoint(&self) -> Result<(), Expired> {
+        if self.is_expired() {
+            Err(Expired)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[must_use]
+    pub fn remaining(&self) -> Duration {
+        let left = self.ticks_left((self.clock.now)());
+        Duration::from_micros(u64::from(left / self.clock.ticks_per_us))
+    }
+}
+
+/// Bounded hex view of a byte slice for logging.
+///
+/// Buffers up to `2 * HexDump::EDGE` bytes are printed in full; longer ones
+/// print the first and last `EDGE` bytes around an ellipsis, followed by the
+/// total length, so a multi-kilobyte buffer costs a single short UART line.
+#[derive(Copy, Clone)]
+pub struct HexDump<'a>(pub &'a [u8]);
+
+impl HexDump<'_> {
+    pub const EDGE: usize = 8;
+
+    fn write_bytes(
+        f: &mut core::fmt::Formatter<'_>,
+        bytes: &[u8],
+        first: bool,
+    ) -> core::fmt::Result {
+        for (i, b) in bytes.iter().enumerate() {
+            if i > 0 || !first {
+                f.write_str(" ")?;
+            }
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let data = self.0;
+        if data.len() <= 2 * Self::EDGE {
+            return Self::write_bytes(f, data, true);
+        }
+        Self::write_bytes(f, &data[..Self::EDGE], true)?;
+        f.write_str(" ..")?;
+        Self::write_bytes(f, &data[data.len() - Self::EDGE..], false)?;
+        write!(f, " ({} bytes)", data.len())
+    }
+}
+
+impl core::fmt::Debug for HexDump<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "[{self}]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_down_counter, write_kv, Deadline, Expired, HexDump, LogLevel, LogValue, Logger,
+        TickClock,
+    };
+    use core::time::Duration;
+    use std::cell::Cell;
+
+    thread_local! {
+        static NOW: Cell<u32> = const { Cell::new(0) };
+    }
+
+    fn fake_now() -> u32 {
+        NOW.with(Cell::get)
+    }
+
+    fn advance_us(us: u32) {
+        NOW.with(|now| now.set(now.get().wrapping_add(us * 10)));
+    }
+
+    const CLOCK: TickClock = TickClock {
+        now: fake_now,
+        ticks_per_us: 10,
+    };
+
+    /// Replays `values`, repeating the last one
+    fn register(values: &[u32]) -> impl FnMut() -> u32 + '_ {
+        let mut i = 0;
+        move || {
+            let v = values[i.min(values.len() - 1)];
+            i += 1;
+            v
+        }
+    }
+
+    #[test]
+    fn test_read_down_counter() {
+        // consecutive reads a few ticks apart are accepted
+        assert_eq!(read_down_counter(register(&[1000, 997]), 16, 4), 997);
+        // a torn read (high half updated before the low half) is retried
+        assert_eq!(
+            read_down_counter(register(&[0x1_0000, 0x1_FFFF, 0xFFFE, 0xFFFC]), 16, 4),
+            0xFFFC
+        );
+        // a read across a reload is retried
+        assert_eq!(read_down_counter(register(&[2, 5000, 4998]), 16, 4), 4998);
+        // a counter that never settles returns the last value read
+        assert_eq!(read_down_counter(register(&[0, 100, 200, 300]), 16, 3), 300);
+    }
+
+    #[test]
+    fn test_deadline_expiry() {
+        NOW.with(|now| now.set(0));
+        let deadline = Deadline::new(CLOCK, Duration::from_micros(100));
+        assert_eq!(deadline.checkpoint(), Ok(()));
+        advance_us(40);
+        assert_eq!(deadline.remaining(), Duration::from_micros(60));
+        advance_us(60);
+        assert_eq!(deadline.checkpoint(), Err(Expired));
+        assert_eq!(deadline.remaining(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_deadline_counter_wrap() {
+        NOW.with(|now| now.set(u32::MAX - 99));
+        let deadline = Deadline::new(CLOCK, Duration::from_micros(50));
+        advance_us(20);
+        assert!(!deadline.is_expired());
+        assert_eq!(deadline.remaining(), Duration::from_micros(30));
+        advance_us(30);
+        assert!(deadline.is_expired());
+        // stays expired even after the counter wraps back around
+        NOW.with(|now| now.set(now.get().wrapping_add(u32::MAX - 10)));
+        assert!(deadline.is_expired());
+    }
+
+    #[test]
+    fn test_nested_deadlines() {
+        NOW.with(|now| now.set(1_000));
+        let outer = Deadline::new(CLOCK, Duration::from_micros(100));
+        advance_us(70);
+
+        // inner asks for more than the outer has left
+        let inner = outer.child(Duration::from_micros(500));
+        assert_eq!(inner.remaining(), Duration::from_micros(30));
+        advance_us(30);
+        assert!(inner.is_expired());
+        assert!(outer.is_expired());
+
+        // a shorter inner deadline expires on its own
+        NOW.with(|now| now.set(0));
+        let outer = Deadline::new(CLOCK, Duration::from_micros(100));
+        let inner = outer.child(Duration::from_micros(10));
+        advance_us(10);
+        assert!(inner.is_expired());
+        assert!(!outer.is_expired());
+
+        // children of an expired deadline start expired
+        advance_us(90);
+        let late = outer.child(Duration::from_micros(10));
+        assert_eq!(late.checkpoint(), Err(Expired));
+    }
+
+    #[test]
+    fn test_hexdump_short() {
+        assert_eq!(format!("{}", HexDump(&[])), "");
+        assert_eq!(format!("{}", HexDump(&[0x0a, 0xff])), "0a ff");
+        assert_eq!(format!("{:?}", HexDump(&[0x0a, 0xff])), "[0a ff]");
+        let full: [u8; 16] = core::array::from_fn(|i| i as u8);
+        assert_eq!(
+            format!("{}", HexDump(&full)),
+            "00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f"
+        );
+    }
+
+    #[test]
+    fn test_hexdump_long() {
+        let data: [u8; 300] = core::array::from_fn(|i| i as u8);
+        assert_eq!(
+            format!("{}", HexDump(&data)),
+            "00 01 02 03 04 05 06 07 .. 24 25 26 27 28 29 2a 2b (300 bytes)"
+        );
+    }
+
+    #[test]
+    fn test_write_kv() {
+        let mut line = String::new();
+        write_kv(
+            &mut line,
+            "i2c nack",
+            &[
+                ("addr", LogValue::U32(0x50)),
+                ("acked", LogValue::I32(-1)),
+                ("data", LogValue::Bytes(&[0xde, 0xad])),
+                ("op", LogValue::Str("write")),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            line,
+            "i2c nack addr=0x50 acked=-1 data=[de ad] op=\"write\""
+        );
+    }
+
+    #[derive(Default)]
+    struct Capture {
+        debug: String,
+        error: String,
+    }
+
+    impl Logger for Capture {
+        fn debug(&mut self, msg: &str) {
+            self.debug.push_str(msg);
+        }
+        fn error(&mut self, msg: &str) {
+            self.error.push_str(msg);
+        }
+    }
+
+    #[test]
+    fn test_log_kv_default() {
+        let mut logger = Capture::default();
+        logger.log_kv(LogLevel::Error, "spi", &[("cs", LogValue::U32(1))]);
+        assert_eq!(logger.error, "spi cs=0x1");
+        assert!(logger.debug.is_empty());
+    }
+}
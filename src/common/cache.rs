@@ -0,0 +1,74 @@
+// Licensed under the Apache-2.0 license
+
+//! Cache maintenance for buffers shared with DMA masters
+//!
+//! The SCU cache fronts SRAM below [`CACHED_SRAM_END`]; buffers in `.ram_nc`
+//! sit above the cached window and need no maintenance. The only
+//! maintenance operation this tree documents is the whole-cache invalidate
+//! `pre_init` issues before enabling the cache, so [`invalidate_range`]
+//! repeats that sequence rather than dropping single lines.
+//!
+//! No clean operation is documented either, so [`clean_range`] only drains
+//! the CPU write buffer. Both helpers therefore rely on the cache never
+//! holding data newer than SRAM, as `pre_init` already does when it
+//! invalidates. Without the `cache` feature every helper is a no-op.
+
+use core::ptr::write_volatile;
+
+/// End of the cached SRAM window: the start of `RAM_NC` in `memory.x`
+pub const CACHED_SRAM_END: usize = 0x000a_0000;
+
+// Cache control and invalidate registers and value, as used in `pre_init`
+const SCU_CACHE_CTRL: usize = 0x7e6e_2a58;
+const SCU_CACHE_INVALIDATE: usize = 0x7e6e_2a54;
+const CACHE_INVALIDATE_ALL: u32 = 0x8660_0000;
+
+/// Whether `[addr, addr + len)` overlaps the cached window
+fn is_cached(addr: usize, len: usize) -> bool {
+    len != 0 && addr < CACHED_SRAM_END
+}
+
+/// Make CPU writes to `[addr, addr + len)` visible to DMA masters
+pub fn clean_range(addr: usize, len: usize) {
+    if !cfg!(feature = "cache") || !is_cached(addr, len) {
+        return;
+    }
+    cortex_m::asm::dsb();
+}
+
+/// Drop cached copies of `[addr, addr + len)` so the CPU sees what a DMA
+/// master wrote
+pub fn invalidate_range(addr: usize, len: usize) {
+    if !cfg!(feature = "cache") || !is_cached(addr, len) {
+        return;
+    }
+    cortex_m::asm::dsb();
+    cortex_m::interrupt::free(|_| unsafe {
+        write_volatile(SCU_CACHE_CTRL as *mut u32, 0);
+        write_volatile(SCU_CACHE_INVALIDATE as *mut u32, CACHE_INVALIDATE_ALL);
+        write_volatile(SCU_CACHE_CTRL as *mut u32, 1);
+    });
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+}
+
+/// [`clean_range`] then [`invalidate_range`], for buffers the device both
+/// reads and writes
+pub fn clean_invalidate_range(addr: usize, len: usize) {
+    clean_range(addr, len);
+    invalidate_range(addr, len);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_cached_clips_to_window() {
+        assert!(is_cached(0x100, 1));
+        assert!(!is_cached(0x100, 0));
+        assert!(is_cached(CACHED_SRAM_END - 1, 0x40));
+        assert!(!is_cached(CACHED_SRAM_END, 0x40));
+        assert!(!is_cached(usize::MAX - 4, 8));
+    }
+}
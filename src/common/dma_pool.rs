@@ -0,0 +1,232 @@
+// Licensed under the Apache-2.0 license
+
+//! Fixed-size DMA buffers shared between drivers
+//!
+//! A [`DmaRegion`] is `N` chunks of `CHUNK` bytes, each aligned to a cache
+//! line like any [`DmaBuffer`]. Put it in `.ram_nc` so no cache maintenance
+//! is needed. A [`DmaPool`] hands the chunks out one transfer at a time
+//! through a lock-free bitmap. A [`DmaChunk`] goes back to the pool when it
+//! is dropped. When every chunk is taken, [`DmaPool::alloc_chunk`] fails
+//! with [`DmaPoolError::Exhausted`] and the caller can fall back to
+//! programmed I/O.
+//!
+//! The bitmap is kept out of the region because `.ram_nc` is not
+//! initialized at reset.
+
+use super::DmaBuffer;
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmaPoolError {
+    /// All chunks are in use
+    Exhausted,
+}
+
+/// Backing storage of a [`DmaPool`]
+pub struct DmaRegion<const CHUNK: usize, const N: usize> {
+    chunks: UnsafeCell<[DmaBuffer<CHUNK>; N]>,
+}
+
+// SAFETY: chunks are only reached through a `DmaChunk`, which the pool hands
+// out to one owner at a time
+unsafe impl<const CHUNK: usize, const N: usize> Sync for DmaRegion<CHUNK, N> {}
+
+impl<const CHUNK: usize, const N: usize> Default for DmaRegion<CHUNK, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CHUNK: usize, const N: usize> DmaRegion<CHUNK, N> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            chunks: UnsafeCell::new([const { DmaBuffer::new() }; N]),
+        }
+    }
+}
+
+/// Allocator over the chunks of one [`DmaRegion`]; at most 32 chunks
+pub struct DmaPool<const CHUNK: usize, const N: usize> {
+    region: &'static DmaRegion<CHUNK, N>,
+    /// Bit `i` set while chunk `i` is handed out
+    used: AtomicU32,
+}
+
+impl<const CHUNK: usize, const N: usize> DmaPool<CHUNK, N> {
+    const ALL: u32 = if N == 32 { u32::MAX } else { (1 << N) - 1 };
+
+    /// # Safety
+    ///
+    /// `region` must not be used by any other pool, or the two would hand
+    /// out the same chunks.
+    #[must_use]
+    pub const unsafe fn new(region: &'static DmaRegion<CHUNK, N>) -> Self {
+        assert!(N > 0 && N <= 32, "a DmaPool holds 1 to 32 chunks");
+        Self {
+            region,
+            used: AtomicU32::new(0),
+        }
+    }
+
+    /// Takes a free chunk; its contents are whatever the last user left
+    pub fn alloc_chunk(&self) -> Result<DmaChunk<'_, CHUNK, N>, DmaPoolError> {
+        let mut used = self.used.load(Ordering::Relaxed);
+        loop {
+            let free = !used & Self::ALL;
+            if free == 0 {
+                return Err(DmaPoolError::Exhausted);
+            }
+            let bit = free & free.wrapping_neg();
+            match self.used.compare_exchange_weak(
+                used,
+                used | bit,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Ok(DmaChunk {
+                        pool: self,
+                        index: bit.trailing_zeros() as usize,
+                    })
+                }
+                Err(current) => used = current,
+            }
+        }
+    }
+
+    /// Number of chunks not handed out
+    #[must_use]
+    pub fn available(&self) -> usize {
+        N - self.used.load(Ordering::Relaxed).count_ones() as usize
+    }
+}
+
+/// A chunk of a [`DmaPool`], returned to it on drop
+pub struct DmaChunk<'p, const CHUNK: usize, const N: usize> {
+    pool: &'p DmaPool<CHUNK, N>,
+    index: usize,
+}
+
+impl<const CHUNK: usize, const N: usize> DmaChunk<'_, CHUNK, N> {
+    fn buffer(&self) -> *mut DmaBuffer<CHUNK> {
+        self.pool
+            .region
+            .chunks
+            .get()
+            .cast::<DmaBuffer<CHUNK>>()
+            .wrapping_add(self.index)
+    }
+}
+
+impl<const CHUNK: usize, const N: usize> Deref for DmaChunk<'_, CHUNK, N> {
+    type Target = DmaBuffer<CHUNK>;
+
+    fn deref(&self) -> &DmaBuffer<CHUNK> {
+        // SAFETY: the chunk's bit in the pool makes this its only user
+        unsafe { &*self.buffer() }
+    }
+}
+
+impl<const CHUNK: usize, const N: usize> DerefMut for DmaChunk<'_, CHUNK, N> {
+    fn deref_mut(&mut self) -> &mut DmaBuffer<CHUNK> {
+        // SAFETY: as for `deref`
+        unsafe { &mut *self.buffer() }
+    }
+}
+
+impl<const CHUNK: usize, const N: usize> Drop for DmaChunk<'_, CHUNK, N> {
+    fn drop(&mut self) {
+        self.pool
+            .used
+            .fetch_and(!(1 << self.index), Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DmaPool, DmaPoolError, DmaRegion};
+
+    #[test]
+    fn test_alloc_until_exhausted() {
+        static REGION: DmaRegion<64, 3> = DmaRegion::new();
+        let pool = unsafe { DmaPool::new(&REGION) };
+
+        let a = pool.alloc_chunk().unwrap();
+        let b = pool.alloc_chunk().unwrap();
+        let c = pool.alloc_chunk().unwrap();
+        assert_eq!(pool.available(), 0);
+        assert_eq!(pool.alloc_chunk().err(), Some(DmaPoolError::Exhausted));
+
+        // distinct, aligned chunks
+        let addrs = [
+            a.as_ptr() as usize,
+            b.as_ptr() as usize,
+            c.as_ptr() as usize,
+        ];
+        for (i, addr) in addrs.iter().enumerate() {
+            assert_eq!(addr % 32, 0);
+            assert!(addrs[i + 1..]
+                .iter()
+                .all(|other| other.abs_diff(*addr) >= 64));
+        }
+
+        drop(b);
+        assert_eq!(pool.available(), 1);
+        let again = pool.alloc_chunk().unwrap();
+        assert_eq!(again.as_ptr() as usize, addrs[1]);
+    }
+
+    #[test]
+    fn test_chunks_hold_data() {
+        static REGION: DmaRegion<32, 2> = DmaRegion::new();
+        let pool = unsafe { DmaPool::new(&REGION) };
+
+        let mut a = pool.alloc_chunk().unwrap();
+        let mut b = pool.alloc_chunk().unwrap();
+        a.as_mut_slice(0, 32).fill(0xa5);
+        b.as_mut_slice(0, 32).fill(0x5a);
+        assert!(a.as_slice(0, 32).iter().all(|&x| x == 0xa5));
+        assert_eq!(b.len(), 32);
+        assert_eq!(b[31], 0x5a);
+    }
+
+    #[test]
+    fn test_full_bitmap() {
+        static REGION: DmaRegion<32, 32> = DmaRegion::new();
+        let pool = unsafe { DmaPool::new(&REGION) };
+
+        let chunks: [_; 32] = core::array::from_fn(|_| pool.alloc_chunk().unwrap());
+        assert!(pool.alloc_chunk().is_err());
+        drop(chunks);
+        assert_eq!(pool.available(), 32);
+    }
+
+    #[test]
+    fn test_concurrent_alloc() {
+        static REGION: DmaRegion<32, 4> = DmaRegion::new();
+        static POOL: DmaPool<32, 4> = unsafe { DmaPool::new(&REGION) };
+
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        if let Ok(mut chunk) = POOL.alloc_chunk() {
+                            // a chunk shared with another thread would
+                            // show a foreign tag here
+                            chunk[0] = t;
+                            std::thread::yield_now();
+                            assert_eq!(chunk[0], t);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(POOL.available(), 4);
+    }
+}
@@ -0,0 +1,144 @@
+// Licensed under the Apache-2.0 license
+
+//! Host doorbell notification over a GPIO interrupt line
+//!
+//! A status publisher calls [`Doorbell::ring`] whenever it has something new
+//! for the host. The line stays asserted until the host has consumed the
+//! status and the publisher calls [`Doorbell::acknowledge`]. Rings that arrive
+//! while the line is already asserted are coalesced, and rings that arrive
+//! within the configured minimum interval of the previous assertion are
+//! deferred until [`Doorbell::poll`] observes that the interval has elapsed.
+//!
+//! Time is supplied by the caller as a free-running tick count (for example
+//! `TimerController::counter()`), so the doorbell has no timer dependency.
+
+use embedded_hal::digital::OutputPin;
+
+/// Electrical polarity of the doorbell line
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DoorbellPolarity {
+    ActiveLow,
+    ActiveHigh,
+}
+
+/// Outcome of a [`Doorbell::ring`] request
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RingResult {
+    /// The line was asserted by this call
+    Asserted,
+    /// The line was already asserted; the notification was merged into it
+    Coalesced,
+    /// Rate limited; the line will be asserted by a later [`Doorbell::poll`]
+    Deferred,
+}
+
+pub struct Doorbell<P: OutputPin> {
+    pin: P,
+    polarity: DoorbellPolarity,
+    min_interval: u32,
+    last_assert: Option<u32>,
+    asserted: bool,
+    deferred: bool,
+    ring_count: u32,
+}
+
+impl<P: OutputPin> Doorbell<P> {
+    /// Creates a doorbell on `pin` and drives the line to its idle level.
+    ///
+    /// `min_interval` is the minimum number of ticks between two assertions.
+    pub fn new(pin: P, polarity: DoorbellPolarity, min_interval: u32) -> Result<Self, P::Error> {
+        let mut doorbell = Self {
+            pin,
+            polarity,
+            min_interval,
+            last_assert: None,
+            asserted: false,
+            deferred: false,
+            ring_count: 0,
+        };
+        doorbell.drive(false)?;
+        Ok(doorbell)
+    }
+
+    fn drive(&mut self, assert: bool) -> Result<(), P::Error> {
+        match (self.polarity, assert) {
+            (DoorbellPolarity::ActiveLow, true) | (DoorbellPolarity::ActiveHigh, false) => {
+                self.pin.set_low()
+            }
+            (DoorbellPolarity::ActiveLow, false) | (DoorbellPolarity::ActiveHigh, true) => {
+                self.pin.set_high()
+            }
+        }
+    }
+
+    fn interval_elapsed(&self, now: u32) -> bool {
+        match self.last_assert {
+            Some(last) => now.wrapping_sub(last) >= self.min_interval,
+            None => true,
+        }
+    }
+
+    fn assert(&mut self, now: u32) -> Result<(), P::Error> {
+        self.drive(true)?;
+        self.asserted = true;
+        self.deferred = false;
+        self.last_assert = Some(now);
+        self.ring_count = self.ring_count.wrapping_add(1);
+        Ok(())
+    }
+
+    /// Requests a host notification at tick `now`.
+    pub fn ring(&mut self, now: u32) -> Result<RingResult, P::Error> {
+        if self.asserted {
+            return Ok(RingResult::Coalesced);
+        }
+        if !self.interval_elapsed(now) {
+            self.deferred = true;
+            return Ok(RingResult::Deferred);
+        }
+        self.assert(now)?;
+        Ok(RingResult::Asserted)
+    }
+
+    /// Asserts a deferred notification once the rate limit allows it.
+    ///
+    /// Returns `true` if the line was asserted by this call.
+    pub fn poll(&mut self, now: u32) -> Result<bool, P::Error> {
+        if self.deferred && !self.asserted && self.interval_elapsed(now) {
+            self.assert(now)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Clears the doorbell after the host has read the status register.
+    pub fn acknowledge(&mut self) -> Result<(), P::Error> {
+        if self.asserted {
+            self.drive(false)?;
+            self.asserted = false;
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn is_asserted(&self) -> bool {
+        self.asserted
+    }
+
+    #[must_use]
+    pub fn is_deferred(&self) -> bool {
+        self.deferred
+    }
+
+    /// Number of times the line has been asserted since creation
+    #[must_use]
+    pub fn ring_count(&self) -> u32 {
+        self.ring_count
+    }
+
+    /// Releases the line (driven idle) and returns the pin
+    pub fn free(mut self) -> Result<P, P::Error> {
+        self.drive(false)?;
+        Ok(self.pin)
+    }
+}
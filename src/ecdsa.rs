@@ -1,5 +1,10 @@
 // Licensed under the Apache-2.0 license
 
+// Without the `ecdsa` feature only the key and signature types remain
+#![cfg_attr(not(feature = "ecdsa"), allow(dead_code, unused_imports))]
+
+use crate::common::VerifyDecision;
+use crate::keystore::{KeyHandle, KeyStore};
 use ast1060_pac::Secure;
 use core::ptr::{read_volatile, write_volatile, NonNull};
 use embedded_hal::delay::DelayNs;
@@ -31,6 +36,10 @@ const SRAM_DST_R: usize = 0x21c0;
 const SRAM_DST_S: usize = 0x2200;
 const SRAM_DST_M: usize = 0x2240;
 
+/// Engine status in SECURE014
+const ECC_STATUS_DONE: u32 = 1 << 20;
+const ECC_STATUS_PASS: u32 = 1 << 21;
+
 #[derive(Debug)]
 pub enum SerdeError {
     NotSupported,
@@ -212,6 +221,7 @@ pub enum AspeedEcdsaError {
     InvalidSignature,
     Busy,
     BadInput,
+    FaultDetected,
 }
 
 impl Error for AspeedEcdsaError {
@@ -219,11 +229,12 @@ impl Error for AspeedEcdsaError {
         match self {
             Self::InvalidSignature => ErrorKind::InvalidSignature,
             Self::Busy => ErrorKind::Busy,
-            Self::BadInput => ErrorKind::Other,
+            Self::BadInput | Self::FaultDetected => ErrorKind::Other,
         }
     }
 }
 
+#[cfg(feature = "ecdsa")]
 pub struct AspeedEcdsa<'a, D: DelayNs> {
     secure: &'a Secure,
     ecdsa_base: NonNull<u32>,
@@ -231,10 +242,39 @@ pub struct AspeedEcdsa<'a, D: DelayNs> {
     delay: D,
 }
 
+/// Stand-in for the engine when the `ecdsa` feature is off, so that using
+/// it fails with an error naming the feature. Sealed: no type implements
+/// it, so the stub below cannot be built.
+#[cfg(not(feature = "ecdsa"))]
+#[diagnostic::on_unimplemented(
+    message = "`AspeedEcdsa` is not available in this build",
+    label = "ECDSA support is compiled out",
+    note = "enable the `ecdsa` cargo feature"
+)]
+pub trait EcdsaEnabled: sealed::Sealed {}
+
+#[cfg(not(feature = "ecdsa"))]
+mod sealed {
+    pub trait Sealed {}
+}
+
+#[cfg(not(feature = "ecdsa"))]
+pub struct AspeedEcdsa<'a, D: EcdsaEnabled>(core::marker::PhantomData<&'a D>);
+
+#[cfg(not(feature = "ecdsa"))]
+impl<'a, D: EcdsaEnabled> AspeedEcdsa<'a, D> {
+    #[must_use]
+    pub fn new(_secure: &'a Secure, _delay: D) -> Self {
+        unreachable!()
+    }
+}
+
+#[cfg(feature = "ecdsa")]
 impl<D: DelayNs> EcdsaErrorType for AspeedEcdsa<'_, D> {
     type Error = AspeedEcdsaError;
 }
 
+#[cfg(feature = "ecdsa")]
 impl<'a, D: DelayNs> AspeedEcdsa<'a, D> {
     pub fn new(secure: &'a Secure, delay: D) -> Self {
         let ecdsa_base = unsafe { NonNull::new_unchecked(ECDSA_BASE as *mut u32) };
@@ -296,6 +336,7 @@ impl<'a, D: DelayNs> AspeedEcdsa<'a, D> {
     }
 }
 
+#[cfg(feature = "ecdsa")]
 impl<D> EcdsaVerify<Secp384r1Curve> for AspeedEcdsa<'_, D>
 where
     D: DelayNs,
@@ -309,66 +350,142 @@ where
         digest: <<Secp384r1Curve as Curve>::DigestType as DigestAlgorithm>::DigestOutput,
         signature: &Self::Signature,
     ) -> Result<(), Self::Error> {
-        unsafe {
-            let digest_bytes = digest.as_ref();
-            if digest_bytes.len() != 48 {
-                return Err(AspeedEcdsaError::BadInput);
-            }
-
-            let digest_array: &[u8; 48] = digest_bytes
-                .try_into()
-                .map_err(|_| AspeedEcdsaError::BadInput)?;
-
-            self.sec_wr(0x7c, 0x0100_f00b);
+        let decision = self.verify_decision(public_key, digest.as_ref(), signature)?;
+        if decision == VerifyDecision::VALID && decision.is_valid() {
+            Ok(())
+        } else {
+            Err(AspeedEcdsaError::InvalidSignature)
+        }
+    }
+}
 
-            // Reset Engine
-            self.secure.secure0b4().write(|w| w.bits(0));
-            self.secure
-                .secure0b4()
-                .write(|w| w.sec_boot_ecceng_enbl().set_bit());
-            self.delay.delay_ns(5000);
+#[cfg(feature = "ecdsa")]
+impl<D: DelayNs> AspeedEcdsa<'_, D> {
+    /// Verifies a secp384r1 signature and returns the raw [`VerifyDecision`].
+    ///
+    /// The engine status is sampled twice and evaluated with independent
+    /// comparisons; `AspeedEcdsaError::FaultDetected` is returned if the two
+    /// decisions disagree.
+    pub fn verify_decision(
+        &mut self,
+        public_key: &PublicKey,
+        digest: &[u8],
+        signature: &Signature,
+    ) -> Result<VerifyDecision, AspeedEcdsaError> {
+        let digest_array: &[u8; 48] = digest.try_into().map_err(|_| AspeedEcdsaError::BadInput)?;
+        self.engine_setup();
+        self.engine_verify(public_key, digest_array, signature)
+    }
 
-            self.load_secp384r1_params();
+    /// Verifies a secp384r1 signature with the public key taken from `store`.
+    /// An unknown handle or a non-ECDSA key is reported as `BadInput`.
+    pub fn verify_with_handle<const N: usize>(
+        &mut self,
+        store: &KeyStore<N>,
+        handle: KeyHandle,
+        digest: &[u8],
+        signature: &Signature,
+    ) -> Result<(), AspeedEcdsaError> {
+        let public_key = store
+            .ecdsa_public(handle)
+            .map_err(|_| AspeedEcdsaError::BadInput)?;
+        let decision = self.verify_decision(&public_key, digest, signature)?;
+        if decision == VerifyDecision::VALID && decision.is_valid() {
+            Ok(())
+        } else {
+            Err(AspeedEcdsaError::InvalidSignature)
+        }
+    }
 
-            self.sec_wr(0x7c, 0x0300_f00b);
+    /// Verifies `(public key, digest, signature)` items in order, e.g. the
+    /// links of a certificate chain.
+    ///
+    /// The engine is reset and loaded with the curve parameters once for the
+    /// whole batch. Stops at the first item that fails and returns its index
+    /// with the error; an invalid signature is reported as
+    /// `AspeedEcdsaError::InvalidSignature`.
+    pub fn verify_batch(
+        &mut self,
+        items: &[(&PublicKey, &[u8], &Signature)],
+    ) -> Result<(), (usize, AspeedEcdsaError)> {
+        self.engine_setup();
+        for (i, &(public_key, digest, signature)) in items.iter().enumerate() {
+            let digest_array: &[u8; 48] = digest
+                .try_into()
+                .map_err(|_| (i, AspeedEcdsaError::BadInput))?;
+            let decision = self
+                .engine_verify(public_key, digest_array, signature)
+                .map_err(|e| (i, e))?;
+            if !(decision == VerifyDecision::VALID && decision.is_valid()) {
+                return Err((i, AspeedEcdsaError::InvalidSignature));
+            }
+        }
+        Ok(())
+    }
 
-            // Write qx, qy, r, s
-            self.sram_wr(SRAM_DST_QX, &public_key.qx.0);
-            self.sram_wr(SRAM_DST_QY, &public_key.qy.0);
-            self.sram_wr(SRAM_DST_R, &signature.r.0);
-            self.sram_wr(SRAM_DST_S, &signature.s.0);
-            self.sram_wr(SRAM_DST_M, digest_array);
+    /// Resets the engine and loads the secp384r1 domain parameters
+    fn engine_setup(&mut self) {
+        self.sec_wr(0x7c, 0x0100_f00b);
 
-            self.sec_wr(0x7c, 0);
+        // Reset Engine
+        self.secure.secure0b4().write(|w| unsafe { w.bits(0) });
+        self.secure
+            .secure0b4()
+            .write(|w| w.sec_boot_ecceng_enbl().set_bit());
+        self.delay.delay_ns(5000);
 
-            // Write ECDSA instruction command
-            self.sram_wr_u32(0x23c0, 1);
+        self.load_secp384r1_params();
+    }
 
-            // Trigger ECDSA Engine
-            self.secure
-                .secure0bc()
-                .write(|w| w.sec_boot_ecceng_trigger_reg().set_bit());
-            self.delay.delay_ns(5000);
-            self.secure
-                .secure0bc()
-                .write(|w| w.sec_boot_ecceng_trigger_reg().clear_bit());
-
-            // Poll
-            let mut retry = 1000;
-            while retry > 0 {
-                let status = self.secure.secure014().read().bits();
-                if status & (1 << 20) != 0 {
-                    return if status & (1 << 21) != 0 {
-                        Ok(())
-                    } else {
-                        Err(AspeedEcdsaError::InvalidSignature)
-                    };
-                }
-                retry -= 1;
-                self.delay.delay_ns(5000);
+    /// Runs one verification on an engine prepared by `engine_setup`
+    fn engine_verify(
+        &mut self,
+        public_key: &PublicKey,
+        digest: &[u8; 48],
+        signature: &Signature,
+    ) -> Result<VerifyDecision, AspeedEcdsaError> {
+        self.sec_wr(0x7c, 0x0300_f00b);
+
+        // Write qx, qy, r, s
+        self.sram_wr(SRAM_DST_QX, &public_key.qx.0);
+        self.sram_wr(SRAM_DST_QY, &public_key.qy.0);
+        self.sram_wr(SRAM_DST_R, &signature.r.0);
+        self.sram_wr(SRAM_DST_S, &signature.s.0);
+        self.sram_wr(SRAM_DST_M, digest);
+
+        self.sec_wr(0x7c, 0);
+
+        // Write ECDSA instruction command
+        self.sram_wr_u32(0x23c0, 1);
+
+        // Trigger ECDSA Engine
+        self.secure
+            .secure0bc()
+            .write(|w| w.sec_boot_ecceng_trigger_reg().set_bit());
+        self.delay.delay_ns(5000);
+        self.secure
+            .secure0bc()
+            .write(|w| w.sec_boot_ecceng_trigger_reg().clear_bit());
+
+        // Poll
+        let mut retry = 1000;
+        while retry > 0 {
+            let status = self.secure.secure014().read().bits();
+            if status & ECC_STATUS_DONE != 0 {
+                let first = VerifyDecision::from_bool(status & ECC_STATUS_PASS != 0);
+                // Decided another way from a fresh read: the inverted word,
+                // done and pass masked together, compared for equality. One
+                // skipped test or bad read cannot give both the same answer.
+                let again = !self.secure.secure014().read().bits();
+                let second =
+                    VerifyDecision::from_bool(again & (ECC_STATUS_DONE | ECC_STATUS_PASS) == 0);
+                return VerifyDecision::confirm(first, second)
+                    .ok_or(AspeedEcdsaError::FaultDetected);
             }
-
-            Err(AspeedEcdsaError::Busy)
+            retry -= 1;
+            self.delay.delay_ns(5000);
         }
+
+        Err(AspeedEcdsaError::Busy)
     }
 }
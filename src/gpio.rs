@@ -115,6 +115,63 @@ impl embedded_hal::digital::Error for GPIOError {
     }
 }
 
+/// GPIO banks
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GpioBank {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+}
+
+impl GpioBank {
+    pub const ALL: [GpioBank; 21] = [
+        GpioBank::A,
+        GpioBank::B,
+        GpioBank::C,
+        GpioBank::D,
+        GpioBank::E,
+        GpioBank::F,
+        GpioBank::G,
+        GpioBank::H,
+        GpioBank::I,
+        GpioBank::J,
+        GpioBank::K,
+        GpioBank::L,
+        GpioBank::M,
+        GpioBank::N,
+        GpioBank::O,
+        GpioBank::P,
+        GpioBank::Q,
+        GpioBank::R,
+        GpioBank::S,
+        GpioBank::T,
+        GpioBank::U,
+    ];
+
+    #[must_use]
+    pub fn name(self) -> char {
+        char::from(b'A' + self as u8)
+    }
+}
+
 /*
 Acquire the GPIOA peripheral
 NOTE: `dp` is the device peripherals from the `PAC` crate
@@ -728,3 +785,18 @@ gpio_macro!( GPIOU, gpiou, 'u', 0, gpio088, gpio08c, gpio148,
     PU6: (pu6, 6, Tristate),
     PU7: (pu7, 7, Tristate),
 ]);
+
+#[cfg(test)]
+mod tests {
+    use super::GpioBank;
+
+    #[test]
+    fn test_bank_names() {
+        assert_eq!(GpioBank::A.name(), 'A');
+        assert_eq!(GpioBank::U.name(), 'U');
+        assert!(GpioBank::ALL
+            .iter()
+            .enumerate()
+            .all(|(i, b)| *b as usize == i));
+    }
+}
@@ -1,11 +1,16 @@
 // Licensed under the Apache-2.0 license
 
+use crate::common::cache;
+use crate::syscon::{self, ClockId, ResetId, SysCon};
 use ast1060_pac::Hace;
 use core::convert::{AsRef, Infallible};
 use core::default::Default;
 use core::marker::Sync;
+use core::sync::atomic::{compiler_fence, AtomicBool, Ordering};
+use embedded_hal::delay::DelayNs;
 use proposed_traits::digest::ErrorType as DigestErrorType;
 use proposed_traits::mac::ErrorType as MacErrorType;
+use proposed_traits::system_control::ResetControl;
 
 const SHA1_IV: [u32; 8] = [
     0x0123_4567,
@@ -40,6 +45,7 @@ const SHA256_IV: [u32; 8] = [
     0x19cd_e05b,
 ];
 
+#[cfg(feature = "sha384")]
 const SHA384_IV: [u32; 16] = [
     0x5d9d_bbcb,
     0xd89e_05c1,
@@ -59,6 +65,7 @@ const SHA384_IV: [u32; 16] = [
     0xa44f_fabe,
 ];
 
+#[cfg(feature = "sha512")]
 const SHA512_IV: [u32; 16] = [
     0x67e6_096a,
     0x08c9_bcf3,
@@ -78,6 +85,7 @@ const SHA512_IV: [u32; 16] = [
     0x7921_7e13,
 ];
 
+#[cfg(feature = "sha512")]
 const SHA512_224_IV: [u32; 16] = [
     0xC837_3D8C,
     0xA24D_5419,
@@ -97,6 +105,7 @@ const SHA512_224_IV: [u32; 16] = [
     0xA192_D691,
 ];
 
+#[cfg(feature = "sha512")]
 const SHA512_256_IV: [u32; 16] = [
     0x9421_3122,
     0x2CF7_2BFC,
@@ -120,15 +129,55 @@ const HACE_SHA_BE_EN: u32 = 1 << 3;
 const HACE_CMD_ACC_MODE: u32 = 1 << 8;
 pub const HACE_SG_EN: u32 = 1 << 18;
 pub const HACE_SG_LAST: u32 = 1 << 31;
+/// Most bytes handed to the engine in one run: a whole number of the
+/// largest blocks, and short enough to leave `HACE_SG_LAST` clear
+pub const HACE_MAX_RUN: usize = 1 << 30;
 
 const HACE_ALGO_SHA1: u32 = 1 << 5;
 const HACE_ALGO_SHA224: u32 = 1 << 6;
 const HACE_ALGO_SHA256: u32 = (1 << 4) | (1 << 6);
+#[cfg(feature = "sha512")]
 const HACE_ALGO_SHA512: u32 = (1 << 5) | (1 << 6);
+#[cfg(feature = "sha384")]
 const HACE_ALGO_SHA384: u32 = (1 << 5) | (1 << 6) | (1 << 10);
+#[cfg(feature = "sha512")]
 const HACE_ALGO_SHA512_224: u32 = (1 << 5) | (1 << 6) | (1 << 10) | (1 << 11);
+#[cfg(feature = "sha512")]
 const HACE_ALGO_SHA512_256: u32 = (1 << 5) | (1 << 6) | (1 << 11);
 
+// HACE1C: engine status
+const HACE_HASH_BUSY: u32 = 1 << 0;
+const HACE_CRYPTO_BUSY: u32 = 1 << 1;
+const HACE_RSA_BUSY: u32 = 1 << 2;
+const HACE_HASH_ISR: u32 = 1 << 9;
+const HACE_CRYPTO_ISR: u32 = 1 << 12;
+const HACE_RSA_ISR: u32 = 1 << 13;
+
+/// Decoded HACE1C engine status
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct HaceStatus {
+    pub hash_busy: bool,
+    pub crypto_busy: bool,
+    pub rsa_busy: bool,
+    pub hash_done: bool,
+    pub crypto_done: bool,
+    pub rsa_done: bool,
+}
+
+/// Decodes a raw HACE1C status word
+#[must_use]
+pub fn decode_hace_status(bits: u32) -> HaceStatus {
+    HaceStatus {
+        hash_busy: bits & HACE_HASH_BUSY != 0,
+        crypto_busy: bits & HACE_CRYPTO_BUSY != 0,
+        rsa_busy: bits & HACE_RSA_BUSY != 0,
+        hash_done: bits & HACE_HASH_ISR != 0,
+        crypto_done: bits & HACE_CRYPTO_ISR != 0,
+        rsa_done: bits & HACE_RSA_ISR != 0,
+    }
+}
+
 /// Common context cleanup functionality
 pub trait ContextCleanup {
     fn cleanup_context(&mut self);
@@ -217,6 +266,52 @@ impl AspeedHashContext {
     }
 }
 
+impl AspeedHashContext {
+    /// Adds `len` bytes to the 128-bit message length in `digcnt`
+    pub fn count(&mut self, len: u64) {
+        let (low, carry) = self.digcnt[0].overflowing_add(len);
+        self.digcnt[0] = low;
+        self.digcnt[1] = self.digcnt[1].wrapping_add(u64::from(carry));
+    }
+
+    /// Appends the final padding and message length after the `bufcnt`
+    /// buffered bytes.
+    ///
+    /// `remaining` bytes hashed from outside the buffer count towards the
+    /// block alignment. The padding spills into a second block when the
+    /// first has no room for the length field, so `buffer` (two of the
+    /// largest blocks) holds it for any `bufcnt` up to one block.
+    pub fn fill_padding(&mut self, remaining: usize) {
+        let block_size = self.block_size as usize;
+        let bufcnt = self.bufcnt as usize;
+        // 64-bit length for 64-byte blocks, 128-bit for 128-byte blocks
+        let len_size = if block_size == 64 { 8 } else { 16 };
+
+        let index = (bufcnt + remaining) & (block_size - 1);
+        let padlen = if index < block_size - len_size {
+            block_size - len_size - index
+        } else {
+            2 * block_size - len_size - index
+        };
+        let end = bufcnt + padlen + len_size;
+        assert!(
+            end <= self.buffer.len(),
+            "hash padding for {bufcnt} buffered bytes overruns the context buffer"
+        );
+
+        self.buffer[bufcnt] = 0x80;
+        self.buffer[bufcnt + 1..bufcnt + padlen].fill(0);
+        let low = (self.digcnt[0] << 3).to_be_bytes();
+        if len_size == 16 {
+            let high = ((self.digcnt[1] << 3) | (self.digcnt[0] >> 61)).to_be_bytes();
+            self.buffer[end - 16..end - 8].copy_from_slice(&high);
+        }
+        self.buffer[end - 8..end].copy_from_slice(&low);
+
+        self.bufcnt = u32::try_from(end).unwrap();
+    }
+}
+
 use core::cell::UnsafeCell;
 
 /// Safe wrapper for section-placed context
@@ -238,14 +333,18 @@ impl SectionPlacedContext {
 #[link_section = ".ram_nc"]
 static SHARED_HASH_CTX: SectionPlacedContext = SectionPlacedContext::new();
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum HashAlgo {
     SHA1,
     SHA224,
     SHA256,
+    #[cfg(feature = "sha384")]
     SHA384,
+    #[cfg(feature = "sha512")]
     SHA512,
+    #[cfg(feature = "sha512")]
     SHA512_224,
+    #[cfg(feature = "sha512")]
     SHA512_256,
 }
 
@@ -254,9 +353,15 @@ impl HashAlgo {
     pub const fn digest_size(&self) -> usize {
         match self {
             HashAlgo::SHA1 => 20,
-            HashAlgo::SHA224 | HashAlgo::SHA512_224 => 28,
-            HashAlgo::SHA256 | HashAlgo::SHA512_256 => 32,
+            HashAlgo::SHA224 => 28,
+            HashAlgo::SHA256 => 32,
+            #[cfg(feature = "sha384")]
             HashAlgo::SHA384 => 48,
+            #[cfg(feature = "sha512")]
+            HashAlgo::SHA512_224 => 28,
+            #[cfg(feature = "sha512")]
+            HashAlgo::SHA512_256 => 32,
+            #[cfg(feature = "sha512")]
             HashAlgo::SHA512 => 64,
         }
     }
@@ -265,9 +370,10 @@ impl HashAlgo {
     pub const fn block_size(&self) -> usize {
         match self {
             HashAlgo::SHA1 | HashAlgo::SHA224 | HashAlgo::SHA256 => 64,
-            HashAlgo::SHA384 | HashAlgo::SHA512 | HashAlgo::SHA512_224 | HashAlgo::SHA512_256 => {
-                128
-            }
+            #[cfg(feature = "sha384")]
+            HashAlgo::SHA384 => 128,
+            #[cfg(feature = "sha512")]
+            HashAlgo::SHA512 | HashAlgo::SHA512_224 | HashAlgo::SHA512_256 => 128,
         }
     }
 
@@ -277,9 +383,13 @@ impl HashAlgo {
             HashAlgo::SHA1 => HACE_ALGO_SHA1,
             HashAlgo::SHA224 => HACE_ALGO_SHA224,
             HashAlgo::SHA256 => HACE_ALGO_SHA256,
+            #[cfg(feature = "sha512")]
             HashAlgo::SHA512 => HACE_ALGO_SHA512,
+            #[cfg(feature = "sha384")]
             HashAlgo::SHA384 => HACE_ALGO_SHA384,
+            #[cfg(feature = "sha512")]
             HashAlgo::SHA512_224 => HACE_ALGO_SHA512_224,
+            #[cfg(feature = "sha512")]
             HashAlgo::SHA512_256 => HACE_ALGO_SHA512_256,
         }
     }
@@ -290,9 +400,13 @@ impl HashAlgo {
             HashAlgo::SHA1 => &SHA1_IV,
             HashAlgo::SHA224 => &SHA224_IV,
             HashAlgo::SHA256 => &SHA256_IV,
+            #[cfg(feature = "sha384")]
             HashAlgo::SHA384 => &SHA384_IV,
+            #[cfg(feature = "sha512")]
             HashAlgo::SHA512 => &SHA512_IV,
+            #[cfg(feature = "sha512")]
             HashAlgo::SHA512_224 => &SHA512_224_IV,
+            #[cfg(feature = "sha512")]
             HashAlgo::SHA512_256 => &SHA512_256_IV,
         }
     }
@@ -303,9 +417,13 @@ impl HashAlgo {
             HashAlgo::SHA1 => SHA1_IV.len(),
             HashAlgo::SHA224 => SHA224_IV.len(),
             HashAlgo::SHA256 => SHA256_IV.len(),
+            #[cfg(feature = "sha384")]
             HashAlgo::SHA384 => SHA384_IV.len(),
+            #[cfg(feature = "sha512")]
             HashAlgo::SHA512 => SHA512_IV.len(),
+            #[cfg(feature = "sha512")]
             HashAlgo::SHA512_224 => SHA512_224_IV.len(),
+            #[cfg(feature = "sha512")]
             HashAlgo::SHA512_256 => SHA512_256_IV.len(),
         }
     }
@@ -317,18 +435,113 @@ impl HashAlgo {
     }
 }
 
+/// Set while a `HaceController` exists. Digest and HMAC operations share
+/// one context in `.ram_nc`, so the controller is the token that grants use
+/// of it; scoped contexts borrow it and owned contexts hold it.
+static ENGINE_OWNED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaceError {
+    /// Another `HaceController` already owns the engine
+    Busy,
+    /// The engine is still hashing
+    Running,
+    /// Gating the clock or driving the reset line failed
+    Power(syscon::Error),
+}
+
 pub struct HaceController {
     pub hace: Hace,
     pub algo: HashAlgo,
 }
 
 impl HaceController {
+    /// Takes ownership of the engine.
+    ///
+    /// Panics if another controller is still alive; see [`Self::try_new`].
     #[must_use]
     pub fn new(hace: Hace) -> Self {
-        Self {
+        Self::try_new(hace).expect("HACE engine already owned")
+    }
+
+    /// Takes ownership of the engine, failing with [`HaceError::Busy`]
+    /// while another controller is alive. Ownership is released when the
+    /// controller is dropped.
+    pub fn try_new(hace: Hace) -> Result<Self, HaceError> {
+        ENGINE_OWNED
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| HaceError::Busy)?;
+        Ok(Self {
             hace,
             algo: HashAlgo::SHA256,
+        })
+    }
+
+    /// Enables the engine clock, takes the engine out of reset and takes
+    /// ownership of it, as after [`Self::power_down`]
+    pub fn power_up<D: DelayNs>(hace: Hace, syscon: &mut SysCon<D>) -> Result<Self, HaceError> {
+        match syscon.enable_clock(ClockId::ClkYCLK as u8) {
+            Ok(()) | Err(syscon::Error::ClockAlreadyEnabled) => {}
+            Err(e) => return Err(HaceError::Power(e)),
+        }
+        syscon
+            .reset_deassert(&ResetId::RstHACE)
+            .map_err(HaceError::Power)?;
+        Self::try_new(hace)
+    }
+
+    /// Holds the engine in reset with its clock stopped and gives up
+    /// ownership, returning the register block for a later
+    /// [`Self::power_up`].
+    ///
+    /// Fails with [`HaceError::Running`] while a hash is in progress, and
+    /// hands the controller back with the error.
+    pub fn power_down<D: DelayNs>(self, syscon: &mut SysCon<D>) -> Result<Hace, (Self, HaceError)> {
+        let status = self.status();
+        if status.hash_busy || status.crypto_busy {
+            return Err((self, HaceError::Running));
+        }
+        if let Err(e) = syscon.reset_assert(&ResetId::RstHACE) {
+            return Err((self, HaceError::Power(e)));
+        }
+        match syscon.disable_clock(ClockId::ClkYCLK as u8) {
+            Ok(()) | Err(syscon::Error::ClockAlreadyDisabled) => {}
+            Err(e) => return Err((self, HaceError::Power(e))),
+        }
+        // `Drop` is skipped to move the register block out, so wipe the
+        // context and release ownership here
+        Self::wipe_context();
+        let this = core::mem::ManuallyDrop::new(self);
+        ENGINE_OWNED.store(false, Ordering::Release);
+        // SAFETY: `this` is never used or dropped again
+        Ok(unsafe { core::ptr::read(&this.hace) })
+    }
+
+    /// Brings a wedged engine back without a chip reset: stops the current
+    /// command, pulses the HACE reset and restarts the clock.
+    ///
+    /// The engine keeps no state between commands; a hash in progress
+    /// lives in the shared context, which the hung command may have half
+    /// written. The context is wiped, so an unfinished digest or HMAC has
+    /// to be started again. Ownership is kept.
+    pub fn recover<D: DelayNs>(&mut self, syscon: &mut SysCon<D>) -> Result<(), HaceError> {
+        unsafe {
+            self.hace.hace30().write(|w| w.bits(0));
+        }
+        syscon
+            .reset_assert(&ResetId::RstHACE)
+            .map_err(HaceError::Power)?;
+        match syscon.enable_clock(ClockId::ClkYCLK as u8) {
+            Ok(()) | Err(syscon::Error::ClockAlreadyEnabled) => {}
+            Err(e) => return Err(HaceError::Power(e)),
         }
+        syscon
+            .reset_deassert(&ResetId::RstHACE)
+            .map_err(HaceError::Power)?;
+        // a done flag left from before the reset would end the next wait early
+        self.hace.hace1c().write(|w| w.hash_intflag().set_bit());
+        Self::wipe_context();
+        Ok(())
     }
 
     /// Get a mutable reference to the shared context in `.ram_nc` section
@@ -336,6 +549,23 @@ impl HaceController {
     pub fn shared_ctx() -> *mut AspeedHashContext {
         SHARED_HASH_CTX.get()
     }
+
+    /// Zeroes the whole shared context: digest state, buffered input, and
+    /// the key and pads of an HMAC left unfinished. Volatile, so the store
+    /// is not dropped as dead.
+    fn wipe_context() {
+        unsafe { core::ptr::write_volatile(Self::shared_ctx(), AspeedHashContext::new()) };
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl Drop for HaceController {
+    fn drop(&mut self) {
+        // the context outlives the controller; the next owner must not see
+        // what a session left in it
+        Self::wipe_context();
+        ENGINE_OWNED.store(false, Ordering::Release);
+    }
 }
 
 impl DigestErrorType for HaceController {
@@ -351,10 +581,23 @@ impl HaceController {
         unsafe { &mut *Self::shared_ctx() }
     }
 
+    /// Current engine status
+    #[must_use]
+    pub fn status(&self) -> HaceStatus {
+        decode_hace_status(self.hace.hace1c().read().bits())
+    }
+
     pub fn start_hash_operation(&mut self, len: u32) {
         let ctx = self.ctx_mut();
 
         let src_addr = if (ctx.method & HACE_SG_EN) != 0 {
+            // the context is in `.ram_nc`, but the caller's data may be cached
+            for sg in &ctx.sg {
+                cache::clean_range(sg.addr as usize, (sg.len & !HACE_SG_LAST) as usize);
+                if sg.len & HACE_SG_LAST != 0 {
+                    break;
+                }
+            }
             ctx.sg.as_ptr() as u32
         } else {
             ctx.buffer.as_ptr() as u32
@@ -428,57 +671,150 @@ impl HaceController {
     }
 
     pub fn fill_padding(&mut self, remaining: usize) {
+        self.ctx_mut().fill_padding(remaining);
+    }
+
+    /// Hashes `input` after the bytes buffered in the context and buffers
+    /// what is left of the last block.
+    ///
+    /// Empty input leaves the context as it is. Longer input than the
+    /// engine takes at once is fed in runs of at most [`HACE_MAX_RUN`]
+    /// bytes, so any length can be hashed.
+    pub fn hash_update(&mut self, input: &[u8]) {
+        for run in input.chunks(HACE_MAX_RUN) {
+            self.hash_run(run);
+        }
+    }
+
+    /// [`Self::hash_update`] for at most [`HACE_MAX_RUN`] bytes
+    fn hash_run(&mut self, input: &[u8]) {
+        let input_len = u32::try_from(input.len()).unwrap();
         let ctx = self.ctx_mut();
-        let block_size = ctx.block_size as usize;
-        let bufcnt = ctx.bufcnt as usize;
+        ctx.count(u64::from(input_len));
 
-        let index = (bufcnt + remaining) & (block_size - 1);
-        let padlen = if block_size == 64 {
-            if index < 56 {
-                56 - index
-            } else {
-                64 + 56 - index
-            }
-        } else if index < 112 {
-            112 - index
-        } else {
-            128 + 112 - index
-        };
+        let bufcnt = ctx.bufcnt;
+        if bufcnt + input_len < ctx.block_size {
+            ctx.buffer[bufcnt as usize..(bufcnt + input_len) as usize].copy_from_slice(input);
+            ctx.bufcnt += input_len;
+            return;
+        }
 
-        ctx.buffer[bufcnt] = 0x80;
-        ctx.buffer[bufcnt + 1..bufcnt + padlen].fill(0);
+        // the buffered bytes, then the whole blocks of `input` in place
+        let remaining = (bufcnt + input_len) % ctx.block_size;
+        let total_len = bufcnt + input_len - remaining;
+        let direct = total_len - bufcnt;
+        let mut i = 0;
+        if bufcnt != 0 {
+            ctx.sg[0].addr = ctx.buffer.as_ptr() as u32;
+            ctx.sg[0].len = bufcnt;
+            i += 1;
+        }
+        ctx.sg[i].addr = input.as_ptr() as u32;
+        ctx.sg[i].len = direct | HACE_SG_LAST;
 
-        if block_size == 64 {
-            let bits = (ctx.digcnt[0] << 3).to_be_bytes();
-            ctx.buffer[bufcnt + padlen..bufcnt + padlen + 8].copy_from_slice(&bits);
+        self.start_hash_operation(total_len);
 
-            // SAFETY: padlen is bounded by block_size (64) + 8, which easily fits in u32
-            debug_assert!(
-                u32::try_from(padlen + 8).is_ok(),
-                "padlen + 8 exceeds u32::MAX"
-            );
+        let ctx = self.ctx_mut();
+        ctx.buffer[..remaining as usize].copy_from_slice(&input[direct as usize..]);
+        ctx.bufcnt = remaining;
+    }
+}
 
-            ctx.bufcnt += u32::try_from(padlen + 8).unwrap_or_else(|_| {
-                debug_assert!(false, "padlen + 8 conversion to u32 failed");
-                u32::MAX
-            });
-        } else {
-            let low = (ctx.digcnt[0] << 3).to_be_bytes();
-            let high = ((ctx.digcnt[1] << 3) | (ctx.digcnt[0] >> 61)).to_be_bytes();
-
-            ctx.buffer[bufcnt + padlen..bufcnt + padlen + 8].copy_from_slice(&high);
-            ctx.buffer[bufcnt + padlen + 8..bufcnt + padlen + 16].copy_from_slice(&low);
-
-            // SAFETY: padlen is bounded by block_size (128) + 16, which easily fits in u32
-            debug_assert!(
-                u32::try_from(padlen + 16).is_ok(),
-                "padlen + 16 exceeds u32::MAX"
-            );
-
-            ctx.bufcnt += u32::try_from(padlen + 16).unwrap_or_else(|_| {
-                debug_assert!(false, "padlen + 16 conversion to u32 failed");
-                u32::MAX
-            });
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_controller() {
+        let hace = || unsafe { ast1060_pac::Peripherals::steal() }.hace;
+        let first = HaceController::try_new(hace()).unwrap();
+        assert_eq!(HaceController::try_new(hace()).err(), Some(HaceError::Busy));
+        drop(first);
+        assert!(HaceController::try_new(hace()).is_ok());
+    }
+
+    #[test]
+    fn test_fill_padding_block_boundary() {
+        let algos = [
+            HashAlgo::SHA1,
+            HashAlgo::SHA224,
+            HashAlgo::SHA256,
+            #[cfg(feature = "sha384")]
+            HashAlgo::SHA384,
+            #[cfg(feature = "sha512")]
+            HashAlgo::SHA512,
+            #[cfg(feature = "sha512")]
+            HashAlgo::SHA512_224,
+            #[cfg(feature = "sha512")]
+            HashAlgo::SHA512_256,
+        ];
+        for algo in algos {
+            let block = algo.block_size();
+            let len_size = if block == 64 { 8 } else { 16 };
+            // no room for the length in the first block, and a full block
+            for buffered in [block - len_size, block - 1, block] {
+                let mut ctx = AspeedHashContext::new();
+                ctx.block_size = u32::try_from(block).unwrap();
+                ctx.bufcnt = u32::try_from(buffered).unwrap();
+                ctx.digcnt = [buffered as u64, 0];
+                ctx.buffer[..buffered].fill(0xa5);
+                ctx.fill_padding(0);
+
+                let end = 2 * block;
+                assert_eq!(ctx.bufcnt as usize, end);
+                assert!(ctx.buffer[..buffered].iter().all(|&b| b == 0xa5));
+                assert_eq!(ctx.buffer[buffered], 0x80);
+                assert!(ctx.buffer[buffered + 1..end - 8].iter().all(|&b| b == 0));
+                let bits = (buffered as u64 * 8).to_be_bytes();
+                assert_eq!(ctx.buffer[end - 8..end], bits);
+            }
         }
     }
+
+    #[test]
+    fn test_count_carries() {
+        let mut ctx = AspeedHashContext::new();
+        ctx.count(0);
+        assert_eq!(ctx.digcnt, [0, 0]);
+        ctx.count(u64::MAX);
+        ctx.count(3);
+        assert_eq!(ctx.digcnt, [2, 1]);
+    }
+
+    #[test]
+    fn test_fill_padding_large_length() {
+        // 2^64 + 2 bytes: the bit count needs the high length word
+        let mut ctx = AspeedHashContext::new();
+        ctx.block_size = 128;
+        ctx.count(u64::MAX);
+        ctx.count(3);
+        ctx.bufcnt = 2;
+        ctx.fill_padding(0);
+        assert_eq!(ctx.bufcnt, 128);
+        assert_eq!(ctx.buffer[112..120], 8u64.to_be_bytes());
+        assert_eq!(ctx.buffer[120..128], 16u64.to_be_bytes());
+
+        // 2^61 + 5 bytes with a 64-bit length field: only the low bits fit
+        let mut ctx = AspeedHashContext::new();
+        ctx.block_size = 64;
+        ctx.count(1 << 61);
+        ctx.count(5);
+        ctx.bufcnt = 5;
+        ctx.fill_padding(0);
+        assert_eq!(ctx.bufcnt, 64);
+        assert_eq!(ctx.buffer[5], 0x80);
+        assert_eq!(ctx.buffer[56..64], 40u64.to_be_bytes());
+    }
+
+    #[test]
+    fn test_decode_hace_status() {
+        assert_eq!(decode_hace_status(0), HaceStatus::default());
+
+        let status = decode_hace_status(HACE_HASH_BUSY);
+        assert!(status.hash_busy && !status.hash_done);
+
+        let status = decode_hace_status(HACE_HASH_ISR | HACE_RSA_BUSY);
+        assert!(status.hash_done && status.rsa_busy);
+        assert!(!status.crypto_busy && !status.crypto_done && !status.rsa_done);
+    }
 }
@@ -1,6 +1,9 @@
 // Licensed under the Apache-2.0 license
 
+use crate::common::DmaBuffer;
 use crate::hace_controller::{ContextCleanup, HaceController, HashAlgo, HACE_SG_LAST};
+use crate::spi::norflashblockdevice::BlockAddrUsize;
+use proposed_traits::block_device::BlockDevice;
 use proposed_traits::digest::{DigestAlgorithm, DigestInit, DigestOp, Error, ErrorKind, ErrorType};
 
 // DigestAlgorithm implementation for HashAlgo
@@ -9,6 +12,10 @@ impl DigestAlgorithm for HashAlgo {
     type DigestOutput = [u8; 64]; // Use the maximum size for all variants
 }
 
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not supported by the hash engine in this build",
+    note = "SHA-384 needs the `sha384` cargo feature and SHA-512 the `sha512` feature"
+)]
 pub trait IntoHashAlgo {
     fn to_hash_algo() -> HashAlgo;
 }
@@ -83,6 +90,18 @@ impl DigestAlgorithm for Sha512 {
     type DigestOutput = Digest64; // Use Digest64 for 512 bits
 }
 
+impl Default for Sha1 {
+    fn default() -> Self {
+        Sha1
+    }
+}
+
+impl Default for Sha224 {
+    fn default() -> Self {
+        Sha224
+    }
+}
+
 impl Default for Sha256 {
     fn default() -> Self {
         Sha256
@@ -101,18 +120,32 @@ impl Default for Sha512 {
     }
 }
 
+impl IntoHashAlgo for Sha1 {
+    fn to_hash_algo() -> HashAlgo {
+        HashAlgo::SHA1
+    }
+}
+
+impl IntoHashAlgo for Sha224 {
+    fn to_hash_algo() -> HashAlgo {
+        HashAlgo::SHA224
+    }
+}
+
 impl IntoHashAlgo for Sha256 {
     fn to_hash_algo() -> HashAlgo {
         HashAlgo::SHA256
     }
 }
 
+#[cfg(feature = "sha384")]
 impl IntoHashAlgo for Sha384 {
     fn to_hash_algo() -> HashAlgo {
         HashAlgo::SHA384
     }
 }
 
+#[cfg(feature = "sha512")]
 impl IntoHashAlgo for Sha512 {
     fn to_hash_algo() -> HashAlgo {
         HashAlgo::SHA512
@@ -144,6 +177,44 @@ where
     }
 }
 
+impl HaceController {
+    /// Like `init`, but starts from `iv` instead of the algorithm's standard
+    /// initial hash value.
+    ///
+    /// `iv` holds the initial hash words as FIPS 180-4 writes them, with the
+    /// 64-bit SHA-384/512 words split high half first, so it must be
+    /// [`HashAlgo::iv_size`] words long.
+    pub fn init_with_iv<A>(
+        &mut self,
+        _algo: A,
+        iv: &[u32],
+    ) -> Result<OpContextImpl<'_, A>, HashError>
+    where
+        A: DigestAlgorithm + IntoHashAlgo,
+    {
+        let algo = A::to_hash_algo();
+        if iv.len() != algo.iv_size() {
+            return Err(ErrorKind::InvalidInputLength.into());
+        }
+
+        self.algo = algo;
+        let ctx = self.ctx_mut();
+        ctx.method = algo.hash_cmd();
+        // the engine reads the state as big-endian bytes
+        for (out, word) in ctx.digest.chunks_exact_mut(4).zip(iv) {
+            out.copy_from_slice(&word.to_be_bytes());
+        }
+        ctx.block_size = u32::try_from(algo.block_size()).unwrap();
+        ctx.bufcnt = 0;
+        ctx.digcnt = [0; 2];
+
+        Ok(OpContextImpl {
+            controller: self,
+            _phantom: core::marker::PhantomData,
+        })
+    }
+}
+
 pub struct OpContextImpl<'a, A: DigestAlgorithm + IntoHashAlgo> {
     pub controller: &'a mut HaceController,
     _phantom: core::marker::PhantomData<A>,
@@ -179,55 +250,7 @@ where
     type Output = A::DigestOutput;
 
     fn update(&mut self, input: &[u8]) -> Result<(), Self::Error> {
-        let input_len = u32::try_from(input.len()).map_err(|_| ErrorKind::InvalidInputLength)?;
-
-        let (new_len, carry) =
-            self.controller.ctx_mut().digcnt[0].overflowing_add(u64::from(input_len));
-
-        self.controller.ctx_mut().digcnt[0] = new_len;
-        if carry {
-            self.controller.ctx_mut().digcnt[1] += 1;
-        }
-
-        let start = self.controller.ctx_mut().bufcnt as usize;
-        let end = start + input_len as usize;
-        if self.controller.ctx_mut().bufcnt + input_len < self.controller.ctx_mut().block_size {
-            self.controller.ctx_mut().buffer[start..end].copy_from_slice(input);
-            self.controller.ctx_mut().bufcnt += input_len;
-            return Ok(());
-        }
-
-        let remaining =
-            (input_len + self.controller.ctx_mut().bufcnt) % self.controller.ctx_mut().block_size;
-        let total_len = (input_len + self.controller.ctx_mut().bufcnt) - remaining;
-        let mut i = 0;
-
-        if self.controller.ctx_mut().bufcnt != 0 {
-            self.controller.ctx_mut().sg[0].addr = self.controller.ctx_mut().buffer.as_ptr() as u32;
-            self.controller.ctx_mut().sg[0].len = self.controller.ctx_mut().bufcnt;
-            if total_len == self.controller.ctx_mut().bufcnt {
-                self.controller.ctx_mut().sg[0].addr = input.as_ptr() as u32;
-                self.controller.ctx_mut().sg[0].len |= HACE_SG_LAST;
-            }
-            i += 1;
-        }
-
-        if total_len != self.controller.ctx_mut().bufcnt {
-            self.controller.ctx_mut().sg[i].addr = input.as_ptr() as u32;
-            self.controller.ctx_mut().sg[i].len =
-                (total_len - self.controller.ctx_mut().bufcnt) | HACE_SG_LAST;
-        }
-
-        self.controller.start_hash_operation(total_len);
-
-        if remaining != 0 {
-            let src_start = (total_len - self.controller.ctx_mut().bufcnt) as usize;
-            let src_end = src_start + remaining as usize;
-
-            self.controller.ctx_mut().buffer[..(remaining as usize)]
-                .copy_from_slice(&input[src_start..src_end]);
-            self.controller.ctx_mut().bufcnt = remaining;
-        }
+        self.controller.hash_update(input);
         Ok(())
     }
 
@@ -256,3 +279,133 @@ where
         Ok(output) // Return the final output
     }
 }
+
+impl<A> OpContextImpl<'_, A>
+where
+    A: DigestAlgorithm + IntoHashAlgo,
+    A::DigestOutput: Default + AsMut<[u8]>,
+{
+    /// Feeds `parts` in order; the digest is the same as one `update` per part
+    pub fn update_iter<'p>(
+        &mut self,
+        parts: impl IntoIterator<Item = &'p [u8]>,
+    ) -> Result<(), HashError> {
+        parts.into_iter().try_for_each(|part| self.update(part))
+    }
+}
+
+/// Default [`HashStreamer`] chunk size: one block of the widest SHA-2 variant
+pub const HASH_STREAM_DEFAULT_CHUNK: usize = 128;
+
+#[derive(Debug)]
+pub enum StreamHashError<E> {
+    /// The reader or block device failed
+    Source(E),
+    Hash(HashError),
+    /// The chunk size, region or device read size violate the alignment rules
+    Unaligned,
+}
+
+/// Hashes data pulled from a reader or a block device through a chunk buffer
+///
+/// `CHUNK` trades RAM for throughput, e.g. 64 bytes for memory-constrained
+/// callers or 4 KiB for bulk hashing. It must be a multiple of the algorithm
+/// block size. Every full chunk is then passed to HACE by DMA directly from
+/// the buffer without going through the context block buffer. The buffer is a
+/// 32-byte aligned [`DmaBuffer`], so the streamer itself must be placed in RAM
+/// that HACE can reach (e.g. a `static` or the stack, not flash).
+pub struct HashStreamer<const CHUNK: usize = HASH_STREAM_DEFAULT_CHUNK> {
+    buf: DmaBuffer<CHUNK>,
+}
+
+impl<const CHUNK: usize> Default for HashStreamer<CHUNK> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CHUNK: usize> HashStreamer<CHUNK> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            buf: DmaBuffer::new(),
+        }
+    }
+
+    /// Hashes everything `reader` yields until end of input.
+    pub fn hash_reader<A, R>(
+        &mut self,
+        ctrl: &mut HaceController,
+        algo: A,
+        reader: &mut R,
+    ) -> Result<A::DigestOutput, StreamHashError<R::Error>>
+    where
+        A: DigestAlgorithm + IntoHashAlgo,
+        A::DigestOutput: Default + AsMut<[u8]>,
+        R: embedded_io::Read,
+    {
+        if CHUNK == 0 || CHUNK % A::to_hash_algo().block_size() != 0 {
+            return Err(StreamHashError::Unaligned);
+        }
+        let mut ctx = ctrl.init(algo).unwrap_or_else(|e| match e {});
+        loop {
+            // Fill the whole chunk so only the final update is partial
+            let mut filled = 0;
+            while filled < CHUNK {
+                let n = reader
+                    .read(self.buf.as_mut_slice(filled, CHUNK))
+                    .map_err(StreamHashError::Source)?;
+                if n == 0 {
+                    break;
+                }
+                filled += n;
+            }
+            ctx.update(self.buf.as_slice(0, filled))
+                .map_err(StreamHashError::Hash)?;
+            if filled < CHUNK {
+                break;
+            }
+        }
+        ctx.finalize().map_err(StreamHashError::Hash)
+    }
+
+    /// Hashes `len` bytes of `device` starting at byte offset `start`.
+    ///
+    /// `CHUNK`, `start` and `len` must all be multiples of the device read
+    /// size.
+    pub fn hash_block_region<A, D>(
+        &mut self,
+        ctrl: &mut HaceController,
+        algo: A,
+        device: &mut D,
+        start: usize,
+        len: usize,
+    ) -> Result<A::DigestOutput, StreamHashError<D::Error>>
+    where
+        A: DigestAlgorithm + IntoHashAlgo,
+        A::DigestOutput: Default + AsMut<[u8]>,
+        D: BlockDevice<Address = BlockAddrUsize>,
+    {
+        let read_size = device.read_size();
+        if CHUNK == 0
+            || CHUNK % A::to_hash_algo().block_size() != 0
+            || CHUNK % read_size != 0
+            || start % read_size != 0
+            || len % read_size != 0
+        {
+            return Err(StreamHashError::Unaligned);
+        }
+        let mut ctx = ctrl.init(algo).unwrap_or_else(|e| match e {});
+        let mut offset = 0;
+        while offset < len {
+            let n = CHUNK.min(len - offset);
+            let chunk = self.buf.as_mut_slice(0, n);
+            device
+                .read(BlockAddrUsize(start + offset), chunk)
+                .map_err(StreamHashError::Source)?;
+            ctx.update(chunk).map_err(StreamHashError::Hash)?;
+            offset += n;
+        }
+        ctx.finalize().map_err(StreamHashError::Hash)
+    }
+}
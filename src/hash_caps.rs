@@ -0,0 +1,271 @@
+// Licensed under the Apache-2.0 license
+
+//! Measured cost of the HACE hash algorithms
+//!
+//! Where a protocol lets us pick the hash, the pick should follow what the
+//! engine actually costs. [`HaceController::calibrate`] hashes a 4 KiB
+//! buffer with each algorithm once and keeps the cycles it took;
+//! [`HaceController::capabilities`] lists the algorithms fastest first and
+//! [`fastest_with_strength`] picks from that list. Builds without the
+//! `hash-calibration` feature skip the measurement: every cost reads 0 and
+//! the list is ordered by strength alone.
+//!
+//! SHA-512/224 and SHA-512/256 have no digest type yet and are not listed.
+
+use crate::hace_controller::{HaceController, HashAlgo};
+use core::ptr::addr_of;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Bytes hashed per algorithm by [`HaceController::calibrate`]
+pub const CALIBRATION_LEN: u32 = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlgoCaps {
+    pub algo: HashAlgo,
+    pub digest_len: usize,
+    pub block_len: usize,
+    /// Collision resistance in bits as rated by NIST SP 800-57; 0 for
+    /// SHA-1, whose collisions are practical
+    pub strength_bits: u16,
+    /// Cycles to hash [`CALIBRATION_LEN`] bytes; 0 until measured. Kept
+    /// whole so algorithms a fraction of a cycle per byte apart still order
+    /// correctly.
+    pub calibration_cycles: u32,
+}
+
+impl AlgoCaps {
+    const fn new(algo: HashAlgo) -> Self {
+        Self {
+            algo,
+            digest_len: algo.digest_size(),
+            block_len: algo.block_size(),
+            strength_bits: strength_bits(algo),
+            calibration_cycles: 0,
+        }
+    }
+
+    /// Measured cost rounded up to whole cycles per byte, for display
+    #[must_use]
+    pub const fn cycles_per_byte(&self) -> u32 {
+        self.calibration_cycles.div_ceil(CALIBRATION_LEN)
+    }
+
+    /// Sort key: cheapest first, then the weakest that is as cheap, as
+    /// it has the shortest digest
+    fn cost_key(&self) -> (u32, u16) {
+        (self.calibration_cycles, self.strength_bits)
+    }
+}
+
+const fn strength_bits(algo: HashAlgo) -> u16 {
+    match algo {
+        HashAlgo::SHA1 => 0,
+        HashAlgo::SHA224 => 112,
+        HashAlgo::SHA256 => 128,
+        #[cfg(feature = "sha384")]
+        HashAlgo::SHA384 => 192,
+        #[cfg(feature = "sha512")]
+        HashAlgo::SHA512 => 256,
+        #[cfg(feature = "sha512")]
+        HashAlgo::SHA512_224 => 112,
+        #[cfg(feature = "sha512")]
+        HashAlgo::SHA512_256 => 128,
+    }
+}
+
+/// Algorithms with a digest type, in order of strength
+const ALGOS: &[HashAlgo] = &[
+    HashAlgo::SHA1,
+    HashAlgo::SHA224,
+    HashAlgo::SHA256,
+    #[cfg(feature = "sha384")]
+    HashAlgo::SHA384,
+    #[cfg(feature = "sha512")]
+    HashAlgo::SHA512,
+];
+const ALGO_COUNT: usize = ALGOS.len();
+
+const fn unmeasured() -> [AlgoCaps; ALGO_COUNT] {
+    let mut table = [AlgoCaps::new(HashAlgo::SHA1); ALGO_COUNT];
+    let mut i = 0;
+    while i < ALGO_COUNT {
+        table[i] = AlgoCaps::new(ALGOS[i]);
+        i += 1;
+    }
+    table
+}
+
+/// Returned until [`HaceController::calibrate`] has run
+static UNMEASURED: [AlgoCaps; ALGO_COUNT] = unmeasured();
+/// Written once by `calibrate`, before `CALIBRATED` is set; only read after
+static mut MEASURED: [AlgoCaps; ALGO_COUNT] = unmeasured();
+static CALIBRATED: AtomicBool = AtomicBool::new(false);
+
+/// Puts `table` in the order [`HaceController::capabilities`] promises
+pub fn order(table: &mut [AlgoCaps]) {
+    table.sort_unstable_by_key(AlgoCaps::cost_key);
+}
+
+/// First algorithm of `table` at least `min_bits` strong; the fastest one
+/// when `table` is ordered
+#[must_use]
+pub fn select(table: &[AlgoCaps], min_bits: u16) -> Option<AlgoCaps> {
+    table.iter().find(|c| c.strength_bits >= min_bits).copied()
+}
+
+/// Fastest algorithm of at least `min_bits` collision resistance, for
+/// negotiating a hash with a peer
+#[must_use]
+pub fn fastest_with_strength(min_bits: u16) -> Option<AlgoCaps> {
+    select(HaceController::capabilities(), min_bits)
+}
+
+impl HaceController {
+    /// Hash algorithms, fastest first, with their measured cost once
+    /// [`Self::calibrate`] has run
+    #[must_use]
+    pub fn capabilities() -> &'static [AlgoCaps] {
+        if CALIBRATED.load(Ordering::Acquire) {
+            // SAFETY: no longer written once `CALIBRATED` is set
+            unsafe { &*addr_of!(MEASURED) }
+        } else {
+            &UNMEASURED
+        }
+    }
+
+    /// Times [`CALIBRATION_LEN`] bytes through each algorithm; later calls
+    /// return at once. Needs the DWT cycle counter running, as
+    /// `boot_trace::init` leaves it. Does nothing without the
+    /// `hash-calibration` feature.
+    pub fn calibrate(&mut self) {
+        #[cfg(feature = "hash-calibration")]
+        if !CALIBRATED.load(Ordering::Acquire) {
+            let mut table = unmeasured();
+            for caps in &mut table {
+                caps.calibration_cycles = calibration::time(self, caps.algo);
+            }
+            order(&mut table);
+            // SAFETY: the controller is unique, so this is the only writer,
+            // and no reference to `MEASURED` exists before the flag is set
+            unsafe { *core::ptr::addr_of_mut!(MEASURED) = table };
+            CALIBRATED.store(true, Ordering::Release);
+        }
+    }
+}
+
+#[cfg(feature = "hash-calibration")]
+mod calibration {
+    use super::CALIBRATION_LEN;
+    use crate::hace_controller::{HaceController, HashAlgo};
+    use crate::hash::IntoHashAlgo;
+    #[cfg(feature = "sha384")]
+    use crate::hash::Sha384;
+    #[cfg(feature = "sha512")]
+    use crate::hash::Sha512;
+    use crate::hash::{Sha1, Sha224, Sha256};
+    use cortex_m::peripheral::DWT;
+    use proposed_traits::digest::{DigestAlgorithm, DigestInit, DigestOp};
+
+    /// In RAM, as the engine reads its input by DMA
+    static mut INPUT: [u8; CALIBRATION_LEN as usize] = [0; CALIBRATION_LEN as usize];
+
+    /// Cycles to hash the calibration input with `algo`
+    pub(super) fn time(hace: &mut HaceController, algo: HashAlgo) -> u32 {
+        match algo {
+            HashAlgo::SHA1 => time_as::<Sha1>(hace),
+            HashAlgo::SHA224 => time_as::<Sha224>(hace),
+            HashAlgo::SHA256 => time_as::<Sha256>(hace),
+            #[cfg(feature = "sha384")]
+            HashAlgo::SHA384 => time_as::<Sha384>(hace),
+            #[cfg(feature = "sha512")]
+            HashAlgo::SHA512 => time_as::<Sha512>(hace),
+            #[cfg(feature = "sha512")]
+            HashAlgo::SHA512_224 | HashAlgo::SHA512_256 => 0,
+        }
+    }
+
+    fn time_as<A>(hace: &mut HaceController) -> u32
+    where
+        A: DigestAlgorithm + IntoHashAlgo + Default,
+        A::DigestOutput: Default + AsMut<[u8]>,
+    {
+        // SAFETY: only read, and only while the controller is borrowed
+        let input = unsafe { &*core::ptr::addr_of!(INPUT) };
+        let start = DWT::cycle_count();
+        let mut ctx = hace.init(A::default()).unwrap_or_else(|e| match e {});
+        let _ = ctx.update(input);
+        let _ = ctx.finalize();
+        DWT::cycle_count().wrapping_sub(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{order, select, unmeasured, AlgoCaps, CALIBRATION_LEN};
+    use crate::hace_controller::HashAlgo;
+
+    fn caps(algo: HashAlgo, cycles_per_byte: u32) -> AlgoCaps {
+        AlgoCaps {
+            calibration_cycles: cycles_per_byte * CALIBRATION_LEN,
+            ..AlgoCaps::new(algo)
+        }
+    }
+
+    #[test]
+    fn test_unmeasured_by_strength() {
+        let mut table = unmeasured();
+        order(&mut table);
+        assert_eq!(table, unmeasured());
+        assert_eq!(table[0].algo, HashAlgo::SHA1);
+        assert!(table
+            .windows(2)
+            .all(|w| w[0].strength_bits < w[1].strength_bits));
+        assert_eq!(select(&table, 128).map(|c| c.algo), Some(HashAlgo::SHA256));
+    }
+
+    #[test]
+    #[cfg(all(feature = "sha384", feature = "sha512"))]
+    fn test_order_and_select() {
+        let mut table = [
+            caps(HashAlgo::SHA1, 3),
+            caps(HashAlgo::SHA224, 4),
+            caps(HashAlgo::SHA256, 4),
+            caps(HashAlgo::SHA384, 2),
+            caps(HashAlgo::SHA512, 2),
+        ];
+        order(&mut table);
+        let algos = table.map(|c| c.algo);
+        assert_eq!(
+            algos,
+            [
+                HashAlgo::SHA384,
+                HashAlgo::SHA512,
+                HashAlgo::SHA1,
+                HashAlgo::SHA224,
+                HashAlgo::SHA256
+            ]
+        );
+        // the cheaper wide hash wins over a slower narrow one
+        assert_eq!(select(&table, 112).map(|c| c.algo), Some(HashAlgo::SHA384));
+        assert_eq!(select(&table, 256).map(|c| c.algo), Some(HashAlgo::SHA512));
+        assert_eq!(select(&table, 0).map(|c| c.algo), Some(HashAlgo::SHA384));
+        assert_eq!(select(&table, 257), None);
+    }
+
+    #[test]
+    fn test_sub_cycle_differences_order() {
+        // 1.1 and 1.9 cycles per byte both round up to 2
+        let slow = AlgoCaps {
+            calibration_cycles: CALIBRATION_LEN * 19 / 10,
+            ..AlgoCaps::new(HashAlgo::SHA1)
+        };
+        let fast = AlgoCaps {
+            calibration_cycles: CALIBRATION_LEN * 11 / 10,
+            ..AlgoCaps::new(HashAlgo::SHA256)
+        };
+        assert_eq!(slow.cycles_per_byte(), fast.cycles_per_byte());
+        let mut table = [slow, fast];
+        order(&mut table);
+        assert_eq!(table.map(|c| c.algo), [HashAlgo::SHA256, HashAlgo::SHA1]);
+    }
+}
@@ -14,7 +14,9 @@
 //! and can be stored in structs, moved across functions, and persist across IPC.
 //!
 
-use crate::hace_controller::{ContextCleanup, HaceController, HashAlgo, HACE_SG_LAST};
+use crate::hace_controller::{
+    ContextCleanup, HaceController, HashAlgo, HACE_MAX_RUN, HACE_SG_LAST,
+};
 use core::convert::Infallible;
 use core::marker::PhantomData;
 use openprot_hal_blocking::digest::owned::{DigestInit, DigestOp};
@@ -27,6 +29,10 @@ pub use crate::hash::{Digest48, Digest64, Sha1, Sha224, Sha256, Sha384, Sha512};
 pub use openprot_hal_blocking::digest::{Digest, Sha2_256, Sha2_384, Sha2_512};
 
 /// Trait to convert digest algorithm types to our internal `HashAlgo` enum
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not supported by the hash engine in this build",
+    note = "SHA-384 needs the `sha384` cargo feature and SHA-512 the `sha512` feature"
+)]
 pub trait IntoHashAlgo {
     fn to_hash_algo() -> HashAlgo;
 }
@@ -37,12 +43,14 @@ impl IntoHashAlgo for Sha2_256 {
     }
 }
 
+#[cfg(feature = "sha384")]
 impl IntoHashAlgo for Sha2_384 {
     fn to_hash_algo() -> HashAlgo {
         HashAlgo::SHA384
     }
 }
 
+#[cfg(feature = "sha512")]
 impl IntoHashAlgo for Sha2_512 {
     fn to_hash_algo() -> HashAlgo {
         HashAlgo::SHA512
@@ -69,6 +77,80 @@ impl<T: DigestAlgorithm + IntoHashAlgo> ErrorType for OwnedDigestContext<T> {
     type Error = Infallible;
 }
 
+/// Errors from the block-aligned update path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignedError {
+    /// Input length is not a multiple of the algorithm block size, or the
+    /// context already holds buffered partial-block data
+    UnalignedInput,
+}
+
+/// Errors from importing or exporting an intermediate digest state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidstateError {
+    /// The midstate buffer does not match the algorithm state size
+    InvalidLength,
+    /// The byte count is not block-aligned, or a partial block is still
+    /// buffered in the context
+    Unaligned,
+}
+
+impl HaceController {
+    /// Continue a digest from an externally computed intermediate state
+    ///
+    /// `midstate` holds the state words big-endian, as produced by
+    /// [`OwnedDigestContext::export_midstate`] (32 bytes for SHA-256, 64 for
+    /// SHA-384/512), and `bytes_processed` the block-aligned number of bytes
+    /// already hashed into it. On error the controller is handed back.
+    pub fn resume_digest<T: DigestAlgorithm + IntoHashAlgo>(
+        mut self,
+        _algo: T,
+        midstate: &[u8],
+        bytes_processed: u64,
+    ) -> Result<OwnedDigestContext<T>, (MidstateError, Self)> {
+        let algo = T::to_hash_algo();
+        if midstate.len() != algo.iv_size() * 4 {
+            return Err((MidstateError::InvalidLength, self));
+        }
+        if bytes_processed % u64::try_from(algo.block_size()).unwrap() != 0 {
+            return Err((MidstateError::Unaligned, self));
+        }
+
+        self.algo = algo;
+        let ctx = self.ctx_mut();
+        ctx.method = algo.hash_cmd();
+        ctx.digest[..midstate.len()].copy_from_slice(midstate);
+        ctx.block_size = u32::try_from(algo.block_size()).unwrap();
+        ctx.bufcnt = 0;
+        ctx.digcnt = [bytes_processed, 0];
+
+        Ok(OwnedDigestContext {
+            controller: self,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<T: DigestAlgorithm + IntoHashAlgo> OwnedDigestContext<T> {
+    /// Copy the intermediate state into `out` and return the number of bytes
+    /// hashed so far, for [`HaceController::resume_digest`]
+    ///
+    /// Only block-aligned states can be exported; feed whole blocks (or
+    /// use `update_aligned`) up to the split point.
+    pub fn export_midstate(&mut self, out: &mut [u8]) -> Result<u64, MidstateError> {
+        let state_len = self.controller.algo.iv_size() * 4;
+        let ctx = self.controller.ctx_mut();
+        if ctx.bufcnt != 0 {
+            return Err(MidstateError::Unaligned);
+        }
+        let out = out
+            .get_mut(..state_len)
+            .ok_or(MidstateError::InvalidLength)?;
+        out.copy_from_slice(&ctx.digest[..state_len]);
+        Ok(ctx.digcnt[0])
+    }
+}
+
 /// Macro to implement owned digest traits for each algorithm
 macro_rules! impl_owned_digest {
     ($algo:ident) => {
@@ -92,71 +174,62 @@ macro_rules! impl_owned_digest {
             }
         }
 
-        impl DigestOp for OwnedDigestContext<$algo> {
-            type Output = <$algo as DigestAlgorithm>::Digest;
-            type Controller = HaceController;
-
-            fn update(mut self, data: &[u8]) -> Result<Self, Self::Error> {
-                let input_len = u32::try_from(data.len()).unwrap_or(u32::MAX);
-
-                // Update digest count
-                let (new_len, carry) =
-                    self.controller.ctx_mut().digcnt[0].overflowing_add(u64::from(input_len));
-
-                self.controller.ctx_mut().digcnt[0] = new_len;
-                if carry {
-                    self.controller.ctx_mut().digcnt[1] += 1;
-                }
-
-                let start = self.controller.ctx_mut().bufcnt as usize;
-                let end = start + input_len as usize;
+        impl OwnedDigestContext<$algo> {
+            /// Finalize the digest and also return the total number of bytes hashed
+            ///
+            /// Framed protocols can compare the returned length with their
+            /// declared length before trusting the digest.
+            pub fn finalize_with_len(
+                mut self,
+            ) -> Result<(<$algo as DigestAlgorithm>::Digest, u64, HaceController), Infallible> {
+                let len = self.controller.ctx_mut().digcnt[0];
+                let (digest, controller) = self.finalize()?;
+                Ok((digest, len, controller))
+            }
 
-                // If we can fit everything in the buffer, just copy it
-                if self.controller.ctx_mut().bufcnt + input_len
-                    < self.controller.ctx_mut().block_size
-                {
-                    self.controller.ctx_mut().buffer[start..end].copy_from_slice(data);
-                    self.controller.ctx_mut().bufcnt += input_len;
-                    return Ok(self);
-                }
+            /// Feed `parts` in order; the digest is the same as one `update`
+            /// per part
+            pub fn update_iter<'p>(
+                self,
+                parts: impl IntoIterator<Item = &'p [u8]>,
+            ) -> Result<Self, Infallible> {
+                parts.into_iter().try_fold(self, DigestOp::update)
+            }
 
-                // Process data in blocks using scatter-gather
-                let remaining = (input_len + self.controller.ctx_mut().bufcnt)
-                    % self.controller.ctx_mut().block_size;
-                let total_len = (input_len + self.controller.ctx_mut().bufcnt) - remaining;
-                let mut i = 0;
-
-                if self.controller.ctx_mut().bufcnt != 0 {
-                    self.controller.ctx_mut().sg[0].addr =
-                        self.controller.ctx_mut().buffer.as_ptr() as u32;
-                    self.controller.ctx_mut().sg[0].len = self.controller.ctx_mut().bufcnt;
-                    if total_len == self.controller.ctx_mut().bufcnt {
-                        self.controller.ctx_mut().sg[0].addr = data.as_ptr() as u32;
-                        self.controller.ctx_mut().sg[0].len |= HACE_SG_LAST;
-                    }
-                    i += 1;
+            /// Hash whole blocks directly from `data` without using the context
+            /// block buffer.
+            ///
+            /// `data` must be a multiple of the block size and no partial block
+            /// may be pending from an earlier `update`. On error the context is
+            /// handed back unchanged so the caller can fall back to `update`.
+            /// Digests are identical to those produced by `update`.
+            pub fn update_aligned(mut self, data: &[u8]) -> Result<Self, (AlignedError, Self)> {
+                let ctx = self.controller.ctx_mut();
+                let block_size = ctx.block_size as usize;
+                if ctx.bufcnt != 0 || data.len() % block_size != 0 {
+                    return Err((AlignedError::UnalignedInput, self));
                 }
 
-                if total_len != self.controller.ctx_mut().bufcnt {
-                    self.controller.ctx_mut().sg[i].addr = data.as_ptr() as u32;
-                    self.controller.ctx_mut().sg[i].len =
-                        (total_len - self.controller.ctx_mut().bufcnt) | HACE_SG_LAST;
+                // whole blocks per run too, as `HACE_MAX_RUN` is a multiple
+                for run in data.chunks(HACE_MAX_RUN) {
+                    let run_len = u32::try_from(run.len()).unwrap();
+                    let ctx = self.controller.ctx_mut();
+                    ctx.count(u64::from(run_len));
+                    ctx.sg[0].addr = run.as_ptr() as u32;
+                    ctx.sg[0].len = run_len | HACE_SG_LAST;
+                    self.controller.start_hash_operation(run_len);
                 }
 
-                self.controller.start_hash_operation(total_len);
-
-                // Handle remaining data
-                if remaining != 0 {
-                    let src_start = (total_len - self.controller.ctx_mut().bufcnt) as usize;
-                    let src_end = src_start + remaining as usize;
+                Ok(self)
+            }
+        }
 
-                    self.controller.ctx_mut().buffer[..(remaining as usize)]
-                        .copy_from_slice(&data[src_start..src_end]);
-                    self.controller.ctx_mut().bufcnt = remaining;
-                } else {
-                    self.controller.ctx_mut().bufcnt = 0;
-                }
+        impl DigestOp for OwnedDigestContext<$algo> {
+            type Output = <$algo as DigestAlgorithm>::Digest;
+            type Controller = HaceController;
 
+            fn update(mut self, data: &[u8]) -> Result<Self, Self::Error> {
+                self.controller.hash_update(data);
                 Ok(self)
             }
 
@@ -213,9 +286,108 @@ macro_rules! impl_owned_digest {
 
 // Implement the owned traits for each supported algorithm
 impl_owned_digest!(Sha2_256);
+#[cfg(feature = "sha384")]
 impl_owned_digest!(Sha2_384);
+#[cfg(feature = "sha512")]
 impl_owned_digest!(Sha2_512);
 
+/// Largest digest produced by the engine, in bytes (SHA-512)
+const MAX_DIGEST_BYTES: usize = 64;
+
+/// Digest output as canonical big-endian bytes.
+///
+/// Compares and prints as bytes, so callers need not know that [`Digest`]
+/// stores its value as `u32` words.
+#[derive(Clone, Copy)]
+pub struct DigestBytes {
+    buf: [u8; MAX_DIGEST_BYTES],
+    len: usize,
+}
+
+impl DigestBytes {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> core::iter::Copied<core::slice::Iter<'_, u8>> {
+        self.as_ref().iter().copied()
+    }
+}
+
+impl AsRef<[u8]> for DigestBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl PartialEq for DigestBytes {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+}
+
+impl Eq for DigestBytes {}
+
+impl PartialEq<[u8]> for DigestBytes {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_ref() == other
+    }
+}
+
+impl core::fmt::Debug for DigestBytes {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for b in self.as_ref() {
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a DigestBytes {
+    type Item = u8;
+    type IntoIter = core::iter::Copied<core::slice::Iter<'a, u8>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Byte access to the [`Digest`] returned by `finalize`.
+///
+/// `Digest` belongs to `openprot-hal-blocking`, so `AsRef<[u8]>` cannot be
+/// implemented on it here; [`DigestBytes`] provides it instead.
+pub trait DigestExt {
+    /// Copy of the digest as big-endian bytes
+    fn to_bytes(&self) -> DigestBytes;
+
+    /// The digest bytes in order, without copying
+    fn bytes(&self) -> impl Iterator<Item = u8> + '_;
+}
+
+impl<const N: usize> DigestExt for Digest<N> {
+    fn to_bytes(&self) -> DigestBytes {
+        let mut out = DigestBytes {
+            buf: [0; MAX_DIGEST_BYTES],
+            len: (N * 4).min(MAX_DIGEST_BYTES),
+        };
+        for (dst, b) in out.buf.iter_mut().zip(self.bytes()) {
+            *dst = b;
+        }
+        out
+    }
+
+    fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.value.iter().flat_map(|w| w.to_be_bytes())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -240,6 +412,21 @@ mod tests {
     }
 
     #[test]
+    fn test_digest_bytes() {
+        let digest = Digest::new([0xba78_16bf, 0x8f01_cfea, 0x4141_40de, 0x5dae_2223, 0, 0, 0]);
+        let bytes = digest.to_bytes();
+        assert_eq!(bytes.len(), 28);
+        assert_eq!(bytes.as_ref()[..6], [0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01]);
+        assert!(bytes.iter().eq(digest.bytes()));
+        assert_eq!(bytes, Digest::new(digest.value).to_bytes());
+        assert_eq!(
+            format!("{:?}", Digest::new([0x0102_0304]).to_bytes()),
+            "01020304"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "sha384")]
     fn test_session_storage_pattern() {
         // Demonstrate controller storage pattern - impossible with scoped API
         // This simulates what a server would do to store controller wrappers
@@ -0,0 +1,277 @@
+// Licensed under the Apache-2.0 license
+
+//! Hash sessions multiplexed over the single HACE hash context
+//!
+//! HACE has one hash context, so a digest server with several clients keeps
+//! each client's running state in a [`SessionTable`] slot and swaps it in
+//! and out of the shared context around every operation.
+//!
+//! Clients hold a [`SessionHandle`]. Closing a session bumps its slot's
+//! generation, so a handle kept after `finalize` or `cancel` is refused
+//! instead of reaching whichever session reuses the slot.
+//! [`SessionTable::resume`] turns a handle kept across requests back into a
+//! [`Session`] that takes further updates.
+//!
+//! Handles are plain values, so a client that drops one without closing
+//! its session leaves the slot taken. [`SessionTable::reap`] closes sessions
+//! that have gone unused for a given number of table operations; a server
+//! calls it when `open` runs out of slots, or periodically.
+//!
+//! Each slot keeps `BUF` bytes of the pending partial block. Clients that
+//! only ever hash whole blocks can use an [`AlignedSessionTable`], which
+//! keeps none: its slots are less than half the size and a switch copies
+//! only the digest and counters.
+
+use core::marker::PhantomData;
+
+use crate::hace_controller::HaceController;
+use crate::hash::IntoHashAlgo;
+use proposed_traits::digest::{DigestAlgorithm, DigestInit, DigestOp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionHandle {
+    slot: u8,
+    generation: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionError {
+    /// Every slot holds an open session
+    NoFreeSlot,
+    /// The handle's session was closed, or never existed
+    InvalidHandle,
+    /// The engine reported an error
+    Hash,
+    /// The update would leave more of a partial block pending than the
+    /// table's slots keep
+    UnalignedInput,
+}
+
+/// Running state of a suspended session
+#[derive(Clone, Copy)]
+struct SavedState<const BUF: usize> {
+    digest: [u8; 64],
+    digcnt: [u64; 2],
+    bufcnt: u32,
+    buffer: [u8; BUF],
+}
+
+#[derive(Clone, Copy)]
+struct Slot<const BUF: usize> {
+    generation: u16,
+    /// Table operation count when the session was last used
+    used_at: u32,
+    state: Option<SavedState<BUF>>,
+}
+
+/// Up to `N` open sessions of algorithm `A`, each keeping up to `BUF` bytes
+/// of a partial block between updates
+///
+/// The default of 128 covers every block size HACE supports.
+pub struct SessionTable<A, const N: usize, const BUF: usize = 128> {
+    slots: [Slot<BUF>; N],
+    /// Operations so far, the clock [`SessionTable::reap`] measures idleness by
+    ops: u32,
+    _algo: PhantomData<A>,
+}
+
+/// Sessions that only accept whole blocks, see [`SessionTable::update`]
+pub type AlignedSessionTable<A, const N: usize> = SessionTable<A, N, 0>;
+
+impl<A, const N: usize, const BUF: usize> Default for SessionTable<A, N, BUF>
+where
+    A: DigestAlgorithm + IntoHashAlgo + Default,
+    A::DigestOutput: Default + AsMut<[u8]>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A, const N: usize, const BUF: usize> SessionTable<A, N, BUF>
+where
+    A: DigestAlgorithm + IntoHashAlgo + Default,
+    A::DigestOutput: Default + AsMut<[u8]>,
+{
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            slots: [Slot {
+                generation: 0,
+                used_at: 0,
+                state: None,
+            }; N],
+            ops: 0,
+            _algo: PhantomData,
+        }
+    }
+
+    /// Number of open sessions
+    #[must_use]
+    pub fn in_use(&self) -> usize {
+        self.slots.iter().filter(|s| s.state.is_some()).count()
+    }
+
+    /// Opens a session in a free slot
+    pub fn open(&mut self, hace: &mut HaceController) -> Result<SessionHandle, SessionError> {
+        self.ops = self.ops.wrapping_add(1);
+        let ops = self.ops;
+        let (index, slot) = self
+            .slots
+            .iter_mut()
+            .enumerate()
+            .find(|(_, s)| s.state.is_none())
+            .ok_or(SessionError::NoFreeSlot)?;
+        let slot_index = u8::try_from(index).map_err(|_| SessionError::NoFreeSlot)?;
+        let ctx = hace.init(A::default()).unwrap_or_else(|e| match e {});
+        slot.used_at = ops;
+        slot.state = Some(save(ctx.controller));
+        Ok(SessionHandle {
+            slot: slot_index,
+            generation: slot.generation,
+        })
+    }
+
+    /// Feeds `data` to the session of `handle`
+    ///
+    /// Fails with [`SessionError::UnalignedInput`], leaving the session
+    /// unchanged, if more than `BUF` bytes of a partial block would be left
+    /// pending. An [`AlignedSessionTable`] therefore takes whole blocks only.
+    pub fn update(
+        &mut self,
+        hace: &mut HaceController,
+        handle: SessionHandle,
+        data: &[u8],
+    ) -> Result<(), SessionError> {
+        let slot = self.lookup(handle)?;
+        let state = slot.state.as_ref().ok_or(SessionError::InvalidHandle)?;
+        let mut ctx = hace.init(A::default()).unwrap_or_else(|e| match e {});
+        let block_size = ctx.controller.ctx_mut().block_size as usize;
+        if (state.bufcnt as usize + data.len()) % block_size > BUF {
+            return Err(SessionError::UnalignedInput);
+        }
+        restore(ctx.controller, state);
+        ctx.update(data).map_err(|_| SessionError::Hash)?;
+        slot.state = Some(save(ctx.controller));
+        Ok(())
+    }
+
+    /// Returns the digest of the session of `handle` and closes it
+    pub fn finalize(
+        &mut self,
+        hace: &mut HaceController,
+        handle: SessionHandle,
+    ) -> Result<A::DigestOutput, SessionError> {
+        let slot = self.lookup(handle)?;
+        let state = slot.state.as_ref().ok_or(SessionError::InvalidHandle)?;
+        let ctx = hace.init(A::default()).unwrap_or_else(|e| match e {});
+        restore(ctx.controller, state);
+        // closed whatever the outcome, so a failed session cannot linger
+        Self::release(slot);
+        ctx.finalize().map_err(|_| SessionError::Hash)
+    }
+
+    /// Closes the session of `handle` without a digest
+    pub fn cancel(&mut self, handle: SessionHandle) -> Result<(), SessionError> {
+        Self::release(self.lookup(handle)?);
+        Ok(())
+    }
+
+    /// Returns the session of `handle` for further updates, or `None` if it
+    /// was closed
+    pub fn resume<'a>(
+        &'a mut self,
+        hace: &'a mut HaceController,
+        handle: SessionHandle,
+    ) -> Option<Session<'a, A, N, BUF>> {
+        self.lookup(handle).ok()?;
+        Some(Session {
+            table: self,
+            hace,
+            handle,
+        })
+    }
+
+    /// Closes every session not used in the last `max_idle` table
+    /// operations and returns how many were closed
+    ///
+    /// Every `open`, and every call that takes a handle, counts as an
+    /// operation, failed ones included. Handles of reaped sessions are refused like those of
+    /// cancelled ones.
+    pub fn reap(&mut self, max_idle: u32) -> usize {
+        let ops = self.ops;
+        let mut reaped = 0;
+        for slot in &mut self.slots {
+            if slot.state.is_some() && ops.wrapping_sub(slot.used_at) > max_idle {
+                Self::release(slot);
+                reaped += 1;
+            }
+        }
+        reaped
+    }
+
+    fn lookup(&mut self, handle: SessionHandle) -> Result<&mut Slot<BUF>, SessionError> {
+        self.ops = self.ops.wrapping_add(1);
+        let ops = self.ops;
+        match self.slots.get_mut(usize::from(handle.slot)) {
+            Some(slot) if slot.generation == handle.generation && slot.state.is_some() => {
+                slot.used_at = ops;
+                Ok(slot)
+            }
+            _ => Err(SessionError::InvalidHandle),
+        }
+    }
+
+    fn release(slot: &mut Slot<BUF>) {
+        slot.state = None;
+        slot.generation = slot.generation.wrapping_add(1);
+    }
+}
+
+/// An open session of a [`SessionTable`], from [`SessionTable::resume`]
+pub struct Session<'a, A, const N: usize, const BUF: usize> {
+    table: &'a mut SessionTable<A, N, BUF>,
+    hace: &'a mut HaceController,
+    handle: SessionHandle,
+}
+
+impl<A, const N: usize, const BUF: usize> Session<'_, A, N, BUF>
+where
+    A: DigestAlgorithm + IntoHashAlgo + Default,
+    A::DigestOutput: Default + AsMut<[u8]>,
+{
+    #[must_use]
+    pub fn handle(&self) -> SessionHandle {
+        self.handle
+    }
+
+    /// See [`SessionTable::update`]
+    pub fn update(&mut self, data: &[u8]) -> Result<(), SessionError> {
+        self.table.update(self.hace, self.handle, data)
+    }
+
+    /// See [`SessionTable::finalize`]
+    pub fn finalize(self) -> Result<A::DigestOutput, SessionError> {
+        self.table.finalize(self.hace, self.handle)
+    }
+}
+
+fn save<const BUF: usize>(hace: &mut HaceController) -> SavedState<BUF> {
+    let ctx = hace.ctx_mut();
+    let mut state = SavedState {
+        digest: ctx.digest,
+        digcnt: ctx.digcnt,
+        bufcnt: ctx.bufcnt,
+        buffer: [0; BUF],
+    };
+    state.buffer.copy_from_slice(&ctx.buffer[..BUF]);
+    state
+}
+
+fn restore<const BUF: usize>(hace: &mut HaceController, state: &SavedState<BUF>) {
+    let ctx = hace.ctx_mut();
+    ctx.digest = state.digest;
+    ctx.digcnt = state.digcnt;
+    ctx.bufcnt = state.bufcnt;
+    ctx.buffer[..BUF].copy_from_slice(&state.buffer);
+}
@@ -1,6 +1,9 @@
 // Licensed under the Apache-2.0 license
 
-use crate::hace_controller::{ContextCleanup, HaceController, HashAlgo, HACE_SG_EN};
+use crate::common::VerifyDecision;
+use crate::hace_controller::{ContextCleanup, HaceController, HashAlgo, HACE_SG_LAST};
+use crate::keystore::{HmacKeyLookup, KeyHandle, KeyStoreError};
+use core::sync::atomic::{compiler_fence, Ordering};
 use proposed_traits::mac::{Error, ErrorKind, ErrorType, MacAlgorithm, MacInit, MacOp};
 
 // MacAlgorithm implementation for HashAlgo
@@ -10,6 +13,10 @@ impl MacAlgorithm for HashAlgo {
     type Key = [u8; 64]; // Use the maximum size for all variants
 }
 
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not supported for HMAC in this build",
+    note = "HMAC needs the `hmac` cargo feature, plus `sha384` or `sha512` for those digests"
+)]
 pub trait IntoHashAlgo {
     fn to_hash_algo() -> HashAlgo;
 }
@@ -107,18 +114,21 @@ impl Default for Sha512 {
     }
 }
 
+#[cfg(feature = "hmac")]
 impl IntoHashAlgo for Sha256 {
     fn to_hash_algo() -> HashAlgo {
         HashAlgo::SHA256
     }
 }
 
+#[cfg(all(feature = "hmac", feature = "sha384"))]
 impl IntoHashAlgo for Sha384 {
     fn to_hash_algo() -> HashAlgo {
         HashAlgo::SHA384
     }
 }
 
+#[cfg(all(feature = "hmac", feature = "sha512"))]
 impl IntoHashAlgo for Sha512 {
     fn to_hash_algo() -> HashAlgo {
         HashAlgo::SHA512
@@ -137,33 +147,7 @@ where
         Self: 'a; // Define your OpContext type here
 
     fn init<'a>(&'a mut self, _algo: A, key: &A::Key) -> Result<Self::OpContext<'a>, Self::Error> {
-        self.algo = A::to_hash_algo();
-        self.ctx_mut().method = self.algo.hash_cmd();
-        self.copy_iv_to_digest();
-        self.ctx_mut().block_size = u32::try_from(self.algo.block_size()).unwrap();
-        self.ctx_mut().bufcnt = 0;
-        self.ctx_mut().digcnt = [0; 2];
-        self.ctx_mut().buffer.fill(0);
-        self.ctx_mut().digest.fill(0);
-        self.ctx_mut().ipad.fill(0);
-        self.ctx_mut().opad.fill(0);
-        self.ctx_mut().key.fill(0);
-
-        if key.as_ref().len() > self.ctx_mut().key.len() {
-            // hash key if it is too long
-            self.hash_key(key);
-        } else {
-            self.ctx_mut().key[..key.as_ref().len()].copy_from_slice(key.as_ref());
-            self.ctx_mut().ipad[..key.as_ref().len()].copy_from_slice(key.as_ref());
-            self.ctx_mut().opad[..key.as_ref().len()].copy_from_slice(key.as_ref());
-            self.ctx_mut().key_len = u32::try_from(key.as_ref().len()).unwrap();
-        }
-
-        for i in 0..self.ctx_mut().block_size as usize {
-            self.ctx_mut().ipad[i] ^= 0x36;
-            self.ctx_mut().opad[i] ^= 0x5c;
-        }
-
+        self.hmac_setup(A::to_hash_algo(), key.as_ref());
         Ok(OpContextImpl {
             controller: self,
             _phantom: core::marker::PhantomData,
@@ -206,54 +190,13 @@ where
     type Output = A::MacOutput;
 
     fn update(&mut self, input: &[u8]) -> Result<(), Self::Error> {
-        let ctrl: &mut HaceController = self.controller;
-        let algo = ctrl.algo;
-        let block_size = algo.block_size();
-        let digest_size = algo.digest_size();
-        let mut bufcnt: u32;
-
-        {
-            let ctx = ctrl.ctx_mut();
-            ctx.digcnt[0] = block_size as u64;
-            ctx.bufcnt =
-                u32::try_from(block_size).map_err(|_| MacError(ErrorKind::InvalidInputLength))?;
-
-            // H(ipad + input)
-            let ipad = &ctx.ipad[..block_size];
-            ctx.buffer[..algo.block_size()].copy_from_slice(ipad);
-            ctx.buffer[algo.block_size()..(algo.block_size() + input.len())].copy_from_slice(input);
-            ctx.digcnt[0] += input.len() as u64;
-            ctx.bufcnt +=
-                u32::try_from(input.len()).map_err(|_| MacError(ErrorKind::InvalidInputLength))?;
-            ctx.method &= !HACE_SG_EN; // Disable SG mode for key hashing
-        }
-
-        ctrl.fill_padding(0);
-        bufcnt = ctrl.ctx_mut().bufcnt;
-        ctrl.copy_iv_to_digest();
-        ctrl.start_hash_operation(bufcnt);
-        let slice =
-            unsafe { core::slice::from_raw_parts(ctrl.ctx_mut().digest.as_ptr(), digest_size) };
-
-        // H(opad + H(opad + hash sum))
-        {
-            let ctx = ctrl.ctx_mut();
-            ctx.digcnt[0] = block_size as u64 + digest_size as u64;
-            ctx.bufcnt = u32::try_from(block_size + digest_size)
-                .map_err(|_| MacError(ErrorKind::UpdateError))?;
-            ctx.buffer[..block_size].copy_from_slice(&ctx.opad[..block_size]);
-            ctx.buffer[block_size..(block_size + digest_size)].copy_from_slice(slice);
-        }
-        ctrl.fill_padding(0);
-        bufcnt = ctrl.ctx_mut().bufcnt;
-        ctrl.copy_iv_to_digest();
-        ctrl.start_hash_operation(bufcnt);
-
+        self.controller.hash_update(input);
         Ok(())
     }
 
     fn finalize(self) -> Result<Self::Output, Self::Error> {
         let digest_size = self.controller.algo.digest_size();
+        self.controller.hmac_finish();
         let ctx = self.controller.ctx_mut();
 
         let slice = unsafe { core::slice::from_raw_parts(ctx.digest.as_ptr(), digest_size) };
@@ -261,8 +204,256 @@ where
         let mut output = A::MacOutput::default();
         output.as_mut()[..digest_size].copy_from_slice(slice);
 
+        self.controller.wipe_hmac_key();
         self.controller.cleanup_context();
 
         Ok(output) // Return the final output
     }
 }
+
+/// MAC tag truncated to its leading `len` bytes, e.g. HMAC-SHA256-128.
+///
+/// Only the truncated bytes are exposed, and [`TruncatedMac::matches`]
+/// compares exactly that many, so a received short tag cannot be checked
+/// against the full-length output by mistake.
+pub struct TruncatedMac<O> {
+    output: O,
+    len: usize,
+}
+
+impl<O: AsRef<[u8]>> TruncatedMac<O> {
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Compares against a received tag in constant time
+    #[must_use]
+    pub fn matches(&self, tag: &[u8]) -> bool {
+        VerifyDecision::compare(self.as_ref(), tag).is_valid()
+    }
+}
+
+impl<O: AsRef<[u8]>> AsRef<[u8]> for TruncatedMac<O> {
+    fn as_ref(&self) -> &[u8] {
+        &self.output.as_ref()[..self.len]
+    }
+}
+
+impl<A> OpContextImpl<'_, A>
+where
+    A: MacAlgorithm + IntoHashAlgo,
+    A::MacOutput: Default + AsMut<[u8]>,
+{
+    /// Feeds `parts` in order; the MAC is the same as one `update` per part
+    pub fn update_iter<'p>(
+        &mut self,
+        parts: impl IntoIterator<Item = &'p [u8]>,
+    ) -> Result<(), MacError> {
+        parts.into_iter().try_for_each(|part| self.update(part))
+    }
+
+    /// Finalizes the MAC and keeps only its leading `len` bytes.
+    ///
+    /// Fails with `InvalidInputLength` if `len` is zero or larger than the
+    /// digest size; the context is cleaned up either way.
+    pub fn finalize_truncated(self, len: usize) -> Result<TruncatedMac<A::MacOutput>, MacError> {
+        let digest_size = self.controller.algo.digest_size();
+        let output = self.finalize()?;
+        if len == 0 || len > digest_size {
+            return Err(MacError(ErrorKind::InvalidInputLength));
+        }
+        Ok(TruncatedMac { output, len })
+    }
+}
+
+impl HaceController {
+    /// Computes the HMAC of a single buffer and hands the controller back,
+    /// also on error.
+    ///
+    /// Equivalent to `init`, one `update` and `finalize`.
+    pub fn mac<A>(
+        mut self,
+        algo: A,
+        key: &A::Key,
+        data: &[u8],
+    ) -> Result<(A::MacOutput, HaceController), (MacError, HaceController)>
+    where
+        A: MacAlgorithm + IntoHashAlgo,
+        A::MacOutput: Default + AsMut<[u8]>,
+        A::Key: AsRef<[u8]>,
+    {
+        let result = match MacInit::init(&mut self, algo, key) {
+            Ok(mut ctx) => ctx.update(data).and_then(|()| ctx.finalize()),
+            Err(never) => match never {},
+        };
+        match result {
+            Ok(output) => Ok((output, self)),
+            Err(e) => Err((e, self)),
+        }
+    }
+
+    /// Loads `key` and the derived pads for `algo` into the shared context
+    /// and hashes the ipad block, leaving the inner hash ready for the
+    /// message.
+    ///
+    /// A key longer than a block is hashed first, as HMAC specifies. It has
+    /// to fit in the context buffer with its hash padding.
+    fn hmac_setup(&mut self, algo: HashAlgo, key: &[u8]) {
+        self.algo = algo;
+        self.ctx_mut().method = algo.hash_cmd();
+        self.copy_iv_to_digest();
+        self.ctx_mut().block_size = u32::try_from(algo.block_size()).unwrap();
+        self.ctx_mut().bufcnt = 0;
+        self.ctx_mut().digcnt = [0; 2];
+        self.ctx_mut().buffer.fill(0);
+        self.ctx_mut().digest.fill(0);
+        self.ctx_mut().ipad.fill(0);
+        self.ctx_mut().opad.fill(0);
+        self.ctx_mut().key.fill(0);
+
+        if key.len() > algo.block_size() {
+            self.hash_key(&key);
+        } else {
+            self.ctx_mut().key[..key.len()].copy_from_slice(key);
+            self.ctx_mut().ipad[..key.len()].copy_from_slice(key);
+            self.ctx_mut().opad[..key.len()].copy_from_slice(key);
+            self.ctx_mut().key_len = u32::try_from(key.len()).unwrap();
+        }
+
+        for i in 0..self.ctx_mut().block_size as usize {
+            self.ctx_mut().ipad[i] ^= 0x36;
+            self.ctx_mut().opad[i] ^= 0x5c;
+        }
+
+        // H(ipad + ...), continued by `hash_update`
+        let block_size = algo.block_size();
+        self.ctx_mut().method = algo.hash_cmd();
+        self.copy_iv_to_digest();
+        {
+            let ctx = self.ctx_mut();
+            ctx.buffer[..block_size].copy_from_slice(&ctx.ipad[..block_size]);
+            ctx.bufcnt = u32::try_from(block_size).unwrap();
+            ctx.digcnt = [block_size as u64, 0];
+        }
+        self.hash_buffer();
+        self.ctx_mut().bufcnt = 0;
+    }
+
+    /// Finishes the inner hash over what `hash_update` fed since
+    /// `hmac_setup` and runs the outer hash; the MAC is left in the context
+    /// digest
+    fn hmac_finish(&mut self) {
+        let block_size = self.algo.block_size();
+        let digest_size = self.algo.digest_size();
+
+        self.fill_padding(0);
+        self.hash_buffer();
+
+        // H(opad + H(ipad + message))
+        {
+            let ctx = self.ctx_mut();
+            let end = block_size + digest_size;
+            ctx.buffer[..block_size].copy_from_slice(&ctx.opad[..block_size]);
+            ctx.buffer[block_size..end].copy_from_slice(&ctx.digest[..digest_size]);
+            ctx.bufcnt = u32::try_from(end).unwrap();
+            ctx.digcnt = [end as u64, 0];
+        }
+        self.copy_iv_to_digest();
+        self.fill_padding(0);
+        self.hash_buffer();
+    }
+
+    /// Hashes the `bufcnt` bytes in the context buffer as the last entry
+    fn hash_buffer(&mut self) {
+        let ctx = self.ctx_mut();
+        ctx.sg[0].addr = ctx.buffer.as_ptr() as u32;
+        ctx.sg[0].len = ctx.bufcnt | HACE_SG_LAST;
+        let bufcnt = ctx.bufcnt;
+        self.start_hash_operation(bufcnt);
+    }
+
+    /// Zeroes the key, the pads and the buffer, which holds the opad block
+    /// after a MAC. Volatile, so the stores are not dropped as dead.
+    fn wipe_hmac_key(&mut self) {
+        let ctx = self.ctx_mut();
+        for b in ctx
+            .key
+            .iter_mut()
+            .chain(&mut ctx.ipad)
+            .chain(&mut ctx.opad)
+            .chain(&mut ctx.buffer)
+        {
+            unsafe { core::ptr::write_volatile(b, 0) };
+        }
+        ctx.key_len = 0;
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// Key for [`HaceController::hmac_oneshot`]
+#[derive(Clone, Copy)]
+pub enum KeyRef<'k> {
+    /// Key bytes held by the caller, of any length
+    Raw(&'k [u8]),
+    /// HMAC-SHA-256 key in a [`crate::keystore::KeyStore`]
+    Handle(&'k dyn HmacKeyLookup, KeyHandle),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HmacError {
+    /// The key handle did not resolve to a key usable with the algorithm
+    Key(KeyStoreError),
+    /// A key longer than a block does not fit in the context buffer
+    KeyTooLong,
+    /// The output buffer is empty or longer than the MAC
+    OutputLength,
+}
+
+impl HaceController {
+    /// Computes the HMAC of `msg` under `key` into `out` and returns the
+    /// number of bytes written.
+    ///
+    /// An `out` shorter than the MAC receives its leading bytes, e.g. 16
+    /// for HMAC-SHA-256-128. Key handles refer to HMAC-SHA-256 keys and
+    /// are refused for other algorithms. The key, the pads and the
+    /// intermediate state are wiped from the context before returning.
+    pub fn hmac_oneshot(
+        &mut self,
+        algo: HashAlgo,
+        key: KeyRef<'_>,
+        msg: &[u8],
+        out: &mut [u8],
+    ) -> Result<usize, HmacError> {
+        let block_size = algo.block_size();
+        let digest_size = algo.digest_size();
+        if out.is_empty() || out.len() > digest_size {
+            return Err(HmacError::OutputLength);
+        }
+        let key = match key {
+            KeyRef::Raw(key) => key,
+            KeyRef::Handle(_, _) if !matches!(algo, HashAlgo::SHA256) => {
+                return Err(HmacError::Key(KeyStoreError::WrongKeyType));
+            }
+            KeyRef::Handle(keys, handle) => keys.hmac_sha256_key(handle).map_err(HmacError::Key)?,
+        };
+        // key + 0x80 marker + length field (1/8 of a block)
+        let capacity = self.ctx_mut().buffer.len() - 1 - block_size / 8;
+        if key.len() > block_size && key.len() > capacity {
+            return Err(HmacError::KeyTooLong);
+        }
+
+        self.hmac_setup(algo, key);
+        self.hash_update(msg);
+        self.hmac_finish();
+        out.copy_from_slice(&self.ctx_mut().digest[..out.len()]);
+        self.wipe_hmac_key();
+        self.cleanup_context();
+        Ok(out.len())
+    }
+}
@@ -0,0 +1,324 @@
+// Licensed under the Apache-2.0 license
+
+//! HMAC challenge-response authentication for host sessions
+//!
+//! Privileged host commands (see [`HostCommand::is_privileged`]) are only
+//! accepted in an authenticated session. The host asks for a nonce with
+//! `GetNonce`, answered from [`HostSessionAuth::challenge`], then sends
+//! `Authenticate` with the opcode it wants to run and
+//! `HMAC-SHA-256(key, nonce || opcode)` under a shared key from the
+//! [`KeyStore`]. A correct response opens the session for a fixed window,
+//! for that opcode only: other privileged commands need their own
+//! `Authenticate`, which replaces the open session.
+//!
+//! A nonce is consumed by the first response, right or wrong, so a captured
+//! response cannot be replayed. After a configured number of failures in a
+//! row the endpoint is locked out until [`HostSessionAuth::unlock`].
+//!
+//! As with [`crate::doorbell`], time is a free-running tick count supplied
+//! by the caller.
+
+use crate::common::VerifyDecision;
+#[cfg(feature = "hmac")]
+use crate::hace_controller::HaceController;
+use crate::host_policy::{CommandStatus, HostCommand};
+use crate::keystore::{HmacKeyLookup, KeyHandle, KeyStore};
+
+pub const NONCE_LEN: usize = 32;
+
+/// Source of fresh challenge nonces, e.g. a hardware RNG
+pub trait NonceSource {
+    fn fill_nonce(&mut self, nonce: &mut [u8; NONCE_LEN]);
+}
+
+/// HMAC-SHA-256 over the concatenation of `parts`
+pub trait ChallengeMac {
+    fn hmac_sha256(&mut self, key: &[u8; 32], parts: &[&[u8]]) -> Option<[u8; 32]>;
+}
+
+#[cfg(feature = "hmac")]
+impl ChallengeMac for HaceController {
+    fn hmac_sha256(&mut self, key: &[u8; 32], parts: &[&[u8]]) -> Option<[u8; 32]> {
+        use crate::hmac::Sha256;
+        use proposed_traits::mac::{MacInit, MacOp};
+
+        let mut ctx = match MacInit::init(self, Sha256, key) {
+            Ok(ctx) => ctx,
+            Err(never) => match never {},
+        };
+        ctx.update_iter(parts.iter().copied()).ok()?;
+        ctx.finalize().ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostAuthError {
+    /// `max_failures` is zero, which would lock the endpoint out for good
+    NoAttempts,
+}
+
+/// An open session: the tick it opened at and the opcode it admits
+#[derive(Clone, Copy)]
+struct Session {
+    at: u32,
+    opcode: u8,
+}
+
+pub struct HostSessionAuth<N> {
+    nonces: N,
+    key: KeyHandle,
+    window: u32,
+    max_failures: u8,
+    nonce: Option<[u8; NONCE_LEN]>,
+    session: Option<Session>,
+    failures: u8,
+}
+
+impl<N: NonceSource> HostSessionAuth<N> {
+    /// Sessions last `window` ticks after authentication; `max_failures`
+    /// wrong responses in a row lock the endpoint out
+    pub fn new(
+        nonces: N,
+        key: KeyHandle,
+        window: u32,
+        max_failures: u8,
+    ) -> Result<Self, HostAuthError> {
+        if max_failures == 0 {
+            return Err(HostAuthError::NoAttempts);
+        }
+        Ok(Self {
+            nonces,
+            key,
+            window,
+            max_failures,
+            nonce: None,
+            session: None,
+            failures: 0,
+        })
+    }
+
+    #[must_use]
+    pub fn is_locked_out(&self) -> bool {
+        self.failures >= self.max_failures
+    }
+
+    /// New nonce for `GetNonce`; any outstanding nonce is discarded
+    pub fn challenge(&mut self) -> Result<[u8; NONCE_LEN], CommandStatus> {
+        if self.is_locked_out() {
+            return Err(CommandStatus::LockedOut);
+        }
+        let mut nonce = [0u8; NONCE_LEN];
+        self.nonces.fill_nonce(&mut nonce);
+        self.nonce = Some(nonce);
+        Ok(nonce)
+    }
+
+    /// Checks the `Authenticate` response for `opcode` against the
+    /// outstanding nonce and opens a session for `opcode` at `now`.
+    ///
+    /// A wrong response, or one without a nonce to answer, ends any open
+    /// session and counts towards the lockout.
+    pub fn authenticate<M: ChallengeMac, const K: usize>(
+        &mut self,
+        mac: &mut M,
+        keys: &KeyStore<K>,
+        opcode: u8,
+        response: &[u8],
+        now: u32,
+    ) -> Result<(), CommandStatus> {
+        if self.is_locked_out() {
+            return Err(CommandStatus::LockedOut);
+        }
+        // single use, whatever the outcome
+        let Some(nonce) = self.nonce.take() else {
+            return Err(self.fail());
+        };
+        // borrowed from the store, so the secret is never copied out
+        let key: &[u8; 32] = keys
+            .hmac_sha256_key(self.key)
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or(CommandStatus::AuthFailed)?;
+        let expected = mac
+            .hmac_sha256(key, &[&nonce, &[opcode]])
+            .ok_or(CommandStatus::AuthFailed)?;
+        if !VerifyDecision::compare(&expected, response).is_valid() {
+            return Err(self.fail());
+        }
+        self.failures = 0;
+        self.session = Some(Session { at: now, opcode });
+        Ok(())
+    }
+
+    /// Admits `cmd` if it is not privileged, or if the session is open at
+    /// `now` and was authenticated for `cmd`
+    pub fn check(&self, cmd: HostCommand, now: u32) -> Result<(), CommandStatus> {
+        let open = self.session.is_some_and(|session| {
+            session.opcode == cmd as u8 && now.wrapping_sub(session.at) < self.window
+        });
+        if !cmd.is_privileged() || open {
+            Ok(())
+        } else {
+            Err(CommandStatus::NotAuthenticated)
+        }
+    }
+
+    /// Closes the session, e.g. once an update has been committed
+    pub fn end_session(&mut self) {
+        self.session = None;
+    }
+
+    /// Clears a lockout, e.g. after a physical presence check
+    pub fn unlock(&mut self) {
+        self.failures = 0;
+    }
+
+    fn fail(&mut self) -> CommandStatus {
+        self.session = None;
+        self.failures = self.failures.saturating_add(1);
+        if self.is_locked_out() {
+            CommandStatus::LockedOut
+        } else {
+            CommandStatus::AuthFailed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChallengeMac, HostAuthError, HostSessionAuth, NonceSource, NONCE_LEN};
+    use crate::host_policy::{CommandStatus, HostCommand};
+    use crate::keystore::KeyStore;
+
+    struct CountingNonces(u8);
+
+    impl NonceSource for CountingNonces {
+        fn fill_nonce(&mut self, nonce: &mut [u8; NONCE_LEN]) {
+            self.0 += 1;
+            nonce.fill(self.0);
+        }
+    }
+
+    /// Stand-in keyed mix; only has to depend on the key and every byte
+    struct MixMac;
+
+    impl ChallengeMac for MixMac {
+        fn hmac_sha256(&mut self, key: &[u8; 32], parts: &[&[u8]]) -> Option<[u8; 32]> {
+            let mut out = *key;
+            for (i, b) in parts.iter().flat_map(|p| p.iter()).enumerate() {
+                out[i % 32] = out[i % 32].rotate_left(3) ^ b;
+            }
+            Some(out)
+        }
+    }
+
+    const UPDATE: u8 = HostCommand::UpdateStart as u8;
+
+    fn setup() -> (KeyStore<1>, HostSessionAuth<CountingNonces>) {
+        let mut keys = KeyStore::new();
+        let key = keys.import_hmac_sha256(&[0x42; 32]).unwrap();
+        (
+            keys,
+            HostSessionAuth::new(CountingNonces(0), key, 100, 3).unwrap(),
+        )
+    }
+
+    fn respond(nonce: &[u8; NONCE_LEN]) -> [u8; 32] {
+        MixMac
+            .hmac_sha256(&[0x42; 32], &[nonce, &[UPDATE]])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_challenge_response() {
+        let (keys, mut auth) = setup();
+        assert_eq!(auth.check(HostCommand::ReadStatus, 0), Ok(()));
+        assert_eq!(
+            auth.check(HostCommand::UpdateStart, 0),
+            Err(CommandStatus::NotAuthenticated)
+        );
+
+        let nonce = auth.challenge().unwrap();
+        let response = respond(&nonce);
+        assert_eq!(
+            auth.authenticate(&mut MixMac, &keys, UPDATE, &response, 10),
+            Ok(())
+        );
+        assert_eq!(auth.check(HostCommand::UpdateStart, 109), Ok(()));
+        // the session admits only the opcode it was authenticated for
+        assert_eq!(auth.check(HostCommand::ReadStatus, 50), Ok(()));
+        assert_eq!(
+            auth.check(HostCommand::ExitRecovery, 50),
+            Err(CommandStatus::NotAuthenticated)
+        );
+        assert_eq!(
+            auth.check(HostCommand::UpdateStart, 110),
+            Err(CommandStatus::NotAuthenticated)
+        );
+
+        // the nonce was consumed, so the same response is refused
+        assert_eq!(
+            auth.authenticate(&mut MixMac, &keys, UPDATE, &response, 20),
+            Err(CommandStatus::AuthFailed)
+        );
+        // and the refused replay closed the session
+        assert_eq!(
+            auth.check(HostCommand::UpdateStart, 20),
+            Err(CommandStatus::NotAuthenticated)
+        );
+    }
+
+    #[test]
+    fn test_response_bound_to_nonce_and_opcode() {
+        let (keys, mut auth) = setup();
+        let old = auth.challenge().unwrap();
+        let _ = auth.challenge().unwrap();
+        assert_eq!(
+            auth.authenticate(&mut MixMac, &keys, UPDATE, &respond(&old), 0),
+            Err(CommandStatus::AuthFailed)
+        );
+
+        let nonce = auth.challenge().unwrap();
+        let exit = HostCommand::ExitRecovery as u8;
+        assert_eq!(
+            auth.authenticate(&mut MixMac, &keys, exit, &respond(&nonce), 0),
+            Err(CommandStatus::AuthFailed)
+        );
+    }
+
+    #[test]
+    fn test_no_attempts_refused() {
+        let mut keys = KeyStore::<1>::new();
+        let key = keys.import_hmac_sha256(&[0x42; 32]).unwrap();
+        assert!(matches!(
+            HostSessionAuth::new(CountingNonces(0), key, 100, 0),
+            Err(HostAuthError::NoAttempts)
+        ));
+    }
+
+    #[test]
+    fn test_lockout() {
+        let (keys, mut auth) = setup();
+        for expected in [CommandStatus::AuthFailed, CommandStatus::AuthFailed] {
+            auth.challenge().unwrap();
+            assert_eq!(
+                auth.authenticate(&mut MixMac, &keys, UPDATE, &[0; 32], 0),
+                Err(expected)
+            );
+        }
+        auth.challenge().unwrap();
+        assert_eq!(
+            auth.authenticate(&mut MixMac, &keys, UPDATE, &[0; 32], 0),
+            Err(CommandStatus::LockedOut)
+        );
+        assert!(auth.is_locked_out());
+        assert_eq!(auth.challenge(), Err(CommandStatus::LockedOut));
+
+        auth.unlock();
+        let nonce = auth.challenge().unwrap();
+        assert_eq!(
+            auth.authenticate(&mut MixMac, &keys, UPDATE, &respond(&nonce), 0),
+            Ok(())
+        );
+    }
+}
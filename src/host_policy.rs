@@ -0,0 +1,264 @@
+// Licensed under the Apache-2.0 license
+
+//! Boot-state command policy for host-facing endpoints
+//!
+//! Host commands arriving over the I2C mailbox or update protocol are checked
+//! against a fixed permission table before they are executed. A handler calls
+//! [`CommandPolicy::check`] with the raw opcode and, on rejection, answers the
+//! host with the returned [`CommandStatus`] instead of running the command.
+//!
+//! Moving between boot states is itself a command and must be authenticated
+//! through a [`TransitionAuthenticator`], e.g. [`EcdsaTransitionAuth`].
+
+use crate::ecdsa::{PublicKey, Scalar48, Secp384r1Curve, Signature};
+use proposed_traits::digest::DigestAlgorithm;
+use proposed_traits::ecdsa::{Curve, EcdsaVerify};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BootState {
+    Normal = 0,
+    Recovery = 1,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum HostCommand {
+    ReadStatus = 0x01,
+    ReadMeasurement = 0x02,
+    UpdateStart = 0x10,
+    UpdateWrite = 0x11,
+    UpdateCommit = 0x12,
+    EnterRecovery = 0x20,
+    ExitRecovery = 0x21,
+    GetNonce = 0x30,
+    Authenticate = 0x31,
+}
+
+impl HostCommand {
+    #[must_use]
+    pub fn from_u8(opcode: u8) -> Option<Self> {
+        PERMISSIONS
+            .iter()
+            .map(|p| p.cmd)
+            .find(|cmd| *cmd as u8 == opcode)
+    }
+
+    /// Commands that need an authenticated session, see
+    /// [`crate::host_auth::HostSessionAuth`]
+    #[must_use]
+    pub fn is_privileged(self) -> bool {
+        matches!(
+            self,
+            HostCommand::UpdateStart
+                | HostCommand::UpdateWrite
+                | HostCommand::UpdateCommit
+                | HostCommand::EnterRecovery
+                | HostCommand::ExitRecovery
+        )
+    }
+}
+
+/// Status byte returned to the host in the protocol response
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CommandStatus {
+    Success = 0x00,
+    UnknownCommand = 0x01,
+    NotAllowedInState = 0x02,
+    AuthFailed = 0x03,
+    /// Privileged command outside an authenticated session
+    NotAuthenticated = 0x04,
+    /// Too many failed authentication attempts
+    LockedOut = 0x05,
+}
+
+struct Permission {
+    cmd: HostCommand,
+    normal: bool,
+    recovery: bool,
+}
+
+const PERMISSIONS: [Permission; 9] = [
+    Permission {
+        cmd: HostCommand::ReadStatus,
+        normal: true,
+        recovery: true,
+    },
+    Permission {
+        cmd: HostCommand::ReadMeasurement,
+        normal: true,
+        recovery: true,
+    },
+    Permission {
+        cmd: HostCommand::UpdateStart,
+        normal: false,
+        recovery: true,
+    },
+    Permission {
+        cmd: HostCommand::UpdateWrite,
+        normal: false,
+        recovery: true,
+    },
+    Permission {
+        cmd: HostCommand::UpdateCommit,
+        normal: false,
+        recovery: true,
+    },
+    Permission {
+        cmd: HostCommand::EnterRecovery,
+        normal: true,
+        recovery: false,
+    },
+    Permission {
+        cmd: HostCommand::ExitRecovery,
+        normal: false,
+        recovery: true,
+    },
+    Permission {
+        cmd: HostCommand::GetNonce,
+        normal: true,
+        recovery: true,
+    },
+    Permission {
+        cmd: HostCommand::Authenticate,
+        normal: true,
+        recovery: true,
+    },
+];
+
+/// Decides whether a boot-state transition request is genuine
+pub trait TransitionAuthenticator {
+    fn authenticate(&mut self, target: BootState, auth: &[u8]) -> bool;
+}
+
+pub struct CommandPolicy {
+    state: BootState,
+}
+
+impl CommandPolicy {
+    #[must_use]
+    pub fn new(state: BootState) -> Self {
+        Self { state }
+    }
+
+    #[must_use]
+    pub fn state(&self) -> BootState {
+        self.state
+    }
+
+    /// Validates `opcode` against the current boot state.
+    pub fn check(&self, opcode: u8) -> Result<HostCommand, CommandStatus> {
+        let cmd = HostCommand::from_u8(opcode).ok_or(CommandStatus::UnknownCommand)?;
+        let allowed = PERMISSIONS
+            .iter()
+            .find(|p| p.cmd == cmd)
+            .is_some_and(|p| match self.state {
+                BootState::Normal => p.normal,
+                BootState::Recovery => p.recovery,
+            });
+        if allowed {
+            Ok(cmd)
+        } else {
+            Err(CommandStatus::NotAllowedInState)
+        }
+    }
+
+    /// Handles a state-transition command (`EnterRecovery`/`ExitRecovery`).
+    ///
+    /// The command must be allowed in the current state and `auth` must be
+    /// accepted by `authenticator`; the state is unchanged otherwise.
+    pub fn transition<A: TransitionAuthenticator>(
+        &mut self,
+        opcode: u8,
+        auth: &[u8],
+        authenticator: &mut A,
+    ) -> Result<BootState, CommandStatus> {
+        let target = match self.check(opcode)? {
+            HostCommand::EnterRecovery => BootState::Recovery,
+            HostCommand::ExitRecovery => BootState::Normal,
+            _ => return Err(CommandStatus::UnknownCommand),
+        };
+        if !authenticator.authenticate(target, auth) {
+            return Err(CommandStatus::AuthFailed);
+        }
+        self.state = target;
+        Ok(target)
+    }
+}
+
+/// Bytes of fresh nonce in an [`EcdsaTransitionAuth`] challenge
+pub const TRANSITION_NONCE_LEN: usize = Scalar48::LEN - 1;
+
+/// Transition authenticator backed by secp384r1 ECDSA
+///
+/// The caller sets a fresh nonce with [`EcdsaTransitionAuth::set_challenge`].
+/// The host signs [`EcdsaTransitionAuth::challenge_for`] the state it asks
+/// for, the nonce followed by the target state, and answers with
+/// `challenge || r || s`. The request is accepted only if that challenge is
+/// the one for the requested target and the signature verifies, so a
+/// signature for one transition cannot authorize another.
+pub struct EcdsaTransitionAuth<'a, V> {
+    verifier: &'a mut V,
+    public_key: PublicKey,
+    nonce: Option<[u8; TRANSITION_NONCE_LEN]>,
+}
+
+impl<'a, V> EcdsaTransitionAuth<'a, V>
+where
+    V: EcdsaVerify<Secp384r1Curve, PublicKey = PublicKey, Signature = Signature>,
+{
+    pub fn new(verifier: &'a mut V, public_key: PublicKey) -> Self {
+        Self {
+            verifier,
+            public_key,
+            nonce: None,
+        }
+    }
+
+    pub fn set_challenge(&mut self, nonce: [u8; TRANSITION_NONCE_LEN]) {
+        self.nonce = Some(nonce);
+    }
+
+    /// The value the host signs to move to `target`
+    #[must_use]
+    pub fn challenge_for(
+        nonce: &[u8; TRANSITION_NONCE_LEN],
+        target: BootState,
+    ) -> [u8; Scalar48::LEN] {
+        let mut challenge = [0u8; Scalar48::LEN];
+        challenge[..TRANSITION_NONCE_LEN].copy_from_slice(nonce);
+        challenge[TRANSITION_NONCE_LEN] = target as u8;
+        challenge
+    }
+}
+
+impl<V> TransitionAuthenticator for EcdsaTransitionAuth<'_, V>
+where
+    V: EcdsaVerify<Secp384r1Curve, PublicKey = PublicKey, Signature = Signature>,
+{
+    fn authenticate(&mut self, target: BootState, auth: &[u8]) -> bool {
+        // A challenge is single use
+        let Some(nonce) = self.nonce.take() else {
+            return false;
+        };
+        let challenge = Self::challenge_for(&nonce, target);
+        if auth.len() != 3 * Scalar48::LEN || auth[..Scalar48::LEN] != challenge {
+            return false;
+        }
+        let mut r = [0u8; 48];
+        let mut s = [0u8; 48];
+        r.copy_from_slice(&auth[Scalar48::LEN..2 * Scalar48::LEN]);
+        s.copy_from_slice(&auth[2 * Scalar48::LEN..]);
+        let signature = Signature {
+            r: Scalar48(r),
+            s: Scalar48(s),
+        };
+        let mut digest =
+            <<Secp384r1Curve as Curve>::DigestType as DigestAlgorithm>::DigestOutput::default();
+        digest.as_mut().copy_from_slice(&challenge);
+        self.verifier
+            .verify(&self.public_key, digest, &signature)
+            .is_ok()
+    }
+}
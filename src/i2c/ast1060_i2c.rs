@@ -1,10 +1,14 @@
 // Licensed under the Apache-2.0 license
 
-use crate::common::{DmaBuffer, DummyDelay, Logger};
+use crate::common::dma_pool::{DmaChunk, DmaPool, DmaRegion};
+use crate::common::{Deadline, DmaBuffer, DummyDelay, LogLevel, LogValue, Logger};
 #[cfg(feature = "i2c_target")]
 use crate::i2c::common::I2cSEvent;
-use crate::i2c::common::{I2cConfig, I2cXferMode};
+#[cfg(feature = "i2c_target")]
+use crate::i2c::common::SMBUS_HOST_NOTIFY_ADDR;
+use crate::i2c::common::{ConfigurationError, HostNotify, I2cConfig, I2cSpeed, I2cXferMode};
 use crate::i2c::i2c_controller::HardwareInterface;
+use crate::pinctrl::Pinctrl;
 use ast1060_pac::{I2cglobal, Scu};
 use core::cmp::min;
 use core::fmt::Write;
@@ -21,10 +25,11 @@ pub trait Instance {
     fn ptr() -> *const ast1060_pac::i2c::RegisterBlock;
     fn buff_ptr() -> *const ast1060_pac::i2cbuff::RegisterBlock;
     const BUS_NUM: u8;
+    const IRQ: ast1060_pac::Interrupt;
 }
 
 macro_rules! macro_i2c {
-    ($I2cx: ident, $I2cbuffx: ident, $x: literal) => {
+    ($I2cx: ident, $I2cbuffx: ident, $x: literal, $irq: ident) => {
         impl Instance for ast1060_pac::$I2cx {
             fn ptr() -> *const ast1060_pac::i2c::RegisterBlock {
                 ast1060_pac::$I2cx::ptr()
@@ -33,25 +38,29 @@ macro_rules! macro_i2c {
                 ast1060_pac::$I2cbuffx::ptr()
             }
             const BUS_NUM: u8 = $x;
+            const IRQ: ast1060_pac::Interrupt = ast1060_pac::Interrupt::$irq;
         }
     };
 }
-macro_i2c!(I2c, I2cbuff, 0);
-macro_i2c!(I2c1, I2cbuff1, 1);
-macro_i2c!(I2c2, I2cbuff2, 2);
-macro_i2c!(I2c3, I2cbuff3, 3);
-macro_i2c!(I2c4, I2cbuff4, 4);
-macro_i2c!(I2c5, I2cbuff5, 5);
-macro_i2c!(I2c6, I2cbuff6, 6);
-macro_i2c!(I2c7, I2cbuff7, 7);
-macro_i2c!(I2c8, I2cbuff8, 8);
-macro_i2c!(I2c9, I2cbuff9, 9);
-macro_i2c!(I2c10, I2cbuff10, 10);
-macro_i2c!(I2c11, I2cbuff11, 11);
-macro_i2c!(I2c12, I2cbuff12, 12);
-macro_i2c!(I2c13, I2cbuff13, 13);
+macro_i2c!(I2c, I2cbuff, 0, i2c);
+macro_i2c!(I2c1, I2cbuff1, 1, i2c1);
+macro_i2c!(I2c2, I2cbuff2, 2, i2c2);
+macro_i2c!(I2c3, I2cbuff3, 3, i2c3);
+macro_i2c!(I2c4, I2cbuff4, 4, i2c4);
+macro_i2c!(I2c5, I2cbuff5, 5, i2c5);
+macro_i2c!(I2c6, I2cbuff6, 6, i2c6);
+macro_i2c!(I2c7, I2cbuff7, 7, i2c7);
+macro_i2c!(I2c8, I2cbuff8, 8, i2c8);
+macro_i2c!(I2c9, I2cbuff9, 9, i2c9);
+macro_i2c!(I2c10, I2cbuff10, 10, i2c10);
+macro_i2c!(I2c11, I2cbuff11, 11, i2c11);
+macro_i2c!(I2c12, I2cbuff12, 12, i2c12);
+macro_i2c!(I2c13, I2cbuff13, 13, i2c13);
 
 const HPLL_FREQ: u32 = 1_000_000_000;
+/// Slowest clock source that reaches 1 MHz: ten base clocks per SCL period
+/// is the least that fits tLOW and tHIGH with the default 9/16 split
+const FAST_PLUS_MIN_CLK_SRC: u32 = 10 * I2cSpeed::FastPlus as u32;
 
 const AST_I2CC_SLAVE_EN: u32 = 1 << 1;
 
@@ -96,6 +105,9 @@ const SLAVE_TRIGGER_CMD: u32 = AST_I2CS_ACTIVE_ALL | AST_I2CS_PKT_MODE_EN;
 const I2C_SLAVE_BUF_SIZE: usize = 256;
 
 const I2C_BUF_SIZE: u8 = 0x20;
+/// Polls `disable_slave_mode` waits for a stop, 100 us apart
+#[cfg(feature = "i2c_target")]
+const SLAVE_QUIESCE_POLLS: u32 = 1000;
 
 //slave
 const AST_I2CS_RX_DMA_EN: u32 = 1 << 9;
@@ -104,39 +116,25 @@ const AST_I2CS_TX_DMA_EN: u32 = 1 << 8;
 const AST_I2CS_RX_BUFF_EN: u32 = 1 << 7;
 #[cfg(feature = "i2c_target")]
 const AST_I2CS_TX_BUFF_EN: u32 = 1 << 6;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_SLAVE_PENDING: u32 = 1 << 29;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_WAIT_TX_DMA: u32 = 1 << 25;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_WAIT_RX_DMA: u32 = 1 << 24;
+const AST_I2CS_ADDR_INDICATE_SHIFT: u32 = 30;
 #[cfg(feature = "i2c_target")]
-const AST_I2CS_ADDR_INDICATE_MASK: u32 = 3 << 30;
-#[cfg(feature = "i2c_target")]
+const AST_I2CS_ADDR_INDICATE_MASK: u32 = 3 << AST_I2CS_ADDR_INDICATE_SHIFT;
 const AST_I2CS_ADDR3_NAK: u32 = 1 << 22;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_ADDR2_NAK: u32 = 1 << 21;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_ADDR1_NAK: u32 = 1 << 20;
 #[cfg(feature = "i2c_target")]
 const AST_I2CS_ADDR_MASK: u32 = 3 << 18;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_PKT_ERROR: u32 = 1 << 17;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_PKT_DONE: u32 = 1 << 16;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_INACTIVE_TO: u32 = 1 << 15;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_SLAVE_MATCH: u32 = 1 << 7;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_STOP: u32 = 1 << 4;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_RX_DONE_NAK: u32 = 1 << 3;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_RX_DONE: u32 = 1 << 2;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_TX_NAK: u32 = 1 << 1;
-#[cfg(feature = "i2c_target")]
 const AST_I2CS_TX_ACK: u32 = 1 << 0;
 #[cfg(feature = "i2c_target")]
 const AST_I2CS_TX_CMD: u32 = 1 << 2;
@@ -145,6 +143,109 @@ const AST_I2CC_AC_TIMING_MASK: u32 = 0x00ff_ffff;
 #[cfg(feature = "i2c_target")]
 const I2C_TIMEOUT_COUNT: u8 = 0x8; //~35ms
 
+// I2CS40: slave address 2 field and its enable bit
+#[cfg(feature = "i2c_target")]
+const AST_I2CS_ADDR2_SHIFT: u32 = 8;
+#[cfg(feature = "i2c_target")]
+const AST_I2CS_ADDR2_MASK: u32 = 0xff << AST_I2CS_ADDR2_SHIFT;
+#[cfg(feature = "i2c_target")]
+const AST_I2CS_ADDR2_EN: u32 = 1 << 15;
+/// Address slot the SMBus Host Notify address is programmed into
+#[cfg(feature = "i2c_target")]
+const HOST_NOTIFY_ADDR_SLOT: u8 = 2;
+
+const HOST_NOTIFY_QUEUE_LEN: usize = 8;
+
+/// Decoded controller interrupt status (I2CM14)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct I2cMasterStatus {
+    pub tx_ack: bool,
+    pub tx_nak: bool,
+    pub rx_done: bool,
+    pub arbitration_lost: bool,
+    pub normal_stop: bool,
+    pub abnormal: bool,
+    pub scl_low_timeout: bool,
+    pub smbus_alert: bool,
+    pub bus_recovered: bool,
+    pub sda_low_timeout: bool,
+    pub bus_recover_failed: bool,
+    pub packet_done: bool,
+    pub packet_error: bool,
+}
+
+impl I2cMasterStatus {
+    /// Whether any of the bus error conditions is set
+    #[must_use]
+    pub fn is_error(&self) -> bool {
+        self.arbitration_lost || self.sda_low_timeout || self.scl_low_timeout || self.abnormal
+    }
+}
+
+/// Decodes a raw I2CM14 controller interrupt status word
+#[must_use]
+pub fn decode_i2c_master_status(bits: u32) -> I2cMasterStatus {
+    I2cMasterStatus {
+        tx_ack: bits & AST_I2CM_TX_ACK != 0,
+        tx_nak: bits & AST_I2CM_TX_NAK != 0,
+        rx_done: bits & AST_I2CM_RX_DONE != 0,
+        arbitration_lost: bits & AST_I2CM_ARBIT_LOSS != 0,
+        normal_stop: bits & AST_I2CM_NORMAL_STOP != 0,
+        abnormal: bits & AST_I2CM_ABNORMAL != 0,
+        scl_low_timeout: bits & AST_I2CM_SCL_LOW_TO != 0,
+        smbus_alert: bits & AST_I2CM_SMBUS_ALT != 0,
+        bus_recovered: bits & AST_I2CM_BUS_RECOVER != 0,
+        sda_low_timeout: bits & AST_I2CM_SDA_DL_TO != 0,
+        bus_recover_failed: bits & AST_I2CM_BUS_RECOVER_FAIL != 0,
+        packet_done: bits & AST_I2CM_PKT_DONE != 0,
+        packet_error: bits & AST_I2CM_PKT_ERROR != 0,
+    }
+}
+
+/// Decoded target interrupt status (I2CS24)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct I2cStatusFlags {
+    pub tx_ack: bool,
+    pub tx_nak: bool,
+    pub rx_done: bool,
+    pub rx_done_nak: bool,
+    pub stop: bool,
+    pub address_match: bool,
+    pub inactive_timeout: bool,
+    pub packet_done: bool,
+    pub packet_error: bool,
+    /// A match on one of the three address slots was NAKed
+    pub address_nak: bool,
+    pub wait_rx_dma: bool,
+    pub wait_tx_dma: bool,
+    pub pending: bool,
+    /// Address slot (1-3) the last match was on, 0 if none is indicated
+    pub matched_address: u8,
+}
+
+/// Decodes a raw I2CS24 target interrupt status word
+#[must_use]
+pub fn decode_i2c_status(bits: u32) -> I2cStatusFlags {
+    I2cStatusFlags {
+        tx_ack: bits & AST_I2CS_TX_ACK != 0,
+        tx_nak: bits & AST_I2CS_TX_NAK != 0,
+        rx_done: bits & AST_I2CS_RX_DONE != 0,
+        rx_done_nak: bits & AST_I2CS_RX_DONE_NAK != 0,
+        stop: bits & AST_I2CS_STOP != 0,
+        address_match: bits & AST_I2CS_SLAVE_MATCH != 0,
+        inactive_timeout: bits & AST_I2CS_INACTIVE_TO != 0,
+        packet_done: bits & AST_I2CS_PKT_DONE != 0,
+        packet_error: bits & AST_I2CS_PKT_ERROR != 0,
+        address_nak: bits & (AST_I2CS_ADDR1_NAK | AST_I2CS_ADDR2_NAK | AST_I2CS_ADDR3_NAK) != 0,
+        wait_rx_dma: bits & AST_I2CS_WAIT_RX_DMA != 0,
+        wait_tx_dma: bits & AST_I2CS_WAIT_TX_DMA != 0,
+        pending: bits & AST_I2CS_SLAVE_PENDING != 0,
+        matched_address: u8::try_from(bits >> AST_I2CS_ADDR_INDICATE_SHIFT).unwrap_or(0),
+    }
+}
+
 //message flag
 //Write message to I2C bus.
 const I2C_MSG_WRITE: u8 = 0;
@@ -180,6 +281,13 @@ pub enum Error {
     Proto,
     Abnormal,
     ArbitrationLoss,
+    InvalidTiming,
+    /// `init` was given an inconsistent [`I2cConfig`]
+    Configuration(ConfigurationError),
+    /// The master cannot start a message in `state`
+    InvalidState {
+        state: MasterState,
+    },
 }
 
 use embedded_hal::i2c::ErrorKind;
@@ -195,19 +303,49 @@ impl embedded_hal::i2c::Error for Error {
             | Self::Proto
             | Self::Abnormal
             | Self::Busy
-            | Self::BusRecoveryFailed => ErrorKind::Other,
+            | Self::BusRecoveryFailed
+            | Self::InvalidTiming
+            | Self::Configuration(_)
+            | Self::InvalidState { .. } => ErrorKind::Other,
+        }
+    }
+}
+
+impl Error {
+    /// Stable short name, used as a structured log value
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Overrun => "overrun",
+            Self::NoAcknowledge(_) => "nack",
+            Self::Timeout => "timeout",
+            Self::BusRecoveryFailed => "bus_recovery_failed",
+            Self::Bus => "bus",
+            Self::Busy => "busy",
+            Self::Invalid => "invalid",
+            Self::Proto => "proto",
+            Self::Abnormal => "abnormal",
+            Self::ArbitrationLoss => "arbitration_loss",
+            Self::InvalidTiming => "invalid_timing",
+            Self::Configuration(_) => "configuration",
+            Self::InvalidState { .. } => "invalid_state",
         }
     }
 }
 
 const I2C_TOTAL: usize = 4;
+/// Master DMA buffers shared by all buses; a bus holds one only while a
+/// DMA message is in flight
+const I2C_DMA_CHUNKS: usize = 2;
+type I2cDmaChunk = DmaChunk<'static, ASPEED_I2C_DMA_SIZE, I2C_DMA_CHUNKS>;
+
+// Uncached, so the DMA paths need no `common::cache` maintenance
 #[link_section = ".ram_nc"]
-static mut MDMA_BUFFER: [DmaBuffer<ASPEED_I2C_DMA_SIZE>; I2C_TOTAL] = [
-    DmaBuffer::new(),
-    DmaBuffer::new(),
-    DmaBuffer::new(),
-    DmaBuffer::new(),
-];
+static I2C_DMA_REGION: DmaRegion<ASPEED_I2C_DMA_SIZE, I2C_DMA_CHUNKS> = DmaRegion::new();
+pub static I2C_DMA_POOL: DmaPool<ASPEED_I2C_DMA_SIZE, I2C_DMA_CHUNKS> =
+    unsafe { DmaPool::new(&I2C_DMA_REGION) };
+// Target mode has to be armed for reception at any time, so each bus keeps
+// its own buffer
 #[link_section = ".ram_nc"]
 static mut SDMA_BUFFER: [DmaBuffer<I2C_SLAVE_BUF_SIZE>; I2C_TOTAL] = [
     DmaBuffer::new(),
@@ -218,6 +356,74 @@ static mut SDMA_BUFFER: [DmaBuffer<I2C_SLAVE_BUF_SIZE>; I2C_TOTAL] = [
 
 static mut I2C_BUF: [[u8; I2C_SLAVE_BUF_SIZE]; 4] = [[0; 256]; I2C_TOTAL];
 
+/// Transaction started with [`Ast1060I2c::begin_transaction`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transfer<'b> {
+    Write(&'b [u8]),
+    /// Read of the given number of bytes
+    Read(usize),
+    /// Write, repeated start, then read of the given number of bytes
+    WriteRead(&'b [u8], usize),
+}
+
+/// Phase of the master side, tracked by the driver rather than read back
+/// from the controller.
+///
+/// Every error leads back to `Idle`. Arbitration loss and a NACK with stop
+/// do so directly, and a timed out blocking message resets the controller
+/// when it was still on the bus. Otherwise the driver stops in
+/// `NeedsRecovery`, which only [`Ast1060I2c::reset_state`],
+/// [`Ast1060I2c::abort_transaction`] or a new `init` leave. After
+/// `deinit` the master is `Disabled` until the next `init`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MasterState {
+    #[default]
+    Idle,
+    /// Start and address issued, waiting for the first packet
+    AddrPhase,
+    /// Address acknowledged, more write data to send
+    TxData,
+    /// Address acknowledged, more read data to receive
+    RxData,
+    /// Message over but the bus is still ours: a write without stop before
+    /// its repeated start, or a NACK the controller did not stop after
+    Stopping,
+    /// The controller may still hold a command or the bus is stuck
+    NeedsRecovery,
+    /// Turned off by `deinit`
+    Disabled,
+}
+
+impl MasterState {
+    /// Fails with `InvalidState` unless a new message may start: nothing is
+    /// in progress, or our own finished message holds the bus
+    pub const fn check_start(self) -> Result<(), Error> {
+        match self {
+            Self::Idle | Self::Stopping => Ok(()),
+            state => Err(Error::InvalidState { state }),
+        }
+    }
+
+    /// State a controller error leaves behind
+    const fn after_error(err: Error) -> Self {
+        match err {
+            // another master has the bus; ours is free to start again
+            Error::ArbitrationLoss => Self::Idle,
+            Error::NoAcknowledge(_) => Self::Stopping,
+            _ => Self::NeedsRecovery,
+        }
+    }
+
+    /// State after a data packet of a message in `phase`
+    const fn after_packet(phase: Self, complete: bool, stopped: bool) -> Self {
+        match (complete, stopped) {
+            (false, _) => phase,
+            (true, true) => Self::Idle,
+            (true, false) => Self::Stopping,
+        }
+    }
+}
+
 pub struct I2cData<'a, I2CT: I2CTarget> {
     pub msg: I2cMsg<'a>,
     pub addr: u8,
@@ -228,6 +434,20 @@ pub struct I2cData<'a, I2CT: I2CTarget> {
     pub slave_addr_last: u8,
     pub slave_target_addr: u8,
     pub slave_target: Option<&'a mut I2CT>,
+    pub host_notify_enabled: bool,
+    pub host_notify_active: bool,
+    pub host_notify_buf: [u8; 3],
+    pub host_notify_len: usize,
+    pub host_notify_queue: heapless::Deque<HostNotify, HOST_NOTIFY_QUEUE_LEN>,
+    // byte mode: bytes received into msg.buf; all modes: bytes sent for the
+    // current slave read
+    pub slave_rx_cnt: u32,
+    pub slave_tx_cnt: u32,
+    // a slave transaction started and no stop was seen yet, so the next
+    // start is a repeated start
+    pub slave_in_transaction: bool,
+    // byte count of the last completed slave read, until taken
+    pub slave_read_done: Option<u32>,
 }
 
 impl<'a, I2CT: I2CTarget> I2cData<'a, I2CT> {
@@ -249,6 +469,15 @@ impl<'a, I2CT: I2CTarget> I2cData<'a, I2CT> {
                 slave_addr_last: 0,
                 slave_target_addr: 0,
                 slave_target: None,
+                host_notify_enabled: false,
+                host_notify_active: false,
+                host_notify_buf: [0; 3],
+                host_notify_len: 0,
+                host_notify_queue: heapless::Deque::new(),
+                slave_rx_cnt: 0,
+                slave_tx_cnt: 0,
+                slave_in_transaction: false,
+                slave_read_done: None,
             }
         }
     }
@@ -266,14 +495,34 @@ pub struct Ast1060I2c<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> {
     pub multi_master: bool,
     pub smbus_alert: bool,
     pub bus_recover: bool,
-    pub mdma_buf: &'a mut DmaBuffer<ASPEED_I2C_DMA_SIZE>,
+    /// Master DMA buffer of the current message, from [`I2C_DMA_POOL`]
+    pub mdma_buf: Option<I2cDmaChunk>,
     pub sdma_buf: &'a mut DmaBuffer<I2C_SLAVE_BUF_SIZE>,
     pub i2c_data: I2cData<'a, I2CT>,
+    /// AC timing register value computed for each bus speed
+    timing_cache: [Option<u32>; 3],
+    /// Read length still to run after the write phase of a non-blocking
+    /// write-read
+    pending_read: Option<u32>,
+    /// See [`I2cConfig::byte_mode_max`]
+    byte_mode_max: usize,
+    /// Mode forced on master messages instead of the size-based choice
+    mode_override: Option<I2cXferMode>,
+    /// Mode chosen for the current master message
+    msg_mode: I2cXferMode,
+    in_flight: bool,
+    master_state: MasterState,
     _marker: PhantomData<I2C>,
     pub logger: L,
 }
 impl<I2C: Instance, I2CT: I2CTarget, L: Logger> Drop for Ast1060I2c<'_, I2C, I2CT, L> {
     fn drop(&mut self) {
+        self.shut_down();
+    }
+}
+
+impl<I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'_, I2C, I2CT, L> {
+    fn shut_down(&mut self) {
         // Disable i2c controller
         self.i2c.i2cc00().write(|w| unsafe { w.bits(0) });
         // Disable interrupt and clear interrupt status
@@ -302,20 +551,95 @@ macro_rules! i2c_error {
     };
 }
 
+const fn speed_index(speed: I2cSpeed) -> usize {
+    match speed {
+        I2cSpeed::Standard => 0,
+        I2cSpeed::Fast => 1,
+        I2cSpeed::FastPlus => 2,
+    }
+}
+
+/// I2C spec minimums in ns: tLOW, tHIGH (also tHD;STA and tSU;STA), tSU;DAT
+const fn spec_min_timing_ns(speed: I2cSpeed) -> (u64, u64, u64) {
+    match speed {
+        I2cSpeed::Standard => (4700, 4000, 250),
+        I2cSpeed::Fast => (1300, 600, 100),
+        I2cSpeed::FastPlus => (500, 260, 50),
+    }
+}
+
+fn check_clock_source(speed: I2cSpeed, clk_src: u32) -> Result<(), Error> {
+    if speed == I2cSpeed::FastPlus && clk_src < FAST_PLUS_MIN_CLK_SRC {
+        return Err(Error::InvalidTiming);
+    }
+    Ok(())
+}
+
+/// Largest master message `mode` can stage: a DMA chunk, or the per-bus
+/// message buffer of byte and buffer mode
+const fn max_message_len(mode: I2cXferMode) -> usize {
+    match mode {
+        I2cXferMode::DmaMode => ASPEED_I2C_DMA_SIZE,
+        I2cXferMode::ByteMode | I2cXferMode::BuffMode => I2C_SLAVE_BUF_SIZE,
+    }
+}
+
+/// Checks messages of `lens` bytes against the mode each would use
+fn check_message_lens(
+    lens: impl IntoIterator<Item = usize>,
+    mode: impl Fn(usize) -> I2cXferMode,
+) -> Result<(), Error> {
+    if lens.into_iter().any(|len| len > max_message_len(mode(len))) {
+        return Err(Error::Invalid);
+    }
+    Ok(())
+}
+
+// Each timing field counts base clock cycles minus one. The controller
+// generates start hold/setup from tCKHigh, and data changes sda_hold + 1
+// cycles into the low phase, which leaves the rest of it as data setup.
+fn check_spec_timing(
+    speed: I2cSpeed,
+    base_clk: u32,
+    scl_low: u8,
+    scl_high: u8,
+    sda_hold: u8,
+) -> Result<(), Error> {
+    if base_clk == 0 {
+        return Err(Error::InvalidTiming);
+    }
+    let cycles_ps = |cycles: u8| u64::from(cycles) * 1_000_000_000_000 / u64::from(base_clk);
+    let t_low = cycles_ps(scl_low + 1);
+    let t_high = cycles_ps(scl_high + 1);
+    let t_su_dat = t_low.saturating_sub(cycles_ps(sda_hold + 1));
+    let (min_low, min_high, min_su_dat) = spec_min_timing_ns(speed);
+    if t_low < min_low * 1000
+        || t_high < min_high * 1000
+        || t_su_dat < min_su_dat * 1000
+        || t_low + t_high < 1_000_000_000_000 / u64::from(speed as u32)
+    {
+        return Err(Error::InvalidTiming);
+    }
+    Ok(())
+}
+
 impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c<'_, I2C, I2CT, L> {
     type Error = Error;
 
-    fn init(&mut self, config: &mut I2cConfig) {
+    fn init(&mut self, config: &mut I2cConfig) -> Result<(), Error> {
         i2c_debug!(self.logger, "i2c init");
+        config.validate().map_err(Error::Configuration)?;
         i2c_debug!(
             self.logger,
-            "mdma_buf {:p}, sdma_buf {:p}",
-            self.mdma_buf.as_ptr(),
-            self.sdma_buf.as_ptr()
+            "sdma_buf {:p}, free dma chunks {}",
+            self.sdma_buf.as_ptr(),
+            I2C_DMA_POOL.available()
         );
         self.xfer_mode = config.xfer_mode;
+        self.byte_mode_max = config.byte_mode_max;
         self.multi_master = config.multi_master;
         self.smbus_alert = config.smbus_alert;
+        self.timing_cache = [None; 3];
         let scu = unsafe { &*Scu::ptr() };
         // global init
         if I2CGLOBAL_INIT
@@ -359,6 +683,7 @@ impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c
 
         // i2c reset
         self.i2c.i2cc00().write(|w| unsafe { w.bits(0) });
+        self.master_state = MasterState::Idle;
         if !self.multi_master {
             self.i2c
                 .i2cc00()
@@ -372,7 +697,7 @@ impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c
         });
 
         // set AC timing
-        self.configure_timing(config);
+        self.configure_timing(config)?;
         // clear interrupts
         self.i2c.i2cm14().write(|w| unsafe { w.bits(0xffff_ffff) });
         // set interrupt
@@ -397,24 +722,18 @@ impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c
             i2c_debug!(self.logger, "i2c target enabled");
             // clear slave interrupts
             self.i2c.i2cs24().write(|w| unsafe { w.bits(0xffff_ffff) });
-            if self.xfer_mode == I2cXferMode::ByteMode {
-                self.i2c.i2cs20().write(|w| unsafe { w.bits(0xffff) });
-            } else {
-                self.i2c.i2cs20().write(|w| {
-                    w.enbl_slave_mode_inactive_timeout_int()
-                        .set_bit()
-                        .enbl_pkt_cmd_done_int()
-                        .set_bit()
-                });
-            }
+            self.set_slave_interrupts_for_mode();
         }
+        Ok(())
     }
     #[allow(clippy::too_many_lines)]
-    fn configure_timing(&mut self, config: &mut I2cConfig) {
+    fn configure_timing(&mut self, config: &mut I2cConfig) -> Result<(), Error> {
         let scu = unsafe { &*Scu::ptr() };
         config.timing_config.clk_src =
             HPLL_FREQ / ((u32::from(scu.scu310().read().apbbus_pclkdivider_sel().bits()) + 1) * 2);
 
+        check_clock_source(config.speed, config.timing_config.clk_src)?;
+
         let p = unsafe { &*I2cglobal::ptr() };
         let mut div: u32;
         let mut divider_ratio: u32;
@@ -484,12 +803,16 @@ impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c
             } else if (config.timing_config.manual_scl_low | config.timing_config.manual_scl_high)
                 != 0
             {
+                // the other half is what is left of the period, if anything
+                let ratio = u8::try_from(divider_ratio & 0xff).unwrap();
                 if config.timing_config.manual_scl_low != 0 {
                     scl_low = config.timing_config.manual_scl_low;
-                    scl_high = u8::try_from(divider_ratio & 0xff).unwrap() - scl_low - 2;
+                    scl_high = ratio.checked_sub(scl_low + 2).ok_or(Error::InvalidTiming)?;
                 } else {
                     scl_high = config.timing_config.manual_scl_high;
-                    scl_low = u8::try_from(divider_ratio & 0xff).unwrap() - scl_high - 2;
+                    scl_low = ratio
+                        .checked_sub(scl_high + 2)
+                        .ok_or(Error::InvalidTiming)?;
                 }
             } else {
                 scl_low = u8::try_from((divider_ratio * 9 / 16 - 1) & 0xff).unwrap();
@@ -498,6 +821,21 @@ impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c
             scl_low = min(scl_low, 0xf);
             scl_high = min(scl_high, 0xf);
 
+            let tbase_clk = match div {
+                0 => base_clk,
+                1 => base_clk1,
+                2 => base_clk2,
+                3 => base_clk3,
+                _ => base_clk4 >> (div - 4),
+            };
+            // the hold register keeps its reset value (0) when not overridden
+            let sda_hold = if config.timing_config.manual_sda_hold < 4 {
+                config.timing_config.manual_sda_hold
+            } else {
+                0
+            };
+            check_spec_timing(config.speed, tbase_clk, scl_low, scl_high, sda_hold)?;
+
             /*Divisor : Base Clock : tCKHighMin : tCK High : tCK Low*/
             self.i2c.i2cc04().write(|w| unsafe {
                 w.base_clk_divisor_tbase_clk()
@@ -530,7 +868,67 @@ impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c
                         .bits(config.timing_config.manual_sda_hold)
                 });
             }
+            self.timing_cache[speed_index(config.speed)] = Some(self.i2c.i2cc04().read().bits());
+        }
+        self.apply_pad_drive(config);
+        Ok(())
+    }
+    fn clock_changed(&mut self, config: &mut I2cConfig) -> Result<(), Error> {
+        self.timing_cache = [None; 3];
+        self.configure_timing(config)
+    }
+    fn set_speed(&mut self, config: &mut I2cConfig, speed: I2cSpeed) -> Result<(), Error> {
+        if self.i2c.i2cc08().read().bus_busy_status().bit() {
+            return Err(Error::Busy);
+        }
+        if let Some(ac_timing) = self.timing_cache[speed_index(speed)] {
+            self.i2c.i2cc04().write(|w| unsafe { w.bits(ac_timing) });
+            config.speed = speed;
+            self.apply_pad_drive(config);
+            return Ok(());
+        }
+        let previous = config.speed;
+        config.speed = speed;
+        if let Err(e) = self.configure_timing(config) {
+            config.speed = previous;
+            return Err(e);
+        }
+        Ok(())
+    }
+    fn set_xfer_mode(&mut self, config: &mut I2cConfig, mode: I2cXferMode) -> Result<(), Error> {
+        // the target side programs its receive buffer for the mode when it
+        // is registered
+        if self.in_flight
+            || self.i2c_data.slave_attached
+            || self.i2c.i2cc08().read().bus_busy_status().bit()
+        {
+            return Err(Error::Busy);
+        }
+        self.master_state.check_start()?;
+        self.mdma_buf = None;
+        self.xfer_mode = mode;
+        config.xfer_mode = mode;
+        if cfg!(feature = "i2c_target") {
+            self.set_slave_interrupts_for_mode();
+        }
+        Ok(())
+    }
+    fn override_xfer_mode(&mut self, mode: Option<I2cXferMode>) -> Result<(), Error> {
+        if self.in_flight {
+            return Err(Error::Busy);
+        }
+        self.mode_override = mode;
+        Ok(())
+    }
+    fn deinit(&mut self) -> Result<(), Error> {
+        if self.in_flight || self.i2c_data.slave_attached {
+            return Err(Error::Busy);
         }
+        self.pending_read = None;
+        self.mdma_buf = None;
+        self.shut_down();
+        self.master_state = MasterState::Disabled;
+        Ok(())
     }
     fn enable_interrupts(&mut self, mask: u32) {
         self.i2c.i2cm10().write(|w| unsafe { w.bits(mask) });
@@ -558,11 +956,11 @@ impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c
     }
 
     fn write(&mut self, addr: SevenBitAddress, bytes: &[u8]) -> Result<(), Error> {
-        self.prepare_write(addr, bytes, true);
+        self.prepare_write(addr, bytes, true)?;
         self.i2c_aspeed_transfer()
     }
     fn read(&mut self, addr: SevenBitAddress, buffer: &mut [u8]) -> Result<(), Error> {
-        self.prepare_read(addr, u32::try_from(buffer.len()).unwrap());
+        self.prepare_read(addr, u32::try_from(buffer.len()).unwrap())?;
         self.i2c_aspeed_transfer()?;
         self.read_processed(buffer);
         Ok(())
@@ -573,11 +971,11 @@ impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c
         bytes: &[u8],
         buffer: &mut [u8],
     ) -> Result<(), Error> {
-        self.prepare_write(addr, bytes, false);
+        self.prepare_write(addr, bytes, false)?;
 
         self.i2c_aspeed_transfer()?;
         //read
-        self.prepare_read(addr, u32::try_from(buffer.len()).unwrap());
+        self.prepare_read(addr, u32::try_from(buffer.len()).unwrap())?;
         self.i2c_aspeed_transfer()?;
         self.read_processed(buffer);
         Ok(())
@@ -587,11 +985,23 @@ impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c
         addr: SevenBitAddress,
         ops_slice: &mut [Operation<'_>],
     ) -> Result<(), Error> {
+        // A message that does not fit would fail with the bus held by the
+        // earlier ones, so the whole transaction is refused up front
+        let lens = ops_slice.iter().map(|op| match op {
+            Operation::Read(rb) => rb.len(),
+            Operation::Write(wb) => wb.len(),
+        });
+        check_message_lens(lens, |len| self.message_mode(len))?;
         transaction_impl!(self, addr, ops_slice, Operation);
         // Fallthrough is success
         Ok(())
     }
     fn recover_bus(&mut self) -> Result<(), Error> {
+        if self.master_state == MasterState::Disabled {
+            return Err(Error::InvalidState {
+                state: MasterState::Disabled,
+            });
+        }
         //disable master and slave functionality to put it in idle state
         self.i2c
             .i2cc00()
@@ -609,7 +1019,9 @@ impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c
             self.i2c
                 .i2cm18()
                 .modify(|_, w| w.enbl_bus_recover_cmd().bit(true));
-            self.i2c_wait_completion()
+            self.i2c_wait_completion(None)?;
+            self.master_state = MasterState::Idle;
+            Ok(())
         } else {
             //can't recover this situation
             Err(Error::Proto)
@@ -617,12 +1029,34 @@ impl<I2C: Instance, I2CT: I2CTarget, L: Logger> HardwareInterface for Ast1060I2c
     }
 }
 
+// The register references only reach this bus's own registers, which the
+// PAC's `Send` peripheral handles already let any context use
+unsafe impl<I2C: Instance + Send, I2CT: I2CTarget + Send, L: Logger + Send> Send
+    for Ast1060I2c<'_, I2C, I2CT, L>
+{
+}
+
+#[cfg(feature = "rt")]
+impl<I2C, I2CT, L> crate::interrupts::IrqHandler for Ast1060I2c<'static, I2C, I2CT, L>
+where
+    I2C: Instance + Send,
+    I2CT: I2CTarget + Send + 'static,
+    L: Logger + Send + 'static,
+{
+    fn irq_number(&self) -> ast1060_pac::Interrupt {
+        I2C::IRQ
+    }
+
+    fn on_irq(&mut self) {
+        self.handle_interrupt();
+    }
+}
+
 impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L> {
     pub fn new(logger: L) -> Self {
         let i2c = unsafe { &*I2C::ptr() };
         let i2c_buff = unsafe { &*I2C::buff_ptr() };
         let index: usize = I2C::BUS_NUM as usize;
-        let mdma_buf: &'a mut DmaBuffer<ASPEED_I2C_DMA_SIZE> = unsafe { &mut MDMA_BUFFER[index] };
         let sdma_buf: &'a mut DmaBuffer<I2C_SLAVE_BUF_SIZE> = unsafe { &mut SDMA_BUFFER[index] };
         let i2c_data = I2cData::new(index);
         Self {
@@ -632,13 +1066,48 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
             multi_master: false,
             smbus_alert: false,
             bus_recover: false,
-            mdma_buf,
+            mdma_buf: None,
             sdma_buf,
             i2c_data,
+            timing_cache: [None; 3],
+            pending_read: None,
+            byte_mode_max: 0,
+            mode_override: None,
+            msg_mode: I2cXferMode::ByteMode,
+            in_flight: false,
+            master_state: MasterState::Idle,
             _marker: PhantomData,
             logger,
         }
     }
+    /// Byte mode takes every target interrupt, packet modes only the
+    /// packet and timeout ones
+    fn set_slave_interrupts_for_mode(&mut self) {
+        if self.xfer_mode == I2cXferMode::ByteMode {
+            self.i2c.i2cs20().write(|w| unsafe { w.bits(0xffff) });
+        } else {
+            self.i2c.i2cs20().write(|w| {
+                w.enbl_slave_mode_inactive_timeout_int()
+                    .set_bit()
+                    .enbl_pkt_cmd_done_int()
+                    .set_bit()
+            });
+        }
+    }
+    /// Switch the bus pads to the drive settings for the configured speed
+    fn apply_pad_drive(&mut self, config: &I2cConfig) {
+        let Some(drive) = config.pad_drive else {
+            if config.speed == I2cSpeed::FastPlus {
+                i2c_debug!(self.logger, "fast-mode plus without pad drive settings");
+            }
+            return;
+        };
+        if config.speed == I2cSpeed::FastPlus {
+            Pinctrl::apply_drive_group(drive.fast_plus);
+        } else {
+            Pinctrl::apply_drive_group(drive.normal);
+        }
+    }
     pub fn dump_regs(&mut self) {
         let i2cg = unsafe { &*I2cglobal::ptr() };
         i2c_debug!(self.logger, "******* i2c registers ******");
@@ -677,13 +1146,14 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
     }
 
     fn aspeed_i2c_is_irq_error(irq_status: u32) -> Result<(), Error> {
-        if irq_status & AST_I2CM_ARBIT_LOSS > 0 {
+        let status = decode_i2c_master_status(irq_status);
+        if status.arbitration_lost {
             return Err(Error::ArbitrationLoss);
         }
-        if irq_status & (AST_I2CM_SDA_DL_TO | AST_I2CM_SCL_LOW_TO) > 0 {
+        if status.sda_low_timeout || status.scl_low_timeout {
             return Err(Error::Busy);
         }
-        if irq_status & (AST_I2CM_ABNORMAL) > 0 {
+        if status.abnormal {
             return Err(Error::Abnormal);
         }
         Ok(())
@@ -695,7 +1165,13 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         let mut cmd = AST_I2CM_PKT_EN;
 
         let msg_len = self.i2c_data.msg.length;
-        let xfer_len: u16 = match self.xfer_mode {
+        if msg_len == 0 {
+            // quick write: the address was ACKed and there is no data phase;
+            // the byte counts still hold the previous transfer's values
+            self.i2c_data.completion = true;
+            return;
+        }
+        let xfer_len: u16 = match self.master_mode() {
             I2cXferMode::DmaMode => self.i2c.i2cm48().read().dmatx_actual_len_byte().bits(),
             I2cXferMode::BuffMode => {
                 u16::from(self.i2c.i2cc0c().read().tx_data_byte_count().bits())
@@ -763,12 +1239,15 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         let xfer_len: u16;
         let msg_len = self.i2c_data.msg.length;
         i2c_debug!(self.logger, "do_i2cm_rx");
-        match self.xfer_mode {
+        if msg_len == 0 {
+            // quick read: the byte clocked in to end the read is dropped
+            self.i2c_data.completion = true;
+            return;
+        }
+        match self.master_mode() {
             I2cXferMode::DmaMode => {
                 xfer_len = self.i2c.i2cm48().read().dmarx_actual_len_byte().bits();
-                i2c_debug!(self.logger, "dma data: {:#x}", unsafe {
-                    *self.mdma_buf.as_ptr()
-                });
+                i2c_debug!(self.logger, "dma data: {:#x}", self.dma_buf()[0]);
             }
             I2cXferMode::BuffMode => {
                 xfer_len = u16::from(
@@ -808,12 +1287,34 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         if sts == AST_I2CM_PKT_ERROR | AST_I2CM_TX_NAK
             || sts == AST_I2CM_PKT_ERROR | AST_I2CM_TX_NAK | AST_I2CM_NORMAL_STOP
         {
-            i2c_debug!(self.logger, "M: PKT ERR | TX NAK (STOP)");
+            self.logger.log_kv(
+                LogLevel::Debug,
+                "i2c nack",
+                &[
+                    ("bus", LogValue::U32(I2C::BUS_NUM.into())),
+                    ("addr", LogValue::U32(self.i2c_data.addr.into())),
+                    ("acked", LogValue::U32(self.i2c_data.master_xfer_cnt)),
+                ],
+            );
             self.i2c_data.completion = true;
-            return Err(Error::NoAcknowledge(NoAcknowledgeSource::Unknown));
+            // master_xfer_cnt only advances on ACK, so it is the accepted
+            // length. Zero cannot tell the address from the first data byte.
+            let source = if self.i2c_data.master_xfer_cnt == 0 {
+                NoAcknowledgeSource::Unknown
+            } else {
+                NoAcknowledgeSource::Data
+            };
+            let err = Error::NoAcknowledge(source);
+            self.master_state = if sts & AST_I2CM_NORMAL_STOP == 0 {
+                MasterState::after_error(err)
+            } else {
+                MasterState::Idle
+            };
+            return Err(err);
         } else if sts == AST_I2CM_NORMAL_STOP {
             i2c_debug!(self.logger, "M: STOP");
             self.i2c_data.completion = true;
+            self.master_state = MasterState::Idle;
         } else if sts == AST_I2CM_TX_ACK || sts == AST_I2CM_TX_ACK | AST_I2CM_NORMAL_STOP {
             i2c_debug!(self.logger, "M: TX_ACK (STOP)");
             //slave mode
@@ -831,9 +1332,19 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                 }
             }
             self.do_i2cm_tx();
+            self.master_state = MasterState::after_packet(
+                MasterState::TxData,
+                self.i2c_data.completion,
+                sts & AST_I2CM_NORMAL_STOP != 0,
+            );
         } else if sts == AST_I2CM_RX_DONE || sts == AST_I2CM_RX_DONE | AST_I2CM_NORMAL_STOP {
             i2c_debug!(self.logger, "M: RX_DONE (STOP)");
             self.do_i2cm_rx();
+            self.master_state = MasterState::after_packet(
+                MasterState::RxData,
+                self.i2c_data.completion,
+                sts & AST_I2CM_NORMAL_STOP != 0,
+            );
         } else {
             i2c_error!(
                 self.logger,
@@ -855,6 +1366,7 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
             if self.bus_recover {
                 self.bus_recover = false;
             }
+            self.master_state = MasterState::NeedsRecovery;
             return Err(Error::BusRecoveryFailed);
         }
         if AST_I2CM_BUS_RECOVER == AST_I2CM_BUS_RECOVER & sts {
@@ -875,7 +1387,17 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                 .i2cm14()
                 .modify(|_, w| w.wcsmbus_dev_alert_intsts().bit(true));
         }
-        Self::aspeed_i2c_is_irq_error(sts).inspect_err(|_e| {
+        Self::aspeed_i2c_is_irq_error(sts).inspect_err(|e| {
+            self.logger.log_kv(
+                LogLevel::Error,
+                "i2c master error",
+                &[
+                    ("bus", LogValue::U32(I2C::BUS_NUM.into())),
+                    ("addr", LogValue::U32(self.i2c_data.addr.into())),
+                    ("sts", LogValue::U32(sts)),
+                    ("err", LogValue::Str(e.as_str())),
+                ],
+            );
             self.i2c.i2cm14().modify(|_, w| {
                 w.wcpkt_cmd_done_intsts()
                     .bit(true)
@@ -883,6 +1405,7 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                     .bit(true)
             });
             self.i2c_data.completion = true;
+            self.master_state = MasterState::after_error(*e);
         })?;
         if AST_I2CM_PKT_DONE == AST_I2CM_PKT_DONE & sts {
             sts &= !AST_I2CM_PKT_DONE;
@@ -898,20 +1421,177 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         Ok(())
     }
 
-    fn i2c_wait_completion(&mut self) -> Result<(), Error> {
+    /// Master write bounded by `deadline`
+    pub fn write_within(
+        &mut self,
+        addr: SevenBitAddress,
+        bytes: &[u8],
+        deadline: &Deadline,
+    ) -> Result<(), Error> {
+        self.prepare_write(addr, bytes, true)?;
+        self.i2c_aspeed_transfer_within(Some(deadline))
+    }
+
+    /// Master read bounded by `deadline`
+    pub fn read_within(
+        &mut self,
+        addr: SevenBitAddress,
+        buffer: &mut [u8],
+        deadline: &Deadline,
+    ) -> Result<(), Error> {
+        self.prepare_read(addr, u32::try_from(buffer.len()).unwrap())?;
+        self.i2c_aspeed_transfer_within(Some(deadline))?;
+        self.read_processed(buffer);
+        Ok(())
+    }
+
+    /// Master write-read bounded by `deadline`; both phases share it
+    pub fn write_read_within(
+        &mut self,
+        addr: SevenBitAddress,
+        bytes: &[u8],
+        buffer: &mut [u8],
+        deadline: &Deadline,
+    ) -> Result<(), Error> {
+        self.prepare_write(addr, bytes, false)?;
+        self.i2c_aspeed_transfer_within(Some(deadline))?;
+        self.read_within(addr, buffer, deadline)
+    }
+
+    /// Reads `buf.len()` consecutive registers starting at `start_reg`.
+    ///
+    /// The register pointer write and the read form one transaction: start,
+    /// pointer, repeated start, data, stop. Devices that auto-increment
+    /// their pointer therefore return the block from `start_reg`. Unlike
+    /// `write_read`, the read phase skips the idle-bus check, so a single
+    /// master never recovers the bus between the phases, and a NACK of the
+    /// pointer write still ends with a stop. A NACK is reported as
+    /// `NoAcknowledge`, with the data source once the target accepted a
+    /// byte and an unknown one before; an empty `buf` is `Invalid`.
+    pub fn read_register_block(
+        &mut self,
+        addr: SevenBitAddress,
+        start_reg: u8,
+        buf: &mut [u8],
+    ) -> Result<(), Error> {
+        if buf.is_empty() {
+            return Err(Error::Invalid);
+        }
+        self.prepare_write(addr, &[start_reg], false)?;
+        if let Err(e) = self.i2c_aspeed_transfer() {
+            self.release_bus();
+            return Err(e);
+        }
+        self.prepare_read(addr, u32::try_from(buf.len()).unwrap())?;
+        self.i2c_aspeed_submit(None, true)?;
+        self.read_processed(buf);
+        Ok(())
+    }
+
+    /// Sends a stop if a failed message left the bus held
+    fn release_bus(&mut self) {
+        let mut delay = DummyDelay {};
+        if !self.i2c.i2cc08().read().bus_busy_status().bit() {
+            return;
+        }
+        self.i2c
+            .i2cm18()
+            .write(|w| unsafe { w.bits(AST_I2CM_STOP_CMD) });
+        for _ in 0..1000 {
+            if !self.i2c.i2cc08().read().bus_busy_status().bit() {
+                break;
+            }
+            delay.delay_ns(1000);
+        }
+        self.i2c
+            .i2cm14()
+            .write(|w| unsafe { w.bits(AST_I2CM_NORMAL_STOP) });
+        if self.master_state == MasterState::Stopping {
+            self.master_state = MasterState::Idle;
+        }
+    }
+
+    /// Number of bytes transferred by the last master message.
+    ///
+    /// After a `NoAcknowledge` error this is the number of data bytes the
+    /// target accepted before the NACK.
+    #[must_use]
+    pub fn transferred(&self) -> u32 {
+        self.i2c_data.master_xfer_cnt
+    }
+
+    fn i2c_wait_completion(&mut self, deadline: Option<&Deadline>) -> Result<(), Error> {
         let mut delay = DummyDelay {};
         let mut timeout = 1_000_000;
         while timeout > 0 && !self.i2c_data.completion {
-            self.aspeed_i2c_master_irq()?;
+            if let Err(e) = self.aspeed_i2c_master_irq() {
+                self.mdma_buf = None;
+                return Err(e);
+            }
+            if deadline.is_some_and(Deadline::is_expired) {
+                break;
+            }
             delay.delay_ns(100_000);
             timeout -= 1;
         }
+        // on a timeout the engine may still be writing to the chunk, so it
+        // is kept until the next message
         if !self.i2c_data.completion {
             return Err(Error::Timeout);
         }
+        if self.i2c_data.msg.flags & I2C_MSG_READ == 0 {
+            self.mdma_buf = None;
+        }
         Ok(())
     }
-    fn prepare_read(&mut self, addr: u8, len: u32) {
+
+    /// Mode of the current master message: DMA mode without a chunk from
+    /// the pool falls back to the buffer pool
+    fn master_mode(&self) -> I2cXferMode {
+        match self.msg_mode {
+            I2cXferMode::DmaMode if self.mdma_buf.is_none() => I2cXferMode::BuffMode,
+            mode => mode,
+        }
+    }
+
+    /// Mode for a master message of `len` bytes: the override if one is
+    /// set, byte mode for short messages, the controller mode otherwise
+    fn message_mode(&self, len: usize) -> I2cXferMode {
+        match self.mode_override {
+            Some(mode) => mode,
+            None if (1..=self.byte_mode_max).contains(&len) => I2cXferMode::ByteMode,
+            None => self.xfer_mode,
+        }
+    }
+
+    /// Picks the mode of a message of `len` bytes and, in DMA mode, takes
+    /// a DMA chunk for it. Once the pool is exhausted, messages that fit
+    /// `msg.buf` go through the buffer pool and longer ones fail with
+    /// `Busy`.
+    fn claim_dma(&mut self, len: usize) -> Result<(), Error> {
+        self.msg_mode = self.message_mode(len);
+        if self.msg_mode != I2cXferMode::DmaMode {
+            // a chunk kept after a timeout is not used by this message
+            self.mdma_buf = None;
+            return Ok(());
+        }
+        if self.mdma_buf.is_some() {
+            return Ok(());
+        }
+        match I2C_DMA_POOL.alloc_chunk() {
+            Ok(chunk) => self.mdma_buf = Some(chunk),
+            Err(_) if len <= self.i2c_data.msg.buf.len() => {}
+            Err(_) => return Err(Error::Busy),
+        }
+        Ok(())
+    }
+
+    fn dma_buf(&mut self) -> &mut DmaBuffer<ASPEED_I2C_DMA_SIZE> {
+        self.mdma_buf.as_mut().unwrap()
+    }
+    fn prepare_read(&mut self, addr: u8, len: u32) -> Result<(), Error> {
+        self.master_state.check_start()?;
+        self.claim_dma(len as usize)?;
         //initialize xfer data
         self.i2c_data.addr = addr;
         //read
@@ -920,14 +1600,13 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         self.i2c_data.stop = true;
         self.i2c_data.completion = false;
         self.i2c_data.master_xfer_cnt = 0;
+        Ok(())
     }
-    //copy data
+    //copy data, handing the DMA chunk back to the pool
     pub fn read_processed(&mut self, buffer: &mut [u8]) {
         i2c_debug!(self.logger, "read_processed");
-        if self.xfer_mode == I2cXferMode::DmaMode {
-            let src = self
-                .mdma_buf
-                .as_mut_slice(0, self.i2c_data.msg.length as usize);
+        if let Some(chunk) = self.mdma_buf.take() {
+            let src = chunk.as_slice(0, self.i2c_data.msg.length as usize);
             i2c_debug!(self.logger, "{:?}", src);
             buffer.copy_from_slice(src);
         } else {
@@ -936,7 +1615,9 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
             buffer.copy_from_slice(src);
         }
     }
-    pub fn prepare_write(&mut self, addr: u8, bytes: &[u8], stop: bool) {
+    pub fn prepare_write(&mut self, addr: u8, bytes: &[u8], stop: bool) -> Result<(), Error> {
+        self.master_state.check_start()?;
+        self.claim_dma(bytes.len())?;
         //initialize xfer data
         self.i2c_data.addr = addr;
         self.i2c_data.msg.flags = I2C_MSG_WRITE;
@@ -944,16 +1625,21 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         self.i2c_data.stop = stop;
         self.i2c_data.completion = false;
         self.i2c_data.master_xfer_cnt = 0;
-        if self.xfer_mode == I2cXferMode::DmaMode {
-            let dest = self.mdma_buf.as_mut_slice(0, bytes.len());
+        if let Some(chunk) = self.mdma_buf.as_mut() {
+            let dest = chunk.as_mut_slice(0, bytes.len());
             dest.copy_from_slice(bytes);
         } else {
             //write
             let dest = &mut self.i2c_data.msg.buf[..bytes.len()];
             dest.copy_from_slice(bytes);
         }
+        Ok(())
     }
 
+    /// Triggers the next read chunk.
+    ///
+    /// A zero-length (quick) read clocks in one byte and NACKs it, so the
+    /// target releases SDA before the stop; the byte is discarded.
     pub fn aspeed_i2c_read(&mut self, ctrl_cmd: u32) {
         let xfer_len: u16;
         let len_left: u32;
@@ -961,7 +1647,7 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         let msg_len = self.i2c_data.msg.length;
         i2c_debug!(self.logger, "aspeed_i2c_read");
         cmd |= AST_I2CM_RX_CMD;
-        match self.xfer_mode {
+        match self.master_mode() {
             I2cXferMode::DmaMode => {
                 len_left = msg_len - self.i2c_data.master_xfer_cnt;
                 if len_left > u32::try_from(ASPEED_I2C_DMA_SIZE).unwrap() {
@@ -973,10 +1659,8 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                 }
                 if xfer_len > 0 {
                     i2c_debug!(self.logger, "rx_len {:#x}", xfer_len);
-                    unsafe {
-                        *self.mdma_buf.as_mut_ptr() = 0;
-                    }
-                    let phy_addr = self.mdma_buf.as_mut_ptr() as u32;
+                    self.dma_buf()[0] = 0;
+                    let phy_addr = self.dma_buf().as_mut_ptr() as u32;
                     cmd |= AST_I2CM_RX_DMA_EN;
                     self.i2c.i2cm1c().modify(|_, w| unsafe {
                         w.dmarx_buf_len_byte()
@@ -984,9 +1668,7 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                             .dmarx_buf_len_wr_enbl_for_cur_write_cmd()
                             .set_bit()
                     });
-                    i2c_debug!(self.logger, "before rx data: {:#x}", unsafe {
-                        *self.mdma_buf.as_ptr()
-                    });
+                    i2c_debug!(self.logger, "before rx data: {:#x}", self.dma_buf()[0]);
                     self.i2c
                         .i2cm34()
                         .modify(|_, w| unsafe { w.sdramdmabuffer_base_addr1().bits(phy_addr) });
@@ -1011,8 +1693,8 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                 }
             }
             I2cXferMode::ByteMode => {
-                //byte mode
-                if msg_len == self.i2c_data.master_xfer_cnt + 1 {
+                //byte mode; a quick read is a single NACKed byte as well
+                if msg_len <= self.i2c_data.master_xfer_cnt + 1 {
                     //last transaction
                     cmd |= AST_I2CM_RX_CMD_LAST | AST_I2CM_STOP_CMD;
                 }
@@ -1023,6 +1705,11 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         self.i2c.i2cm18().write(|w| unsafe { w.bits(cmd) });
     }
 
+    /// Triggers the next write chunk.
+    ///
+    /// The TX command is only issued when there are data bytes, so a
+    /// zero-length (quick) write is just start, address and stop and the
+    /// length registers are never programmed with zero.
     pub fn aspeed_i2c_write(&mut self, ctrl_cmd: u32) {
         let xfer_len: u16;
         let len_left: u32;
@@ -1030,8 +1717,7 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         let msg_len = self.i2c_data.msg.length;
 
         i2c_debug!(self.logger, "aspeed_i2c_write");
-        cmd |= AST_I2CM_TX_CMD;
-        match self.xfer_mode {
+        match self.master_mode() {
             I2cXferMode::DmaMode => {
                 //dma mode
                 len_left = msg_len - self.i2c_data.master_xfer_cnt;
@@ -1045,12 +1731,12 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                     }
                 }
                 if xfer_len > 0 {
-                    let phy_addr = self.mdma_buf.as_mut_ptr() as u32;
+                    let phy_addr = self.dma_buf().as_mut_ptr() as u32;
                     i2c_debug!(
                         self.logger,
                         "write len {:#x}, data {:#x}",
                         xfer_len,
-                        unsafe { *self.mdma_buf.as_ptr() }
+                        self.dma_buf()[0]
                     );
                     cmd |= AST_I2CM_TX_DMA_EN | AST_I2CM_TX_CMD;
 
@@ -1085,7 +1771,14 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                     });
                 }
             }
+            I2cXferMode::ByteMode if msg_len == 0 => {
+                // quick write: start, address and (optionally) stop only
+                if self.i2c_data.stop {
+                    cmd |= AST_I2CM_STOP_CMD;
+                }
+            }
             I2cXferMode::ByteMode => {
+                cmd |= AST_I2CM_TX_CMD;
                 if self.i2c_data.master_xfer_cnt + 1 == msg_len && self.i2c_data.stop {
                     cmd |= AST_I2CM_STOP_CMD;
                 }
@@ -1106,67 +1799,217 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
     }
 
     pub fn i2c_aspeed_transfer(&mut self) -> Result<(), Error> {
-        let mut cmd: u32;
+        self.i2c_aspeed_transfer_within(None)
+    }
+
+    /// Like [`Self::i2c_aspeed_transfer`], but gives up with
+    /// `Error::Timeout` once `deadline` expires
+    pub fn i2c_aspeed_transfer_within(&mut self, deadline: Option<&Deadline>) -> Result<(), Error> {
+        self.i2c_aspeed_submit(deadline, false)
+    }
+
+    /// Starts the prepared message and waits for it. With `repeated_start`
+    /// the bus is held by our own unfinished message, so it is not checked
+    /// for idle (which could recover it and put a stop on the wire).
+    fn i2c_aspeed_submit(
+        &mut self,
+        deadline: Option<&Deadline>,
+        repeated_start: bool,
+    ) -> Result<(), Error> {
+        if deadline.is_some_and(Deadline::is_expired) {
+            return Err(Error::Timeout);
+        }
+        if !repeated_start {
+            self.check_bus_idle()?;
+        }
+        self.start_message();
+        if let Err(e) = self.i2c_wait_completion(deadline) {
+            //timeout, do controller reset to recover
+            let isr = self.i2c.i2cm14().read().bits();
+            if isr > 0 || self.i2c.i2cc08().read().xfer_data_direction().bits() > 0 {
+                self.reset_controller();
+                return Err(Error::Timeout);
+            }
+            if e == Error::Timeout {
+                // the command may not have been issued yet, so a new one
+                // must not be queued behind it
+                self.master_state = MasterState::NeedsRecovery;
+                return Err(Error::Timeout);
+            }
+            if self.master_state == MasterState::NeedsRecovery {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
 
-        //If bus is busy in a single master environment, attempt recovery
+    /// If bus is busy in a single master environment, attempt recovery
+    fn check_bus_idle(&mut self) -> Result<(), Error> {
         if !self.multi_master
             && self.i2c.i2cc08().read().bus_busy_status().bit()
             && self.recover_bus().is_err()
         {
             return Err(Error::Bus);
         }
-        cmd = AST_I2CM_PKT_EN | ast_i2cm_pkt_addr(self.i2c_data.addr) | AST_I2CM_START_CMD;
+        Ok(())
+    }
+
+    /// Triggers the prepared message without waiting for it
+    fn start_message(&mut self) {
+        self.master_state = MasterState::AddrPhase;
+        let cmd = AST_I2CM_PKT_EN | ast_i2cm_pkt_addr(self.i2c_data.addr) | AST_I2CM_START_CMD;
         if self.i2c_data.msg.flags & I2C_MSG_READ > 0 {
             self.aspeed_i2c_read(cmd);
         } else {
             self.aspeed_i2c_write(cmd);
         }
-        if self.i2c_wait_completion().is_err() {
-            //timeout, do controller reset to recover
-            let isr = self.i2c.i2cm14().read().bits();
-            if isr > 0 || self.i2c.i2cc08().read().xfer_data_direction().bits() > 0 {
-                let ctrl = self.i2c.i2cc00().read().bits();
-                self.i2c.i2cc00().write(|w| unsafe { w.bits(0) });
-                self.i2c.i2cc00().write(|w| unsafe { w.bits(ctrl) });
-                if cfg!(feature = "i2c_target") {
-                    cmd = AST_I2CS_ACTIVE_ALL | AST_I2CS_PKT_MODE_EN;
-                    if ctrl & AST_I2CC_SLAVE_EN == AST_I2CC_SLAVE_EN {
-                        match self.xfer_mode {
-                            I2cXferMode::DmaMode => {
-                                cmd |= AST_I2CS_RX_DMA_EN;
-                                self.i2c.i2cs3c().write(|w| unsafe {
-                                    w.sdramdmabuffer_base_addr3()
-                                        .bits(self.sdma_buf.as_mut_ptr() as u32)
-                                });
-                                self.i2c.i2cs38().write(|w| unsafe {
-                                    w.sdramdmabuffer_base_addr2()
-                                        .bits(self.sdma_buf.as_mut_ptr() as u32)
-                                });
-                                self.i2c.i2cs2c().write(|w| unsafe {
-                                    w.dmarx_buf_len_byte()
-                                        .bits(u16::try_from(I2C_SLAVE_BUF_SIZE - 1).unwrap())
-                                        .dmarx_buf_len_wr_enbl_for_cur_cmd()
-                                        .set_bit()
-                                });
-                            }
-                            I2cXferMode::BuffMode => {
-                                cmd |= AST_I2CS_RX_BUFF_EN;
-                                self.i2c.i2cc0c().write(|w| unsafe {
-                                    w.rx_pool_buffer_size().bits(I2C_BUF_SIZE - 1)
-                                });
-                            }
-                            I2cXferMode::ByteMode => {
-                                cmd &= !AST_I2CS_PKT_MODE_EN;
-                            }
-                        }
-                        self.i2c.i2cs28().write(|w| unsafe { w.bits(cmd) });
+    }
+
+    /// Cycles the controller enable to drop a stuck master transfer, then
+    /// re-arms target reception if it was enabled
+    fn reset_controller(&mut self) {
+        if self.master_state == MasterState::Disabled {
+            return;
+        }
+        let ctrl = self.i2c.i2cc00().read().bits();
+        self.i2c.i2cc00().write(|w| unsafe { w.bits(0) });
+        self.i2c.i2cc00().write(|w| unsafe { w.bits(ctrl) });
+        self.master_state = MasterState::Idle;
+        if cfg!(feature = "i2c_target") {
+            let mut cmd = AST_I2CS_ACTIVE_ALL | AST_I2CS_PKT_MODE_EN;
+            if ctrl & AST_I2CC_SLAVE_EN == AST_I2CC_SLAVE_EN {
+                match self.xfer_mode {
+                    I2cXferMode::DmaMode => {
+                        cmd |= AST_I2CS_RX_DMA_EN;
+                        self.i2c.i2cs3c().write(|w| unsafe {
+                            w.sdramdmabuffer_base_addr3()
+                                .bits(self.sdma_buf.as_mut_ptr() as u32)
+                        });
+                        self.i2c.i2cs38().write(|w| unsafe {
+                            w.sdramdmabuffer_base_addr2()
+                                .bits(self.sdma_buf.as_mut_ptr() as u32)
+                        });
+                        self.i2c.i2cs2c().write(|w| unsafe {
+                            w.dmarx_buf_len_byte()
+                                .bits(u16::try_from(I2C_SLAVE_BUF_SIZE - 1).unwrap())
+                                .dmarx_buf_len_wr_enbl_for_cur_cmd()
+                                .set_bit()
+                        });
+                    }
+                    I2cXferMode::BuffMode => {
+                        cmd |= AST_I2CS_RX_BUFF_EN;
+                        self.i2c
+                            .i2cc0c()
+                            .write(|w| unsafe { w.rx_pool_buffer_size().bits(I2C_BUF_SIZE - 1) });
+                    }
+                    I2cXferMode::ByteMode => {
+                        cmd &= !AST_I2CS_PKT_MODE_EN;
                     }
                 }
-                return Err(Error::Timeout);
+                self.i2c.i2cs28().write(|w| unsafe { w.bits(cmd) });
             }
         }
+    }
+
+    #[must_use]
+    pub fn master_state(&self) -> MasterState {
+        self.master_state
+    }
+
+    /// Returns the master to `Idle` from any state: drops the transaction
+    /// in flight and its DMA chunk, resets the controller and puts a stop
+    /// on the bus if it is still held
+    pub fn reset_state(&mut self) {
+        self.in_flight = false;
+        self.pending_read = None;
+        self.reset_controller();
+        self.mdma_buf = None;
+        self.release_bus();
+    }
+
+    /// Starts `transfer` to `addr` and returns without waiting for it.
+    ///
+    /// Drive it to completion with [`Self::poll_transaction`]; data of a
+    /// read is then available through [`Self::read_processed`]. A
+    /// `WriteRead` runs both phases as one transaction with a repeated
+    /// start. Fails with `Busy` while an earlier transaction is in flight
+    /// and with `InvalidState` while the master needs recovery.
+    pub fn begin_transaction(
+        &mut self,
+        addr: SevenBitAddress,
+        transfer: Transfer<'_>,
+    ) -> Result<(), Error> {
+        if self.in_flight {
+            return Err(Error::Busy);
+        }
+        self.master_state.check_start()?;
+        self.check_bus_idle()?;
+        self.pending_read = match transfer {
+            Transfer::Write(bytes) => {
+                self.prepare_write(addr, bytes, true)?;
+                None
+            }
+            Transfer::Read(len) => {
+                self.prepare_read(addr, u32::try_from(len).unwrap())?;
+                None
+            }
+            Transfer::WriteRead(bytes, len) => {
+                self.prepare_write(addr, bytes, false)?;
+                Some(u32::try_from(len).unwrap())
+            }
+        };
+        self.start_message();
+        self.in_flight = true;
         Ok(())
     }
+
+    /// Advances the transaction started by [`Self::begin_transaction`].
+    ///
+    /// Returns `WouldBlock` until it has finished. A failed write phase of
+    /// a `WriteRead` still ends with a stop. Polling with nothing in flight
+    /// is `Invalid`.
+    pub fn poll_transaction(&mut self) -> nb::Result<(), Error> {
+        if !self.in_flight {
+            return Err(nb::Error::Other(Error::Invalid));
+        }
+        if let Err(e) = self.aspeed_i2c_master_irq() {
+            self.in_flight = false;
+            self.mdma_buf = None;
+            if self.pending_read.take().is_some() {
+                self.release_bus();
+            }
+            return Err(nb::Error::Other(e));
+        }
+        if !self.i2c_data.completion {
+            return Err(nb::Error::WouldBlock);
+        }
+        // the write phase's DMA chunk carries over to the read
+        if let Some(len) = self.pending_read.take() {
+            if let Err(e) = self.prepare_read(self.i2c_data.addr, len) {
+                self.in_flight = false;
+                self.release_bus();
+                return Err(nb::Error::Other(e));
+            }
+            self.start_message();
+            return Err(nb::Error::WouldBlock);
+        }
+        if self.i2c_data.msg.flags & I2C_MSG_READ == 0 {
+            self.mdma_buf = None;
+        }
+        self.in_flight = false;
+        Ok(())
+    }
+
+    /// Gives up on the transaction in flight, resetting the controller as a
+    /// timed out blocking transfer does
+    pub fn abort_transaction(&mut self) {
+        if self.in_flight {
+            self.in_flight = false;
+            self.pending_read = None;
+            self.reset_controller();
+            self.mdma_buf = None;
+        }
+    }
     //slave
     #[cfg(feature = "i2c_target")]
     pub fn i2c_aspeed_slave_register(
@@ -1174,8 +2017,6 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         target_addr: u8,
         target: Option<&'a mut I2CT>,
     ) -> Result<(), Error> {
-        let mut cmd = AST_I2CS_ACTIVE_ALL | AST_I2CS_PKT_MODE_EN;
-
         // check slave config exist or has attached ever
         if self.i2c_data.slave_attached || self.i2c.i2cc00().read().enbl_slave_fn().bit() {
             return Err(Error::Invalid);
@@ -1186,7 +2027,42 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         }
 
         self.i2c_data.set_target(target_addr, target);
+        self.arm_slave();
+        self.i2c_data.slave_attached = true;
+
+        self.dump_regs();
+
+        Ok(())
+    }
+    #[cfg(feature = "i2c_target")]
+    pub fn i2c_aspeed_slave_unregister(&mut self) -> Result<(), Error> {
+        if !self.i2c_data.slave_attached {
+            return Err(Error::Invalid);
+        }
 
+        self.i2c_data.slave_target = None;
+        self.i2c_data.slave_target_addr = 0;
+        //Turn off slave mode.
+        self.i2c
+            .i2cc00()
+            .modify(|_, w| w.enbl_slave_fn().bit(false));
+        //remove slave address
+        self.i2c.i2cs40().modify(|_, w| unsafe {
+            w.slave_dev_addr1()
+                .bits(0)
+                .enbl_slave_dev_addr1only_for_new_reg_mode()
+                .bit(false)
+        });
+        self.i2c_data.slave_attached = false;
+        self.i2c_data.slave_in_transaction = false;
+        Ok(())
+    }
+    /// Programs the target address and receive buffer for the registered
+    /// target and turns the target side on
+    #[cfg(feature = "i2c_target")]
+    fn arm_slave(&mut self) {
+        let mut cmd = AST_I2CS_ACTIVE_ALL | AST_I2CS_PKT_MODE_EN;
+        let target_addr = self.i2c_data.slave_target_addr;
         i2c_debug!(self.logger, "set slave addr {:#x}", target_addr);
         //set slave addr
         self.i2c.i2cs40().modify(|_, w| unsafe {
@@ -1215,9 +2091,10 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
             }
             I2cXferMode::BuffMode => {
                 cmd |= AST_I2CS_RX_BUFF_EN;
+                // modify, so the master transmit count is left alone
                 self.i2c
                     .i2cc0c()
-                    .write(|w| unsafe { w.rx_pool_buffer_size().bits(I2C_BUF_SIZE - 1) });
+                    .modify(|_, w| unsafe { w.rx_pool_buffer_size().bits(I2C_BUF_SIZE - 1) });
             }
             I2cXferMode::ByteMode => {
                 cmd &= !AST_I2CS_PKT_MODE_EN;
@@ -1228,35 +2105,144 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
 
         // enable slave device
         self.i2c.i2cc00().modify(|_, w| w.enbl_slave_fn().bit(true));
-        self.i2c_data.slave_attached = true;
-
-        self.dump_regs();
-
-        Ok(())
     }
+    /// Stops answering as a target, keeping the registered target for
+    /// [`Self::enable_slave_mode`].
+    ///
+    /// A transaction in progress gets 100 ms to reach its stop. After that
+    /// the target side is turned off
+    /// anyway, the target sees a stop, and `Timeout` is returned.
+    ///
+    /// Master transfers work with the target side on or off; the bus
+    /// timing and transfer mode are not touched. `set_xfer_mode` and
+    /// `deinit` stay refused in both modes until the target is
+    /// unregistered.
     #[cfg(feature = "i2c_target")]
-    pub fn i2c_aspeed_slave_unregister(&mut self) -> Result<(), Error> {
+    pub fn disable_slave_mode(&mut self) -> Result<(), Error> {
         if !self.i2c_data.slave_attached {
             return Err(Error::Invalid);
         }
-
-        self.i2c_data.slave_target = None;
-        self.i2c_data.slave_target_addr = 0;
-        //Turn off slave mode.
+        if !self.i2c.i2cc00().read().enbl_slave_fn().bit() {
+            return Ok(());
+        }
+        let mut delay = DummyDelay {};
+        let mut polls = SLAVE_QUIESCE_POLLS;
+        while self.i2c_data.slave_in_transaction && polls > 0 {
+            self.aspeed_i2c_slave_irq();
+            delay.delay_ns(100_000);
+            polls -= 1;
+        }
         self.i2c
             .i2cc00()
             .modify(|_, w| w.enbl_slave_fn().bit(false));
-        //remove slave address
-        self.i2c.i2cs40().modify(|_, w| unsafe {
-            w.slave_dev_addr1()
-                .bits(0)
-                .enbl_slave_dev_addr1only_for_new_reg_mode()
-                .bit(false)
+        self.i2c
+            .i2cs40()
+            .modify(|_, w| w.enbl_slave_dev_addr1only_for_new_reg_mode().bit(false));
+        // status left over from the cut transaction must not show up after
+        // the next enable
+        let sts = self.i2c.i2cs24().read().bits();
+        self.i2c.i2cs24().write(|w| unsafe { w.bits(sts) });
+        if self.i2c_data.slave_in_transaction {
+            self.i2c_slave_event_stop();
+            return Err(Error::Timeout);
+        }
+        Ok(())
+    }
+    /// Answers as the registered target again
+    #[cfg(feature = "i2c_target")]
+    pub fn enable_slave_mode(&mut self) -> Result<(), Error> {
+        if !self.i2c_data.slave_attached {
+            return Err(Error::Invalid);
+        }
+        if !self.i2c.i2cc00().read().enbl_slave_fn().bit() {
+            self.arm_slave();
+        }
+        Ok(())
+    }
+    /// Whether the target side is answering
+    #[must_use]
+    pub fn slave_mode_enabled(&self) -> bool {
+        self.i2c.i2cc00().read().enbl_slave_fn().bit()
+    }
+    /// Respond to the SMBus Host Notify address (0x08) on slave address slot 2.
+    ///
+    /// The slave function must already be enabled with
+    /// `i2c_aspeed_slave_register`. Received notifications are queued and
+    /// retrieved with `pop_host_notify`.
+    #[cfg(feature = "i2c_target")]
+    pub fn enable_host_notify(&mut self) -> Result<(), Error> {
+        if !self.i2c_data.slave_attached {
+            return Err(Error::Invalid);
+        }
+        self.i2c.i2cs40().modify(|r, w| unsafe {
+            w.bits(
+                (r.bits() & !AST_I2CS_ADDR2_MASK)
+                    | (u32::from(SMBUS_HOST_NOTIFY_ADDR) << AST_I2CS_ADDR2_SHIFT)
+                    | AST_I2CS_ADDR2_EN,
+            )
         });
-        self.i2c_data.slave_attached = false;
+        self.i2c_data.host_notify_len = 0;
+        self.i2c_data.host_notify_enabled = true;
         Ok(())
     }
     #[cfg(feature = "i2c_target")]
+    pub fn disable_host_notify(&mut self) {
+        self.i2c.i2cs40().modify(|r, w| unsafe {
+            w.bits(r.bits() & !(AST_I2CS_ADDR2_MASK | AST_I2CS_ADDR2_EN))
+        });
+        self.i2c_data.host_notify_enabled = false;
+        self.i2c_data.host_notify_active = false;
+    }
+    /// Take the oldest pending SMBus Host Notify message, if any.
+    pub fn pop_host_notify(&mut self) -> Option<HostNotify> {
+        self.i2c_data.host_notify_queue.pop_front()
+    }
+    /// Take the number of bytes the controller read in the last completed
+    /// read from this target, if one completed since the last call.
+    pub fn take_read_complete(&mut self) -> Option<u32> {
+        self.i2c_data.slave_read_done.take()
+    }
+    #[cfg(feature = "i2c_target")]
+    fn host_notify_push_byte(&mut self, byte: u8) {
+        let len = self.i2c_data.host_notify_len;
+        if len < self.i2c_data.host_notify_buf.len() {
+            self.i2c_data.host_notify_buf[len] = byte;
+        }
+        self.i2c_data.host_notify_len = len + 1;
+    }
+    #[cfg(feature = "i2c_target")]
+    fn host_notify_complete(&mut self) {
+        let buf = self.i2c_data.host_notify_buf;
+        // Host Notify is exactly: device address, data low, data high
+        if self.i2c_data.host_notify_len == buf.len() {
+            let notify = HostNotify {
+                addr: buf[0] >> 1,
+                data: u16::from_le_bytes([buf[1], buf[2]]),
+            };
+            i2c_debug!(
+                self.logger,
+                "host notify from {:#x}: {:#x}",
+                notify.addr,
+                notify.data
+            );
+            if self.i2c_data.host_notify_queue.push_back(notify).is_err() {
+                i2c_error!(
+                    self.logger,
+                    "host notify queue full, dropped {:#x}",
+                    notify.addr
+                );
+            }
+        } else {
+            i2c_error!(
+                self.logger,
+                "malformed host notify, len {}",
+                self.i2c_data.host_notify_len
+            );
+        }
+        self.i2c_data.host_notify_len = 0;
+        self.i2c_data.host_notify_active = false;
+    }
+    #[cfg(feature = "i2c_target")]
     pub fn aspeed_i2c_slave_timeout(&mut self, sts: u32, reset_slave: bool) {
         let cmd: u32;
         // Reset time out counter
@@ -1306,25 +2292,25 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
             return 0;
         }
         i2c_debug!(self.logger, "Slave irq ier {:#x}, sts {:#x}", ier, sts);
-        // remove unnessary status flags
-        sts &= !(AST_I2CS_ADDR_INDICATE_MASK | AST_I2CS_SLAVE_PENDING);
-        if AST_I2CS_ADDR1_NAK == AST_I2CS_ADDR1_NAK & sts {
-            sts &= !AST_I2CS_ADDR1_NAK;
-        }
-        if AST_I2CS_ADDR2_NAK == AST_I2CS_ADDR2_NAK & sts {
-            sts &= !AST_I2CS_ADDR2_NAK;
-        }
-        if AST_I2CS_ADDR3_NAK == AST_I2CS_ADDR3_NAK & sts {
-            sts &= !AST_I2CS_ADDR3_NAK;
+        let status = decode_i2c_status(sts);
+        if status.address_match {
+            self.i2c_data.host_notify_active = self.i2c_data.host_notify_enabled
+                && status.matched_address == HOST_NOTIFY_ADDR_SLOT;
         }
+        // remove unnessary status flags
+        sts &= !(AST_I2CS_ADDR_INDICATE_MASK
+            | AST_I2CS_SLAVE_PENDING
+            | AST_I2CS_ADDR1_NAK
+            | AST_I2CS_ADDR2_NAK
+            | AST_I2CS_ADDR3_NAK);
         if AST_I2CS_ADDR_MASK == AST_I2CS_ADDR_MASK & sts {
             sts &= !AST_I2CS_ADDR_MASK;
         }
-        if AST_I2CS_INACTIVE_TO == AST_I2CS_INACTIVE_TO & sts {
+        if status.inactive_timeout {
             self.aspeed_i2c_slave_timeout(sts, true);
             return 1;
         }
-        if AST_I2CS_PKT_DONE & sts == AST_I2CS_PKT_DONE {
+        if status.packet_done {
             self.aspeed_i2c_slave_packet_irq(sts);
         } else {
             self.aspeed_i2c_slave_byte_irq(sts);
@@ -1337,21 +2323,50 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
     //
     #[cfg(feature = "i2c_target")]
     pub fn i2c_slave_event_stop(&mut self) {
+        self.i2c_data.slave_in_transaction = false;
+        if self.i2c_data.host_notify_active {
+            self.host_notify_complete();
+            return;
+        }
+        self.i2c_slave_byte_flush();
+        self.i2c_slave_finish_read();
         if let Some(target) = self.i2c_data.slave_target.as_mut() {
             target.on_stop();
         } else {
             // Handle the case where config is not set
         }
     }
+    //A start before the stop of the previous transaction is a repeated start
+    #[cfg(feature = "i2c_target")]
+    fn i2c_slave_start(&mut self) {
+        let repeated = core::mem::replace(&mut self.i2c_data.slave_in_transaction, true);
+        i2c_debug!(self.logger, "start, repeated {}", repeated);
+        if let Some(target) = self.i2c_data.slave_target.as_mut() {
+            target.on_transaction_start(repeated);
+        }
+    }
+    //A read ends at the stop or at the next start; report the bytes sent
+    #[cfg(feature = "i2c_target")]
+    fn i2c_slave_finish_read(&mut self) {
+        if self.i2c_data.slave_tx_cnt == 0 {
+            return;
+        }
+        if self.xfer_mode == I2cXferMode::ByteMode {
+            let mut unused = 0;
+            self.i2c_slave_byte_read(I2cSEvent::SlaveReadComplete, &mut unused);
+        } else {
+            self.i2c_slave_pkt_read(I2cSEvent::SlaveReadComplete);
+        }
+    }
     #[cfg(feature = "i2c_target")]
     pub fn i2c_slave_pkt_read(&mut self, event: I2cSEvent) {
         if event == I2cSEvent::SlaveRdReq {
             i2c_debug!(self.logger, "read_requested");
-            if let Some(target) = self.i2c_data.slave_target.as_mut() {
-                target.on_transaction_start(false);
-            }
+            self.i2c_slave_finish_read();
+            self.i2c_slave_start();
         } else if event == I2cSEvent::SlaveRdProc {
             i2c_debug!(self.logger, "read_processed");
+            self.i2c_data.slave_tx_cnt += 1;
             match self.xfer_mode {
                 I2cXferMode::DmaMode => {
                     let tx_len = self.i2c.i2cs4c().read().dmatx_actual_len_byte().bits();
@@ -1378,9 +2393,17 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                 }
                 I2cXferMode::ByteMode => {}
             }
+        } else if event == I2cSEvent::SlaveReadComplete {
+            self.i2c_slave_read_complete();
         }
     }
     #[cfg(feature = "i2c_target")]
+    fn i2c_slave_read_complete(&mut self) {
+        let sent = core::mem::take(&mut self.i2c_data.slave_tx_cnt);
+        i2c_debug!(self.logger, "read_complete: len={}", sent);
+        self.i2c_data.slave_read_done = Some(sent);
+    }
+    #[cfg(feature = "i2c_target")]
     pub fn i2c_slave_pkt_write(&mut self, event: I2cSEvent) {
         if event == I2cSEvent::SlaveWrReq {
             //Another I2C master wants to write data to us.
@@ -1389,8 +2412,11 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
             //ack the address phase
             //if slave is ready to receive
             i2c_debug!(self.logger, "write_requested");
-            if let Some(target) = self.i2c_data.slave_target.as_mut() {
-                target.on_transaction_start(false);
+            self.i2c_slave_finish_read();
+            if self.i2c_data.host_notify_active {
+                self.i2c_data.host_notify_len = 0;
+            } else {
+                self.i2c_slave_start();
             }
         } else if event == I2cSEvent::SlaveWrRecvd {
             //Another I2C master has sent a byte to us which needs to be set in ‘val’
@@ -1401,11 +2427,13 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                     i2c_debug!(self.logger, "dma write_received: len={:#x}", slave_rx_len);
                     //target expects one byte each time
                     for i in 0..slave_rx_len {
-                        let slice = self.sdma_buf.as_slice(i as usize, i as usize + 1);
-                        if let Some(target) = self.i2c_data.slave_target.as_mut() {
-                            target.on_write(slice).unwrap();
+                        let byte = self.sdma_buf[i as usize];
+                        if self.i2c_data.host_notify_active {
+                            self.host_notify_push_byte(byte);
+                        } else if let Some(target) = self.i2c_data.slave_target.as_mut() {
+                            target.on_write(&[byte]).unwrap();
                         }
-                        i2c_debug!(self.logger, "write_received: data={:?}", slice);
+                        i2c_debug!(self.logger, "write_received: data={:#x}", byte);
                     }
                 }
                 I2cXferMode::BuffMode => {
@@ -1419,7 +2447,9 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                     i2c_debug!(self.logger, "buff write_received: len={:#x}", slave_rx_len);
                     //target expects one byte each time
                     for i in 0..slave_rx_len {
-                        if let Some(target) = self.i2c_data.slave_target.as_mut() {
+                        if self.i2c_data.host_notify_active {
+                            self.host_notify_push_byte(self.i2c_data.msg.buf[i as usize]);
+                        } else if let Some(target) = self.i2c_data.slave_target.as_mut() {
                             target
                                 .on_write(&self.i2c_data.msg.buf[(i as usize)..(i as usize + 1)])
                                 .unwrap();
@@ -1435,17 +2465,46 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
             }
         }
     }
+    //Hand the bytes collected in byte mode to the target as one message
+    #[cfg(feature = "i2c_target")]
+    fn i2c_slave_byte_flush(&mut self) {
+        let len = self.i2c_data.slave_rx_cnt as usize;
+        if len == 0 {
+            return;
+        }
+        self.i2c_data.slave_rx_cnt = 0;
+        i2c_debug!(
+            self.logger,
+            "byte write message {:?}",
+            &self.i2c_data.msg.buf[..len]
+        );
+        if let Some(target) = self.i2c_data.slave_target.as_mut() {
+            target.on_write(&self.i2c_data.msg.buf[..len]).unwrap();
+        }
+    }
     #[cfg(feature = "i2c_target")]
     pub fn i2c_slave_byte_write(&mut self, event: I2cSEvent, val: u8) {
         if event == I2cSEvent::SlaveWrReq {
             i2c_debug!(self.logger, "byte write_requested");
-            if let Some(target) = self.i2c_data.slave_target.as_mut() {
-                target.on_transaction_start(false);
+            self.i2c_slave_finish_read();
+            if self.i2c_data.host_notify_active {
+                self.i2c_data.host_notify_len = 0;
+            } else {
+                self.i2c_slave_byte_flush();
+                self.i2c_slave_start();
             }
         } else if event == I2cSEvent::SlaveWrRecvd {
             i2c_debug!(self.logger, "byte write_received");
-            if let Some(target) = self.i2c_data.slave_target.as_mut() {
-                target.on_write(&[val]).unwrap();
+            if self.i2c_data.host_notify_active {
+                self.host_notify_push_byte(val);
+            } else {
+                let idx = self.i2c_data.slave_rx_cnt as usize;
+                if idx < self.i2c_data.msg.buf.len() {
+                    self.i2c_data.msg.buf[idx] = val;
+                    self.i2c_data.slave_rx_cnt += 1;
+                } else {
+                    i2c_error!(self.logger, "byte rx overrun, dropped {:#x}", val);
+                }
             }
         }
     }
@@ -1453,9 +2512,8 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
     pub fn i2c_slave_byte_read(&mut self, event: I2cSEvent, val: &mut u8) {
         if event == I2cSEvent::SlaveRdReq {
             i2c_debug!(self.logger, "byte read_requested");
-            if let Some(target) = self.i2c_data.slave_target.as_mut() {
-                target.on_transaction_start(false);
-            }
+            self.i2c_slave_finish_read();
+            self.i2c_slave_start();
         } else if event == I2cSEvent::SlaveRdProc {
             i2c_debug!(self.logger, "byte read_processed");
             if let Some(target) = self.i2c_data.slave_target.as_mut() {
@@ -1464,6 +2522,9 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                 i2c_debug!(self.logger, "byte dummy read");
                 *val = 0xdd;
             }
+            self.i2c_data.slave_tx_cnt += 1;
+        } else if event == I2cSEvent::SlaveReadComplete {
+            self.i2c_slave_read_complete();
         }
     }
     #[cfg(feature = "i2c_target")]
@@ -1600,6 +2661,7 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         } else if sts == AST_I2CS_SLAVE_MATCH | AST_I2CS_WAIT_TX_DMA {
             //First Start read
             i2c_debug!(self.logger, "S: Sw | AST_I2CS_Wait_TX_DMA\n");
+            self.i2c_slave_pkt_read(I2cSEvent::SlaveRdReq);
             cmd = SLAVE_TRIGGER_CMD;
             match self.xfer_mode {
                 I2cXferMode::DmaMode => {
@@ -1733,6 +2795,10 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
         else if sts == AST_I2CS_SLAVE_MATCH | AST_I2CS_RX_DONE | AST_I2CS_WAIT_TX_DMA {
             i2c_debug!(self.logger, "S : Sr|D\n");
             cmd |= AST_I2CS_TX_CMD;
+            // a pending write (register offset) or read completes before the
+            // read starts
+            self.i2c_slave_byte_flush();
+            self.i2c_slave_byte_read(I2cSEvent::SlaveRdReq, &mut byte_data);
             byte_data = self.i2c.i2cc08().read().rx_byte_buffer().bits();
             i2c_debug!(
                 self.logger,
@@ -1792,6 +2858,10 @@ impl<'a, I2C: Instance, I2CT: I2CTarget, L: Logger> Ast1060I2c<'a, I2C, I2CT, L>
                 };
                 prev_op = op;
             }
+            match &mut prev_op {
+                Operation::Read(rb) => self.read(addr, rb)?,
+                Operation::Write(wb) => self.write(addr, wb)?,
+            };
         }
 
         // Fallthrough is success
@@ -1814,7 +2884,126 @@ macro_rules! transaction_impl {
                 };
                 prev_op = op;
             }
+            match &mut prev_op {
+                $Operation::Read(rb) => i2c.read(addr, rb)?,
+                $Operation::Write(wb) => i2c.write(addr, &wb)?,
+            };
         }
     };
 }
 use transaction_impl;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fast_plus_clock_source() {
+        assert!(check_clock_source(I2cSpeed::FastPlus, 50_000_000).is_ok());
+        assert_eq!(
+            check_clock_source(I2cSpeed::FastPlus, FAST_PLUS_MIN_CLK_SRC - 1),
+            Err(Error::InvalidTiming)
+        );
+        assert!(check_clock_source(I2cSpeed::Fast, FAST_PLUS_MIN_CLK_SRC - 1).is_ok());
+    }
+
+    #[test]
+    fn test_message_lens() {
+        let buff = |_| I2cXferMode::BuffMode;
+        assert!(check_message_lens([0, 1, I2C_SLAVE_BUF_SIZE], buff).is_ok());
+        assert_eq!(
+            check_message_lens([1, I2C_SLAVE_BUF_SIZE + 1], buff),
+            Err(Error::Invalid)
+        );
+        let dma = |_| I2cXferMode::DmaMode;
+        assert!(check_message_lens([ASPEED_I2C_DMA_SIZE], dma).is_ok());
+        assert_eq!(
+            check_message_lens([ASPEED_I2C_DMA_SIZE + 1], dma),
+            Err(Error::Invalid)
+        );
+        // short messages of a DMA controller go in byte mode
+        let split = |len| {
+            if len <= 4 {
+                I2cXferMode::ByteMode
+            } else {
+                I2cXferMode::DmaMode
+            }
+        };
+        assert!(check_message_lens([2, 1024], split).is_ok());
+    }
+
+    #[test]
+    fn test_decode_master_status() {
+        assert_eq!(decode_i2c_master_status(0), I2cMasterStatus::default());
+
+        let status = decode_i2c_master_status(AST_I2CM_PKT_DONE | AST_I2CM_TX_ACK);
+        assert!(status.packet_done && status.tx_ack);
+        assert!(!status.is_error());
+
+        let status = decode_i2c_master_status(AST_I2CM_PKT_ERROR | AST_I2CM_ARBIT_LOSS);
+        assert!(status.packet_error && status.arbitration_lost);
+        assert!(status.is_error());
+        assert!(decode_i2c_master_status(AST_I2CM_SDA_DL_TO).is_error());
+    }
+
+    #[test]
+    fn test_decode_target_status() {
+        assert_eq!(decode_i2c_status(0), I2cStatusFlags::default());
+
+        let status =
+            decode_i2c_status(AST_I2CS_SLAVE_MATCH | AST_I2CS_RX_DONE | AST_I2CS_WAIT_RX_DMA);
+        assert!(status.address_match && status.rx_done && status.wait_rx_dma);
+        assert!(!status.stop && !status.wait_tx_dma);
+        assert_eq!(status.matched_address, 0);
+
+        let status = decode_i2c_status(AST_I2CS_SLAVE_MATCH | (2 << AST_I2CS_ADDR_INDICATE_SHIFT));
+        assert_eq!(status.matched_address, 2);
+
+        assert!(decode_i2c_status(AST_I2CS_ADDR3_NAK).address_nak);
+        assert!(decode_i2c_status(AST_I2CS_INACTIVE_TO).inactive_timeout);
+    }
+
+    #[test]
+    fn test_master_state_after_timeout() {
+        // an SDA/SCL low timeout leaves the bus stuck, so new messages are
+        // refused instead of queued behind it
+        let state = MasterState::after_error(Error::Busy);
+        assert_eq!(state, MasterState::NeedsRecovery);
+        assert_eq!(state.check_start(), Err(Error::InvalidState { state }));
+        for state in [
+            MasterState::AddrPhase,
+            MasterState::TxData,
+            MasterState::RxData,
+        ] {
+            assert_eq!(state.check_start(), Err(Error::InvalidState { state }));
+        }
+        assert_eq!(MasterState::Idle.check_start(), Ok(()));
+        let state = MasterState::Disabled;
+        assert_eq!(state.check_start(), Err(Error::InvalidState { state }));
+    }
+
+    #[test]
+    fn test_master_state_transitions() {
+        let lost = MasterState::after_error(Error::ArbitrationLoss);
+        assert_eq!(lost, MasterState::Idle);
+        let nack = MasterState::after_error(Error::NoAcknowledge(NoAcknowledgeSource::Data));
+        assert_eq!(nack, MasterState::Stopping);
+        // the bus is still ours, so a repeated start may follow
+        assert_eq!(nack.check_start(), Ok(()));
+        assert_eq!(
+            MasterState::after_error(Error::Abnormal),
+            MasterState::NeedsRecovery
+        );
+
+        let tx = MasterState::TxData;
+        assert_eq!(MasterState::after_packet(tx, false, false), tx);
+        assert_eq!(
+            MasterState::after_packet(tx, true, false),
+            MasterState::Stopping
+        );
+        assert_eq!(
+            MasterState::after_packet(MasterState::RxData, true, true),
+            MasterState::Idle
+        );
+    }
+}
@@ -1,5 +1,7 @@
 // Licensed under the Apache-2.0 license
 
+use crate::pinctrl::PinctrlDrive;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u32)]
 pub enum I2cSpeed {
@@ -23,6 +25,47 @@ pub enum I2cSEvent {
     SlaveRdProc,
     SlaveWrRecvd,
     SlaveStop,
+    /// The controller finished reading from the target (NACK or stop after
+    /// a read)
+    SlaveReadComplete,
+}
+
+/// SMBus Host Notify protocol target address
+pub const SMBUS_HOST_NOTIFY_ADDR: u8 = 0x08;
+
+/// Notification pushed by a device to the SMBus host (address 0x08)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HostNotify {
+    /// 7-bit address of the notifying device
+    pub addr: u8,
+    /// Data word, sent low byte first on the wire
+    pub data: u16,
+}
+
+/// Board pad settings for the SDA/SCL pins of a bus
+///
+/// Fast-mode Plus needs stronger drive and the fast slew setting to meet
+/// its rise time; `normal` puts the pads back for the slower speeds. The
+/// SCU fields depend on the package and pins, so the board supplies them.
+#[derive(Copy, Clone)]
+pub struct I2cPadDrive {
+    pub fast_plus: &'static [PinctrlDrive],
+    pub normal: &'static [PinctrlDrive],
+}
+
+/// Largest manual tCKLow or tCKHigh; the fields are 4 bits wide
+pub const MAX_MANUAL_SCL_CYCLES: u8 = 0xf;
+/// Largest manual SDA hold; the field is 2 bits wide
+pub const MAX_MANUAL_SDA_HOLD: u8 = 3;
+
+/// A setting of an [`I2cConfig`] the controller cannot honour
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigurationError {
+    /// `manual_scl_low` or `manual_scl_high` is above
+    /// [`MAX_MANUAL_SCL_CYCLES`]
+    SclCycles,
+    /// `manual_sda_hold` is above [`MAX_MANUAL_SDA_HOLD`]
+    SdaHold,
 }
 
 pub struct TimingConfig {
@@ -38,7 +81,31 @@ pub struct I2cConfig {
     pub smbus_alert: bool,
     pub timing_config: TimingConfig,
     pub speed: I2cSpeed,
+    pub pad_drive: Option<I2cPadDrive>,
+    /// Master messages of 1 to this many bytes use byte mode, whatever
+    /// `xfer_mode` says; 0 keeps every message in `xfer_mode`
+    pub byte_mode_max: usize,
+}
+
+impl I2cConfig {
+    /// Rejects settings the hardware would otherwise clamp or ignore.
+    ///
+    /// A zero timing field asks for the computed value, so only values too
+    /// wide for their register field are refused.
+    pub fn validate(&self) -> Result<(), ConfigurationError> {
+        let timing = &self.timing_config;
+        if timing.manual_scl_low > MAX_MANUAL_SCL_CYCLES
+            || timing.manual_scl_high > MAX_MANUAL_SCL_CYCLES
+        {
+            return Err(ConfigurationError::SclCycles);
+        }
+        if timing.manual_sda_hold > MAX_MANUAL_SDA_HOLD {
+            return Err(ConfigurationError::SdaHold);
+        }
+        Ok(())
+    }
 }
+
 pub struct I2cConfigBuilder {
     xfer_mode: I2cXferMode,
     multi_master: bool,
@@ -46,6 +113,8 @@ pub struct I2cConfigBuilder {
     smbus_alert: bool,
     timing_config: Option<TimingConfig>,
     speed: I2cSpeed,
+    pad_drive: Option<I2cPadDrive>,
+    byte_mode_max: usize,
 }
 impl Default for I2cConfigBuilder {
     fn default() -> Self {
@@ -63,6 +132,8 @@ impl I2cConfigBuilder {
             smbus_timeout: false,
             timing_config: None,
             speed: I2cSpeed::Standard,
+            pad_drive: None,
+            byte_mode_max: 0,
         }
     }
     #[must_use]
@@ -96,6 +167,18 @@ impl I2cConfigBuilder {
         self
     }
     #[must_use]
+    pub fn pad_drive(mut self, drive: I2cPadDrive) -> Self {
+        self.pad_drive = Some(drive);
+        self
+    }
+    /// Sends messages of up to `len` bytes in byte mode, where setting up
+    /// a buffer or DMA transfer costs more than it saves
+    #[must_use]
+    pub fn byte_mode_max(mut self, len: usize) -> Self {
+        self.byte_mode_max = len;
+        self
+    }
+    #[must_use]
     pub fn build(self) -> I2cConfig {
         I2cConfig {
             xfer_mode: self.xfer_mode,
@@ -109,6 +192,42 @@ impl I2cConfigBuilder {
                 clk_src: 0,
             }),
             speed: self.speed,
+            pad_drive: self.pad_drive,
+            byte_mode_max: self.byte_mode_max,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigurationError, I2cConfigBuilder, TimingConfig};
+
+    fn timing(manual_scl_low: u8, manual_scl_high: u8, manual_sda_hold: u8) -> TimingConfig {
+        TimingConfig {
+            manual_scl_high,
+            manual_scl_low,
+            manual_sda_hold,
+            clk_src: 0,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(I2cConfigBuilder::new().build().validate(), Ok(()));
+        let config = I2cConfigBuilder::new().timing_config(timing(0xf, 0xf, 3));
+        assert_eq!(config.build().validate(), Ok(()));
+
+        let config = I2cConfigBuilder::new().timing_config(timing(0x10, 0, 0));
+        assert_eq!(
+            config.build().validate(),
+            Err(ConfigurationError::SclCycles)
+        );
+        let config = I2cConfigBuilder::new().timing_config(timing(0, 0x20, 0));
+        assert_eq!(
+            config.build().validate(),
+            Err(ConfigurationError::SclCycles)
+        );
+        let config = I2cConfigBuilder::new().timing_config(timing(0, 0, 4));
+        assert_eq!(config.build().validate(), Err(ConfigurationError::SdaHold));
+    }
+}
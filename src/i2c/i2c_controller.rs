@@ -1,15 +1,35 @@
 // Licensed under the Apache-2.0 license
 
-use crate::common::{Logger, NoOpLogger};
-use crate::i2c::common::I2cConfig;
+use crate::common::{LogErr, Logger, NoOpLogger};
+use crate::i2c::common::{I2cConfig, I2cSpeed, I2cXferMode};
+use crate::syscon::{self, ClockConsumer, ClockId};
 use embedded_hal::i2c::{Operation, SevenBitAddress};
 
 pub trait HardwareInterface {
     type Error: embedded_hal::i2c::Error + core::fmt::Debug;
 
     // Methods return hardware-specific errors
-    fn init(&mut self, config: &mut I2cConfig);
-    fn configure_timing(&mut self, config: &mut I2cConfig);
+    fn init(&mut self, config: &mut I2cConfig) -> Result<(), Self::Error>;
+    fn configure_timing(&mut self, config: &mut I2cConfig) -> Result<(), Self::Error>;
+    /// Recomputes the timing after the source clock changed; timing saved
+    /// for other speeds is dropped, as it was worked out for the old clock
+    fn clock_changed(&mut self, config: &mut I2cConfig) -> Result<(), Self::Error>;
+    /// Switches the bus to `speed`, failing if a transfer is in progress
+    fn set_speed(&mut self, config: &mut I2cConfig, speed: I2cSpeed) -> Result<(), Self::Error>;
+    /// Switches master transfers to `mode`, failing if a transfer is in
+    /// progress or a target is registered
+    fn set_xfer_mode(
+        &mut self,
+        config: &mut I2cConfig,
+        mode: I2cXferMode,
+    ) -> Result<(), Self::Error>;
+    /// Sends the following master messages in `mode`, or picks the mode
+    /// by message size again for `None`; fails while a transfer is in
+    /// progress
+    fn override_xfer_mode(&mut self, mode: Option<I2cXferMode>) -> Result<(), Self::Error>;
+    /// Turns the bus off until the next `init`, failing if a transfer is
+    /// in progress or a target is registered
+    fn deinit(&mut self) -> Result<(), Self::Error>;
     fn enable_interrupts(&mut self, mask: u32);
     fn clear_interrupts(&mut self, mask: u32);
     #[cfg(feature = "i2c_target")]
@@ -41,6 +61,132 @@ pub struct I2cController<H: HardwareInterface, L: Logger = NoOpLogger> {
     pub logger: L,
 }
 
+impl<H: HardwareInterface, L: Logger> I2cController<H, L> {
+    /// Rebuilds the controller around another logger, e.g. a `UartLogger`
+    /// to trace a single bus while debugging
+    pub fn with_logger<L2: Logger>(self, logger: L2) -> I2cController<H, L2> {
+        I2cController {
+            hardware: self.hardware,
+            config: self.config,
+            logger,
+        }
+    }
+
+    #[must_use]
+    pub fn speed(&self) -> I2cSpeed {
+        self.config.speed
+    }
+
+    /// Reclocks the bus in place; the timing is recomputed for a speed not
+    /// used before. The old speed stays on error.
+    pub fn set_speed(&mut self, speed: I2cSpeed) -> Result<(), H::Error> {
+        if speed == self.config.speed {
+            return Ok(());
+        }
+        self.hardware.set_speed(&mut self.config, speed)
+    }
+
+    #[must_use]
+    pub fn xfer_mode(&self) -> I2cXferMode {
+        self.config.xfer_mode
+    }
+
+    /// Switches between byte, buffer and DMA transfers without
+    /// reinitializing the controller
+    pub fn set_xfer_mode(&mut self, mode: I2cXferMode) -> Result<(), H::Error> {
+        if mode == self.config.xfer_mode {
+            return Ok(());
+        }
+        self.hardware.set_xfer_mode(&mut self.config, mode)
+    }
+
+    /// Runs `f` with master messages sent in `mode`, then goes back to the
+    /// controller mode, also when `f` fails. Unlike [`Self::set_xfer_mode`]
+    /// this leaves a registered target alone.
+    pub fn with_xfer_mode<R>(
+        &mut self,
+        mode: I2cXferMode,
+        f: impl FnOnce(&mut Self) -> Result<R, H::Error>,
+    ) -> Result<R, H::Error> {
+        self.hardware.override_xfer_mode(Some(mode))?;
+        let result = f(self);
+        let restored = self.hardware.override_xfer_mode(None);
+        let value = result?;
+        restored.map(|()| value)
+    }
+
+    /// Writes `bytes` to `addr` in `mode`, e.g. DMA for a large block on a
+    /// bus that otherwise runs in byte mode
+    pub fn write_with_mode(
+        &mut self,
+        addr: SevenBitAddress,
+        bytes: &[u8],
+        mode: I2cXferMode,
+    ) -> Result<(), H::Error> {
+        self.with_xfer_mode(mode, |c| c.hardware.write(addr, bytes))
+    }
+
+    /// Reads `buffer.len()` bytes from `addr` in `mode`
+    pub fn read_with_mode(
+        &mut self,
+        addr: SevenBitAddress,
+        buffer: &mut [u8],
+        mode: I2cXferMode,
+    ) -> Result<(), H::Error> {
+        self.with_xfer_mode(mode, |c| c.hardware.read(addr, buffer))
+    }
+
+    /// Brings the bus up with the current config; also undoes `deinit`
+    pub fn init(&mut self) -> Result<(), H::Error> {
+        self.hardware.init(&mut self.config)
+    }
+
+    /// Turns the bus off. Transfers fail with an error until `init` is
+    /// called again.
+    pub fn deinit(&mut self) -> Result<(), H::Error> {
+        self.hardware.deinit()
+    }
+
+    /// Runs `f` with the bus clocked at `speed`, then restores the previous
+    /// speed, also when `f` fails. Lets devices of different speed classes
+    /// share one bus.
+    pub fn with_speed<R>(
+        &mut self,
+        speed: I2cSpeed,
+        f: impl FnOnce(&mut Self) -> Result<R, H::Error>,
+    ) -> Result<R, H::Error> {
+        let previous = self.config.speed;
+        if speed == previous {
+            return f(self);
+        }
+        self.hardware.set_speed(&mut self.config, speed)?;
+        let result = f(self);
+        let mut restored = self.hardware.set_speed(&mut self.config, previous);
+        if restored.is_err() {
+            // a failed transfer can leave the bus held; free it and retry
+            let _ = self.hardware.recover_bus();
+            restored = self.hardware.set_speed(&mut self.config, previous);
+        }
+        let value = result?;
+        restored.map(|()| value)
+    }
+}
+
+impl<H: HardwareInterface, L: Logger> ClockConsumer for I2cController<H, L> {
+    fn clock_id(&self) -> ClockId {
+        ClockId::ClkPCLK
+    }
+
+    /// Recomputes the bus timing; the divider search reads the new APB
+    /// frequency from the SCU itself
+    fn reconfigure_for_clock(&mut self, _freq_hz: u32) -> Result<(), syscon::Error> {
+        self.hardware
+            .clock_changed(&mut self.config)
+            .log_err(&mut self.logger, "i2c timing after clock change")
+            .map_err(|_| syscon::Error::ClockConfigurationFailed)
+    }
+}
+
 impl<H: HardwareInterface, L: Logger> embedded_hal::i2c::ErrorType for I2cController<H, L> {
     type Error = H::Error;
 }
@@ -71,3 +217,119 @@ impl<H: HardwareInterface, L: Logger> embedded_hal::i2c::I2c for I2cController<H
         self.hardware.transaction_slice(addr, operations)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{HardwareInterface, I2cController};
+    use crate::common::NoOpLogger;
+    use crate::i2c::common::{I2cConfig, I2cConfigBuilder, I2cSpeed, I2cXferMode};
+    use crate::syscon::ClockConsumer;
+    use embedded_hal::i2c::{ErrorKind, Operation, SevenBitAddress};
+
+    /// Keeps per-speed timing like the AST1060 driver; the timing is the
+    /// source clock divided by the bus speed
+    struct FakeBus {
+        clk_src: u32,
+        cache: [Option<u32>; 3],
+        timing: u32,
+    }
+
+    fn index(speed: I2cSpeed) -> usize {
+        match speed {
+            I2cSpeed::Standard => 0,
+            I2cSpeed::Fast => 1,
+            I2cSpeed::FastPlus => 2,
+        }
+    }
+
+    impl HardwareInterface for FakeBus {
+        type Error = ErrorKind;
+
+        fn init(&mut self, config: &mut I2cConfig) -> Result<(), ErrorKind> {
+            self.configure_timing(config)
+        }
+        fn configure_timing(&mut self, config: &mut I2cConfig) -> Result<(), ErrorKind> {
+            self.timing = self.clk_src / config.speed as u32;
+            self.cache[index(config.speed)] = Some(self.timing);
+            Ok(())
+        }
+        fn clock_changed(&mut self, config: &mut I2cConfig) -> Result<(), ErrorKind> {
+            self.cache = [None; 3];
+            self.configure_timing(config)
+        }
+        fn set_speed(&mut self, config: &mut I2cConfig, speed: I2cSpeed) -> Result<(), ErrorKind> {
+            config.speed = speed;
+            match self.cache[index(speed)] {
+                Some(timing) => {
+                    self.timing = timing;
+                    Ok(())
+                }
+                None => self.configure_timing(config),
+            }
+        }
+        fn set_xfer_mode(&mut self, _: &mut I2cConfig, _: I2cXferMode) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+        fn override_xfer_mode(&mut self, _: Option<I2cXferMode>) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+        fn deinit(&mut self) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+        fn enable_interrupts(&mut self, _: u32) {}
+        fn clear_interrupts(&mut self, _: u32) {}
+        #[cfg(feature = "i2c_target")]
+        fn enable_slave_interrupts(&mut self, _: u32) {}
+        #[cfg(feature = "i2c_target")]
+        fn clear_slave_interrupts(&mut self, _: u32) {}
+        fn write(&mut self, _: SevenBitAddress, _: &[u8]) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+        fn read(&mut self, _: SevenBitAddress, _: &mut [u8]) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+        fn write_read(
+            &mut self,
+            _: SevenBitAddress,
+            _: &[u8],
+            _: &mut [u8],
+        ) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+        fn transaction_slice(
+            &mut self,
+            _: SevenBitAddress,
+            _: &mut [Operation<'_>],
+        ) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+        fn handle_interrupt(&mut self) {}
+        fn recover_bus(&mut self) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_clock_change_drops_cached_timing() {
+        let mut i2c = I2cController {
+            hardware: FakeBus {
+                clk_src: 50_000_000,
+                cache: [None; 3],
+                timing: 0,
+            },
+            config: I2cConfigBuilder::new().build(),
+            logger: NoOpLogger,
+        };
+        i2c.init().unwrap();
+        i2c.set_speed(I2cSpeed::Fast).unwrap();
+        assert_eq!(i2c.hardware.timing, 125);
+        i2c.set_speed(I2cSpeed::Standard).unwrap();
+
+        i2c.hardware.clk_src = 100_000_000;
+        i2c.reconfigure_for_clock(100_000_000).unwrap();
+        assert_eq!(i2c.hardware.timing, 1000);
+        // the Fast timing saved before the change must not come back
+        i2c.set_speed(I2cSpeed::Fast).unwrap();
+        assert_eq!(i2c.hardware.timing, 250);
+    }
+}
@@ -3,3 +3,6 @@
 pub mod ast1060_i2c;
 pub mod common;
 pub mod i2c_controller;
+pub mod scheduler;
+pub mod shared_bus;
+pub mod write_queue;
@@ -0,0 +1,236 @@
+// Licensed under the Apache-2.0 license
+
+//! Drives transactions on several I2C buses at once
+//!
+//! Each controller runs its transfer in hardware, so blocking on one bus
+//! at a time leaves the others idle. Start a transaction on every bus with
+//! [`Ast1060I2c::begin_transaction`], add the buses to an [`I2cScheduler`]
+//! and let it poll them round robin until all have finished. Buses are held
+//! as trait objects since each controller instance is its own type.
+
+use crate::common::{Deadline, Logger};
+use crate::i2c::ast1060_i2c::{Ast1060I2c, Error, Instance};
+use crate::i2c::i2c_controller::{HardwareInterface, I2cController};
+use proposed_traits::i2c_target::I2CTarget;
+
+/// A bus whose transaction can be advanced without blocking
+pub trait PolledTransfer {
+    fn poll_transaction(&mut self) -> nb::Result<(), Error>;
+    fn abort_transaction(&mut self);
+}
+
+impl<I2C: Instance, I2CT: I2CTarget, L: Logger> PolledTransfer for Ast1060I2c<'_, I2C, I2CT, L> {
+    fn poll_transaction(&mut self) -> nb::Result<(), Error> {
+        Ast1060I2c::poll_transaction(self)
+    }
+
+    fn abort_transaction(&mut self) {
+        Ast1060I2c::abort_transaction(self);
+    }
+}
+
+impl<T: PolledTransfer + ?Sized> PolledTransfer for &mut T {
+    fn poll_transaction(&mut self) -> nb::Result<(), Error> {
+        T::poll_transaction(self)
+    }
+
+    fn abort_transaction(&mut self) {
+        T::abort_transaction(self);
+    }
+}
+
+impl<H: HardwareInterface + PolledTransfer, L: Logger> PolledTransfer for I2cController<H, L> {
+    fn poll_transaction(&mut self) -> nb::Result<(), Error> {
+        self.hardware.poll_transaction()
+    }
+
+    fn abort_transaction(&mut self) {
+        self.hardware.abort_transaction();
+    }
+}
+
+/// Up to `N` buses with a transaction in flight
+pub struct I2cScheduler<'s, const N: usize> {
+    buses: [Option<&'s mut dyn PolledTransfer>; N],
+    results: [Option<Result<(), Error>>; N],
+}
+
+impl<const N: usize> Default for I2cScheduler<'_, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'s, const N: usize> I2cScheduler<'s, N> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            buses: [const { None }; N],
+            results: [None; N],
+        }
+    }
+
+    /// Adds a bus whose transaction has been started and returns its slot,
+    /// or gives the bus back when all slots are taken
+    pub fn add(
+        &mut self,
+        bus: &'s mut dyn PolledTransfer,
+    ) -> Result<usize, &'s mut dyn PolledTransfer> {
+        match self.buses.iter().position(Option::is_none) {
+            Some(slot) => {
+                self.buses[slot] = Some(bus);
+                self.results[slot] = None;
+                Ok(slot)
+            }
+            None => Err(bus),
+        }
+    }
+
+    /// Polls every unfinished transaction once and returns how many are
+    /// still running
+    pub fn poll(&mut self) -> usize {
+        let mut running = 0;
+        for (bus, result) in self.buses.iter_mut().zip(self.results.iter_mut()) {
+            let Some(bus) = bus else { continue };
+            if result.is_some() {
+                continue;
+            }
+            match bus.poll_transaction() {
+                Ok(()) => *result = Some(Ok(())),
+                Err(nb::Error::Other(e)) => *result = Some(Err(e)),
+                Err(nb::Error::WouldBlock) => running += 1,
+            }
+        }
+        running
+    }
+
+    /// Polls until every transaction has finished or `deadline` expires.
+    /// Those still running then are aborted and fail with `Timeout`.
+    pub fn run(&mut self, deadline: &Deadline) {
+        while self.poll() > 0 {
+            if deadline.is_expired() {
+                self.abort_running();
+                return;
+            }
+        }
+    }
+
+    fn abort_running(&mut self) {
+        for (bus, result) in self.buses.iter_mut().zip(self.results.iter_mut()) {
+            let Some(bus) = bus else { continue };
+            if result.is_none() {
+                bus.abort_transaction();
+                *result = Some(Err(Error::Timeout));
+            }
+        }
+    }
+
+    /// Outcome of the transaction in `slot`, `None` while it is running.
+    /// Read data is taken from the bus once the scheduler is dropped.
+    #[must_use]
+    pub fn result(&self, slot: usize) -> Option<Result<(), Error>> {
+        self.results.get(slot).copied().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{I2cScheduler, PolledTransfer};
+    use crate::common::{Deadline, TickClock};
+    use crate::i2c::ast1060_i2c::Error;
+    use core::time::Duration;
+
+    /// Finishes with `outcome` on its `polls`-th poll
+    struct FakeBus {
+        polls: u32,
+        outcome: Result<(), Error>,
+        polled: u32,
+        aborted: bool,
+    }
+
+    impl FakeBus {
+        fn new(polls: u32, outcome: Result<(), Error>) -> Self {
+            Self {
+                polls,
+                outcome,
+                polled: 0,
+                aborted: false,
+            }
+        }
+    }
+
+    impl PolledTransfer for FakeBus {
+        fn poll_transaction(&mut self) -> nb::Result<(), Error> {
+            self.polled += 1;
+            if self.polled < self.polls {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.outcome.map_err(nb::Error::Other)
+        }
+
+        fn abort_transaction(&mut self) {
+            self.aborted = true;
+        }
+    }
+
+    fn fixed_clock() -> u32 {
+        0
+    }
+
+    fn clock() -> TickClock {
+        TickClock {
+            now: fixed_clock,
+            ticks_per_us: 1,
+        }
+    }
+
+    #[test]
+    fn test_polls_buses_together() {
+        let mut a = FakeBus::new(1, Ok(()));
+        let mut b = FakeBus::new(3, Err(Error::Timeout));
+        let mut c = FakeBus::new(5, Ok(()));
+        {
+            let mut sched = I2cScheduler::<4>::new();
+            assert_eq!(sched.add(&mut a).ok(), Some(0));
+            assert_eq!(sched.add(&mut b).ok(), Some(1));
+            assert_eq!(sched.add(&mut c).ok(), Some(2));
+
+            assert_eq!(sched.poll(), 2);
+            assert_eq!(sched.result(0), Some(Ok(())));
+            assert_eq!(sched.result(1), None);
+
+            sched.run(&Deadline::new(clock(), Duration::from_secs(1)));
+            assert_eq!(sched.result(1), Some(Err(Error::Timeout)));
+            assert_eq!(sched.result(2), Some(Ok(())));
+            assert_eq!(sched.result(3), None);
+        }
+        // finished buses are not polled again
+        assert_eq!((a.polled, b.polled, c.polled), (1, 3, 5));
+        assert!(!a.aborted && !b.aborted && !c.aborted);
+    }
+
+    #[test]
+    fn test_full_scheduler_returns_bus() {
+        let mut a = FakeBus::new(1, Ok(()));
+        let mut b = FakeBus::new(1, Ok(()));
+        let mut sched = I2cScheduler::<1>::new();
+        assert!(sched.add(&mut a).is_ok());
+        assert!(sched.add(&mut b).is_err());
+    }
+
+    #[test]
+    fn test_deadline_aborts_stragglers() {
+        let mut done = FakeBus::new(1, Ok(()));
+        let mut stuck = FakeBus::new(u32::MAX, Ok(()));
+        {
+            let mut sched = I2cScheduler::<2>::new();
+            assert!(sched.add(&mut done).is_ok());
+            assert!(sched.add(&mut stuck).is_ok());
+            sched.run(&Deadline::new(clock(), Duration::ZERO));
+            assert_eq!(sched.result(0), Some(Ok(())));
+            assert_eq!(sched.result(1), Some(Err(Error::Timeout)));
+        }
+        assert!(stuck.aborted);
+        assert!(!done.aborted);
+    }
+}
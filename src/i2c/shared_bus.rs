@@ -0,0 +1,79 @@
+// Licensed under the Apache-2.0 license
+
+//! Sharing one I2C bus between several device drivers
+//!
+//! Drivers written against `embedded_hal::i2c::I2c` each want their own
+//! `&mut impl I2c`. [`SharedI2cBus`] owns the bus (typically an
+//! `I2cController`) in a `RefCell` and hands out [`SharedI2cDevice`] proxies
+//! that borrow it for the duration of a single transaction, so each driver
+//! gets its own handle while bus accesses never overlap.
+//!
+//! The wrapper is not `Sync`; all proxies must be used from the same
+//! execution context. Using a proxy from inside another proxy's transaction
+//! (e.g. from an interrupt handler) panics on the `RefCell` borrow.
+
+use core::cell::RefCell;
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+
+pub struct SharedI2cBus<BUS: I2c> {
+    bus: RefCell<BUS>,
+}
+
+impl<BUS: I2c> SharedI2cBus<BUS> {
+    pub fn new(bus: BUS) -> Self {
+        Self {
+            bus: RefCell::new(bus),
+        }
+    }
+
+    /// Creates a new device handle on the shared bus
+    pub fn acquire(&self) -> SharedI2cDevice<'_, BUS> {
+        SharedI2cDevice { bus: &self.bus }
+    }
+
+    /// Runs `f` with exclusive access to the underlying bus
+    pub fn with_bus<R>(&self, f: impl FnOnce(&mut BUS) -> R) -> R {
+        f(&mut self.bus.borrow_mut())
+    }
+
+    /// Returns the underlying bus once all device handles are dropped
+    pub fn into_inner(self) -> BUS {
+        self.bus.into_inner()
+    }
+}
+
+/// Per-driver handle on a [`SharedI2cBus`]
+pub struct SharedI2cDevice<'a, BUS: I2c> {
+    bus: &'a RefCell<BUS>,
+}
+
+impl<BUS: I2c> ErrorType for SharedI2cDevice<'_, BUS> {
+    type Error = BUS::Error;
+}
+
+impl<BUS: I2c> I2c for SharedI2cDevice<'_, BUS> {
+    fn read(&mut self, addr: SevenBitAddress, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.bus.borrow_mut().read(addr, buffer)
+    }
+
+    fn write(&mut self, addr: SevenBitAddress, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.bus.borrow_mut().write(addr, bytes)
+    }
+
+    fn write_read(
+        &mut self,
+        addr: SevenBitAddress,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.bus.borrow_mut().write_read(addr, bytes, buffer)
+    }
+
+    fn transaction(
+        &mut self,
+        addr: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.bus.borrow_mut().transaction(addr, operations)
+    }
+}
@@ -0,0 +1,435 @@
+// Licensed under the Apache-2.0 license
+
+//! Fire-and-forget master writes
+//!
+//! Logging and telemetry writes to a downstream device should not hold up
+//! the main loop. [`QueuedI2c`] owns a bus and keeps up to `DEPTH` writes
+//! of at most `LEN` bytes waiting for it. [`QueuedI2c::enqueue_write`]
+//! copies the data and returns a [`Ticket`]; [`QueuedI2c::poll`], called
+//! from the loop or the bus interrupt, runs the writes one after another
+//! in the order they were queued.
+//!
+//! A write that fails with the bus needing recovery fails every write
+//! queued behind it with `Error::Bus`, rather than starting them on a bus
+//! in an unknown state. The blocking `I2c` methods finish the queue first,
+//! so they stay ordered after the writes queued before them.
+
+use crate::common::Logger;
+use crate::i2c::ast1060_i2c::{Ast1060I2c, Error, Instance, MasterState, Transfer};
+use crate::i2c::i2c_controller::{HardwareInterface, I2cController};
+use crate::i2c::scheduler::PolledTransfer;
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+use heapless::{Deque, Vec};
+use proposed_traits::i2c_target::I2CTarget;
+
+/// A bus that can start a write without waiting for it
+pub trait QueuedTransfer: PolledTransfer {
+    fn begin_write(&mut self, addr: SevenBitAddress, bytes: &[u8]) -> Result<(), Error>;
+    /// Whether the master must be recovered before the next message
+    fn needs_recovery(&self) -> bool;
+}
+
+impl<I2C: Instance, I2CT: I2CTarget, L: Logger> QueuedTransfer for Ast1060I2c<'_, I2C, I2CT, L> {
+    fn begin_write(&mut self, addr: SevenBitAddress, bytes: &[u8]) -> Result<(), Error> {
+        self.begin_transaction(addr, Transfer::Write(bytes))
+    }
+
+    fn needs_recovery(&self) -> bool {
+        self.master_state() == MasterState::NeedsRecovery
+    }
+}
+
+impl<T: QueuedTransfer + ?Sized> QueuedTransfer for &mut T {
+    fn begin_write(&mut self, addr: SevenBitAddress, bytes: &[u8]) -> Result<(), Error> {
+        T::begin_write(self, addr, bytes)
+    }
+
+    fn needs_recovery(&self) -> bool {
+        T::needs_recovery(self)
+    }
+}
+
+impl<H: HardwareInterface + QueuedTransfer, L: Logger> QueuedTransfer for I2cController<H, L> {
+    fn begin_write(&mut self, addr: SevenBitAddress, bytes: &[u8]) -> Result<(), Error> {
+        self.hardware.begin_write(addr, bytes)
+    }
+
+    fn needs_recovery(&self) -> bool {
+        self.hardware.needs_recovery()
+    }
+}
+
+/// Identifies a queued write
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ticket(u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnqueueError {
+    /// `DEPTH` writes are already waiting
+    QueueFull,
+    /// The data is longer than `LEN`
+    TooLong,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteStatus {
+    Queued,
+    InFlight,
+    Done(Result<(), Error>),
+}
+
+/// Called with each write's outcome as it finishes
+pub type WriteCallback = fn(Ticket, Result<(), Error>);
+
+struct Pending<const LEN: usize> {
+    ticket: Ticket,
+    addr: SevenBitAddress,
+    data: Vec<u8, LEN>,
+}
+
+/// Whether a failed write leaves the bus unfit for the writes behind it
+fn ends_queue(error: Error, needs_recovery: bool) -> bool {
+    needs_recovery || matches!(error, Error::Bus | Error::BusRecoveryFailed)
+}
+
+/// Bus with a queue of up to `DEPTH` pending writes of up to `LEN` bytes
+pub struct QueuedI2c<B, const DEPTH: usize, const LEN: usize> {
+    bus: B,
+    pending: Deque<Pending<LEN>, DEPTH>,
+    /// The write at the front of `pending` has been started
+    in_flight: bool,
+    /// Outcomes of the last `DEPTH` finished writes, oldest first
+    done: Deque<(Ticket, Result<(), Error>), DEPTH>,
+    next_ticket: u32,
+    callback: Option<WriteCallback>,
+}
+
+impl<B, const DEPTH: usize, const LEN: usize> QueuedI2c<B, DEPTH, LEN>
+where
+    B: QueuedTransfer,
+{
+    pub fn new(bus: B) -> Self {
+        Self {
+            bus,
+            pending: Deque::new(),
+            in_flight: false,
+            done: Deque::new(),
+            next_ticket: 0,
+            callback: None,
+        }
+    }
+
+    /// Queues a write of `data` to `addr`; it starts from a later
+    /// [`Self::poll`]
+    pub fn enqueue_write(
+        &mut self,
+        addr: SevenBitAddress,
+        data: &[u8],
+    ) -> Result<Ticket, EnqueueError> {
+        let data = Vec::from_slice(data).map_err(|()| EnqueueError::TooLong)?;
+        let ticket = Ticket(self.next_ticket);
+        self.pending
+            .push_back(Pending { ticket, addr, data })
+            .map_err(|_| EnqueueError::QueueFull)?;
+        self.next_ticket = self.next_ticket.wrapping_add(1);
+        Ok(ticket)
+    }
+
+    /// Status of `ticket`, or `None` once its outcome has been pushed out
+    /// by `DEPTH` later ones
+    #[must_use]
+    pub fn status(&self, ticket: Ticket) -> Option<WriteStatus> {
+        if let Some(&(_, result)) = self.done.iter().find(|(t, _)| *t == ticket) {
+            return Some(WriteStatus::Done(result));
+        }
+        let position = self.pending.iter().position(|p| p.ticket == ticket)?;
+        if position == 0 && self.in_flight {
+            Some(WriteStatus::InFlight)
+        } else {
+            Some(WriteStatus::Queued)
+        }
+    }
+
+    pub fn set_callback(&mut self, callback: Option<WriteCallback>) {
+        self.callback = callback;
+    }
+
+    /// Writes not finished yet, including the one in flight
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Advances the write in flight and starts the next ones as earlier
+    /// ones finish. Returns how many writes are left.
+    pub fn poll(&mut self) -> usize {
+        loop {
+            if self.in_flight {
+                match self.bus.poll_transaction() {
+                    Err(nb::Error::WouldBlock) => break,
+                    Ok(()) => self.finish(Ok(())),
+                    Err(nb::Error::Other(e)) => self.finish(Err(e)),
+                }
+                continue;
+            }
+            let Some(next) = self.pending.front() else {
+                break;
+            };
+            match self.bus.begin_write(next.addr, &next.data) {
+                Ok(()) => self.in_flight = true,
+                // the bus is running a transfer started elsewhere
+                Err(Error::Busy) => break,
+                Err(e) => self.finish(Err(e)),
+            }
+        }
+        self.pending.len()
+    }
+
+    /// Polls until the queue is empty
+    pub fn flush(&mut self) {
+        while self.poll() > 0 {}
+    }
+
+    /// Runs `f` on the bus once the queue is empty
+    pub fn with_bus<R>(&mut self, f: impl FnOnce(&mut B) -> R) -> R {
+        self.flush();
+        f(&mut self.bus)
+    }
+
+    /// Finishes the queued writes and returns the bus
+    pub fn into_inner(mut self) -> B {
+        self.flush();
+        self.bus
+    }
+
+    /// Records the outcome of the front write, and fails the rest too when
+    /// it left the bus needing recovery
+    fn finish(&mut self, result: Result<(), Error>) {
+        self.in_flight = false;
+        let Some(front) = self.pending.pop_front() else {
+            return;
+        };
+        self.complete(front.ticket, result);
+        if let Err(e) = result {
+            if ends_queue(e, self.bus.needs_recovery()) {
+                while let Some(dropped) = self.pending.pop_front() {
+                    self.complete(dropped.ticket, Err(Error::Bus));
+                }
+            }
+        }
+    }
+
+    fn complete(&mut self, ticket: Ticket, result: Result<(), Error>) {
+        if self.done.is_full() {
+            self.done.pop_front();
+        }
+        let _ = self.done.push_back((ticket, result));
+        if let Some(callback) = self.callback {
+            callback(ticket, result);
+        }
+    }
+}
+
+#[cfg(feature = "rt")]
+impl<B, const DEPTH: usize, const LEN: usize> crate::interrupts::IrqHandler
+    for QueuedI2c<B, DEPTH, LEN>
+where
+    B: QueuedTransfer + crate::interrupts::IrqHandler,
+{
+    fn irq_number(&self) -> ast1060_pac::Interrupt {
+        self.bus.irq_number()
+    }
+
+    /// The completion interrupt of a queued write moves the queue on; with
+    /// none in flight the bus handles the interrupt itself. While a write
+    /// is in flight target side interrupts are not handled, so a bus that
+    /// also acts as a target should be polled from the loop instead.
+    fn on_irq(&mut self) {
+        if self.in_flight {
+            self.poll();
+        } else {
+            self.bus.on_irq();
+        }
+    }
+}
+
+impl<B, const DEPTH: usize, const LEN: usize> ErrorType for QueuedI2c<B, DEPTH, LEN>
+where
+    B: QueuedTransfer + I2c<Error = Error>,
+{
+    type Error = Error;
+}
+
+impl<B, const DEPTH: usize, const LEN: usize> I2c for QueuedI2c<B, DEPTH, LEN>
+where
+    B: QueuedTransfer + I2c<Error = Error>,
+{
+    fn read(&mut self, addr: SevenBitAddress, buffer: &mut [u8]) -> Result<(), Error> {
+        self.flush();
+        self.bus.read(addr, buffer)
+    }
+
+    fn write(&mut self, addr: SevenBitAddress, bytes: &[u8]) -> Result<(), Error> {
+        self.flush();
+        self.bus.write(addr, bytes)
+    }
+
+    fn write_read(
+        &mut self,
+        addr: SevenBitAddress,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        self.flush();
+        self.bus.write_read(addr, bytes, buffer)
+    }
+
+    fn transaction(
+        &mut self,
+        addr: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        self.flush();
+        self.bus.transaction(addr, operations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EnqueueError, QueuedI2c, QueuedTransfer, Ticket, WriteStatus};
+    use crate::i2c::ast1060_i2c::Error;
+    use crate::i2c::scheduler::PolledTransfer;
+    use embedded_hal::i2c::{NoAcknowledgeSource, SevenBitAddress};
+    use std::vec::Vec;
+
+    const ABSENT: SevenBitAddress = 0x7f;
+
+    /// Takes two polls per write; NACKs `ABSENT`, and with `wedge_on_nack`
+    /// is left needing recovery by it
+    #[derive(Default)]
+    struct FakeBus {
+        started: Vec<(SevenBitAddress, Vec<u8>)>,
+        current: Option<SevenBitAddress>,
+        polled: u32,
+        wedge_on_nack: bool,
+        wedged: bool,
+    }
+
+    impl PolledTransfer for FakeBus {
+        fn poll_transaction(&mut self) -> nb::Result<(), Error> {
+            let addr = self.current.ok_or(nb::Error::Other(Error::Invalid))?;
+            self.polled += 1;
+            if self.polled % 2 == 1 {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.current = None;
+            if addr == ABSENT {
+                self.wedged = self.wedge_on_nack;
+                return Err(nb::Error::Other(Error::NoAcknowledge(
+                    NoAcknowledgeSource::Address,
+                )));
+            }
+            Ok(())
+        }
+
+        fn abort_transaction(&mut self) {
+            self.current = None;
+        }
+    }
+
+    impl QueuedTransfer for FakeBus {
+        fn begin_write(&mut self, addr: SevenBitAddress, bytes: &[u8]) -> Result<(), Error> {
+            if self.current.is_some() {
+                return Err(Error::Busy);
+            }
+            self.started.push((addr, bytes.to_vec()));
+            self.current = Some(addr);
+            Ok(())
+        }
+
+        fn needs_recovery(&self) -> bool {
+            self.wedged
+        }
+    }
+
+    fn queue_five(queue: &mut QueuedI2c<FakeBus, 8, 4>) -> Vec<Ticket> {
+        [0x10, 0x11, ABSENT, 0x12, 0x13]
+            .iter()
+            .map(|&addr| queue.enqueue_write(addr, &[addr]).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_fifo_with_absent_target() {
+        let mut queue = QueuedI2c::<_, 8, 4>::new(FakeBus::default());
+        let tickets = queue_five(&mut queue);
+        assert_eq!(queue.status(tickets[0]), Some(WriteStatus::Queued));
+
+        assert_eq!(queue.poll(), 5);
+        assert_eq!(queue.status(tickets[0]), Some(WriteStatus::InFlight));
+        assert_eq!(queue.status(tickets[1]), Some(WriteStatus::Queued));
+        queue.flush();
+
+        let nack = Err(Error::NoAcknowledge(NoAcknowledgeSource::Address));
+        let outcomes: Vec<_> = tickets.iter().map(|&t| queue.status(t)).collect();
+        assert_eq!(
+            outcomes,
+            [Ok(()), Ok(()), nack, Ok(()), Ok(())].map(|r| Some(WriteStatus::Done(r)))
+        );
+        let order: Vec<_> = queue.into_inner().started.iter().map(|s| s.0).collect();
+        assert_eq!(order, [0x10, 0x11, ABSENT, 0x12, 0x13]);
+    }
+
+    #[test]
+    fn test_recovery_drops_the_rest() {
+        let bus = FakeBus {
+            wedge_on_nack: true,
+            ..FakeBus::default()
+        };
+        let mut queue = QueuedI2c::<_, 8, 4>::new(bus);
+        let tickets = queue_five(&mut queue);
+        queue.flush();
+
+        assert_eq!(queue.status(tickets[1]), Some(WriteStatus::Done(Ok(()))));
+        assert!(matches!(
+            queue.status(tickets[2]),
+            Some(WriteStatus::Done(Err(Error::NoAcknowledge(_))))
+        ));
+        assert_eq!(
+            queue.status(tickets[3]),
+            Some(WriteStatus::Done(Err(Error::Bus)))
+        );
+        assert_eq!(
+            queue.status(tickets[4]),
+            Some(WriteStatus::Done(Err(Error::Bus)))
+        );
+        // the dropped writes never reached the bus
+        assert_eq!(queue.into_inner().started.len(), 3);
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut queue = QueuedI2c::<_, 2, 4>::new(FakeBus::default());
+        assert_eq!(
+            queue.enqueue_write(0x10, &[0; 5]),
+            Err(EnqueueError::TooLong)
+        );
+        let first = queue.enqueue_write(0x10, &[1]).unwrap();
+        queue.enqueue_write(0x11, &[2]).unwrap();
+        assert_eq!(
+            queue.enqueue_write(0x12, &[3]),
+            Err(EnqueueError::QueueFull)
+        );
+        queue.flush();
+        // outcomes are kept for the last DEPTH writes only
+        let third = queue.enqueue_write(0x12, &[3]).unwrap();
+        queue.flush();
+        assert_eq!(queue.status(first), None);
+        assert_eq!(queue.status(third), Some(WriteStatus::Done(Ok(()))));
+    }
+}
@@ -0,0 +1,143 @@
+// Licensed under the Apache-2.0 license
+
+//! Registration-based peripheral interrupt dispatch
+//!
+//! Drivers implement [`IrqHandler`]; the application registers each one once
+//! with [`register`], which sets the NVIC priority and unmasks the line. The
+//! vector entries for the supported interrupts (currently `uart`, `timer`,
+//! `gpio`, `hace` and I2C buses 1 to 13) are provided here and forward to the
+//! registered handler; bus 0's vector is still defined by the I2C target
+//! test. Interrupts that fire with nothing registered are counted and can be
+//! read back with [`spurious_count`].
+//!
+//! The table lock is only held to look a handler up and to put it back.
+//! Handlers themselves run with interrupts enabled, so a higher priority
+//! interrupt can preempt them.
+
+use ast1060_pac::Interrupt;
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicU32, Ordering};
+use cortex_m::interrupt::{InterruptNumber, Mutex};
+use cortex_m::peripheral::NVIC;
+
+/// Maximum number of simultaneously registered handlers
+pub const MAX_IRQ_HANDLERS: usize = 16;
+
+/// A handler moves into the table for good and runs in interrupt context,
+/// hence `Send`
+pub trait IrqHandler: Send {
+    fn irq_number(&self) -> Interrupt;
+    fn on_irq(&mut self);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrqError {
+    TableFull,
+    AlreadyRegistered,
+    NotRegistered,
+}
+
+struct Slot {
+    irq: u16,
+    /// Taken out by `dispatch` while the handler runs
+    handler: Option<&'static mut dyn IrqHandler>,
+}
+
+static HANDLERS: Mutex<RefCell<[Option<Slot>; MAX_IRQ_HANDLERS]>> =
+    Mutex::new(RefCell::new([const { None }; MAX_IRQ_HANDLERS]));
+static SPURIOUS: AtomicU32 = AtomicU32::new(0);
+
+/// Registers `handler` for its interrupt at `priority` and unmasks the line.
+pub fn register(handler: &'static mut dyn IrqHandler, priority: u8) -> Result<(), IrqError> {
+    let irq = handler.irq_number();
+    cortex_m::interrupt::free(|cs| {
+        let mut table = HANDLERS.borrow(cs).borrow_mut();
+        if table.iter().flatten().any(|slot| slot.irq == irq.number()) {
+            return Err(IrqError::AlreadyRegistered);
+        }
+        let free = table
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(IrqError::TableFull)?;
+        *free = Some(Slot {
+            irq: irq.number(),
+            handler: Some(handler),
+        });
+        Ok(())
+    })?;
+
+    unsafe {
+        let mut cp = cortex_m::Peripherals::steal();
+        cp.NVIC.set_priority(irq, priority);
+        NVIC::unmask(irq);
+    }
+    Ok(())
+}
+
+/// Masks `irq` and removes its handler from the table.
+///
+/// A call from a higher priority interrupt while the handler runs lets it
+/// finish; it is not put back.
+pub fn unregister(irq: Interrupt) -> Result<(), IrqError> {
+    NVIC::mask(irq);
+    cortex_m::interrupt::free(|cs| {
+        let mut table = HANDLERS.borrow(cs).borrow_mut();
+        let slot = table
+            .iter_mut()
+            .find(|slot| slot.as_ref().is_some_and(|s| s.irq == irq.number()))
+            .ok_or(IrqError::NotRegistered)?;
+        *slot = None;
+        Ok(())
+    })
+}
+
+/// Number of interrupts taken with no handler registered
+#[must_use]
+pub fn spurious_count() -> u32 {
+    SPURIOUS.load(Ordering::Relaxed)
+}
+
+/// Calls the handler registered for `irq`, or counts the interrupt as spurious.
+pub fn dispatch(irq: Interrupt) {
+    let handler = cortex_m::interrupt::free(|cs| {
+        let mut table = HANDLERS.borrow(cs).borrow_mut();
+        table
+            .iter_mut()
+            .flatten()
+            .find(|slot| slot.irq == irq.number())
+            .and_then(|slot| slot.handler.take())
+    });
+    let Some(handler) = handler else {
+        SPURIOUS.fetch_add(1, Ordering::Relaxed);
+        return;
+    };
+
+    handler.on_irq();
+
+    cortex_m::interrupt::free(|cs| {
+        let mut table = HANDLERS.borrow(cs).borrow_mut();
+        if let Some(slot) = table
+            .iter_mut()
+            .flatten()
+            .find(|slot| slot.irq == irq.number() && slot.handler.is_none())
+        {
+            slot.handler = Some(handler);
+        }
+    });
+}
+
+macro_rules! irq_trampoline {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[no_mangle]
+            pub extern "C" fn $name() {
+                dispatch(Interrupt::$name);
+            }
+        )*
+    };
+}
+
+irq_trampoline!(
+    uart, timer, gpio, hace, i2c1, i2c2, i2c3, i2c4, i2c5, i2c6, i2c7, i2c8, i2c9, i2c10, i2c11,
+    i2c12, i2c13,
+);
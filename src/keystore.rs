@@ -0,0 +1,472 @@
+// Licensed under the Apache-2.0 license
+
+//! Key storage referenced by handle
+//!
+//! A [`KeyStore`] owns a fixed table of key slots, so verification code can
+//! pass a small [`KeyHandle`] around instead of copying key bytes through
+//! every call. Slots hold public keys or shared HMAC keys. Handles are
+//! reference counted: [`KeyStore::retain`] hands out another reference and
+//! [`KeyStore::release`] drops one, wiping the slot when the last reference
+//! goes. [`KeyStore::destroy`] wipes a key at once,
+//! e.g. on revocation, and invalidates every outstanding handle to it.
+//!
+//! Each slot carries a generation number that changes whenever the slot is
+//! freed, so a handle kept past `destroy` is rejected even after the slot
+//! has been reused for another key.
+//!
+//! Stores belong in the `.keystore` section, which `memory.x` sets aside in
+//! non-cacheable SRAM, away from stacks and ordinary statics. The runtime
+//! does not initialize it, so a store there starts out through
+//! [`KeyStore::init_in`]:
+//!
+//! ```ignore
+//! #[link_section = ".keystore"]
+//! static mut KEYS: MaybeUninit<KeyStore<4>> = MaybeUninit::uninit();
+//!
+//! let keys = KeyStore::init_in(unsafe { &mut *core::ptr::addr_of_mut!(KEYS) });
+//! ```
+
+use crate::ecdsa::{PublicKey, Scalar48};
+use crate::rsa::RsaPublicKey;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Bytes of key material per slot: an RSA-4096 modulus plus an exponent of
+/// up to the same size
+pub const KEY_SLOT_SIZE: usize = 1024;
+
+const RSA_MAX_MODULUS_LEN: usize = 512;
+const ECDSA_P384_POINT_LEN: usize = 2 * Scalar48::LEN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStoreError {
+    /// Every slot holds a key
+    Full,
+    /// The handle does not refer to a live key
+    InvalidHandle,
+    /// The key exists but is not of the requested type
+    WrongKeyType,
+    /// The encoding could not be parsed
+    Malformed,
+    /// The key is larger than a slot can hold
+    TooLarge,
+    /// The reference count would overflow
+    TooManyReferences,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    RsaPublic,
+    EcdsaP384Public,
+    /// Shared secret for HMAC-SHA-256
+    HmacSha256,
+}
+
+/// Reference to a key in a [`KeyStore`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyHandle {
+    slot: u8,
+    generation: u16,
+}
+
+struct KeySlot {
+    kind: Option<KeyKind>,
+    generation: u16,
+    refs: u16,
+    // RSA: modulus then exponent; ECDSA: qx then qy
+    data: [u8; KEY_SLOT_SIZE],
+    first_len: usize,
+    second_len: usize,
+}
+
+impl KeySlot {
+    const EMPTY: Self = Self {
+        kind: None,
+        generation: 0,
+        refs: 0,
+        data: [0; KEY_SLOT_SIZE],
+        first_len: 0,
+        second_len: 0,
+    };
+
+    fn wipe(&mut self) {
+        for b in &mut self.data {
+            unsafe { core::ptr::write_volatile(b, 0) };
+        }
+        compiler_fence(Ordering::SeqCst);
+        self.kind = None;
+        self.refs = 0;
+        self.first_len = 0;
+        self.second_len = 0;
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn first(&self) -> &[u8] {
+        &self.data[..self.first_len]
+    }
+
+    fn second(&self) -> &[u8] {
+        &self.data[self.first_len..self.first_len + self.second_len]
+    }
+}
+
+/// Fixed capacity table of keys
+pub struct KeyStore<const N: usize> {
+    slots: [KeySlot; N],
+}
+
+impl<const N: usize> Default for KeyStore<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> KeyStore<N> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            slots: [KeySlot::EMPTY; N],
+        }
+    }
+
+    /// Sets up an empty store in `place`, e.g. a static in the uninitialized
+    /// `.keystore` section
+    pub fn init_in(place: &mut MaybeUninit<Self>) -> &mut Self {
+        place.write(Self::new())
+    }
+
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Number of slots holding a key
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|s| s.kind.is_some()).count()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Imports a PKCS#1 `RSAPublicKey` (DER `SEQUENCE { n, e }`)
+    pub fn import_rsa_public(&mut self, der: &[u8]) -> Result<KeyHandle, KeyStoreError> {
+        let (seq, rest) = der_element(der, DER_SEQUENCE)?;
+        if !rest.is_empty() {
+            return Err(KeyStoreError::Malformed);
+        }
+        let (n, seq) = der_element(seq, DER_INTEGER)?;
+        let (e, seq) = der_element(seq, DER_INTEGER)?;
+        if !seq.is_empty() {
+            return Err(KeyStoreError::Malformed);
+        }
+        let (n, e) = (strip_leading_zeros(n), strip_leading_zeros(e));
+        if n.is_empty() || e.is_empty() {
+            return Err(KeyStoreError::Malformed);
+        }
+        if n.len() > RSA_MAX_MODULUS_LEN || n.len() + e.len() > KEY_SLOT_SIZE {
+            return Err(KeyStoreError::TooLarge);
+        }
+        self.insert(KeyKind::RsaPublic, n, e)
+    }
+
+    /// Imports a raw secp384r1 point: `qx || qy`, optionally preceded by the
+    /// SEC1 uncompressed marker `0x04`
+    pub fn import_ecdsa_public(&mut self, raw: &[u8]) -> Result<KeyHandle, KeyStoreError> {
+        let point = match raw {
+            [0x04, point @ ..] if point.len() == ECDSA_P384_POINT_LEN => point,
+            point if point.len() == ECDSA_P384_POINT_LEN => point,
+            _ => return Err(KeyStoreError::Malformed),
+        };
+        let (qx, qy) = point.split_at(Scalar48::LEN);
+        self.insert(KeyKind::EcdsaP384Public, qx, qy)
+    }
+
+    /// Imports a shared 32-byte HMAC-SHA-256 key
+    pub fn import_hmac_sha256(&mut self, key: &[u8; 32]) -> Result<KeyHandle, KeyStoreError> {
+        self.insert(KeyKind::HmacSha256, key, &[])
+    }
+
+    fn insert(
+        &mut self,
+        kind: KeyKind,
+        first: &[u8],
+        second: &[u8],
+    ) -> Result<KeyHandle, KeyStoreError> {
+        let (index, slot) = self
+            .slots
+            .iter_mut()
+            .enumerate()
+            .find(|(_, s)| s.kind.is_none())
+            .ok_or(KeyStoreError::Full)?;
+        slot.data[..first.len()].copy_from_slice(first);
+        slot.data[first.len()..first.len() + second.len()].copy_from_slice(second);
+        slot.first_len = first.len();
+        slot.second_len = second.len();
+        slot.kind = Some(kind);
+        slot.refs = 1;
+        Ok(KeyHandle {
+            slot: u8::try_from(index).map_err(|_| KeyStoreError::Full)?,
+            generation: slot.generation,
+        })
+    }
+
+    fn slot(&self, handle: KeyHandle) -> Result<&KeySlot, KeyStoreError> {
+        self.slots
+            .get(usize::from(handle.slot))
+            .filter(|s| s.kind.is_some() && s.generation == handle.generation)
+            .ok_or(KeyStoreError::InvalidHandle)
+    }
+
+    fn slot_mut(&mut self, handle: KeyHandle) -> Result<&mut KeySlot, KeyStoreError> {
+        self.slots
+            .get_mut(usize::from(handle.slot))
+            .filter(|s| s.kind.is_some() && s.generation == handle.generation)
+            .ok_or(KeyStoreError::InvalidHandle)
+    }
+
+    pub fn kind(&self, handle: KeyHandle) -> Result<KeyKind, KeyStoreError> {
+        self.slot(handle)?.kind.ok_or(KeyStoreError::InvalidHandle)
+    }
+
+    /// Takes another reference to the key
+    pub fn retain(&mut self, handle: KeyHandle) -> Result<KeyHandle, KeyStoreError> {
+        let slot = self.slot_mut(handle)?;
+        slot.refs = slot
+            .refs
+            .checked_add(1)
+            .ok_or(KeyStoreError::TooManyReferences)?;
+        Ok(handle)
+    }
+
+    /// Drops one reference; the key is wiped when the last one goes
+    pub fn release(&mut self, handle: KeyHandle) -> Result<(), KeyStoreError> {
+        let slot = self.slot_mut(handle)?;
+        slot.refs -= 1;
+        if slot.refs == 0 {
+            slot.wipe();
+        }
+        Ok(())
+    }
+
+    /// Wipes the key regardless of outstanding references, invalidating
+    /// every handle to it
+    pub fn destroy(&mut self, handle: KeyHandle) -> Result<(), KeyStoreError> {
+        self.slot_mut(handle)?.wipe();
+        Ok(())
+    }
+
+    /// Borrows an RSA public key for the verify APIs
+    pub fn rsa_public(&self, handle: KeyHandle) -> Result<RsaPublicKey<'_>, KeyStoreError> {
+        let slot = self.slot(handle)?;
+        if slot.kind != Some(KeyKind::RsaPublic) {
+            return Err(KeyStoreError::WrongKeyType);
+        }
+        Ok(RsaPublicKey {
+            m: slot.first(),
+            e: slot.second(),
+            m_bits: bit_len(slot.first()),
+            e_bits: bit_len(slot.second()),
+        })
+    }
+
+    /// Copies out a secp384r1 public key for the verify APIs
+    pub fn ecdsa_public(&self, handle: KeyHandle) -> Result<PublicKey, KeyStoreError> {
+        let slot = self.slot(handle)?;
+        if slot.kind != Some(KeyKind::EcdsaP384Public) {
+            return Err(KeyStoreError::WrongKeyType);
+        }
+        let mut qx = [0u8; Scalar48::LEN];
+        let mut qy = [0u8; Scalar48::LEN];
+        qx.copy_from_slice(slot.first());
+        qy.copy_from_slice(slot.second());
+        Ok(PublicKey {
+            qx: Scalar48(qx),
+            qy: Scalar48(qy),
+        })
+    }
+}
+
+/// Lookup of shared HMAC keys, so [`crate::hmac::KeyRef`] can refer to a
+/// key in a [`KeyStore`] of any size
+pub trait HmacKeyLookup {
+    /// Borrows a shared HMAC-SHA-256 key
+    fn hmac_sha256_key(&self, handle: KeyHandle) -> Result<&[u8], KeyStoreError>;
+}
+
+impl<const N: usize> HmacKeyLookup for KeyStore<N> {
+    fn hmac_sha256_key(&self, handle: KeyHandle) -> Result<&[u8], KeyStoreError> {
+        let slot = self.slot(handle)?;
+        if slot.kind != Some(KeyKind::HmacSha256) {
+            return Err(KeyStoreError::WrongKeyType);
+        }
+        Ok(slot.first())
+    }
+}
+
+const DER_INTEGER: u8 = 0x02;
+const DER_SEQUENCE: u8 = 0x30;
+
+/// Splits one DER element with tag `tag` off the front of `der`, returning
+/// its contents and the remaining bytes
+fn der_element(der: &[u8], tag: u8) -> Result<(&[u8], &[u8]), KeyStoreError> {
+    let (&first, rest) = der.split_first().ok_or(KeyStoreError::Malformed)?;
+    if first != tag {
+        return Err(KeyStoreError::Malformed);
+    }
+    let (&len_byte, mut rest) = rest.split_first().ok_or(KeyStoreError::Malformed)?;
+    let len = if len_byte < 0x80 {
+        usize::from(len_byte)
+    } else {
+        let count = usize::from(len_byte & 0x7f);
+        if count == 0 || count > 2 || rest.len() < count {
+            return Err(KeyStoreError::Malformed);
+        }
+        let (len_bytes, tail) = rest.split_at(count);
+        rest = tail;
+        len_bytes
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | usize::from(b))
+    };
+    if rest.len() < len {
+        return Err(KeyStoreError::Malformed);
+    }
+    Ok(rest.split_at(len))
+}
+
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// Bit length of a big-endian integer without leading zero bytes
+pub(crate) fn bit_len(bytes: &[u8]) -> u32 {
+    match bytes.first() {
+        Some(&msb) => u32::try_from(bytes.len() * 8).unwrap_or(u32::MAX) - msb.leading_zeros(),
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HmacKeyLookup, KeyKind, KeyStore, KeyStoreError};
+
+    // SEQUENCE { INTEGER 0x00c3..(129 bytes with sign byte), INTEGER 65537 }
+    fn rsa_der() -> Vec<u8> {
+        let mut n = vec![0x00, 0xc3];
+        n.extend((0..127).map(|i| i as u8 | 1));
+        let mut body = vec![0x02, 0x81, n.len() as u8];
+        body.extend(&n);
+        body.extend([0x02, 0x03, 0x01, 0x00, 0x01]);
+        let mut der = vec![0x30, 0x81, body.len() as u8];
+        der.extend(body);
+        der
+    }
+
+    #[test]
+    fn test_import_rsa() {
+        let mut store = KeyStore::<2>::new();
+        let handle = store.import_rsa_public(&rsa_der()).unwrap();
+        assert_eq!(store.kind(handle), Ok(KeyKind::RsaPublic));
+        let key = store.rsa_public(handle).unwrap();
+        assert_eq!(key.m.len(), 128);
+        assert_eq!(key.m[0], 0xc3);
+        assert_eq!(key.m_bits, 1024);
+        assert_eq!(key.e, [0x01, 0x00, 0x01]);
+        assert_eq!(key.e_bits, 17);
+        assert_eq!(
+            store.ecdsa_public(handle).err(),
+            Some(KeyStoreError::WrongKeyType)
+        );
+
+        let mut truncated = rsa_der();
+        truncated.pop();
+        assert_eq!(
+            store.import_rsa_public(&truncated),
+            Err(KeyStoreError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_import_ecdsa() {
+        let mut store = KeyStore::<2>::new();
+        let mut raw = [0u8; 97];
+        raw[0] = 0x04;
+        raw[1] = 0xaa;
+        raw[49] = 0xbb;
+        let prefixed = store.import_ecdsa_public(&raw).unwrap();
+        let bare = store.import_ecdsa_public(&raw[1..]).unwrap();
+        for handle in [prefixed, bare] {
+            let key = store.ecdsa_public(handle).unwrap();
+            assert_eq!(key.qx.0[0], 0xaa);
+            assert_eq!(key.qy.0[0], 0xbb);
+        }
+        assert_eq!(
+            store.import_ecdsa_public(&raw[2..]),
+            Err(KeyStoreError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_import_hmac() {
+        let mut store = KeyStore::<1>::new();
+        let handle = store.import_hmac_sha256(&[0x5a; 32]).unwrap();
+        assert_eq!(store.kind(handle), Ok(KeyKind::HmacSha256));
+        assert_eq!(store.hmac_sha256_key(handle), Ok(&[0x5a; 32][..]));
+        assert_eq!(
+            store.ecdsa_public(handle).err(),
+            Some(KeyStoreError::WrongKeyType)
+        );
+    }
+
+    #[test]
+    fn test_capacity_and_destroy() {
+        let mut store = KeyStore::<2>::new();
+        let raw = [0x11u8; 96];
+        let a = store.import_ecdsa_public(&raw).unwrap();
+        let _b = store.import_ecdsa_public(&raw).unwrap();
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.import_ecdsa_public(&raw), Err(KeyStoreError::Full));
+
+        store.destroy(a).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.destroy(a), Err(KeyStoreError::InvalidHandle));
+
+        // the freed slot is reused, but the stale handle stays rejected
+        let c = store.import_ecdsa_public(&raw).unwrap();
+        assert_ne!(a, c);
+        assert_eq!(
+            store.ecdsa_public(a).err(),
+            Some(KeyStoreError::InvalidHandle)
+        );
+        assert!(store.ecdsa_public(c).is_ok());
+    }
+
+    #[test]
+    fn test_reference_counting() {
+        let mut store = KeyStore::<1>::new();
+        let handle = store.import_rsa_public(&rsa_der()).unwrap();
+        let second = store.retain(handle).unwrap();
+
+        store.release(handle).unwrap();
+        assert!(store.rsa_public(second).is_ok());
+        store.release(second).unwrap();
+        assert!(store.is_empty());
+        assert_eq!(store.release(second), Err(KeyStoreError::InvalidHandle));
+        assert_eq!(store.retain(handle), Err(KeyStoreError::InvalidHandle));
+
+        // the wiped slot holds no key material
+        assert!(store.slots[0].data.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_init_in() {
+        let mut place = core::mem::MaybeUninit::uninit();
+        let store = KeyStore::<1>::init_in(&mut place);
+        assert!(store.is_empty());
+        let handle = store.import_hmac_sha256(&[0x5a; 32]).unwrap();
+        assert_eq!(store.hmac_sha256_key(handle), Ok(&[0x5a; 32][..]));
+    }
+}
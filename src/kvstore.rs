@@ -0,0 +1,546 @@
+// Licensed under the Apache-2.0 license
+
+//! Small key-value store for persistent configuration on NOR flash
+//!
+//! The store occupies `sectors` consecutive erase sectors of a block device
+//! and keeps them as a log. Every program unit (a "slot") holds at most one
+//! record: `crc32 | key | len | kind | seq | data`, where the CRC covers all
+//! following bytes. Slot 0 of each sector holds a header with the sector
+//! sequence number. Sectors are filled in ring order, and the newest record
+//! of a key (highest `seq`) wins. [`KvStore::delete`] appends a tombstone.
+//!
+//! When the head sector fills, the next free sector is opened. When that
+//! leaves no free sector, the oldest sector is garbage collected. Records
+//! that are still the newest for their key are copied to the head and the
+//! sector is erased. Tombstones in the oldest sector are dropped, because
+//! every other sector only holds newer records.
+//!
+//! Power loss: a torn record or sector header fails its CRC and is skipped
+//! (or erased) on [`KvStore::mount`]. Copies made by an interrupted
+//! collection keep their sequence number, so duplicates are harmless, and
+//! mount finishes the collection.
+//!
+//! Worst-case latency:
+//! - [`KvStore::get`] reads one slot.
+//! - [`KvStore::set`] and [`KvStore::delete`] program one record and one
+//!   sector header, and may collect one sector. A collection is at most
+//!   `slots_per_sector` reads and programs plus one sector erase.
+//! - [`KvStore::mount`] reads every slot of the partition once.
+
+use crate::common::crc32;
+use crate::spi::norflashblockdevice::BlockAddrUsize;
+use proposed_traits::block_device::{BlockDevice, BlockRange};
+
+/// Largest value accepted by [`KvStore::set`]
+pub const KV_MAX_VALUE_LEN: usize = 64;
+
+const KV_SECTOR_MAGIC: u32 = 0x3153_564B; // "KVS1"
+const KV_HDR_LEN: usize = 12;
+const KV_MAX_SLOT_SIZE: usize = 256;
+const KV_KIND_SET: u8 = 0x5E;
+const KV_KIND_DELETE: u8 = 0xDE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KvError {
+    Flash,
+    InvalidLayout,
+    NotFound,
+    ValueTooLarge,
+    BufferTooSmall(usize),
+    Full,
+}
+
+#[derive(Clone, Copy)]
+struct IndexEntry {
+    key: u16,
+    seq: u32,
+    slot: usize,
+    deleted: bool,
+}
+
+struct Record {
+    key: u16,
+    len: usize,
+    kind: u8,
+    seq: u32,
+}
+
+enum Slot {
+    Erased,
+    Corrupt,
+    Valid(Record),
+}
+
+/// Append-only key-value store; `MAX_KEYS` bounds the in-RAM index
+pub struct KvStore<D, const MAX_KEYS: usize> {
+    device: D,
+    base: usize,
+    sectors: usize,
+    sector_size: usize,
+    slot_size: usize,
+    head: usize,
+    tail: usize,
+    next_slot: usize,
+    sector_seq: u32,
+    record_seq: u32,
+    index: heapless::Vec<IndexEntry, MAX_KEYS>,
+    buf: [u8; KV_MAX_SLOT_SIZE],
+}
+
+impl<D, const MAX_KEYS: usize> KvStore<D, MAX_KEYS>
+where
+    D: BlockDevice<Address = BlockAddrUsize>,
+{
+    /// Opens the store in `sectors` erase sectors starting at byte `base`.
+    ///
+    /// A blank partition is formatted. Torn sector headers are erased and an
+    /// interrupted garbage collection is completed.
+    pub fn mount(device: D, base: usize, sectors: usize) -> Result<Self, KvError> {
+        let sector_size = device.erase_size();
+        let slot_size = device.program_size();
+        if sectors < 2
+            || !(KV_HDR_LEN + KV_MAX_VALUE_LEN..=KV_MAX_SLOT_SIZE).contains(&slot_size)
+            || sector_size % slot_size != 0
+            || sector_size / slot_size < 2
+            || slot_size % device.read_size() != 0
+            || base % sector_size != 0
+            || base + sectors * sector_size > device.capacity()
+        {
+            return Err(KvError::InvalidLayout);
+        }
+
+        let mut store = Self {
+            device,
+            base,
+            sectors,
+            sector_size,
+            slot_size,
+            head: 0,
+            tail: 0,
+            next_slot: 1,
+            sector_seq: 0,
+            record_seq: 0,
+            index: heapless::Vec::new(),
+            buf: [0xFF; KV_MAX_SLOT_SIZE],
+        };
+
+        // Locate the newest and oldest sectors; erase torn headers
+        let mut newest: Option<(usize, u32)> = None;
+        let mut oldest: Option<(usize, u32)> = None;
+        for sector in 0..sectors {
+            match store.read_sector_header(sector)? {
+                Some(seq) => {
+                    if newest.is_none_or(|(_, s)| seq > s) {
+                        newest = Some((sector, seq));
+                    }
+                    if oldest.is_none_or(|(_, s)| seq < s) {
+                        oldest = Some((sector, seq));
+                    }
+                }
+                None => {
+                    if !store.sector_is_erased(sector)? {
+                        store.erase_sector(sector)?;
+                    }
+                }
+            }
+        }
+
+        let (Some((head, head_seq)), Some((tail, _))) = (newest, oldest) else {
+            store.sector_seq = 1;
+            store.write_sector_header(0)?;
+            return Ok(store);
+        };
+        store.head = head;
+        store.tail = tail;
+        store.sector_seq = head_seq.wrapping_add(1);
+
+        // Replay sectors from oldest to newest so later copies win
+        let slots = store.slots_per_sector();
+        let mut sector = tail;
+        loop {
+            let mut last_used = 0;
+            for slot in 1..slots {
+                match store.read_slot(sector * slots + slot)? {
+                    Slot::Erased => {}
+                    Slot::Corrupt => last_used = slot,
+                    Slot::Valid(rec) => {
+                        last_used = slot;
+                        store.record_seq = store.record_seq.max(rec.seq.wrapping_add(1));
+                        store.index_update(&rec, sector * slots + slot)?;
+                    }
+                }
+            }
+            if sector == head {
+                store.next_slot = last_used + 1;
+                break;
+            }
+            sector = (sector + 1) % sectors;
+        }
+
+        if store.free_sectors() == 0 {
+            store.collect()?;
+        }
+        Ok(store)
+    }
+
+    /// Copies the value of `key` into `buf` and returns its length.
+    pub fn get(&mut self, key: u16, buf: &mut [u8]) -> Result<usize, KvError> {
+        let entry = self
+            .index
+            .iter()
+            .find(|e| e.key == key && !e.deleted)
+            .copied()
+            .ok_or(KvError::NotFound)?;
+        let Slot::Valid(rec) = self.read_slot(entry.slot)? else {
+            return Err(KvError::Flash);
+        };
+        if buf.len() < rec.len {
+            return Err(KvError::BufferTooSmall(rec.len));
+        }
+        buf[..rec.len].copy_from_slice(&self.buf[KV_HDR_LEN..KV_HDR_LEN + rec.len]);
+        Ok(rec.len)
+    }
+
+    /// Stores `data` (at most [`KV_MAX_VALUE_LEN`] bytes) under `key`.
+    pub fn set(&mut self, key: u16, data: &[u8]) -> Result<(), KvError> {
+        if data.len() > KV_MAX_VALUE_LEN {
+            return Err(KvError::ValueTooLarge);
+        }
+        if self.index.is_full() && !self.index.iter().any(|e| e.key == key) {
+            return Err(KvError::Full);
+        }
+        self.append(key, KV_KIND_SET, data)
+    }
+
+    /// Removes `key` from the store.
+    pub fn delete(&mut self, key: u16) -> Result<(), KvError> {
+        if !self.index.iter().any(|e| e.key == key && !e.deleted) {
+            return Err(KvError::NotFound);
+        }
+        self.append(key, KV_KIND_DELETE, &[])
+    }
+
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+
+    fn slots_per_sector(&self) -> usize {
+        self.sector_size / self.slot_size
+    }
+
+    fn free_sectors(&self) -> usize {
+        self.sectors - ((self.head + self.sectors - self.tail) % self.sectors + 1)
+    }
+
+    fn slot_addr(&self, slot: usize) -> BlockAddrUsize {
+        BlockAddrUsize(self.base + slot * self.slot_size)
+    }
+
+    fn append(&mut self, key: u16, kind: u8, data: &[u8]) -> Result<(), KvError> {
+        if self.next_slot == self.slots_per_sector() {
+            self.advance()?;
+        }
+        let seq = self.record_seq;
+        let slot = self.head * self.slots_per_sector() + self.next_slot;
+        // The slot is consumed even if programming is interrupted
+        self.next_slot += 1;
+        self.record_seq = self.record_seq.wrapping_add(1);
+        self.write_record(slot, key, kind, seq, data)?;
+        let rec = Record {
+            key,
+            len: data.len(),
+            kind,
+            seq,
+        };
+        self.index_update(&rec, slot)
+    }
+
+    /// Opens the next sector and collects the oldest one if none is left free
+    fn advance(&mut self) -> Result<(), KvError> {
+        self.head = (self.head + 1) % self.sectors;
+        self.next_slot = 1;
+        self.write_sector_header(self.head)?;
+        if self.free_sectors() == 0 {
+            self.collect()?;
+        }
+        if self.next_slot == self.slots_per_sector() {
+            return Err(KvError::Full);
+        }
+        Ok(())
+    }
+
+    /// Moves the live records of the tail sector to the head and erases it
+    fn collect(&mut self) -> Result<(), KvError> {
+        let slots = self.slots_per_sector();
+        let tail = self.tail;
+        for slot in tail * slots + 1..(tail + 1) * slots {
+            let Slot::Valid(rec) = self.read_slot(slot)? else {
+                continue;
+            };
+            let Some(pos) = self
+                .index
+                .iter()
+                .position(|e| e.key == rec.key && e.slot == slot)
+            else {
+                continue;
+            };
+            if self.index[pos].deleted {
+                self.index.swap_remove(pos);
+                continue;
+            }
+            if self.next_slot == slots {
+                return Err(KvError::Full);
+            }
+            let dst = self.head * slots + self.next_slot;
+            self.next_slot += 1;
+            // Same seq: a duplicate left by power loss is indistinguishable
+            let src = self.buf;
+            self.program_slot(dst, &src)?;
+            self.index[pos].slot = dst;
+        }
+        self.erase_sector(tail)?;
+        self.tail = (tail + 1) % self.sectors;
+        Ok(())
+    }
+
+    fn index_update(&mut self, rec: &Record, slot: usize) -> Result<(), KvError> {
+        let deleted = rec.kind == KV_KIND_DELETE;
+        if let Some(entry) = self.index.iter_mut().find(|e| e.key == rec.key) {
+            if rec.seq >= entry.seq {
+                *entry = IndexEntry {
+                    key: rec.key,
+                    seq: rec.seq,
+                    slot,
+                    deleted,
+                };
+            }
+            return Ok(());
+        }
+        self.index
+            .push(IndexEntry {
+                key: rec.key,
+                seq: rec.seq,
+                slot,
+                deleted,
+            })
+            .map_err(|_| KvError::Full)
+    }
+
+    /// Reads `slot` into the scratch buffer and decodes its record header
+    fn read_slot(&mut self, slot: usize) -> Result<Slot, KvError> {
+        let addr = self.slot_addr(slot);
+        let len = self.slot_size;
+        self.device
+            .read(addr, &mut self.buf[..len])
+            .map_err(|_| KvError::Flash)?;
+        let hdr = &self.buf[..KV_HDR_LEN];
+        if hdr.iter().all(|&b| b == 0xFF) {
+            return Ok(Slot::Erased);
+        }
+        let data_len = usize::from(hdr[6]);
+        let kind = hdr[7];
+        if data_len > KV_MAX_VALUE_LEN || (kind != KV_KIND_SET && kind != KV_KIND_DELETE) {
+            return Ok(Slot::Corrupt);
+        }
+        let crc = u32::from_le_bytes([hdr[0], hdr[1], hdr[2], hdr[3]]);
+        if crc != crc32(&self.buf[4..KV_HDR_LEN + data_len]) {
+            return Ok(Slot::Corrupt);
+        }
+        Ok(Slot::Valid(Record {
+            key: u16::from_le_bytes([hdr[4], hdr[5]]),
+            len: data_len,
+            kind,
+            seq: u32::from_le_bytes([hdr[8], hdr[9], hdr[10], hdr[11]]),
+        }))
+    }
+
+    fn write_record(
+        &mut self,
+        slot: usize,
+        key: u16,
+        kind: u8,
+        seq: u32,
+        data: &[u8],
+    ) -> Result<(), KvError> {
+        let mut rec = [0xFFu8; KV_MAX_SLOT_SIZE];
+        rec[4..6].copy_from_slice(&key.to_le_bytes());
+        rec[6] = u8::try_from(data.len()).map_err(|_| KvError::ValueTooLarge)?;
+        rec[7] = kind;
+        rec[8..12].copy_from_slice(&seq.to_le_bytes());
+        rec[KV_HDR_LEN..KV_HDR_LEN + data.len()].copy_from_slice(data);
+        let crc = crc32(&rec[4..KV_HDR_LEN + data.len()]);
+        rec[..4].copy_from_slice(&crc.to_le_bytes());
+        self.program_slot(slot, &rec)
+    }
+
+    fn program_slot(&mut self, slot: usize, data: &[u8; KV_MAX_SLOT_SIZE]) -> Result<(), KvError> {
+        let addr = self.slot_addr(slot);
+        self.device
+            .program(addr, &data[..self.slot_size])
+            .map_err(|_| KvError::Flash)
+    }
+
+    fn read_sector_header(&mut self, sector: usize) -> Result<Option<u32>, KvError> {
+        let addr = self.slot_addr(sector * self.slots_per_sector());
+        let mut hdr = [0u8; KV_HDR_LEN];
+        self.device
+            .read(addr, &mut hdr)
+            .map_err(|_| KvError::Flash)?;
+        let magic = u32::from_le_bytes([hdr[0], hdr[1], hdr[2], hdr[3]]);
+        let crc = u32::from_le_bytes([hdr[8], hdr[9], hdr[10], hdr[11]]);
+        if magic != KV_SECTOR_MAGIC || crc != crc32(&hdr[..8]) {
+            return Ok(None);
+        }
+        Ok(Some(u32::from_le_bytes([hdr[4], hdr[5], hdr[6], hdr[7]])))
+    }
+
+    fn write_sector_header(&mut self, sector: usize) -> Result<(), KvError> {
+        let mut hdr = [0xFFu8; KV_MAX_SLOT_SIZE];
+        hdr[..4].copy_from_slice(&KV_SECTOR_MAGIC.to_le_bytes());
+        hdr[4..8].copy_from_slice(&self.sector_seq.to_le_bytes());
+        let crc = crc32(&hdr[..8]);
+        hdr[8..12].copy_from_slice(&crc.to_le_bytes());
+        self.sector_seq = self.sector_seq.wrapping_add(1);
+        self.program_slot(sector * self.slots_per_sector(), &hdr)
+    }
+
+    fn sector_is_erased(&mut self, sector: usize) -> Result<bool, KvError> {
+        let slots = self.slots_per_sector();
+        for slot in sector * slots..(sector + 1) * slots {
+            let addr = self.slot_addr(slot);
+            let len = self.slot_size;
+            self.device
+                .read(addr, &mut self.buf[..len])
+                .map_err(|_| KvError::Flash)?;
+            if self.buf[..len].iter().any(|&b| b != 0xFF) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn erase_sector(&mut self, sector: usize) -> Result<(), KvError> {
+        let start = BlockAddrUsize(self.base + sector * self.sector_size);
+        self.device
+            .erase(BlockRange { start, count: 1 })
+            .map_err(|_| KvError::Flash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KvError, KvStore, KV_MAX_VALUE_LEN};
+    use crate::mock_flash::MockFlash;
+
+    type Flash = MockFlash<4, 1024>;
+    type Store = KvStore<Flash, 8>;
+
+    fn blank() -> Flash {
+        Flash::new(128)
+    }
+
+    fn value(key: u16, n: usize) -> [u8; 8] {
+        let mut v = [0u8; 8];
+        v[..2].copy_from_slice(&key.to_le_bytes());
+        v[2..6].copy_from_slice(&u32::try_from(n).unwrap().to_le_bytes());
+        v
+    }
+
+    fn get(store: &mut Store, key: u16) -> Option<[u8; 8]> {
+        let mut buf = [0u8; 8];
+        match store.get(key, &mut buf) {
+            Ok(8) => Some(buf),
+            Err(KvError::NotFound) => None,
+            other => panic!("unexpected get result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_set_get_delete() {
+        let mut store = Store::mount(blank(), 0, 4).unwrap();
+        assert_eq!(get(&mut store, 1), None);
+        store.set(1, &value(1, 1)).unwrap();
+        store.set(2, &value(2, 1)).unwrap();
+        assert_eq!(get(&mut store, 1), Some(value(1, 1)));
+        store.delete(1).unwrap();
+        assert_eq!(get(&mut store, 1), None);
+        assert_eq!(store.delete(1), Err(KvError::NotFound));
+        assert_eq!(
+            store.set(3, &[0u8; KV_MAX_VALUE_LEN + 1]),
+            Err(KvError::ValueTooLarge)
+        );
+        let mut small = [0u8; 4];
+        assert_eq!(store.get(2, &mut small), Err(KvError::BufferTooSmall(8)));
+
+        let mut store = Store::mount(store.into_inner(), 0, 4).unwrap();
+        assert_eq!(get(&mut store, 1), None);
+        assert_eq!(get(&mut store, 2), Some(value(2, 1)));
+    }
+
+    #[test]
+    fn test_garbage_collection() {
+        let mut store = Store::mount(blank(), 0, 4).unwrap();
+        store.set(100, &value(100, 0)).unwrap();
+        store.set(101, &value(101, 0)).unwrap();
+        store.delete(101).unwrap();
+        // Many times the partition size, forcing repeated collection
+        for n in 0..200 {
+            let key = u16::try_from(n % 3).unwrap();
+            store.set(key, &value(key, n)).unwrap();
+        }
+        assert_eq!(get(&mut store, 100), Some(value(100, 0)));
+        assert_eq!(get(&mut store, 101), None);
+
+        let mut store = Store::mount(store.into_inner(), 0, 4).unwrap();
+        assert_eq!(get(&mut store, 0), Some(value(0, 198)));
+        assert_eq!(get(&mut store, 1), Some(value(1, 199)));
+        assert_eq!(get(&mut store, 2), Some(value(2, 197)));
+        assert_eq!(get(&mut store, 100), Some(value(100, 0)));
+        assert_eq!(get(&mut store, 101), None);
+    }
+
+    #[test]
+    fn test_program_failure() {
+        let mut store = Store::mount(blank(), 0, 4).unwrap();
+        store.set(1, &value(1, 1)).unwrap();
+        store.device.fail_program_after(0);
+        assert_eq!(store.set(1, &value(1, 2)), Err(KvError::Flash));
+        assert_eq!(get(&mut store, 1), Some(value(1, 1)));
+        store.set(1, &value(1, 3)).unwrap();
+        assert_eq!(get(&mut store, 1), Some(value(1, 3)));
+    }
+
+    #[test]
+    fn test_torn_write_recovery() {
+        // Cut power at every program of the sequence in turn
+        for cut in 0..60 {
+            let mut store = Store::mount(blank(), 0, 4).unwrap();
+            store.device.power_cut_after(cut);
+            let mut expected: [Option<[u8; 8]>; 3] = [None; 3];
+            let mut torn = None;
+            for n in 0..40 {
+                let key = u16::try_from(n % 3).unwrap();
+                if store.set(key, &value(key, n)).is_err() {
+                    torn = Some((key, n));
+                    break;
+                }
+                expected[usize::from(key)] = Some(value(key, n));
+            }
+            let mut flash = store.into_inner();
+            flash.clear_faults();
+            let mut store = Store::mount(flash, 0, 4).unwrap();
+            for key in 0..3u16 {
+                let got = get(&mut store, key);
+                match torn {
+                    Some((k, n)) if k == key => {
+                        assert!(got == expected[usize::from(key)] || got == Some(value(key, n)));
+                    }
+                    _ => assert_eq!(got, expected[usize::from(key)], "cut {cut} key {key}"),
+                }
+            }
+            // The store stays usable after recovery
+            store.set(7, &value(7, 1)).unwrap();
+            assert_eq!(get(&mut store, 7), Some(value(7, 1)));
+        }
+    }
+}
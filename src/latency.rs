@@ -0,0 +1,298 @@
+// Licensed under the Apache-2.0 license
+
+//! Interrupt latency and jitter measurement
+//!
+//! [`LatencyHistogram`] collects samples in fixed-width buckets and reports
+//! min, median, 99th percentile and max. With the `rt` feature two probes
+//! feed it from interrupt handlers:
+//!
+//! - [`TimerProbe`] runs the timer at a fixed rate and, on each entry, reads
+//!   how far the counter has run past expiry (latency) and how far the DWT
+//!   time since the previous entry is from the period (jitter).
+//! - [`GpioProbe`] measures from [`mark_gpio_edge`], called just before the
+//!   main loop toggles a loopback pin, to entry of the pin's handler.
+//!
+//! Both are entered through [`crate::interrupts::dispatch`], so the numbers
+//! include the registry lookup, as they would for any driver.
+
+use core::fmt;
+
+/// Number of fixed-width buckets; slower samples land in the overflow count
+pub const LATENCY_BUCKETS: usize = 64;
+
+#[derive(Clone, Copy, Debug)]
+pub struct LatencyHistogram {
+    bucket_ns: u32,
+    buckets: [u32; LATENCY_BUCKETS],
+    overflow: u32,
+    count: u32,
+    min: u32,
+    max: u32,
+}
+
+impl LatencyHistogram {
+    /// Empty histogram with buckets `bucket_ns` wide
+    #[must_use]
+    pub const fn new(bucket_ns: u32) -> Self {
+        Self {
+            bucket_ns: if bucket_ns == 0 { 1 } else { bucket_ns },
+            buckets: [0; LATENCY_BUCKETS],
+            overflow: 0,
+            count: 0,
+            min: u32::MAX,
+            max: 0,
+        }
+    }
+
+    pub fn record(&mut self, ns: u32) {
+        match self.buckets.get_mut((ns / self.bucket_ns) as usize) {
+            Some(bucket) => *bucket += 1,
+            None => self.overflow += 1,
+        }
+        self.count += 1;
+        self.min = self.min.min(ns);
+        self.max = self.max.max(ns);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new(self.bucket_ns);
+    }
+
+    #[must_use]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Samples past the last bucket
+    #[must_use]
+    pub fn overflow(&self) -> u32 {
+        self.overflow
+    }
+
+    #[must_use]
+    pub fn min(&self) -> Option<u32> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    #[must_use]
+    pub fn max(&self) -> Option<u32> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Upper bound on the `pct` percentile: the top of the bucket holding
+    /// that sample, kept within the recorded min and max
+    #[must_use]
+    pub fn percentile(&self, pct: u32) -> Option<u32> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (u64::from(self.count) * u64::from(pct.min(100))).div_ceil(100);
+        let rank = rank.max(1);
+        let mut seen = 0u64;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += u64::from(n);
+            if seen >= rank {
+                let top = (u32::try_from(i).unwrap() + 1).saturating_mul(self.bucket_ns) - 1;
+                return Some(top.clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+impl fmt::Display for LatencyHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (Some(min), Some(max)) = (self.min(), self.max()) else {
+            return f.write_str("n=0");
+        };
+        write!(
+            f,
+            "n={} min={min}ns p50={}ns p99={}ns max={max}ns",
+            self.count,
+            self.percentile(50).unwrap_or(max),
+            self.percentile(99).unwrap_or(max),
+        )?;
+        if self.overflow > 0 {
+            write!(f, " over={}", self.overflow)?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts `cycles` at `cpu_hz` to nanoseconds, saturating
+#[must_use]
+pub fn cycles_to_ns(cycles: u32, cpu_hz: u32) -> u32 {
+    let ns = u64::from(cycles) * 1_000_000_000 / u64::from(cpu_hz.max(1));
+    u32::try_from(ns).unwrap_or(u32::MAX)
+}
+
+#[cfg(feature = "rt")]
+pub use probes::{mark_gpio_edge, GpioProbe, TimerProbe};
+
+#[cfg(feature = "rt")]
+mod probes {
+    use super::{cycles_to_ns, LatencyHistogram};
+    use crate::interrupts::IrqHandler;
+    use crate::timer::{TimerController, TimerError, TimerType};
+    use ast1060_pac::{Interrupt, Timer};
+    use core::sync::atomic::{AtomicU32, Ordering};
+    use cortex_m::peripheral::DWT;
+    use embedded_hal_old::timer::CountDown;
+    use fugit::MicrosDurationU32;
+
+    /// DWT time of the last [`mark_gpio_edge`]; 0 when nothing is pending
+    static GPIO_EDGE_AT: AtomicU32 = AtomicU32::new(0);
+
+    /// Records the time just before the loopback pin is toggled
+    pub fn mark_gpio_edge() {
+        GPIO_EDGE_AT.store(DWT::cycle_count() | 1, Ordering::Release);
+    }
+
+    /// Timer handler measuring its own entry latency and period jitter
+    pub struct TimerProbe {
+        timer: TimerController<Timer>,
+        tick_per_us: u32,
+        cpu_hz: u32,
+        reload: u32,
+        period_cycles: u32,
+        last_entry: Option<u32>,
+        pub latency: LatencyHistogram,
+        pub jitter: LatencyHistogram,
+    }
+
+    impl TimerProbe {
+        #[must_use]
+        pub fn new(tick_per_us: u32, cpu_hz: u32, bucket_ns: u32) -> Self {
+            Self {
+                timer: TimerController::new(tick_per_us),
+                tick_per_us,
+                cpu_hz,
+                reload: 0,
+                period_cycles: 0,
+                last_entry: None,
+                latency: LatencyHistogram::new(bucket_ns),
+                jitter: LatencyHistogram::new(bucket_ns),
+            }
+        }
+
+        /// Starts the timer firing every `period` and clears the histograms
+        pub fn start(&mut self, period: MicrosDurationU32) -> Result<(), TimerError> {
+            self.latency.clear();
+            self.jitter.clear();
+            self.last_entry = None;
+            self.reload = period.ticks() * self.tick_per_us;
+            self.period_cycles = period.ticks() * (self.cpu_hz / 1_000_000);
+            self.timer.set_callback(None, TimerType::Periodic);
+            self.timer.try_start(period)
+        }
+
+        pub fn stop(&mut self) {
+            self.timer.stop();
+        }
+    }
+
+    impl IrqHandler for TimerProbe {
+        fn irq_number(&self) -> Interrupt {
+            Interrupt::timer
+        }
+
+        fn on_irq(&mut self) {
+            let now = DWT::cycle_count();
+            // the counter reloaded at expiry and has been counting down since
+            let late_ticks = self.reload.saturating_sub(self.timer.current());
+            self.latency
+                .record(late_ticks.saturating_mul(1000) / self.tick_per_us.max(1));
+            if let Some(last) = self.last_entry {
+                let drift = now.wrapping_sub(last).abs_diff(self.period_cycles);
+                self.jitter.record(cycles_to_ns(drift, self.cpu_hz));
+            }
+            self.last_entry = Some(now);
+            self.timer.handle_interrupt();
+        }
+    }
+
+    /// GPIO handler measuring the time from [`mark_gpio_edge`] to entry
+    pub struct GpioProbe {
+        irq: Interrupt,
+        clear: fn(),
+        cpu_hz: u32,
+        pub latency: LatencyHistogram,
+    }
+
+    impl GpioProbe {
+        /// `clear` acknowledges the pin's interrupt status
+        #[must_use]
+        pub fn new(irq: Interrupt, clear: fn(), cpu_hz: u32, bucket_ns: u32) -> Self {
+            Self {
+                irq,
+                clear,
+                cpu_hz,
+                latency: LatencyHistogram::new(bucket_ns),
+            }
+        }
+    }
+
+    impl IrqHandler for GpioProbe {
+        fn irq_number(&self) -> Interrupt {
+            self.irq
+        }
+
+        fn on_irq(&mut self) {
+            let now = DWT::cycle_count();
+            (self.clear)();
+            let at = GPIO_EDGE_AT.swap(0, Ordering::Acquire);
+            if at != 0 {
+                self.latency
+                    .record(cycles_to_ns(now.wrapping_sub(at), self.cpu_hz));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cycles_to_ns, LatencyHistogram, LATENCY_BUCKETS};
+
+    #[test]
+    fn test_histogram_stats() {
+        let mut hist = LatencyHistogram::new(100);
+        assert_eq!(hist.percentile(50), None);
+        assert_eq!(format!("{hist}"), "n=0");
+
+        for ns in [120, 150, 180, 250, 950] {
+            hist.record(ns);
+        }
+        assert_eq!(hist.count(), 5);
+        assert_eq!((hist.min(), hist.max()), (Some(120), Some(950)));
+        // the median falls in the 100..200 bucket
+        assert_eq!(hist.percentile(50), Some(199));
+        assert_eq!(hist.percentile(99), Some(950));
+        assert_eq!(
+            format!("{hist}"),
+            "n=5 min=120ns p50=199ns p99=950ns max=950ns"
+        );
+    }
+
+    #[test]
+    fn test_histogram_overflow() {
+        let mut hist = LatencyHistogram::new(10);
+        let limit = u32::try_from(LATENCY_BUCKETS).unwrap() * 10;
+        hist.record(5);
+        hist.record(limit);
+        hist.record(u32::MAX);
+        assert_eq!(hist.overflow(), 2);
+        assert_eq!(hist.percentile(99), Some(u32::MAX));
+        // a single sample is its own percentile, not the bucket top
+        let mut one = LatencyHistogram::new(1000);
+        one.record(42);
+        assert_eq!(one.percentile(50), Some(42));
+        one.clear();
+        assert_eq!(one.count(), 0);
+    }
+
+    #[test]
+    fn test_cycles_to_ns() {
+        assert_eq!(cycles_to_ns(200, 200_000_000), 1_000);
+        assert_eq!(cycles_to_ns(u32::MAX, 1), u32::MAX);
+    }
+}
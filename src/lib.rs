@@ -2,20 +2,43 @@
 
 #![cfg_attr(not(test), no_std)]
 pub mod astdebug;
+pub mod board;
+pub mod boot_state;
+pub mod boot_trace;
 pub mod common;
+pub mod doorbell;
 pub mod ecdsa;
 pub mod gpio;
 pub mod hace_controller;
 pub mod hash;
+pub mod hash_caps;
 pub mod hash_owned;
+pub mod hash_session;
 pub mod hmac;
+pub mod host_auth;
+pub mod host_policy;
 pub mod i2c;
+#[cfg(feature = "rt")]
+pub mod interrupts;
+pub mod keystore;
+pub mod kvstore;
+pub mod latency;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod mock_flash;
+pub mod pin_state;
 pub mod pinctrl;
+pub mod rolling_hash;
 pub mod rsa;
+pub mod sequencer;
+pub mod soft_pwm;
+pub mod soft_watchdog;
 pub mod spi;
 pub mod spimonitor;
 pub mod syscon;
+pub mod telemetry;
 pub mod tests;
 pub mod timer;
 pub mod uart;
+pub mod verify;
+pub mod version;
 pub mod watchdog;
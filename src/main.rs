@@ -3,35 +3,73 @@
 #![no_std]
 #![no_main]
 
-use core::sync::atomic::AtomicBool;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 // use core::arch::asm;
-use aspeed_ddk::uart::{Config, UartController};
-use aspeed_ddk::watchdog::WdtController;
-use ast1060_pac::Peripherals;
+use aspeed_ddk::board::{Board, GpioPorts};
+use aspeed_ddk::boot_trace::{self, Milestone};
+use aspeed_ddk::common::{LogErr, UartLogger};
+use aspeed_ddk::uart::{early, Config, UartController};
+use aspeed_ddk::version;
+use aspeed_ddk::watchdog::{WdtController, WdtResetScope};
+#[cfg(any(feature = "ecdsa", feature = "rsa"))]
+use ast1060_pac::Secure;
 use ast1060_pac::{Wdt, Wdt1};
 
+#[cfg(feature = "ecdsa")]
 use aspeed_ddk::ecdsa::AspeedEcdsa;
 use aspeed_ddk::hace_controller::HaceController;
+#[cfg(feature = "rsa")]
 use aspeed_ddk::rsa::AspeedRsa;
-use aspeed_ddk::spi;
 use aspeed_ddk::syscon::{ClockId, ResetId, SysCon};
 use fugit::MillisDurationU32 as MilliSeconds;
 
-use aspeed_ddk::tests::functional::ecdsa_test::run_ecdsa_tests;
+use aspeed_ddk::tests::functional::board_test::run_board_tests;
+use aspeed_ddk::tests::functional::coop_test::run_coop_tests;
+#[cfg(feature = "ecdsa")]
+use aspeed_ddk::tests::functional::ecdsa_test::{
+    run_ecdsa_batch_tests, run_ecdsa_decision_tests, run_ecdsa_tests,
+};
 use aspeed_ddk::tests::functional::gpio_test;
-use aspeed_ddk::tests::functional::hash_test::run_hash_tests;
-use aspeed_ddk::tests::functional::hmac_test::run_hmac_tests;
+use aspeed_ddk::tests::functional::hash_test::{
+    run_hace_ownership_test, run_hace_recover_test, run_hash_caps_test, run_hash_empty_tests,
+    run_hash_iv_tests, run_hash_tests,
+};
+#[cfg(feature = "sha512")]
+use aspeed_ddk::tests::functional::hash_test::{
+    run_hash_interleave_tests, run_hash_iter_tests, run_hash_stream_tests,
+};
+#[cfg(feature = "hmac")]
+use aspeed_ddk::tests::functional::hmac_test::{run_hmac_oneshot_test, run_hmac_tests};
+#[cfg(feature = "hmac")]
+use aspeed_ddk::tests::functional::host_auth_test::run_host_auth_tests;
+use aspeed_ddk::tests::functional::host_policy_test::{
+    run_host_policy_tests, run_transition_auth_tests,
+};
+#[cfg(all(feature = "rt", feature = "i2c_target"))]
+use aspeed_ddk::tests::functional::i2c_loopback_test::{self, LoopbackTargetBus};
 use aspeed_ddk::tests::functional::i2c_test;
+#[cfg(feature = "rsa")]
 use aspeed_ddk::tests::functional::rsa_test::run_rsa_tests;
+use aspeed_ddk::tests::functional::session_stress_test::{
+    run_aligned_session_test, run_session_reap_test, run_session_stress_test,
+};
+#[cfg(feature = "test-spi")]
+use aspeed_ddk::tests::functional::spi_test::{self, SpiSuiteConfig, SpiTarget};
 use aspeed_ddk::tests::functional::timer_test::run_timer_tests;
-use panic_halt as _;
+use aspeed_ddk::tests::functional::{TestResult, TestSummary};
 
 // Import owned API traits and types
-use aspeed_ddk::hash_owned::{Sha2_256, Sha2_384, Sha2_512};
+use aspeed_ddk::hash_owned::Sha2_256;
+#[cfg(feature = "sha384")]
+use aspeed_ddk::hash_owned::Sha2_384;
+#[cfg(feature = "sha512")]
+use aspeed_ddk::hash_owned::Sha2_512;
+use aspeed_ddk::hash_owned::{DigestBytes, DigestExt};
 use openprot_hal_blocking::digest::owned::{DigestInit, DigestOp};
 
 use proposed_traits::system_control::ResetControl;
 
+use core::panic::PanicInfo;
 use core::ptr::{read_volatile, write_volatile};
 use cortex_m_rt::entry;
 use cortex_m_rt::pre_init;
@@ -61,6 +99,27 @@ unsafe fn pre_init() {
 
     // Enable Cache
     write_volatile(cache_ctrl_offset as *mut u32, 1);
+
+    // Polled console for faults before the UART driver is up
+    early::early_init(CONSOLE_BAUD, CONSOLE_CLOCK);
+    early::early_print("\r\nearly console up\r\n");
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    // The console controller is out of reach here and may be mid-write
+    unsafe {
+        early::early_print("\r\npanic at ");
+        if let Some(location) = info.location() {
+            early::early_print(location.file());
+            early::early_print(":");
+            early::early_hex(location.line());
+        }
+        early::early_print("\r\n");
+    }
+    loop {
+        cortex_m::asm::nop();
+    }
 }
 
 #[derive(Clone, Default)]
@@ -74,16 +133,18 @@ impl DelayNs for DummyDelay {
     }
 }
 
-fn test_wdt(uart: &mut UartController<'_>) {
-    //instantiates the controller for the hardware watchdog Wdt and Wdt1
-    let mut wdt0 = WdtController::<Wdt>::new();
-    let mut wdt1 = WdtController::<Wdt1>::new();
+fn test_wdt(
+    uart: &mut UartController<'_>,
+    mut wdt0: WdtController<Wdt>,
+    mut wdt1: WdtController<Wdt1>,
+) {
     let mut delay = DummyDelay {};
+    wdt1.set_reset_scope(WdtResetScope::CpuOnly);
 
     // Start watchdog with a timeout of 2000 milliseconds (2 seconds)
     uart.write_all(b"\r\nstart wdt\r\n").unwrap();
-    wdt0.start(MilliSeconds::millis(5000));
-    wdt1.start(MilliSeconds::millis(10000));
+    wdt0.start(MilliSeconds::millis(5000)).unwrap();
+    wdt1.start(MilliSeconds::millis(10000)).unwrap();
     let mut cnt = 0;
 
     loop {
@@ -101,9 +162,40 @@ fn test_wdt(uart: &mut UartController<'_>) {
     }
 }
 
+const CONSOLE_BAUD: u32 = 115_200;
+const CONSOLE_CLOCK: u32 = 24_000_000;
+
+/// HCLK, which clocks the Cortex-M4 and its DWT cycle counter
+const CPU_HZ: u32 = 200_000_000;
+
+/// Flash the `test-spi` suite drives. The boot flash is only read; the
+/// others get the scratch sectors from 0x2000, next to the sectors the old
+/// bring-up tests already rewrote.
+#[cfg(feature = "test-spi")]
+const SPI_TARGETS: &[SpiSuiteConfig] = &[
+    SpiSuiteConfig::read_only(SpiTarget::FmcCs0),
+    SpiSuiteConfig {
+        scratch_offset: Some(0x2000),
+        ..SpiSuiteConfig::read_only(SpiTarget::FmcCs1)
+    },
+    SpiSuiteConfig {
+        scratch_offset: Some(0x2000),
+        ..SpiSuiteConfig::read_only(SpiTarget::Spi0Cs0)
+    },
+    SpiSuiteConfig {
+        scratch_offset: Some(0x2000),
+        ..SpiSuiteConfig::read_only(SpiTarget::Spi1Cs0)
+    },
+];
+
 #[no_mangle]
 pub static HALT: AtomicBool = AtomicBool::new(true);
 
+/// [`TestSummary::status`] of the last run, for a debugger or test rig to
+/// read; `u32::MAX` until the tests have finished
+#[no_mangle]
+pub static TEST_STATUS: AtomicU32 = AtomicU32::new(u32::MAX);
+
 #[macro_export]
 macro_rules! debug_halt {
     () => {{
@@ -120,63 +212,51 @@ macro_rules! debug_halt {
     }};
 }
 
+#[cfg(all(feature = "rt", feature = "i2c_target"))]
+static mut LOOPBACK_TARGET: Option<LoopbackTargetBus<ast1060_pac::I2c3>> = None;
+
 /// Test the owned digest API demonstrating move-based resource management
-fn test_owned_digest_api(uart: &mut UartController<'_>) {
+fn test_owned_digest_api(
+    uart: &mut UartController<'_>,
+    hace: HaceController,
+) -> (TestResult, HaceController) {
     writeln!(uart, "\r\nRunning owned digest API tests...\r\n").unwrap();
-
-    // Get a fresh HACE peripheral for owned API testing
-    let peripherals = unsafe { Peripherals::steal() };
-    let hace = peripherals.hace;
+    let mut result = TestResult::new();
 
     // Test SHA256 with owned API
     writeln!(uart, "Testing owned SHA256 API...").unwrap();
-    test_owned_sha256(uart, hace);
+    let hace = test_owned_sha256(uart, &mut result, hace);
 
-    // Get fresh peripheral for SHA384 test (since it was consumed)
-    let peripherals = unsafe { Peripherals::steal() };
-    let hace = peripherals.hace;
+    #[cfg(feature = "sha384")]
+    let hace = {
+        writeln!(uart, "Testing owned SHA384 API...").unwrap();
+        test_owned_sha384(uart, &mut result, hace)
+    };
 
-    writeln!(uart, "Testing owned SHA384 API...").unwrap();
-    test_owned_sha384(uart, hace);
+    #[cfg(feature = "sha512")]
+    let hace = {
+        writeln!(uart, "Testing owned SHA512 API...").unwrap();
+        test_owned_sha512(uart, &mut result, hace)
+    };
 
-    // Get fresh peripheral for SHA512 test
-    let peripherals = unsafe { Peripherals::steal() };
-    let hace = peripherals.hace;
+    writeln!(uart, "Testing owned SHA256 aligned update...").unwrap();
+    let hace = test_owned_sha256_aligned(uart, &mut result, hace);
 
-    writeln!(uart, "Testing owned SHA512 API...").unwrap();
-    test_owned_sha512(uart, hace);
+    writeln!(uart, "Testing owned SHA256 midstate export/resume...").unwrap();
+    let hace = test_owned_sha256_midstate(uart, &mut result, hace);
 
     writeln!(uart, "All owned digest API tests completed!\r\n").unwrap();
+    (result, hace)
 }
 
 /// Validate digest against known test vector
 fn validate_digest(
-    actual: &[u32],
+    actual: &DigestBytes,
     expected: &[u8],
     algorithm: &str,
     uart: &mut UartController<'_>,
 ) -> bool {
-    // Convert actual u32 array to bytes in big-endian format and compare
-    let mut matches = true;
-    let mut byte_index = 0;
-
-    for &word in actual {
-        let word_bytes = word.to_be_bytes();
-        for &byte in &word_bytes {
-            if byte_index < expected.len() {
-                if byte != expected[byte_index] {
-                    matches = false;
-                    break;
-                }
-                byte_index += 1;
-            }
-        }
-        if !matches || byte_index >= expected.len() {
-            break;
-        }
-    }
-
-    if matches && byte_index == expected.len() {
+    if *actual == *expected {
         writeln!(uart, "{algorithm} test vector validation: PASSED ✅").unwrap();
         true
     } else {
@@ -186,26 +266,17 @@ fn validate_digest(
             write!(uart, "{byte:02x}").unwrap();
         }
         writeln!(uart).unwrap();
-        write!(uart, "Actual:   ").unwrap();
-        let mut byte_index = 0;
-        for &word in actual {
-            let word_bytes = word.to_be_bytes();
-            for &byte in &word_bytes {
-                if byte_index < expected.len() {
-                    write!(uart, "{byte:02x}").unwrap();
-                    byte_index += 1;
-                }
-            }
-        }
-        writeln!(uart).unwrap();
+        writeln!(uart, "Actual:   {actual:?}").unwrap();
         false
     }
 }
 
 /// Test owned SHA256 API demonstrating move semantics
-fn test_owned_sha256(uart: &mut UartController<'_>, hace: ast1060_pac::Hace) {
-    let controller = HaceController::new(hace);
-
+fn test_owned_sha256(
+    uart: &mut UartController<'_>,
+    result: &mut TestResult,
+    controller: HaceController,
+) -> HaceController {
     // Initialize digest context - controller wrapper is moved
     let context = controller.init(Sha2_256).unwrap();
 
@@ -213,10 +284,13 @@ fn test_owned_sha256(uart: &mut UartController<'_>, hace: ast1060_pac::Hace) {
     // Expected: ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad
     let context = context.update(b"abc").unwrap();
 
-    // Finalize and get both digest and controller back
-    let (digest, _recovered_controller) = context.finalize().unwrap();
+    // Finalize and get digest, hashed length and controller back
+    let (digest, len, controller) = context.finalize_with_len().unwrap();
 
-    writeln!(uart, "SHA256 owned API digest: {:02x?}", &digest.value[..8]).unwrap();
+    writeln!(uart, "SHA256 owned API digest: {:?}", digest.to_bytes()).unwrap();
+    if !result.check(len == 3) {
+        writeln!(uart, "SHA256 owned API hashed length {len}: FAILED ❌").unwrap();
+    }
 
     // Known test vector for "abc"
     let expected_sha256 = [
@@ -225,17 +299,121 @@ fn test_owned_sha256(uart: &mut UartController<'_>, hace: ast1060_pac::Hace) {
         0x15, 0xad,
     ];
 
-    if validate_digest(&digest.value, &expected_sha256, "SHA256", uart) {
+    if result.check(validate_digest(
+        &digest.to_bytes(),
+        &expected_sha256,
+        "SHA256",
+        uart,
+    )) {
         writeln!(uart, "SHA256 owned API: PASSED ✅").unwrap();
     } else {
         writeln!(uart, "SHA256 owned API: FAILED ❌").unwrap();
     }
+    controller
 }
 
-/// Test owned SHA384 API demonstrating controller recovery
-fn test_owned_sha384(uart: &mut UartController<'_>, hace: ast1060_pac::Hace) {
-    let controller = HaceController::new(hace);
+/// Aligned-only updates must produce the same digest as buffered updates
+fn test_owned_sha256_aligned(
+    uart: &mut UartController<'_>,
+    result: &mut TestResult,
+    controller: HaceController,
+) -> HaceController {
+    let mut data = [0u8; 128];
+    for (i, b) in data.iter_mut().enumerate() {
+        *b = u8::try_from(i).unwrap();
+    }
+
+    let context = controller.init(Sha2_256).unwrap();
+    let context = context.update(&data).unwrap();
+    let (expected, controller) = context.finalize().unwrap();
 
+    let context = controller.init(Sha2_256).unwrap();
+    // A partial block cannot go through the aligned path
+    let context = match context.update_aligned(&data[..10]) {
+        Ok(context) => {
+            result.check(false);
+            writeln!(uart, "SHA256 aligned reject unaligned: FAILED ❌").unwrap();
+            context
+        }
+        Err((_, context)) => context,
+    };
+    let context = match context.update_aligned(&data[..64]) {
+        Ok(context) => context,
+        Err((_, context)) => {
+            result.check(false);
+            writeln!(uart, "SHA256 aligned update: FAILED ❌").unwrap();
+            return context.cancel();
+        }
+    };
+    // Mix with a normal update on the same session
+    let context = context.update(&data[64..]).unwrap();
+    let (digest, controller) = context.finalize().unwrap();
+
+    if result.check(digest.to_bytes() == expected.to_bytes()) {
+        writeln!(uart, "SHA256 aligned update: PASSED ✅").unwrap();
+    } else {
+        writeln!(uart, "SHA256 aligned update: FAILED ❌").unwrap();
+    }
+    controller
+}
+
+/// A digest exported at a block boundary and resumed in a fresh session
+/// must match the digest computed in one session
+fn test_owned_sha256_midstate(
+    uart: &mut UartController<'_>,
+    result: &mut TestResult,
+    controller: HaceController,
+) -> HaceController {
+    let mut data = [0u8; 150];
+    for (i, b) in data.iter_mut().enumerate() {
+        *b = u8::try_from(i).unwrap();
+    }
+
+    let context = controller.init(Sha2_256).unwrap();
+    let context = context.update(&data).unwrap();
+    let (expected, controller) = context.finalize().unwrap();
+
+    let context = controller.init(Sha2_256).unwrap();
+    let mut context = context.update(&data[..128]).unwrap();
+    let mut midstate = [0u8; 32];
+    let exported = context.export_midstate(&mut midstate);
+    let controller = context.cancel();
+
+    // a count that is not a whole number of blocks is refused
+    let controller = match controller.resume_digest(Sha2_256, &midstate, 100) {
+        Ok(context) => {
+            result.check(false);
+            writeln!(uart, "SHA256 midstate reject unaligned: FAILED ❌").unwrap();
+            return context.cancel();
+        }
+        Err((_, controller)) => controller,
+    };
+    let context = match controller.resume_digest(Sha2_256, &midstate, 128) {
+        Ok(context) => context,
+        Err((_, controller)) => {
+            result.check(false);
+            writeln!(uart, "SHA256 midstate resume: FAILED ❌").unwrap();
+            return controller;
+        }
+    };
+    let context = context.update(&data[128..]).unwrap();
+    let (digest, controller) = context.finalize().unwrap();
+
+    if result.check(exported == Ok(128) && digest.to_bytes() == expected.to_bytes()) {
+        writeln!(uart, "SHA256 midstate resume: PASSED ✅").unwrap();
+    } else {
+        writeln!(uart, "SHA256 midstate resume: FAILED ❌").unwrap();
+    }
+    controller
+}
+
+/// Test owned SHA384 API demonstrating controller recovery
+#[cfg(feature = "sha384")]
+fn test_owned_sha384(
+    uart: &mut UartController<'_>,
+    result: &mut TestResult,
+    controller: HaceController,
+) -> HaceController {
     // Test with known test vector: "abc" -> SHA384
     // Expected: cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7
     let context = controller.init(Sha2_384).unwrap();
@@ -243,7 +421,7 @@ fn test_owned_sha384(uart: &mut UartController<'_>, hace: ast1060_pac::Hace) {
 
     let (digest, recovered_controller) = context.finalize().unwrap();
 
-    writeln!(uart, "SHA384 owned API digest: {:02x?}", &digest.value[..8]).unwrap();
+    writeln!(uart, "SHA384 owned API digest: {:?}", digest.to_bytes()).unwrap();
 
     // Known test vector for "abc"
     let expected_sha384 = [
@@ -253,7 +431,12 @@ fn test_owned_sha384(uart: &mut UartController<'_>, hace: ast1060_pac::Hace) {
         0xc8, 0x25, 0xa7,
     ];
 
-    if validate_digest(&digest.value, &expected_sha384, "SHA384", uart) {
+    if result.check(validate_digest(
+        &digest.to_bytes(),
+        &expected_sha384,
+        "SHA384",
+        uart,
+    )) {
         writeln!(uart, "SHA384 owned API: PASSED ✅").unwrap();
     } else {
         writeln!(uart, "SHA384 owned API: FAILED ❌").unwrap();
@@ -262,15 +445,19 @@ fn test_owned_sha384(uart: &mut UartController<'_>, hace: ast1060_pac::Hace) {
     // Demonstrate controller recovery by using it again
     let context2 = recovered_controller.init(Sha2_384).unwrap();
     let context2 = context2.update(b"Reused controller").unwrap();
-    let (_digest2, _final_controller) = context2.finalize().unwrap();
+    let (_digest2, controller) = context2.finalize().unwrap();
 
     writeln!(uart, "Controller recovery: PASSED ✅").unwrap();
+    controller
 }
 
 /// Test owned SHA512 API demonstrating cancellation
-fn test_owned_sha512(uart: &mut UartController<'_>, hace: ast1060_pac::Hace) {
-    let controller = HaceController::new(hace);
-
+#[cfg(feature = "sha512")]
+fn test_owned_sha512(
+    uart: &mut UartController<'_>,
+    result: &mut TestResult,
+    controller: HaceController,
+) -> HaceController {
     let context = controller.init(Sha2_512).unwrap();
     let context = context.update(b"This will be").unwrap();
     let context = context.update(b" cancelled").unwrap();
@@ -286,9 +473,9 @@ fn test_owned_sha512(uart: &mut UartController<'_>, hace: ast1060_pac::Hace) {
     let context = recovered_controller.init(Sha2_512).unwrap();
     let context = context.update(b"abc").unwrap();
 
-    let (digest, _final_controller) = context.finalize().unwrap();
+    let (digest, controller) = context.finalize().unwrap();
 
-    writeln!(uart, "SHA512 owned API digest: {:02x?}", &digest.value[..8]).unwrap();
+    writeln!(uart, "SHA512 owned API digest: {:?}", digest.to_bytes()).unwrap();
 
     // Known test vector for "abc"
     let expected_sha512 = [
@@ -299,79 +486,213 @@ fn test_owned_sha512(uart: &mut UartController<'_>, hace: ast1060_pac::Hace) {
         0xa5, 0x4c, 0xa4, 0x9f,
     ];
 
-    if validate_digest(&digest.value, &expected_sha512, "SHA512", uart) {
+    if result.check(validate_digest(
+        &digest.to_bytes(),
+        &expected_sha512,
+        "SHA512",
+        uart,
+    )) {
         writeln!(uart, "SHA512 owned API: PASSED ✅").unwrap();
     } else {
         writeln!(uart, "SHA512 owned API: FAILED ❌").unwrap();
     }
+    controller
+}
+
+/// Runs the functional tests in order and totals their results
+fn run_all(
+    uart: &mut UartController<'_>,
+    mut hace: HaceController,
+    syscon: &mut SysCon<DummyDelay>,
+    #[cfg(any(feature = "ecdsa", feature = "rsa"))] secure: &Secure,
+    gpio: GpioPorts,
+    wdt0: WdtController<Wdt>,
+    wdt1: WdtController<Wdt1>,
+) -> TestSummary {
+    let mut summary = TestSummary::new();
+    summary.add(run_board_tests(uart));
+
+    summary.add(run_hash_tests(uart, &mut hace));
+    #[cfg(feature = "sha512")]
+    {
+        summary.add(run_hash_interleave_tests(uart, &mut hace));
+        summary.add(run_hash_stream_tests(uart, &mut hace));
+        summary.add(run_hash_iter_tests(uart, &mut hace));
+    }
+    summary.add(run_session_stress_test(uart, &mut hace));
+    summary.add(run_aligned_session_test(uart, &mut hace));
+    summary.add(run_session_reap_test(uart, &mut hace));
+    boot_trace::mark(Milestone::HacePostDone);
+
+    #[cfg(feature = "hmac")]
+    {
+        summary.add(run_hmac_tests(uart, &mut hace));
+        summary.add(run_host_auth_tests(uart, &mut hace));
+    }
+    summary.add(run_hash_iv_tests(uart, &mut hace));
+    summary.add(run_hash_empty_tests(uart, &mut hace));
+    summary.add(run_hace_ownership_test(uart, &mut hace));
+    summary.add(run_hace_recover_test(uart, &mut hace, syscon));
+    summary.add(run_hash_caps_test(uart, &mut hace));
+    summary.add(run_coop_tests(uart, &mut hace, CPU_HZ));
+    #[cfg(feature = "hmac")]
+    let hace = {
+        let (result, hace) = run_hmac_oneshot_test(uart, hace);
+        summary.add(result);
+        hace
+    };
+
+    // Test the owned digest API
+    let (result, hace) = test_owned_digest_api(uart, hace);
+    summary.add(result);
+    #[cfg(feature = "test-utils")]
+    let hace = {
+        let (result, hace) =
+            aspeed_ddk::tests::functional::measure_test::run_measure_region_test(uart, hace);
+        summary.add(result);
+        hace
+    };
+    boot_trace::mark(Milestone::HmacTests);
+
+    #[cfg(feature = "ecdsa")]
+    {
+        let mut ecdsa = AspeedEcdsa::new(secure, DummyDelay);
+        summary.add(run_ecdsa_tests(uart, &mut ecdsa));
+        summary.add(run_ecdsa_decision_tests(uart, &mut ecdsa));
+        summary.add(run_ecdsa_batch_tests(uart, &mut ecdsa));
+        boot_trace::mark(Milestone::EcdsaTests);
+    }
+
+    #[cfg(feature = "rsa")]
+    {
+        let mut rsa = AspeedRsa::new(secure, DummyDelay);
+        summary.add(run_rsa_tests(uart, &mut rsa));
+        boot_trace::mark(Milestone::RsaTests);
+    }
+    let (result, pa4) = gpio_test::test_gpioa(uart, gpio.gpioa);
+    summary.add(result);
+    summary.add(gpio_test::test_gpio_doorbell(uart, pa4));
+    summary.add(gpio_test::test_pin_state(uart));
+    boot_trace::mark(Milestone::GpioTests);
+    summary.add(i2c_test::test_i2c_master(uart));
+    summary.add(i2c_test::test_i2c_master_byte_mode(uart));
+    summary.add(i2c_test::test_i2c_mixed_speed(uart));
+    summary.add(i2c_test::test_i2c_lifecycle(uart));
+    summary.add(i2c_test::test_i2c_dma_pool(uart, CPU_HZ));
+    summary.add(i2c_test::test_i2c_timeout_recovery(uart, CPU_HZ));
+    #[cfg(feature = "i2c_target")]
+    summary.add(i2c_test::test_i2c_slave(uart));
+    // re-registers i2c0, so the byte mode target is the one left answering
+    // the host
+    #[cfg(feature = "i2c_target")]
+    summary.add(i2c_test::test_i2c_slave_byte_mode(uart));
+    // i2c2 and i2c3 wired together; skipped when they are not
+    #[cfg(all(feature = "rt", feature = "i2c_target"))]
+    summary.add(i2c_loopback_test::run_i2c_loopback_tests::<
+        ast1060_pac::I2c2,
+        ast1060_pac::I2c3,
+    >(
+        uart,
+        aspeed_ddk::pinctrl::PINCTRL_I2C2,
+        aspeed_ddk::pinctrl::PINCTRL_I2C3,
+        unsafe { &mut *core::ptr::addr_of_mut!(LOOPBACK_TARGET) },
+    ));
+    boot_trace::mark(Milestone::I2cTests);
+    test_wdt(uart, wdt0, wdt1);
+    boot_trace::mark(Milestone::WdtTests);
+    summary.add(run_timer_tests(uart));
+    boot_trace::mark(Milestone::TimerTests);
+    summary.add(run_host_policy_tests(uart));
+    summary.add(run_transition_auth_tests(uart));
+    boot_trace::mark(Milestone::HostPolicyTests);
+    #[cfg(feature = "rt")]
+    {
+        summary.add(aspeed_ddk::tests::functional::interrupts_test::run_interrupts_tests(uart));
+        boot_trace::mark(Milestone::InterruptTests);
+        summary.add(
+            aspeed_ddk::tests::functional::latency_test::run_latency_tests(
+                uart, hace, gpio.gpiob, CPU_HZ,
+            ),
+        );
+        summary.add(
+            aspeed_ddk::tests::functional::soft_watchdog_test::run_soft_watchdog_tests(
+                uart, CPU_HZ,
+            ),
+        );
+    }
+    #[cfg(not(feature = "rt"))]
+    drop(hace);
+
+    // Opt-in: the suite reprograms the scratch sectors of SPI_TARGETS
+    #[cfg(feature = "test-spi")]
+    {
+        gpio_test::test_gpio_flash_power(uart, gpio.gpiol);
+        summary.add(spi_test::run_spi_tests(uart, SPI_TARGETS));
+        boot_trace::mark(Milestone::SpiTests);
+    }
+    summary
 }
 
 #[entry]
 fn main() -> ! {
-    let peripherals = unsafe { Peripherals::steal() };
-    let uart = peripherals.uart;
+    boot_trace::init();
+    boot_trace::mark(Milestone::Start);
+    let board = Board::take().unwrap();
     let mut delay = DummyDelay;
 
     // For jlink attach
     // set aspeed_ddk::__cortex_m_rt_main::HALT.v.value = 0 in gdb
     // debug_halt!();
-    let mut uart_controller = UartController::new(uart, &mut delay);
+    let mut uart_controller = UartController::new(board.uart, &mut delay);
     unsafe {
         uart_controller.init(&Config {
-            baud_rate: 115_200,
+            baud_rate: CONSOLE_BAUD,
             word_length: aspeed_ddk::uart::WordLength::Eight as u8,
             parity: aspeed_ddk::uart::Parity::None,
             stop_bits: aspeed_ddk::uart::StopBits::One,
-            clock: 24_000_000,
+            clock: CONSOLE_CLOCK,
         });
     }
+    boot_trace::mark(Milestone::UartUp);
 
-    let hace = peripherals.hace;
-    let scu = peripherals.scu;
-    let secure = peripherals.secure;
+    #[cfg(any(feature = "ecdsa", feature = "rsa"))]
+    let secure = board.secure;
 
     writeln!(uart_controller, "\r\nHello, world!!\r\n").unwrap();
+    version::FIRMWARE.print(&mut uart_controller);
 
-    let delay = DummyDelay;
-    let mut syscon = SysCon::new(delay.clone(), scu);
+    let mut syscon = SysCon::new(DummyDelay, board.scu);
 
+    let mut logger = UartLogger::new(&mut uart_controller);
     // Enable HACE (Hash and Crypto Engine)
-    let _ = syscon.enable_clock(ClockId::ClkYCLK as u8);
+    let _ = syscon
+        .enable_clock(ClockId::ClkYCLK as u8)
+        .log_err(&mut logger, "enable HACE clock");
     let reset_id = ResetId::RstHACE;
-    let _ = syscon.reset_deassert(&reset_id);
-
-    let mut hace_controller = HaceController::new(hace);
-
-    run_hash_tests(&mut uart_controller, &mut hace_controller);
-
-    run_hmac_tests(&mut uart_controller, &mut hace_controller);
-
-    // Test the owned digest API
-    test_owned_digest_api(&mut uart_controller);
-
+    let _ = syscon
+        .reset_deassert(&reset_id)
+        .log_err(&mut logger, "deassert HACE reset");
     // Enable RSA and ECC
-    let _ = syscon.enable_clock(ClockId::ClkRSACLK as u8);
-
-    let mut ecdsa = AspeedEcdsa::new(&secure, delay.clone());
-    run_ecdsa_tests(&mut uart_controller, &mut ecdsa);
-
-    let mut rsa = AspeedRsa::new(&secure, delay);
-    run_rsa_tests(&mut uart_controller, &mut rsa);
-    gpio_test::test_gpioa(&mut uart_controller);
-    i2c_test::test_i2c_master(&mut uart_controller);
-    #[cfg(feature = "i2c_target")]
-    i2c_test::test_i2c_slave(&mut uart_controller);
-    test_wdt(&mut uart_controller);
-    run_timer_tests(&mut uart_controller);
-
-    let test_spicontroller = false;
-    if test_spicontroller {
-        spi::spitest::test_fmc(&mut uart_controller);
-        spi::spitest::test_spi(&mut uart_controller);
-
-        gpio_test::test_gpio_flash_power(&mut uart_controller);
-        spi::spitest::test_spi2(&mut uart_controller);
-    }
+    #[cfg(any(feature = "ecdsa", feature = "rsa"))]
+    let _ = syscon
+        .enable_clock(ClockId::ClkRSACLK as u8)
+        .log_err(&mut logger, "enable RSA clock");
+    boot_trace::mark(Milestone::ClockInit);
+
+    let summary = run_all(
+        &mut uart_controller,
+        HaceController::new(board.hace),
+        &mut syscon,
+        #[cfg(any(feature = "ecdsa", feature = "rsa"))]
+        &secure,
+        board.gpio,
+        board.wdt0,
+        board.wdt1,
+    );
+    boot_trace::mark(Milestone::Done);
+    boot_trace::report(&mut uart_controller, CPU_HZ);
+    summary.report(&mut uart_controller);
+    TEST_STATUS.store(summary.status(), Ordering::SeqCst);
     // Initialize the peripherals here if needed
     loop {
         cortex_m::asm::wfi();
@@ -0,0 +1,276 @@
+// Licensed under the Apache-2.0 license
+
+//! In-memory NOR flash for host-side tests of `BlockDevice` consumers
+//!
+//! [`MockFlash`] behaves like NOR: erase sets a sector to `0xFF` and program
+//! can only clear bits. Programming over data that is not erased either
+//! fails with [`MockFlashError::NotErased`] (strict mode) or stores the
+//! bitwise AND of old and new data, as real parts do.
+//!
+//! Faults can be injected into future program operations:
+//! - [`MockFlash::fail_program_after`] makes a program fail without
+//!   touching the array.
+//! - [`MockFlash::power_cut_after`] stores only the first half of the data
+//!   before failing, like a program interrupted by power loss.
+
+use crate::spi::norflashblockdevice::BlockAddrUsize;
+use proposed_traits::block_device as BD;
+use proposed_traits::block_device::{BlockDevice, BlockRange, ErrorType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockFlashError {
+    OutOfBounds,
+    Misaligned,
+    NotErased,
+    ProgramFailed,
+    PowerCut,
+}
+
+impl BD::Error for MockFlashError {
+    fn kind(&self) -> BD::ErrorKind {
+        match self {
+            MockFlashError::OutOfBounds => BD::ErrorKind::OutOfBounds,
+            MockFlashError::Misaligned
+            | MockFlashError::NotErased
+            | MockFlashError::ProgramFailed
+            | MockFlashError::PowerCut => BD::ErrorKind::ProgramError,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Fault {
+    Fail,
+    PowerCut,
+}
+
+/// `SECTORS` erase sectors of `SECTOR` bytes each
+pub struct MockFlash<const SECTORS: usize, const SECTOR: usize> {
+    mem: [[u8; SECTOR]; SECTORS],
+    page_size: usize,
+    strict: bool,
+    fault: Option<(usize, Fault)>,
+    program_count: usize,
+    erase_count: usize,
+}
+
+impl<const SECTORS: usize, const SECTOR: usize> MockFlash<SECTORS, SECTOR> {
+    /// Creates an erased, strict device programmed in `page_size` units
+    #[must_use]
+    pub fn new(page_size: usize) -> Self {
+        assert!(page_size > 0 && SECTOR % page_size == 0);
+        Self {
+            mem: [[0xFF; SECTOR]; SECTORS],
+            page_size,
+            strict: true,
+            fault: None,
+            program_count: 0,
+            erase_count: 0,
+        }
+    }
+
+    /// Selects whether programming non-erased bytes is an error
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// The program after the next `programs` successful ones fails
+    pub fn fail_program_after(&mut self, programs: usize) {
+        self.fault = Some((programs, Fault::Fail));
+    }
+
+    /// The program after the next `programs` successful ones is torn
+    pub fn power_cut_after(&mut self, programs: usize) {
+        self.fault = Some((programs, Fault::PowerCut));
+    }
+
+    pub fn clear_faults(&mut self) {
+        self.fault = None;
+    }
+
+    #[must_use]
+    pub fn program_count(&self) -> usize {
+        self.program_count
+    }
+
+    #[must_use]
+    pub fn erase_count(&self) -> usize {
+        self.erase_count
+    }
+
+    #[must_use]
+    pub fn byte(&self, offset: usize) -> u8 {
+        self.mem[offset / SECTOR][offset % SECTOR]
+    }
+
+    /// Overwrites a byte directly, bypassing NOR semantics
+    pub fn set_byte(&mut self, offset: usize, value: u8) {
+        self.mem[offset / SECTOR][offset % SECTOR] = value;
+    }
+
+    fn check_range(offset: usize, len: usize) -> Result<(), MockFlashError> {
+        match offset.checked_add(len) {
+            Some(end) if end <= SECTORS * SECTOR => Ok(()),
+            _ => Err(MockFlashError::OutOfBounds),
+        }
+    }
+
+    fn take_fault(&mut self) -> Option<Fault> {
+        match self.fault {
+            Some((0, fault)) => {
+                self.fault = None;
+                Some(fault)
+            }
+            Some((n, fault)) => {
+                self.fault = Some((n - 1, fault));
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+impl<const SECTORS: usize, const SECTOR: usize> ErrorType for MockFlash<SECTORS, SECTOR> {
+    type Error = MockFlashError;
+}
+
+impl<const SECTORS: usize, const SECTOR: usize> BlockDevice for MockFlash<SECTORS, SECTOR> {
+    type Address = BlockAddrUsize;
+
+    fn read_size(&self) -> usize {
+        1
+    }
+
+    fn read(&mut self, address: Self::Address, data: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = address.to_byte_offset();
+        Self::check_range(offset, data.len())?;
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = self.byte(offset + i);
+        }
+        Ok(())
+    }
+
+    fn erase_size(&self) -> usize {
+        SECTOR
+    }
+
+    fn erase(&mut self, range: BlockRange<Self::Address>) -> Result<(), Self::Error> {
+        let offset = range.start.to_byte_offset();
+        if offset % SECTOR != 0 {
+            return Err(MockFlashError::Misaligned);
+        }
+        Self::check_range(offset, range.count * SECTOR)?;
+        for sector in &mut self.mem[offset / SECTOR..offset / SECTOR + range.count] {
+            sector.fill(0xFF);
+        }
+        self.erase_count += range.count;
+        Ok(())
+    }
+
+    fn program_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn program(&mut self, address: Self::Address, data: &[u8]) -> Result<(), Self::Error> {
+        let offset = address.to_byte_offset();
+        if offset % self.page_size != 0 || data.len() % self.page_size != 0 {
+            return Err(MockFlashError::Misaligned);
+        }
+        Self::check_range(offset, data.len())?;
+        if self.strict
+            && data
+                .iter()
+                .enumerate()
+                .any(|(i, &b)| self.byte(offset + i) & b != b)
+        {
+            return Err(MockFlashError::NotErased);
+        }
+
+        let (len, result) = match self.take_fault() {
+            Some(Fault::Fail) => return Err(MockFlashError::ProgramFailed),
+            Some(Fault::PowerCut) => (data.len() / 2, Err(MockFlashError::PowerCut)),
+            None => (data.len(), Ok(())),
+        };
+        for (i, &b) in data[..len].iter().enumerate() {
+            let old = self.byte(offset + i);
+            self.set_byte(offset + i, old & b);
+        }
+        self.program_count += 1;
+        result
+    }
+
+    fn capacity(&self) -> usize {
+        SECTORS * SECTOR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MockFlash, MockFlashError};
+    use crate::spi::norflashblockdevice::BlockAddrUsize;
+    use proposed_traits::block_device::{BlockDevice, BlockRange};
+
+    type Flash = MockFlash<2, 64>;
+
+    #[test]
+    fn test_nor_semantics() {
+        let mut flash = Flash::new(16);
+        let mut buf = [0u8; 16];
+        flash.program(BlockAddrUsize(16), &[0xF0; 16]).unwrap();
+        flash.read(BlockAddrUsize(16), &mut buf).unwrap();
+        assert_eq!(buf, [0xF0; 16]);
+
+        // Setting bits again needs an erase
+        assert_eq!(
+            flash.program(BlockAddrUsize(16), &[0x0F; 16]),
+            Err(MockFlashError::NotErased)
+        );
+        flash.set_strict(false);
+        flash.program(BlockAddrUsize(16), &[0x3C; 16]).unwrap();
+        flash.read(BlockAddrUsize(16), &mut buf).unwrap();
+        assert_eq!(buf, [0x30; 16]);
+
+        flash
+            .erase(BlockRange {
+                start: BlockAddrUsize(0),
+                count: 1,
+            })
+            .unwrap();
+        flash.read(BlockAddrUsize(16), &mut buf).unwrap();
+        assert_eq!(buf, [0xFF; 16]);
+        assert_eq!(flash.erase_count(), 1);
+
+        assert_eq!(
+            flash.program(BlockAddrUsize(8), &[0; 16]),
+            Err(MockFlashError::Misaligned)
+        );
+        assert_eq!(
+            flash.read(BlockAddrUsize(120), &mut buf),
+            Err(MockFlashError::OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn test_fault_injection() {
+        let mut flash = Flash::new(16);
+        flash.fail_program_after(1);
+        flash.program(BlockAddrUsize(0), &[0; 16]).unwrap();
+        assert_eq!(
+            flash.program(BlockAddrUsize(16), &[0; 16]),
+            Err(MockFlashError::ProgramFailed)
+        );
+        assert_eq!(flash.byte(16), 0xFF);
+
+        flash.power_cut_after(0);
+        assert_eq!(
+            flash.program(BlockAddrUsize(32), &[0; 16]),
+            Err(MockFlashError::PowerCut)
+        );
+        assert_eq!(flash.byte(39), 0);
+        assert_eq!(flash.byte(40), 0xFF);
+
+        // Faults are one-shot
+        flash.program(BlockAddrUsize(48), &[0; 16]).unwrap();
+        assert_eq!(flash.program_count(), 3);
+    }
+}
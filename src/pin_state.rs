@@ -0,0 +1,442 @@
+// Licensed under the Apache-2.0 license
+
+//! Snapshot and restore of GPIO and pin mux state
+//!
+//! Recovery and update flows borrow pins, for example to take the SPI
+//! muxes over from the host, and must hand them back exactly as they were.
+//! [`PinStateSnapshot::capture`] saves the direction, output value and
+//! command source of a declared set of GPIO pins and the SCU mux bits of a
+//! set of pinctrl groups into a buffer the caller provides, one
+//! [`SavedReg`] per register touched. [`PinStateSnapshot::restore`] writes
+//! them back and reads every register again to check.
+//!
+//! Restore order matters. GPIO values go before directions, so a pin that
+//! turns back into an output drives its old level from the start. Both go
+//! before command sources, while this core still owns the banks. The muxes
+//! are last, so pins return to GPIO only once their state is right.
+
+use crate::gpio::GpioBank;
+use crate::pinctrl::PinctrlPin;
+
+/// Pins of one GPIO bank covered by a snapshot
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GpioPins {
+    pub bank: GpioBank,
+    /// Bit `n` selects pin `n` of the bank
+    pub mask: u8,
+}
+
+impl GpioPins {
+    /// All eight pins of `bank`
+    #[must_use]
+    pub const fn all(bank: GpioBank) -> Self {
+        Self { bank, mask: 0xff }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinStateError {
+    /// The buffer given to `capture` has too few entries
+    BufferTooSmall,
+    /// The GPIO register at this offset read back wrong after a restore
+    Gpio(u32),
+    /// The SCU register at this offset read back wrong after a restore
+    Scu(u32),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Block {
+    #[default]
+    Gpio,
+    Scu,
+}
+
+/// The saved bits of one register
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SavedReg {
+    block: Block,
+    /// Register written on restore
+    offset: u32,
+    /// Register read on capture and verify; the data read-back register
+    /// for GPIO values, as reading the data register gives the pad levels
+    readback: u32,
+    mask: u32,
+    bits: u32,
+}
+
+/// Registers of a group of four GPIO banks
+struct GroupRegs {
+    data: u32,
+    data_read: u32,
+    dir: u32,
+    cmd_src0: u32,
+    cmd_src1: u32,
+}
+
+/// By group: ABCD, EFGH, IJKL, MNOP, QRST, U
+const GROUP_REGS: [GroupRegs; 6] = [
+    GroupRegs {
+        data: 0x000,
+        data_read: 0x0c0,
+        dir: 0x004,
+        cmd_src0: 0x060,
+        cmd_src1: 0x064,
+    },
+    GroupRegs {
+        data: 0x020,
+        data_read: 0x0c4,
+        dir: 0x024,
+        cmd_src0: 0x068,
+        cmd_src1: 0x06c,
+    },
+    GroupRegs {
+        data: 0x070,
+        data_read: 0x0b8,
+        dir: 0x074,
+        cmd_src0: 0x090,
+        cmd_src1: 0x094,
+    },
+    GroupRegs {
+        data: 0x078,
+        data_read: 0x0cc,
+        dir: 0x07c,
+        cmd_src0: 0x0e0,
+        cmd_src1: 0x0e4,
+    },
+    GroupRegs {
+        data: 0x080,
+        data_read: 0x0d0,
+        dir: 0x084,
+        cmd_src0: 0x110,
+        cmd_src1: 0x114,
+    },
+    GroupRegs {
+        data: 0x088,
+        data_read: 0x0d4,
+        dir: 0x08c,
+        cmd_src0: 0x140,
+        cmd_src1: 0x144,
+    },
+];
+
+fn group_regs(bank: GpioBank) -> (&'static GroupRegs, u32) {
+    (&GROUP_REGS[bank as usize / 4], (bank as u32 % 4) * 8)
+}
+
+trait RegisterIo {
+    fn read(&self, block: Block, offset: u32) -> u32;
+    fn write(&mut self, block: Block, offset: u32, value: u32);
+}
+
+struct Hardware;
+
+impl Hardware {
+    fn reg(block: Block, offset: u32) -> *mut u32 {
+        let base = match block {
+            Block::Gpio => ast1060_pac::Gpio::ptr() as usize,
+            Block::Scu => ast1060_pac::Scu::ptr() as usize,
+        };
+        (base + offset as usize) as *mut u32
+    }
+}
+
+impl RegisterIo for Hardware {
+    fn read(&self, block: Block, offset: u32) -> u32 {
+        unsafe { core::ptr::read_volatile(Self::reg(block, offset)) }
+    }
+
+    fn write(&mut self, block: Block, offset: u32, value: u32) {
+        unsafe { core::ptr::write_volatile(Self::reg(block, offset), value) }
+    }
+}
+
+/// Collects the registers of a snapshot, merging those named twice
+struct Filler<'b> {
+    buf: &'b mut [SavedReg],
+    len: usize,
+}
+
+impl Filler<'_> {
+    fn add(
+        &mut self,
+        block: Block,
+        offset: u32,
+        readback: u32,
+        mask: u32,
+    ) -> Result<(), PinStateError> {
+        if let Some(saved) = self.buf[..self.len]
+            .iter_mut()
+            .find(|r| r.block == block && r.offset == offset)
+        {
+            saved.mask |= mask;
+            return Ok(());
+        }
+        let slot = self
+            .buf
+            .get_mut(self.len)
+            .ok_or(PinStateError::BufferTooSmall)?;
+        *slot = SavedReg {
+            block,
+            offset,
+            readback,
+            mask,
+            bits: 0,
+        };
+        self.len += 1;
+        Ok(())
+    }
+}
+
+/// Saved pin state, in restore order
+#[derive(Debug, PartialEq, Eq)]
+pub struct PinStateSnapshot<'a> {
+    regs: &'a [SavedReg],
+}
+
+impl<'a> PinStateSnapshot<'a> {
+    /// Saves the state of `banks` and the mux bits of `pinctrl_groups`
+    /// into `buf`, which needs an entry per register touched: four per
+    /// group of four banks, plus one per SCU register.
+    pub fn capture(
+        banks: &[GpioPins],
+        pinctrl_groups: &[&[PinctrlPin]],
+        buf: &'a mut [SavedReg],
+    ) -> Result<Self, PinStateError> {
+        Self::capture_from(&Hardware, banks, pinctrl_groups, buf)
+    }
+
+    fn capture_from(
+        io: &impl RegisterIo,
+        banks: &[GpioPins],
+        pinctrl_groups: &[&[PinctrlPin]],
+        buf: &'a mut [SavedReg],
+    ) -> Result<Self, PinStateError> {
+        let mut filler = Filler { buf, len: 0 };
+        for pins in banks {
+            let (regs, shift) = group_regs(pins.bank);
+            let mask = u32::from(pins.mask) << shift;
+            filler.add(Block::Gpio, regs.data, regs.data_read, mask)?;
+        }
+        for pins in banks {
+            let (regs, shift) = group_regs(pins.bank);
+            let mask = u32::from(pins.mask) << shift;
+            filler.add(Block::Gpio, regs.dir, regs.dir, mask)?;
+        }
+        // the command source belongs to the whole bank
+        for pins in banks {
+            let (regs, shift) = group_regs(pins.bank);
+            let mask = 0xff << shift;
+            filler.add(Block::Gpio, regs.cmd_src0, regs.cmd_src0, mask)?;
+            filler.add(Block::Gpio, regs.cmd_src1, regs.cmd_src1, mask)?;
+        }
+        for pin in pinctrl_groups.iter().flat_map(|group| group.iter()) {
+            filler.add(Block::Scu, pin.offset, pin.offset, 1 << pin.bit)?;
+        }
+
+        let Filler { buf, len } = filler;
+        let regs = &mut buf[..len];
+        for saved in regs.iter_mut() {
+            saved.bits = io.read(saved.block, saved.readback) & saved.mask;
+        }
+        Ok(Self { regs })
+    }
+
+    /// Registers saved, GPIO and SCU together
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.regs.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.regs.is_empty()
+    }
+
+    /// Writes the saved state back, leaving the other bits of each
+    /// register alone, then checks every register.
+    ///
+    /// All registers are written even if one fails to verify; the error
+    /// names the first that reads back wrong.
+    pub fn restore(&self) -> Result<(), PinStateError> {
+        self.restore_to(&mut Hardware)
+    }
+
+    fn restore_to(&self, io: &mut impl RegisterIo) -> Result<(), PinStateError> {
+        for saved in self.regs {
+            let current = io.read(saved.block, saved.readback);
+            io.write(
+                saved.block,
+                saved.offset,
+                (current & !saved.mask) | saved.bits,
+            );
+        }
+        self.verify(io)
+    }
+
+    fn verify(&self, io: &impl RegisterIo) -> Result<(), PinStateError> {
+        match self
+            .regs
+            .iter()
+            .find(|s| io.read(s.block, s.readback) & s.mask != s.bits)
+        {
+            None => Ok(()),
+            Some(s) if s.block == Block::Gpio => Err(PinStateError::Gpio(s.readback)),
+            Some(s) => Err(PinStateError::Scu(s.readback)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Block, GpioPins, PinStateError, PinStateSnapshot, RegisterIo, SavedReg, GROUP_REGS,
+    };
+    use crate::gpio::GpioBank;
+    use crate::pinctrl::{PinctrlPin, PINCTRL_GPIOA0, PINCTRL_GPIOL2};
+    use std::collections::BTreeMap;
+
+    /// Register file; writes to a GPIO data register show in its read-back
+    /// register, and `stuck` bits never change
+    #[derive(Default)]
+    struct FakeIo {
+        regs: BTreeMap<(u8, u32), u32>,
+        stuck: u32,
+    }
+
+    impl RegisterIo for FakeIo {
+        fn read(&self, block: Block, offset: u32) -> u32 {
+            self.regs.get(&(block as u8, offset)).copied().unwrap_or(0)
+        }
+
+        fn write(&mut self, block: Block, offset: u32, value: u32) {
+            let old = self.read(block, offset);
+            let value = (value & !self.stuck) | (old & self.stuck);
+            self.regs.insert((block as u8, offset), value);
+            if let Some(g) = GROUP_REGS.iter().find(|g| g.data == offset) {
+                if block == Block::Gpio {
+                    self.regs.insert((block as u8, g.data_read), value);
+                }
+            }
+        }
+    }
+
+    impl FakeIo {
+        fn scramble(&mut self) {
+            for value in self.regs.values_mut() {
+                *value = !*value;
+            }
+        }
+    }
+
+    const MUX: &[PinctrlPin] = &[
+        PinctrlPin {
+            offset: 0x410,
+            bit: 3,
+            clear: true,
+        },
+        PinctrlPin {
+            offset: 0x410,
+            bit: 4,
+            clear: false,
+        },
+    ];
+
+    fn seeded() -> FakeIo {
+        let mut io = FakeIo::default();
+        let mut seed = 0x1234_5678u32;
+        for (block, offset) in [
+            (Block::Gpio, 0x000),
+            (Block::Gpio, 0x004),
+            (Block::Gpio, 0x060),
+            (Block::Gpio, 0x064),
+            (Block::Gpio, 0x070),
+            (Block::Gpio, 0x074),
+            (Block::Gpio, 0x090),
+            (Block::Gpio, 0x094),
+            (Block::Scu, 0x410),
+            (Block::Scu, 0x418),
+            (Block::Scu, 0x4b0),
+            (Block::Scu, 0x690),
+        ] {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            io.write(block, offset, seed);
+        }
+        io
+    }
+
+    #[test]
+    fn test_capture_merges_registers() {
+        let io = seeded();
+        let mut buf = [SavedReg::default(); 16];
+        let banks = [
+            GpioPins {
+                bank: GpioBank::A,
+                mask: 0x1f,
+            },
+            GpioPins::all(GpioBank::B),
+        ];
+        let snapshot =
+            PinStateSnapshot::capture_from(&io, &banks, &[MUX, PINCTRL_GPIOA0], &mut buf).unwrap();
+        // data, dir, cmd_src0, cmd_src1 shared by A and B, then SCU410,
+        // SCU4B0 and SCU690
+        assert_eq!(snapshot.len(), 7);
+        assert_eq!(snapshot.regs[0].mask, 0xff1f);
+        assert_eq!(snapshot.regs[0].readback, 0x0c0);
+        assert_eq!(snapshot.regs[2].mask, 0xffff);
+        assert_eq!(snapshot.regs[4].offset, 0x410);
+        assert_eq!(snapshot.regs[4].mask, 0x19);
+
+        let mut small = [SavedReg::default(); 4];
+        assert_eq!(
+            PinStateSnapshot::capture_from(&io, &banks, &[MUX], &mut small),
+            Err(PinStateError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn test_restore_after_scramble() {
+        let mut io = seeded();
+        let banks = [
+            GpioPins {
+                bank: GpioBank::A,
+                mask: 0x1f,
+            },
+            GpioPins {
+                bank: GpioBank::L,
+                mask: 0x0c,
+            },
+        ];
+        let groups = [MUX, PINCTRL_GPIOL2];
+        let mut buf = [SavedReg::default(); 16];
+        let before = PinStateSnapshot::capture_from(&io, &banks, &groups, &mut buf).unwrap();
+
+        io.scramble();
+        let untouched = io.read(Block::Gpio, 0x004) & !0x1f;
+        assert_eq!(before.restore_to(&mut io), Ok(()));
+
+        let mut again = [SavedReg::default(); 16];
+        let after = PinStateSnapshot::capture_from(&io, &banks, &groups, &mut again).unwrap();
+        assert_eq!(before, after);
+        assert_eq!(io.read(Block::Gpio, 0x004) & !0x1f, untouched);
+    }
+
+    #[test]
+    fn test_restore_reports_stuck_register() {
+        let mut io = seeded();
+        let banks = [GpioPins::all(GpioBank::A)];
+        let mut buf = [SavedReg::default(); 8];
+        let before = PinStateSnapshot::capture_from(&io, &banks, &[MUX], &mut buf).unwrap();
+
+        io.scramble();
+        io.stuck = 1 << 3;
+        // the data register is first to read back wrong
+        assert_eq!(before.restore_to(&mut io), Err(PinStateError::Gpio(0x0c0)));
+
+        let mut io = seeded();
+        let mut buf = [SavedReg::default(); 8];
+        let before = PinStateSnapshot::capture_from(&io, &[], &[MUX], &mut buf).unwrap();
+        io.scramble();
+        io.stuck = 1 << 4;
+        assert_eq!(before.restore_to(&mut io), Err(PinStateError::Scu(0x410)));
+    }
+}
@@ -9,6 +9,24 @@ pub struct PinctrlPin {
     pub clear: bool,
 }
 
+/// Multi-bit pad setting, such as drive strength or slew rate, in an SCU
+/// register
+pub struct PinctrlDrive {
+    /// Register offset from the SCU base
+    pub offset: u32,
+    pub shift: u32,
+    /// Field width in bits, 1 to 32
+    pub width: u32,
+    pub value: u32,
+}
+
+impl PinctrlDrive {
+    fn update(&self, reg: u32) -> u32 {
+        let mask = (u32::MAX >> (32 - self.width)) << self.shift;
+        (reg & !mask) | ((self.value << self.shift) & mask)
+    }
+}
+
 macro_rules! gen_pin_pairs {
     ($reg_name:ident, $offset:expr, $bit:expr) => {
         paste! {
@@ -1937,4 +1955,40 @@ impl Pinctrl {
             } //match
         } //for
     }
+
+    /// Write pad drive settings to SCU registers
+    pub fn apply_drive_group(drives: &[PinctrlDrive]) {
+        let base = ast1060_pac::Scu::ptr() as usize;
+        for drive in drives {
+            let reg = (base + drive.offset as usize) as *mut u32;
+            unsafe {
+                core::ptr::write_volatile(reg, drive.update(core::ptr::read_volatile(reg)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drive_update() {
+        let drive = PinctrlDrive {
+            offset: 0,
+            shift: 4,
+            width: 2,
+            value: 0b10,
+        };
+        assert_eq!(drive.update(0xffff_ffff), 0xffff_ffef);
+        assert_eq!(drive.update(0), 0x20);
+
+        let whole = PinctrlDrive {
+            offset: 0,
+            shift: 0,
+            width: 32,
+            value: 0x1234_5678,
+        };
+        assert_eq!(whole.update(0xdead_beef), 0x1234_5678);
+    }
 }
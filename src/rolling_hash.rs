@@ -0,0 +1,164 @@
+// Licensed under the Apache-2.0 license
+
+//! Buzhash rolling hash for content-defined chunking
+//!
+//! The hash engine only digests whole messages, so finding chunk boundaries
+//! is done in software: [`RollingHash`] keeps a 32-bit hash of the last
+//! `window` bytes that is updated in constant time per byte, and a boundary
+//! is declared where the hash matches a mask. The chunks are then digested
+//! by the hash engine as usual.
+//!
+//! The byte table is generated from a fixed seed, so boundaries are stable
+//! across builds and between devices.
+
+/// Per-byte values, from a splitmix32 sequence
+const TABLE: [u32; 256] = table();
+
+const fn table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut state = 0x9E37_79B9u32;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9);
+        let mut z = state;
+        z = (z ^ (z >> 16)).wrapping_mul(0x85EB_CA6B);
+        z = (z ^ (z >> 13)).wrapping_mul(0xC2B2_AE35);
+        table[i] = z ^ (z >> 16);
+        i += 1;
+    }
+    table
+}
+
+/// Hash of a sliding window of bytes.
+///
+/// The caller owns the window: every byte passed to [`Self::roll_in`] once
+/// the window is full must be matched by a [`Self::roll_out`] of the byte
+/// leaving it.
+#[derive(Clone, Debug)]
+pub struct RollingHash {
+    hash: u32,
+    /// Rotation the oldest byte of a full window has picked up
+    out_rotation: u32,
+}
+
+impl RollingHash {
+    /// Hash over windows of `window` bytes, starting empty
+    #[must_use]
+    pub fn new(window: usize) -> Self {
+        Self {
+            hash: 0,
+            out_rotation: u32::try_from((window + 31) % 32).unwrap(),
+        }
+    }
+
+    /// Adds `byte` at the head of the window
+    pub fn roll_in(&mut self, byte: u8) {
+        self.hash = self.hash.rotate_left(1) ^ TABLE[usize::from(byte)];
+    }
+
+    /// Removes `byte`, which entered `window` bytes ago, from the tail.
+    /// Call it before the matching [`Self::roll_in`].
+    pub fn roll_out(&mut self, byte: u8) {
+        self.hash ^= TABLE[usize::from(byte)].rotate_left(self.out_rotation);
+    }
+
+    /// Replaces `out` at the tail with `byte` at the head
+    pub fn roll(&mut self, out: u8, byte: u8) {
+        self.roll_out(out);
+        self.roll_in(byte);
+    }
+
+    #[must_use]
+    pub fn value(&self) -> u32 {
+        self.hash
+    }
+
+    /// Whether the current window ends a chunk, i.e. the hash bits under
+    /// `mask` are all zero. A mask of `n` bits gives chunks of `2^n` bytes
+    /// on average.
+    #[must_use]
+    pub fn is_boundary(&self, mask: u32) -> bool {
+        self.hash & mask == 0
+    }
+
+    pub fn reset(&mut self) {
+        self.hash = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RollingHash;
+
+    const WINDOW: usize = 16;
+
+    fn data() -> [u8; 200] {
+        let mut data = [0u8; 200];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = u8::try_from((i * 37 + i / 7) % 251).unwrap();
+        }
+        data
+    }
+
+    fn hash_of(window: &[u8]) -> u32 {
+        let mut h = RollingHash::new(window.len());
+        for &b in window {
+            h.roll_in(b);
+        }
+        h.value()
+    }
+
+    #[test]
+    fn test_rolling_matches_fresh_hash() {
+        let data = data();
+        let mut h = RollingHash::new(WINDOW);
+        for &b in &data[..WINDOW] {
+            h.roll_in(b);
+        }
+        for end in WINDOW..data.len() {
+            h.roll(data[end - WINDOW], data[end]);
+            assert_eq!(h.value(), hash_of(&data[end + 1 - WINDOW..=end]));
+        }
+    }
+
+    #[test]
+    fn test_window_multiple_of_32() {
+        let data = data();
+        let mut h = RollingHash::new(64);
+        for &b in &data[..64] {
+            h.roll_in(b);
+        }
+        h.roll(data[0], data[64]);
+        assert_eq!(h.value(), hash_of(&data[1..65]));
+    }
+
+    #[test]
+    fn test_depends_on_window_content_only() {
+        let data = data();
+        // the same window reached from different histories
+        let mut a = RollingHash::new(WINDOW);
+        let mut b = RollingHash::new(WINDOW);
+        for &x in &data[..WINDOW] {
+            a.roll_in(x);
+        }
+        for &x in &data[50..50 + WINDOW] {
+            b.roll_in(x);
+        }
+        for i in 0..WINDOW {
+            b.roll(data[50 + i], data[i]);
+        }
+        assert_eq!(a.value(), b.value());
+        assert_ne!(a.value(), hash_of(&data[1..=WINDOW]));
+    }
+
+    #[test]
+    fn test_boundary_mask() {
+        let mut h = RollingHash::new(WINDOW);
+        assert!(h.is_boundary(0xFFFF_FFFF));
+        h.roll_in(0x5A);
+        assert!(h.is_boundary(0));
+        assert_eq!(h.is_boundary(0xFF), h.value() & 0xFF == 0);
+        h.reset();
+        assert_eq!(h.value(), 0);
+    }
+}
@@ -1,5 +1,10 @@
 // Licensed under the Apache-2.0 license
 
+// Without the `rsa` feature only the key and signature types remain
+#![cfg_attr(not(feature = "rsa"), allow(dead_code, unused_imports))]
+
+use crate::common::VerifyDecision;
+use crate::keystore::{bit_len, KeyHandle, KeyStore};
 use ast1060_pac::Secure;
 use core::ptr::{read_volatile, write_bytes, write_volatile, NonNull};
 use embedded_hal::delay::DelayNs;
@@ -21,20 +26,47 @@ const SRAM_DST_RESULT: usize = 0x1400;
 const SRAM_SIZE: usize = 0x1800; // SRAM size for RSA operations
 
 const RSA_MAX_LEN: usize = 0x400;
+/// Widest modulus the engine takes
+const RSA_MAX_BITS: u32 = 4096;
+
+/// Widest public exponent [`AspeedRsa`] accepts unless configured
+/// otherwise
+pub const DEFAULT_MAX_E_BITS: u32 = 32;
 
 #[derive(Debug)]
 pub enum RsaDriverError {
+    /// A key or signature slice is shorter than its declared size
     InvalidLength,
     HardwareError,
     VerificationFailed,
+    FaultDetected,
+    InvalidKeyHandle,
+    EvenExponent,
+    /// The public exponent is 1
+    ExponentTooSmall,
+    /// The public exponent is wider than the configured maximum
+    ExponentTooLarge,
+    EvenModulus,
+    /// The modulus is empty, wider than 4096 bits, or its top bit does not
+    /// match the declared bit size
+    ModulusSize,
+    /// The signature is not below the modulus
+    SignatureOutOfRange,
 }
 
 impl Error for RsaDriverError {
     fn kind(&self) -> ErrorKind {
         match self {
-            RsaDriverError::InvalidLength => ErrorKind::InvalidLength,
+            RsaDriverError::InvalidLength | RsaDriverError::ModulusSize => ErrorKind::InvalidLength,
             RsaDriverError::HardwareError => ErrorKind::SignError,
-            RsaDriverError::VerificationFailed => ErrorKind::VerifyError,
+            RsaDriverError::VerificationFailed
+            | RsaDriverError::FaultDetected
+            | RsaDriverError::InvalidKeyHandle
+            | RsaDriverError::EvenExponent
+            | RsaDriverError::ExponentTooSmall
+            | RsaDriverError::ExponentTooLarge
+            | RsaDriverError::EvenModulus
+            | RsaDriverError::SignatureOutOfRange => ErrorKind::VerifyError,
         }
     }
 }
@@ -53,6 +85,78 @@ pub struct RsaPublicKey<'a> {
     pub e_bits: u32,
 }
 
+impl<'a> RsaPublicKey<'a> {
+    /// Key from a big-endian modulus and exponent, checked with
+    /// [`Self::validate`]; leading zero bytes are dropped
+    pub fn new(m: &'a [u8], e: &'a [u8], max_e_bits: u32) -> Result<Self, RsaDriverError> {
+        let (m, e) = (significant(m), significant(e));
+        let key = Self {
+            m,
+            e,
+            m_bits: bit_len(m),
+            e_bits: bit_len(e),
+        };
+        key.validate(max_e_bits)?;
+        Ok(key)
+    }
+
+    /// Checks the modulus as the engine needs it (odd, top bit at
+    /// `m_bits`), and that the exponent is odd, at least 3 and at most
+    /// `max_e_bits` wide
+    pub fn validate(&self, max_e_bits: u32) -> Result<(), RsaDriverError> {
+        check_modulus(self.m, self.m_bits)?;
+        let e = self
+            .e
+            .get(..byte_len(self.e_bits))
+            .ok_or(RsaDriverError::InvalidLength)?;
+        let bits = bit_len(significant(e));
+        if bits > max_e_bits {
+            return Err(RsaDriverError::ExponentTooLarge);
+        }
+        if e.last().is_none_or(|&b| b & 1 == 0) {
+            return Err(RsaDriverError::EvenExponent);
+        }
+        if bits < 2 {
+            return Err(RsaDriverError::ExponentTooSmall);
+        }
+        Ok(())
+    }
+}
+
+fn byte_len(bits: u32) -> usize {
+    bits.div_ceil(8) as usize
+}
+
+/// `bytes` without its leading zero bytes
+fn significant(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// The first `m_bits` bits of `m` must be an odd modulus of exactly that
+/// width
+fn check_modulus(m: &[u8], m_bits: u32) -> Result<(), RsaDriverError> {
+    if m_bits == 0 || m_bits > RSA_MAX_BITS {
+        return Err(RsaDriverError::ModulusSize);
+    }
+    let m = m
+        .get(..byte_len(m_bits))
+        .ok_or(RsaDriverError::InvalidLength)?;
+    if m[0] >> ((m_bits - 1) % 8) != 1 {
+        return Err(RsaDriverError::ModulusSize);
+    }
+    if m[m.len() - 1] & 1 == 0 {
+        return Err(RsaDriverError::EvenModulus);
+    }
+    Ok(())
+}
+
+/// Whether the big-endian `value` is below the big-endian modulus `m`
+fn below_modulus(value: &[u8], m: &[u8]) -> bool {
+    let (value, m) = (significant(value), significant(m));
+    value.len() < m.len() || (value.len() == m.len() && value < m)
+}
+
 pub struct RsaSignatureData {
     pub data: [u8; 512],
     pub len: usize,
@@ -154,21 +258,58 @@ pub enum PaddingError {
     UnsupportedDigest,
 }
 
+#[cfg(feature = "rsa")]
 pub struct AspeedRsa<'a, D: DelayNs> {
     pub secure: &'a Secure,
     sram_base: NonNull<u8>,
     delay: D,
+    max_e_bits: u32,
+}
+
+/// Stand-in for the engine when the `rsa` feature is off, so that using
+/// it fails with an error naming the feature. Sealed: no type implements
+/// it, so the stub below cannot be built.
+#[cfg(not(feature = "rsa"))]
+#[diagnostic::on_unimplemented(
+    message = "`AspeedRsa` is not available in this build",
+    label = "RSA support is compiled out",
+    note = "enable the `rsa` cargo feature"
+)]
+pub trait RsaEnabled: sealed::Sealed {}
+
+#[cfg(not(feature = "rsa"))]
+mod sealed {
+    pub trait Sealed {}
+}
+
+#[cfg(not(feature = "rsa"))]
+pub struct AspeedRsa<'a, D: RsaEnabled>(core::marker::PhantomData<&'a D>);
+
+#[cfg(not(feature = "rsa"))]
+impl<'a, D: RsaEnabled> AspeedRsa<'a, D> {
+    #[must_use]
+    pub fn new(_secure: &'a Secure, _delay: D) -> Self {
+        unreachable!()
+    }
 }
 
+#[cfg(feature = "rsa")]
 impl<'a, D: DelayNs> AspeedRsa<'a, D> {
     pub fn new(secure: &'a Secure, delay: D) -> Self {
         Self {
             secure,
             sram_base: unsafe { NonNull::new_unchecked(RSA_SRAM_BASE as *mut u8) },
             delay,
+            max_e_bits: DEFAULT_MAX_E_BITS,
         }
     }
 
+    /// Widest public exponent `verify` accepts, [`DEFAULT_MAX_E_BITS`]
+    /// unless set
+    pub fn set_max_exponent_bits(&mut self, bits: u32) {
+        self.max_e_bits = bits;
+    }
+
     pub fn pkcs1_v1_5_pad_inplace(digest: &[u8], out: &mut [u8]) -> Result<usize, PaddingError> {
         const DER_SHA256: &[u8] = &[
             0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
@@ -234,6 +375,7 @@ impl<'a, D: DelayNs> AspeedRsa<'a, D> {
         if input.len() > 512 {
             return Err(RsaDriverError::InvalidLength);
         }
+        check_modulus(m, m_bits)?;
 
         unsafe {
             for i in 0..SRAM_SIZE {
@@ -298,23 +440,28 @@ impl<'a, D: DelayNs> AspeedRsa<'a, D> {
     }
 }
 
+#[cfg(feature = "rsa")]
 impl<D: DelayNs> RsaErrorType for AspeedRsa<'_, D> {
     type Error = RsaDriverError;
 }
 
+#[cfg(feature = "rsa")]
 impl<D: DelayNs> RsaMessage for AspeedRsa<'_, D> {
     type Message = RsaDigest;
 }
 
+#[cfg(feature = "rsa")]
 impl<'a, D: DelayNs> RsaKeys for AspeedRsa<'a, D> {
     type PrivateKey = RsaPrivateKey<'a>;
     type PublicKey = RsaPublicKey<'a>;
 }
 
+#[cfg(feature = "rsa")]
 impl<D: DelayNs> RsaSignature for AspeedRsa<'_, D> {
     type Signature = RsaSignatureData;
 }
 
+#[cfg(feature = "rsa")]
 impl<D: DelayNs> RsaKeyGen for AspeedRsa<'_, D> {
     fn generate_keys(_bits: RsaSize) -> Result<(Self::PrivateKey, Self::PublicKey), Self::Error> {
         // Not supported by hardware
@@ -322,6 +469,7 @@ impl<D: DelayNs> RsaKeyGen for AspeedRsa<'_, D> {
     }
 }
 
+#[cfg(feature = "rsa")]
 impl<D: DelayNs> RsaSign for AspeedRsa<'_, D> {
     /// Performs RSA signature generation using PKCS#1 v1.5 padding and a private key.
     ///
@@ -392,6 +540,7 @@ impl<D: DelayNs> RsaSign for AspeedRsa<'_, D> {
     }
 }
 
+#[cfg(feature = "rsa")]
 impl<D: DelayNs> RsaVerify for AspeedRsa<'_, D> {
     /// Verifies an RSA signature using the provided public key and digest.
     ///
@@ -412,6 +561,11 @@ impl<D: DelayNs> RsaVerify for AspeedRsa<'_, D> {
     /// # Returns
     /// - `Ok(signature)` if verification succeeds
     /// - `Err(RsaDriverError::VerificationFailed)` if digest mismatch
+    /// - `Err(RsaDriverError::FaultDetected)` if the two independent digest
+    ///   comparisons disagree
+    /// - the [`RsaPublicKey::validate`] error for a malformed key, or
+    ///   `Err(RsaDriverError::SignatureOutOfRange)` if the signature is not
+    ///   below the modulus
     ///
     /// # Notes
     /// - The implementation uses a fixed-size internal buffer (512 bytes) for output.
@@ -422,8 +576,35 @@ impl<D: DelayNs> RsaVerify for AspeedRsa<'_, D> {
         _padding_mode: PaddingMode,
         signature: &Self::Signature,
     ) -> Result<Self::Signature, Self::Error> {
+        self.verify_public(public_key, &message, signature)
+    }
+}
+
+#[cfg(feature = "rsa")]
+impl<D: DelayNs> AspeedRsa<'_, D> {
+    /// [`RsaVerify::verify`] with the public key taken from `store`
+    pub fn verify_with_handle<const N: usize>(
+        &mut self,
+        store: &KeyStore<N>,
+        handle: KeyHandle,
+        message: &RsaDigest,
+        signature: &RsaSignatureData,
+    ) -> Result<RsaSignatureData, RsaDriverError> {
+        let public_key = store
+            .rsa_public(handle)
+            .map_err(|_| RsaDriverError::InvalidKeyHandle)?;
+        self.verify_public(&public_key, message, signature)
+    }
+
+    fn verify_public(
+        &mut self,
+        public_key: &RsaPublicKey<'_>,
+        message: &RsaDigest,
+        signature: &RsaSignatureData,
+    ) -> Result<RsaSignatureData, RsaDriverError> {
         let mut output = [0u8; 512];
 
+        public_key.validate(self.max_e_bits)?;
         let input_len = signature.len;
         let e_len = ((public_key.e_bits + 7) / 8) as usize;
         let m_len = ((public_key.m_bits + 7) / 8) as usize;
@@ -431,6 +612,9 @@ impl<D: DelayNs> RsaVerify for AspeedRsa<'_, D> {
         let input = &signature.data[..input_len];
         let m = &public_key.m[..m_len];
         let e = &public_key.e[..e_len];
+        if !below_modulus(input, m) {
+            return Err(RsaDriverError::SignatureOutOfRange);
+        }
 
         let len = self.aspeed_rsa_trigger(
             input,
@@ -441,7 +625,15 @@ impl<D: DelayNs> RsaVerify for AspeedRsa<'_, D> {
             public_key.e_bits,
         )?;
 
-        if output[len.saturating_sub(message.len)..len] == message.data[..message.len] {
+        let recovered = &output[len.saturating_sub(message.len)..len];
+        let expected = &message.data[..message.len];
+        let decision = VerifyDecision::confirm(
+            VerifyDecision::compare(recovered, expected),
+            VerifyDecision::compare_rev(recovered, expected),
+        )
+        .ok_or(RsaDriverError::FaultDetected)?;
+
+        if decision == VerifyDecision::VALID && decision.is_valid() {
             Ok(RsaSignatureData {
                 data: signature.data,
                 len,
@@ -451,3 +643,84 @@ impl<D: DelayNs> RsaVerify for AspeedRsa<'_, D> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{below_modulus, RsaDriverError, RsaPublicKey, DEFAULT_MAX_E_BITS};
+
+    const M: [u8; 4] = [0xc5, 0x00, 0x12, 0x0b];
+
+    fn key<'a>(m: &'a [u8], e: &'a [u8]) -> Result<RsaPublicKey<'a>, RsaDriverError> {
+        RsaPublicKey::new(m, e, DEFAULT_MAX_E_BITS)
+    }
+
+    #[test]
+    fn test_valid_key() {
+        let trimmed = key(&[0, 0, 0xc5, 0x00, 0x12, 0x0b], &[0, 1, 0, 1]).unwrap();
+        assert_eq!((trimmed.m, trimmed.m_bits), (&M[..], 32));
+        assert_eq!((trimmed.e, trimmed.e_bits), (&[1, 0, 1][..], 17));
+        assert!(key(&M, &[3]).is_ok());
+        // declared sizes rounded up to whole bytes, as in the test vectors
+        let rounded = RsaPublicKey {
+            m: &M,
+            e: &[0x01, 0x00, 0x01],
+            m_bits: 32,
+            e_bits: 24,
+        };
+        assert!(rounded.validate(DEFAULT_MAX_E_BITS).is_ok());
+    }
+
+    #[test]
+    fn test_bad_exponent() {
+        assert!(matches!(
+            key(&M, &[1]),
+            Err(RsaDriverError::ExponentTooSmall)
+        ));
+        for e in [&[][..], &[2], &[1, 0]] {
+            assert!(matches!(key(&M, e), Err(RsaDriverError::EvenExponent)));
+        }
+        let wide = [1, 0, 0, 0, 1];
+        assert!(matches!(
+            key(&M, &wide),
+            Err(RsaDriverError::ExponentTooLarge)
+        ));
+        assert!(RsaPublicKey::new(&M, &wide, 33).is_ok());
+    }
+
+    #[test]
+    fn test_bad_modulus() {
+        assert!(matches!(
+            key(&[0xc5, 0x00, 0x12, 0x0a], &[3]),
+            Err(RsaDriverError::EvenModulus)
+        ));
+        assert!(matches!(key(&[], &[3]), Err(RsaDriverError::ModulusSize)));
+        let declared = |m_bits| RsaPublicKey {
+            m: &M,
+            e: &[3],
+            m_bits,
+            e_bits: 2,
+        };
+        // declared wider than the slice, and narrower than the value
+        assert!(matches!(
+            declared(33).validate(DEFAULT_MAX_E_BITS),
+            Err(RsaDriverError::InvalidLength)
+        ));
+        assert!(matches!(
+            declared(31).validate(DEFAULT_MAX_E_BITS),
+            Err(RsaDriverError::ModulusSize)
+        ));
+        assert!(matches!(
+            declared(4104).validate(DEFAULT_MAX_E_BITS),
+            Err(RsaDriverError::ModulusSize)
+        ));
+    }
+
+    #[test]
+    fn test_signature_range() {
+        assert!(below_modulus(&[0xc5, 0x00, 0x12, 0x0a], &M));
+        assert!(below_modulus(&[0, 0xff, 0xff, 0xff], &M));
+        assert!(!below_modulus(&M, &M));
+        assert!(!below_modulus(&[0xc5, 0x00, 0x13, 0x00], &M));
+        assert!(!below_modulus(&[1, 0, 0, 0, 0], &M));
+    }
+}
@@ -0,0 +1,381 @@
+// Licensed under the Apache-2.0 license
+
+//! Power and reset sequencing of the host platform over GPIO
+//!
+//! A sequence is a `const` table of [`Step`]s: drive an output, wait, or
+//! check that an input (a power-good or reset-done line) reaches a level
+//! within a timeout. Pins are named by their index in the output and input
+//! arrays handed to [`Sequencer::new`], so board variants differ only in
+//! their [`PlatformSequences`] tables and pin arrays.
+//!
+//! When a step fails, the outputs set by the earlier steps are driven back
+//! to their opposite level in reverse order, leaving the lines as they were
+//! before the sequence started. Waits are not repeated on the way back.
+//! Flows that also rewired pins, such as a recovery that took the SPI muxes
+//! over, use [`Sequencer::run_restoring`] to put the wiring back as well,
+//! typically from a [`PinStateSnapshot`](crate::pin_state::PinStateSnapshot).
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{Error, InputPin, OutputPin};
+
+/// Interval at which [`Step::Expect`] samples its input
+const POLL_US: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Drive output `pin` high or low
+    Set { pin: usize, high: bool },
+    /// Wait `ms` milliseconds
+    Delay { ms: u32 },
+    /// Fail unless input `pin` reads `high` within `timeout_ms`
+    Expect {
+        pin: usize,
+        high: bool,
+        timeout_ms: u32,
+    },
+}
+
+/// The sequences of one board
+pub struct PlatformSequences {
+    pub power_on: &'static [Step],
+    pub power_off: &'static [Step],
+    pub reset_host: &'static [Step],
+}
+
+/// Failure of the step at index `step` of the sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceError<E> {
+    /// The input did not reach the expected level in time
+    Timeout { step: usize },
+    /// Reading or driving the pin failed
+    Pin { step: usize, error: E },
+    /// The step names a pin beyond the pin arrays
+    NoSuchPin { step: usize },
+    /// The step failed, and restoring the pin wiring afterwards failed too
+    Rewire { step: usize },
+}
+
+pub struct Sequencer<'p, E, D, const OUT: usize, const IN: usize> {
+    tables: &'static PlatformSequences,
+    outputs: [&'p mut dyn OutputPin<Error = E>; OUT],
+    inputs: [&'p mut dyn InputPin<Error = E>; IN],
+    delay: D,
+}
+
+impl<'p, E: Error, D: DelayNs, const OUT: usize, const IN: usize> Sequencer<'p, E, D, OUT, IN> {
+    pub fn new(
+        tables: &'static PlatformSequences,
+        outputs: [&'p mut dyn OutputPin<Error = E>; OUT],
+        inputs: [&'p mut dyn InputPin<Error = E>; IN],
+        delay: D,
+    ) -> Self {
+        Self {
+            tables,
+            outputs,
+            inputs,
+            delay,
+        }
+    }
+
+    pub fn power_on(&mut self) -> Result<(), SequenceError<E>> {
+        self.run(self.tables.power_on)
+    }
+
+    pub fn power_off(&mut self) -> Result<(), SequenceError<E>> {
+        self.run(self.tables.power_off)
+    }
+
+    pub fn reset_host(&mut self) -> Result<(), SequenceError<E>> {
+        self.run(self.tables.reset_host)
+    }
+
+    /// Runs `steps` in order, rolling back the outputs already set if one
+    /// fails
+    pub fn run(&mut self, steps: &[Step]) -> Result<(), SequenceError<E>> {
+        for (index, step) in steps.iter().enumerate() {
+            if let Err(e) = self.apply(index, *step) {
+                self.roll_back(&steps[..index]);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs `steps` like [`Self::run`]; if one fails, also calls `restore`
+    /// once the outputs are rolled back
+    pub fn run_restoring<R>(
+        &mut self,
+        steps: &[Step],
+        restore: impl FnOnce() -> Result<(), R>,
+    ) -> Result<(), SequenceError<E>> {
+        self.run(steps).map_err(|e| match (restore(), e) {
+            (Ok(()), e) => e,
+            (
+                Err(_),
+                SequenceError::Timeout { step }
+                | SequenceError::Pin { step, .. }
+                | SequenceError::NoSuchPin { step }
+                | SequenceError::Rewire { step },
+            ) => SequenceError::Rewire { step },
+        })
+    }
+
+    fn apply(&mut self, index: usize, step: Step) -> Result<(), SequenceError<E>> {
+        match step {
+            Step::Set { pin, high } => self.drive(index, pin, high),
+            Step::Delay { ms } => {
+                self.delay.delay_ms(ms);
+                Ok(())
+            }
+            Step::Expect {
+                pin,
+                high,
+                timeout_ms,
+            } => {
+                let input = self
+                    .inputs
+                    .get_mut(pin)
+                    .ok_or(SequenceError::NoSuchPin { step: index })?;
+                let polls = timeout_ms.saturating_mul(1000 / POLL_US);
+                for poll in 0..=polls {
+                    let level = input
+                        .is_high()
+                        .map_err(|error| SequenceError::Pin { step: index, error })?;
+                    if level == high {
+                        return Ok(());
+                    }
+                    if poll < polls {
+                        self.delay.delay_us(POLL_US);
+                    }
+                }
+                Err(SequenceError::Timeout { step: index })
+            }
+        }
+    }
+
+    fn drive(&mut self, index: usize, pin: usize, high: bool) -> Result<(), SequenceError<E>> {
+        let output = self
+            .outputs
+            .get_mut(pin)
+            .ok_or(SequenceError::NoSuchPin { step: index })?;
+        let result = if high {
+            output.set_high()
+        } else {
+            output.set_low()
+        };
+        result.map_err(|error| SequenceError::Pin { step: index, error })
+    }
+
+    /// Best effort: a pin that fails to drive back does not stop the others
+    fn roll_back(&mut self, applied: &[Step]) {
+        for (index, step) in applied.iter().enumerate().rev() {
+            if let Step::Set { pin, high } = *step {
+                let _ = self.drive(index, pin, !high);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PlatformSequences, SequenceError, Sequencer, Step};
+    use core::cell::{Cell, RefCell};
+    use core::convert::Infallible;
+    use embedded_hal::delay::DelayNs;
+    use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+
+    /// Output that logs its writes; a [`Loopback`] on the same cell reads
+    /// it back
+    struct FakeOut<'a> {
+        id: usize,
+        level: &'a Cell<bool>,
+        log: &'a RefCell<Vec<(usize, bool)>>,
+    }
+
+    impl ErrorType for FakeOut<'_> {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for FakeOut<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.level.set(false);
+            self.log.borrow_mut().push((self.id, false));
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.level.set(true);
+            self.log.borrow_mut().push((self.id, true));
+            Ok(())
+        }
+    }
+
+    struct Loopback<'a>(&'a Cell<bool>);
+
+    impl ErrorType for Loopback<'_> {
+        type Error = Infallible;
+    }
+
+    impl InputPin for Loopback<'_> {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            Ok(self.0.get())
+        }
+
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            Ok(!self.0.get())
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeDelay(u64);
+
+    impl DelayNs for FakeDelay {
+        fn delay_ns(&mut self, ns: u32) {
+            self.0 += u64::from(ns);
+        }
+    }
+
+    // outputs: 0 = rail enable, 1 = host reset release, 2 = spare
+    // inputs: 0 = power good (looped back from 0), 1 = reset done (from 2)
+    static SEQUENCES: PlatformSequences = PlatformSequences {
+        power_on: &[
+            Step::Set { pin: 0, high: true },
+            Step::Delay { ms: 5 },
+            Step::Expect {
+                pin: 0,
+                high: true,
+                timeout_ms: 10,
+            },
+            Step::Set { pin: 1, high: true },
+        ],
+        power_off: &[
+            Step::Set {
+                pin: 1,
+                high: false,
+            },
+            Step::Set {
+                pin: 0,
+                high: false,
+            },
+        ],
+        reset_host: &[
+            Step::Set {
+                pin: 1,
+                high: false,
+            },
+            Step::Delay { ms: 1 },
+            Step::Set { pin: 1, high: true },
+        ],
+    };
+
+    #[test]
+    fn test_power_on_off() {
+        let levels = [Cell::new(false), Cell::new(false), Cell::new(false)];
+        let log = RefCell::new(Vec::new());
+        let [mut a, mut b, mut c] = [0, 1, 2].map(|id| FakeOut {
+            id,
+            level: &levels[id],
+            log: &log,
+        });
+        let (mut good, mut done) = (Loopback(&levels[0]), Loopback(&levels[2]));
+        let mut seq = Sequencer::new(
+            &SEQUENCES,
+            [&mut a, &mut b, &mut c],
+            [&mut good, &mut done],
+            FakeDelay::default(),
+        );
+
+        assert_eq!(seq.power_on(), Ok(()));
+        assert_eq!(*log.borrow(), [(0, true), (1, true)]);
+        // the delay ran, and power good was seen on the first poll
+        assert_eq!(seq.delay.0, 5_000_000);
+        assert_eq!(seq.reset_host(), Ok(()));
+        assert_eq!(seq.power_off(), Ok(()));
+        assert!(levels.iter().all(|l| !l.get()));
+    }
+
+    #[test]
+    fn test_failure_rolls_back() {
+        let levels = [Cell::new(false), Cell::new(false), Cell::new(false)];
+        let log = RefCell::new(Vec::new());
+        let [mut a, mut b, mut c] = [0, 1, 2].map(|id| FakeOut {
+            id,
+            level: &levels[id],
+            log: &log,
+        });
+        let (mut good, mut done) = (Loopback(&levels[0]), Loopback(&levels[2]));
+        let mut seq = Sequencer::new(
+            &SEQUENCES,
+            [&mut a, &mut b, &mut c],
+            [&mut good, &mut done],
+            FakeDelay::default(),
+        );
+
+        // step 3 waits for reset done, which nothing drives
+        let steps = [
+            Step::Set { pin: 0, high: true },
+            Step::Set { pin: 1, high: true },
+            Step::Expect {
+                pin: 1,
+                high: true,
+                timeout_ms: 2,
+            },
+            Step::Set { pin: 2, high: true },
+        ];
+        assert_eq!(seq.run(&steps), Err(SequenceError::Timeout { step: 2 }));
+        assert_eq!(
+            *log.borrow(),
+            [(0, true), (1, true), (1, false), (0, false)]
+        );
+        assert_eq!(seq.delay.0, 2_000_000);
+
+        let steps = [
+            Step::Set { pin: 2, high: true },
+            Step::Set { pin: 3, high: true },
+        ];
+        assert_eq!(seq.run(&steps), Err(SequenceError::NoSuchPin { step: 1 }));
+        assert!(!levels[2].get());
+    }
+
+    #[test]
+    fn test_failure_restores_wiring() {
+        let levels = [Cell::new(false), Cell::new(false), Cell::new(false)];
+        let log = RefCell::new(Vec::new());
+        let [mut a, mut b, mut c] = [0, 1, 2].map(|id| FakeOut {
+            id,
+            level: &levels[id],
+            log: &log,
+        });
+        let (mut good, mut done) = (Loopback(&levels[0]), Loopback(&levels[2]));
+        let mut seq = Sequencer::new(
+            &SEQUENCES,
+            [&mut a, &mut b, &mut c],
+            [&mut good, &mut done],
+            FakeDelay::default(),
+        );
+
+        let restored = Cell::new(0);
+        let restore = || {
+            restored.set(restored.get() + 1);
+            Ok::<(), ()>(())
+        };
+        assert_eq!(seq.run_restoring(SEQUENCES.power_on, restore), Ok(()));
+        assert_eq!(restored.get(), 0);
+
+        let steps = [
+            Step::Set { pin: 2, high: true },
+            Step::Set { pin: 3, high: true },
+        ];
+        assert_eq!(
+            seq.run_restoring(&steps, restore),
+            Err(SequenceError::NoSuchPin { step: 1 })
+        );
+        assert_eq!(restored.get(), 1);
+        assert!(!levels[2].get());
+
+        assert_eq!(
+            seq.run_restoring(&steps, || Err(())),
+            Err(SequenceError::Rewire { step: 1 })
+        );
+    }
+}
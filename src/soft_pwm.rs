@@ -0,0 +1,255 @@
+// Licensed under the Apache-2.0 license
+
+//! Software PWM on a GPIO output
+//!
+//! For fans and LEDs on pins without a PWM channel. A [`SoftPwm`] is
+//! driven by calls to [`update`](SoftPwm::update) with a free-running tick
+//! count, as in [`crate::doorbell`]; each call sets the pin to the level
+//! the current point of the period calls for. Polling from the main loop
+//! is enough for fan control, where periods are tens of milliseconds. A
+//! late poll stretches the current pulse but does not shift the phase of
+//! later periods.
+//!
+//! With the `rt` feature, [`SoftPwmTimer`] updates the output from the
+//! timer interrupt at a fixed step instead.
+
+use embedded_hal::digital::OutputPin;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoftPwmError {
+    /// The frequency is zero or above the tick rate
+    InvalidFrequency,
+}
+
+pub struct SoftPwm<P> {
+    pin: P,
+    /// Period in ticks
+    period: u32,
+    /// Ticks per period the pin is high
+    high: u32,
+    duty: u8,
+    /// Tick the current period started at
+    start: u32,
+    /// Level last driven, `None` until the first update
+    level: Option<bool>,
+}
+
+impl<P: OutputPin> SoftPwm<P> {
+    /// PWM at `freq_hz` for updates counted in ticks of `tick_hz`, with
+    /// `duty` out of 255 high
+    pub fn new(pin: P, freq_hz: u32, tick_hz: u32, duty: u8) -> Result<Self, SoftPwmError> {
+        let period = tick_hz.checked_div(freq_hz).unwrap_or(0);
+        if period == 0 {
+            return Err(SoftPwmError::InvalidFrequency);
+        }
+        let mut pwm = Self {
+            pin,
+            period,
+            high: 0,
+            duty: 0,
+            start: 0,
+            level: None,
+        };
+        pwm.set_duty(duty);
+        Ok(pwm)
+    }
+
+    /// Sets the high time to `duty` out of 255 from the next update on;
+    /// 0 holds the pin low and 255 holds it high
+    pub fn set_duty(&mut self, duty: u8) {
+        self.duty = duty;
+        self.high = u32::try_from(u64::from(self.period) * u64::from(duty) / 255).unwrap();
+    }
+
+    #[must_use]
+    pub fn duty(&self) -> u8 {
+        self.duty
+    }
+
+    /// Period in ticks, after rounding down to whole ticks
+    #[must_use]
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    /// Drives the pin for time `now`; the pin is only written when its
+    /// level changes
+    pub fn update(&mut self, now: u32) -> Result<(), P::Error> {
+        let elapsed = now.wrapping_sub(self.start);
+        if elapsed >= self.period {
+            self.start = self.start.wrapping_add(elapsed - elapsed % self.period);
+        }
+        let high = now.wrapping_sub(self.start) < self.high;
+        if self.level != Some(high) {
+            if high {
+                self.pin.set_high()?;
+            } else {
+                self.pin.set_low()?;
+            }
+            self.level = Some(high);
+        }
+        Ok(())
+    }
+
+    /// Stops the PWM and gives the pin back at its last level
+    pub fn release(self) -> P {
+        self.pin
+    }
+}
+
+#[cfg(feature = "rt")]
+pub use timer::SoftPwmTimer;
+
+#[cfg(feature = "rt")]
+mod timer {
+    use super::{SoftPwm, SoftPwmError};
+    use crate::interrupts::IrqHandler;
+    use crate::timer::{TimerController, TimerError, TimerType};
+    use ast1060_pac::{Interrupt, Timer};
+    use embedded_hal::digital::OutputPin;
+    use embedded_hal_old::timer::CountDown;
+    use fugit::MicrosDurationU32;
+
+    /// Timer interrupt handler that updates a [`SoftPwm`] every step
+    pub struct SoftPwmTimer<P> {
+        timer: TimerController<Timer>,
+        pwm: SoftPwm<P>,
+        step: MicrosDurationU32,
+        steps: u32,
+    }
+
+    impl<P: OutputPin> SoftPwmTimer<P> {
+        /// PWM at `freq_hz` with `duty` out of 255, resolved in steps of
+        /// `step`; a period has to be at least one step
+        pub fn new(
+            tick_per_us: u32,
+            pin: P,
+            freq_hz: u32,
+            duty: u8,
+            step: MicrosDurationU32,
+        ) -> Result<Self, SoftPwmError> {
+            let steps_per_sec = 1_000_000u32.checked_div(step.ticks()).unwrap_or(0);
+            Ok(Self {
+                timer: TimerController::new(tick_per_us),
+                pwm: SoftPwm::new(pin, freq_hz, steps_per_sec, duty)?,
+                step,
+                steps: 0,
+            })
+        }
+
+        pub fn start(&mut self) -> Result<(), TimerError> {
+            self.timer.set_callback(None, TimerType::Periodic);
+            self.timer.try_start(self.step)
+        }
+
+        pub fn stop(&mut self) {
+            self.timer.stop();
+        }
+
+        pub fn set_duty(&mut self, duty: u8) {
+            self.pwm.set_duty(duty);
+        }
+    }
+
+    impl<P: OutputPin + Send> IrqHandler for SoftPwmTimer<P> {
+        fn irq_number(&self) -> Interrupt {
+            Interrupt::timer
+        }
+
+        fn on_irq(&mut self) {
+            self.timer.handle_interrupt();
+            self.steps = self.steps.wrapping_add(1);
+            // a GPIO write cannot fail, and there is no one to report to
+            let _ = self.pwm.update(self.steps);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SoftPwm, SoftPwmError};
+    use core::convert::Infallible;
+    use embedded_hal::digital::{ErrorType, OutputPin};
+
+    #[derive(Default)]
+    struct FakePin {
+        high: bool,
+        writes: u32,
+    }
+
+    impl ErrorType for FakePin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for FakePin {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.high = false;
+            self.writes += 1;
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.high = true;
+            self.writes += 1;
+            Ok(())
+        }
+    }
+
+    /// Ticks high over `ticks` updates, one per tick from `from`
+    fn high_ticks(pwm: &mut SoftPwm<FakePin>, from: u32, ticks: u32) -> u32 {
+        (0..ticks)
+            .filter(|&t| {
+                pwm.update(from.wrapping_add(t)).unwrap();
+                pwm.pin.high
+            })
+            .count()
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_duty_cycle() {
+        // 1 kHz on a 1 MHz tick: 1000 ticks per period
+        let mut pwm = SoftPwm::new(FakePin::default(), 1000, 1_000_000, 64).unwrap();
+        assert_eq!(pwm.period(), 1000);
+        assert_eq!(high_ticks(&mut pwm, 0, 10_000), 2500);
+        // one rising and one falling edge per period
+        assert_eq!(pwm.pin.writes, 20);
+
+        pwm.set_duty(0);
+        assert_eq!(high_ticks(&mut pwm, 10_000, 3000), 0);
+        pwm.set_duty(255);
+        assert_eq!(high_ticks(&mut pwm, 13_000, 3000), 3000);
+        assert_eq!(pwm.duty(), 255);
+    }
+
+    #[test]
+    fn test_late_poll_keeps_phase() {
+        let mut pwm = SoftPwm::new(FakePin::default(), 100, 1000, 128).unwrap();
+        // 10 ticks per period, high for the first 5
+        for (now, high) in [(0, true), (6, false), (37, false), (42, true), (45, false)] {
+            pwm.update(now).unwrap();
+            assert_eq!(pwm.pin.high, high, "at {now}");
+        }
+    }
+
+    #[test]
+    fn test_counter_wrap() {
+        let mut pwm = SoftPwm::new(FakePin::default(), 1, 16, 128).unwrap();
+        // periods keep their length across the wrap of the tick count
+        let from = u32::MAX - 40;
+        assert_eq!(high_ticks(&mut pwm, from, 160), 80);
+    }
+
+    #[test]
+    fn test_invalid_frequency() {
+        assert!(matches!(
+            SoftPwm::new(FakePin::default(), 0, 1000, 0),
+            Err(SoftPwmError::InvalidFrequency)
+        ));
+        assert!(matches!(
+            SoftPwm::new(FakePin::default(), 2000, 1000, 0),
+            Err(SoftPwmError::InvalidFrequency)
+        ));
+    }
+}
@@ -0,0 +1,282 @@
+// Licensed under the Apache-2.0 license
+
+//! Software watchdogs for subsystems that have no hardware watchdog
+//!
+//! A [`SoftWatchdog`] holds up to `N` supervised subsystems, each with its
+//! own timeout. Subsystems [`kick`](SoftWatchdog::kick) through the handle
+//! they got at registration; a periodic timer interrupt calls
+//! [`tick`](SoftWatchdog::tick), which counts their time down and reports
+//! each expiry once. Because the check runs in the interrupt, a main loop
+//! that is stuck is still caught.
+//!
+//! On expiry the watchdog sets the subsystem's bit in
+//! [`expired`](SoftWatchdog::expired) and calls the policy function it was
+//! built with. A policy that only records can be `None`; one that resets
+//! can log and then start a hardware watchdog with a short period.
+//!
+//! All state is atomic, so a `static` watchdog can be kicked from thread
+//! mode while the timer interrupt ticks it.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Called from the timer interrupt with the handle of an expired subsystem
+pub type ExpiryPolicy = fn(WatchdogHandle);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoftWdtError {
+    /// Every slot is supervising a subsystem
+    Full,
+    /// A zero timeout would expire on the first tick
+    InvalidTimeout,
+    /// The handle's subsystem has been unregistered
+    InvalidHandle,
+}
+
+/// Identifies a registered subsystem; stale once it is unregistered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchdogHandle {
+    slot: u8,
+    generation: u32,
+}
+
+impl WatchdogHandle {
+    /// Bit of this subsystem in [`SoftWatchdog::expired`]
+    #[must_use]
+    pub fn mask(self) -> u32 {
+        1 << self.slot
+    }
+}
+
+struct Slot {
+    /// Timeout in ms; 0 while the slot is free
+    timeout: AtomicU32,
+    /// Time left in ms; 0 once the expiry has been reported
+    remaining: AtomicU32,
+    generation: AtomicU32,
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Self {
+            timeout: AtomicU32::new(0),
+            remaining: AtomicU32::new(0),
+            generation: AtomicU32::new(0),
+        }
+    }
+}
+
+pub struct SoftWatchdog<const N: usize> {
+    slots: [Slot; N],
+    expired: AtomicU32,
+    policy: Option<ExpiryPolicy>,
+}
+
+impl<const N: usize> SoftWatchdog<N> {
+    /// Empty watchdog calling `policy` on each expiry.
+    ///
+    /// At most 32 subsystems are supported, one per bit of the expired mask.
+    #[must_use]
+    pub const fn new(policy: Option<ExpiryPolicy>) -> Self {
+        assert!(N <= 32);
+        Self {
+            slots: [const { Slot::new() }; N],
+            expired: AtomicU32::new(0),
+            policy,
+        }
+    }
+
+    /// Starts supervising a subsystem that must kick at least every
+    /// `timeout_ms`
+    pub fn register(&self, timeout_ms: u32) -> Result<WatchdogHandle, SoftWdtError> {
+        if timeout_ms == 0 {
+            return Err(SoftWdtError::InvalidTimeout);
+        }
+        for (index, slot) in self.slots.iter().enumerate() {
+            if slot
+                .timeout
+                .compare_exchange(0, timeout_ms, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                let handle = WatchdogHandle {
+                    slot: u8::try_from(index).unwrap(),
+                    generation: slot.generation.load(Ordering::Relaxed),
+                };
+                self.expired.fetch_and(!handle.mask(), Ordering::Relaxed);
+                slot.remaining.store(timeout_ms, Ordering::Release);
+                return Ok(handle);
+            }
+        }
+        Err(SoftWdtError::Full)
+    }
+
+    /// Stops supervising the subsystem; its handle becomes invalid
+    pub fn unregister(&self, handle: WatchdogHandle) -> Result<(), SoftWdtError> {
+        let slot = self.slot(handle)?;
+        slot.remaining.store(0, Ordering::Release);
+        slot.generation.fetch_add(1, Ordering::Relaxed);
+        slot.timeout.store(0, Ordering::Release);
+        Ok(())
+    }
+
+    /// Restarts the subsystem's timeout, also after it has expired
+    pub fn kick(&self, handle: WatchdogHandle) -> Result<(), SoftWdtError> {
+        let slot = self.slot(handle)?;
+        slot.remaining
+            .store(slot.timeout.load(Ordering::Relaxed), Ordering::Release);
+        Ok(())
+    }
+
+    /// Counts `elapsed_ms` off every subsystem and reports the ones that
+    /// run out; call from the periodic timer interrupt
+    pub fn tick(&self, elapsed_ms: u32) {
+        for (index, slot) in self.slots.iter().enumerate() {
+            let left = slot
+                .remaining
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |left| {
+                    (left != 0).then(|| left.saturating_sub(elapsed_ms))
+                });
+            if left.is_ok_and(|left| left <= elapsed_ms) {
+                let handle = WatchdogHandle {
+                    slot: u8::try_from(index).unwrap(),
+                    generation: slot.generation.load(Ordering::Relaxed),
+                };
+                self.expired.fetch_or(handle.mask(), Ordering::Relaxed);
+                if let Some(policy) = self.policy {
+                    policy(handle);
+                }
+            }
+        }
+    }
+
+    /// Subsystems that have expired since registering, one bit per
+    /// [`WatchdogHandle::mask`]; a kick does not clear the bit
+    #[must_use]
+    pub fn expired(&self) -> u32 {
+        self.expired.load(Ordering::Relaxed)
+    }
+
+    fn slot(&self, handle: WatchdogHandle) -> Result<&Slot, SoftWdtError> {
+        match self.slots.get(usize::from(handle.slot)) {
+            Some(slot)
+                if slot.timeout.load(Ordering::Acquire) != 0
+                    && slot.generation.load(Ordering::Relaxed) == handle.generation =>
+            {
+                Ok(slot)
+            }
+            _ => Err(SoftWdtError::InvalidHandle),
+        }
+    }
+}
+
+#[cfg(feature = "rt")]
+pub use timer::SoftWatchdogTimer;
+
+#[cfg(feature = "rt")]
+mod timer {
+    use super::SoftWatchdog;
+    use crate::interrupts::IrqHandler;
+    use crate::timer::{TimerController, TimerError, TimerType};
+    use ast1060_pac::{Interrupt, Timer};
+    use embedded_hal_old::timer::CountDown;
+    use fugit::{MicrosDurationU32, MillisDurationU32};
+
+    /// Timer interrupt handler that ticks a [`SoftWatchdog`] every period
+    pub struct SoftWatchdogTimer<const N: usize> {
+        timer: TimerController<Timer>,
+        watchdog: &'static SoftWatchdog<N>,
+        period_ms: u32,
+    }
+
+    impl<const N: usize> SoftWatchdogTimer<N> {
+        #[must_use]
+        pub fn new(tick_per_us: u32, watchdog: &'static SoftWatchdog<N>) -> Self {
+            Self {
+                timer: TimerController::new(tick_per_us),
+                watchdog,
+                period_ms: 0,
+            }
+        }
+
+        /// Starts ticking every `period`; expiries are detected at most one
+        /// period late
+        pub fn start(&mut self, period: MillisDurationU32) -> Result<(), TimerError> {
+            self.period_ms = period.ticks();
+            self.timer.set_callback(None, TimerType::Periodic);
+            self.timer
+                .try_start(MicrosDurationU32::millis(period.ticks()))
+        }
+
+        pub fn stop(&mut self) {
+            self.timer.stop();
+        }
+    }
+
+    impl<const N: usize> IrqHandler for SoftWatchdogTimer<N> {
+        fn irq_number(&self) -> Interrupt {
+            Interrupt::timer
+        }
+
+        fn on_irq(&mut self) {
+            self.timer.handle_interrupt();
+            self.watchdog.tick(self.period_ms);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SoftWatchdog, SoftWdtError, WatchdogHandle};
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    static FIRED: AtomicU32 = AtomicU32::new(0);
+
+    fn count_expiry(handle: WatchdogHandle) {
+        FIRED.fetch_add(handle.mask(), Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_expiry_and_kick() {
+        let wdt = SoftWatchdog::<2>::new(Some(count_expiry));
+        let stalled = wdt.register(30).unwrap();
+        let healthy = wdt.register(30).unwrap();
+
+        for _ in 0..2 {
+            wdt.tick(10);
+            wdt.kick(healthy).unwrap();
+        }
+        assert_eq!(wdt.expired(), 0);
+        // the stalled subsystem runs out on the third period and is
+        // reported once, however long it stays stalled
+        for _ in 0..5 {
+            wdt.tick(10);
+            wdt.kick(healthy).unwrap();
+        }
+        assert_eq!(wdt.expired(), stalled.mask());
+        assert_eq!(FIRED.load(Ordering::Relaxed), stalled.mask());
+
+        // a kick re-arms it
+        wdt.kick(stalled).unwrap();
+        wdt.tick(10);
+        assert_eq!(FIRED.load(Ordering::Relaxed), stalled.mask());
+    }
+
+    #[test]
+    fn test_registration() {
+        let wdt = SoftWatchdog::<1>::new(None);
+        assert_eq!(wdt.register(0), Err(SoftWdtError::InvalidTimeout));
+        let first = wdt.register(5).unwrap();
+        assert_eq!(wdt.register(5), Err(SoftWdtError::Full));
+
+        wdt.unregister(first).unwrap();
+        assert_eq!(wdt.kick(first), Err(SoftWdtError::InvalidHandle));
+        assert_eq!(wdt.unregister(first), Err(SoftWdtError::InvalidHandle));
+        // an unregistered slot never expires
+        wdt.tick(100);
+        assert_eq!(wdt.expired(), 0);
+
+        let second = wdt.register(5).unwrap();
+        assert_ne!(first, second);
+        wdt.tick(5);
+        assert_eq!(wdt.expired(), second.mask());
+    }
+}
@@ -1,14 +1,15 @@
 // Licensed under the Apache-2.0 license
 
 use super::{
-    aspeed_get_spi_freq_div, get_addr_buswidth, get_hclock_rate, get_mid_point_of_longest_one,
-    spi_cal_dummy_cycle, spi_calibration_enable, spi_io_mode, spi_io_mode_user, spi_read_data,
-    spi_write_data, CtrlType, SpiBusWithCs, SpiConfig, SpiData, SpiError, Write, ASPEED_MAX_CS,
+    aspeed_get_spi_freq_div, decode_spi_status, get_addr_buswidth, get_hclock_rate,
+    get_mid_point_of_longest_one, spi_calibration_enable, spi_io_mode, spi_io_mode_user,
+    spi_normal_read_cmd, spi_normal_write_cmd, spi_read_data, spi_write_data, CtrlType,
+    SpiBusWithCs, SpiConfig, SpiData, SpiError, SpiStatus, Write, ASPEED_MAX_CS,
     ASPEED_SPI_NORMAL_READ, ASPEED_SPI_NORMAL_WRITE, ASPEED_SPI_SZ_256M, ASPEED_SPI_SZ_2M,
     ASPEED_SPI_USER, ASPEED_SPI_USER_INACTIVE, SPI_CALIB_LEN, SPI_CTRL_FREQ_MASK,
     SPI_DMA_CALC_CKSUM, SPI_DMA_CALIB_MODE, SPI_DMA_DISCARD_REQ_MAGIC, SPI_DMA_ENABLE,
     SPI_DMA_FLASH_MAP_BASE, SPI_DMA_GET_REQ_MAGIC, SPI_DMA_GRANT, SPI_DMA_RAM_MAP_BASE,
-    SPI_DMA_REQUEST, SPI_DMA_STATUS, SPI_DMA_TIMEOUT,
+    SPI_DMA_REQUEST, SPI_DMA_STATUS, SPI_DMA_TIMEOUT, SPI_STATUS_MASK,
 };
 
 #[cfg(feature = "spi_dma")]
@@ -20,7 +21,11 @@ use crate::spi::{
     SPI_CTRL_CEX_SPI_CMD_MASK, SPI_CTRL_CEX_SPI_CMD_SHIFT, SPI_DMA_CLK_FREQ_MASK,
     SPI_DMA_CLK_FREQ_SHIFT, SPI_DMA_DELAY_MASK, SPI_DMA_DELAY_SHIFT,
 };
-use crate::{common::DummyDelay, spi::norflash::SpiNorData, uart::UartController};
+use crate::{
+    common::{cache, DummyDelay},
+    spi::norflash::SpiNorData,
+    uart::UartController,
+};
 use embedded_hal::{
     delay::DelayNs,
     spi::{ErrorType, SpiBus},
@@ -97,6 +102,40 @@ impl<'a> FmcController<'a> {
 
         Ok(())
     }
+
+    /// Decoded DMA, error and chip select state
+    #[must_use]
+    pub fn status(&self) -> SpiStatus {
+        let ce_ctrl = [cs_ctrlreg_r!(self, 0), cs_ctrlreg_r!(self, 1)];
+        decode_spi_status(
+            self.regs.fmc008().read().bits(),
+            self.regs.fmc080().read().bits(),
+            &ce_ctrl[..self.spi_config.max_cs.min(ce_ctrl.len())],
+        )
+    }
+
+    /// Soft reset: aborts any DMA in flight, releases every chip select and
+    /// clears the latched status. Decode windows and read commands are kept.
+    pub fn reset(&mut self) -> Result<(), SpiError> {
+        dbg!(self, "reset()");
+        self.dma_disable();
+        for cs in 0..self.spi_config.max_cs {
+            let user_reg = self.spi_data.cmd_mode[cs].user;
+            cs_ctrlreg_w!(self, cs, user_reg | ASPEED_SPI_USER_INACTIVE);
+            if !self.spi_data.continuous_read[cs] {
+                cs_ctrlreg_w!(self, cs, self.spi_data.cmd_mode[cs].normal_read);
+            }
+        }
+        self.regs
+            .fmc008()
+            .modify(|r, w| unsafe { w.bits(r.bits() | SPI_STATUS_MASK) });
+
+        if self.status().is_busy() {
+            return Err(SpiError::BusError);
+        }
+        Ok(())
+    }
+
     fn decode_range_pre_init(&mut self) {
         let max_cs = self.spi_config.max_cs;
         let mut unit_sz = ASPEED_SPI_SZ_2M;
@@ -236,22 +275,12 @@ impl<'a> FmcController<'a> {
         if !self.spi_config.pure_spi_mode_only {
             self.decode_range_reinit(op_info.data_len);
         }
-        let io_mode = spi_io_mode(op_info.mode);
-        let dummy = spi_cal_dummy_cycle(
-            get_addr_buswidth(op_info.mode as u32).into(),
-            op_info.dummy_cycle,
-        );
-        let read_cmd = (io_mode
-            | ((op_info.opcode & SPI_CTRL_CEX_SPI_CMD_MASK) << SPI_CTRL_CEX_SPI_CMD_SHIFT)
-            | (dummy as u32))
-            | ASPEED_SPI_NORMAL_READ;
+        let read_cmd = spi_normal_read_cmd(op_info);
         self.spi_data.cmd_mode[cs].normal_read = read_cmd;
         dbg!(
             self,
-            "cs: {:08x}, io_mode: {:08x}, dummy: {:08x}, op: {:08x}, normal read: {:08x}",
+            "cs: {:08x}, op: {:08x}, normal read: {:08x}",
             cs,
-            io_mode,
-            dummy,
             op_info.opcode,
             read_cmd
         );
@@ -279,13 +308,7 @@ impl<'a> FmcController<'a> {
     }
 
     fn spi_nor_write_init(&mut self, cs: usize, op_info: &SpiNorData) {
-        let io_mode = spi_io_mode(op_info.mode);
-        let dummy = 0;
-        let write_cmd = (io_mode
-            | ((op_info.opcode & SPI_CTRL_CEX_SPI_CMD_MASK) << SPI_CTRL_CEX_SPI_CMD_SHIFT)
-            | dummy)
-            | ASPEED_SPI_NORMAL_WRITE;
-        self.spi_data.cmd_mode[cs].normal_write = write_cmd;
+        self.spi_data.cmd_mode[cs].normal_write = spi_normal_write_cmd(op_info);
     }
 
     pub fn timing_calibration(&mut self, cs: usize) {
@@ -482,7 +505,7 @@ impl<'a> FmcController<'a> {
     }
     fn spi_nor_transceive_user(&mut self, op_info: &mut SpiNorData) {
         let cs: usize = self.current_cs;
-        let dummy = [0u8; 12];
+        let mut dummy = [0u8; 12];
         let start_ptr = self.spi_data.decode_addr[cs].start as *mut u32;
         dbg!(
             self,
@@ -491,12 +514,15 @@ impl<'a> FmcController<'a> {
             self.spi_data.decode_addr[cs].start
         );
 
-        // Send command
-        let cmd_mode = self.spi_data.cmd_mode[cs].user
-            | super::spi_io_mode_user(u32::from(super::get_cmd_buswidth(op_info.mode as u32)));
-        cs_ctrlreg_w!(self, cs, cmd_mode);
-        dbg!(self, "write opcode/cmd: 0x{:08x}", op_info.opcode);
-        unsafe { super::spi_write_data(start_ptr, &[op_info.opcode.try_into().unwrap()]) };
+        // Send command, unless implied by continuous read
+        let cmd_width = super::get_cmd_buswidth(op_info.mode as u32);
+        if cmd_width != 0 {
+            let cmd_mode =
+                self.spi_data.cmd_mode[cs].user | super::spi_io_mode_user(u32::from(cmd_width));
+            cs_ctrlreg_w!(self, cs, cmd_mode);
+            dbg!(self, "write opcode/cmd: 0x{:08x}", op_info.opcode);
+            unsafe { super::spi_write_data(start_ptr, &[op_info.opcode.try_into().unwrap()]) };
+        }
 
         // Send address
         let addr_mode = self.spi_data.cmd_mode[cs].user
@@ -516,6 +542,9 @@ impl<'a> FmcController<'a> {
         let dummy_len: u8 = (op_info.dummy_cycle / (8 / u32::from(bus_width)))
             .try_into()
             .unwrap();
+        if self.spi_data.continuous_read[cs] {
+            dummy[0] = super::norflash::SPI_NOR_CONT_READ_MODE_BITS;
+        }
         dbg!(self, "write dummy len: 0x{:08x}", dummy_len);
         unsafe { super::spi_write_data(start_ptr, &dummy[..dummy_len as usize]) };
 
@@ -543,7 +572,9 @@ impl<'a> FmcController<'a> {
 
             if op_info.data_direct == SPI_NOR_DATA_DIRECT_READ {
                 let buf_aligned = (op_info.rx_buf.as_ptr() as usize) % 4 == 0;
+                // DMA reads go through normal read mode, which sends the opcode
                 let use_dma = !self.spi_config.pure_spi_mode_only
+                    && !self.spi_data.continuous_read[self.current_cs]
                     && op_info.rx_buf.len() > SPI_DMA_TRIGGER_LEN as usize
                     && addr_aligned
                     && buf_aligned;
@@ -697,7 +728,9 @@ impl<'a> FmcController<'a> {
         });
 
         dbg!(self, "start wait for dma");
-        self.wait_for_dma_completion(SPI_DMA_TIMEOUT)
+        self.wait_for_dma_completion(SPI_DMA_TIMEOUT)?;
+        cache::invalidate_range(op.rx_buf.as_ptr() as usize, op.rx_buf.len());
+        Ok(())
     }
 
     #[allow(dead_code)]
@@ -743,6 +776,7 @@ impl<'a> FmcController<'a> {
 
         // Enable DMA IRQ if needed
         // self.enable_dma_irq(); // implement if necessary
+        cache::clean_range(op.tx_buf.as_ptr() as usize, op.tx_buf.len());
         // Start DMA with write direction
         self.regs.fmc080().modify(|_, w| {
             w.dmaenbl().enable_dma_operation();
@@ -811,7 +845,9 @@ impl<'a> SpiBusWithCs for FmcController<'a> {
             return Err(SpiError::CsSelectFailed(cs));
         }
         cs_ctrlreg_w!(self, cs, user_reg | ASPEED_SPI_USER_INACTIVE);
-        cs_ctrlreg_w!(self, cs, self.spi_data.cmd_mode[cs].normal_read);
+        if !self.spi_data.continuous_read[cs] {
+            cs_ctrlreg_w!(self, cs, self.spi_data.cmd_mode[cs].normal_read);
+        }
         dbg!(self, "deactivate cs:{}", u32::try_from(cs).unwrap());
         dbg!(
             self,
@@ -844,4 +880,16 @@ impl<'a> SpiBusWithCs for FmcController<'a> {
     fn get_master_id(&mut self) -> u32 {
         self.spi_config.master_idx
     }
+
+    fn continuous_read(&mut self, cs: usize) -> bool {
+        self.spi_data.continuous_read[cs]
+    }
+
+    fn set_continuous_read(&mut self, cs: usize, enable: bool) {
+        self.spi_data.continuous_read[cs] = enable;
+    }
+
+    fn read_mapped(&mut self, cs: usize, offset: u32, buf: &mut [u8]) -> Result<(), SpiError> {
+        self.spi_data.read_mapped(cs, offset, buf)
+    }
 }
@@ -14,6 +14,8 @@ pub mod device;
 pub mod fmccontroller;
 pub mod norflash;
 pub mod norflashblockdevice;
+pub mod otp;
+pub mod protection;
 pub mod spicontroller;
 pub mod spitest;
 
@@ -22,12 +24,18 @@ pub mod spitest;
 pub enum SpiError {
     BusError,
     DmaTimeout,
+    /// A deadline passed while waiting on the device
+    Timeout,
     CsSelectFailed(usize),
     LengthMismatch,
     CapacityOutOfRange,
     UnsupportedDevice(u8),
     AddressNotAligned(u32),
     InvalidCommand(u8),
+    /// The OTP security register was locked and can no longer be programmed
+    OtpLocked(u8),
+    /// The flash is in continuous read mode, so its mapped window is off
+    ContinuousRead,
     Other(&'static str),
 }
 
@@ -37,17 +45,64 @@ impl spi::Error for SpiError {
         match self {
             SpiError::BusError
             | SpiError::DmaTimeout
+            | SpiError::Timeout
             | SpiError::CsSelectFailed(_)
             | SpiError::LengthMismatch
             | SpiError::CapacityOutOfRange
             | SpiError::UnsupportedDevice(_)
             | SpiError::InvalidCommand(_)
+            | SpiError::OtpLocked(_)
+            | SpiError::ContinuousRead
             | SpiError::AddressNotAligned(_)
             | SpiError::Other(_) => spi::ErrorKind::Other,
         }
     }
 }
 
+/// Controller state decoded from the interrupt control register (0x08), the
+/// DMA control register (0x80) and the CE control registers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpiStatus {
+    /// DMA is enabled and has not reported completion
+    pub dma_busy: bool,
+    pub dma_done: bool,
+    pub cmd_abort: bool,
+    pub write_protected: bool,
+    /// One bit per chip select held active in user mode
+    pub cs_asserted: u8,
+}
+
+impl SpiStatus {
+    #[must_use]
+    pub fn is_busy(&self) -> bool {
+        self.dma_busy || self.cs_asserted != 0
+    }
+
+    #[must_use]
+    pub fn is_error(&self) -> bool {
+        self.cmd_abort || self.write_protected
+    }
+}
+
+/// Decodes the raw interrupt control, DMA control and CE control values
+#[must_use]
+pub fn decode_spi_status(intr: u32, dma_ctrl: u32, ce_ctrl: &[u32]) -> SpiStatus {
+    let dma_done = intr & SPI_DMA_STATUS != 0;
+    let mut cs_asserted = 0u8;
+    for (cs, &ctrl) in ce_ctrl.iter().enumerate() {
+        if ctrl & ASPEED_SPI_MODE_MASK == ASPEED_SPI_USER && ctrl & ASPEED_SPI_USER_INACTIVE == 0 {
+            cs_asserted |= 1 << cs;
+        }
+    }
+    SpiStatus {
+        dma_busy: dma_ctrl & SPI_DMA_ENABLE != 0 && !dma_done,
+        dma_done,
+        cmd_abort: intr & SPI_STATUS_CMD_ABORT != 0,
+        write_protected: intr & SPI_STATUS_WRITE_PROTECT != 0,
+        cs_asserted,
+    }
+}
+
 pub trait SpiBusWithCs: SpiBus<u8, Error = SpiError> + ErrorType<Error = SpiError> {
     fn select_cs(&mut self, cs: usize) -> Result<(), SpiError>;
     fn deselect_cs(&mut self, cs: usize) -> Result<(), SpiError>;
@@ -57,6 +112,15 @@ pub trait SpiBusWithCs: SpiBus<u8, Error = SpiError> + ErrorType<Error = SpiErro
 
     fn get_device_info(&mut self, cs: usize) -> (u32, u32);
     fn get_master_id(&mut self) -> u32;
+    /// Whether the flash on `cs` is in continuous read mode
+    fn continuous_read(&mut self, cs: usize) -> bool;
+    /// Records the continuous read state of `cs`. While set, user mode reads
+    /// send the mode bits and the mapped read window is parked, since normal
+    /// read mode always sends the opcode.
+    fn set_continuous_read(&mut self, cs: usize, enable: bool);
+    /// Copies from the memory-mapped window of `cs`, see
+    /// [`SpiData::read_mapped`]
+    fn read_mapped(&mut self, cs: usize, offset: u32, buf: &mut [u8]) -> Result<(), SpiError>;
 }
 
 // Constants (unchanged)
@@ -94,6 +158,10 @@ const SPI_DMA_CALC_CKSUM: u32 = 1 << 2;
 
 const SPI_DMA_ENABLE: u32 = 1 << 0;
 const SPI_DMA_STATUS: u32 = 1 << 11;
+const SPI_STATUS_CMD_ABORT: u32 = 1 << 10;
+const SPI_STATUS_WRITE_PROTECT: u32 = 1 << 9;
+const SPI_STATUS_MASK: u32 = SPI_DMA_STATUS | SPI_STATUS_CMD_ABORT | SPI_STATUS_WRITE_PROTECT;
+const ASPEED_SPI_MODE_MASK: u32 = 0x3;
 
 const ASPEED_MAX_CS: usize = 5; // Must be usize for array indexing
 
@@ -153,6 +221,7 @@ pub struct SpiConfig {
 pub struct SpiData {
     pub decode_addr: [SpiDecodeAddress; ASPEED_MAX_CS],
     pub cmd_mode: [CommandMode; ASPEED_MAX_CS],
+    pub continuous_read: [bool; ASPEED_MAX_CS],
     pub hclk: u32,
     pub spim_proprietary_pre_config: u32,
 }
@@ -176,10 +245,35 @@ impl SpiData {
         Self {
             decode_addr: [ZERO_ADDR; ASPEED_MAX_CS],
             cmd_mode: [ZERO_CMD; ASPEED_MAX_CS],
+            continuous_read: [false; ASPEED_MAX_CS],
             hclk: 0,
             spim_proprietary_pre_config: 0,
         }
     }
+
+    /// Copies `buf.len()` bytes from `offset` in the memory-mapped window
+    /// of `cs`. Fails with [`SpiError::ContinuousRead`] while the flash is
+    /// in continuous read mode: the controller's normal read command always
+    /// sends the opcode, so the window stays parked until the mode is left.
+    pub fn read_mapped(&self, cs: usize, offset: u32, buf: &mut [u8]) -> Result<(), SpiError> {
+        let window = self
+            .decode_addr
+            .get(cs)
+            .ok_or(SpiError::CsSelectFailed(cs))?;
+        if self.continuous_read[cs] {
+            return Err(SpiError::ContinuousRead);
+        }
+        let end = u32::try_from(buf.len())
+            .ok()
+            .and_then(|len| offset.checked_add(len));
+        if end.map_or(true, |end| end > window.len) {
+            return Err(SpiError::CapacityOutOfRange);
+        }
+        let src = (window.start as usize + offset as usize) as *const u8;
+        // SAFETY: the range lies in the decode window of `cs`
+        unsafe { core::ptr::copy_nonoverlapping(src, buf.as_mut_ptr(), buf.len()) };
+        Ok(())
+    }
 }
 
 #[macro_export]
@@ -235,6 +329,25 @@ pub fn spi_cal_dummy_cycle(bus_width: u32, dummy_cycle: u32) -> u32 {
     ((dummy_byte & 0x3) << 6) | (((dummy_byte & 0x4) >> 2) << 14)
 }
 
+/// CE control value for normal read mode with `op`'s opcode, bus widths
+/// and dummy cycles
+#[must_use]
+pub fn spi_normal_read_cmd(op: &SpiNorData) -> u32 {
+    let dummy = spi_cal_dummy_cycle(u32::from(get_addr_buswidth(op.mode as u32)), op.dummy_cycle);
+    spi_io_mode(op.mode)
+        | ((op.opcode & SPI_CTRL_CEX_SPI_CMD_MASK) << SPI_CTRL_CEX_SPI_CMD_SHIFT)
+        | dummy
+        | ASPEED_SPI_NORMAL_READ
+}
+
+/// CE control value for normal write mode with `op`'s opcode and bus widths
+#[must_use]
+pub fn spi_normal_write_cmd(op: &SpiNorData) -> u32 {
+    spi_io_mode(op.mode)
+        | ((op.opcode & SPI_CTRL_CEX_SPI_CMD_MASK) << SPI_CTRL_CEX_SPI_CMD_SHIFT)
+        | ASPEED_SPI_NORMAL_WRITE
+}
+
 const fn get_cmd_buswidth(v: u32) -> u8 {
     ((v & 0x0000_0F00) >> 8) as u8
 }
@@ -503,3 +616,105 @@ pub fn spim_proprietary_post_config() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_spi_status() {
+        assert_eq!(decode_spi_status(0, 0, &[0, 0]), SpiStatus::default());
+
+        let status = decode_spi_status(0, SPI_DMA_ENABLE, &[]);
+        assert!(status.dma_busy && !status.dma_done && status.is_busy());
+
+        let status = decode_spi_status(SPI_DMA_STATUS, SPI_DMA_ENABLE, &[]);
+        assert!(!status.dma_busy && status.dma_done && !status.is_busy());
+
+        let status = decode_spi_status(SPI_STATUS_CMD_ABORT | SPI_STATUS_WRITE_PROTECT, 0, &[]);
+        assert!(status.cmd_abort && status.write_protected && status.is_error());
+
+        // CE1 active in user mode, CE0 parked in normal read
+        let status = decode_spi_status(
+            0,
+            0,
+            &[ASPEED_SPI_NORMAL_READ, 0x0b00_0000 | ASPEED_SPI_USER],
+        );
+        assert_eq!(status.cs_asserted, 0b10);
+        let status = decode_spi_status(0, 0, &[ASPEED_SPI_USER | ASPEED_SPI_USER_INACTIVE]);
+        assert_eq!(status.cs_asserted, 0);
+    }
+
+    #[test]
+    fn test_read_mapped_refusals() {
+        let mut data = SpiData::new();
+        data.decode_addr[0] = SpiDecodeAddress {
+            start: 0x8000_0000,
+            len: 0x1000,
+        };
+        let mut buf = [0u8; 16];
+        data.continuous_read[0] = true;
+        assert!(matches!(
+            data.read_mapped(0, 0, &mut buf),
+            Err(SpiError::ContinuousRead)
+        ));
+        data.continuous_read[0] = false;
+        assert!(matches!(
+            data.read_mapped(0, 0xff8, &mut buf),
+            Err(SpiError::CapacityOutOfRange)
+        ));
+        assert!(matches!(
+            data.read_mapped(0, u32::MAX, &mut buf),
+            Err(SpiError::CapacityOutOfRange)
+        ));
+        assert!(matches!(
+            data.read_mapped(ASPEED_MAX_CS, 0, &mut buf),
+            Err(SpiError::CsSelectFailed(ASPEED_MAX_CS))
+        ));
+    }
+
+    #[test]
+    fn test_command_descriptors() {
+        use norflash::{NOR_PP_4B, NOR_QREAD, NOR_RDSR, NOR_SE};
+
+        let mut status = [0u8];
+        let op = NOR_RDSR.nor_data(0, &[], &mut status);
+        assert_eq!((op.opcode, op.addr_len, op.data_len), (0x05, 0, 1));
+        assert_eq!(op.data_direct, SPI_NOR_DATA_DIRECT_READ);
+
+        let op = NOR_SE.nor_data(0x1000, &[0xaa], &mut []);
+        assert_eq!((op.addr, op.addr_len, op.data_len), (0x1000, 3, 0));
+        assert_eq!(op.data_direct, SPI_NOR_DATA_DIRECT_WRITE);
+
+        let op = NOR_PP_4B.nor_data(0x100_0000, &[0; 16], &mut []);
+        assert_eq!((op.opcode, op.addr_len, op.data_len), (0x12, 4, 16));
+
+        let op = NOR_QREAD.mapped(0x200);
+        assert_eq!(op.mode, Jesd216Mode::Mode114);
+        assert_eq!((op.dummy_cycle, op.data_len), (8, 0x200));
+    }
+
+    #[test]
+    fn test_normal_mode_cmd() {
+        use norflash::{NOR_4READ_CONT, NOR_PP, NOR_QREAD, NOR_READ_FAST_4B};
+
+        // 1-1-4, opcode 0x6b, 8 dummy cycles on a 1-bit address bus
+        assert_eq!(
+            spi_normal_read_cmd(&NOR_QREAD.mapped(0)),
+            0x4000_0000 | (0x6b << 16) | (1 << 6) | ASPEED_SPI_NORMAL_READ
+        );
+        assert_eq!(
+            spi_normal_read_cmd(&NOR_READ_FAST_4B.mapped(0)),
+            (0x0c << 16) | (1 << 6) | ASPEED_SPI_NORMAL_READ
+        );
+        // 1-4-4: 6 cycles on a 4-bit address bus are 3 dummy bytes
+        assert_eq!(
+            spi_normal_read_cmd(&NOR_4READ_CONT.mapped(0)),
+            0x5000_0000 | (0xeb << 16) | (3 << 6) | ASPEED_SPI_NORMAL_READ
+        );
+        assert_eq!(
+            spi_normal_write_cmd(&NOR_PP.mapped(0)),
+            (0x02 << 16) | ASPEED_SPI_NORMAL_WRITE
+        );
+    }
+}
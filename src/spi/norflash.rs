@@ -1,11 +1,14 @@
 // Licensed under the Apache-2.0 license
 
 use super::device::ChipSelectDevice;
+use super::otp::{self, OtpLayout};
+use super::protection::{self, ProtectionMap};
 use super::SpiBusWithCs;
-use super::{norflash, SpiError, SPI_NOR_DATA_DIRECT_READ, SPI_NOR_DATA_DIRECT_WRITE};
-use crate::common::DummyDelay;
+use super::{SpiError, SPI_NOR_DATA_DIRECT_READ, SPI_NOR_DATA_DIRECT_WRITE};
+use crate::common::{Deadline, DummyDelay};
 use crate::spimonitor::SpipfInstance;
 use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::SpiDevice;
 
 /* Flash opcodes */
 pub const SPI_NOR_CMD_WRSR: u32 = 0x01; /* Write status register */
@@ -54,6 +57,14 @@ pub const SPI_NOR_CMD_RESET_EN: u32 = 0x66; /* Reset Enable */
 pub const SPI_NOR_CMD_RESET_MEM: u32 = 0x99; /* Reset Memory */
 
 pub const SPI_NOR_CMD_RDSFDP: u32 = 0x5A; /* Read SFDP */
+
+pub const SPI_NOR_CMD_RDSCR: u32 = 0x48; /* Read security register */
+pub const SPI_NOR_CMD_PSCR: u32 = 0x42; /* Program security register */
+
+/* Continuous read (1-4-4 without opcode) */
+pub const SPI_NOR_CONT_READ_MODE_BITS: u8 = 0x20; /* M5-4 = 10b: keep continuous read */
+pub const SPI_NOR_CONT_READ_DUMMY: u32 = 6; /* 2 mode + 4 wait cycles */
+pub const SPI_NOR_CONT_READ_EXIT: [u8; 2] = [0xFF, 0xFF]; /* Mode bit reset */
 /* Status register bits */
 pub const SPI_NOR_WIP_BIT: u32 = 0x1; /* Write in progress */
 pub const SPI_NOR_WEL_BIT: u32 = 0x2; /* Write enable latch */
@@ -69,9 +80,9 @@ pub const SPI_NOR_MFR_ID_CYPRESS: u8 = 0x34;
 pub const SPI_NOR_PAGE_SIZE: usize = 256;
 pub const SPI_NOR_SECTOR_SIZE: usize = 4096;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Jesd216Mode {
-    Mode044 = 0x0000_0044, /* implied instruction, execute in place */
+    Mode044 = 0x0000_0044, /* implied instruction, continuous read */
     Mode088 = 0x0000_0088,
     Mode111 = 0x0000_0111,
     Mode111Fast = 0x1000_0111,
@@ -101,6 +112,180 @@ pub struct SpiNorData<'a> {
     pub data_direct: u32,
 }
 
+/// Address phase of a [`SpiCommand`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Addr {
+    ThreeByte,
+    FourByte,
+}
+
+impl Addr {
+    /// Number of address bytes
+    #[must_use]
+    pub const fn bytes(self) -> u32 {
+        match self {
+            Self::ThreeByte => 3,
+            Self::FourByte => 4,
+        }
+    }
+}
+
+/// Data phase of a [`SpiCommand`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataDir {
+    None,
+    Read,
+    Write,
+}
+
+/// Descriptor of a flash command: opcode, address and dummy phases, data
+/// direction and bus widths.
+///
+/// The NOR operations are the `NOR_*` constants below. Board code can
+/// describe vendor commands the same way and run them with
+/// [`ChipSelectDevice::execute`] without changes to the driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpiCommand {
+    pub opcode: u8,
+    pub addr: Option<Addr>,
+    pub dummy_cycles: u8,
+    pub data: DataDir,
+    pub io_mode: Jesd216Mode,
+}
+
+impl SpiCommand {
+    /// 1-1-1 command with only an opcode
+    #[must_use]
+    pub const fn new(opcode: u8) -> Self {
+        Self {
+            opcode,
+            addr: None,
+            dummy_cycles: 0,
+            data: DataDir::None,
+            io_mode: Jesd216Mode::Mode111,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_addr(mut self, addr: Addr) -> Self {
+        self.addr = Some(addr);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_dummy(mut self, cycles: u8) -> Self {
+        self.dummy_cycles = cycles;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_io_mode(mut self, mode: Jesd216Mode) -> Self {
+        self.io_mode = mode;
+        self
+    }
+
+    #[must_use]
+    pub const fn reading(mut self) -> Self {
+        self.data = DataDir::Read;
+        self
+    }
+
+    #[must_use]
+    pub const fn writing(mut self) -> Self {
+        self.data = DataDir::Write;
+        self
+    }
+
+    /// Transfer for this command at `addr`; the data phase uses `rx` for
+    /// reads and `tx` otherwise
+    #[must_use]
+    pub fn nor_data<'b>(&self, addr: u32, tx: &'b [u8], rx: &'b mut [u8]) -> SpiNorData<'b> {
+        let data_len = match self.data {
+            DataDir::None => 0,
+            DataDir::Read => rx.len(),
+            DataDir::Write => tx.len(),
+        };
+        SpiNorData {
+            data_len: u32::try_from(data_len).unwrap(),
+            addr,
+            tx_buf: tx,
+            rx_buf: rx,
+            ..self.mapped(0)
+        }
+    }
+
+    /// Descriptor for setting up the memory-mapped window with
+    /// [`SpiNorDevice::nor_read_init`] or [`SpiNorDevice::nor_write_init`]
+    #[must_use]
+    pub fn mapped<'b>(&self, len: u32) -> SpiNorData<'b> {
+        SpiNorData {
+            mode: self.io_mode,
+            opcode: u32::from(self.opcode),
+            dummy_cycle: u32::from(self.dummy_cycles),
+            addr_len: self.addr.map_or(0, Addr::bytes),
+            addr: 0,
+            data_len: len,
+            tx_buf: &[],
+            rx_buf: &mut [],
+            data_direct: if self.data == DataDir::Read {
+                SPI_NOR_DATA_DIRECT_READ
+            } else {
+                SPI_NOR_DATA_DIRECT_WRITE
+            },
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+const fn op(opcode: u32) -> u8 {
+    opcode as u8
+}
+
+pub const NOR_WREN: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_WREN));
+pub const NOR_WRDI: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_WRDI));
+pub const NOR_RDSR: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_RDSR)).reading();
+pub const NOR_RDID: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_RDID)).reading();
+pub const NOR_RDSR2: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_RDSR2)).reading();
+pub const NOR_WRSR2: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_WRSR2)).writing();
+pub const NOR_RESET_EN: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_RESET_EN));
+pub const NOR_RESET_MEM: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_RESET_MEM));
+pub const NOR_SE: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_SE)).with_addr(Addr::ThreeByte);
+pub const NOR_PP: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_PP))
+    .with_addr(Addr::ThreeByte)
+    .writing();
+pub const NOR_PP_4B: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_PP_4B))
+    .with_addr(Addr::FourByte)
+    .writing();
+pub const NOR_QREAD: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_QREAD))
+    .with_addr(Addr::ThreeByte)
+    .with_dummy(8)
+    .with_io_mode(Jesd216Mode::Mode114)
+    .reading();
+pub const NOR_RDSCR: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_RDSCR))
+    .with_addr(Addr::ThreeByte)
+    .with_dummy(8)
+    .with_io_mode(Jesd216Mode::Mode111Fast)
+    .reading();
+pub const NOR_PSCR: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_PSCR))
+    .with_addr(Addr::ThreeByte)
+    .writing();
+pub const NOR_READ_FAST_4B: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_READ_FAST_4B))
+    .with_addr(Addr::FourByte)
+    .with_dummy(8)
+    .with_io_mode(Jesd216Mode::Mode111Fast)
+    .reading();
+/// 1-4-4 read sending the continuous read mode bits
+pub const NOR_4READ_CONT: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_4READ))
+    .with_addr(Addr::ThreeByte)
+    .with_dummy(op(SPI_NOR_CONT_READ_DUMMY))
+    .with_io_mode(Jesd216Mode::Mode144)
+    .reading();
+pub const NOR_4READ_CONT_4B: SpiCommand = SpiCommand::new(op(SPI_NOR_CMD_4READ_4B))
+    .with_addr(Addr::FourByte)
+    .with_dummy(op(SPI_NOR_CONT_READ_DUMMY))
+    .with_io_mode(Jesd216Mode::Mode144)
+    .reading();
+
 pub trait SpiNorDevice {
     type Error;
     fn nor_read_init(&mut self, data: &SpiNorData) -> Result<(), Self::Error>;
@@ -117,11 +302,40 @@ pub trait SpiNorDevice {
     fn nor_wait_until_ready(&mut self);
     fn nor_reset(&mut self) -> Result<(), Self::Error>;
     fn nor_reset_enable(&mut self) -> Result<(), Self::Error>;
+    /// Puts the flash in continuous read mode; user mode reads then skip the
+    /// opcode until [`SpiNorDevice::nor_exit_continuous_read`].
+    ///
+    /// This is not execute in place: the controller's read command register
+    /// is left alone, as its normal read mode always sends the opcode. The
+    /// memory-mapped window is off meanwhile, and
+    /// [`SpiNorDevice::nor_read_mapped`] fails with `ContinuousRead`.
+    fn nor_enter_continuous_read(&mut self) -> Result<(), Self::Error>;
+    fn nor_exit_continuous_read(&mut self) -> Result<(), Self::Error>;
+    /// Reads through the memory-mapped window instead of a user mode
+    /// command
+    fn nor_read_mapped(&mut self, address: u32, buf: &mut [u8]) -> Result<(), Self::Error>;
+    /// Reads the status registers and decodes the range they write protect
+    fn protection_map(&mut self) -> Result<ProtectionMap, Self::Error>;
+    /// Reads the OTP security registers from `offset`, see [`otp`]. Parts
+    /// without a known OTP area fail with `UnsupportedDevice`.
+    fn read_otp(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), Self::Error>;
+    /// Programs `data` into the OTP security registers at `offset`. The
+    /// bytes must still be erased, and no register touched may be locked.
+    fn program_otp(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error>;
+    /// Sets the lock bit of OTP register `region`. This cannot be undone.
+    fn lock_otp(&mut self, region: u8) -> Result<(), Self::Error>;
+    fn otp_locked(&mut self, region: u8) -> Result<bool, Self::Error>;
 }
 
 macro_rules! start_transfer {
     ($this:expr, $data:expr) => {{
         let _ = (|| -> Result<(), SpiError> {
+            // Anything but a 1-4-4 read would have its opcode taken as address
+            if $this.bus.continuous_read($this.cs)
+                && !matches!($data.mode, Jesd216Mode::Mode044 | Jesd216Mode::Mode144)
+            {
+                $this.nor_exit_continuous_read()?;
+            }
             $this.bus.select_cs($this.cs)?;
             // SPIM config
             if let Some(spim) = $this.spi_monitor.as_mut() {
@@ -146,6 +360,79 @@ macro_rules! start_transfer {
     }};
 }
 
+impl<B, SPIPF> ChipSelectDevice<'_, B, SPIPF>
+where
+    B: SpiBusWithCs,
+    SPIPF: SpipfInstance,
+{
+    /// 1-4-4 read that sends the continuous read mode bits. With `implied`
+    /// the opcode is skipped, as the flash is already in continuous mode.
+    fn nor_continuous_read(
+        &mut self,
+        address: u32,
+        buf: &mut [u8],
+        implied: bool,
+    ) -> Result<(), SpiError> {
+        let (capacity, _) = self.bus.get_device_info(self.cs);
+        let mut cmd = if capacity > 0x100_0000 {
+            NOR_4READ_CONT_4B
+        } else {
+            NOR_4READ_CONT
+        };
+        if implied {
+            cmd.io_mode = Jesd216Mode::Mode044;
+        }
+        self.execute(&cmd, address, &[], buf)
+    }
+
+    /// Runs `cmd` on this chip select. The data phase reads into `rx` or
+    /// writes `tx`, as given by `cmd.data`.
+    pub fn execute(
+        &mut self,
+        cmd: &SpiCommand,
+        addr: u32,
+        tx: &[u8],
+        rx: &mut [u8],
+    ) -> Result<(), SpiError> {
+        let mut nor_data = cmd.nor_data(addr, tx, rx);
+        start_transfer!(self, &mut nor_data);
+        Ok(())
+    }
+
+    /// Waits for the write in progress bit to clear, giving up with
+    /// `SpiError::Timeout` once `deadline` expires
+    pub fn nor_wait_until_ready_within(&mut self, deadline: &Deadline) -> Result<(), SpiError> {
+        self.nor_poll_ready(Some(deadline))
+    }
+
+    fn otp_layout(&mut self) -> Result<OtpLayout, SpiError> {
+        let id = self.nor_read_jedec_id()?;
+        otp::otp_layout(id).ok_or(SpiError::UnsupportedDevice(id[0]))
+    }
+
+    fn read_sr2(&mut self) -> Result<u8, SpiError> {
+        let mut sr2 = [0u8];
+        self.execute(&NOR_RDSR2, 0, &[], &mut sr2)?;
+        Ok(sr2[0])
+    }
+
+    fn nor_poll_ready(&mut self, deadline: Option<&Deadline>) -> Result<(), SpiError> {
+        let mut delay = DummyDelay {};
+        let mut buf: [u8; 1] = [0u8];
+
+        loop {
+            self.execute(&NOR_RDSR, 0, &[], &mut buf)?;
+            delay.delay_ns(1_000);
+            if (u32::from(buf[0]) & SPI_NOR_WIP_BIT) == 0 {
+                return Ok(());
+            }
+            if deadline.is_some_and(Deadline::is_expired) {
+                return Err(SpiError::Timeout);
+            }
+        }
+    }
+}
+
 //TODO: add 4byte address mode support
 impl<'a, B, SPIPF> SpiNorDevice for ChipSelectDevice<'a, B, SPIPF>
 where
@@ -155,69 +442,23 @@ where
     type Error = B::Error;
 
     fn nor_write_enable(&mut self) -> Result<(), Self::Error> {
-        let mut nor_data = SpiNorData {
-            mode: Jesd216Mode::Mode111,
-            opcode: SPI_NOR_CMD_WREN,
-            dummy_cycle: 0,
-            addr: 0,
-            addr_len: 0,
-            data_len: 0,
-            data_direct: SPI_NOR_DATA_DIRECT_WRITE,
-            tx_buf: &[],
-            rx_buf: &mut [],
-        };
-        start_transfer!(self, &mut nor_data);
-        Ok(())
+        self.execute(&NOR_WREN, 0, &[], &mut [])
     }
 
     fn nor_write_disable(&mut self) -> Result<(), Self::Error> {
-        let mut nor_data = SpiNorData {
-            mode: Jesd216Mode::Mode111,
-            opcode: SPI_NOR_CMD_WRDI,
-            dummy_cycle: 0,
-            addr: 0,
-            addr_len: 0,
-            data_len: 0,
-            data_direct: SPI_NOR_DATA_DIRECT_WRITE,
-            tx_buf: &[],
-            rx_buf: &mut [],
-        };
-        start_transfer!(self, &mut nor_data);
-        Ok(())
+        self.execute(&NOR_WRDI, 0, &[], &mut [])
     }
 
     fn nor_read_jedec_id(&mut self) -> Result<[u8; 3], Self::Error> {
         let mut read_buf: [u8; 3] = [0, 0, 0];
-        let mut nor_data = SpiNorData {
-            mode: Jesd216Mode::Mode111,
-            opcode: 0x9F,
-            dummy_cycle: 0,
-            addr: 0,
-            addr_len: 0,
-            data_len: 0,
-            rx_buf: &mut read_buf,
-            tx_buf: &[],
-            data_direct: SPI_NOR_DATA_DIRECT_READ,
-        };
-        start_transfer!(self, &mut nor_data);
-        Ok([read_buf[0], read_buf[1], read_buf[2]])
+        self.execute(&NOR_RDID, 0, &[], &mut read_buf)?;
+        Ok(read_buf)
     }
 
     fn nor_sector_erase(&mut self, address: u32) -> Result<(), Self::Error> {
         self.nor_write_enable()?;
         if self.nor_sector_aligned(address) {
-            let mut nor_data = SpiNorData {
-                mode: Jesd216Mode::Mode111,
-                opcode: norflash::SPI_NOR_CMD_SE,
-                dummy_cycle: 0,
-                addr: address,
-                addr_len: 3,
-                data_len: 0,
-                tx_buf: &[],
-                rx_buf: &mut [],
-                data_direct: SPI_NOR_DATA_DIRECT_WRITE,
-            };
-            start_transfer!(self, &mut nor_data);
+            self.execute(&NOR_SE, address, &[], &mut [])?;
             self.nor_wait_until_ready();
             Ok(())
         } else {
@@ -227,109 +468,50 @@ where
 
     fn nor_page_program(&mut self, address: u32, data: &[u8]) -> Result<(), Self::Error> {
         self.nor_write_enable()?;
-        let mut nor_data = SpiNorData {
-            mode: Jesd216Mode::Mode111,
-            opcode: norflash::SPI_NOR_CMD_PP,
-            dummy_cycle: 0,
-            addr: address,
-            addr_len: 3,
-            data_len: u32::try_from(data.len()).unwrap(),
-            tx_buf: data,
-            rx_buf: &mut [],
-            data_direct: SPI_NOR_DATA_DIRECT_WRITE,
-        };
-        start_transfer!(self, &mut nor_data);
+        self.execute(&NOR_PP, address, data, &mut [])?;
         self.nor_wait_until_ready();
         Ok(())
     }
 
     fn nor_page_program_4b(&mut self, address: u32, data: &[u8]) -> Result<(), Self::Error> {
         self.nor_write_enable()?;
-        let mut nor_data = SpiNorData {
-            mode: Jesd216Mode::Mode111,
-            opcode: norflash::SPI_NOR_CMD_PP_4B,
-            dummy_cycle: 0,
-            addr: address,
-            addr_len: 4,
-            data_len: u32::try_from(data.len()).unwrap(),
-            tx_buf: data,
-            rx_buf: &mut [],
-            data_direct: SPI_NOR_DATA_DIRECT_WRITE,
-        };
-        start_transfer!(self, &mut nor_data);
+        self.execute(&NOR_PP_4B, address, data, &mut [])?;
         self.nor_wait_until_ready();
         Ok(())
     }
 
     fn nor_read_data(&mut self, address: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
-        let mut nor_data = SpiNorData {
-            mode: Jesd216Mode::Mode114,
-            opcode: SPI_NOR_CMD_QREAD,
-            dummy_cycle: 8,
-            addr: address,
-            addr_len: 3,
-            data_len: u32::try_from(buf.len()).unwrap(), // it is not in used.
-            tx_buf: &[],
-            rx_buf: buf,
-            data_direct: SPI_NOR_DATA_DIRECT_READ,
-        };
-        start_transfer!(self, &mut nor_data);
-
-        Ok(())
+        if self.bus.continuous_read(self.cs) {
+            return self.nor_continuous_read(address, buf, true);
+        }
+        self.execute(&NOR_QREAD, address, &[], buf)
     }
 
     fn nor_read_fast_4b_data(&mut self, address: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
-        let mut nor_data = SpiNorData {
-            mode: Jesd216Mode::Mode111Fast,
-            opcode: SPI_NOR_CMD_READ_FAST_4B,
-            dummy_cycle: 8,
-            addr: address,
-            addr_len: 4,
-            data_len: u32::try_from(buf.len()).unwrap(), // it is not in used.
-            tx_buf: &[],
-            rx_buf: buf,
-            data_direct: SPI_NOR_DATA_DIRECT_READ,
-        };
-        start_transfer!(self, &mut nor_data);
+        if self.bus.continuous_read(self.cs) {
+            return self.nor_continuous_read(address, buf, true);
+        }
+        self.execute(&NOR_READ_FAST_4B, address, &[], buf)
+    }
 
-        Ok(())
+    fn nor_read_mapped(&mut self, address: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.bus.read_mapped(self.cs, address, buf)
     }
 
     fn nor_reset_enable(&mut self) -> Result<(), Self::Error> {
-        let mut nor_data = SpiNorData {
-            mode: Jesd216Mode::Mode111,
-            opcode: SPI_NOR_CMD_RESET_EN,
-            dummy_cycle: 0,
-            addr: 0x0,
-            addr_len: 0x0,
-            data_len: 0x0, // it is not in used.
-            tx_buf: &[],
-            rx_buf: &mut [],
-            data_direct: SPI_NOR_DATA_DIRECT_WRITE,
-        };
-        start_transfer!(self, &mut nor_data);
-
-        Ok(())
+        // A flash left in continuous read would take the opcode as address
+        self.nor_exit_continuous_read()?;
+        self.execute(&NOR_RESET_EN, 0, &[], &mut [])
     }
 
     fn nor_reset(&mut self) -> Result<(), Self::Error> {
-        let mut nor_data = SpiNorData {
-            mode: Jesd216Mode::Mode111,
-            opcode: SPI_NOR_CMD_RESET_MEM,
-            dummy_cycle: 0,
-            addr: 0x0,
-            addr_len: 0x0,
-            data_len: 0x0, // it is not in used.
-            tx_buf: &[],
-            rx_buf: &mut [],
-            data_direct: SPI_NOR_DATA_DIRECT_WRITE,
-        };
-        start_transfer!(self, &mut nor_data);
-
-        Ok(())
+        self.execute(&NOR_RESET_MEM, 0, &[], &mut [])
     }
 
     fn nor_read_init(&mut self, nor_data: &SpiNorData) -> Result<(), Self::Error> {
+        if self.bus.continuous_read(self.cs) {
+            self.nor_exit_continuous_read()?;
+        }
         if let Some(spim) = self.spi_monitor.as_mut() {
             if self.bus.get_master_id() != 0 {
                 spim.spim_scu_ctrl_set(0x8, 0x8);
@@ -349,6 +531,23 @@ where
         Ok(())
     }
 
+    fn nor_enter_continuous_read(&mut self) -> Result<(), Self::Error> {
+        if self.bus.continuous_read(self.cs) {
+            return Ok(());
+        }
+        // User mode sends the mode bits once continuous read is set; the
+        // controller's read command register keeps the normal read
+        self.bus.set_continuous_read(self.cs, true);
+        let mut buf = [0u8; 4];
+        self.nor_continuous_read(0, &mut buf, false)
+    }
+
+    fn nor_exit_continuous_read(&mut self) -> Result<(), Self::Error> {
+        // Also sent when the state is unknown, e.g. from recovery
+        self.bus.set_continuous_read(self.cs, false);
+        self.write(&SPI_NOR_CONT_READ_EXIT)
+    }
+
     fn nor_write_init(&mut self, nor_data: &SpiNorData) -> Result<(), Self::Error> {
         self.bus.nor_write_init(self.cs, nor_data);
         Ok(())
@@ -362,26 +561,87 @@ where
     }
 
     fn nor_wait_until_ready(&mut self) {
-        let mut delay = DummyDelay {};
-        let mut buf: [u8; 1] = [0u8];
+        let _ = self.nor_poll_ready(None);
+    }
 
-        let mut nor_data = SpiNorData {
-            mode: Jesd216Mode::Mode111,
-            opcode: SPI_NOR_CMD_RDSR,
-            dummy_cycle: 0,
-            addr: 0,
-            addr_len: 0,
-            data_len: 1, // it is not in used.
-            tx_buf: &[],
-            rx_buf: &mut buf,
-            data_direct: SPI_NOR_DATA_DIRECT_READ,
-        };
-        loop {
-            start_transfer!(self, &mut nor_data);
-            delay.delay_ns(1_000);
-            if (u32::from(nor_data.rx_buf[0]) & SPI_NOR_WIP_BIT) == 0 {
-                break;
+    fn protection_map(&mut self) -> Result<ProtectionMap, Self::Error> {
+        let id = self.nor_read_jedec_id()?;
+        let opcode = protection::status2_opcode(id).ok_or(SpiError::UnsupportedDevice(id[0]))?;
+        let mut sr1 = [0u8];
+        let mut sr2 = [0u8];
+        self.execute(&NOR_RDSR, 0, &[], &mut sr1)?;
+        self.execute(&SpiCommand::new(op(opcode)).reading(), 0, &[], &mut sr2)?;
+        protection::decode_protection(id, sr1[0], sr2[0]).ok_or(SpiError::UnsupportedDevice(id[0]))
+    }
+
+    fn read_otp(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let layout = self.otp_layout()?;
+        if !layout.contains(offset, buf.len()) {
+            return Err(SpiError::CapacityOutOfRange);
+        }
+        let mut at = offset;
+        let mut done = 0;
+        // One transfer per register, as reads do not cross into the next
+        while let Some(loc) = layout.locate(at).filter(|_| done < buf.len()) {
+            let len = (buf.len() - done).min(loc.room as usize);
+            self.execute(&NOR_RDSCR, loc.addr, &[], &mut buf[done..done + len])?;
+            done += len;
+            at += loc.room;
+        }
+        Ok(())
+    }
+
+    fn program_otp(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
+        let layout = self.otp_layout()?;
+        if !layout.contains(offset, data.len()) {
+            return Err(SpiError::CapacityOutOfRange);
+        }
+        // Check every register first so a locked one fails before any write
+        let sr2 = self.read_sr2()?;
+        let mut at = offset;
+        let mut done = 0;
+        while let Some(loc) = layout.locate(at).filter(|_| done < data.len()) {
+            if layout.lock_mask(loc.region).is_some_and(|m| sr2 & m != 0) {
+                return Err(SpiError::OtpLocked(loc.region));
             }
+            done += loc.room as usize;
+            at += loc.room;
+        }
+
+        let mut at = offset;
+        let mut done = 0;
+        while let Some(loc) = layout.locate(at).filter(|_| done < data.len()) {
+            let len = (data.len() - done).min(loc.room as usize);
+            self.nor_write_enable()?;
+            self.execute(&NOR_PSCR, loc.addr, &data[done..done + len], &mut [])?;
+            self.nor_wait_until_ready();
+            done += len;
+            at += loc.room;
         }
+        Ok(())
+    }
+
+    fn lock_otp(&mut self, region: u8) -> Result<(), Self::Error> {
+        let mask = self
+            .otp_layout()?
+            .lock_mask(region)
+            .ok_or(SpiError::CapacityOutOfRange)?;
+        // Keep QE and the other bits; WRSR2 writes the whole register
+        let sr2 = self.read_sr2()?;
+        if sr2 & mask != 0 {
+            return Ok(());
+        }
+        self.nor_write_enable()?;
+        self.execute(&NOR_WRSR2, 0, &[sr2 | mask], &mut [])?;
+        self.nor_wait_until_ready();
+        Ok(())
+    }
+
+    fn otp_locked(&mut self, region: u8) -> Result<bool, Self::Error> {
+        let mask = self
+            .otp_layout()?
+            .lock_mask(region)
+            .ok_or(SpiError::CapacityOutOfRange)?;
+        Ok(self.read_sr2()? & mask != 0)
     }
 }
@@ -1,6 +1,7 @@
 // Licensed under the Apache-2.0 license
 
 use crate::spi::norflash;
+use crate::spi::protection::{self, Protection};
 use crate::{
     common::DummyDelay,
     spi::{norflash::SpiNorDevice, SpiError},
@@ -16,14 +17,23 @@ pub struct NorFlashBlockDevice<T: SpiNorDevice> {
     page_size: usize,   // Size of a programmable page (typically 256 bytes)
     sector_size: usize, // Size of an erasable sector (typically 4KB)
     supports_4byte_addr: bool,
+    program_retries: u8, // Extra attempts per page in `program_verified`
+    /// Whether erases can be checked against the block protection bits
+    protection_known: bool,
 }
 
+/// Default number of re-program attempts per page for `program_verified`
+pub const DEFAULT_PROGRAM_RETRIES: u8 = 2;
+
 #[derive(Debug)]
 pub enum BlockError {
     ReadError,
     ProgramError,
     EraseError,
     OutOfBounds,
+    /// The block protection bits cover part of the range, which the flash
+    /// would silently leave unchanged
+    WriteProtected,
 }
 
 /// Required by embedded-hal 1.0
@@ -32,7 +42,7 @@ impl BD::Error for BlockError {
         match self {
             BlockError::ReadError => BD::ErrorKind::ReadError,
             BlockError::ProgramError => BD::ErrorKind::ProgramError,
-            BlockError::EraseError => BD::ErrorKind::EraseError,
+            BlockError::EraseError | BlockError::WriteProtected => BD::ErrorKind::EraseError,
             BlockError::OutOfBounds => BD::ErrorKind::OutOfBounds,
         }
     }
@@ -66,15 +76,249 @@ impl<T: SpiNorDevice> NorFlashBlockDevice<T> {
             page_size,
             sector_size,
             supports_4byte_addr: capacity > 16 * 1024 * 1024,
+            program_retries: DEFAULT_PROGRAM_RETRIES,
+            protection_known: protection::status2_opcode(jedec_id).is_some(),
         })
     }
+
+    /// Sets how many times `program_verified` re-programs a page whose
+    /// read-back does not match before giving up.
+    pub fn set_program_retries(&mut self, retries: u8) {
+        self.program_retries = retries;
+    }
+
+    #[must_use]
+    pub fn program_retries(&self) -> u8 {
+        self.program_retries
+    }
+
+    /// Runs the checks `BlockDevice::program` makes before writing, without
+    /// touching the flash.
+    ///
+    /// Lets an updater pre-flight a whole image. Returns `OutOfBounds` if the
+    /// range does not fit the device and `ProgramError` if `data` is not a
+    /// whole number of pages.
+    pub fn program_check(&self, address: BlockAddrUsize, data: &[u8]) -> Result<(), BlockError> {
+        check_program(
+            address.to_byte_offset(),
+            data.len(),
+            self.program_size(),
+            self.capacity(),
+        )
+    }
+
+    fn program_page(&mut self, addr: usize, chunk: &[u8]) -> Result<(), BlockError> {
+        let result = if self.supports_4byte_addr {
+            self.device
+                .nor_page_program_4b(u32::try_from(addr).unwrap(), chunk)
+        } else {
+            self.device
+                .nor_page_program(u32::try_from(addr).unwrap(), chunk)
+        };
+        result.map_err(|_| BlockError::ProgramError)
+    }
+
+    /// Programs `data` like `BlockDevice::program`, reading back each page and
+    /// comparing it with the source.
+    ///
+    /// A mismatching page is programmed again up to `program_retries` times
+    /// before `BlockError::ProgramError` is returned. The target range must
+    /// have been erased beforehand.
+    pub fn program_verified(
+        &mut self,
+        address: BlockAddrUsize,
+        data: &[u8],
+    ) -> Result<(), BlockError> {
+        let addr = address.to_byte_offset();
+        let program_block = self.program_size();
+
+        self.program_check(address, data)?;
+        if program_block > norflash::SPI_NOR_PAGE_SIZE {
+            return Err(BlockError::ProgramError);
+        }
+
+        let mut readback = [0u8; norflash::SPI_NOR_PAGE_SIZE];
+        let readback = &mut readback[..program_block];
+        let mut delay = DummyDelay {};
+        for (i, chunk) in data.chunks(program_block).enumerate() {
+            let write_addr = addr + i * program_block;
+            let mut attempts = 0;
+            loop {
+                self.program_page(write_addr, chunk)?;
+                delay.delay_ns(2_000_000);
+                self.read(BlockAddrUsize(write_addr), readback)?;
+                if *readback == *chunk {
+                    break;
+                }
+                if attempts >= self.program_retries {
+                    return Err(BlockError::ProgramError);
+                }
+                attempts += 1;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: SpiNorDevice> NorFlashBlockDevice<T> {
+    /// Replaces the contents of the sectors in `range` with `data`.
+    ///
+    /// See [`erase_and_program`]. A misaligned start is reported as
+    /// `EraseError`, data longer than the range as `OutOfBounds` and a
+    /// read-back mismatch as `ProgramError`.
+    pub fn update(
+        &mut self,
+        range: BlockRange<BlockAddrUsize>,
+        data: &[u8],
+        verify: bool,
+    ) -> Result<(), BlockError> {
+        erase_and_program(self, range, data, verify).map_err(|e| match e {
+            UpdateError::Misaligned => BlockError::EraseError,
+            UpdateError::TooLong => BlockError::OutOfBounds,
+            UpdateError::VerifyFailed => BlockError::ProgramError,
+            UpdateError::Device(e) => e,
+        })
+    }
+}
+
+/// Bounds and alignment rules shared by `program` and `program_check`
+fn check_program(
+    addr: usize,
+    len: usize,
+    program_block: usize,
+    capacity: usize,
+) -> Result<(), BlockError> {
+    if addr.checked_add(len).map_or(true, |end| end > capacity) {
+        return Err(BlockError::OutOfBounds);
+    }
+    // Ensure data is aligned to full program_size chunks
+    if program_block == 0 || len % program_block != 0 {
+        return Err(BlockError::ProgramError);
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum UpdateError<E> {
+    /// `range` does not start on an erase sector boundary
+    Misaligned,
+    /// `data` does not fit in `range`
+    TooLong,
+    /// The read-back differs from `data`
+    VerifyFailed,
+    Device(E),
+}
+
+/// Erases the sectors of `range`, then programs `data` at its start.
+///
+/// NOR programming can only clear bits, so writing without erasing first
+/// silently ANDs old and new contents. `data` may be shorter than the
+/// range; the rest of the last page is padded with `0xFF` and any sectors
+/// past the data are left erased. With `verify` the programmed bytes are
+/// read back and compared.
+pub fn erase_and_program<D>(
+    device: &mut D,
+    range: BlockRange<BlockAddrUsize>,
+    data: &[u8],
+    verify: bool,
+) -> Result<(), UpdateError<D::Error>>
+where
+    D: BlockDevice<Address = BlockAddrUsize>,
+{
+    let start = range.start.to_byte_offset();
+    let sector = device.erase_size();
+    let page = device.program_size();
+    if sector == 0 || start % sector != 0 || page > UPDATE_MAX_PAGE {
+        return Err(UpdateError::Misaligned);
+    }
+    if data.len() > range.count.saturating_mul(sector) {
+        return Err(UpdateError::TooLong);
+    }
+
+    device.erase(range).map_err(UpdateError::Device)?;
+
+    let whole = data.len() - data.len() % page;
+    if whole > 0 {
+        device
+            .program(BlockAddrUsize(start), &data[..whole])
+            .map_err(UpdateError::Device)?;
+    }
+    if whole < data.len() {
+        let mut last = [0xFFu8; UPDATE_MAX_PAGE];
+        let tail = &data[whole..];
+        last[..tail.len()].copy_from_slice(tail);
+        device
+            .program(BlockAddrUsize(start + whole), &last[..page])
+            .map_err(UpdateError::Device)?;
+    }
+
+    if verify {
+        let mut readback = [0u8; UPDATE_MAX_PAGE];
+        for (i, chunk) in data.chunks(UPDATE_MAX_PAGE).enumerate() {
+            let readback = &mut readback[..chunk.len()];
+            device
+                .read(BlockAddrUsize(start + i * UPDATE_MAX_PAGE), readback)
+                .map_err(UpdateError::Device)?;
+            if *readback != *chunk {
+                return Err(UpdateError::VerifyFailed);
+            }
+        }
+    }
+    Ok(())
 }
 
+/// Largest program unit [`erase_and_program`] can pad a partial page for
+const UPDATE_MAX_PAGE: usize = norflash::SPI_NOR_PAGE_SIZE;
+
+/// Byte address on the flash device
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct BlockAddrUsize(pub usize);
 
 impl BlockAddress for BlockAddrUsize {}
 
+impl BlockAddrUsize {
+    #[must_use]
+    pub const fn new(offset: usize) -> Self {
+        Self(offset)
+    }
+
+    /// Address of byte `offset`, which must be aligned to `block_size`
+    #[must_use]
+    pub fn from_byte_offset(offset: usize, block_size: usize) -> Option<Self> {
+        (block_size != 0 && offset % block_size == 0).then_some(Self(offset))
+    }
+
+    /// Address of the first byte of block `index` for blocks of `block_size`
+    #[must_use]
+    pub fn from_block_index(index: usize, block_size: usize) -> Option<Self> {
+        index.checked_mul(block_size).map(Self)
+    }
+
+    #[must_use]
+    pub fn to_byte_offset(self) -> usize {
+        self.0
+    }
+
+    /// Index of the block containing this address
+    #[must_use]
+    pub fn block_index(self, block_size: usize) -> usize {
+        self.0 / block_size
+    }
+}
+
+impl From<usize> for BlockAddrUsize {
+    fn from(offset: usize) -> Self {
+        Self(offset)
+    }
+}
+
+impl From<u32> for BlockAddrUsize {
+    fn from(offset: u32) -> Self {
+        Self(offset as usize)
+    }
+}
+
 impl<T> BlockDevice for NorFlashBlockDevice<T>
 where
     T: SpiNorDevice,
@@ -96,7 +340,7 @@ where
     /// # Returns
     /// A result indicating success or failure.
     fn read(&mut self, address: Self::Address, data: &mut [u8]) -> Result<(), Self::Error> {
-        let addr = address.0;
+        let addr = address.to_byte_offset();
         let end = addr + data.len();
 
         if end > self.capacity() {
@@ -121,12 +365,21 @@ where
     }
 
     fn erase(&mut self, range: BlockRange<Self::Address>) -> Result<(), Self::Error> {
-        let mut addr = range.start.0;
+        let mut addr = range.start.to_byte_offset();
         let end: usize = addr + self.erase_size() * range.count;
 
         if end > self.capacity() {
             return Err(BlockError::OutOfBounds);
         }
+        if self.protection_known {
+            let map = self
+                .device
+                .protection_map()
+                .map_err(|_| BlockError::EraseError)?;
+            if map.protection_for(addr..end) != Protection::None {
+                return Err(BlockError::WriteProtected);
+            }
+        }
 
         for _i in 0..range.count {
             if let Err(_e) = self.device.nor_sector_erase(addr.try_into().unwrap()) {
@@ -144,19 +397,10 @@ where
     }
 
     fn program(&mut self, address: Self::Address, data: &[u8]) -> Result<(), Self::Error> {
-        let addr = address.0;
+        let addr = address.to_byte_offset();
         let program_block = self.program_size();
-        let end = addr + data.len();
-
-        // Ensure we don't go out of bounds
-        if end > self.capacity() {
-            return Err(BlockError::OutOfBounds);
-        }
 
-        // Ensure data is aligned to full program_size chunks
-        if data.len() % program_block != 0 {
-            return Err(BlockError::ProgramError); // Or define a new `MisalignedWrite` variant
-        }
+        self.program_check(address, data)?;
 
         let mut offset = 0;
         let mut delay = DummyDelay {};
@@ -165,17 +409,7 @@ where
 
             let write_addr = addr + offset;
 
-            let result = if self.supports_4byte_addr {
-                self.device
-                    .nor_page_program_4b(u32::try_from(write_addr).unwrap(), chunk)
-            } else {
-                self.device
-                    .nor_page_program(u32::try_from(write_addr).unwrap(), chunk)
-            };
-
-            if result.is_err() {
-                return Err(BlockError::ProgramError);
-            }
+            self.program_page(write_addr, chunk)?;
             offset += program_block;
             delay.delay_ns(2_000_000);
         }
@@ -187,3 +421,84 @@ where
         self.capacity
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{check_program, erase_and_program, BlockAddrUsize, BlockError, UpdateError};
+    use crate::mock_flash::MockFlash;
+    use proposed_traits::block_device::{BlockDevice, BlockRange};
+
+    fn range(start: usize, count: usize) -> BlockRange<BlockAddrUsize> {
+        BlockRange {
+            start: BlockAddrUsize(start),
+            count,
+        }
+    }
+
+    #[test]
+    fn test_erase_and_program() {
+        let mut flash = MockFlash::<4, 1024>::new(256);
+        // stale data that a plain program would AND into the new contents
+        flash.program(BlockAddrUsize(1024), &[0x0F; 1024]).unwrap();
+
+        let data: Vec<u8> = (0..1300).map(|i| (i % 251) as u8).collect();
+        erase_and_program(&mut flash, range(1024, 2), &data, true).unwrap();
+        for (i, &b) in data.iter().enumerate() {
+            assert_eq!(flash.byte(1024 + i), b);
+        }
+        // rest of the last page and of the second sector stay erased
+        assert!((1024 + 1300..3072).all(|i| flash.byte(i) == 0xFF));
+        // only the covered sectors were erased
+        assert_eq!(flash.erase_count(), 2);
+    }
+
+    #[test]
+    fn test_erase_and_program_rejects() {
+        let mut flash = MockFlash::<4, 1024>::new(256);
+        assert_eq!(
+            erase_and_program(&mut flash, range(512, 1), &[0; 16], false),
+            Err(UpdateError::Misaligned)
+        );
+        assert_eq!(
+            erase_and_program(&mut flash, range(0, 1), &[0; 1025], false),
+            Err(UpdateError::TooLong)
+        );
+        assert_eq!(flash.erase_count(), 0);
+    }
+
+    #[test]
+    fn test_check_program() {
+        assert!(check_program(0, 512, 256, 4096).is_ok());
+        assert!(check_program(3840, 256, 256, 4096).is_ok());
+        assert!(matches!(
+            check_program(3840, 512, 256, 4096),
+            Err(BlockError::OutOfBounds)
+        ));
+        assert!(matches!(
+            check_program(usize::MAX, 256, 256, 4096),
+            Err(BlockError::OutOfBounds)
+        ));
+        assert!(matches!(
+            check_program(0, 100, 256, 4096),
+            Err(BlockError::ProgramError)
+        ));
+    }
+
+    #[test]
+    fn test_block_addr_conversions() {
+        assert_eq!(
+            BlockAddrUsize::from_byte_offset(0x2000, 0x1000),
+            Some(BlockAddrUsize(0x2000))
+        );
+        assert_eq!(BlockAddrUsize::from_byte_offset(0x2001, 0x1000), None);
+        assert_eq!(BlockAddrUsize::from_byte_offset(0x2000, 0), None);
+        assert_eq!(
+            BlockAddrUsize::from_block_index(3, 256),
+            Some(BlockAddrUsize(0x300))
+        );
+        assert_eq!(BlockAddrUsize::from_block_index(usize::MAX, 2), None);
+        assert_eq!(BlockAddrUsize(0x300).to_byte_offset(), 0x300);
+        assert_eq!(BlockAddrUsize(0x3ff).block_index(256), 3);
+        assert_eq!(BlockAddrUsize::from(0x100u32), BlockAddrUsize::new(0x100));
+    }
+}
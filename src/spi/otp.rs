@@ -0,0 +1,154 @@
+// Licensed under the Apache-2.0 license
+
+//! One-time programmable security registers of NOR flash
+//!
+//! Winbond parts carry three 256-byte security registers beside the main
+//! array, read with 0x48 and programmed with 0x42 at `n << 12`. Each has a
+//! lock bit in status register 2 that, once set, makes the register read
+//! only for good. Callers address the registers as one space of
+//! [`OtpLayout::size`] bytes starting at 0.
+
+use super::norflash::SPI_NOR_MFR_ID_WINBOND;
+use core::ops::RangeInclusive;
+
+/// Security register organisation of a part
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OtpLayout {
+    /// Number of registers
+    pub regions: u8,
+    /// Bytes per register
+    pub region_size: u32,
+    /// Bit of status register 2 locking register 0; the next registers
+    /// use the following bits
+    pub lock_bit: u8,
+}
+
+/// Where an OTP offset lands in the flash
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OtpLocation {
+    pub region: u8,
+    /// Address to send with the security register opcodes
+    pub addr: u32,
+    /// Bytes from `addr` to the end of the register
+    pub room: u32,
+}
+
+impl OtpLayout {
+    /// Size of the OTP space in bytes
+    #[must_use]
+    pub fn size(&self) -> u32 {
+        u32::from(self.regions) * self.region_size
+    }
+
+    /// Whether `len` bytes from `offset` fit in the OTP space
+    #[must_use]
+    pub fn contains(&self, offset: u32, len: usize) -> bool {
+        u32::try_from(len)
+            .ok()
+            .and_then(|len| offset.checked_add(len))
+            .is_some_and(|end| end <= self.size())
+    }
+
+    /// Register, flash address and bytes left for `offset` in the OTP space
+    #[must_use]
+    pub fn locate(&self, offset: u32) -> Option<OtpLocation> {
+        if offset >= self.size() {
+            return None;
+        }
+        let region = u8::try_from(offset / self.region_size).ok()?;
+        let within = offset % self.region_size;
+        Some(OtpLocation {
+            region,
+            addr: ((u32::from(region) + 1) << 12) | within,
+            room: self.region_size - within,
+        })
+    }
+
+    /// Status register 2 mask of the lock bit of `region`
+    #[must_use]
+    pub fn lock_mask(&self, region: u8) -> Option<u8> {
+        (region < self.regions).then(|| 1 << (self.lock_bit + region))
+    }
+}
+
+struct Part {
+    manufacturer: u8,
+    /// JEDEC capacity codes
+    capacity: RangeInclusive<u8>,
+    layout: OtpLayout,
+}
+
+const PARTS: [Part; 1] = [
+    // Winbond W25Q32JV, W25Q64JV, W25Q128JV; LB1-LB3 in SR2[5:3]
+    Part {
+        manufacturer: SPI_NOR_MFR_ID_WINBOND,
+        capacity: 0x16..=0x18,
+        layout: OtpLayout {
+            regions: 3,
+            region_size: 256,
+            lock_bit: 3,
+        },
+    },
+];
+
+/// Security register layout of the part `jedec_id`, or `None` if it has no
+/// OTP area this driver knows how to reach
+#[must_use]
+pub fn otp_layout(jedec_id: [u8; 3]) -> Option<OtpLayout> {
+    PARTS
+        .iter()
+        .find(|p| p.manufacturer == jedec_id[0] && p.capacity.contains(&jedec_id[2]))
+        .map(|p| p.layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{otp_layout, OtpLocation};
+
+    const W25Q128JV: [u8; 3] = [0xEF, 0x40, 0x18];
+    const W25Q256JV: [u8; 3] = [0xEF, 0x40, 0x19];
+    const MX25L12845G: [u8; 3] = [0xC2, 0x20, 0x18];
+
+    #[test]
+    fn test_supported_parts() {
+        assert!(otp_layout(W25Q128JV).is_some());
+        assert!(otp_layout(W25Q256JV).is_none());
+        assert!(otp_layout(MX25L12845G).is_none());
+    }
+
+    #[test]
+    fn test_locate() {
+        let layout = otp_layout(W25Q128JV).unwrap();
+        assert_eq!(layout.size(), 768);
+        assert_eq!(
+            layout.locate(0),
+            Some(OtpLocation {
+                region: 0,
+                addr: 0x1000,
+                room: 256
+            })
+        );
+        assert_eq!(
+            layout.locate(0x1f0),
+            Some(OtpLocation {
+                region: 1,
+                addr: 0x20f0,
+                room: 0x10
+            })
+        );
+        assert_eq!(layout.locate(767).map(|l| l.addr), Some(0x30ff));
+        assert_eq!(layout.locate(768), None);
+        assert!(layout.contains(0, 768));
+        assert!(layout.contains(768, 0));
+        assert!(!layout.contains(767, 2));
+        assert!(!layout.contains(u32::MAX, 2));
+    }
+
+    #[test]
+    fn test_lock_mask() {
+        let layout = otp_layout(W25Q128JV).unwrap();
+        assert_eq!(layout.lock_mask(0), Some(0x08));
+        assert_eq!(layout.lock_mask(2), Some(0x20));
+        assert_eq!(layout.lock_mask(3), None);
+    }
+}
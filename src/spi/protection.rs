@@ -0,0 +1,294 @@
+// Licensed under the Apache-2.0 license
+
+//! Decoding of the NOR flash block protection bits
+//!
+//! The BP, TB, SEC and CMP bits select one contiguous protected region at
+//! the top or bottom of the array, but their positions and the size each
+//! BP value stands for differ between vendors and densities. The parts
+//! known here are described by `SCHEMES`, taken from the protection
+//! tables of their datasheets.
+
+use super::norflash::{
+    SPI_NOR_CMD_RDCR, SPI_NOR_CMD_RDSR2, SPI_NOR_MFR_ID_GIGADEVICE, SPI_NOR_MFR_ID_MXIC,
+    SPI_NOR_MFR_ID_WINBOND,
+};
+use core::ops::{Range, RangeInclusive};
+
+/// How much of an address range is write protected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protection {
+    Full,
+    Partial,
+    None,
+}
+
+/// Byte range the status registers currently protect
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtectionMap {
+    capacity: usize,
+    protected: Range<usize>,
+}
+
+impl ProtectionMap {
+    /// Protected bytes; empty when nothing is protected
+    #[must_use]
+    pub fn protected(&self) -> Range<usize> {
+        self.protected.clone()
+    }
+
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Whether programs and erases in `range` would be ignored by the flash
+    #[must_use]
+    pub fn protection_for(&self, range: Range<usize>) -> Protection {
+        let protected = &self.protected;
+        if range.is_empty()
+            || protected.is_empty()
+            || range.end <= protected.start
+            || range.start >= protected.end
+        {
+            Protection::None
+        } else if protected.start <= range.start && range.end <= protected.end {
+            Protection::Full
+        } else {
+            Protection::Partial
+        }
+    }
+}
+
+/// A bit in status register 1 or in the second register of a [`Scheme`]
+#[derive(Clone, Copy)]
+enum Bit {
+    Sr1(u8),
+    Sr2(u8),
+}
+
+impl Bit {
+    fn is_set(self, sr1: u8, sr2: u8) -> bool {
+        match self {
+            Bit::Sr1(bit) => sr1 & (1 << bit) != 0,
+            Bit::Sr2(bit) => sr2 & (1 << bit) != 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum BpLayout {
+    /// BP2..0 in SR1[4:2], TB in SR1[5], SEC in SR1[6]. BP 1 to 6 protect
+    /// 1/64 to 1/2 of the array, or 4 KiB to 32 KiB with SEC set; 7 all.
+    Fraction,
+    /// BP3..0 in SR1[5:2] protect 64 KiB doubling with each step, up to
+    /// the whole array
+    Blocks { tb: Bit },
+}
+
+struct Scheme {
+    manufacturer: u8,
+    /// JEDEC capacity codes (log2 of the size in bytes)
+    capacity: RangeInclusive<u8>,
+    /// Opcode reading the register that holds CMP or TB
+    sr2_opcode: u32,
+    layout: BpLayout,
+    /// CMP bit in the second register, if the part has one
+    cmp: Option<u8>,
+}
+
+const SCHEMES: [Scheme; 4] = [
+    // Winbond W25Q32JV, W25Q64JV, W25Q128JV
+    Scheme {
+        manufacturer: SPI_NOR_MFR_ID_WINBOND,
+        capacity: 0x16..=0x18,
+        sr2_opcode: SPI_NOR_CMD_RDSR2,
+        layout: BpLayout::Fraction,
+        cmp: Some(6),
+    },
+    // Winbond W25Q256JV
+    Scheme {
+        manufacturer: SPI_NOR_MFR_ID_WINBOND,
+        capacity: 0x19..=0x19,
+        sr2_opcode: SPI_NOR_CMD_RDSR2,
+        layout: BpLayout::Blocks { tb: Bit::Sr1(6) },
+        cmp: Some(6),
+    },
+    // GigaDevice GD25Q32C, GD25Q64C, GD25Q128C; BP3 and BP4 sit where
+    // Winbond has TB and SEC and mean the same
+    Scheme {
+        manufacturer: SPI_NOR_MFR_ID_GIGADEVICE,
+        capacity: 0x16..=0x18,
+        sr2_opcode: SPI_NOR_CMD_RDSR2,
+        layout: BpLayout::Fraction,
+        cmp: Some(6),
+    },
+    // Macronix MX25L3233F to MX25L25645G; TB is in the configuration register
+    Scheme {
+        manufacturer: SPI_NOR_MFR_ID_MXIC,
+        capacity: 0x16..=0x19,
+        sr2_opcode: SPI_NOR_CMD_RDCR,
+        layout: BpLayout::Blocks { tb: Bit::Sr2(3) },
+        cmp: None,
+    },
+];
+
+fn scheme(jedec_id: [u8; 3]) -> Option<&'static Scheme> {
+    SCHEMES
+        .iter()
+        .find(|s| s.manufacturer == jedec_id[0] && s.capacity.contains(&jedec_id[2]))
+}
+
+/// Opcode of the register to pass as `sr2` to [`decode_protection`], or
+/// `None` if the part's protection scheme is not known
+#[must_use]
+pub fn status2_opcode(jedec_id: [u8; 3]) -> Option<u32> {
+    scheme(jedec_id).map(|s| s.sr2_opcode)
+}
+
+/// Protected range for the part `jedec_id` given its status register 1 and
+/// the register read with [`status2_opcode`]
+#[must_use]
+pub fn decode_protection(jedec_id: [u8; 3], sr1: u8, sr2: u8) -> Option<ProtectionMap> {
+    let scheme = scheme(jedec_id)?;
+    let capacity = 1usize << jedec_id[2];
+
+    let (len, bottom) = match scheme.layout {
+        BpLayout::Fraction => {
+            let bp = (sr1 >> 2) & 0x7;
+            let len = match bp {
+                0 => 0,
+                7 => capacity,
+                _ if sr1 & 0x40 != 0 => (0x1000 << (bp - 1)).min(0x8000),
+                _ => capacity >> (7 - bp),
+            };
+            (len, sr1 & 0x20 != 0)
+        }
+        BpLayout::Blocks { tb } => {
+            let bp = (sr1 >> 2) & 0xf;
+            let len = if bp == 0 {
+                0
+            } else {
+                (0x1_0000usize << (bp - 1)).min(capacity)
+            };
+            (len, tb.is_set(sr1, sr2))
+        }
+    };
+
+    let (mut start, mut end) = if bottom {
+        (0, len)
+    } else {
+        (capacity - len, capacity)
+    };
+    // CMP protects the rest of the array instead
+    if scheme.cmp.is_some_and(|bit| sr2 & (1 << bit) != 0) {
+        (start, end) = if start == 0 {
+            (end, capacity)
+        } else {
+            (0, start)
+        };
+    }
+    if start == end {
+        (start, end) = (0, 0);
+    }
+
+    Some(ProtectionMap {
+        capacity,
+        protected: start..end,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_protection, status2_opcode, Protection};
+    use crate::spi::norflash::{SPI_NOR_CMD_RDCR, SPI_NOR_CMD_RDSR2};
+
+    const W25Q128JV: [u8; 3] = [0xEF, 0x40, 0x18];
+    const W25Q256JV: [u8; 3] = [0xEF, 0x40, 0x19];
+    const GD25Q64C: [u8; 3] = [0xC8, 0x40, 0x17];
+    const MX25L12845G: [u8; 3] = [0xC2, 0x20, 0x18];
+
+    fn protected(id: [u8; 3], sr1: u8, sr2: u8) -> core::ops::Range<usize> {
+        decode_protection(id, sr1, sr2).unwrap().protected()
+    }
+
+    #[test]
+    fn test_winbond_w25q128jv() {
+        assert_eq!(status2_opcode(W25Q128JV), Some(SPI_NOR_CMD_RDSR2));
+        assert_eq!(protected(W25Q128JV, 0x00, 0), 0..0);
+        // blocks 252-255, upper 1/64
+        assert_eq!(protected(W25Q128JV, 0x04, 0), 0xFC_0000..0x100_0000);
+        // blocks 128-255, upper 1/2
+        assert_eq!(protected(W25Q128JV, 0x18, 0), 0x80_0000..0x100_0000);
+        // TB: blocks 0-3, lower 1/64
+        assert_eq!(protected(W25Q128JV, 0x24, 0), 0..0x4_0000);
+        assert_eq!(protected(W25Q128JV, 0x1C, 0), 0..0x100_0000);
+        // SEC: 4 KB upper and lower, 32 KB for BP 10x
+        assert_eq!(protected(W25Q128JV, 0x44, 0), 0xFF_F000..0x100_0000);
+        assert_eq!(protected(W25Q128JV, 0x64, 0), 0..0x1000);
+        assert_eq!(protected(W25Q128JV, 0x54, 0), 0xFF_8000..0x100_0000);
+        // CMP: blocks 0-251, lower 63/64; BP 000 all, BP 111 none
+        assert_eq!(protected(W25Q128JV, 0x04, 0x40), 0..0xFC_0000);
+        assert_eq!(protected(W25Q128JV, 0x00, 0x40), 0..0x100_0000);
+        assert_eq!(protected(W25Q128JV, 0x1C, 0x40), 0..0);
+    }
+
+    #[test]
+    fn test_winbond_w25q256jv() {
+        // block 511, upper 1/512
+        assert_eq!(protected(W25Q256JV, 0x04, 0), 0x1FF_0000..0x200_0000);
+        // TB: block 0
+        assert_eq!(protected(W25Q256JV, 0x44, 0), 0..0x1_0000);
+        // blocks 256-511, upper 1/2, and BP 1010 all
+        assert_eq!(protected(W25Q256JV, 0x24, 0), 0x100_0000..0x200_0000);
+        assert_eq!(protected(W25Q256JV, 0x28, 0), 0..0x200_0000);
+        // CMP: blocks 0-510
+        assert_eq!(protected(W25Q256JV, 0x04, 0x40), 0..0x1FF_0000);
+    }
+
+    #[test]
+    fn test_gigadevice_gd25q64c() {
+        // blocks 124-127, upper 1/32
+        assert_eq!(protected(GD25Q64C, 0x08, 0), 0x7C_0000..0x80_0000);
+        // BP3: blocks 0-63, lower 1/2
+        assert_eq!(protected(GD25Q64C, 0x38, 0), 0..0x40_0000);
+        // BP4: 16 KB upper
+        assert_eq!(protected(GD25Q64C, 0x4C, 0), 0x7F_C000..0x80_0000);
+    }
+
+    #[test]
+    fn test_macronix_mx25l12845g() {
+        assert_eq!(status2_opcode(MX25L12845G), Some(SPI_NOR_CMD_RDCR));
+        // block 255
+        assert_eq!(protected(MX25L12845G, 0x04, 0), 0xFF_0000..0x100_0000);
+        // blocks 192-255 and 128-255
+        assert_eq!(protected(MX25L12845G, 0x1C, 0), 0xC0_0000..0x100_0000);
+        assert_eq!(protected(MX25L12845G, 0x20, 0), 0x80_0000..0x100_0000);
+        // BP 1001 and above: all
+        assert_eq!(protected(MX25L12845G, 0x24, 0), 0..0x100_0000);
+        // TB in the configuration register: block 0
+        assert_eq!(protected(MX25L12845G, 0x04, 0x08), 0..0x1_0000);
+        // SR1 bit 6 is QE here, not TB
+        assert_eq!(protected(MX25L12845G, 0x44, 0), 0xFF_0000..0x100_0000);
+    }
+
+    #[test]
+    fn test_unknown_part() {
+        assert_eq!(status2_opcode([0x20, 0xBA, 0x18]), None);
+        assert!(decode_protection([0xEF, 0x40, 0x15], 0x04, 0).is_none());
+    }
+
+    #[test]
+    fn test_protection_for() {
+        let map = decode_protection(W25Q128JV, 0x04, 0).unwrap();
+        assert_eq!(map.protection_for(0..0x1000), Protection::None);
+        assert_eq!(map.protection_for(0xFC_0000..0xFC_1000), Protection::Full);
+        assert_eq!(
+            map.protection_for(0xFB_F000..0xFC_1000),
+            Protection::Partial
+        );
+        assert_eq!(map.protection_for(0xFC_0000..0xFC_0000), Protection::None);
+
+        let none = decode_protection(W25Q128JV, 0, 0).unwrap();
+        assert_eq!(none.protection_for(0..0x100_0000), Protection::None);
+    }
+}
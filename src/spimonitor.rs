@@ -1,9 +1,13 @@
 // Licensed under the Apache-2.0 license
 
+use crate::common::crc32;
+use crate::spi::norflashblockdevice::{self, BlockAddrUsize};
+use crate::watchdog::{WdtController, WdtInstance};
 use ast1060_pac::Scu;
 use core::cmp::min;
 use core::fmt;
 use core::marker::PhantomData;
+use proposed_traits::block_device::{BlockDevice, BlockRange};
 //use core::ops::bit;
 //use embedded_hal::delay::DelayNs;
 
@@ -96,6 +100,10 @@ pub enum SpiMonitorError {
     AddressInvalid(u32),
     LengthInvalid(u32),
     AddrTblRegsLocked(u32),
+    PolicyBufferTooSmall(u32),
+    PolicyInvalid(u32),
+    PolicyNotFound,
+    PolicyFlash,
 }
 //Allow command table information
 pub const SPIM_CMD_TABLE_NUM: usize = 32;
@@ -186,6 +194,21 @@ pub const CMD_WREAR: u8 = 0xC5;
 // define the total ram size used to record exception log
 pub const SPIM_LOG_RAM_TOTAL_SIZE: u32 = 2048;
 
+// serialized filter policy: header, body, CRC-32
+pub const SPIM_POLICY_MAGIC: u32 = 0x504d_5053; // "SPMP"
+pub const SPIM_POLICY_VERSION: u16 = 2;
+const SPIM_POLICY_HDR_SIZE: usize = 8;
+// monitor id, mux select, flags, command and region counts
+const SPIM_POLICY_FIXED_SIZE: usize = 6;
+const SPIM_POLICY_CRC_SIZE: usize = 4;
+const SPIM_POLICY_FLAG_REL_FLASH_RST: u8 = 1 << 0;
+const SPIM_POLICY_FLAG_EXTRA_CLK: u8 = 1 << 1;
+pub const SPIM_POLICY_MAX_SIZE: usize = SPIM_POLICY_HDR_SIZE
+    + SPIM_POLICY_FIXED_SIZE
+    + SPIM_CMD_TABLE_NUM
+    + 2 * BLOCK_REGION_NUM * 8
+    + SPIM_POLICY_CRC_SIZE;
+
 pub const SPIM_CMD_TABLE_LOCK_MASK: u32 = 1 << 23;
 pub const SPIM_CMD_TABLE_VALID_ONCE_BIT: u32 = 1 << 31;
 pub const SPIM_CMD_TABLE_VALID_BIT: u32 = 1 << 30;
@@ -1072,6 +1095,149 @@ impl<SPIPF: SpipfInstance> SpiMonitor<SPIPF> {
         self.allow_cmd_num = allow_cmd_num;
     }
 
+    /// Serializes everything `aspeed_spi_monitor_init` programs (allowed
+    /// commands, blocked regions, mux select, extra clock and flash-reset
+    /// release) into `buf` and returns the length used.
+    ///
+    /// The image carries a magic, version, monitor id and CRC-32 so it can
+    /// be kept in a [`PolicyStore`] and validated by `apply_policy`.
+    pub fn export_policy(&self, buf: &mut [u8]) -> Result<usize, SpiMonitorError> {
+        let allow_num = self.allow_cmd_num as usize;
+        let read_num = self.read_blocked_region_num as usize;
+        let write_num = self.write_blocked_region_num as usize;
+        let body_len = SPIM_POLICY_FIXED_SIZE + allow_num + (read_num + write_num) * 8;
+        let total = SPIM_POLICY_HDR_SIZE + body_len + SPIM_POLICY_CRC_SIZE;
+        if buf.len() < total {
+            return Err(SpiMonitorError::PolicyBufferTooSmall(
+                u32::try_from(total).unwrap_or(u32::MAX),
+            ));
+        }
+
+        buf[0..4].copy_from_slice(&SPIM_POLICY_MAGIC.to_le_bytes());
+        buf[4..6].copy_from_slice(&SPIM_POLICY_VERSION.to_le_bytes());
+        buf[6..8].copy_from_slice(&u16::try_from(body_len).unwrap().to_le_bytes());
+
+        let mut pos = SPIM_POLICY_HDR_SIZE;
+        let mut flags = 0;
+        if self.force_rel_flash_rst {
+            flags |= SPIM_POLICY_FLAG_REL_FLASH_RST;
+        }
+        if self.extra_clk_en {
+            flags |= SPIM_POLICY_FLAG_EXTRA_CLK;
+        }
+        for b in [
+            SPIPF::FILTER_ID as u8,
+            self.ext_mux_sel as u8,
+            flags,
+            self.allow_cmd_num,
+            self.read_blocked_region_num,
+            self.write_blocked_region_num,
+        ] {
+            buf[pos] = b;
+            pos += 1;
+        }
+        buf[pos..pos + allow_num].copy_from_slice(&self.allow_cmd_list[..allow_num]);
+        pos += allow_num;
+        for region in self.read_blocked_regions[..read_num]
+            .iter()
+            .chain(&self.write_blocked_regions[..write_num])
+        {
+            buf[pos..pos + 4].copy_from_slice(&region.start.to_le_bytes());
+            buf[pos + 4..pos + 8].copy_from_slice(&region.length.to_le_bytes());
+            pos += 8;
+        }
+
+        let crc = crc32(&buf[..pos]);
+        buf[pos..pos + SPIM_POLICY_CRC_SIZE].copy_from_slice(&crc.to_le_bytes());
+        Ok(total)
+    }
+
+    /// Loads a policy produced by `export_policy` into this monitor.
+    ///
+    /// Only the software copy is updated; call `aspeed_spi_monitor_init` (or
+    /// use `restore`) to program the hardware. Nothing is changed if the
+    /// image fails validation or was exported from another monitor.
+    pub fn apply_policy(&mut self, buf: &[u8]) -> Result<(), SpiMonitorError> {
+        let len = policy_len(buf)?;
+        let body = &buf[SPIM_POLICY_HDR_SIZE..len - SPIM_POLICY_CRC_SIZE];
+        let (allow_num, read_num, write_num) =
+            (body[3] as usize, body[4] as usize, body[5] as usize);
+        if body[0] != SPIPF::FILTER_ID as u8
+            || body[1] > 1
+            || body[2] & !(SPIM_POLICY_FLAG_REL_FLASH_RST | SPIM_POLICY_FLAG_EXTRA_CLK) != 0
+            || allow_num > SPIM_CMD_TABLE_NUM
+            || read_num > BLOCK_REGION_NUM
+            || write_num > BLOCK_REGION_NUM
+            || body.len() != SPIM_POLICY_FIXED_SIZE + allow_num + (read_num + write_num) * 8
+        {
+            return Err(SpiMonitorError::PolicyInvalid(0));
+        }
+
+        let mut regions = [RegionInfo {
+            start: 0,
+            length: 0,
+        }; 2 * BLOCK_REGION_NUM];
+        let region_bytes = &body[SPIM_POLICY_FIXED_SIZE + allow_num..];
+        for (region, chunk) in regions.iter_mut().zip(region_bytes.chunks_exact(8)) {
+            region.start = u32::from_le_bytes(chunk[0..4].try_into().unwrap());
+            region.length = u32::from_le_bytes(chunk[4..8].try_into().unwrap());
+        }
+
+        self.ext_mux_sel = if body[1] == 0 {
+            SpimExtMuxSel::SpimExtMuxSel0
+        } else {
+            SpimExtMuxSel::SpimExtMuxSel1
+        };
+        self.force_rel_flash_rst = body[2] & SPIM_POLICY_FLAG_REL_FLASH_RST != 0;
+        self.extra_clk_en = body[2] & SPIM_POLICY_FLAG_EXTRA_CLK != 0;
+        self.spim_set_cmd_table(
+            &body[SPIM_POLICY_FIXED_SIZE..SPIM_POLICY_FIXED_SIZE + allow_num],
+            body[3],
+        );
+        self.spim_set_read_blocked_regions(&regions[..read_num], body[4]);
+        self.spim_set_write_blocked_regions(&regions[read_num..read_num + write_num], body[5]);
+        Ok(())
+    }
+
+    /// Builds a monitor from a stored policy and programs the hardware.
+    ///
+    /// The hardware is brought up in the same order as
+    /// `aspeed_spi_monitor_init`: software reset, passthrough and external
+    /// mux, command table, address privilege tables, monitor enable,
+    /// interrupts, pin control and finally flash reset release.
+    pub fn restore(policy: &[u8]) -> Result<Self, SpiMonitorError> {
+        let mut monitor = Self::new(false, SpimExtMuxSel::SpimExtMuxSel0, &[], 0, &[], 0, &[], 0);
+        monitor.apply_policy(policy)?;
+        monitor.spim_sw_rst();
+        monitor.aspeed_spi_monitor_init();
+        Ok(monitor)
+    }
+
+    /// Re-applies the policy saved in `store` if the last reset was caused
+    /// by `wdt` timing out, and returns `Ok(None)` otherwise.
+    ///
+    /// Call it right after the RoT's own flash is up and before any other
+    /// SPI or host-facing initialization, so the host never sees an
+    /// unfiltered flash across a watchdog reset. `wdt` must not have been
+    /// started yet, as `start` clears the reset status. On a cold reset the
+    /// caller runs its normal init and saves the resulting policy.
+    pub fn early_restore<D, W>(
+        store: &PolicyStore,
+        device: &mut D,
+        wdt: &WdtController<W>,
+    ) -> Result<Option<Self>, SpiMonitorError>
+    where
+        D: BlockDevice<Address = BlockAddrUsize>,
+        W: WdtInstance,
+    {
+        if !wdt.reset_by_timeout() {
+            return Ok(None);
+        }
+        let mut policy = [0u8; SPIM_POLICY_MAX_SIZE];
+        let len = store.load(device, &mut policy)?;
+        Self::restore(&policy[..len]).map(Some)
+    }
+
     pub fn spim_dump_read_blocked_regions(&mut self) {}
     pub fn spim_dump_write_blocked_regions(&mut self) {}
 
@@ -1373,6 +1539,152 @@ impl<SPIPF: SpipfInstance> SpiMonitor<SPIPF> {
         }
     }
 }
+
+/// Checks the header and CRC of a policy image and returns its length
+fn policy_len(buf: &[u8]) -> Result<usize, SpiMonitorError> {
+    if buf.len() < SPIM_POLICY_HDR_SIZE {
+        return Err(SpiMonitorError::PolicyInvalid(0));
+    }
+    let magic = u32::from_le_bytes(buf[0..4].try_into().unwrap());
+    let version = u16::from_le_bytes(buf[4..6].try_into().unwrap());
+    let body_len = u16::from_le_bytes(buf[6..8].try_into().unwrap()) as usize;
+    if magic != SPIM_POLICY_MAGIC {
+        return Err(SpiMonitorError::PolicyInvalid(magic));
+    }
+    if version != SPIM_POLICY_VERSION {
+        return Err(SpiMonitorError::PolicyInvalid(u32::from(version)));
+    }
+    let end = SPIM_POLICY_HDR_SIZE + body_len;
+    if body_len < SPIM_POLICY_FIXED_SIZE
+        || end + SPIM_POLICY_CRC_SIZE > SPIM_POLICY_MAX_SIZE
+        || buf.len() < end + SPIM_POLICY_CRC_SIZE
+    {
+        return Err(SpiMonitorError::PolicyInvalid(0));
+    }
+    let crc = u32::from_le_bytes(buf[end..end + SPIM_POLICY_CRC_SIZE].try_into().unwrap());
+    if crc != crc32(&buf[..end]) {
+        return Err(SpiMonitorError::PolicyInvalid(crc));
+    }
+    Ok(end + SPIM_POLICY_CRC_SIZE)
+}
+
+// policy store slot: CRC-32 of the following bytes, sequence number, image
+const POLICY_SLOT_HDR_SIZE: usize = 8;
+const POLICY_SLOT_SIZE: usize = POLICY_SLOT_HDR_SIZE + SPIM_POLICY_MAX_SIZE;
+
+/// Keeps the latest filter policy in two erase sectors of a block device
+///
+/// Each save goes to the sector that does not hold the newest copy, tagged
+/// with the next sequence number, so losing power mid-save leaves the
+/// previous policy readable. The sectors should be dedicated ones on the
+/// RoT's own (FMC) flash, outside any firmware image or [`crate::kvstore`]
+/// partition, as the host can reach neither there.
+#[derive(Debug, Clone, Copy)]
+pub struct PolicyStore {
+    base: usize,
+}
+
+impl PolicyStore {
+    /// Uses the two erase sectors starting at byte `base`
+    #[must_use]
+    pub const fn new(base: usize) -> Self {
+        Self { base }
+    }
+
+    /// Writes `policy`, an image from `export_policy`, to the older slot
+    pub fn save<D>(&self, device: &mut D, policy: &[u8]) -> Result<(), SpiMonitorError>
+    where
+        D: BlockDevice<Address = BlockAddrUsize>,
+    {
+        let len = policy_len(policy)?;
+        let sector = self.check_layout(device)?;
+        let (slot, seq) = match self.newest(device)? {
+            Some((slot, seq, _)) => (1 - slot, seq.wrapping_add(1)),
+            None => (0, 1),
+        };
+
+        let mut image = [0xFFu8; POLICY_SLOT_SIZE];
+        image[4..8].copy_from_slice(&seq.to_le_bytes());
+        image[POLICY_SLOT_HDR_SIZE..POLICY_SLOT_HDR_SIZE + len].copy_from_slice(&policy[..len]);
+        let crc = crc32(&image[4..POLICY_SLOT_HDR_SIZE + len]);
+        image[0..4].copy_from_slice(&crc.to_le_bytes());
+        norflashblockdevice::erase_and_program(
+            device,
+            BlockRange {
+                start: BlockAddrUsize(self.base + slot * sector),
+                count: 1,
+            },
+            &image[..POLICY_SLOT_HDR_SIZE + len],
+            true,
+        )
+        .map_err(|_| SpiMonitorError::PolicyFlash)
+    }
+
+    /// Copies the newest valid policy into `buf` and returns its length
+    pub fn load<D>(&self, device: &mut D, buf: &mut [u8]) -> Result<usize, SpiMonitorError>
+    where
+        D: BlockDevice<Address = BlockAddrUsize>,
+    {
+        let sector = self.check_layout(device)?;
+        let (slot, _, len) = self
+            .newest(device)?
+            .ok_or(SpiMonitorError::PolicyNotFound)?;
+        if buf.len() < len {
+            return Err(SpiMonitorError::PolicyBufferTooSmall(
+                u32::try_from(len).unwrap_or(u32::MAX),
+            ));
+        }
+        device
+            .read(
+                BlockAddrUsize(self.base + slot * sector + POLICY_SLOT_HDR_SIZE),
+                &mut buf[..len],
+            )
+            .map_err(|_| SpiMonitorError::PolicyFlash)?;
+        Ok(len)
+    }
+
+    fn check_layout<D>(&self, device: &D) -> Result<usize, SpiMonitorError>
+    where
+        D: BlockDevice<Address = BlockAddrUsize>,
+    {
+        let sector = device.erase_size();
+        if sector < POLICY_SLOT_SIZE
+            || self.base % sector != 0
+            || self.base + 2 * sector > device.capacity()
+        {
+            return Err(SpiMonitorError::PolicyFlash);
+        }
+        Ok(sector)
+    }
+
+    /// Returns the slot, sequence number and image length of the newest
+    /// valid copy
+    fn newest<D>(&self, device: &mut D) -> Result<Option<(usize, u32, usize)>, SpiMonitorError>
+    where
+        D: BlockDevice<Address = BlockAddrUsize>,
+    {
+        let sector = device.erase_size();
+        let mut newest: Option<(usize, u32, usize)> = None;
+        let mut image = [0u8; POLICY_SLOT_SIZE];
+        for slot in 0..2 {
+            device
+                .read(BlockAddrUsize(self.base + slot * sector), &mut image)
+                .map_err(|_| SpiMonitorError::PolicyFlash)?;
+            let Ok(len) = policy_len(&image[POLICY_SLOT_HDR_SIZE..]) else {
+                continue;
+            };
+            let crc = u32::from_le_bytes([image[0], image[1], image[2], image[3]]);
+            if crc != crc32(&image[4..POLICY_SLOT_HDR_SIZE + len]) {
+                continue;
+            }
+            let seq = u32::from_le_bytes([image[4], image[5], image[6], image[7]]);
+            if newest.is_none_or(|(_, s, _)| seq > s) {
+                newest = Some((slot, seq, len));
+            }
+        }
+        Ok(newest)
+    }
+}
 //
 // Example trait for enabling SPI filter
 //pub trait SpiFilterEnable {
@@ -1387,3 +1699,150 @@ impl<SPIPF: SpipfInstance> SpiFilterEnable for SpiMonitor<SPIPF> {
     }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        PolicyStore, SpiMonitorError, BLOCK_REGION_NUM, SPIM_POLICY_CRC_SIZE,
+        SPIM_POLICY_FIXED_SIZE, SPIM_POLICY_HDR_SIZE, SPIM_POLICY_MAGIC, SPIM_POLICY_MAX_SIZE,
+        SPIM_POLICY_VERSION,
+    };
+    use crate::common::crc32;
+    use crate::mock_flash::MockFlash;
+
+    type Flash = MockFlash<4, 1024>;
+
+    const BASE: usize = 1024;
+
+    /// Builds a valid image allowing the single command `cmd` and blocking
+    /// writes to every 64 KB region of the first 2 MB
+    fn policy(cmd: u8) -> ([u8; SPIM_POLICY_MAX_SIZE], usize) {
+        let mut buf = [0u8; SPIM_POLICY_MAX_SIZE];
+        let body_len = SPIM_POLICY_FIXED_SIZE + 1 + BLOCK_REGION_NUM * 8;
+        buf[0..4].copy_from_slice(&SPIM_POLICY_MAGIC.to_le_bytes());
+        buf[4..6].copy_from_slice(&SPIM_POLICY_VERSION.to_le_bytes());
+        buf[6..8].copy_from_slice(&u16::try_from(body_len).unwrap().to_le_bytes());
+        let end = SPIM_POLICY_HDR_SIZE + body_len;
+        let fixed = [0, 1, 1, 1, 0, u8::try_from(BLOCK_REGION_NUM).unwrap(), cmd];
+        buf[SPIM_POLICY_HDR_SIZE..SPIM_POLICY_HDR_SIZE + fixed.len()].copy_from_slice(&fixed);
+        let regions = &mut buf[SPIM_POLICY_HDR_SIZE + fixed.len()..end];
+        for (i, region) in regions.chunks_exact_mut(8).enumerate() {
+            region[0..4].copy_from_slice(&(u32::try_from(i).unwrap() << 16).to_le_bytes());
+            region[4..8].copy_from_slice(&0x1_0000_u32.to_le_bytes());
+        }
+        let crc = crc32(&buf[..end]);
+        buf[end..end + SPIM_POLICY_CRC_SIZE].copy_from_slice(&crc.to_le_bytes());
+        (buf, end + SPIM_POLICY_CRC_SIZE)
+    }
+
+    fn load(store: &PolicyStore, flash: &mut Flash) -> Result<u8, SpiMonitorError> {
+        let mut buf = [0u8; SPIM_POLICY_MAX_SIZE];
+        store.load(flash, &mut buf)?;
+        Ok(buf[SPIM_POLICY_HDR_SIZE + SPIM_POLICY_FIXED_SIZE])
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut flash = Flash::new(128);
+        let store = PolicyStore::new(BASE);
+        assert_eq!(
+            load(&store, &mut flash),
+            Err(SpiMonitorError::PolicyNotFound)
+        );
+
+        let (image, len) = policy(0x03);
+        store.save(&mut flash, &image[..len]).unwrap();
+        let mut buf = [0u8; SPIM_POLICY_MAX_SIZE];
+        assert_eq!(store.load(&mut flash, &mut buf), Ok(len));
+        assert_eq!(buf[..len], image[..len]);
+        assert_eq!(
+            store.load(&mut flash, &mut buf[..len - 1]),
+            Err(SpiMonitorError::PolicyBufferTooSmall(
+                u32::try_from(len).unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_saves_alternate_slots() {
+        let mut flash = Flash::new(128);
+        let store = PolicyStore::new(BASE);
+        for cmd in 1..=5u8 {
+            let (image, len) = policy(cmd);
+            store.save(&mut flash, &image[..len]).unwrap();
+            assert_eq!(load(&store, &mut flash), Ok(cmd));
+        }
+        // Five saves erase each slot on every other save
+        assert_eq!(flash.erase_count(), 5);
+        assert_ne!(flash.byte(BASE + 8), 0xFF);
+        assert_ne!(flash.byte(BASE + 1024 + 8), 0xFF);
+    }
+
+    #[test]
+    fn test_torn_save_keeps_previous() {
+        let mut flash = Flash::new(128);
+        let store = PolicyStore::new(BASE);
+        let (image, len) = policy(0x03);
+        store.save(&mut flash, &image[..len]).unwrap();
+
+        // A cut before the whole image is written leaves the old copy in use
+        let (image, len) = policy(0x0b);
+        flash.power_cut_after(0);
+        assert_eq!(
+            store.save(&mut flash, &image[..len]),
+            Err(SpiMonitorError::PolicyFlash)
+        );
+        flash.clear_faults();
+        assert_eq!(load(&store, &mut flash), Ok(0x03));
+
+        // Whatever a later cut leaves behind, one of the two copies loads
+        for programs in 1..8 {
+            flash.power_cut_after(programs);
+            let saved = store.save(&mut flash, &image[..len]);
+            flash.clear_faults();
+            if saved.is_ok() {
+                break;
+            }
+            assert!(matches!(load(&store, &mut flash), Ok(0x03 | 0x0b)));
+        }
+        assert_eq!(load(&store, &mut flash), Ok(0x0b));
+    }
+
+    #[test]
+    fn test_corrupted_copies_are_rejected() {
+        let mut flash = Flash::new(128);
+        let store = PolicyStore::new(BASE);
+        let (mut image, len) = policy(0x03);
+        image[len / 2] ^= 0xff;
+        assert!(matches!(
+            store.save(&mut flash, &image[..len]),
+            Err(SpiMonitorError::PolicyInvalid(_))
+        ));
+
+        let (image, len) = policy(0x03);
+        store.save(&mut flash, &image[..len]).unwrap();
+        let (image, len) = policy(0x0b);
+        store.save(&mut flash, &image[..len]).unwrap();
+        // A flipped sequence number invalidates the newer copy
+        let seq = BASE + 1024 + 4;
+        flash.set_byte(seq, flash.byte(seq) ^ 0x80);
+        assert_eq!(load(&store, &mut flash), Ok(0x03));
+    }
+
+    #[test]
+    fn test_layout_is_checked() {
+        let mut flash = Flash::new(128);
+        let (image, len) = policy(0x03);
+        for base in [BASE + 1, 3 * 1024] {
+            assert_eq!(
+                PolicyStore::new(base).save(&mut flash, &image[..len]),
+                Err(SpiMonitorError::PolicyFlash)
+            );
+        }
+        let mut small = MockFlash::<4, 512>::new(128);
+        assert_eq!(
+            PolicyStore::new(0).save(&mut small, &image[..len]),
+            Err(SpiMonitorError::PolicyFlash)
+        );
+    }
+}
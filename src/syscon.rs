@@ -106,6 +106,16 @@ impl proposed_traits::system_control::Error for Error {
     }
 }
 
+/// Implemented by drivers whose timing is derived from a SoC clock, so that
+/// [`SysCon::set_frequency_and_notify`] can bring them back in line after
+/// the clock changes.
+pub trait ClockConsumer {
+    /// Clock the driver's timing is derived from
+    fn clock_id(&self) -> ClockId;
+    /// Recomputes clock-derived settings for the new frequency
+    fn reconfigure_for_clock(&mut self, freq_hz: u32) -> Result<(), Error>;
+}
+
 pub struct SysCon<D: DelayNs> {
     delay: D,
     scu: Scu,
@@ -216,6 +226,29 @@ impl<D: DelayNs> SysCon<D> {
         }
     }
 
+    /// Changes the frequency of `clock_id` and reconfigures every consumer
+    /// of that clock for the frequency actually reached.
+    ///
+    /// All matching consumers are notified even if one fails; the first
+    /// failure is returned.
+    pub fn set_frequency_and_notify(
+        &mut self,
+        clock_id: ClockId,
+        frequency_hz: u64,
+        consumers: &mut [&mut dyn ClockConsumer],
+    ) -> Result<(), Error> {
+        self.set_frequency(clock_id, frequency_hz)?;
+        let freq = u32::try_from(self.get_frequency(clock_id)?)
+            .map_err(|_| Error::InvalidClockFrequency)?;
+        let mut result = Ok(());
+        for consumer in consumers.iter_mut() {
+            if consumer.clock_id() == clock_id {
+                result = result.and(consumer.reconfigure_for_clock(freq));
+            }
+        }
+        result
+    }
+
     fn get_frequency(&self, clock_id: ClockId) -> Result<u64, Error> {
         let src: u32;
         let clk_div: u32;
@@ -0,0 +1,384 @@
+// Licensed under the Apache-2.0 license
+
+//! Binary telemetry frames for the host
+//!
+//! A frame is `A5 5A`, a type byte, a payload length byte, the payload and
+//! a CRC-16/CCITT-FALSE over type, length and payload, little-endian like
+//! every multi-byte field. The sync bytes let a host decoder find frames in
+//! a capture that also holds console text. `cargo xtask decode-telemetry`
+//! prints them.
+//!
+//! Any [`embedded_io::Write`], such as the UART, is a [`TelemetrySink`].
+
+use crate::boot_trace::BootTrace;
+use crate::latency::LatencyHistogram;
+use crate::tests::functional::TestSummary;
+
+pub const SYNC: [u8; 2] = [0xa5, 0x5a];
+pub const MAX_PAYLOAD: usize = 255;
+/// Sync, type, length, payload and CRC
+pub const MAX_FRAME: usize = SYNC.len() + 2 + MAX_PAYLOAD + 2;
+
+/// Bytes per boot trace record: milestone and cycles since the previous one
+const BOOT_TRACE_RECORD: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameType {
+    /// Dropped count (u16), then a milestone index (u8) and cycle delta
+    /// (u32) per record
+    BootTrace = 1,
+    /// Count, overflow, min, p50, p99 and max of a [`LatencyHistogram`],
+    /// u32 each
+    Latency = 2,
+    /// Passed and failed checks, suites and failed suites of a
+    /// [`TestSummary`], u32 each
+    TestSummary = 3,
+}
+
+impl FrameType {
+    #[must_use]
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::BootTrace),
+            2 => Some(Self::Latency),
+            3 => Some(Self::TestSummary),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TelemetryError {
+    /// The payload or the output buffer is too small or too large
+    Length,
+    /// The buffer does not start with [`SYNC`]
+    Sync,
+    Crc,
+    UnknownType(u8),
+}
+
+/// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xFFFF
+#[must_use]
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xffffu16;
+    for &b in data {
+        crc ^= u16::from(b) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Frames `payload` into `out`, returning the frame length
+pub fn encode(kind: FrameType, payload: &[u8], out: &mut [u8]) -> Result<usize, TelemetryError> {
+    let len = u8::try_from(payload.len()).map_err(|_| TelemetryError::Length)?;
+    let total = SYNC.len() + 2 + payload.len() + 2;
+    let frame = out.get_mut(..total).ok_or(TelemetryError::Length)?;
+    frame[..2].copy_from_slice(&SYNC);
+    frame[2] = kind as u8;
+    frame[3] = len;
+    frame[4..total - 2].copy_from_slice(payload);
+    let crc = crc16(&frame[2..total - 2]);
+    frame[total - 2..].copy_from_slice(&crc.to_le_bytes());
+    Ok(total)
+}
+
+/// A checked frame
+#[derive(Debug, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub kind: FrameType,
+    pub payload: &'a [u8],
+}
+
+/// Checks the frame at the start of `buf`, returning it and its length
+pub fn decode(buf: &[u8]) -> Result<(Frame<'_>, usize), TelemetryError> {
+    if !buf.starts_with(&SYNC) {
+        return Err(TelemetryError::Sync);
+    }
+    let header = buf.get(2..4).ok_or(TelemetryError::Length)?;
+    let total = SYNC.len() + 2 + usize::from(header[1]) + 2;
+    let frame = buf.get(..total).ok_or(TelemetryError::Length)?;
+    let crc = u16::from_le_bytes([frame[total - 2], frame[total - 1]]);
+    if crc16(&frame[2..total - 2]) != crc {
+        return Err(TelemetryError::Crc);
+    }
+    let kind = FrameType::from_u8(header[0]).ok_or(TelemetryError::UnknownType(header[0]))?;
+    Ok((
+        Frame {
+            kind,
+            payload: &frame[4..total - 2],
+        },
+        total,
+    ))
+}
+
+fn put_u32s(words: &[u32], out: &mut [u8]) -> Result<usize, TelemetryError> {
+    let out = out
+        .get_mut(..words.len() * 4)
+        .ok_or(TelemetryError::Length)?;
+    for (chunk, word) in out.chunks_exact_mut(4).zip(words) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    Ok(out.len())
+}
+
+fn get_u32s<const N: usize>(payload: &[u8]) -> Result<[u32; N], TelemetryError> {
+    if payload.len() != N * 4 {
+        return Err(TelemetryError::Length);
+    }
+    let mut words = [0; N];
+    for (word, chunk) in words.iter_mut().zip(payload.chunks_exact(4)) {
+        *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    Ok(words)
+}
+
+/// Payload of a [`FrameType::BootTrace`] frame. Records that do not fit
+/// are counted as dropped.
+pub fn boot_trace_payload<const N: usize>(
+    trace: &BootTrace<N>,
+    out: &mut [u8],
+) -> Result<usize, TelemetryError> {
+    let room = (out.len().min(MAX_PAYLOAD)).saturating_sub(2) / BOOT_TRACE_RECORD;
+    if out.len() < 2 {
+        return Err(TelemetryError::Length);
+    }
+    let kept = trace.len().min(room);
+    let dropped = trace.dropped() + trace.len() - kept;
+    out[..2].copy_from_slice(&u16::try_from(dropped).unwrap_or(u16::MAX).to_le_bytes());
+    let records = out[2..].chunks_exact_mut(BOOT_TRACE_RECORD);
+    for (record, (milestone, delta, _)) in records.zip(trace.deltas().take(kept)) {
+        record[0] = milestone as u8;
+        record[1..].copy_from_slice(&delta.to_le_bytes());
+    }
+    Ok(2 + kept * BOOT_TRACE_RECORD)
+}
+
+/// Decoded boot trace payload: the dropped count and the records
+pub fn parse_boot_trace(
+    payload: &[u8],
+) -> Result<(u16, impl Iterator<Item = (u8, u32)> + '_), TelemetryError> {
+    if payload.len() < 2 || (payload.len() - 2) % BOOT_TRACE_RECORD != 0 {
+        return Err(TelemetryError::Length);
+    }
+    let dropped = u16::from_le_bytes([payload[0], payload[1]]);
+    let records = payload[2..]
+        .chunks_exact(BOOT_TRACE_RECORD)
+        .map(|r| (r[0], u32::from_le_bytes([r[1], r[2], r[3], r[4]])));
+    Ok((dropped, records))
+}
+
+/// Summary statistics of a [`LatencyHistogram`], as carried by a
+/// [`FrameType::Latency`] frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyReport {
+    pub count: u32,
+    pub overflow: u32,
+    pub min_ns: u32,
+    pub p50_ns: u32,
+    pub p99_ns: u32,
+    pub max_ns: u32,
+}
+
+impl LatencyReport {
+    #[must_use]
+    pub fn of(histogram: &LatencyHistogram) -> Self {
+        let max_ns = histogram.max().unwrap_or(0);
+        Self {
+            count: histogram.count(),
+            overflow: histogram.overflow(),
+            min_ns: histogram.min().unwrap_or(0),
+            p50_ns: histogram.percentile(50).unwrap_or(max_ns),
+            p99_ns: histogram.percentile(99).unwrap_or(max_ns),
+            max_ns,
+        }
+    }
+
+    pub fn payload(&self, out: &mut [u8]) -> Result<usize, TelemetryError> {
+        put_u32s(
+            &[
+                self.count,
+                self.overflow,
+                self.min_ns,
+                self.p50_ns,
+                self.p99_ns,
+                self.max_ns,
+            ],
+            out,
+        )
+    }
+
+    pub fn parse(payload: &[u8]) -> Result<Self, TelemetryError> {
+        let [count, overflow, min_ns, p50_ns, p99_ns, max_ns] = get_u32s(payload)?;
+        Ok(Self {
+            count,
+            overflow,
+            min_ns,
+            p50_ns,
+            p99_ns,
+            max_ns,
+        })
+    }
+}
+
+/// Payload of a [`FrameType::TestSummary`] frame
+pub fn summary_payload(summary: &TestSummary, out: &mut [u8]) -> Result<usize, TelemetryError> {
+    put_u32s(
+        &[
+            summary.total.passed,
+            summary.total.failed,
+            summary.suites,
+            summary.failed_suites,
+        ],
+        out,
+    )
+}
+
+pub fn parse_summary(payload: &[u8]) -> Result<TestSummary, TelemetryError> {
+    let [passed, failed, suites, failed_suites] = get_u32s(payload)?;
+    let mut summary = TestSummary::new();
+    summary.total.passed = passed;
+    summary.total.failed = failed;
+    summary.suites = suites;
+    summary.failed_suites = failed_suites;
+    Ok(summary)
+}
+
+/// Error of [`TelemetrySink::send`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendError<E> {
+    Frame(TelemetryError),
+    Sink(E),
+}
+
+/// A transport for telemetry frames
+pub trait TelemetrySink {
+    type Error;
+
+    /// Sends one complete frame
+    fn send_frame(&mut self, frame: &[u8]) -> Result<(), Self::Error>;
+
+    /// Frames `payload` and sends it
+    fn send(&mut self, kind: FrameType, payload: &[u8]) -> Result<(), SendError<Self::Error>> {
+        let mut frame = [0u8; MAX_FRAME];
+        let len = encode(kind, payload, &mut frame).map_err(SendError::Frame)?;
+        self.send_frame(&frame[..len]).map_err(SendError::Sink)
+    }
+}
+
+impl<W: embedded_io::Write> TelemetrySink for W {
+    type Error = W::Error;
+
+    fn send_frame(&mut self, frame: &[u8]) -> Result<(), W::Error> {
+        self.write_all(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boot_trace::Milestone;
+
+    #[test]
+    fn test_crc16() {
+        // the CRC-16/CCITT-FALSE check value
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        let mut buf = [0u8; MAX_FRAME];
+        let len = encode(FrameType::Latency, &[1, 2, 3], &mut buf).unwrap();
+        assert_eq!(len, 9);
+        let (frame, used) = decode(&buf).unwrap();
+        assert_eq!(used, len);
+        assert_eq!(frame.kind, FrameType::Latency);
+        assert_eq!(frame.payload, &[1, 2, 3]);
+
+        buf[5] ^= 1;
+        assert_eq!(decode(&buf).unwrap_err(), TelemetryError::Crc);
+        assert_eq!(decode(&buf[1..]).unwrap_err(), TelemetryError::Sync);
+        assert_eq!(decode(&buf[..8]).unwrap_err(), TelemetryError::Length);
+        assert_eq!(
+            encode(FrameType::Latency, &[0; 256], &mut buf),
+            Err(TelemetryError::Length)
+        );
+    }
+
+    #[test]
+    fn test_boot_trace_round_trip() {
+        let mut trace = BootTrace::<4>::new();
+        trace.record(Milestone::Start, 100);
+        trace.record(Milestone::UartUp, 350);
+        trace.record(Milestone::Done, 1350);
+
+        let mut payload = [0u8; MAX_PAYLOAD];
+        let len = boot_trace_payload(&trace, &mut payload).unwrap();
+        let mut frame = [0u8; MAX_FRAME];
+        let len = encode(FrameType::BootTrace, &payload[..len], &mut frame).unwrap();
+        let (frame, _) = decode(&frame[..len]).unwrap();
+        assert_eq!(frame.kind, FrameType::BootTrace);
+
+        let (dropped, records) = parse_boot_trace(frame.payload).unwrap();
+        assert_eq!(dropped, 0);
+        let expected = [
+            (Milestone::Start as u8, 0),
+            (Milestone::UartUp as u8, 250),
+            (Milestone::Done as u8, 1000),
+        ];
+        assert!(records.eq(expected));
+
+        // a short buffer keeps the first records and counts the rest
+        let len = boot_trace_payload(&trace, &mut payload[..12]).unwrap();
+        let (dropped, records) = parse_boot_trace(&payload[..len]).unwrap();
+        assert_eq!((dropped, records.count()), (1, 2));
+    }
+
+    #[test]
+    fn test_latency_round_trip() {
+        let mut histogram = LatencyHistogram::new(100);
+        for ns in [120, 180, 450, 90_000] {
+            histogram.record(ns);
+        }
+        let report = LatencyReport::of(&histogram);
+        assert_eq!(report.count, 4);
+        assert_eq!(report.max_ns, 90_000);
+
+        let mut payload = [0u8; 24];
+        let len = report.payload(&mut payload).unwrap();
+        assert_eq!(LatencyReport::parse(&payload[..len]), Ok(report));
+        assert_eq!(
+            LatencyReport::parse(&payload[..20]),
+            Err(TelemetryError::Length)
+        );
+    }
+
+    #[test]
+    fn test_summary_round_trip() {
+        let mut summary = TestSummary::new();
+        summary.add(crate::tests::functional::TestResult::of(true));
+        summary.add(crate::tests::functional::TestResult::of(false));
+
+        let mut payload = [0u8; 16];
+        let len = summary_payload(&summary, &mut payload).unwrap();
+        assert_eq!(parse_summary(&payload[..len]), Ok(summary));
+    }
+
+    #[test]
+    fn test_sink() {
+        let mut out = [0u8; 16];
+        let mut sink = &mut out[..];
+        sink.send(FrameType::TestSummary, &[7]).unwrap();
+        let (frame, len) = decode(&out).unwrap();
+        assert_eq!(
+            (frame.kind, frame.payload, len),
+            (FrameType::TestSummary, &[7][..], 7)
+        );
+    }
+}
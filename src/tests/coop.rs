@@ -0,0 +1,260 @@
+// Licensed under the Apache-2.0 license
+
+//! Cooperative scheduling of functional test suites
+//!
+//! Long suites hold the CPU for seconds, and whatever the main loop polls
+//! meanwhile, such as an I2C target endpoint or the console, waits just as
+//! long. A [`Resumable`] suite instead does a bounded piece of work per
+//! [`Resumable::step`], and a [`CoopScheduler`] runs the steps of its
+//! suites round robin with a pass over its services after every step. No
+//! allocation and no RTOS: a service waits at most one step.
+//!
+//! Each step gets a [`Deadline`] for its time budget and should return soon
+//! after it expires. The scheduler cannot preempt a step; it counts the
+//! steps that overran instead.
+
+use super::functional::{TestResult, TestSummary};
+use crate::common::{Deadline, TickClock};
+use core::time::Duration;
+
+/// What a suite reports after a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepResult {
+    /// More steps to come
+    Pending,
+    /// The suite has finished with this result
+    Done(TestResult),
+}
+
+/// A test suite that runs in steps
+pub trait Resumable {
+    fn name(&self) -> &'static str;
+
+    /// Advances the suite, returning soon after `budget` expires
+    fn step(&mut self, budget: &Deadline) -> StepResult;
+}
+
+/// A suite made of its state and a step function over it
+pub struct StepFn<S> {
+    pub name: &'static str,
+    pub state: S,
+    pub step: fn(&mut S, &Deadline) -> StepResult,
+}
+
+impl<S> Resumable for StepFn<S> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn step(&mut self, budget: &Deadline) -> StepResult {
+        (self.step)(&mut self.state, budget)
+    }
+}
+
+/// Step timing of a scheduler run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoopStats {
+    pub steps: u32,
+    /// Steps that ran past their budget
+    pub overruns: u32,
+    /// Longest step, in clock ticks
+    pub worst_step: u32,
+}
+
+/// Up to `N` suites sharing the CPU with the services of the main loop
+pub struct CoopScheduler<'s, const N: usize> {
+    clock: TickClock,
+    budget: Duration,
+    suites: [Option<&'s mut dyn Resumable>; N],
+    next: usize,
+    stats: CoopStats,
+}
+
+impl<'s, const N: usize> CoopScheduler<'s, N> {
+    /// Scheduler giving each step `budget` of `clock` time
+    #[must_use]
+    pub fn new(clock: TickClock, budget: Duration) -> Self {
+        Self {
+            clock,
+            budget,
+            suites: [const { None }; N],
+            next: 0,
+            stats: CoopStats::default(),
+        }
+    }
+
+    /// Adds a suite, or gives it back when all slots are taken
+    pub fn add(&mut self, suite: &'s mut dyn Resumable) -> Result<(), &'s mut dyn Resumable> {
+        match self.suites.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(suite);
+                Ok(())
+            }
+            None => Err(suite),
+        }
+    }
+
+    /// Whether every suite has finished
+    #[must_use]
+    pub fn is_idle(&self) -> bool {
+        self.suites.iter().all(Option::is_none)
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> CoopStats {
+        self.stats
+    }
+
+    /// Runs one step of the next unfinished suite, then every service.
+    /// A finished suite's result goes to `summary`. Returns `false` once
+    /// no suite is left.
+    pub fn poll(&mut self, summary: &mut TestSummary, services: &mut [&mut dyn FnMut()]) -> bool {
+        let Some(index) = (0..N)
+            .map(|i| (self.next + i) % N)
+            .find(|&i| self.suites[i].is_some())
+        else {
+            return false;
+        };
+        self.next = (index + 1) % N;
+
+        if let Some(suite) = self.suites[index].as_mut() {
+            let budget = Deadline::new(self.clock, self.budget);
+            let start = (self.clock.now)();
+            let result = suite.step(&budget);
+            let took = (self.clock.now)().wrapping_sub(start);
+
+            self.stats.steps += 1;
+            self.stats.worst_step = self.stats.worst_step.max(took);
+            if took > self.budget_ticks() {
+                self.stats.overruns += 1;
+            }
+            if let StepResult::Done(result) = result {
+                summary.add(result);
+                self.suites[index] = None;
+            }
+        }
+
+        for service in services.iter_mut() {
+            service();
+        }
+        true
+    }
+
+    /// Polls until every suite has finished
+    pub fn run(&mut self, summary: &mut TestSummary, services: &mut [&mut dyn FnMut()]) {
+        while self.poll(summary, services) {}
+    }
+
+    fn budget_ticks(&self) -> u32 {
+        let ticks = self
+            .budget
+            .as_micros()
+            .saturating_mul(u128::from(self.clock.ticks_per_us));
+        u32::try_from(ticks).unwrap_or(u32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CoopScheduler, StepFn, StepResult};
+    use crate::common::{Deadline, TickClock};
+    use crate::tests::functional::{TestResult, TestSummary};
+    use core::time::Duration;
+    use std::cell::{Cell, RefCell};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    thread_local! {
+        static NOW: Cell<u32> = const { Cell::new(0) };
+    }
+
+    fn now() -> u32 {
+        NOW.with(Cell::get)
+    }
+
+    fn advance(ticks: u32) {
+        NOW.with(|n| n.set(n.get().wrapping_add(ticks)));
+    }
+
+    const CLOCK: TickClock = TickClock {
+        now,
+        ticks_per_us: 1,
+    };
+
+    /// Counts down `left` pieces of work of `cost` ticks each, as many per
+    /// step as the budget allows
+    struct Countdown {
+        left: u32,
+        cost: u32,
+    }
+
+    fn count_down(state: &mut Countdown, budget: &Deadline) -> StepResult {
+        while state.left > 0 {
+            advance(state.cost);
+            state.left -= 1;
+            if budget.is_expired() {
+                return StepResult::Pending;
+            }
+        }
+        StepResult::Done(TestResult::of(true))
+    }
+
+    #[test]
+    fn test_round_robin_with_services() {
+        let mut a = StepFn {
+            name: "a",
+            state: Countdown { left: 4, cost: 400 },
+            step: count_down,
+        };
+        let mut b = StepFn {
+            name: "b",
+            state: Countdown { left: 1, cost: 10 },
+            step: count_down,
+        };
+        let order = RefCell::new(Vec::new());
+        let mut sched = CoopScheduler::<3>::new(CLOCK, Duration::from_millis(1));
+        assert!(sched.add(&mut a).is_ok());
+        assert!(sched.add(&mut b).is_ok());
+
+        let mut summary = TestSummary::new();
+        let mut service = || order.borrow_mut().push(now());
+        while sched.poll(&mut summary, &mut [&mut service]) {}
+
+        // a: 3 pieces in its first step, 1 in its second; b finishes between
+        assert_eq!(sched.stats().steps, 3);
+        assert_eq!(sched.stats().overruns, 1);
+        assert_eq!(sched.stats().worst_step, 1200);
+        assert_eq!(*order.borrow(), [1200, 1210, 1610]);
+        assert_eq!(summary.suites, 2);
+        assert_eq!(summary.status(), 0);
+        assert!(sched.is_idle());
+    }
+
+    #[test]
+    fn test_add_when_full() {
+        static STEPS: AtomicU32 = AtomicU32::new(0);
+        fn once(_: &mut (), _: &Deadline) -> StepResult {
+            STEPS.fetch_add(1, Ordering::Relaxed);
+            StepResult::Done(TestResult::of(false))
+        }
+        let mut a = StepFn {
+            name: "a",
+            state: (),
+            step: once,
+        };
+        let mut b = StepFn {
+            name: "b",
+            state: (),
+            step: once,
+        };
+        let mut sched = CoopScheduler::<1>::new(CLOCK, Duration::from_millis(1));
+        assert!(sched.add(&mut a).is_ok());
+        let refused = sched.add(&mut b).err().map(|s| s.name());
+        assert_eq!(refused, Some("b"));
+
+        let mut summary = TestSummary::new();
+        sched.run(&mut summary, &mut []);
+        assert_eq!(STEPS.load(Ordering::Relaxed), 1);
+        assert_eq!(summary.failed_suites, 1);
+        assert!(!sched.poll(&mut summary, &mut []));
+    }
+}
@@ -0,0 +1,19 @@
+// Licensed under the Apache-2.0 license
+
+use super::TestResult;
+use crate::board::Board;
+use crate::uart::UartController;
+use embedded_io::Write;
+
+/// The caller already holds the board, so a second claim must be refused,
+/// through the board and directly from the PAC alike. The drivers built
+/// from it are exercised by the rest of the suite.
+pub fn run_board_tests(uart: &mut UartController) -> TestResult {
+    let ok = Board::take().is_none() && ast1060_pac::Peripherals::take().is_none();
+    if ok {
+        writeln!(uart, "\r\nBoard take once: PASSED").unwrap();
+    } else {
+        writeln!(uart, "\r\nBoard take once: FAILED").unwrap();
+    }
+    TestResult::of(ok)
+}
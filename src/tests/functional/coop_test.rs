@@ -0,0 +1,148 @@
+// Licensed under the Apache-2.0 license
+
+use super::{TestResult, TestSummary};
+use crate::common::{crc32, Deadline, TickClock};
+use crate::hace_controller::HaceController;
+use crate::hash::Sha256;
+use crate::tests::coop::{CoopScheduler, StepFn, StepResult};
+use crate::uart::UartController;
+use core::time::Duration;
+use embedded_io::Write;
+use proposed_traits::digest::{DigestInit, DigestOp};
+
+/// Time each suite step may take
+const STEP_BUDGET: Duration = Duration::from_millis(1);
+/// Longest wait between two service turns that still passes; a step may
+/// finish its current piece of work after the budget expires
+const MAX_GAP: Duration = Duration::from_millis(3);
+const CHUNK: usize = 4096;
+/// 1 MiB through SHA-256
+const HASH_CHUNKS: u32 = 256;
+const CRC_ROUNDS: u32 = 64;
+
+struct HashBench<'a> {
+    hace: &'a mut HaceController,
+    input: [u8; CHUNK],
+    reference: Option<[u8; 32]>,
+    left: u32,
+    result: TestResult,
+}
+
+/// Hashes chunks until the budget runs out; every digest must match the
+/// first, as the input does not change
+fn hash_step(bench: &mut HashBench<'_>, budget: &Deadline) -> StepResult {
+    while bench.left > 0 {
+        let mut ctx = bench.hace.init(Sha256).unwrap();
+        ctx.update(&bench.input).unwrap();
+        let digest = ctx.finalize().unwrap();
+        let reference = *bench.reference.get_or_insert(digest);
+        bench.result.check(digest == reference);
+        bench.left -= 1;
+        if budget.is_expired() {
+            return StepResult::Pending;
+        }
+    }
+    StepResult::Done(bench.result)
+}
+
+struct CrcBench {
+    input: [u8; CHUNK],
+    reference: u32,
+    left: u32,
+    result: TestResult,
+}
+
+fn crc_step(bench: &mut CrcBench, budget: &Deadline) -> StepResult {
+    while bench.left > 0 {
+        bench.result.check(crc32(&bench.input) == bench.reference);
+        bench.left -= 1;
+        if budget.is_expired() {
+            return StepResult::Pending;
+        }
+    }
+    StepResult::Done(bench.result)
+}
+
+/// Runs a SHA-256 benchmark and a CRC benchmark as stepped suites and
+/// checks that the main loop services still get a turn every [`MAX_GAP`].
+///
+/// The service is a probe standing in for a polled I2C target endpoint:
+/// the longest gap between its calls is the response latency such an
+/// endpoint would see while the suites run.
+pub fn run_coop_tests(
+    uart: &mut UartController,
+    hace: &mut HaceController,
+    cpu_hz: u32,
+) -> TestResult {
+    writeln!(uart, "\r\nRunning cooperative scheduler test").unwrap();
+    let clock = TickClock::dwt(cpu_hz);
+    let mut result = TestResult::new();
+
+    let mut input = [0u8; CHUNK];
+    for (b, v) in input.iter_mut().zip((0..=250u8).cycle()) {
+        *b = v;
+    }
+    let mut hash = StepFn {
+        name: "sha256 bench",
+        state: HashBench {
+            hace,
+            input,
+            reference: None,
+            left: HASH_CHUNKS,
+            result: TestResult::new(),
+        },
+        step: hash_step,
+    };
+    let mut crc = StepFn {
+        name: "crc32 bench",
+        state: CrcBench {
+            input,
+            reference: crc32(&input),
+            left: CRC_ROUNDS,
+            result: TestResult::new(),
+        },
+        step: crc_step,
+    };
+
+    let mut sched = CoopScheduler::<2>::new(clock, STEP_BUDGET);
+    result.check(sched.add(&mut hash).is_ok());
+    result.check(sched.add(&mut crc).is_ok());
+
+    let mut last = (clock.now)();
+    let mut worst_gap = 0u32;
+    let mut turns = 0u32;
+    let mut probe = || {
+        let now = (clock.now)();
+        worst_gap = worst_gap.max(now.wrapping_sub(last));
+        last = now;
+        turns += 1;
+    };
+    let mut suites = TestSummary::new();
+    sched.run(&mut suites, &mut [&mut probe]);
+
+    let stats = sched.stats();
+    let worst_us = worst_gap / clock.ticks_per_us;
+    writeln!(
+        uart,
+        "\rcoop: {} steps, {} overruns, worst step {} us, worst service gap {} us over {} turns",
+        stats.steps,
+        stats.overruns,
+        stats.worst_step / clock.ticks_per_us,
+        worst_us,
+        turns
+    )
+    .unwrap();
+
+    result.check(suites.suites == 2 && suites.status() == 0);
+    result.check(turns == stats.steps);
+    // the suites must actually have been split up
+    result.check(stats.steps > 2);
+    result.check(u128::from(worst_us) < MAX_GAP.as_micros());
+
+    if result.is_ok() {
+        writeln!(uart, "\rcoop: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\rcoop: Test failed!").unwrap();
+    }
+    result
+}
@@ -1,7 +1,10 @@
 // Licensed under the Apache-2.0 license
 
-use crate::ecdsa::{PublicKey, Scalar48, Secp384r1Curve, Signature};
+use super::TestResult;
+use crate::common::VerifyDecision;
+use crate::ecdsa::{AspeedEcdsa, AspeedEcdsaError, PublicKey, Scalar48, Secp384r1Curve, Signature};
 use crate::uart::UartController;
+use embedded_hal::delay::DelayNs;
 use embedded_io::Write;
 use proposed_traits::digest::DigestAlgorithm;
 use proposed_traits::ecdsa::{Curve, EcdsaVerify};
@@ -87,8 +90,9 @@ pub const SECP384R1_TESTVEC: &[EcdsaTestVec] = &[
 pub fn run_ecdsa_tests(
     uart: &mut UartController,
     verifier: &mut impl EcdsaVerify<Secp384r1Curve, PublicKey = PublicKey, Signature = Signature>,
-) {
+) -> TestResult {
     writeln!(uart, "\r\nRunning ECDSA test").unwrap();
+    let mut results = TestResult::new();
     for (i, vec) in SECP384R1_TESTVEC.iter().enumerate() {
         let pubkey = PublicKey {
             qx: Scalar48(vec.qx),
@@ -106,10 +110,106 @@ pub fn run_ecdsa_tests(
 
         writeln!(uart, "\r\nTest case {i}... ").unwrap();
 
+        results.check(result.is_ok() == vec.result);
         let _ = match (result.is_ok(), vec.result) {
             (true, true) => writeln!(uart, "\rresult expected (pass), Pass"),
             (false, false) => writeln!(uart, "\rresult expected (fail), Pass"),
             _ => writeln!(uart, "\rresult unexpected (got {result:?}), Failed"),
         };
     }
+    results
+}
+
+/// Checks the hardened decision values and that `verify` agrees with them
+pub fn run_ecdsa_decision_tests<D: DelayNs>(
+    uart: &mut UartController,
+    ecdsa: &mut AspeedEcdsa<D>,
+) -> TestResult {
+    writeln!(uart, "\r\nRunning ECDSA decision test").unwrap();
+    let mut pass = VerifyDecision::VALID.bits() == !VerifyDecision::INVALID.bits()
+        && VerifyDecision::confirm(VerifyDecision::VALID, VerifyDecision::INVALID).is_none()
+        && VerifyDecision::compare(b"abc", b"abc") == VerifyDecision::VALID
+        && VerifyDecision::compare_rev(b"abc", b"abd") == VerifyDecision::INVALID;
+
+    for vec in SECP384R1_TESTVEC {
+        let pubkey = PublicKey {
+            qx: Scalar48(vec.qx),
+            qy: Scalar48(vec.qy),
+        };
+        let sig = Signature {
+            r: Scalar48(vec.r),
+            s: Scalar48(vec.s),
+        };
+        let decision = ecdsa.verify_decision(&pubkey, &vec.m, &sig);
+        if !matches!(decision, Ok(d) if d == VerifyDecision::from_bool(vec.result)) {
+            pass = false;
+        }
+
+        let mut digest =
+            <<Secp384r1Curve as Curve>::DigestType as DigestAlgorithm>::DigestOutput::default();
+        digest.as_mut().copy_from_slice(&vec.m);
+        if ecdsa.verify(&pubkey, digest, &sig).is_ok() != vec.result {
+            pass = false;
+        }
+    }
+
+    if pass {
+        writeln!(uart, "\rECDSA decision: PASSED").unwrap();
+    } else {
+        writeln!(uart, "\rECDSA decision: FAILED").unwrap();
+    }
+    TestResult::of(pass)
+}
+
+fn vec_key_sig(vec: &EcdsaTestVec) -> (PublicKey, Signature) {
+    (
+        PublicKey {
+            qx: Scalar48(vec.qx),
+            qy: Scalar48(vec.qy),
+        },
+        Signature {
+            r: Scalar48(vec.r),
+            s: Scalar48(vec.s),
+        },
+    )
+}
+
+/// Batch verification of a valid chain, and of chains with a bad link
+pub fn run_ecdsa_batch_tests<D: DelayNs>(
+    uart: &mut UartController,
+    ecdsa: &mut AspeedEcdsa<D>,
+) -> TestResult {
+    writeln!(uart, "\r\nRunning ECDSA batch test").unwrap();
+    let v = SECP384R1_TESTVEC;
+    let (k0, s0) = vec_key_sig(&v[0]);
+    let (k1, s1) = vec_key_sig(&v[1]);
+    let (k2, s2) = vec_key_sig(&v[2]);
+    let (k4, s4) = vec_key_sig(&v[4]);
+
+    let valid = ecdsa.verify_batch(&[
+        (&k0, &v[0].m[..], &s0),
+        (&k2, &v[2].m[..], &s2),
+        (&k4, &v[4].m[..], &s4),
+    ]);
+    let bad_sig = ecdsa.verify_batch(&[
+        (&k0, &v[0].m[..], &s0),
+        (&k2, &v[2].m[..], &s2),
+        (&k1, &v[1].m[..], &s1),
+        (&k4, &v[4].m[..], &s4),
+    ]);
+    let bad_digest = ecdsa.verify_batch(&[(&k0, &v[0].m[..], &s0), (&k2, &v[2].m[..32], &s2)]);
+
+    let ok = valid.is_ok()
+        && matches!(bad_sig, Err((2, AspeedEcdsaError::InvalidSignature)))
+        && matches!(bad_digest, Err((1, AspeedEcdsaError::BadInput)));
+    if ok {
+        writeln!(uart, "\rECDSA batch: PASSED").unwrap();
+    } else {
+        writeln!(
+            uart,
+            "\rECDSA batch: FAILED {valid:?} {bad_sig:?} {bad_digest:?}"
+        )
+        .unwrap();
+    }
+    TestResult::of(ok)
 }
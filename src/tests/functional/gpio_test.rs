@@ -1,24 +1,29 @@
 // Licensed under the Apache-2.0 license
 
-use ast1060_pac::Peripherals;
 use embedded_hal::digital::{InputPin, OutputPin, StatefulOutputPin};
 use embedded_io::Write;
 
+use super::TestResult;
 use crate::common::DummyDelay;
-use crate::gpio::{gpioa, gpioh, gpiol, gpiom, Floating, GpioExt};
-use crate::pinctrl;
+use crate::doorbell::{Doorbell, DoorbellPolarity, RingResult};
+use crate::gpio::{gpioa, gpioh, gpiol, gpiom, Floating, GpioBank, GpioExt, Output, PushPull};
+use crate::pin_state::{GpioPins, PinStateSnapshot, SavedReg};
+use crate::pinctrl::{self, PinctrlPin};
 use crate::uart::UartController;
 use embedded_hal::delay::DelayNs;
 
-pub fn test_gpioa(uart: &mut UartController<'_>) {
-    let peripherals = unsafe { Peripherals::steal() };
-    let gpio = peripherals.gpio;
-
+/// The input levels depend on the board and are only reported. Hands back
+/// PA4 as a push-pull output for [`test_gpio_doorbell`].
+pub fn test_gpioa(
+    uart: &mut UartController<'_>,
+    gpioa: gpioa::GPIOA,
+) -> (TestResult, gpioa::PA4<Output<PushPull>>) {
+    let mut result = TestResult::new();
     pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_GPIOA0);
     pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_GPIOA1);
     pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_GPIOA3);
     pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_GPIOA4);
-    let gpioa = gpioa::GPIOA::new(gpio).split();
+    let gpioa = gpioa.split();
     uart.write_all(b"\r\n####### GPIO test #######\r\n")
         .unwrap();
     // input test
@@ -33,36 +38,95 @@ pub fn test_gpioa(uart: &mut UartController<'_>) {
     // output test
     let mut pa3 = gpioa.pa3.into_open_drain_output::<Floating>();
     pa3.set_low().unwrap();
-    if pa3.is_set_low().unwrap() {
+    if result.check(pa3.is_set_low().unwrap()) {
         uart.write_all(b"\rGPIOA pin3 set low successfully\r\n")
             .unwrap();
     }
     pa3.set_high().unwrap();
-    if pa3.is_set_high().unwrap() {
+    if result.check(pa3.is_set_high().unwrap()) {
         uart.write_all(b"\rGPIOA pin3 set high successfully\r\n")
             .unwrap();
     }
 
     let mut pa4 = gpioa.pa4.into_push_pull_output();
     pa4.set_low().unwrap();
-    if pa4.is_set_low().unwrap() {
+    if result.check(pa4.is_set_low().unwrap()) {
         uart.write_all(b"\rGPIOA pin4 set low successfully\r\n")
             .unwrap();
     }
     pa4.set_high().unwrap();
-    if pa4.is_set_high().unwrap() {
+    if result.check(pa4.is_set_high().unwrap()) {
         uart.write_all(b"\rGPIOA pin4 set high successfully\r\n")
             .unwrap();
     }
+    (result, pa4)
+}
+
+/// Saves GPIOA0-A4 and their muxes, scrambles the values, directions and
+/// muxes, and checks that a restore brings every register back. The
+/// command source is left alone, as handing bank A to another master would
+/// lock this core out of the restore.
+pub fn test_pin_state(uart: &mut UartController<'_>) -> TestResult {
+    const MUXES: &[&[PinctrlPin]] = &[
+        pinctrl::PINCTRL_GPIOA0,
+        pinctrl::PINCTRL_GPIOA1,
+        pinctrl::PINCTRL_GPIOA3,
+        pinctrl::PINCTRL_GPIOA4,
+    ];
+    const PINS: u8 = 0x1f;
+    let banks = [GpioPins {
+        bank: GpioBank::A,
+        mask: PINS,
+    }];
+
+    let mut result = TestResult::new();
+    let mut saved = [SavedReg::default(); 8];
+    let Ok(snapshot) = PinStateSnapshot::capture(&banks, MUXES, &mut saved) else {
+        result.check(false);
+        return result;
+    };
+
+    let gpio = unsafe { &*ast1060_pac::Gpio::ptr() };
+    let value = gpio.gpio0c0().read().bits();
+    gpio.gpio000()
+        .write(|w| unsafe { w.bits(value ^ u32::from(PINS)) });
+    gpio.gpio004()
+        .modify(|r, w| unsafe { w.bits(r.bits() ^ u32::from(PINS)) });
+    for pin in MUXES.iter().flat_map(|group| group.iter()) {
+        pinctrl::Pinctrl::apply_pinctrl_group(&[PinctrlPin {
+            offset: pin.offset,
+            bit: pin.bit,
+            clear: !pin.clear,
+        }]);
+    }
+
+    let mut again = [SavedReg::default(); 8];
+    let scrambled = PinStateSnapshot::capture(&banks, MUXES, &mut again);
+    result.check(scrambled.as_ref() != Ok(&snapshot));
+    let restored = snapshot.restore();
+    result.check(restored.is_ok());
+    let mut again = [SavedReg::default(); 8];
+    let recaptured = PinStateSnapshot::capture(&banks, MUXES, &mut again);
+    result.check(recaptured.as_ref() == Ok(&snapshot));
+
+    if result.is_ok() {
+        uart.write_all(b"\r\nPin state snapshot: Test passed!\r\n")
+            .unwrap();
+    } else {
+        writeln!(
+            uart,
+            "\r\nPin state snapshot: Test failed! restore {restored:?}"
+        )
+        .unwrap();
+    }
+    result
 }
 
-pub fn test_gpio_flash_power(uart: &mut UartController<'_>) {
+pub fn test_gpio_flash_power(uart: &mut UartController<'_>, gpiol: gpiol::GPIOL) {
     let mut delay = DummyDelay {};
     if true {
         /* Older demo board required this */
-        let peripherals = unsafe { Peripherals::steal() };
-        let gpio = peripherals.gpio;
-        let gpiol = gpiol::GPIOL::new(gpio).split();
+        let gpiol = gpiol.split();
         uart.write_all(b"\r\n####### GPIO flash power #######\r\n")
             .unwrap();
 
@@ -78,46 +142,91 @@ pub fn test_gpio_flash_power(uart: &mut UartController<'_>) {
     }
 }
 #[allow(dead_code)]
-pub fn test_gpio_bmc_reset(uart: &mut UartController<'_>) {
+pub fn test_gpio_bmc_reset(
+    uart: &mut UartController<'_>,
+    gpiom: gpiom::GPIOM,
+    gpioh: gpioh::GPIOH,
+) -> TestResult {
+    let mut result = TestResult::new();
     {
-        let peripherals = unsafe { Peripherals::steal() };
-        let gpio = peripherals.gpio;
-        let gpiom = gpiom::GPIOM::new(gpio).split();
+        let gpiom = gpiom.split();
         uart.write_all(b"\r\n####### GPIO BMC SRST #######\r\n")
             .unwrap();
 
         pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_GPIOM5);
         let mut pm5 = gpiom.pm5.into_push_pull_output();
         pm5.set_low().unwrap();
-        if pm5.is_set_low().unwrap() {
+        if result.check(pm5.is_set_low().unwrap()) {
             uart.write_all(b"\r\nGPIOM pin5 set low successfully\r\n")
                 .unwrap();
         }
         pm5.set_high().unwrap();
-        if pm5.is_set_high().unwrap() {
+        if result.check(pm5.is_set_high().unwrap()) {
             uart.write_all(b"\r\nGPIOM pin5 set high successfully\r\n")
                 .unwrap();
         }
     }
 
     {
-        let peripherals = unsafe { Peripherals::steal() };
-        let gpio = peripherals.gpio;
-        let gpioh = gpioh::GPIOH::new(gpio).split();
+        let gpioh = gpioh.split();
         uart.write_all(b"\r\n####### GPIO BMC EXTRST #######\r\n")
             .unwrap();
 
         pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_GPIOH2);
         let mut ph2 = gpioh.ph2.into_push_pull_output();
         ph2.set_low().unwrap();
-        if ph2.is_set_low().unwrap() {
+        if result.check(ph2.is_set_low().unwrap()) {
             uart.write_all(b"\r\nGPIOH pin2 set low successfully\r\n")
                 .unwrap();
         }
         ph2.set_high().unwrap();
-        if ph2.is_set_high().unwrap() {
+        if result.check(ph2.is_set_high().unwrap()) {
             uart.write_all(b"\r\nGPIOH pin2 set high successfully\r\n")
                 .unwrap();
         }
     }
+    result
+}
+
+/// Level of PA4 as read back through the data read register (GPIO0C0),
+/// i.e. what the host sees on the line
+fn pa4_level() -> bool {
+    let gpio = unsafe { &*ast1060_pac::Gpio::ptr() };
+    gpio.gpio0c0().read().bits() & (1 << 4) != 0
+}
+
+/// Drives an active-low doorbell on PA4 and checks the line level after
+/// every step
+pub fn test_gpio_doorbell(
+    uart: &mut UartController<'_>,
+    pa4: gpioa::PA4<Output<PushPull>>,
+) -> TestResult {
+    uart.write_all(b"\r\n####### GPIO doorbell #######\r\n")
+        .unwrap();
+
+    let mut doorbell = Doorbell::new(pa4, DoorbellPolarity::ActiveLow, 100).unwrap();
+    let idle = pa4_level();
+
+    // A burst of publishes must assert the line exactly once
+    let mut now = 0;
+    for _ in 0..3 {
+        doorbell.ring(now).unwrap();
+        now += 1;
+    }
+    let asserted = !pa4_level();
+    // Host reads the status register, then a publish inside the rate limit window
+    doorbell.acknowledge().unwrap();
+    let released = pa4_level();
+    let deferred = matches!(doorbell.ring(now), Ok(RingResult::Deferred)) && pa4_level();
+    let asserted_late = doorbell.poll(now + 100).unwrap() && !pa4_level();
+
+    let ok =
+        doorbell.ring_count() == 2 && idle && asserted && released && deferred && asserted_late;
+    if ok {
+        uart.write_all(b"\r\nGPIO doorbell: PASSED\r\n").unwrap();
+    } else {
+        uart.write_all(b"\r\nGPIO doorbell: FAILED\r\n").unwrap();
+    }
+    let freed = doorbell.free().is_ok() && pa4_level();
+    TestResult::of(ok && freed)
 }
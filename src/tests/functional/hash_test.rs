@@ -1,9 +1,18 @@
 // Licensed under the Apache-2.0 license
 
-use crate::hace_controller::HaceController;
-use crate::hash::{IntoHashAlgo, Sha256, Sha384, Sha512};
+use super::TestResult;
+use crate::hace_controller::{HaceController, HaceError, HashAlgo};
+#[cfg(feature = "sha512")]
+use crate::hash::{Digest64, HashStreamer, StreamHashError};
+use crate::hash::{IntoHashAlgo, Sha1, Sha224, Sha256, Sha384, Sha512};
+use crate::hash_caps::{fastest_with_strength, CALIBRATION_LEN};
+#[cfg(feature = "sha512")]
+use crate::hash_session::{SessionError, SessionTable};
+use crate::syscon::SysCon;
 use crate::uart::UartController;
 use core::any::TypeId;
+use cortex_m::peripheral::DWT;
+use embedded_hal::delay::DelayNs;
 use embedded_io::Write;
 use proposed_traits::digest::{DigestAlgorithm, DigestInit, DigestOp};
 
@@ -38,15 +47,24 @@ fn print_input(uart: &mut UartController, algo: &str, input: &[u8]) {
     writeln!(uart, "]:").unwrap();
 }
 
-pub fn run_hash_tests(uart: &mut UartController, hace: &mut HaceController) {
+pub fn run_hash_tests(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
     let input = *b"hello_world";
 
-    run_hash::<Sha256>(uart, hace, &input);
-    run_hash::<Sha384>(uart, hace, &input);
-    run_hash::<Sha512>(uart, hace, &input);
+    let mut result = run_hash::<Sha1>(uart, hace, &input);
+    result += run_hash::<Sha224>(uart, hace, &input);
+    result += run_hash::<Sha256>(uart, hace, &input);
+    #[cfg(feature = "sha384")]
+    {
+        result += run_hash::<Sha384>(uart, hace, &input);
+    }
+    #[cfg(feature = "sha512")]
+    {
+        result += run_hash::<Sha512>(uart, hace, &input);
+    }
+    result
 }
 
-fn run_hash<A>(uart: &mut UartController, ctrl: &mut HaceController, input: &[u8])
+fn run_hash<A>(uart: &mut UartController, ctrl: &mut HaceController, input: &[u8]) -> TestResult
 where
     A: DigestAlgorithm + IntoHashAlgo + Default + 'static,
     A::DigestOutput: Default + AsRef<[u8]> + AsMut<[u8]>,
@@ -59,7 +77,23 @@ where
     writeln!(uart, "\r\nOutput:").unwrap();
     print_hex_array(uart, output.as_ref(), 16);
 
-    let expected = if TypeId::of::<A>() == TypeId::of::<Sha256>() {
+    let expected = if TypeId::of::<A>() == TypeId::of::<Sha1>() {
+        Some(
+            &[
+                // Expected SHA-1 hash of "hello_world"
+                0xe4, 0xec, 0xd6, 0xfc, 0x11, 0x89, 0x85, 0x65, 0xaf, 0x24, 0x97, 0x7e, 0x99, 0x2c,
+                0xea, 0x0c, 0x9c, 0x7b, 0x70, 0x25,
+            ][..],
+        )
+    } else if TypeId::of::<A>() == TypeId::of::<Sha224>() {
+        Some(
+            &[
+                // Expected SHA-224 hash of "hello_world"
+                0x69, 0xc9, 0x39, 0x2f, 0x54, 0xe5, 0xa0, 0xe0, 0xff, 0xf8, 0x94, 0x5e, 0x9e, 0xd6,
+                0x47, 0x5e, 0xf8, 0x92, 0x36, 0x09, 0x2a, 0x52, 0xb2, 0x00, 0x57, 0x76, 0x91, 0x2c,
+            ][..],
+        )
+    } else if TypeId::of::<A>() == TypeId::of::<Sha256>() {
         Some(
             &[
                 // Expected SHA-256 hash of "hello_world"
@@ -91,8 +125,9 @@ where
         None
     };
 
+    let mut result = TestResult::new();
     if let Some(expected) = expected {
-        if output.as_ref() == expected {
+        if result.check(output.as_ref() == expected) {
             writeln!(uart, "\r\n{}: Test passed!", core::any::type_name::<A>()).unwrap();
         } else {
             writeln!(uart, "\r\n{}: Test failed!", core::any::type_name::<A>()).unwrap();
@@ -109,4 +144,385 @@ where
         )
         .unwrap();
     }
+    result
+}
+
+// SHA-256 / SHA-512 of the bytes 0..119. SHA-256 pads inside its last
+// block (55 bytes + 9 of padding = 64); SHA-512 needs an extra block
+// (119 + 17 > 128).
+#[cfg(feature = "sha512")]
+const INTERLEAVE_SHA256: [u8; 32] = [
+    0xda, 0x18, 0x79, 0x7e, 0xd7, 0xc3, 0xa7, 0x77, 0xf0, 0x84, 0x7f, 0x42, 0x97, 0x24, 0xa2, 0xd8,
+    0xcd, 0x51, 0x38, 0xe6, 0xed, 0x28, 0x95, 0xc3, 0xfa, 0x1a, 0x6d, 0x39, 0xd1, 0x8f, 0x7e, 0xc6,
+];
+#[cfg(feature = "sha512")]
+const INTERLEAVE_SHA512: [u8; 64] = [
+    0x43, 0xe4, 0x97, 0x27, 0x9c, 0x2c, 0xe8, 0x05, 0x90, 0x3a, 0x33, 0xb5, 0x4b, 0x74, 0x6e, 0xa9,
+    0x2d, 0x60, 0x7f, 0x7c, 0x48, 0x07, 0x98, 0x6c, 0x84, 0x98, 0x23, 0xb8, 0x10, 0x97, 0xa9, 0x09,
+    0x9b, 0x58, 0x96, 0xac, 0x7c, 0xc6, 0x6d, 0xf3, 0xa9, 0x3e, 0xdc, 0x8a, 0x91, 0xb6, 0xf3, 0x97,
+    0x1d, 0x6c, 0x7f, 0x56, 0x88, 0xda, 0xf6, 0x35, 0x73, 0x77, 0x60, 0xbd, 0x08, 0x0e, 0x27, 0xb3,
+];
+
+#[cfg(feature = "sha512")]
+/// Alternates updates of a SHA-256 and a SHA-512 session on the shared
+/// context, with a partial block carried in each, to check that block size
+/// and padding state do not leak between sessions.
+pub fn run_hash_interleave_tests(
+    uart: &mut UartController,
+    hace: &mut HaceController,
+) -> TestResult {
+    let mut input = [0u8; 119];
+    for (i, b) in input.iter_mut().enumerate() {
+        *b = u8::try_from(i).unwrap();
+    }
+
+    writeln!(uart, "\r\nRunning hash interleave test...").unwrap();
+    let (head, tail) = input.split_at(70);
+    let mut sha256 = SessionTable::<Sha256, 1>::new();
+    let mut sha512 = SessionTable::<Sha512, 1>::new();
+    let mut pass = true;
+    for round in 0..2 {
+        let digests = interleave_round(hace, &mut sha256, &mut sha512, head, tail);
+        let ok = matches!(
+            &digests,
+            Ok((d256, d512)) if *d256 == INTERLEAVE_SHA256 && d512.0 == INTERLEAVE_SHA512
+        );
+        if !ok {
+            writeln!(uart, "\r\nround {round} mismatch").unwrap();
+            pass = false;
+        }
+    }
+    if pass {
+        writeln!(uart, "\r\nHash interleave: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nHash interleave: Test failed!").unwrap();
+    }
+    TestResult::of(pass)
+}
+
+#[cfg(feature = "sha512")]
+fn interleave_round(
+    hace: &mut HaceController,
+    sha256: &mut SessionTable<Sha256, 1>,
+    sha512: &mut SessionTable<Sha512, 1>,
+    head: &[u8],
+    tail: &[u8],
+) -> Result<([u8; 32], Digest64), SessionError> {
+    let h256 = sha256.open(hace)?;
+    let h512 = sha512.open(hace)?;
+    sha256.update(hace, h256, head)?;
+    sha512.update(hace, h512, head)?;
+    sha256.update(hace, h256, tail)?;
+    sha512.update(hace, h512, tail)?;
+    Ok((sha256.finalize(hace, h256)?, sha512.finalize(hace, h512)?))
+}
+
+#[cfg(feature = "sha512")]
+pub fn run_hash_stream_tests(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    let mut input = [0u8; 119];
+    for (i, b) in input.iter_mut().enumerate() {
+        *b = u8::try_from(i).unwrap();
+    }
+
+    writeln!(uart, "\r\nRunning hash stream test...").unwrap();
+    // Smallest chunk for SHA-256: one full block plus a partial one
+    let mut small = HashStreamer::<64>::new();
+    let sha256 = small.hash_reader(hace, Sha256, &mut &input[..]);
+    let mut wide = HashStreamer::<128>::new();
+    let sha512 = wide.hash_reader(hace, Sha512, &mut &input[..]);
+    // 64 bytes is not a whole SHA-512 block
+    let rejected = matches!(
+        small.hash_reader(hace, Sha512, &mut &input[..]),
+        Err(StreamHashError::Unaligned)
+    );
+
+    let pass = sha256.is_ok_and(|d| d.as_ref() == INTERLEAVE_SHA256)
+        && sha512.is_ok_and(|d| d.as_ref() == INTERLEAVE_SHA512)
+        && rejected;
+    if pass {
+        writeln!(uart, "\r\nHash stream: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nHash stream: Test failed!").unwrap();
+    }
+    TestResult::of(pass)
+}
+
+#[cfg(feature = "sha512")]
+/// Hashes the interleave input as header, payload and trailer parts, the
+/// header and trailer shorter than a block, and checks it against the
+/// single-buffer digest
+pub fn run_hash_iter_tests(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    let mut input = [0u8; 119];
+    for (i, b) in input.iter_mut().enumerate() {
+        *b = u8::try_from(i).unwrap();
+    }
+    let parts = [&input[..10], &input[10..80], &input[80..]];
+
+    writeln!(uart, "\r\nRunning hash update_iter test...").unwrap();
+    let mut ctx = hace.init(Sha256).unwrap();
+    ctx.update_iter(parts).unwrap();
+    let sha256 = ctx.finalize().unwrap();
+    let mut ctx = hace.init(Sha512).unwrap();
+    ctx.update_iter(parts).unwrap();
+    let sha512 = ctx.finalize().unwrap();
+
+    let ok = sha256.as_ref() == INTERLEAVE_SHA256 && sha512.as_ref() == INTERLEAVE_SHA512;
+    if ok {
+        writeln!(uart, "\r\nHash update_iter: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nHash update_iter: Test failed!").unwrap();
+    }
+    TestResult::of(ok)
+}
+
+/// Starting from the standard SHA-256 initial value given as words must
+/// match a plain `init`, and an IV of the wrong length is refused
+pub fn run_hash_iv_tests(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    const SHA256_IV: [u32; 8] = [
+        0x6a09_e667,
+        0xbb67_ae85,
+        0x3c6e_f372,
+        0xa54f_f53a,
+        0x510e_527f,
+        0x9b05_688c,
+        0x1f83_d9ab,
+        0x5be0_cd19,
+    ];
+
+    writeln!(uart, "\r\nRunning hash IV override test...").unwrap();
+    let mut ctx = hace.init(Sha256).unwrap();
+    ctx.update(b"hello_world").unwrap();
+    let expected = ctx.finalize().unwrap();
+    let mut ctx = hace.init_with_iv(Sha256, &SHA256_IV).unwrap();
+    ctx.update(b"hello_world").unwrap();
+    let custom = ctx.finalize().unwrap();
+    let rejected = hace.init_with_iv(Sha256, &SHA256_IV[..7]).is_err();
+
+    let ok = custom.as_ref() == expected.as_ref() && rejected;
+    if ok {
+        writeln!(uart, "\r\nHash IV override: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nHash IV override: Test failed!").unwrap();
+    }
+    TestResult::of(ok)
+}
+
+/// The empty message must hash to the standard digest whether or not an
+/// empty `update` is made, and empty updates between data change nothing
+pub fn run_hash_empty_tests(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    writeln!(uart, "\r\nRunning hash empty input tests...").unwrap();
+    let mut result = run_hash_empty::<Sha1>(
+        hace,
+        &[
+            0xda, 0x39, 0xa3, 0xee, 0x5e, 0x6b, 0x4b, 0x0d, 0x32, 0x55, 0xbf, 0xef, 0x95, 0x60,
+            0x18, 0x90, 0xaf, 0xd8, 0x07, 0x09,
+        ],
+    );
+    result += run_hash_empty::<Sha224>(
+        hace,
+        &[
+            0xd1, 0x4a, 0x02, 0x8c, 0x2a, 0x3a, 0x2b, 0xc9, 0x47, 0x61, 0x02, 0xbb, 0x28, 0x82,
+            0x34, 0xc4, 0x15, 0xa2, 0xb0, 0x1f, 0x82, 0x8e, 0xa6, 0x2a, 0xc5, 0xb3, 0xe4, 0x2f,
+        ],
+    );
+    result += run_hash_empty::<Sha256>(
+        hace,
+        &[
+            0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f,
+            0xb9, 0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b,
+            0x78, 0x52, 0xb8, 0x55,
+        ],
+    );
+    #[cfg(feature = "sha384")]
+    {
+        result += run_hash_empty::<Sha384>(
+            hace,
+            &[
+                0x38, 0xb0, 0x60, 0xa7, 0x51, 0xac, 0x96, 0x38, 0x4c, 0xd9, 0x32, 0x7e, 0xb1, 0xb1,
+                0xe3, 0x6a, 0x21, 0xfd, 0xb7, 0x11, 0x14, 0xbe, 0x07, 0x43, 0x4c, 0x0c, 0xc7, 0xbf,
+                0x63, 0xf6, 0xe1, 0xda, 0x27, 0x4e, 0xde, 0xbf, 0xe7, 0x6f, 0x65, 0xfb, 0xd5, 0x1a,
+                0xd2, 0xf1, 0x48, 0x98, 0xb9, 0x5b,
+            ],
+        );
+    }
+    #[cfg(feature = "sha512")]
+    {
+        result += run_hash_empty::<Sha512>(
+            hace,
+            &[
+                0xcf, 0x83, 0xe1, 0x35, 0x7e, 0xef, 0xb8, 0xbd, 0xf1, 0x54, 0x28, 0x50, 0xd6, 0x6d,
+                0x80, 0x07, 0xd6, 0x20, 0xe4, 0x05, 0x0b, 0x57, 0x15, 0xdc, 0x83, 0xf4, 0xa9, 0x21,
+                0xd3, 0x6c, 0xe9, 0xce, 0x47, 0xd0, 0xd1, 0x3c, 0x5d, 0x85, 0xf2, 0xb0, 0xff, 0x83,
+                0x18, 0xd2, 0x87, 0x7e, 0xec, 0x2f, 0x63, 0xb9, 0x31, 0xbd, 0x47, 0x41, 0x7a, 0x81,
+                0xa5, 0x38, 0x32, 0x7a, 0xf9, 0x27, 0xda, 0x3e,
+            ],
+        );
+    }
+
+    if result.is_ok() {
+        writeln!(uart, "\r\nHash empty input: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nHash empty input: Test failed!").unwrap();
+    }
+    result
+}
+
+fn run_hash_empty<A>(hace: &mut HaceController, expected: &[u8]) -> TestResult
+where
+    A: DigestAlgorithm + IntoHashAlgo + Default,
+    A::DigestOutput: Default + AsRef<[u8]> + AsMut<[u8]>,
+{
+    let mut result = TestResult::new();
+
+    let ctx = hace.init(A::default()).unwrap();
+    result.check(ctx.finalize().unwrap().as_ref() == expected);
+    let mut ctx = hace.init(A::default()).unwrap();
+    ctx.update(&[]).unwrap();
+    result.check(ctx.finalize().unwrap().as_ref() == expected);
+
+    // empty updates at the start, mid-block, on a block boundary and last
+    let mut input = [0u8; 300];
+    for (i, b) in input.iter_mut().enumerate() {
+        *b = u8::try_from(i % 251).unwrap();
+    }
+    let mut ctx = hace.init(A::default()).unwrap();
+    ctx.update(&input).unwrap();
+    let whole = ctx.finalize().unwrap();
+    let mut ctx = hace.init(A::default()).unwrap();
+    for part in [
+        &[][..],
+        &input[..70],
+        &[],
+        &input[70..128],
+        &[],
+        &input[128..],
+        &[],
+    ] {
+        ctx.update(part).unwrap();
+    }
+    result.check(ctx.finalize().unwrap().as_ref() == whole.as_ref());
+    result
+}
+
+/// A second controller must be refused while `hace` owns the engine, so a
+/// digest and an HMAC can never share the context at the same time
+pub fn run_hace_ownership_test(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    let stolen = unsafe { ast1060_pac::Peripherals::steal() }.hace;
+    let busy = matches!(HaceController::try_new(stolen), Err(HaceError::Busy));
+    // the live controller still works after the refused claim
+    let mut ctx = hace.init(Sha256).unwrap();
+    ctx.update(b"hello_world").unwrap();
+    let _ = ctx.finalize().unwrap();
+
+    if busy {
+        writeln!(uart, "\r\nHACE ownership: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nHACE ownership: Test failed!").unwrap();
+    }
+    TestResult::of(busy)
+}
+
+/// Recovers the engine with a hash left half done, as after a hang, and
+/// checks the next hash is correct and nothing of the old one is left
+pub fn run_hace_recover_test<D: DelayNs>(
+    uart: &mut UartController,
+    hace: &mut HaceController,
+    syscon: &mut SysCon<D>,
+) -> TestResult {
+    let input = [0x5au8; 100];
+    let mut ctx = hace.init(Sha256).unwrap();
+    ctx.update(&input).unwrap();
+    let expected = ctx.finalize().unwrap();
+
+    // leaves a partial block and a running digest in the shared context
+    let mut ctx = hace.init(Sha256).unwrap();
+    ctx.update(&input[..70]).unwrap();
+    drop(ctx);
+
+    let recovered = hace.recover(syscon).is_ok();
+    let wiped = hace.ctx_mut().bufcnt == 0 && hace.ctx_mut().digcnt == [0; 2];
+    let status = hace.status();
+    let idle = !status.hash_busy && !status.hash_done;
+    let mut ctx = hace.init(Sha256).unwrap();
+    ctx.update(&input).unwrap();
+    let correct = ctx.finalize().unwrap() == expected;
+
+    let ok = recovered && wiped && idle && correct;
+    if ok {
+        writeln!(uart, "\r\nHACE recover: Test passed!").unwrap();
+    } else {
+        writeln!(
+            uart,
+            "\r\nHACE recover: Test failed! recover {recovered} wiped {wiped} idle {idle} digest {correct}"
+        )
+        .unwrap();
+    }
+    TestResult::of(ok)
+}
+
+/// Calibrates the engine, then times SHA-256 again to check the table
+/// against a second measurement
+pub fn run_hash_caps_test(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    hace.calibrate();
+    let caps = HaceController::capabilities();
+    let mut result = TestResult::new();
+
+    writeln!(uart, "\r\nHash capabilities, fastest first:").unwrap();
+    for c in caps {
+        writeln!(
+            uart,
+            "\r  {:?}: {} cycles/byte, {}-bit strength, {}-byte digest",
+            c.algo,
+            c.cycles_per_byte(),
+            c.strength_bits,
+            c.digest_len
+        )
+        .unwrap();
+    }
+
+    let ordered = caps
+        .windows(2)
+        .all(|w| w[0].calibration_cycles <= w[1].calibration_cycles);
+    let measured =
+        !cfg!(feature = "hash-calibration") || caps.iter().all(|c| c.calibration_cycles > 0);
+    result.check(ordered);
+    result.check(measured);
+
+    // nothing faster than the pick may be as strong
+    let pick = fastest_with_strength(128);
+    let consistent = pick.is_some_and(|p| {
+        p.strength_bits >= 128
+            && caps
+                .iter()
+                .take_while(|c| c.algo != p.algo)
+                .all(|c| c.strength_bits < 128)
+    });
+    result.check(consistent);
+
+    if cfg!(feature = "hash-calibration") {
+        let input = [0u8; CALIBRATION_LEN as usize];
+        let start = DWT::cycle_count();
+        let mut ctx = hace.init(Sha256).unwrap();
+        ctx.update(&input).unwrap();
+        ctx.finalize().unwrap();
+        let cycles = DWT::cycle_count().wrapping_sub(start);
+        let table = caps
+            .iter()
+            .find(|c| c.algo == HashAlgo::SHA256)
+            .map_or(0, |c| c.calibration_cycles);
+        writeln!(uart, "\r  SHA256 retimed: {cycles} cycles, table {table}").unwrap();
+        let close = cycles <= table * 2 && table <= cycles * 2;
+        result.check(close);
+    }
+
+    if result.is_ok() {
+        writeln!(uart, "\r\nHash capabilities: Test passed!").unwrap();
+    } else {
+        writeln!(
+            uart,
+            "\r\nHash capabilities: Test failed! ordered {ordered} measured {measured} pick {consistent}"
+        )
+        .unwrap();
+    }
+    result
 }
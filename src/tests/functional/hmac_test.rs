@@ -1,12 +1,18 @@
 // Licensed under the Apache-2.0 license
 
-use crate::hace_controller::HaceController;
-use crate::hmac::{IntoHashAlgo, Sha256, Sha384, Sha512};
+use super::TestResult;
+use crate::hace_controller::{HaceController, HashAlgo};
+use crate::hmac::{HmacError, IntoHashAlgo, KeyRef, Sha256, Sha384, Sha512};
+use crate::keystore::KeyStore;
 use crate::uart::UartController;
 use core::any::TypeId;
+use core::mem::MaybeUninit;
 use embedded_io::Write;
 use proposed_traits::mac::{MacAlgorithm, MacInit, MacOp};
 
+#[link_section = ".keystore"]
+static mut KEYS: MaybeUninit<KeyStore<1>> = MaybeUninit::uninit();
+
 fn print_hex_array(uart: &mut UartController, data: &[u8], bytes_per_line: usize) {
     for (i, b) in data.iter().enumerate() {
         if i % bytes_per_line == 0 {
@@ -38,19 +44,209 @@ fn print_input(uart: &mut UartController, algo: &str, input: &[u8]) {
     writeln!(uart, "]:").unwrap();
 }
 
-pub fn run_hmac_tests(uart: &mut UartController, hace: &mut HaceController) {
+const HMAC_SHA256_EXPECTED: [u8; 32] = [
+    0xde, 0x60, 0xb1, 0xd4, 0x83, 0xd2, 0x00, 0x11, 0xf1, 0xb4, 0x2f, 0x33, 0x70, 0x0c, 0xb4, 0x4f,
+    0xa3, 0x16, 0xc4, 0x43, 0xce, 0x43, 0x03, 0x78, 0xcb, 0x5d, 0x65, 0x42, 0x7f, 0x64, 0x34, 0x8d,
+];
+
+/// One-shot `HaceController::mac` must match the streaming API result
+pub fn run_hmac_oneshot_test(
+    uart: &mut UartController,
+    hace: HaceController,
+) -> (TestResult, HaceController) {
+    let key = [0xb; 32];
+    let message = *b"The quick brown fox jumps over the lazy dog";
+
+    writeln!(uart, "\r\nRunning one-shot HMAC test...").unwrap();
+    match hace.mac(Sha256, &key, &message) {
+        Ok((output, controller)) if output == HMAC_SHA256_EXPECTED => {
+            writeln!(uart, "\r\nOne-shot HMAC-SHA256: Test passed!").unwrap();
+            (TestResult::of(true), controller)
+        }
+        Ok((output, controller)) => {
+            writeln!(uart, "\r\nOne-shot HMAC-SHA256: Test failed!").unwrap();
+            print_hex_array(uart, &output, 16);
+            (TestResult::of(false), controller)
+        }
+        Err((e, controller)) => {
+            writeln!(uart, "\r\nOne-shot HMAC-SHA256: Test failed! {e:?}").unwrap();
+            (TestResult::of(false), controller)
+        }
+    }
+}
+
+/// RFC 4231 test case 5: HMAC-SHA-256 truncated to 128 bits
+const HMAC_SHA256_128_EXPECTED: [u8; 16] = [
+    0xa3, 0xb6, 0x16, 0x74, 0x73, 0x10, 0x0e, 0xe0, 0x6e, 0x0c, 0x79, 0x6c, 0x29, 0x55, 0x55, 0x2b,
+];
+
+fn run_hmac_truncated_test(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    let mut result = TestResult::new();
+    // A 20-byte key zero-padded to the Key type hashes the same, as HMAC
+    // pads keys with zeros to the block size
+    let mut key = [0u8; 32];
+    key[..20].fill(0x0c);
+    let message = *b"Test With Truncation";
+
+    let mut ctx = hace.init(Sha256, &key).unwrap();
+    ctx.update(&message).unwrap();
+    let tag = ctx.finalize_truncated(16).unwrap();
+    if result.check(tag.len() == 16 && tag.matches(&HMAC_SHA256_128_EXPECTED)) {
+        writeln!(uart, "\r\nHMAC-SHA256-128: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nHMAC-SHA256-128: Test failed!").unwrap();
+        print_hex_array(uart, tag.as_ref(), 16);
+    }
+
+    let ctx = hace.init(Sha256, &key).unwrap();
+    if !result.check(ctx.finalize_truncated(33).is_err()) {
+        writeln!(uart, "\r\nHMAC truncation past digest size: Test failed!").unwrap();
+    }
+    result
+}
+
+/// RFC 4231 test case 6: a key longer than the block is hashed first
+const HMAC_SHA256_LONG_KEY_EXPECTED: [u8; 32] = [
+    0x60, 0xe4, 0x31, 0x59, 0x1e, 0xe0, 0xb6, 0x7f, 0x0d, 0x8a, 0x26, 0xaa, 0xcb, 0xf5, 0xb7, 0x7f,
+    0x8e, 0x0b, 0xc6, 0x21, 0x37, 0x28, 0xc5, 0x14, 0x05, 0x46, 0x04, 0x0f, 0x0e, 0xe3, 0x7f, 0x54,
+];
+
+fn report(uart: &mut UartController, result: &mut TestResult, name: &str, ok: bool) {
+    if result.check(ok) {
+        writeln!(uart, "\r\n{name}: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\n{name}: Test failed!").unwrap();
+    }
+}
+
+/// `HaceController::hmac_oneshot` with raw keys, a stored key and
+/// truncated output
+fn run_hmac_keyref_test(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    let mut result = TestResult::new();
+
+    // RFC 4231 test case 5, with the 20-byte key as is
+    let mut tag = [0u8; 16];
+    let written = hace.hmac_oneshot(
+        HashAlgo::SHA256,
+        KeyRef::Raw(&[0x0c; 20]),
+        b"Test With Truncation",
+        &mut tag,
+    );
+    let ok = written == Ok(16) && tag == HMAC_SHA256_128_EXPECTED;
+    report(uart, &mut result, "hmac_oneshot HMAC-SHA256-128", ok);
+
+    let mut mac = [0u8; 32];
+    let written = hace.hmac_oneshot(
+        HashAlgo::SHA256,
+        KeyRef::Raw(&[0xaa; 131]),
+        b"Test Using Larger Than Block-Size Key - Hash Key First",
+        &mut mac,
+    );
+    let ok = written == Ok(32) && mac == HMAC_SHA256_LONG_KEY_EXPECTED;
+    report(uart, &mut result, "hmac_oneshot long key", ok);
+
+    // SAFETY: the test runs once, so this is the only reference to KEYS
+    let keys = KeyStore::init_in(unsafe { &mut *core::ptr::addr_of_mut!(KEYS) });
+    let handle = keys.import_hmac_sha256(&[0xb; 32]).unwrap();
+    let mut mac = [0u8; 32];
+    let written = hace.hmac_oneshot(
+        HashAlgo::SHA256,
+        KeyRef::Handle(keys, handle),
+        b"The quick brown fox jumps over the lazy dog",
+        &mut mac,
+    );
+    let ok = written == Ok(32) && mac == HMAC_SHA256_EXPECTED;
+    report(uart, &mut result, "hmac_oneshot key handle", ok);
+
+    let mut long = [0u8; 33];
+    let written = hace.hmac_oneshot(HashAlgo::SHA256, KeyRef::Raw(&[0xb; 32]), b"", &mut long);
+    let ok = written == Err(HmacError::OutputLength);
+    report(uart, &mut result, "hmac_oneshot output past MAC size", ok);
+    result
+}
+
+fn run_hmac_iter_test(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    let key = [0xb; 32];
+    let message = *b"The quick brown fox jumps over the lazy dog";
+    let parts = [&message[..4], &message[4..20], &message[20..]];
+
+    let mut ctx = hace.init(Sha256, &key).unwrap();
+    ctx.update_iter(parts).unwrap();
+    let output = ctx.finalize().unwrap();
+    let ok = output.as_ref() == HMAC_SHA256_EXPECTED;
+    if ok {
+        writeln!(uart, "\r\nHMAC-SHA256 update_iter: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nHMAC-SHA256 update_iter: Test failed!").unwrap();
+        print_hex_array(uart, output.as_ref(), 16);
+    }
+    TestResult::of(ok)
+}
+
+/// HMAC-SHA-256 under `[0x0b; 32]` of the bytes `i % 251` for i in 0..300
+const HMAC_SHA256_STREAM_EXPECTED: [u8; 32] = [
+    0xd1, 0x99, 0xe6, 0xa4, 0xcb, 0x2a, 0x39, 0xa9, 0xe0, 0xb5, 0x12, 0x1c, 0x83, 0x41, 0xb4, 0xa2,
+    0x97, 0xb8, 0x86, 0x08, 0xf2, 0x0f, 0x31, 0x08, 0x81, 0x7d, 0xd4, 0x8f, 0x18, 0xc0, 0xa5, 0x17,
+];
+
+/// Sequential updates carry partial blocks between calls, and together
+/// with the ipad block the message is longer than the context buffer
+fn run_hmac_stream_test(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    let key = [0xb; 32];
+    let mut message = [0u8; 300];
+    for (i, b) in message.iter_mut().enumerate() {
+        *b = u8::try_from(i % 251).unwrap();
+    }
+
+    let mut ctx = hace.init(Sha256, &key).unwrap();
+    for part in [&message[..10], &message[10..70], &message[70..]] {
+        ctx.update(part).unwrap();
+    }
+    let sequential = ctx.finalize().unwrap();
+
+    let mut ctx = hace.init(Sha256, &key).unwrap();
+    ctx.update_iter([&message[..63], &message[63..65], &message[65..]])
+        .unwrap();
+    let iterated = ctx.finalize().unwrap();
+
+    let ok = sequential == HMAC_SHA256_STREAM_EXPECTED && iterated == HMAC_SHA256_STREAM_EXPECTED;
+    if ok {
+        writeln!(uart, "\r\nHMAC-SHA256 streaming: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nHMAC-SHA256 streaming: Test failed!").unwrap();
+        print_hex_array(uart, &sequential, 16);
+        print_hex_array(uart, &iterated, 16);
+    }
+    TestResult::of(ok)
+}
+
+pub fn run_hmac_tests(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
     let key256 = [0xb; 32];
-    let key384 = [0xb; 48];
-    let key512 = [0xb; 64];
     let message = *b"The quick brown fox jumps over the lazy dog";
 
     writeln!(uart, "\r\nRunning HMAC tests...").unwrap();
-    run_hmac::<Sha256>(uart, hace, &key256, &message);
-    run_hmac::<Sha384>(uart, hace, &key384, &message);
-    run_hmac::<Sha512>(uart, hace, &key512, &message);
+    let mut result = run_hmac::<Sha256>(uart, hace, &key256, &message);
+    #[cfg(feature = "sha384")]
+    {
+        result += run_hmac::<Sha384>(uart, hace, &[0xb; 48], &message);
+    }
+    #[cfg(feature = "sha512")]
+    {
+        result += run_hmac::<Sha512>(uart, hace, &[0xb; 64], &message);
+    }
+    result += run_hmac_truncated_test(uart, hace);
+    result += run_hmac_iter_test(uart, hace);
+    result += run_hmac_stream_test(uart, hace);
+    result += run_hmac_keyref_test(uart, hace);
+    result
 }
 
-fn run_hmac<A>(uart: &mut UartController, ctrl: &mut HaceController, key: &A::Key, input: &[u8])
+fn run_hmac<A>(
+    uart: &mut UartController,
+    ctrl: &mut HaceController,
+    key: &A::Key,
+    input: &[u8],
+) -> TestResult
 where
     A: MacAlgorithm + IntoHashAlgo + Default + 'static,
     A::MacOutput: Default + AsRef<[u8]> + AsMut<[u8]>,
@@ -67,13 +263,7 @@ where
     print_hex_array(uart, output.as_ref(), 16);
 
     let expected = if TypeId::of::<A>() == TypeId::of::<Sha256>() {
-        Some(
-            &[
-                0xde, 0x60, 0xb1, 0xd4, 0x83, 0xd2, 0x00, 0x11, 0xf1, 0xb4, 0x2f, 0x33, 0x70, 0x0c,
-                0xb4, 0x4f, 0xa3, 0x16, 0xc4, 0x43, 0xce, 0x43, 0x03, 0x78, 0xcb, 0x5d, 0x65, 0x42,
-                0x7f, 0x64, 0x34, 0x8d,
-            ][..],
-        )
+        Some(&HMAC_SHA256_EXPECTED[..])
     } else if TypeId::of::<A>() == TypeId::of::<Sha384>() {
         Some(
             &[
@@ -97,8 +287,9 @@ where
         None
     };
 
+    let mut result = TestResult::new();
     if let Some(expected) = expected {
-        if output.as_ref() == expected {
+        if result.check(output.as_ref() == expected) {
             writeln!(uart, "\r\n{}: Test passed!", core::any::type_name::<A>()).unwrap();
         } else {
             writeln!(uart, "\r\n{}: Test failed!", core::any::type_name::<A>()).unwrap();
@@ -115,4 +306,5 @@ where
         )
         .unwrap();
     }
+    result
 }
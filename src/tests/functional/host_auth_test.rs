@@ -0,0 +1,66 @@
+// Licensed under the Apache-2.0 license
+
+use super::TestResult;
+use crate::hace_controller::HaceController;
+use crate::host_auth::{ChallengeMac, HostSessionAuth, NonceSource, NONCE_LEN};
+use crate::host_policy::{CommandStatus, HostCommand};
+use crate::keystore::KeyStore;
+use crate::uart::UartController;
+use core::mem::MaybeUninit;
+use embedded_io::Write;
+
+const KEY: [u8; 32] = [0x3c; 32];
+
+#[link_section = ".keystore"]
+static mut KEYS: MaybeUninit<KeyStore<1>> = MaybeUninit::uninit();
+/// Ticks a session stays open
+const WINDOW: u32 = 1000;
+
+/// Distinct nonces without an RNG; good enough to exercise the protocol
+struct CountingNonces(u8);
+
+impl NonceSource for CountingNonces {
+    fn fill_nonce(&mut self, nonce: &mut [u8; NONCE_LEN]) {
+        self.0 = self.0.wrapping_add(1);
+        for (i, b) in nonce.iter_mut().enumerate() {
+            *b = self.0 ^ u8::try_from(i).unwrap();
+        }
+    }
+}
+
+/// Runs the nonce, response and privileged command flow on the hash
+/// engine, checks the session admits no other privileged command, then
+/// replays the accepted response
+pub fn run_host_auth_tests(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    writeln!(uart, "\r\nRunning host challenge-response test").unwrap();
+    // SAFETY: the test runs once, so this is the only reference to KEYS
+    let keys = KeyStore::init_in(unsafe { &mut *core::ptr::addr_of_mut!(KEYS) });
+    let handle = keys.import_hmac_sha256(&KEY).unwrap();
+    let mut auth = HostSessionAuth::new(CountingNonces(0), handle, WINDOW, 3).unwrap();
+    let update = HostCommand::UpdateStart as u8;
+
+    let before = auth.check(HostCommand::UpdateStart, 0);
+    // what the host computes with its copy of the key
+    let nonce = auth.challenge().unwrap();
+    let response = hace.hmac_sha256(&KEY, &[&nonce, &[update]]).unwrap();
+    let accepted = auth.authenticate(hace, keys, update, &response, 100);
+    let during = auth.check(HostCommand::UpdateStart, 100 + WINDOW - 1);
+    let other = auth.check(HostCommand::UpdateCommit, 100);
+    let expired = auth.check(HostCommand::UpdateStart, 100 + WINDOW);
+    let replayed = auth.authenticate(hace, keys, update, &response, 200);
+
+    let checks = [
+        before == Err(CommandStatus::NotAuthenticated),
+        accepted == Ok(()),
+        during == Ok(()),
+        other == Err(CommandStatus::NotAuthenticated),
+        expired == Err(CommandStatus::NotAuthenticated),
+        replayed == Err(CommandStatus::AuthFailed),
+    ];
+
+    match checks.iter().position(|ok| !ok) {
+        None => writeln!(uart, "\rhost challenge-response: PASSED").unwrap(),
+        Some(i) => writeln!(uart, "\rhost challenge-response: FAILED at check {i}").unwrap(),
+    }
+    TestResult::from_checks(&checks)
+}
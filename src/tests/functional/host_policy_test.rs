@@ -0,0 +1,133 @@
+// Licensed under the Apache-2.0 license
+
+use super::TestResult;
+use crate::ecdsa::{AspeedEcdsaError, PublicKey, Scalar48, Secp384r1Curve, Signature};
+use crate::host_policy::{
+    BootState, CommandPolicy, CommandStatus, EcdsaTransitionAuth, HostCommand,
+    TransitionAuthenticator, TRANSITION_NONCE_LEN,
+};
+use crate::uart::UartController;
+use embedded_io::Write;
+use proposed_traits::digest::DigestAlgorithm;
+use proposed_traits::ecdsa::{Curve, EcdsaVerify, ErrorType};
+
+/// Accepts a transition only when the auth payload is the expected token
+struct TokenAuth(&'static [u8]);
+
+impl TransitionAuthenticator for TokenAuth {
+    fn authenticate(&mut self, _target: BootState, auth: &[u8]) -> bool {
+        auth == self.0
+    }
+}
+
+pub fn run_host_policy_tests(uart: &mut UartController) -> TestResult {
+    writeln!(uart, "\r\nRunning host command policy test").unwrap();
+    let mut auth = TokenAuth(b"token");
+    let mut policy = CommandPolicy::new(BootState::Normal);
+    let update = HostCommand::UpdateWrite as u8;
+    let enter = HostCommand::EnterRecovery as u8;
+    let exit = HostCommand::ExitRecovery as u8;
+
+    let checks = [
+        // Normal state: reads allowed, update denied
+        policy.check(HostCommand::ReadMeasurement as u8) == Ok(HostCommand::ReadMeasurement),
+        policy.check(update) == Err(CommandStatus::NotAllowedInState),
+        policy.check(0xee) == Err(CommandStatus::UnknownCommand),
+        // Unauthenticated transition is rejected and state is unchanged
+        policy.transition(enter, b"bad", &mut auth) == Err(CommandStatus::AuthFailed),
+        policy.state() == BootState::Normal,
+        policy.transition(exit, b"token", &mut auth) == Err(CommandStatus::NotAllowedInState),
+        // Authenticated transition into recovery enables updates
+        policy.transition(enter, b"token", &mut auth) == Ok(BootState::Recovery),
+        policy.check(update) == Ok(HostCommand::UpdateWrite),
+        policy.check(HostCommand::ReadMeasurement as u8) == Ok(HostCommand::ReadMeasurement),
+        policy.check(enter) == Err(CommandStatus::NotAllowedInState),
+        // And back to normal
+        policy.transition(exit, b"token", &mut auth) == Ok(BootState::Normal),
+        policy.check(update) == Err(CommandStatus::NotAllowedInState),
+    ];
+
+    match checks.iter().position(|ok| !ok) {
+        None => writeln!(uart, "\rhost command policy: PASSED").unwrap(),
+        Some(i) => writeln!(uart, "\rhost command policy: FAILED at check {i}").unwrap(),
+    }
+    TestResult::from_checks(&checks)
+}
+
+/// Stands in for the ECDSA engine: a signature is valid when `r` is the
+/// signed digest
+struct EchoVerifier;
+
+impl ErrorType for EchoVerifier {
+    type Error = AspeedEcdsaError;
+}
+
+impl EcdsaVerify<Secp384r1Curve> for EchoVerifier {
+    type PublicKey = PublicKey;
+    type Signature = Signature;
+
+    fn verify(
+        &mut self,
+        _public_key: &PublicKey,
+        digest: <<Secp384r1Curve as Curve>::DigestType as DigestAlgorithm>::DigestOutput,
+        signature: &Signature,
+    ) -> Result<(), AspeedEcdsaError> {
+        if digest.as_ref() == &signature.r.0[..] {
+            Ok(())
+        } else {
+            Err(AspeedEcdsaError::InvalidSignature)
+        }
+    }
+}
+
+/// `challenge || r || s` as the host sends it after signing the challenge
+/// for `target`
+fn signed_response(
+    nonce: &[u8; TRANSITION_NONCE_LEN],
+    target: BootState,
+) -> [u8; 3 * Scalar48::LEN] {
+    let challenge = EcdsaTransitionAuth::<EchoVerifier>::challenge_for(nonce, target);
+    let mut response = [0u8; 3 * Scalar48::LEN];
+    response[..Scalar48::LEN].copy_from_slice(&challenge);
+    response[Scalar48::LEN..2 * Scalar48::LEN].copy_from_slice(&challenge);
+    response
+}
+
+/// A signed transition request is bound to the state it was signed for
+pub fn run_transition_auth_tests(uart: &mut UartController) -> TestResult {
+    writeln!(uart, "\r\nRunning transition authentication test").unwrap();
+    let mut verifier = EchoVerifier;
+    let key = PublicKey {
+        qx: Scalar48([0; 48]),
+        qy: Scalar48([0; 48]),
+    };
+    let mut auth = EcdsaTransitionAuth::new(&mut verifier, key);
+    let mut policy = CommandPolicy::new(BootState::Normal);
+    let enter = HostCommand::EnterRecovery as u8;
+    let exit = HostCommand::ExitRecovery as u8;
+
+    let mut checks = [false; 5];
+    auth.set_challenge([0x11; TRANSITION_NONCE_LEN]);
+    let to_recovery = signed_response(&[0x11; TRANSITION_NONCE_LEN], BootState::Recovery);
+    checks[0] = policy.transition(enter, &to_recovery, &mut auth) == Ok(BootState::Recovery);
+
+    // signed for Recovery, presented to leave it
+    auth.set_challenge([0x22; TRANSITION_NONCE_LEN]);
+    let wrong_target = signed_response(&[0x22; TRANSITION_NONCE_LEN], BootState::Recovery);
+    checks[1] = policy.transition(exit, &wrong_target, &mut auth) == Err(CommandStatus::AuthFailed);
+    checks[2] = policy.state() == BootState::Recovery;
+
+    // the nonce went with the failed attempt
+    let to_normal = signed_response(&[0x22; TRANSITION_NONCE_LEN], BootState::Normal);
+    checks[3] = policy.transition(exit, &to_normal, &mut auth) == Err(CommandStatus::AuthFailed);
+
+    auth.set_challenge([0x33; TRANSITION_NONCE_LEN]);
+    let to_normal = signed_response(&[0x33; TRANSITION_NONCE_LEN], BootState::Normal);
+    checks[4] = policy.transition(exit, &to_normal, &mut auth) == Ok(BootState::Normal);
+
+    match checks.iter().position(|ok| !ok) {
+        None => writeln!(uart, "\rtransition authentication: PASSED").unwrap(),
+        Some(i) => writeln!(uart, "\rtransition authentication: FAILED at check {i}").unwrap(),
+    }
+    TestResult::from_checks(&checks)
+}
@@ -0,0 +1,516 @@
+// Licensed under the Apache-2.0 license
+
+//! Loopback tests between two I2C buses wired together externally
+//!
+//! Bus `A` is the controller and bus `B` the target at [`LOOPBACK_ADDR`],
+//! serviced through the [`interrupts`] registry. The wiring is probed with a
+//! quick (zero-length) write first; without an ACK the suite is reported as
+//! skipped.
+//!
+//! Both buses must be among 0-3 (the driver only has DMA buffers for those),
+//! and `B` cannot be bus 0, whose vector the target test owns.
+
+use super::TestResult;
+use crate::common::NoOpLogger;
+use crate::i2c::ast1060_i2c::{Ast1060I2c, Error, Instance};
+use crate::i2c::common::{I2cConfigBuilder, I2cSpeed, I2cXferMode};
+use crate::i2c::i2c_controller::{HardwareInterface, I2cController};
+use crate::i2c::write_queue::{QueuedI2c, WriteStatus};
+use crate::interrupts;
+use crate::pinctrl::{Pinctrl, PinctrlPin};
+use crate::uart::UartController;
+use embedded_hal::i2c::{ErrorKind, I2c, NoAcknowledgeSource, Operation};
+use embedded_io::Write;
+use proposed_traits::i2c_target::{
+    I2CCoreTarget, ReadTarget, RegisterAccess, WriteReadTarget, WriteTarget,
+};
+
+/// Address the target bus answers to
+pub const LOOPBACK_ADDR: u8 = 0x3a;
+/// Address nothing on the loopback pair answers to
+const ABSENT_ADDR: u8 = 0x3b;
+const MEM_SIZE: usize = 32;
+const LENGTHS: [usize; 4] = [1, 4, 16, MEM_SIZE - 1];
+/// Target side off/on cycles of the mode toggle check
+const TOGGLES: u8 = 100;
+
+#[derive(Debug)]
+pub struct LoopbackTargetError;
+
+impl embedded_hal::i2c::Error for LoopbackTargetError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// Register-file target: the first byte written after a start selects the
+/// offset and further bytes are stored from there. A read after a repeated
+/// start continues from the offset just written; a read in a new
+/// transaction starts at 0.
+pub struct LoopbackTarget {
+    mem: [u8; MEM_SIZE],
+    offset: usize,
+    expect_offset: bool,
+}
+
+impl LoopbackTarget {
+    const fn new() -> Self {
+        Self {
+            mem: [0; MEM_SIZE],
+            offset: 0,
+            expect_offset: false,
+        }
+    }
+}
+
+impl embedded_hal::i2c::ErrorType for LoopbackTarget {
+    type Error = LoopbackTargetError;
+}
+
+impl I2CCoreTarget for LoopbackTarget {
+    fn init(&mut self, address: u8) -> Result<(), Self::Error> {
+        if address == LOOPBACK_ADDR {
+            Ok(())
+        } else {
+            Err(LoopbackTargetError)
+        }
+    }
+    fn on_transaction_start(&mut self, repeated: bool) {
+        self.expect_offset = true;
+        if !repeated {
+            self.offset = 0;
+        }
+    }
+    fn on_stop(&mut self) {}
+    fn on_address_match(&mut self, address: u8) -> bool {
+        address == LOOPBACK_ADDR
+    }
+}
+
+impl ReadTarget for LoopbackTarget {
+    fn on_read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        for b in buffer.iter_mut() {
+            *b = self.mem[self.offset];
+            self.offset = (self.offset + 1) % MEM_SIZE;
+        }
+        Ok(buffer.len())
+    }
+}
+
+impl WriteTarget for LoopbackTarget {
+    fn on_write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        for &b in data {
+            if self.expect_offset {
+                self.offset = usize::from(b) % MEM_SIZE;
+                self.expect_offset = false;
+            } else {
+                self.mem[self.offset] = b;
+                self.offset = (self.offset + 1) % MEM_SIZE;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WriteReadTarget for LoopbackTarget {}
+
+impl RegisterAccess for LoopbackTarget {
+    fn write_register(&mut self, address: u8, data: u8) -> Result<(), Self::Error> {
+        *self
+            .mem
+            .get_mut(usize::from(address))
+            .ok_or(LoopbackTargetError)? = data;
+        Ok(())
+    }
+    fn read_register(&mut self, address: u8, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        buffer[0] = *self
+            .mem
+            .get(usize::from(address))
+            .ok_or(LoopbackTargetError)?;
+        Ok(1)
+    }
+}
+
+/// Target side of the pair; the caller provides the `'static` slot so the
+/// bus can be handed to the interrupt registry
+pub type LoopbackTargetBus<B> = Ast1060I2c<'static, B, LoopbackTarget, NoOpLogger>;
+
+type Controller<A> = I2cController<Ast1060I2c<'static, A, LoopbackTarget, NoOpLogger>, NoOpLogger>;
+
+static mut TARGET: LoopbackTarget = LoopbackTarget::new();
+
+fn check(uart: &mut UartController<'_>, tally: &mut TestResult, name: &str, ok: bool) {
+    if tally.check(ok) {
+        writeln!(uart, "i2c loopback {name}: PASSED\r").unwrap();
+    } else {
+        writeln!(uart, "i2c loopback {name}: FAILED\r").unwrap();
+    }
+}
+
+/// Runs the loopback suite with bus `A` as controller and bus `B` as target
+pub fn run_i2c_loopback_tests<A: Instance, B: Instance>(
+    uart: &mut UartController<'_>,
+    pins_a: &[PinctrlPin],
+    pins_b: &[PinctrlPin],
+    target_bus: &'static mut Option<LoopbackTargetBus<B>>,
+) -> TestResult {
+    writeln!(
+        uart,
+        "\r\n####### I2C loopback test: i2c{} -> i2c{} #######\r\n",
+        A::BUS_NUM,
+        B::BUS_NUM
+    )
+    .unwrap();
+    Pinctrl::apply_pinctrl_group(pins_a);
+    Pinctrl::apply_pinctrl_group(pins_b);
+
+    let mut tally = TestResult::new();
+    for mode in [
+        I2cXferMode::DmaMode,
+        I2cXferMode::BuffMode,
+        I2cXferMode::ByteMode,
+    ] {
+        let target: *mut LoopbackTargetBus<B> = target_bus.insert(Ast1060I2c::new(NoOpLogger {}));
+        let mut controller: Controller<A> = I2cController {
+            hardware: Ast1060I2c::new(NoOpLogger {}),
+            config: config(mode),
+            logger: NoOpLogger {},
+        };
+        let attached = unsafe { attach_target(&mut *target, mode) };
+        let ready = attached.is_ok() && controller.hardware.init(&mut controller.config).is_ok();
+        if !ready {
+            writeln!(uart, "i2c loopback {mode:?}: init FAILED\r").unwrap();
+            tally.check(false);
+        } else if probe(&mut controller) {
+            run_mode(uart, &mut tally, &mut controller, mode);
+            toggle_target_mode(uart, &mut tally, &mut controller, unsafe { &mut *target });
+        } else {
+            writeln!(uart, "i2c loopback: no wiring detected, SKIPPED\r").unwrap();
+            let _ = interrupts::unregister(B::IRQ);
+            return tally;
+        }
+        let _ = interrupts::unregister(B::IRQ);
+        unsafe {
+            let _ = (*target).i2c_aspeed_slave_unregister();
+        }
+    }
+    writeln!(
+        uart,
+        "i2c loopback: {} passed, {} failed\r",
+        tally.passed, tally.failed
+    )
+    .unwrap();
+    tally
+}
+
+fn config(mode: I2cXferMode) -> crate::i2c::common::I2cConfig {
+    I2cConfigBuilder::new()
+        .xfer_mode(mode)
+        .multi_master(true)
+        .smbus_timeout(true)
+        .smbus_alert(false)
+        .speed(I2cSpeed::Standard)
+        .build()
+}
+
+/// Initializes the target bus in `mode`, registers [`TARGET`] on it and
+/// hands it to the interrupt registry
+unsafe fn attach_target<B: Instance>(
+    bus: &'static mut LoopbackTargetBus<B>,
+    mode: I2cXferMode,
+) -> Result<(), Error> {
+    let target = &mut *core::ptr::addr_of_mut!(TARGET);
+    *target = LoopbackTarget::new();
+    bus.init(&mut config(mode))?;
+    let handle: *mut LoopbackTargetBus<B> = bus;
+    (*handle).i2c_aspeed_slave_register(LOOPBACK_ADDR, Some(target))?;
+    interrupts::register(&mut *handle, 0x40).map_err(|_| Error::Invalid)
+}
+
+/// Whether a quick write to the target is ACKed
+fn probe<A: Instance>(controller: &mut Controller<A>) -> bool {
+    controller.write(LOOPBACK_ADDR, &[]).is_ok()
+}
+
+fn is_address_nack(result: &Result<(), Error>) -> bool {
+    matches!(
+        result,
+        Err(
+            Error::NoAcknowledge(NoAcknowledgeSource::Address | NoAcknowledgeSource::Unknown)
+                | Error::Timeout
+        )
+    )
+}
+
+fn run_mode<A: Instance>(
+    uart: &mut UartController<'_>,
+    tally: &mut TestResult,
+    controller: &mut Controller<A>,
+    mode: I2cXferMode,
+) {
+    writeln!(uart, "\r\n--- {mode:?} ---\r").unwrap();
+    for len in LENGTHS {
+        let mut pattern = [0u8; MEM_SIZE];
+        for (i, b) in pattern.iter_mut().enumerate() {
+            *b = u8::try_from(i).unwrap() ^ 0xa5;
+        }
+        let mut frame = [0u8; MEM_SIZE + 1];
+        frame[1..=len].copy_from_slice(&pattern[..len]);
+
+        // offset 0, then the data
+        let written = controller.write(LOOPBACK_ADDR, &frame[..=len]).is_ok();
+        check(uart, tally, "write", written);
+
+        // repeated start between the offset and the read
+        let mut back = [0u8; MEM_SIZE];
+        let ok = controller
+            .write_read(LOOPBACK_ADDR, &[0], &mut back[..len])
+            .is_ok()
+            && back[..len] == pattern[..len];
+        check(uart, tally, "write_read", ok);
+
+        // plain read continues from the offset set by a separate write
+        let mut back = [0u8; MEM_SIZE];
+        let ok = controller.write(LOOPBACK_ADDR, &[0]).is_ok()
+            && controller.read(LOOPBACK_ADDR, &mut back[..len]).is_ok()
+            && back[..len] == pattern[..len];
+        check(uart, tally, "read", ok);
+    }
+
+    // operations run as separate transactions, so the read starts over at 0
+    let mut reg = [0u8];
+    let ok = controller
+        .transaction(
+            LOOPBACK_ADDR,
+            &mut [Operation::Write(&[1]), Operation::Read(&mut reg)],
+        )
+        .is_ok()
+        && reg[0] == 0xa5;
+    check(uart, tally, "transaction", ok);
+
+    // the same offset with and without a repeated start before the read
+    let mut repeated = [0u8; 2];
+    let mut fresh = [0u8; 2];
+    let ok = controller
+        .write_read(LOOPBACK_ADDR, &[4], &mut repeated)
+        .is_ok()
+        && controller.write(LOOPBACK_ADDR, &[4]).is_ok()
+        && controller.read(LOOPBACK_ADDR, &mut fresh).is_ok()
+        && repeated == [4 ^ 0xa5, 5 ^ 0xa5]
+        && fresh == [0xa5, 1 ^ 0xa5];
+    check(uart, tally, "repeated start keeps offset", ok);
+
+    // pointer and data in one transaction, from the requested register on
+    let mut block = [0u8; 3];
+    let ok = controller
+        .hardware
+        .read_register_block(LOOPBACK_ADDR, 4, &mut block)
+        .is_ok()
+        && block == [4 ^ 0xa5, 5 ^ 0xa5, 6 ^ 0xa5];
+    check(uart, tally, "read_register_block", ok);
+    let nack = controller
+        .hardware
+        .read_register_block(ABSENT_ADDR, 4, &mut block);
+    check(
+        uart,
+        tally,
+        "read_register_block from absent address",
+        is_address_nack(&nack),
+    );
+    let empty = controller
+        .hardware
+        .read_register_block(LOOPBACK_ADDR, 4, &mut []);
+    check(
+        uart,
+        tally,
+        "read_register_block empty",
+        empty == Err(Error::Invalid),
+    );
+
+    let nack = controller.write(ABSENT_ADDR, &[0]);
+    check(
+        uart,
+        tally,
+        "nack to absent address",
+        is_address_nack(&nack),
+    );
+
+    // quick commands: address and R/W bit only
+    check(
+        uart,
+        tally,
+        "quick write",
+        controller.write(LOOPBACK_ADDR, &[]).is_ok(),
+    );
+    check(
+        uart,
+        tally,
+        "quick read",
+        controller.read(LOOPBACK_ADDR, &mut []).is_ok(),
+    );
+    let nack = controller.write(ABSENT_ADDR, &[]);
+    check(
+        uart,
+        tally,
+        "quick write to absent address",
+        is_address_nack(&nack),
+    );
+    let nack = controller.read(ABSENT_ADDR, &mut []);
+    check(
+        uart,
+        tally,
+        "quick read from absent address",
+        is_address_nack(&nack),
+    );
+    // the bus is usable after a quick command
+    let mut back = [0u8; 1];
+    let ok = controller
+        .write_read(LOOPBACK_ADDR, &[0], &mut back)
+        .is_ok()
+        && back == [0xa5];
+    check(uart, tally, "transfer after quick command", ok);
+
+    for speed in [I2cSpeed::Fast, I2cSpeed::FastPlus] {
+        let mut back = [0u8; 4];
+        let ok = controller
+            .with_speed(speed, |bus| bus.write_read(LOOPBACK_ADDR, &[0], &mut back))
+            .is_ok()
+            && back == [0xa5, 0xa4, 0xa7, 0xa6];
+        check(uart, tally, "speed change", ok);
+    }
+
+    // reconfigure in place and back
+    let mut back = [0u8; 2];
+    let ok = controller.set_speed(I2cSpeed::Fast).is_ok()
+        && controller.speed() == I2cSpeed::Fast
+        && controller
+            .write_read(LOOPBACK_ADDR, &[0], &mut back)
+            .is_ok()
+        && controller.set_speed(I2cSpeed::Standard).is_ok()
+        && back == [0xa5, 0xa4];
+    check(uart, tally, "set_speed", ok);
+    let other = if mode == I2cXferMode::ByteMode {
+        I2cXferMode::BuffMode
+    } else {
+        I2cXferMode::ByteMode
+    };
+    let mut back = [0u8; 2];
+    let ok = controller.set_xfer_mode(other).is_ok()
+        && controller.xfer_mode() == other
+        && controller
+            .write_read(LOOPBACK_ADDR, &[0], &mut back)
+            .is_ok()
+        && controller.set_xfer_mode(mode).is_ok()
+        && back == [0xa5, 0xa4];
+    check(uart, tally, "set_xfer_mode", ok);
+
+    // one message in the other mode, the controller mode is kept
+    let mut back = [0u8; 2];
+    let ok = controller
+        .write_with_mode(LOOPBACK_ADDR, &[0, 0x11, 0x22], other)
+        .is_ok()
+        && controller.xfer_mode() == mode
+        && controller
+            .read_with_mode(LOOPBACK_ADDR, &mut back, other)
+            .is_ok()
+        && back == [0x11, 0x22];
+    check(uart, tally, "write_with_mode", ok);
+
+    queued_writes(uart, tally, controller);
+}
+
+/// Five queued writes, the third to an absent address: only that one
+/// fails, and the blocking read after them sees the last two, so the
+/// queue ran in order and was flushed first
+fn queued_writes<A: Instance>(
+    uart: &mut UartController<'_>,
+    tally: &mut TestResult,
+    controller: &mut Controller<A>,
+) {
+    let mut queue = QueuedI2c::<_, 8, 4>::new(controller);
+    let writes: [(u8, &[u8]); 5] = [
+        (LOOPBACK_ADDR, &[8, 0x11]),
+        (LOOPBACK_ADDR, &[9, 0x22]),
+        (ABSENT_ADDR, &[0]),
+        (LOOPBACK_ADDR, &[8, 0x33]),
+        (LOOPBACK_ADDR, &[9, 0x44]),
+    ];
+    let mut tickets = [None; 5];
+    for (ticket, (addr, data)) in tickets.iter_mut().zip(writes) {
+        *ticket = queue.enqueue_write(addr, data).ok();
+    }
+    let mut back = [0u8; 2];
+    let read = queue.write_read(LOOPBACK_ADDR, &[8], &mut back).is_ok();
+
+    let outcome = |i: usize| match tickets[i].and_then(|t| queue.status(t)) {
+        Some(WriteStatus::Done(result)) => Some(result),
+        _ => None,
+    };
+    let ok = read
+        && back == [0x33, 0x44]
+        && [0, 1, 3, 4].iter().all(|&i| outcome(i) == Some(Ok(())))
+        && outcome(2).is_some_and(|r| is_address_nack(&r));
+    check(uart, tally, "queued writes", ok);
+}
+
+/// Control, AC timing, interrupt enable and target address registers,
+/// which switching the target side on and off must leave as they were
+fn bus_regs<B: Instance>(bus: &LoopbackTargetBus<B>) -> [u32; 5] {
+    [
+        bus.i2c.i2cc00().read().bits(),
+        bus.i2c.i2cc04().read().bits(),
+        bus.i2c.i2cm10().read().bits(),
+        bus.i2c.i2cs20().read().bits(),
+        bus.i2c.i2cs40().read().bits(),
+    ]
+}
+
+/// Switches the target side of `B` off and on [`TOGGLES`] times. While it
+/// is off, `B` is not addressable but runs a master transfer of its own;
+/// while it is on, a write and read from `A` must reach the target.
+fn toggle_target_mode<A: Instance, B: Instance>(
+    uart: &mut UartController<'_>,
+    tally: &mut TestResult,
+    controller: &mut Controller<A>,
+    target: &mut LoopbackTargetBus<B>,
+) {
+    let before = bus_regs(target);
+    let mut failures = 0u32;
+    for i in 0..TOGGLES {
+        let off = target.disable_slave_mode().is_ok()
+            && !target.slave_mode_enabled()
+            && is_address_nack(&controller.write(LOOPBACK_ADDR, &[]));
+        // `B` masters with its target side off and on; its interrupt is
+        // masked meanwhile, as the handler would take the NACK as its own
+        let on_master = |target: &mut LoopbackTargetBus<B>| {
+            cortex_m::interrupt::free(|_| {
+                let nack = is_address_nack(&target.write(ABSENT_ADDR, &[]));
+                cortex_m::peripheral::NVIC::unpend(B::IRQ);
+                nack
+            })
+        };
+        let master_off = on_master(target);
+
+        let mut back = [0u8; 1];
+        let on = target.enable_slave_mode().is_ok()
+            && target.slave_mode_enabled()
+            && on_master(target)
+            && controller.write(LOOPBACK_ADDR, &[1, i]).is_ok()
+            && controller
+                .write_read(LOOPBACK_ADDR, &[1], &mut back)
+                .is_ok()
+            && back == [i];
+        let kept = bus_regs(target) == before;
+        if !(off && master_off && on && kept) {
+            if failures == 0 {
+                writeln!(
+                    uart,
+                    "i2c loopback toggle {i}: off {off}, master {master_off}, on {on}, registers kept {kept}\r"
+                )
+                .unwrap();
+            }
+            failures += 1;
+        }
+    }
+    check(uart, tally, "target mode toggle", failures == 0);
+}
@@ -0,0 +1,122 @@
+// Licensed under the Apache-2.0 license
+
+use super::TestResult;
+use crate::interrupts::{self, IrqHandler};
+use crate::timer::{TimerController, TimerType};
+use crate::uart::{UartController, UartRxIrq};
+use ast1060_pac::{Interrupt, Timer, Uart};
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicU32, Ordering};
+use cortex_m::interrupt::Mutex;
+use embedded_hal_old::timer::CountDown;
+use embedded_io::Write;
+use fugit::MicrosDurationU32;
+use heapless::spsc::Queue;
+
+/// Sent through the UART in loopback; shorter than the receive FIFO
+const PATTERN: &[u8] = b"irq-loop";
+const RX_QUEUE_LEN: usize = 16;
+
+static TIMER_COUNT: AtomicU32 = AtomicU32::new(0);
+
+static mut RX_QUEUE: Queue<u8, RX_QUEUE_LEN> = Queue::new();
+static mut UART_RX: Option<UartRxIrq<RX_QUEUE_LEN>> = None;
+static mut TIMER_IRQ: TimerIrqHandler = TimerIrqHandler;
+static TIMER_INSTANCE: Mutex<RefCell<Option<TimerController<Timer>>>> =
+    Mutex::new(RefCell::new(None));
+
+/// Runs the interrupt of the timer in `TIMER_INSTANCE`; only this stand-in
+/// is registered, so the test and the registry never share a borrow
+struct TimerIrqHandler;
+
+impl IrqHandler for TimerIrqHandler {
+    fn irq_number(&self) -> Interrupt {
+        Interrupt::timer
+    }
+
+    fn on_irq(&mut self) {
+        cortex_m::interrupt::free(|cs| {
+            if let Some(timer) = TIMER_INSTANCE.borrow(cs).borrow_mut().as_mut() {
+                timer.handle_interrupt();
+            }
+        });
+    }
+}
+
+fn timer_callback() {
+    TIMER_COUNT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Checks both the UART and the timer interrupt reach their registered
+/// handlers. The UART is put in loopback and fed a pattern, so no input is
+/// needed; the console is silent until it is taken out again.
+pub fn run_interrupts_tests(uart: &mut UartController) -> TestResult {
+    writeln!(uart, "\r\nRunning interrupt dispatch test").unwrap();
+    uart.flush().unwrap();
+
+    // The timer ISR test may have left its own handler registered
+    let _ = interrupts::unregister(Interrupt::timer);
+
+    let mut timer = TimerController::<Timer>::new(50); // tick_per_us
+    timer.set_callback(Some(timer_callback), TimerType::Periodic);
+    timer.try_start(MicrosDurationU32::millis(100)).unwrap();
+    cortex_m::interrupt::free(|cs| TIMER_INSTANCE.borrow(cs).replace(Some(timer)));
+
+    // Drop whatever is already in the receive FIFO
+    let regs = unsafe { &*Uart::ptr() };
+    while regs.uartlsr().read().dr().bit_is_set() {
+        let _ = regs.uartrbr().read().uartrbr().bits();
+    }
+    let (producer, mut consumer) = unsafe { (*core::ptr::addr_of_mut!(RX_QUEUE)).split() };
+    uart.enable_rx_interrupt();
+    uart.set_loopback(true);
+    let registered = unsafe {
+        let rx = (*core::ptr::addr_of_mut!(UART_RX)).insert(UartRxIrq::new(producer));
+        interrupts::register(rx, 0x40)
+            .and_then(|()| interrupts::register(&mut *core::ptr::addr_of_mut!(TIMER_IRQ), 0x80))
+    };
+
+    let mut received = [0u8; PATTERN.len()];
+    let mut rx = 0;
+    if registered.is_ok() {
+        for &byte in PATTERN {
+            uart.send_byte_fifo(byte);
+        }
+        // 1 s at the 100 ms timer period
+        while rx < PATTERN.len() && TIMER_COUNT.load(Ordering::Relaxed) < 10 {
+            if let Some(byte) = consumer.dequeue() {
+                received[rx] = byte;
+                rx += 1;
+            }
+        }
+    }
+
+    uart.flush().unwrap();
+    uart.set_loopback(false);
+    let _ = interrupts::unregister(Interrupt::uart);
+    let _ = interrupts::unregister(Interrupt::timer);
+    cortex_m::interrupt::free(|cs| {
+        if let Some(timer) = TIMER_INSTANCE.borrow(cs).borrow_mut().as_mut() {
+            timer.stop();
+        }
+    });
+
+    if let Err(e) = registered {
+        writeln!(uart, "\rinterrupt register failed: {e:?}").unwrap();
+    }
+    let ticks = TIMER_COUNT.load(Ordering::Relaxed);
+    writeln!(
+        uart,
+        "\ruart rx: {rx}/{}, timer: {ticks}, spurious: {}",
+        PATTERN.len(),
+        interrupts::spurious_count()
+    )
+    .unwrap();
+    let ok = registered.is_ok() && received[..] == *PATTERN && ticks > 0;
+    if ok {
+        writeln!(uart, "\rinterrupt dispatch: PASSED").unwrap();
+    } else {
+        writeln!(uart, "\rinterrupt dispatch: FAILED").unwrap();
+    }
+    TestResult::of(ok)
+}
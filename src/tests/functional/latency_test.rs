@@ -0,0 +1,191 @@
+// Licensed under the Apache-2.0 license
+
+use super::TestResult;
+use crate::common::{Deadline, TickClock};
+use crate::gpio::{gpiob, GpioExt, Input, InterruptMode, PullDown};
+use crate::hace_controller::HaceController;
+use crate::interrupts;
+use crate::latency::{mark_gpio_edge, GpioProbe, TimerProbe};
+use crate::pinctrl;
+use crate::uart::UartController;
+use ast1060_pac::Interrupt;
+use core::time::Duration;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_io::Write;
+use fugit::MicrosDurationU32;
+
+/// Timer period; short enough to collect samples quickly, long enough that
+/// a SHA-512 request in flight does not swallow whole periods
+const PERIOD_US: u32 = 500;
+const SAMPLES: u32 = 1000;
+/// 50 ns buckets cover 0..3.2 us
+const BUCKET_NS: u32 = 50;
+
+static mut PROBE: Option<TimerProbe> = None;
+static mut GPIO_PROBE: Option<GpioProbe> = None;
+/// Loopback input, kept here so the probe's `clear` can reach it
+static mut GPIO_IN: Option<gpiob::PB1<Input<PullDown>>> = None;
+
+fn probe() -> &'static mut TimerProbe {
+    unsafe { (*core::ptr::addr_of_mut!(PROBE)).as_mut().unwrap() }
+}
+
+fn gpio_probe() -> &'static mut GpioProbe {
+    unsafe { (*core::ptr::addr_of_mut!(GPIO_PROBE)).as_mut().unwrap() }
+}
+
+fn clear_gpio_in() {
+    if let Some(pin) = unsafe { (*core::ptr::addr_of!(GPIO_IN)).as_ref() } {
+        pin.clear_interrupt();
+    }
+}
+
+/// Runs `load` until the probe has [`SAMPLES`] latency samples or two
+/// seconds pass, then prints the latency and jitter reports for `label`
+fn run_timer_mode(
+    uart: &mut UartController,
+    cpu_hz: u32,
+    label: &str,
+    mut load: impl FnMut(),
+) -> TestResult {
+    // the histograms are only touched with the handler masked
+    cortex_m::interrupt::free(|_| probe().start(MicrosDurationU32::micros(PERIOD_US)).unwrap());
+
+    let deadline = Deadline::new(TickClock::dwt(cpu_hz), Duration::from_secs(2));
+    while cortex_m::interrupt::free(|_| probe().latency.count()) < SAMPLES && !deadline.is_expired()
+    {
+        load();
+    }
+    let (latency, jitter) = cortex_m::interrupt::free(|_| {
+        probe().stop();
+        (probe().latency, probe().jitter)
+    });
+
+    writeln!(uart, "\rtimer latency {label}: {latency}").unwrap();
+    writeln!(uart, "\rtimer jitter {label}: {jitter}").unwrap();
+    let ok = latency.count() >= SAMPLES;
+    if ok {
+        writeln!(uart, "\rtimer latency {label}: PASSED").unwrap();
+    } else {
+        writeln!(uart, "\rtimer latency {label}: FAILED").unwrap();
+    }
+    TestResult::of(ok)
+}
+
+/// Measures the time from a rising edge on PB0 to entry of the GPIO handler
+/// seeing it on PB1. The pins must be wired together; the mode is skipped
+/// when they are not.
+fn run_gpio_mode(uart: &mut UartController, gpiob: gpiob::GPIOB, cpu_hz: u32) -> TestResult {
+    pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_GPIOB0);
+    pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_GPIOB1);
+    let pins = gpiob.split();
+    let mut out = pins.pb0.into_push_pull_output();
+    let mut input = pins.pb1.into_pull_down_input();
+
+    let _ = out.set_high();
+    let wired = input.is_high().unwrap_or(false);
+    let _ = out.set_low();
+    if !(wired && input.is_low().unwrap_or(false)) {
+        writeln!(uart, "\rgpio latency: PB0 and PB1 not wired, SKIPPED").unwrap();
+        return TestResult::new();
+    }
+
+    input.set_interrupt_mode(InterruptMode::EdgeRising);
+    input.clear_interrupt();
+    let registered = unsafe {
+        GPIO_IN = Some(input);
+        GPIO_PROBE = Some(GpioProbe::new(
+            Interrupt::gpio,
+            clear_gpio_in,
+            cpu_hz,
+            BUCKET_NS,
+        ));
+        interrupts::register(gpio_probe(), 0x80)
+    };
+    if let Err(e) = registered {
+        writeln!(uart, "\rinterrupt register failed: {e:?}").unwrap();
+        writeln!(uart, "\rgpio latency: FAILED").unwrap();
+        return TestResult::of(false);
+    }
+
+    let clock = TickClock::dwt(cpu_hz);
+    let deadline = Deadline::new(clock, Duration::from_secs(2));
+    let count = || cortex_m::interrupt::free(|_| gpio_probe().latency.count());
+    while count() < SAMPLES && !deadline.is_expired() {
+        let before = count();
+        mark_gpio_edge();
+        let _ = out.set_high();
+        let edge = Deadline::new(clock, Duration::from_millis(1));
+        while count() == before && !edge.is_expired() {}
+        let _ = out.set_low();
+    }
+
+    let _ = interrupts::unregister(Interrupt::gpio);
+    let latency = cortex_m::interrupt::free(|_| gpio_probe().latency);
+    if let Some(pin) = unsafe { (*core::ptr::addr_of_mut!(GPIO_IN)).as_mut() } {
+        pin.set_interrupt_mode(InterruptMode::Disabled);
+    }
+
+    writeln!(uart, "\rgpio latency: {latency}").unwrap();
+    let ok = latency.count() >= SAMPLES;
+    if ok {
+        writeln!(uart, "\rgpio latency: PASSED").unwrap();
+    } else {
+        writeln!(uart, "\rgpio latency: FAILED").unwrap();
+    }
+    TestResult::of(ok)
+}
+
+/// Keeps the hash engine busy with back-to-back SHA-512 requests
+#[cfg(feature = "sha512")]
+fn sha512_load(mut hace: HaceController) -> impl FnMut() {
+    use crate::hash::Sha512;
+    use proposed_traits::digest::{DigestInit, DigestOp};
+
+    let data = [0xa5u8; 1024];
+    move || {
+        let mut ctx = hace.init(Sha512).unwrap();
+        ctx.update(&data).unwrap();
+        let _ = ctx.finalize().unwrap();
+    }
+}
+
+/// Measures timer interrupt latency and jitter with the CPU idle and, when
+/// SHA-512 is enabled, with the hash engine running, then GPIO interrupt
+/// latency over the PB0 to PB1 loopback
+pub fn run_latency_tests(
+    uart: &mut UartController,
+    hace: HaceController,
+    gpiob: gpiob::GPIOB,
+    cpu_hz: u32,
+) -> TestResult {
+    writeln!(uart, "\r\nRunning interrupt latency tests").unwrap();
+
+    // An earlier test may have left its own timer handler registered
+    let _ = interrupts::unregister(Interrupt::timer);
+    let registered = unsafe {
+        PROBE = Some(TimerProbe::new(50, cpu_hz, BUCKET_NS)); // tick_per_us
+        interrupts::register(probe(), 0x80)
+    };
+    if let Err(e) = registered {
+        writeln!(uart, "\rinterrupt register failed: {e:?}").unwrap();
+        writeln!(uart, "\rtimer latency: FAILED").unwrap();
+        return TestResult::of(false);
+    }
+
+    let mut result = run_timer_mode(uart, cpu_hz, "idle", cortex_m::asm::nop);
+
+    #[cfg(feature = "sha512")]
+    {
+        result += run_timer_mode(uart, cpu_hz, "sha512", sha512_load(hace));
+    }
+    #[cfg(not(feature = "sha512"))]
+    {
+        drop(hace);
+        writeln!(uart, "\rtimer latency sha512: SKIPPED").unwrap();
+    }
+
+    let _ = interrupts::unregister(Interrupt::timer);
+    result += run_gpio_mode(uart, gpiob, cpu_hz);
+    result
+}
@@ -0,0 +1,88 @@
+// Licensed under the Apache-2.0 license
+
+use super::TestResult;
+use crate::hace_controller::HaceController;
+use crate::hash_owned::{DigestExt, Sha2_256};
+use crate::mock_flash::MockFlash;
+use crate::spi::norflashblockdevice::BlockAddrUsize;
+use crate::uart::UartController;
+use crate::verify::{measure_region, MeasureError};
+use embedded_hal_old::watchdog::Watchdog;
+use embedded_io::Write;
+use openprot_hal_blocking::digest::owned::{DigestInit, DigestOp};
+use proposed_traits::block_device::{BlockDevice, BlockRange};
+
+const SECTOR: usize = 4096;
+const PAGE: usize = 256;
+
+/// Counts feeds instead of touching a hardware watchdog
+#[derive(Default)]
+struct FeedCounter(usize);
+
+impl Watchdog for FeedCounter {
+    type Error = ();
+
+    fn try_feed(&mut self) -> Result<(), Self::Error> {
+        self.0 += 1;
+        Ok(())
+    }
+}
+
+fn page(index: usize) -> [u8; PAGE] {
+    let mut page = [0u8; PAGE];
+    for (i, b) in page.iter_mut().enumerate() {
+        *b = u8::try_from((index * PAGE + i) % 251).unwrap();
+    }
+    page
+}
+
+/// Measures two sectors of a mock flash and checks the digest, length and
+/// watchdog feeds against hashing the same pages directly
+pub fn run_measure_region_test(
+    uart: &mut UartController,
+    hace: HaceController,
+) -> (TestResult, HaceController) {
+    writeln!(uart, "\r\nRunning measure_region test...").unwrap();
+    let mut flash = MockFlash::<2, SECTOR>::new(PAGE);
+    let pages = 2 * SECTOR / PAGE;
+    for i in 0..pages {
+        flash.program(BlockAddrUsize(i * PAGE), &page(i)).unwrap();
+    }
+
+    let mut ctx = hace.init(Sha2_256).unwrap();
+    for i in 0..pages {
+        ctx = ctx.update(&page(i)).unwrap();
+    }
+    let (expected, hace) = ctx.finalize().unwrap();
+
+    let mut wdt = FeedCounter::default();
+    let whole = BlockRange {
+        start: BlockAddrUsize(0),
+        count: 2,
+    };
+    let (pass, hace) = match measure_region(&mut flash, &mut wdt, hace, whole, Sha2_256) {
+        Ok((digest, len, hace)) => (
+            digest.to_bytes() == expected.to_bytes()
+                && len == u64::try_from(2 * SECTOR).unwrap()
+                && wdt.0 == 2,
+            hace,
+        ),
+        Err((_, hace)) => (false, hace),
+    };
+
+    let past_end = BlockRange {
+        start: BlockAddrUsize(SECTOR),
+        count: 2,
+    };
+    let (rejected, hace) = match measure_region(&mut flash, &mut wdt, hace, past_end, Sha2_256) {
+        Ok((_, _, hace)) => (false, hace),
+        Err((e, hace)) => (matches!(e, MeasureError::OutOfBounds), hace),
+    };
+
+    if pass && rejected {
+        writeln!(uart, "\r\nmeasure_region: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nmeasure_region: Test failed!").unwrap();
+    }
+    (TestResult::from_checks(&[pass, rejected]), hace)
+}
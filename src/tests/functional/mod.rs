@@ -1,10 +1,134 @@
 // Licensed under the Apache-2.0 license
 
+pub mod board_test;
+pub mod coop_test;
+#[cfg(feature = "ecdsa")]
 pub mod ecdsa_test;
 pub mod gpio_test;
 pub mod hash_test;
+#[cfg(feature = "hmac")]
 pub mod hmac_test;
+#[cfg(feature = "hmac")]
+pub mod host_auth_test;
+pub mod host_policy_test;
+#[cfg(all(feature = "rt", feature = "i2c_target"))]
+pub mod i2c_loopback_test;
 pub mod i2c_test;
+#[cfg(feature = "rt")]
+pub mod interrupts_test;
+#[cfg(feature = "rt")]
+pub mod latency_test;
+#[cfg(feature = "test-utils")]
+pub mod measure_test;
+#[cfg(feature = "rsa")]
 pub mod rsa_test;
+#[cfg(feature = "rsa")]
 pub mod rsa_test_vec;
+pub mod session_stress_test;
+#[cfg(feature = "rt")]
+pub mod soft_watchdog_test;
+pub mod spi_test;
 pub mod timer_test;
+
+use crate::uart::UartController;
+use core::ops::AddAssign;
+use embedded_io::Write;
+
+/// Checks passed and failed by one functional test
+#[must_use]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TestResult {
+    pub passed: u32,
+    pub failed: u32,
+}
+
+impl TestResult {
+    pub const fn new() -> Self {
+        Self {
+            passed: 0,
+            failed: 0,
+        }
+    }
+
+    /// Result of a single check
+    pub fn of(ok: bool) -> Self {
+        Self {
+            passed: u32::from(ok),
+            failed: u32::from(!ok),
+        }
+    }
+
+    /// One check per entry of `checks`
+    pub fn from_checks(checks: &[bool]) -> Self {
+        let mut result = Self::new();
+        for &ok in checks {
+            result.check(ok);
+        }
+        result
+    }
+
+    /// Counts one check and hands `ok` back
+    pub fn check(&mut self, ok: bool) -> bool {
+        *self += Self::of(ok);
+        ok
+    }
+
+    #[must_use]
+    pub const fn is_ok(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+impl AddAssign for TestResult {
+    fn add_assign(&mut self, other: Self) {
+        self.passed += other.passed;
+        self.failed += other.failed;
+    }
+}
+
+/// Totals of a functional test run, for tooling that gates on the outcome
+#[must_use]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TestSummary {
+    pub total: TestResult,
+    pub suites: u32,
+    /// Suites with at least one failed check
+    pub failed_suites: u32,
+}
+
+impl TestSummary {
+    pub const fn new() -> Self {
+        Self {
+            total: TestResult::new(),
+            suites: 0,
+            failed_suites: 0,
+        }
+    }
+
+    /// Adds the result of one suite
+    pub fn add(&mut self, result: TestResult) {
+        self.total += result;
+        self.suites += 1;
+        if !result.is_ok() {
+            self.failed_suites += 1;
+        }
+    }
+
+    /// 0 when every check passed, otherwise the number of failed checks
+    #[must_use]
+    pub const fn status(&self) -> u32 {
+        self.total.failed
+    }
+
+    /// Prints the totals and a final `TEST SUMMARY: PASS` or `FAIL` line
+    pub fn report(&self, uart: &mut UartController<'_>) {
+        writeln!(
+            uart,
+            "\r\nfunctional tests: {} passed, {} failed in {} of {} suites\r",
+            self.total.passed, self.total.failed, self.failed_suites, self.suites
+        )
+        .unwrap();
+        let verdict = if self.status() == 0 { "PASS" } else { "FAIL" };
+        writeln!(uart, "TEST SUMMARY: {verdict}\r").unwrap();
+    }
+}
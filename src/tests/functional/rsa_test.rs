@@ -1,16 +1,19 @@
 // Licensed under the Apache-2.0 license
 
+use super::TestResult;
+use crate::common::HexDump;
 use crate::rsa::{RsaDigest, RsaPrivateKey, RsaPublicKey, RsaSignatureData};
 use crate::tests::functional::rsa_test_vec::RSA_VERIFY_TV;
 use crate::uart::UartController;
 use embedded_io::Write;
 use proposed_traits::rsa::{PaddingMode, RsaSign, RsaVerify};
 
-pub fn run_rsa_signing_tests<'a, T>(uart: &mut UartController, engine: &mut T)
+pub fn run_rsa_signing_tests<'a, T>(uart: &mut UartController, engine: &mut T) -> TestResult
 where
     T: RsaSign<PrivateKey = RsaPrivateKey<'a>, Message = RsaDigest, Signature = RsaSignatureData>,
 {
     writeln!(uart, "\rRunning RSA Signing tests...").unwrap();
+    let mut results = TestResult::new();
 
     for (i, vec) in RSA_VERIFY_TV.iter().enumerate() {
         let pubkey = RsaPrivateKey {
@@ -47,6 +50,7 @@ where
                 digest.len()
             )
             .ok();
+            results.check(false);
             continue;
         }
 
@@ -70,40 +74,46 @@ where
                     .ok();
                     writeln!(
                         uart,
-                        "\rRSA vector[{}] signature mismatch:\r\n  expected: {:02x?}\r\n  got     : {:02x?}",
+                        "\rRSA vector[{}] signature mismatch:\r\n  expected: {}\r\n  got     : {}",
                         i,
-                        &vec.signature[..vec.s_size],
-                        &signature.data[..signature.len]
+                        HexDump(&vec.signature[..vec.s_size]),
+                        HexDump(&signature.data[..signature.len])
                     )
-                        .ok();
+                    .ok();
+                    results.check(false);
                     continue;
                 }
                 if signature.data[..signature.len] != vec.signature[..vec.s_size] {
                     writeln!(
                         uart,
-                        "\rRSA vector[{}] signature mismatch:\r\n  expected: {:02x?}\r\n  got     : {:02x?}",
+                        "\rRSA vector[{}] signature mismatch:\r\n  expected: {}\r\n  got     : {}",
                         i,
-                        &vec.signature[..vec.s_size],
-                        &signature.data[..signature.len]
+                        HexDump(&vec.signature[..vec.s_size]),
+                        HexDump(&signature.data[..signature.len])
                     )
-                        .ok();
+                    .ok();
+                    results.check(false);
                     continue;
                 }
 
+                results.check(true);
                 writeln!(uart, "\rRSA vector[{i}] sign passed").ok();
             }
             Err(_err) => {
+                results.check(false);
                 writeln!(uart, "\rRSA vector[{i}] sign failed").ok();
             }
         }
     }
+    results
 }
 
-pub fn run_rsa_verification_tests<'a, T>(uart: &mut UartController, engine: &mut T)
+pub fn run_rsa_verification_tests<'a, T>(uart: &mut UartController, engine: &mut T) -> TestResult
 where
     T: RsaVerify<PublicKey = RsaPublicKey<'a>, Message = RsaDigest, Signature = RsaSignatureData>,
 {
     writeln!(uart, "\rRunning RSA verification tests...").unwrap();
+    let mut results = TestResult::new();
 
     for (i, vec) in RSA_VERIFY_TV.iter().enumerate() {
         let pubkey = RsaPublicKey {
@@ -139,6 +149,7 @@ where
                 digest.len()
             )
             .ok();
+            results.check(false);
             continue;
         }
 
@@ -157,6 +168,7 @@ where
                 i, vec.s_size
             )
             .ok();
+            results.check(false);
             continue;
         }
         sig[..vec.s_size].copy_from_slice(&vec.signature[..vec.s_size]);
@@ -170,21 +182,25 @@ where
 
         match result {
             Ok(_decrypted) => {
+                results.check(true);
                 writeln!(uart, "\rRSA vector[{i}] verify passed").ok();
             }
             Err(err) => {
+                results.check(false);
                 writeln!(uart, "\rRSA vector[{i}] verify failed: {err:?}").ok();
             }
         }
     }
+    results
 }
 
-pub fn run_rsa_tests<'a, T>(uart: &mut UartController, engine: &mut T)
+pub fn run_rsa_tests<'a, T>(uart: &mut UartController, engine: &mut T) -> TestResult
 where
     T: RsaVerify<PublicKey = RsaPublicKey<'a>, Message = RsaDigest, Signature = RsaSignatureData>
         + RsaSign<PrivateKey = RsaPrivateKey<'a>, Message = RsaDigest, Signature = RsaSignatureData>,
 {
     writeln!(uart, "\r\nRunning RSA tests...").unwrap();
-    run_rsa_verification_tests(uart, engine);
-    run_rsa_signing_tests(uart, engine);
+    let mut result = run_rsa_verification_tests(uart, engine);
+    result += run_rsa_signing_tests(uart, engine);
+    result
 }
@@ -0,0 +1,402 @@
+// Licensed under the Apache-2.0 license
+
+//! Digest server workload: interleaved clients sharing a few hash sessions
+//!
+//! Stresses the library's [`SessionTable`], which switches sessions by
+//! saving and restoring the running state of the shared HACE context around
+//! each operation, the way a digest server multiplexes its clients. A
+//! software SHA-256 model checks every finalized digest.
+//!
+//! [`run_aligned_session_test`] runs a whole-block-only session alongside a
+//! normal one that carries a partial block, and [`run_session_reap_test`]
+//! checks that a session whose handle was dropped gets its slot back while
+//! a resumed one carries on.
+
+use super::TestResult;
+use crate::hace_controller::HaceController;
+use crate::hash::Sha256;
+use crate::hash_session::{
+    AlignedSessionTable, Session, SessionError, SessionHandle, SessionTable,
+};
+use crate::uart::UartController;
+use cortex_m::peripheral::DWT;
+use embedded_io::Write;
+
+const SLOTS: usize = 4;
+const CLIENTS: usize = 8;
+const OPS: usize = 1000;
+const MAX_UPDATE: usize = 200;
+const SEED: u32 = 0x5eed_1060;
+
+/// Minimal streaming SHA-256 used as the reference model
+struct SoftSha256 {
+    h: [u32; 8],
+    block: [u8; 64],
+    len: usize,
+    total: u64,
+}
+
+const K: [u32; 64] = [
+    0x428a_2f98,
+    0x7137_4491,
+    0xb5c0_fbcf,
+    0xe9b5_dba5,
+    0x3956_c25b,
+    0x59f1_11f1,
+    0x923f_82a4,
+    0xab1c_5ed5,
+    0xd807_aa98,
+    0x1283_5b01,
+    0x2431_85be,
+    0x550c_7dc3,
+    0x72be_5d74,
+    0x80de_b1fe,
+    0x9bdc_06a7,
+    0xc19b_f174,
+    0xe49b_69c1,
+    0xefbe_4786,
+    0x0fc1_9dc6,
+    0x240c_a1cc,
+    0x2de9_2c6f,
+    0x4a74_84aa,
+    0x5cb0_a9dc,
+    0x76f9_88da,
+    0x983e_5152,
+    0xa831_c66d,
+    0xb003_27c8,
+    0xbf59_7fc7,
+    0xc6e0_0bf3,
+    0xd5a7_9147,
+    0x06ca_6351,
+    0x1429_2967,
+    0x27b7_0a85,
+    0x2e1b_2138,
+    0x4d2c_6dfc,
+    0x5338_0d13,
+    0x650a_7354,
+    0x766a_0abb,
+    0x81c2_c92e,
+    0x9272_2c85,
+    0xa2bf_e8a1,
+    0xa81a_664b,
+    0xc24b_8b70,
+    0xc76c_51a3,
+    0xd192_e819,
+    0xd699_0624,
+    0xf40e_3585,
+    0x106a_a070,
+    0x19a4_c116,
+    0x1e37_6c08,
+    0x2748_774c,
+    0x34b0_bcb5,
+    0x391c_0cb3,
+    0x4ed8_aa4a,
+    0x5b9c_ca4f,
+    0x682e_6ff3,
+    0x748f_82ee,
+    0x78a5_636f,
+    0x84c8_7814,
+    0x8cc7_0208,
+    0x90be_fffa,
+    0xa450_6ceb,
+    0xbef9_a3f7,
+    0xc671_78f2,
+];
+
+impl SoftSha256 {
+    const fn new() -> Self {
+        Self {
+            h: [
+                0x6a09_e667,
+                0xbb67_ae85,
+                0x3c6e_f372,
+                0xa54f_f53a,
+                0x510e_527f,
+                0x9b05_688c,
+                0x1f83_d9ab,
+                0x5be0_cd19,
+            ],
+            block: [0; 64],
+            len: 0,
+            total: 0,
+        }
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 64];
+        for (i, word) in self.block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, v) in self.h.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(v);
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.total += u64::try_from(data.len()).unwrap();
+        for &byte in data {
+            self.block[self.len] = byte;
+            self.len += 1;
+            if self.len == 64 {
+                self.compress();
+                self.len = 0;
+            }
+        }
+    }
+
+    fn finalize(mut self) -> [u8; 32] {
+        let bits = self.total * 8;
+        self.update(&[0x80]);
+        while self.len != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bits.to_be_bytes());
+        let mut out = [0u8; 32];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(self.h) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        out
+    }
+}
+
+/// xorshift32, so every run replays the same workload
+struct Prng(u32);
+
+impl Prng {
+    fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        usize::try_from(self.next_u32()).unwrap() % n
+    }
+}
+
+struct Client {
+    session: Option<(SessionHandle, SoftSha256)>,
+    stale: Option<SessionHandle>,
+}
+
+#[allow(clippy::too_many_lines)]
+pub fn run_session_stress_test(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    writeln!(uart, "\r\nRunning session stress test...").unwrap();
+
+    // The model itself must be right before it can judge HACE
+    let mut model = SoftSha256::new();
+    model.update(b"abc");
+    let model_ok = model.finalize()[..4] == [0xba, 0x78, 0x16, 0xbf];
+
+    let mut table = SessionTable::<Sha256, SLOTS>::new();
+    let mut clients: [Client; CLIENTS] = core::array::from_fn(|_| Client {
+        session: None,
+        stale: None,
+    });
+    let mut rng = Prng(SEED);
+    let mut data = [0u8; MAX_UPDATE];
+    let (mut finalized, mut cancelled, mut rejected, mut full) = (0, 0, 0, 0);
+    let mut failures = 0;
+
+    let start = DWT::cycle_count();
+    for _ in 0..OPS {
+        let client = &mut clients[rng.below(CLIENTS)];
+        let op = rng.below(16);
+        match client.session.take() {
+            None if op < 12 => match table.open(hace) {
+                Ok(handle) => client.session = Some((handle, SoftSha256::new())),
+                Err(SessionError::NoFreeSlot) if table.in_use() == SLOTS => full += 1,
+                Err(_) => failures += 1,
+            },
+            None => {
+                // Closed sessions must not be reachable through old handles
+                if let Some(stale) = client.stale {
+                    if table.update(hace, stale, &data[..1]) == Err(SessionError::InvalidHandle) {
+                        rejected += 1;
+                    } else {
+                        failures += 1;
+                    }
+                }
+            }
+            Some((handle, mut model)) if op < 11 => {
+                let len = rng.below(MAX_UPDATE + 1);
+                for b in &mut data[..len] {
+                    *b = u8::try_from(rng.next_u32() & 0xff).unwrap();
+                }
+                if table.update(hace, handle, &data[..len]).is_err() {
+                    failures += 1;
+                }
+                model.update(&data[..len]);
+                client.session = Some((handle, model));
+            }
+            Some((handle, model)) if op < 14 => {
+                match table.finalize(hace, handle) {
+                    Ok(digest) if digest == model.finalize() => finalized += 1,
+                    _ => failures += 1,
+                }
+                client.stale = Some(handle);
+            }
+            Some((handle, _)) => {
+                if table.cancel(handle).is_err() {
+                    failures += 1;
+                }
+                cancelled += 1;
+                client.stale = Some(handle);
+            }
+        }
+    }
+    let cycles = DWT::cycle_count().wrapping_sub(start);
+
+    // Slot accounting: one slot per open client session, all free after close
+    let open = clients.iter().filter(|c| c.session.is_some()).count();
+    let mut accounting_ok = table.in_use() == open;
+    for client in &mut clients {
+        if let Some((handle, _)) = client.session.take() {
+            accounting_ok &= table.cancel(handle).is_ok();
+        }
+    }
+    accounting_ok &= table.in_use() == 0;
+
+    let hclk = u64::from(crate::spi::get_hclock_rate());
+    let ops_per_sec = u64::try_from(OPS).unwrap() * hclk / u64::from(cycles.max(1));
+    writeln!(
+        uart,
+        "\r\n{OPS} ops: {finalized} finalized, {cancelled} cancelled, {rejected} stale rejected, {full} full, {ops_per_sec} ops/s"
+    )
+    .unwrap();
+    let ok = model_ok && failures == 0 && accounting_ok && finalized > 0 && rejected > 0;
+    if ok {
+        writeln!(uart, "\r\nSession stress: Test passed!").unwrap();
+    } else {
+        writeln!(
+            uart,
+            "\r\nSession stress: Test failed! {failures} failures, model {model_ok}, accounting {accounting_ok}"
+        )
+        .unwrap();
+    }
+    TestResult::of(ok)
+}
+
+/// Checks that an aligned-only session and a normal session interleave on
+/// the shared context and give the same digest for whole-block data
+pub fn run_aligned_session_test(
+    uart: &mut UartController,
+    hace: &mut HaceController,
+) -> TestResult {
+    writeln!(uart, "\r\nRunning aligned session test...").unwrap();
+
+    let mut rng = Prng(SEED);
+    let mut data = [0u8; 3 * 64];
+    for b in &mut data {
+        *b = u8::try_from(rng.next_u32() & 0xff).unwrap();
+    }
+    let mut model = SoftSha256::new();
+    model.update(&data);
+    let expected = model.finalize();
+
+    let mut normal = SessionTable::<Sha256, 1>::new();
+    let mut aligned = AlignedSessionTable::<Sha256, 1>::new();
+    let mut result = TestResult::new();
+    let (Ok(hn), Ok(ha)) = (normal.open(hace), aligned.open(hace)) else {
+        writeln!(uart, "\r\nAligned session: Test failed! open").unwrap();
+        return TestResult::of(false);
+    };
+
+    // the normal session holds a partial block while the aligned one runs
+    result.check(normal.update(hace, hn, &data[..10]).is_ok());
+    result.check(aligned.update(hace, ha, &data[..64]).is_ok());
+    result.check(aligned.update(hace, ha, &data[64..74]) == Err(SessionError::UnalignedInput));
+    result.check(normal.update(hace, hn, &data[10..]).is_ok());
+    result.check(aligned.update(hace, ha, &data[64..]).is_ok());
+    let digests = (normal.finalize(hace, hn), aligned.finalize(hace, ha));
+    result.check(digests == (Ok(expected), Ok(expected)));
+
+    let (full, slim) = (
+        core::mem::size_of::<SessionTable<Sha256, 1>>(),
+        core::mem::size_of::<AlignedSessionTable<Sha256, 1>>(),
+    );
+    writeln!(uart, "\r\nslot size: {full} bytes, aligned {slim} bytes").unwrap();
+    result.check(2 * slim < full);
+
+    if result.is_ok() {
+        writeln!(uart, "\r\nAligned session: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nAligned session: Test failed!").unwrap();
+    }
+    result
+}
+
+/// A client drops its handle without closing the session; `reap` must free
+/// the slot, refuse the stale handle and leave the busy session intact
+pub fn run_session_reap_test(uart: &mut UartController, hace: &mut HaceController) -> TestResult {
+    writeln!(uart, "\r\nRunning session reap test...").unwrap();
+
+    let data = [0x5au8; 100];
+    let mut model = SoftSha256::new();
+    model.update(&data);
+    let expected = model.finalize();
+
+    let mut table = SessionTable::<Sha256, 2>::new();
+    let mut result = TestResult::new();
+    let (Ok(leaked), Ok(busy)) = (table.open(hace), table.open(hace)) else {
+        writeln!(uart, "\r\nSession reap: Test failed! open").unwrap();
+        return TestResult::of(false);
+    };
+    result.check(table.open(hace) == Err(SessionError::NoFreeSlot));
+
+    if let Some(mut session) = table.resume(hace, busy) {
+        for chunk in data.chunks(40) {
+            result.check(session.update(chunk).is_ok());
+        }
+    } else {
+        result.check(false);
+    }
+    // nothing has been idle that long yet
+    result.check(table.reap(8) == 0);
+    result.check(table.reap(2) == 1);
+    result.check(table.in_use() == 1);
+    result.check(table.update(hace, leaked, &data) == Err(SessionError::InvalidHandle));
+    result.check(table.resume(hace, leaked).is_none());
+
+    let reopened = table.open(hace);
+    result.check(reopened.is_ok_and(|h| h != leaked));
+    let digest = table.resume(hace, busy).map(Session::finalize);
+    result.check(digest == Some(Ok(expected)));
+
+    if result.is_ok() {
+        writeln!(uart, "\r\nSession reap: Test passed!").unwrap();
+    } else {
+        writeln!(uart, "\r\nSession reap: Test failed!").unwrap();
+    }
+    result
+}
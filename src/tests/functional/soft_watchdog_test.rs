@@ -0,0 +1,86 @@
+// Licensed under the Apache-2.0 license
+
+use super::TestResult;
+use crate::boot_trace::cycles_to_us;
+use crate::common::{Deadline, TickClock};
+use crate::interrupts;
+use crate::soft_watchdog::{SoftWatchdog, SoftWatchdogTimer, WatchdogHandle};
+use crate::uart::UartController;
+use ast1060_pac::Interrupt;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+use cortex_m::peripheral::DWT;
+use embedded_io::Write;
+use fugit::MillisDurationU32;
+
+const PERIOD_MS: u32 = 10;
+const TIMEOUT_MS: u32 = 50;
+
+/// DWT time of the expiry callback, 0 until it runs
+static FIRED_AT: AtomicU32 = AtomicU32::new(0);
+static FIRED_MASK: AtomicU32 = AtomicU32::new(0);
+
+static WATCHDOG: SoftWatchdog<2> = SoftWatchdog::new(Some(on_expiry));
+static mut TICKER: Option<SoftWatchdogTimer<2>> = None;
+
+fn on_expiry(handle: WatchdogHandle) {
+    FIRED_AT.store(DWT::cycle_count() | 1, Ordering::Release);
+    FIRED_MASK.fetch_or(handle.mask(), Ordering::Relaxed);
+}
+
+/// A subsystem that stops kicking must be reported within one timer period
+/// of its timeout, while one that keeps kicking is never reported
+pub fn run_soft_watchdog_tests(uart: &mut UartController, cpu_hz: u32) -> TestResult {
+    writeln!(uart, "\r\nRunning software watchdog test").unwrap();
+
+    // An earlier test may have left its own timer handler registered
+    let _ = interrupts::unregister(Interrupt::timer);
+    let registered = unsafe {
+        TICKER = Some(SoftWatchdogTimer::new(50, &WATCHDOG)); // tick_per_us
+        let ticker = (*core::ptr::addr_of_mut!(TICKER)).as_mut().unwrap();
+        ticker.start(MillisDurationU32::millis(PERIOD_MS)).unwrap();
+        interrupts::register(ticker, 0x80)
+    };
+    if let Err(e) = registered {
+        writeln!(uart, "\rinterrupt register failed: {e:?}").unwrap();
+        writeln!(uart, "\rsoft watchdog: FAILED").unwrap();
+        return TestResult::of(false);
+    }
+
+    let stalled = WATCHDOG.register(TIMEOUT_MS).unwrap();
+    let healthy = WATCHDOG.register(TIMEOUT_MS).unwrap();
+    let start = DWT::cycle_count();
+
+    let deadline = Deadline::new(TickClock::dwt(cpu_hz), Duration::from_millis(500));
+    while FIRED_AT.load(Ordering::Acquire) == 0 && !deadline.is_expired() {
+        let _ = WATCHDOG.kick(healthy);
+    }
+
+    let _ = interrupts::unregister(Interrupt::timer);
+    unsafe {
+        if let Some(ticker) = (*core::ptr::addr_of_mut!(TICKER)).as_mut() {
+            ticker.stop();
+        }
+    }
+    let _ = WATCHDOG.unregister(stalled);
+    let _ = WATCHDOG.unregister(healthy);
+
+    let fired_at = FIRED_AT.load(Ordering::Acquire);
+    let elapsed_us = cycles_to_us(u64::from(fired_at.wrapping_sub(start)), cpu_hz);
+    let limit_us = u64::from(TIMEOUT_MS + PERIOD_MS) * 1000;
+    writeln!(
+        uart,
+        "\rexpired mask: {:#x}, after {elapsed_us} us (limit {limit_us} us)",
+        FIRED_MASK.load(Ordering::Relaxed)
+    )
+    .unwrap();
+    let ok = fired_at != 0
+        && FIRED_MASK.load(Ordering::Relaxed) == stalled.mask()
+        && elapsed_us <= limit_us;
+    if ok {
+        writeln!(uart, "\rsoft watchdog: PASSED").unwrap();
+    } else {
+        writeln!(uart, "\rsoft watchdog: FAILED").unwrap();
+    }
+    TestResult::of(ok)
+}
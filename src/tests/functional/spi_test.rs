@@ -0,0 +1,584 @@
+// Licensed under the Apache-2.0 license
+
+//! SPI NOR flash suite, run once per controller and chip select
+//!
+//! Every target starts with a JEDEC ID probe. A target that answers with all
+//! zeros or all ones has no flash fitted and is skipped; so is a part
+//! [`NorFlashBlockDevice`] does not know. The read checks always run: mapped
+//! and user mode reads, continuous read, DMA into cached SRAM and the OTP
+//! registers. The erase/program/verify and config store steps rewrite
+//! [`SCRATCH_SECTORS`] sectors, so they only run when the target is given a
+//! scratch offset, or when its part is on the allow-list, in which case the
+//! last sectors are used. SPI monitor 0 also gets its policy export and
+//! restore checked, and on the FMC targets the scratch sectors hold a policy
+//! store that `SpiMonitor::early_restore` reads.
+
+use super::TestResult;
+use crate::common::DmaBuffer;
+use crate::kvstore::KvStore;
+use crate::pinctrl;
+use crate::spi::device::ChipSelectDevice;
+use crate::spi::fmccontroller::FmcController;
+use crate::spi::norflash::{SpiNorData, SpiNorDevice};
+use crate::spi::norflashblockdevice::{BlockAddrUsize, NorFlashBlockDevice};
+use crate::spi::spicontroller::SpiController;
+use crate::spi::spitest::{
+    nor_device_read_4b_data, nor_device_read_data, nor_device_write_4b_data, nor_device_write_data,
+    spim0_monitor, start_spim0, start_spim2, FMC_CONFIG, FMC_CS0_CAPACITY, SPI0_CONFIG,
+    SPI1_CONFIG, SPI_CS0_CAPACITY,
+};
+use crate::spi::{get_hclock_rate, SpiData, SpiError};
+use crate::spimonitor::{
+    PolicyStore, RegionInfo, SpiMonitor, SpiMonitorError, SpipfInstance, SPIM_POLICY_MAX_SIZE,
+};
+use crate::uart::UartController;
+use crate::watchdog::WdtController;
+use ast1060_pac::{Spipf, Spipf2, Wdt};
+use cortex_m::peripheral::DWT;
+use embedded_io::Write;
+use proposed_traits::block_device::{BlockDevice, BlockRange};
+
+/// Bytes read, written and compared by each step
+const CHUNK: usize = 0x400;
+
+/// Sectors from the scratch offset the destructive steps may rewrite
+pub const SCRATCH_SECTORS: usize = 4;
+
+/// User mode reads stay below the controller's DMA threshold
+const PIO_CHUNK: usize = 64;
+
+const SCU_QSPI_MUX: usize = 0x7e6e_20f0;
+
+#[link_section = ".ram_nc"]
+static mut SUITE_BUF: [DmaBuffer<CHUNK>; 2] = [DmaBuffer::new(), DmaBuffer::new()];
+
+// In cached SRAM on purpose, for the coherence check
+static mut CACHED_BUF: DmaBuffer<CHUNK> = DmaBuffer::new();
+
+/// Flash on the board that the suite may drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpiTarget {
+    /// Boot flash
+    FmcCs0,
+    FmcCs1,
+    /// Host flash behind SPI monitor 0
+    Spi0Cs0,
+    /// Flash behind SPI monitor 2
+    Spi1Cs0,
+}
+
+/// What the suite may do to one target
+#[derive(Debug, Clone, Copy)]
+pub struct SpiSuiteConfig {
+    pub target: SpiTarget,
+    /// Sector-aligned start of the [`SCRATCH_SECTORS`] sectors the
+    /// destructive steps may rewrite
+    pub scratch_offset: Option<usize>,
+    /// JEDEC IDs whose last sectors may be rewritten without a scratch offset
+    pub allow: &'static [[u8; 3]],
+}
+
+impl SpiSuiteConfig {
+    /// Probe and read checks only
+    #[must_use]
+    pub const fn read_only(target: SpiTarget) -> Self {
+        Self {
+            target,
+            scratch_offset: None,
+            allow: &[],
+        }
+    }
+}
+
+macro_rules! test_log {
+    ($uart:expr, $($arg:tt)*) => {{
+        writeln!($uart, $($arg)*).ok();
+        write!($uart, "\r").ok();
+    }};
+}
+
+/// Runs the suite on each of `configs`
+pub fn run_spi_tests(uart: &mut UartController<'_>, configs: &[SpiSuiteConfig]) -> TestResult {
+    let mut result = TestResult::new();
+    for config in configs {
+        result += match config.target {
+            SpiTarget::FmcCs0 | SpiTarget::FmcCs1 => run_fmc(uart, config),
+            SpiTarget::Spi0Cs0 => run_spi0(uart, config),
+            SpiTarget::Spi1Cs0 => run_spi1(uart, config),
+        };
+    }
+    result
+}
+
+fn run_fmc(uart: &mut UartController<'_>, config: &SpiSuiteConfig) -> TestResult {
+    pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_FMC_QUAD);
+    let cs = usize::from(config.target == SpiTarget::FmcCs1);
+    let fmc = unsafe { &*ast1060_pac::Fmc::ptr() };
+    let mut controller = FmcController::new(fmc, cs, FMC_CONFIG, SpiData::new(), None);
+    let _ = controller.init();
+
+    let device: ChipSelectDevice<'_, FmcController<'_>, Spipf> = ChipSelectDevice {
+        bus: &mut controller,
+        cs,
+        spi_monitor: None,
+    };
+    run_target(
+        uart,
+        device,
+        &nor_device_read_data(FMC_CS0_CAPACITY),
+        &nor_device_write_data(FMC_CS0_CAPACITY),
+        config,
+    )
+}
+
+fn run_spi0(uart: &mut UartController<'_>, config: &SpiSuiteConfig) -> TestResult {
+    pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_SPIM0_QUAD_DEFAULT);
+    pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_SPI1_QUAD);
+    unsafe { core::ptr::write_volatile(SCU_QSPI_MUX as *mut u32, 0x0000_fff0) };
+    let spi0 = unsafe { &*ast1060_pac::Spi::ptr() };
+    let mut controller = SpiController::new(spi0, 0, SPI0_CONFIG, SpiData::new(), None);
+    let _ = controller.init();
+
+    let mut monitor = start_spim0();
+    let mut result = check_policy_restore(uart, &monitor);
+    let device = ChipSelectDevice {
+        bus: &mut controller,
+        cs: 0,
+        spi_monitor: Some(&mut monitor),
+    };
+    result += run_target(
+        uart,
+        device,
+        &nor_device_read_4b_data(SPI_CS0_CAPACITY),
+        &nor_device_write_4b_data(SPI_CS0_CAPACITY),
+        config,
+    );
+    result
+}
+
+fn run_spi1(uart: &mut UartController<'_>, config: &SpiSuiteConfig) -> TestResult {
+    pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_SPIM2_PINCTRL0);
+    pinctrl::Pinctrl::apply_pinctrl_group(pinctrl::PINCTRL_SPI2_QUAD);
+    let spi1 = unsafe { &*ast1060_pac::Spi1::ptr() };
+    let mut controller = SpiController::new(spi1, 0, SPI1_CONFIG, SpiData::new(), None);
+    let _ = controller.init();
+
+    let mut monitor = start_spim2();
+    let device = ChipSelectDevice {
+        bus: &mut controller,
+        cs: 0,
+        spi_monitor: Some(&mut monitor),
+    };
+    run_target(
+        uart,
+        device,
+        &nor_device_read_4b_data(SPI_CS0_CAPACITY),
+        &nor_device_write_4b_data(SPI_CS0_CAPACITY),
+        config,
+    )
+}
+
+fn run_target<T: SpiNorDevice<Error = SpiError>>(
+    uart: &mut UartController<'_>,
+    mut device: T,
+    read: &SpiNorData<'_>,
+    write: &SpiNorData<'_>,
+    config: &SpiSuiteConfig,
+) -> TestResult {
+    test_log!(uart, "\n####### SPI flash {:?} #######", config.target);
+    let mut result = TestResult::new();
+    let _ = device.nor_read_init(read);
+    let _ = device.nor_write_init(write);
+
+    let Ok(id) = device.nor_read_jedec_id() else {
+        test_log!(uart, "JEDEC ID read failed");
+        result.check(false);
+        return result;
+    };
+    test_log!(uart, "JEDEC ID {:02x} {:02x} {:02x}", id[0], id[1], id[2]);
+    if id == [0; 3] || id == [0xff; 3] {
+        test_log!(uart, "no flash, skipped");
+        return result;
+    }
+
+    let (first, second) = unsafe {
+        let [a, b] = &mut *core::ptr::addr_of_mut!(SUITE_BUF);
+        (a.as_mut_slice(0, CHUNK), b.as_mut_slice(0, CHUNK))
+    };
+    // The boot ROM reads the boot flash with normal commands after a reset
+    if config.target != SpiTarget::FmcCs0 {
+        result += check_continuous_read(uart, &mut device, first, second);
+    }
+    result += check_dma_coherence(uart, &mut device, first);
+    result += check_otp_read(uart, &mut device);
+
+    let Ok(mut blockdev) = NorFlashBlockDevice::from_jedec_id(device, id) else {
+        test_log!(uart, "unknown part, skipped");
+        return result;
+    };
+    result += check_reads(uart, &mut blockdev, first, second);
+
+    let sector = blockdev.erase_size();
+    let scratch = match config.scratch_offset {
+        Some(offset) => offset,
+        None if config.allow.contains(&id) => blockdev.capacity() - SCRATCH_SECTORS * sector,
+        None => {
+            test_log!(uart, "no scratch area, destructive steps skipped");
+            return result;
+        }
+    };
+    if scratch % sector != 0 || CHUNK > sector {
+        test_log!(uart, "scratch offset {:#x} is not sector aligned", scratch);
+        result.check(false);
+        return result;
+    }
+    result += check_program(uart, &mut blockdev, scratch, first, second);
+    // The policy store belongs on the RoT's own flash
+    if matches!(config.target, SpiTarget::FmcCs0 | SpiTarget::FmcCs1) {
+        result += check_policy_store(uart, &mut blockdev, scratch);
+    }
+    result += check_kvstore(uart, blockdev, scratch);
+    result
+}
+
+/// Reads `buf.len()` bytes at `addr` in user mode chunks and returns the
+/// throughput in KB/s
+fn read_throughput<T: SpiNorDevice>(device: &mut T, addr: u32, buf: &mut [u8]) -> (bool, u64) {
+    let start = DWT::cycle_count();
+    let mut ok = true;
+    for (i, chunk) in buf.chunks_mut(PIO_CHUNK).enumerate() {
+        let offset = u32::try_from(i * PIO_CHUNK).unwrap();
+        ok &= device.nor_read_data(addr + offset, chunk).is_ok();
+    }
+    let cycles = DWT::cycle_count().wrapping_sub(start);
+    (ok, kbps(buf.len(), cycles))
+}
+
+fn kbps(len: usize, cycles: u32) -> u64 {
+    u64::try_from(len).unwrap() * u64::from(get_hclock_rate()) / u64::from(cycles.max(1)) / 1024
+}
+
+/// Compares user mode read throughput with and without the opcode, checking
+/// both against the mapped window. The mapped window must refuse reads
+/// while continuous read is on and return the same data again after exit.
+fn check_continuous_read<T: SpiNorDevice<Error = SpiError>>(
+    uart: &mut UartController<'_>,
+    device: &mut T,
+    mapped: &mut [u8],
+    user: &mut [u8],
+) -> TestResult {
+    let mut result = TestResult::new();
+    let mapped_read = device.nor_read_mapped(0, mapped).is_ok();
+    let (user_ok, user_kbps) = read_throughput(device, 0, user);
+    if !result.check(mapped_read && user_ok && user == mapped) {
+        test_log!(uart, "ERROR: mapped and user mode reads disagree");
+    }
+
+    user.fill(0);
+    let entered = device.nor_enter_continuous_read().is_ok();
+    let (cont_ok, cont_kbps) = read_throughput(device, 0, user);
+    if !result.check(entered && cont_ok && user == mapped) {
+        test_log!(uart, "ERROR: continuous read");
+    }
+    result.check(matches!(
+        device.nor_read_mapped(0, &mut user[..4]),
+        Err(SpiError::ContinuousRead)
+    ));
+    result.check(device.nor_exit_continuous_read().is_ok());
+
+    // Normal commands and the mapped window work again after exit
+    result.check(device.nor_read_jedec_id().is_ok());
+    user.fill(0);
+    if !result.check(device.nor_read_mapped(0, user).is_ok() && user == mapped) {
+        test_log!(uart, "ERROR: mapped read after continuous read");
+    }
+    test_log!(
+        uart,
+        "user: {} KB/s, without opcode: {} KB/s",
+        user_kbps,
+        cont_kbps
+    );
+    result
+}
+
+/// DMA-reads into a buffer in cached SRAM whose lines were just pulled into
+/// the cache, and checks the CPU sees the flash data rather than stale
+/// lines. The read starts one word into the buffer so the range is not line
+/// aligned.
+fn check_dma_coherence<T: SpiNorDevice>(
+    uart: &mut UartController<'_>,
+    device: &mut T,
+    mapped: &mut [u8],
+) -> TestResult {
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(CACHED_BUF) };
+    let cached = buf.as_mut_slice(4, CHUNK - 4);
+    let mapped = &mut mapped[..CHUNK - 4];
+
+    cached.fill(0x5a);
+    let primed = cached.iter().all(|&b| b == 0x5a);
+    let ok = primed
+        && device.nor_read_data(0, cached).is_ok()
+        && device.nor_read_mapped(0, mapped).is_ok()
+        && cached == mapped;
+    if !ok {
+        test_log!(uart, "ERROR: DMA read into cached SRAM is stale");
+    }
+    TestResult::of(ok)
+}
+
+/// Reads the OTP security registers without programming them: a read across
+/// a register boundary must be repeatable and a read past the end refused
+fn check_otp_read<T: SpiNorDevice<Error = SpiError>>(
+    uart: &mut UartController<'_>,
+    device: &mut T,
+) -> TestResult {
+    let mut first = [0u8; 16];
+    let mut second = [0xffu8; 16];
+    match device.read_otp(248, &mut first) {
+        Err(SpiError::UnsupportedDevice(mfr)) => {
+            test_log!(uart, "no OTP layout for {:#x}, OTP read skipped", mfr);
+            return TestResult::new();
+        }
+        Err(e) => {
+            test_log!(uart, "ERROR: OTP read {:?}", e);
+            return TestResult::of(false);
+        }
+        Ok(()) => {}
+    }
+    let repeat = device.read_otp(248, &mut second).is_ok() && first == second;
+    let bounded = matches!(
+        device.read_otp(760, &mut second),
+        Err(SpiError::CapacityOutOfRange)
+    );
+    let lock = device.otp_locked(0);
+    test_log!(uart, "OTP register 0 locked: {:?}", lock);
+    TestResult::from_checks(&[repeat, bounded, lock.is_ok()])
+}
+
+/// Two reads of the start of the flash must agree, and one past the end
+/// must be refused
+fn check_reads<T: SpiNorDevice>(
+    uart: &mut UartController<'_>,
+    blockdev: &mut NorFlashBlockDevice<T>,
+    first: &mut [u8],
+    second: &mut [u8],
+) -> TestResult {
+    let mut result = TestResult::new();
+    let read = blockdev.read(BlockAddrUsize(0), first).is_ok()
+        && blockdev.read(BlockAddrUsize(0), second).is_ok();
+    if !result.check(read && first == second) {
+        test_log!(uart, "ERROR: reads of offset 0 disagree");
+    }
+    let end = BlockAddrUsize(blockdev.capacity() - CHUNK / 2);
+    result.check(blockdev.read(end, first).is_err());
+    result
+}
+
+/// Erase, program and verify the sector at `offset`, then erase it again
+fn check_program<T: SpiNorDevice>(
+    uart: &mut UartController<'_>,
+    blockdev: &mut NorFlashBlockDevice<T>,
+    offset: usize,
+    pattern: &mut [u8],
+    readback: &mut [u8],
+) -> TestResult {
+    let mut result = TestResult::new();
+    let range = BlockRange {
+        start: BlockAddrUsize(offset),
+        count: 1,
+    };
+    test_log!(uart, "scratch sector at {:#x}", offset);
+
+    let erased = blockdev.erase(range).is_ok()
+        && blockdev.read(BlockAddrUsize(offset), readback).is_ok()
+        && readback.iter().all(|&b| b == 0xff);
+    if !result.check(erased) {
+        test_log!(uart, "ERROR: erase");
+        return result;
+    }
+
+    for (i, b) in pattern.iter_mut().enumerate() {
+        *b = u8::try_from(i % 251).unwrap();
+    }
+    let programmed = blockdev.program(BlockAddrUsize(offset), pattern).is_ok()
+        && blockdev.read(BlockAddrUsize(offset), readback).is_ok()
+        && pattern == readback;
+    if !result.check(programmed) {
+        test_log!(uart, "ERROR: program and read back");
+    }
+
+    let verified = blockdev.erase(range).is_ok()
+        && blockdev
+            .program_verified(BlockAddrUsize(offset), pattern)
+            .is_ok();
+    if !result.check(verified) {
+        test_log!(uart, "ERROR: verified program");
+    }
+
+    // leave the scratch sector erased
+    result.check(blockdev.erase(range).is_ok());
+    test_log!(uart, "{} passed, {} failed", result.passed, result.failed);
+    result
+}
+
+/// Stores a value in a config store on the scratch sectors, and reads it
+/// back after a remount. The sectors are left erased.
+fn check_kvstore<T: SpiNorDevice>(
+    uart: &mut UartController<'_>,
+    blockdev: NorFlashBlockDevice<T>,
+    offset: usize,
+) -> TestResult {
+    const KEY: u16 = 1;
+    let value = 0x5a5a_0001_u32.to_le_bytes();
+    let mut buf = [0u8; 4];
+
+    let stored = KvStore::<_, 8>::mount(blockdev, offset, SCRATCH_SECTORS).and_then(|mut store| {
+        store.set(KEY, &value)?;
+        let mut store = KvStore::<_, 8>::mount(store.into_inner(), offset, SCRATCH_SECTORS)?;
+        let len = store.get(KEY, &mut buf)?;
+        Ok((len, store.into_inner()))
+    });
+    let (len, mut blockdev) = match stored {
+        Ok(stored) => stored,
+        Err(e) => {
+            test_log!(uart, "ERROR: config store {:?}", e);
+            return TestResult::of(false);
+        }
+    };
+    let mut result = TestResult::new();
+    if !result.check(len == value.len() && buf == value) {
+        test_log!(uart, "ERROR: config store read back");
+    }
+    result.check(
+        blockdev
+            .erase(BlockRange {
+                start: BlockAddrUsize(offset),
+                count: SCRATCH_SECTORS,
+            })
+            .is_ok(),
+    );
+    result
+}
+
+/// Exports SPI monitor 0's policy, restores it into a new monitor and
+/// checks every setting came back unchanged, and that a corrupted image or
+/// one exported from another monitor is rejected
+fn check_policy_restore(uart: &mut UartController<'_>, original: &SpiMonitor<Spipf>) -> TestResult {
+    let mut policy = [0u8; SPIM_POLICY_MAX_SIZE];
+    let len = match original.export_policy(&mut policy) {
+        Ok(len) => len,
+        Err(e) => {
+            test_log!(uart, "ERROR: SPIM policy export {:?}", e);
+            return TestResult::of(false);
+        }
+    };
+    let mut result = TestResult::new();
+
+    match SpiMonitor::<Spipf>::restore(&policy[..len]) {
+        Ok(restored) => {
+            if !result.check(same_policy(&restored, original)) {
+                test_log!(uart, "ERROR: restored SPIM policy differs");
+            }
+        }
+        Err(e) => {
+            test_log!(uart, "ERROR: SPIM policy restore {:?}", e);
+            result.check(false);
+        }
+    }
+
+    // Rejected images are never programmed, so these leave monitor 2 alone
+    result.check(matches!(
+        SpiMonitor::<Spipf2>::restore(&policy[..len]),
+        Err(SpiMonitorError::PolicyInvalid(_))
+    ));
+    policy[len / 2] ^= 0xff;
+    result.check(matches!(
+        SpiMonitor::<Spipf>::restore(&policy[..len]),
+        Err(SpiMonitorError::PolicyInvalid(_))
+    ));
+    result
+}
+
+/// Saves SPI monitor 0's policy twice in a policy store on the scratch
+/// sectors and loads it back, then checks `early_restore` only restores
+/// after a watchdog reset. The sectors are left erased.
+fn check_policy_store<T: SpiNorDevice>(
+    uart: &mut UartController<'_>,
+    blockdev: &mut NorFlashBlockDevice<T>,
+    offset: usize,
+) -> TestResult {
+    let monitor = spim0_monitor();
+    let mut policy = [0u8; SPIM_POLICY_MAX_SIZE];
+    let mut loaded = [0u8; SPIM_POLICY_MAX_SIZE];
+    let Ok(len) = monitor.export_policy(&mut policy) else {
+        return TestResult::of(false);
+    };
+    let store = PolicyStore::new(offset);
+    let mut result = TestResult::new();
+
+    // The second save goes to the other sector
+    for _ in 0..2 {
+        let stored = store
+            .save(blockdev, &policy[..len])
+            .and_then(|()| store.load(blockdev, &mut loaded));
+        if !result.check(stored == Ok(len) && loaded[..len] == policy[..len]) {
+            test_log!(uart, "ERROR: SPIM policy store {:?}", stored);
+        }
+    }
+
+    // Started watchdogs have their reset status cleared, so this is
+    // normally the cold path; a warm one reprograms monitor 0 with the
+    // policy `start_spim0` uses
+    let wdt = WdtController::<Wdt>::new();
+    let warm = wdt.reset_by_timeout();
+    let restored = SpiMonitor::<Spipf>::early_restore(&store, blockdev, &wdt);
+    let restored_ok = match &restored {
+        Ok(Some(restored)) => warm && same_policy(restored, &monitor),
+        Ok(None) => !warm,
+        Err(_) => false,
+    };
+    if !result.check(restored_ok) {
+        test_log!(
+            uart,
+            "ERROR: SPIM early restore (watchdog reset {}) {:?}",
+            warm,
+            restored.err()
+        );
+    }
+
+    result.check(
+        blockdev
+            .erase(BlockRange {
+                start: BlockAddrUsize(offset),
+                count: 2,
+            })
+            .is_ok(),
+    );
+    result
+}
+
+fn same_policy<S: SpipfInstance>(a: &SpiMonitor<S>, b: &SpiMonitor<S>) -> bool {
+    let allow_num = b.allow_cmd_num as usize;
+    let read_num = b.read_blocked_region_num as usize;
+    let write_num = b.write_blocked_region_num as usize;
+    let same_regions = |x: &[RegionInfo], y: &[RegionInfo]| {
+        x.iter()
+            .zip(y)
+            .all(|(p, q)| p.start == q.start && p.length == q.length)
+    };
+    a.ext_mux_sel == b.ext_mux_sel
+        && a.extra_clk_en == b.extra_clk_en
+        && a.force_rel_flash_rst == b.force_rel_flash_rst
+        && a.allow_cmd_num == b.allow_cmd_num
+        && a.allow_cmd_list[..allow_num] == b.allow_cmd_list[..allow_num]
+        && a.read_blocked_region_num == b.read_blocked_region_num
+        && a.write_blocked_region_num == b.write_blocked_region_num
+        && same_regions(
+            &a.read_blocked_regions[..read_num],
+            &b.read_blocked_regions[..read_num],
+        )
+        && same_regions(
+            &a.write_blocked_regions[..write_num],
+            &b.write_blocked_regions[..write_num],
+        )
+}
@@ -1,8 +1,14 @@
 // Licensed under the Apache-2.0 license
 
-use crate::timer::{TimerController, TimerType};
+use super::TestResult;
+#[cfg(feature = "rt")]
+use crate::interrupts::IrqHandler;
+use crate::timer::{NbDelay, TimerController, TimerType};
 use crate::uart::UartController;
 use ast1060_pac::Timer;
+use core::cell::RefCell;
+use cortex_m::interrupt::Mutex;
+#[cfg(not(feature = "rt"))]
 use cortex_m::peripheral::NVIC;
 use embedded_hal_old::timer::CountDown;
 use fugit::MicrosDurationU32;
@@ -10,34 +16,96 @@ use fugit::MicrosDurationU32;
 use embedded_io::Write;
 
 static mut UART_PTR: Option<&'static mut UartController<'static>> = None;
-static mut TIMER_INSTANCE: Option<TimerController<Timer>> = None;
+static TIMER_INSTANCE: Mutex<RefCell<Option<TimerController<Timer>>>> =
+    Mutex::new(RefCell::new(None));
+#[cfg(feature = "rt")]
+static mut TIMER_IRQ: TimerIrqHandler = TimerIrqHandler;
 
+fn run_timer_irq() {
+    cortex_m::interrupt::free(|cs| {
+        if let Some(timer) = TIMER_INSTANCE.borrow(cs).borrow_mut().as_mut() {
+            timer.handle_interrupt();
+        }
+    });
+}
+
+// With the `rt` feature the vector is owned by `crate::interrupts`
+#[cfg(not(feature = "rt"))]
 #[no_mangle]
 pub extern "C" fn timer() {
     unsafe {
         if let Some(uart) = UART_PTR.as_mut() {
             let _ = uart.write_all(b"[ISR] Timer\r\n");
         }
-        if let Some(timer) = TIMER_INSTANCE.as_mut() {
-            let () = timer.handle_interrupt();
-        }
+    }
+    run_timer_irq();
+}
+
+/// Registered in place of the timer, which stays in `TIMER_INSTANCE`, so
+/// the test and the registry never share a borrow
+#[cfg(feature = "rt")]
+struct TimerIrqHandler;
+
+#[cfg(feature = "rt")]
+impl IrqHandler for TimerIrqHandler {
+    fn irq_number(&self) -> ast1060_pac::Interrupt {
+        ast1060_pac::Interrupt::timer
+    }
+
+    fn on_irq(&mut self) {
+        run_timer_irq();
     }
 }
 
-pub fn test_timer_isr(uart: &mut UartController<'_>) {
+/// Only checks that the timer starts; the ticks show up on the console
+pub fn test_timer_isr(uart: &mut UartController<'_>) -> TestResult {
+    let mut result = TestResult::new();
     let mut timer = TimerController::<Timer>::new(50); // tick_per_us
     timer.set_callback(Some(timer_callback), TimerType::Periodic);
-    timer.try_start(MicrosDurationU32::millis(1000)).unwrap();
+    result.check(timer.try_start(MicrosDurationU32::millis(1000)).is_ok());
 
     unsafe {
         UART_PTR = Some(core::mem::transmute::<
             &mut UartController<'_>,
             &'static mut UartController<'static>,
         >(uart));
-
-        TIMER_INSTANCE = Some(timer);
+    }
+    cortex_m::interrupt::free(|cs| TIMER_INSTANCE.borrow(cs).replace(Some(timer)));
+    #[cfg(not(feature = "rt"))]
+    unsafe {
         NVIC::unmask(ast1060_pac::Interrupt::timer);
     }
+    #[cfg(feature = "rt")]
+    result.check(
+        crate::interrupts::register(unsafe { &mut *core::ptr::addr_of_mut!(TIMER_IRQ) }, 0x80)
+            .is_ok(),
+    );
+    result
+}
+
+/// Polls a 2 ms delay to completion: it must not be done right after the
+/// start, and it must finish. A zero delay is done at once.
+pub fn test_nb_delay(uart: &mut UartController<'_>) -> TestResult {
+    let mut result = TestResult::new();
+    let mut delay = NbDelay::new(TimerController::<Timer>::new(50));
+
+    result.check(delay.start(MicrosDurationU32::millis(2)).is_ok());
+    result.check(matches!(delay.wait(), Err(nb::Error::WouldBlock)));
+    let mut polls = 0u32;
+    while delay.wait().is_err() && polls < 10_000_000 {
+        polls += 1;
+    }
+    result.check(!delay.is_running());
+    writeln!(uart, "\r\nnb delay: 2 ms after {polls} polls").unwrap();
+
+    result.check(delay.start(MicrosDurationU32::from_ticks(0)).is_ok());
+    result.check(delay.wait().is_ok());
+
+    result.check(delay.start(MicrosDurationU32::millis(100)).is_ok());
+    delay.cancel();
+    result.check(delay.wait().is_ok());
+    let _ = delay.free();
+    result
 }
 
 fn timer_callback() {
@@ -48,7 +116,11 @@ fn timer_callback() {
     }
 }
 
-pub fn run_timer_tests(uart: &mut UartController) {
+pub fn run_timer_tests(uart: &mut UartController) -> TestResult {
+    // before the ISR test, which leaves the timer interrupt handled
+    writeln!(uart, "\r\nRunning non-blocking delay test").unwrap();
+    let mut result = test_nb_delay(uart);
     writeln!(uart, "\r\nRunning Timer ISR test").unwrap();
-    test_timer_isr(uart);
+    result += test_timer_isr(uart);
+    result
 }
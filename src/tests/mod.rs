@@ -1,3 +1,5 @@
 // Licensed under the Apache-2.0 license
 
+pub mod coop;
 pub mod functional;
+pub mod vectors_generated;
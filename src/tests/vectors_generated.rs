@@ -0,0 +1,988 @@
+// Licensed under the Apache-2.0 license
+
+// @generated by `cargo xtask gen-vectors`. Do not edit; change the tables in
+// xtask/src/vectors.rs and regenerate.
+
+pub struct HashVector {
+    pub msg: &'static [u8],
+    pub digest: &'static [u8],
+}
+
+pub struct HmacVector {
+    pub key: &'static [u8],
+    pub msg: &'static [u8],
+    pub mac: &'static [u8],
+}
+
+pub struct HkdfVector {
+    pub ikm: &'static [u8],
+    pub salt: &'static [u8],
+    pub info: &'static [u8],
+    pub okm: &'static [u8],
+}
+
+pub const SHA256: &[HashVector] = &[
+    HashVector {
+        msg: &[],
+        digest: &[
+            0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f,
+            0xb9, 0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b,
+            0x78, 0x52, 0xb8, 0x55,
+        ],
+    },
+    HashVector {
+        msg: &[0x11, 0x22, 0x33],
+        digest: &[
+            0x49, 0xee, 0x2b, 0xf9, 0x3a, 0xac, 0x3b, 0x1f, 0xb4, 0x11, 0x7e, 0x59, 0x09, 0x5e,
+            0x07, 0xab, 0xe5, 0x55, 0xc3, 0x38, 0x3b, 0x38, 0xd6, 0x08, 0xda, 0x37, 0x68, 0x0a,
+            0x40, 0x60, 0x96, 0xe8,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7,
+        ],
+        digest: &[
+            0x14, 0x54, 0xe9, 0xfd, 0xe9, 0xca, 0xfe, 0x87, 0x91, 0x41, 0xa0, 0x44, 0xb8, 0x26,
+            0x9e, 0x7d, 0xe2, 0xaa, 0xa8, 0xdf, 0x8a, 0x3b, 0x5a, 0x35, 0x0a, 0x1e, 0x5c, 0xd4,
+            0x85, 0x5d, 0xbf, 0x7c,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+        ],
+        digest: &[
+            0x41, 0xae, 0x84, 0xc1, 0x57, 0x9a, 0x3b, 0x80, 0x2e, 0xfd, 0x0a, 0x4b, 0xa1, 0x7d,
+            0x56, 0xf0, 0x38, 0x82, 0x29, 0xe4, 0xfa, 0xf8, 0x67, 0xed, 0x59, 0xb4, 0x64, 0xe2,
+            0x1a, 0x5c, 0x09, 0xcc,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f,
+        ],
+        digest: &[
+            0xa6, 0xe5, 0x08, 0x46, 0x50, 0xfd, 0x0e, 0xa4, 0xd2, 0xb3, 0x17, 0xf4, 0x5e, 0x98,
+            0xa7, 0x61, 0x8a, 0x42, 0xd6, 0xe1, 0xaa, 0x4d, 0x22, 0x4c, 0x4d, 0x62, 0x78, 0x6e,
+            0xe9, 0xef, 0x14, 0x53,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40,
+        ],
+        digest: &[
+            0x45, 0x75, 0x53, 0xba, 0x73, 0x73, 0xa7, 0x57, 0x6b, 0xa7, 0x0d, 0x56, 0x32, 0x20,
+            0x7f, 0xbb, 0xa2, 0x74, 0x21, 0xb1, 0x08, 0x79, 0xb4, 0x9f, 0x86, 0x3e, 0x98, 0x88,
+            0xdc, 0xd9, 0xc1, 0x04,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51,
+        ],
+        digest: &[
+            0xea, 0xea, 0xb9, 0x79, 0xb7, 0x12, 0xbc, 0x73, 0x72, 0x55, 0x0d, 0xd3, 0x74, 0x09,
+            0x98, 0xb3, 0x4e, 0xe7, 0xcf, 0xb7, 0x12, 0x70, 0xf7, 0x28, 0x9a, 0xa0, 0xea, 0xe7,
+            0x7f, 0x15, 0x0e, 0x47,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f,
+        ],
+        digest: &[
+            0x8f, 0x08, 0x1e, 0xcf, 0x8f, 0x8d, 0xd7, 0xda, 0xad, 0xd4, 0xc1, 0x93, 0x9a, 0x02,
+            0xc3, 0x3f, 0x93, 0x83, 0xea, 0x28, 0xbe, 0x52, 0x87, 0xb6, 0xf4, 0x3d, 0xcf, 0xb1,
+            0x0a, 0x57, 0xce, 0xcf,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+        ],
+        digest: &[
+            0xc6, 0xdc, 0xd9, 0x24, 0xfa, 0x26, 0x29, 0xa0, 0xd6, 0x56, 0xdd, 0x72, 0xca, 0x07,
+            0xf3, 0x92, 0x58, 0xb6, 0x27, 0x8a, 0x55, 0x90, 0x98, 0x65, 0xc1, 0x12, 0xe3, 0xe7,
+            0x9e, 0x2a, 0x6b, 0x11,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f,
+        ],
+        digest: &[
+            0xef, 0x6c, 0xc0, 0xac, 0x41, 0xa3, 0xdc, 0x52, 0x49, 0xe0, 0xba, 0xa0, 0xe6, 0x2e,
+            0x00, 0x29, 0x9b, 0xe1, 0xc3, 0x04, 0x3f, 0xdc, 0x0b, 0xff, 0xa3, 0xaa, 0x39, 0xe4,
+            0x95, 0xe6, 0xa9, 0x7c,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f, 0x80,
+        ],
+        digest: &[
+            0xc9, 0x4f, 0x30, 0xaa, 0x09, 0xf0, 0x6f, 0xf2, 0x23, 0x21, 0x98, 0xb5, 0x05, 0x9d,
+            0xe9, 0xa4, 0xc2, 0xca, 0x76, 0xfb, 0x44, 0x70, 0x84, 0x7d, 0x30, 0x34, 0x0f, 0xab,
+            0x10, 0x15, 0x81, 0xbf,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f, 0x80, 0x91,
+        ],
+        digest: &[
+            0x94, 0x59, 0xec, 0x39, 0xb0, 0xc7, 0xdc, 0xf0, 0x4d, 0x4c, 0x2d, 0x6f, 0xf1, 0x75,
+            0x2a, 0xc3, 0xf8, 0x97, 0x18, 0x8d, 0xdb, 0x13, 0xf7, 0x49, 0x27, 0x6a, 0xe8, 0x8e,
+            0xb7, 0x27, 0xe9, 0x78,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f, 0x80, 0x91, 0xa2, 0xb3, 0xc4, 0xd5, 0xe6, 0xf7, 0x08, 0x19, 0x2a, 0x3b, 0x4c,
+            0x5d, 0x6e, 0x7f, 0x90, 0xa1, 0xb2, 0xc3, 0xd4, 0xe5, 0xf6, 0x07, 0x18, 0x29, 0x3a,
+            0x4b, 0x5c, 0x6d, 0x7e, 0x8f, 0xa0, 0xb1, 0xc2, 0xd3, 0xe4, 0xf5, 0x06, 0x17, 0x28,
+            0x39, 0x4a, 0x5b, 0x6c, 0x7d, 0x8e, 0x9f, 0xb0, 0xc1, 0xd2, 0xe3, 0xf4, 0x05, 0x16,
+            0x27, 0x38, 0x49, 0x5a, 0x6b, 0x7c, 0x8d, 0x9e, 0xaf, 0xc0, 0xd1, 0xe2, 0xf3, 0x04,
+            0x15, 0x26, 0x37, 0x48,
+        ],
+        digest: &[
+            0xca, 0x9d, 0xfb, 0x09, 0x7c, 0xf1, 0xb9, 0x94, 0x3f, 0x8b, 0x9f, 0x45, 0xee, 0xc1,
+            0xb0, 0xf1, 0xc6, 0xa8, 0x45, 0x80, 0x29, 0x86, 0x2e, 0xfa, 0x5e, 0x58, 0xa5, 0x41,
+            0x41, 0x8f, 0x92, 0x0f,
+        ],
+    },
+];
+
+pub const SHA384: &[HashVector] = &[
+    HashVector {
+        msg: &[],
+        digest: &[
+            0x38, 0xb0, 0x60, 0xa7, 0x51, 0xac, 0x96, 0x38, 0x4c, 0xd9, 0x32, 0x7e, 0xb1, 0xb1,
+            0xe3, 0x6a, 0x21, 0xfd, 0xb7, 0x11, 0x14, 0xbe, 0x07, 0x43, 0x4c, 0x0c, 0xc7, 0xbf,
+            0x63, 0xf6, 0xe1, 0xda, 0x27, 0x4e, 0xde, 0xbf, 0xe7, 0x6f, 0x65, 0xfb, 0xd5, 0x1a,
+            0xd2, 0xf1, 0x48, 0x98, 0xb9, 0x5b,
+        ],
+    },
+    HashVector {
+        msg: &[0x11, 0x22, 0x33],
+        digest: &[
+            0xda, 0x79, 0x06, 0xa1, 0x06, 0x63, 0xb6, 0xf4, 0x7e, 0x39, 0xb7, 0xfc, 0x7a, 0x00,
+            0xcf, 0x15, 0x0f, 0xa5, 0x95, 0x14, 0x28, 0x7f, 0x13, 0x78, 0x57, 0xbd, 0xeb, 0x15,
+            0xcc, 0x08, 0x58, 0xda, 0x11, 0x87, 0x4e, 0x57, 0xe6, 0xd4, 0xee, 0xde, 0x95, 0x25,
+            0x5e, 0x52, 0x8c, 0x8d, 0x16, 0xe5,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7,
+        ],
+        digest: &[
+            0xf7, 0x00, 0xbc, 0x87, 0xeb, 0x1d, 0xbf, 0xa0, 0xf9, 0x5a, 0x74, 0xd8, 0x7a, 0xaa,
+            0xc8, 0xd2, 0x6d, 0x74, 0x5a, 0xc4, 0xa5, 0x9b, 0x03, 0x73, 0xe8, 0x88, 0x1b, 0x56,
+            0x4e, 0x32, 0xd5, 0xd1, 0x01, 0x70, 0xd5, 0x15, 0xff, 0x8b, 0xd4, 0x34, 0x57, 0x6f,
+            0x1c, 0xcc, 0x4d, 0x0c, 0x45, 0x0f,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+        ],
+        digest: &[
+            0x09, 0xfa, 0x41, 0x07, 0xcc, 0x9f, 0x3c, 0xd4, 0x2b, 0x1b, 0xfa, 0x34, 0x95, 0x4a,
+            0x47, 0x06, 0x9a, 0x9b, 0x35, 0x0a, 0xd7, 0x7f, 0x6c, 0x5a, 0xef, 0x04, 0x01, 0x3f,
+            0xf2, 0xd9, 0x19, 0x46, 0x64, 0xe8, 0x1b, 0x5e, 0x73, 0x83, 0x16, 0x14, 0xa0, 0x46,
+            0xd8, 0x74, 0xd0, 0x78, 0x5f, 0xb4,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f,
+        ],
+        digest: &[
+            0xfc, 0x07, 0x4c, 0x76, 0x7f, 0x0d, 0xd1, 0xb7, 0x12, 0x95, 0x3d, 0xfa, 0x7d, 0x44,
+            0x6a, 0x8f, 0x53, 0x2e, 0x2e, 0x4f, 0xb1, 0xf8, 0x38, 0x47, 0x8a, 0xd1, 0xd8, 0x18,
+            0x63, 0xab, 0xb2, 0x01, 0xd5, 0xab, 0xec, 0x64, 0x80, 0xce, 0xf7, 0x8d, 0x45, 0x43,
+            0x31, 0x33, 0x43, 0x19, 0x0e, 0x24,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40,
+        ],
+        digest: &[
+            0xf9, 0x1e, 0xb8, 0x19, 0x4f, 0x02, 0xfb, 0x49, 0x35, 0xb5, 0x2f, 0xfd, 0xf4, 0xc3,
+            0x56, 0x42, 0xd9, 0xbf, 0xa2, 0x2d, 0x2d, 0x9f, 0x08, 0xb3, 0x9b, 0x6d, 0x63, 0xf1,
+            0xdc, 0xea, 0x7f, 0x9e, 0x56, 0xcf, 0x46, 0xe9, 0xa8, 0xc1, 0xea, 0x1a, 0x36, 0xe0,
+            0xea, 0x95, 0x85, 0x21, 0x2b, 0xc0,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51,
+        ],
+        digest: &[
+            0x1a, 0xe7, 0x6b, 0xf0, 0xe1, 0x68, 0xa2, 0x76, 0x93, 0x82, 0x57, 0x41, 0xba, 0x2c,
+            0x2e, 0xb8, 0x17, 0xe4, 0x4d, 0xec, 0xfe, 0xf3, 0x0a, 0x76, 0xe1, 0xa9, 0x5f, 0xd5,
+            0xcd, 0xcb, 0x10, 0xea, 0xe8, 0x43, 0x2e, 0x3f, 0x57, 0xd8, 0x6b, 0xc2, 0xee, 0x37,
+            0x7c, 0x6c, 0x6d, 0x24, 0x96, 0x18,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f,
+        ],
+        digest: &[
+            0x6c, 0x47, 0x47, 0x2e, 0xa8, 0x39, 0xfc, 0x5d, 0x06, 0x96, 0x27, 0x86, 0x68, 0x7b,
+            0xc6, 0xd0, 0xff, 0x59, 0xe0, 0x27, 0x23, 0x0c, 0x94, 0x56, 0x42, 0xbf, 0xf5, 0xc7,
+            0x88, 0x1d, 0x07, 0x7d, 0xec, 0x21, 0xcd, 0xb6, 0x19, 0xa7, 0xff, 0xbb, 0x38, 0x7d,
+            0x3e, 0x77, 0x5a, 0x94, 0x62, 0xe3,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+        ],
+        digest: &[
+            0x73, 0x73, 0x1f, 0xde, 0xde, 0xbb, 0xd7, 0x19, 0x4f, 0xbb, 0xec, 0x5c, 0x22, 0x94,
+            0x58, 0x66, 0x10, 0xc0, 0x65, 0xaa, 0x9e, 0x4c, 0x84, 0x6b, 0x87, 0x5b, 0x30, 0x88,
+            0x73, 0x48, 0xff, 0xf1, 0xa9, 0x62, 0x7a, 0x56, 0x98, 0xf0, 0xcc, 0x00, 0x7d, 0xad,
+            0xe3, 0x0b, 0x84, 0xab, 0xcb, 0xac,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f,
+        ],
+        digest: &[
+            0x1b, 0x5b, 0x94, 0x51, 0x70, 0xef, 0xa4, 0x1b, 0x90, 0xbb, 0xb8, 0x5e, 0x13, 0xc9,
+            0x7d, 0xa3, 0x3b, 0xa3, 0x4d, 0x3f, 0x44, 0x0f, 0x0e, 0xce, 0x6a, 0xeb, 0x38, 0x64,
+            0xe9, 0xe8, 0xac, 0x95, 0x6f, 0x1d, 0xaf, 0xf5, 0xdc, 0x88, 0x97, 0x62, 0x42, 0x4f,
+            0x22, 0x41, 0xae, 0x44, 0x23, 0xb1,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f, 0x80,
+        ],
+        digest: &[
+            0x4f, 0x9e, 0x39, 0xf2, 0x1a, 0x03, 0x56, 0x60, 0x6a, 0x7e, 0x0c, 0xcb, 0xbd, 0xf4,
+            0xd5, 0x72, 0x58, 0xa7, 0xe5, 0xb3, 0xc4, 0xae, 0x75, 0xb5, 0x71, 0x53, 0x8d, 0x72,
+            0xdb, 0x0c, 0xa4, 0x76, 0x5d, 0xd5, 0x4e, 0xbb, 0x9c, 0x32, 0xda, 0x47, 0xfa, 0x6e,
+            0xca, 0x22, 0x58, 0x97, 0x14, 0x40,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f, 0x80, 0x91,
+        ],
+        digest: &[
+            0x20, 0x6b, 0xaa, 0x67, 0x7f, 0x52, 0xb3, 0xf2, 0x03, 0xda, 0x0e, 0x08, 0x0b, 0xfb,
+            0x31, 0x89, 0x62, 0xda, 0xb9, 0xb4, 0x73, 0xb6, 0x27, 0xb5, 0x1e, 0xd9, 0x86, 0xde,
+            0xd0, 0x36, 0x06, 0x5b, 0xdb, 0xc5, 0x94, 0x3b, 0x70, 0x05, 0x29, 0x91, 0xec, 0x0f,
+            0x90, 0xdf, 0xd2, 0x29, 0x5e, 0x83,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f, 0x80, 0x91, 0xa2, 0xb3, 0xc4, 0xd5, 0xe6, 0xf7, 0x08, 0x19, 0x2a, 0x3b, 0x4c,
+            0x5d, 0x6e, 0x7f, 0x90, 0xa1, 0xb2, 0xc3, 0xd4, 0xe5, 0xf6, 0x07, 0x18, 0x29, 0x3a,
+            0x4b, 0x5c, 0x6d, 0x7e, 0x8f, 0xa0, 0xb1, 0xc2, 0xd3, 0xe4, 0xf5, 0x06, 0x17, 0x28,
+            0x39, 0x4a, 0x5b, 0x6c, 0x7d, 0x8e, 0x9f, 0xb0, 0xc1, 0xd2, 0xe3, 0xf4, 0x05, 0x16,
+            0x27, 0x38, 0x49, 0x5a, 0x6b, 0x7c, 0x8d, 0x9e, 0xaf, 0xc0, 0xd1, 0xe2, 0xf3, 0x04,
+            0x15, 0x26, 0x37, 0x48,
+        ],
+        digest: &[
+            0xd0, 0x68, 0x36, 0x82, 0x01, 0xcc, 0xfb, 0x68, 0xf5, 0x51, 0x4b, 0xc1, 0x05, 0xad,
+            0xab, 0xbf, 0x8f, 0xc3, 0xbd, 0x9b, 0x66, 0xe9, 0x10, 0x67, 0x8f, 0x38, 0xaa, 0xab,
+            0xae, 0xab, 0x1f, 0xff, 0x0d, 0x4f, 0xf6, 0xe4, 0xdd, 0xec, 0xa7, 0x2f, 0xcf, 0x56,
+            0xc3, 0x38, 0x28, 0x28, 0x5e, 0x5d,
+        ],
+    },
+];
+
+pub const SHA512: &[HashVector] = &[
+    HashVector {
+        msg: &[],
+        digest: &[
+            0xcf, 0x83, 0xe1, 0x35, 0x7e, 0xef, 0xb8, 0xbd, 0xf1, 0x54, 0x28, 0x50, 0xd6, 0x6d,
+            0x80, 0x07, 0xd6, 0x20, 0xe4, 0x05, 0x0b, 0x57, 0x15, 0xdc, 0x83, 0xf4, 0xa9, 0x21,
+            0xd3, 0x6c, 0xe9, 0xce, 0x47, 0xd0, 0xd1, 0x3c, 0x5d, 0x85, 0xf2, 0xb0, 0xff, 0x83,
+            0x18, 0xd2, 0x87, 0x7e, 0xec, 0x2f, 0x63, 0xb9, 0x31, 0xbd, 0x47, 0x41, 0x7a, 0x81,
+            0xa5, 0x38, 0x32, 0x7a, 0xf9, 0x27, 0xda, 0x3e,
+        ],
+    },
+    HashVector {
+        msg: &[0x11, 0x22, 0x33],
+        digest: &[
+            0x67, 0x82, 0x1c, 0xd1, 0xdc, 0x9c, 0x80, 0xee, 0x1f, 0x64, 0x8b, 0xe5, 0x39, 0x14,
+            0x97, 0xc2, 0x67, 0x27, 0xbc, 0x28, 0x1a, 0xe5, 0x90, 0x42, 0x37, 0xe9, 0xdf, 0x9b,
+            0xb0, 0x0a, 0x8d, 0x66, 0xc7, 0xe9, 0x91, 0x89, 0x63, 0xba, 0xad, 0xbd, 0x52, 0x4e,
+            0xef, 0x95, 0xf2, 0x34, 0xfa, 0x8f, 0x58, 0xb9, 0xcc, 0xb9, 0x0a, 0x65, 0x1c, 0x39,
+            0x19, 0x03, 0xcf, 0xcb, 0x0a, 0x5e, 0x16, 0xe9,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7,
+        ],
+        digest: &[
+            0xba, 0x30, 0x27, 0x6c, 0xa2, 0x22, 0x5f, 0xf1, 0xcb, 0x17, 0xf5, 0x03, 0x62, 0x59,
+            0xf3, 0x20, 0x57, 0x10, 0x32, 0x2a, 0x95, 0x0a, 0x56, 0x88, 0x6a, 0xac, 0xef, 0x4b,
+            0x26, 0xc0, 0xbe, 0x0f, 0xeb, 0x1d, 0x6c, 0xb9, 0x74, 0x40, 0x57, 0xf7, 0x25, 0x82,
+            0x85, 0x10, 0x56, 0xc4, 0x8d, 0xcc, 0xef, 0x25, 0x7e, 0xdc, 0x7f, 0x1b, 0x90, 0xb2,
+            0x2b, 0xb7, 0xde, 0xff, 0x7a, 0x51, 0x0d, 0xd1,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+        ],
+        digest: &[
+            0x36, 0xe3, 0xb9, 0x6b, 0xa2, 0xe7, 0x32, 0x86, 0x5d, 0xda, 0xb5, 0x5c, 0x63, 0x1a,
+            0xd5, 0x3e, 0xd8, 0xf3, 0x51, 0x55, 0x26, 0x71, 0x13, 0x31, 0x9b, 0xb2, 0xc6, 0x36,
+            0x90, 0x99, 0x12, 0x11, 0x20, 0xa5, 0xba, 0x76, 0x4e, 0xd9, 0x33, 0xfa, 0x16, 0x02,
+            0x71, 0x2d, 0x1b, 0x24, 0xce, 0xc3, 0x46, 0x9c, 0x2d, 0x9c, 0x77, 0x41, 0x91, 0xf2,
+            0x3f, 0x73, 0xc3, 0x14, 0xc6, 0x74, 0xf4, 0x03,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f,
+        ],
+        digest: &[
+            0xcc, 0x39, 0xd4, 0x98, 0x69, 0x45, 0xb0, 0x64, 0xe7, 0x64, 0x56, 0xf3, 0x77, 0x27,
+            0xf4, 0x40, 0xf7, 0xc8, 0xaa, 0x24, 0x13, 0x8f, 0xc2, 0xbc, 0xf1, 0x1e, 0x3c, 0x17,
+            0x0e, 0x7d, 0xff, 0x2f, 0xe8, 0x7d, 0x86, 0x66, 0xbc, 0x4b, 0x55, 0x1a, 0xe6, 0x15,
+            0xf1, 0x8a, 0x78, 0x4a, 0x93, 0x37, 0xfd, 0xd2, 0x1b, 0x93, 0xde, 0x36, 0x08, 0x92,
+            0x53, 0x5a, 0xe3, 0x81, 0x2c, 0x86, 0xb7, 0x2e,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40,
+        ],
+        digest: &[
+            0x55, 0x64, 0x78, 0x81, 0x0c, 0x57, 0x15, 0x84, 0x29, 0x91, 0x1c, 0x0e, 0x52, 0xd3,
+            0x81, 0x92, 0xad, 0xd9, 0x93, 0x7f, 0x2e, 0x38, 0x7a, 0xda, 0x7e, 0x43, 0x77, 0x82,
+            0x43, 0xc4, 0x5d, 0x71, 0x5a, 0x98, 0x2a, 0xe9, 0x05, 0x6b, 0x9f, 0xe9, 0x85, 0x80,
+            0xf2, 0x92, 0x71, 0xbb, 0xac, 0x41, 0xb8, 0x8c, 0x29, 0xfc, 0x08, 0xe8, 0x29, 0xe0,
+            0x80, 0xc3, 0xf2, 0x72, 0xb7, 0x05, 0x7e, 0x39,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51,
+        ],
+        digest: &[
+            0x31, 0xbb, 0x61, 0x2b, 0x08, 0x41, 0xa8, 0x55, 0x0e, 0x6e, 0x0f, 0xc8, 0x29, 0xd0,
+            0xde, 0x91, 0xa2, 0xe0, 0xed, 0xd5, 0x49, 0x32, 0x2d, 0x35, 0xf9, 0x1d, 0x86, 0x6e,
+            0xd8, 0xd6, 0x14, 0xbd, 0x57, 0x5a, 0x37, 0x54, 0x7c, 0xd0, 0x3d, 0x33, 0xe7, 0xf0,
+            0x04, 0x17, 0xe4, 0x4a, 0x73, 0xcf, 0xaa, 0xd1, 0x84, 0xc8, 0xaa, 0x70, 0xda, 0xbe,
+            0xf0, 0x93, 0xa1, 0x61, 0xc7, 0xdb, 0x83, 0xd8,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f,
+        ],
+        digest: &[
+            0x99, 0xa4, 0xae, 0x64, 0xb2, 0xa2, 0x75, 0xd4, 0xb8, 0x4a, 0xb8, 0xa8, 0xd2, 0x1d,
+            0x22, 0x6b, 0xf6, 0xd1, 0xc7, 0x2a, 0xa4, 0x89, 0x2d, 0x9d, 0x49, 0x65, 0xc4, 0x9e,
+            0x45, 0xee, 0x53, 0x38, 0x91, 0x2c, 0x3e, 0xdb, 0xf1, 0x75, 0x9d, 0x6d, 0x02, 0xc0,
+            0xea, 0xc1, 0xe9, 0x77, 0xdc, 0x6c, 0x1f, 0xd3, 0x00, 0x65, 0x25, 0xdf, 0x57, 0xa7,
+            0xe2, 0xf7, 0xc7, 0xbc, 0x6e, 0x84, 0xc9, 0x8c,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+        ],
+        digest: &[
+            0xab, 0x80, 0x28, 0x2e, 0x79, 0xe1, 0xe5, 0xf8, 0xf8, 0x90, 0x6e, 0x94, 0x8a, 0x08,
+            0xda, 0xa3, 0x7e, 0x76, 0x27, 0xbf, 0x1a, 0x82, 0xc5, 0xb3, 0x22, 0x1b, 0xf0, 0x16,
+            0x29, 0x7e, 0x3e, 0xcc, 0x50, 0xb9, 0xed, 0xf8, 0x05, 0xe3, 0x6c, 0xdf, 0xdb, 0xa2,
+            0x31, 0x2c, 0x03, 0x1d, 0x43, 0x53, 0x6c, 0x94, 0xa9, 0x14, 0x2d, 0xad, 0xe6, 0xae,
+            0xbf, 0x23, 0x6f, 0x12, 0x60, 0x5b, 0xf4, 0xf0,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f,
+        ],
+        digest: &[
+            0xae, 0xc8, 0x89, 0x3b, 0x90, 0xc9, 0x22, 0xcf, 0x30, 0xfd, 0x04, 0xaf, 0xe9, 0x4e,
+            0xde, 0xde, 0xcd, 0x52, 0x27, 0x2c, 0x1b, 0x7d, 0x8b, 0x24, 0x12, 0x91, 0x4b, 0x59,
+            0xb8, 0xb4, 0x40, 0x95, 0x2a, 0x2b, 0x2e, 0xe9, 0x26, 0xfd, 0xae, 0x82, 0x8a, 0x17,
+            0x03, 0xda, 0x3f, 0xdc, 0x77, 0xb1, 0x0c, 0x8e, 0xe5, 0xfe, 0xb3, 0xe9, 0xac, 0xbe,
+            0x4c, 0x46, 0x3c, 0x5e, 0xf9, 0x50, 0x13, 0x2c,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f, 0x80,
+        ],
+        digest: &[
+            0xe9, 0x9d, 0x3f, 0x3b, 0x27, 0x33, 0x78, 0x41, 0xeb, 0x34, 0x36, 0x25, 0x93, 0x42,
+            0x1f, 0xf6, 0x9d, 0xeb, 0xdd, 0x35, 0x58, 0x30, 0x73, 0x57, 0x49, 0x2a, 0x42, 0xdd,
+            0xeb, 0xdf, 0x80, 0xce, 0xf9, 0x0c, 0x84, 0xae, 0x1c, 0x5d, 0x48, 0x35, 0x31, 0x87,
+            0xd3, 0xcb, 0x23, 0xfb, 0x99, 0x48, 0xb5, 0xe6, 0x8a, 0x8b, 0x62, 0x59, 0x9f, 0x79,
+            0x62, 0xb5, 0x28, 0x1a, 0x07, 0xed, 0x61, 0x7d,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f, 0x80, 0x91,
+        ],
+        digest: &[
+            0x8f, 0xa8, 0x0d, 0x42, 0x58, 0xdc, 0xf9, 0x59, 0x2f, 0x65, 0x4c, 0x0f, 0xbf, 0x00,
+            0x37, 0xff, 0x5f, 0xea, 0xed, 0x9f, 0xb0, 0x6a, 0x67, 0x17, 0x4b, 0x10, 0xe8, 0x17,
+            0x18, 0x9d, 0x62, 0xf9, 0xc4, 0xfd, 0x2d, 0xf4, 0xba, 0xe8, 0x0d, 0xc5, 0x33, 0xab,
+            0xf8, 0x03, 0x42, 0x4f, 0x20, 0x34, 0x0a, 0xb9, 0x58, 0x39, 0x75, 0x33, 0xdb, 0xcf,
+            0x2a, 0x68, 0x71, 0x04, 0x48, 0x36, 0xfe, 0xd3,
+        ],
+    },
+    HashVector {
+        msg: &[
+            0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+            0xff, 0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xa9, 0xba, 0xcb, 0xdc,
+            0xed, 0xfe, 0x0f, 0x20, 0x31, 0x42, 0x53, 0x64, 0x75, 0x86, 0x97, 0xa8, 0xb9, 0xca,
+            0xdb, 0xec, 0xfd, 0x0e, 0x1f, 0x30, 0x41, 0x52, 0x63, 0x74, 0x85, 0x96, 0xa7, 0xb8,
+            0xc9, 0xda, 0xeb, 0xfc, 0x0d, 0x1e, 0x2f, 0x40, 0x51, 0x62, 0x73, 0x84, 0x95, 0xa6,
+            0xb7, 0xc8, 0xd9, 0xea, 0xfb, 0x0c, 0x1d, 0x2e, 0x3f, 0x50, 0x61, 0x72, 0x83, 0x94,
+            0xa5, 0xb6, 0xc7, 0xd8, 0xe9, 0xfa, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x60, 0x71, 0x82,
+            0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x0a, 0x1b, 0x2c, 0x3d, 0x4e, 0x5f, 0x70,
+            0x81, 0x92, 0xa3, 0xb4, 0xc5, 0xd6, 0xe7, 0xf8, 0x09, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e,
+            0x6f, 0x80, 0x91, 0xa2, 0xb3, 0xc4, 0xd5, 0xe6, 0xf7, 0x08, 0x19, 0x2a, 0x3b, 0x4c,
+            0x5d, 0x6e, 0x7f, 0x90, 0xa1, 0xb2, 0xc3, 0xd4, 0xe5, 0xf6, 0x07, 0x18, 0x29, 0x3a,
+            0x4b, 0x5c, 0x6d, 0x7e, 0x8f, 0xa0, 0xb1, 0xc2, 0xd3, 0xe4, 0xf5, 0x06, 0x17, 0x28,
+            0x39, 0x4a, 0x5b, 0x6c, 0x7d, 0x8e, 0x9f, 0xb0, 0xc1, 0xd2, 0xe3, 0xf4, 0x05, 0x16,
+            0x27, 0x38, 0x49, 0x5a, 0x6b, 0x7c, 0x8d, 0x9e, 0xaf, 0xc0, 0xd1, 0xe2, 0xf3, 0x04,
+            0x15, 0x26, 0x37, 0x48,
+        ],
+        digest: &[
+            0x31, 0x7f, 0xf3, 0x10, 0xb8, 0x1d, 0x48, 0xc2, 0x5e, 0xf0, 0xf6, 0x66, 0x6e, 0x70,
+            0xac, 0xc8, 0x14, 0xb3, 0x25, 0x72, 0x51, 0x16, 0xcb, 0xf4, 0x99, 0xeb, 0xaa, 0x9a,
+            0x0b, 0xa2, 0x4a, 0x47, 0x22, 0x82, 0xd6, 0x33, 0x39, 0xed, 0x30, 0xb5, 0xa6, 0x56,
+            0x4f, 0x34, 0x47, 0x29, 0x90, 0xa6, 0x5c, 0x0d, 0x5c, 0xf2, 0x11, 0x1c, 0xa6, 0x6f,
+            0xf5, 0x01, 0x38, 0xca, 0xe0, 0xcd, 0x82, 0x95,
+        ],
+    },
+];
+
+pub const HMAC_SHA256: &[HmacVector] = &[
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60,
+        ],
+        msg: &[],
+        mac: &[
+            0x05, 0x41, 0x0a, 0x17, 0x80, 0xd7, 0xe7, 0x21, 0x42, 0x12, 0x85, 0x82, 0x31, 0xf3,
+            0x66, 0xfb, 0xa0, 0xc3, 0x62, 0x03, 0xcb, 0xc4, 0x54, 0x58, 0x08, 0xe5, 0xe6, 0x77,
+            0x80, 0x37, 0x46, 0xc8,
+        ],
+    },
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60,
+        ],
+        msg: &[
+            0x23, 0x46, 0x69, 0x8c, 0xaf, 0xd2, 0xf5, 0x18, 0x3b, 0x5e, 0x81, 0xa4, 0xc7, 0xea,
+            0x0d, 0x30, 0x53, 0x76, 0x99, 0xbc, 0xdf, 0x02, 0x25, 0x48, 0x6b, 0x8e, 0xb1, 0xd4,
+            0xf7, 0x1a, 0x3d, 0x60, 0x83, 0xa6, 0xc9, 0xec, 0x0f, 0x32, 0x55, 0x78, 0x9b, 0xbe,
+            0xe1,
+        ],
+        mac: &[
+            0xe4, 0xab, 0xf6, 0x4a, 0x35, 0x7b, 0x1c, 0xb7, 0xad, 0xda, 0x75, 0x25, 0xb3, 0x5b,
+            0xfc, 0xe1, 0xd2, 0x75, 0xda, 0xe5, 0x40, 0x2d, 0x8a, 0x2f, 0x81, 0x33, 0x4d, 0x14,
+            0x26, 0xdf, 0x91, 0xfc,
+        ],
+    },
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60,
+        ],
+        msg: &[
+            0x23, 0x46, 0x69, 0x8c, 0xaf, 0xd2, 0xf5, 0x18, 0x3b, 0x5e, 0x81, 0xa4, 0xc7, 0xea,
+            0x0d, 0x30, 0x53, 0x76, 0x99, 0xbc, 0xdf, 0x02, 0x25, 0x48, 0x6b, 0x8e, 0xb1, 0xd4,
+            0xf7, 0x1a, 0x3d, 0x60, 0x83, 0xa6, 0xc9, 0xec, 0x0f, 0x32, 0x55, 0x78, 0x9b, 0xbe,
+            0xe1, 0x04, 0x27, 0x4a, 0x6d, 0x90, 0xb3, 0xd6, 0xf9, 0x1c, 0x3f, 0x62, 0x85, 0xa8,
+            0xcb, 0xee, 0x11, 0x34, 0x57, 0x7a, 0x9d, 0xc0,
+        ],
+        mac: &[
+            0x32, 0x1f, 0x5a, 0xf6, 0xc5, 0x5d, 0x70, 0x84, 0x34, 0x4d, 0x26, 0x35, 0x7a, 0x17,
+            0x24, 0x7c, 0xdb, 0x9e, 0x71, 0x1e, 0xa4, 0x2c, 0xa7, 0xe1, 0xd1, 0xd7, 0xc5, 0x25,
+            0x15, 0x40, 0xf9, 0x7e,
+        ],
+    },
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60,
+        ],
+        msg: &[
+            0x23, 0x46, 0x69, 0x8c, 0xaf, 0xd2, 0xf5, 0x18, 0x3b, 0x5e, 0x81, 0xa4, 0xc7, 0xea,
+            0x0d, 0x30, 0x53, 0x76, 0x99, 0xbc, 0xdf, 0x02, 0x25, 0x48, 0x6b, 0x8e, 0xb1, 0xd4,
+            0xf7, 0x1a, 0x3d, 0x60, 0x83, 0xa6, 0xc9, 0xec, 0x0f, 0x32, 0x55, 0x78, 0x9b, 0xbe,
+            0xe1, 0x04, 0x27, 0x4a, 0x6d, 0x90, 0xb3, 0xd6, 0xf9, 0x1c, 0x3f, 0x62, 0x85, 0xa8,
+            0xcb, 0xee, 0x11, 0x34, 0x57, 0x7a, 0x9d, 0xc0, 0xe3, 0x06, 0x29, 0x4c, 0x6f, 0x92,
+            0xb5, 0xd8, 0xfb, 0x1e, 0x41, 0x64, 0x87, 0xaa, 0xcd, 0xf0, 0x13, 0x36, 0x59, 0x7c,
+            0x9f, 0xc2, 0xe5, 0x08, 0x2b, 0x4e, 0x71, 0x94, 0xb7, 0xda, 0xfd, 0x20, 0x43, 0x66,
+            0x89, 0xac, 0xcf, 0xf2, 0x15, 0x38, 0x5b, 0x7e, 0xa1, 0xc4, 0xe7, 0x0a, 0x2d, 0x50,
+            0x73, 0x96, 0xb9, 0xdc, 0xff, 0x22, 0x45, 0x68, 0x8b, 0xae, 0xd1, 0xf4, 0x17, 0x3a,
+            0x5d, 0x80, 0xa3, 0xc6, 0xe9, 0x0c, 0x2f, 0x52, 0x75, 0x98, 0xbb, 0xde, 0x01, 0x24,
+            0x47, 0x6a, 0x8d, 0xb0, 0xd3, 0xf6, 0x19, 0x3c, 0x5f, 0x82, 0xa5, 0xc8, 0xeb, 0x0e,
+            0x31, 0x54, 0x77, 0x9a, 0xbd, 0xe0, 0x03, 0x26, 0x49, 0x6c, 0x8f, 0xb2, 0xd5, 0xf8,
+            0x1b, 0x3e, 0x61, 0x84, 0xa7, 0xca, 0xed, 0x10, 0x33, 0x56, 0x79, 0x9c, 0xbf, 0xe2,
+            0x05, 0x28, 0x4b, 0x6e, 0x91, 0xb4, 0xd7, 0xfa, 0x1d, 0x40, 0x63, 0x86, 0xa9, 0xcc,
+            0xef, 0x12, 0x35, 0x58,
+        ],
+        mac: &[
+            0x43, 0x27, 0x24, 0x81, 0xa5, 0xa1, 0xe2, 0xe2, 0x13, 0x83, 0xa1, 0x0e, 0xd0, 0x4e,
+            0x9f, 0x79, 0x72, 0xe1, 0x08, 0x15, 0x03, 0x64, 0x91, 0xf4, 0x07, 0x34, 0x18, 0x20,
+            0x73, 0xd0, 0x99, 0x90,
+        ],
+    },
+];
+
+pub const HMAC_SHA384: &[HmacVector] = &[
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60, 0x6b, 0x76, 0x81, 0x8c, 0x97, 0xa2, 0xad, 0xb8, 0xc3, 0xce,
+            0xd9, 0xe4, 0xef, 0xfa, 0x05, 0x10,
+        ],
+        msg: &[],
+        mac: &[
+            0xcf, 0xae, 0x71, 0xef, 0x21, 0x72, 0x66, 0xb7, 0xa7, 0x69, 0xb4, 0xa5, 0x45, 0x4b,
+            0xa9, 0x61, 0x4e, 0x5f, 0x27, 0x2e, 0x4a, 0x72, 0x0a, 0xab, 0x40, 0x96, 0x97, 0x6b,
+            0x5e, 0x77, 0x7c, 0x0a, 0x3d, 0xcd, 0xb4, 0x7e, 0x25, 0x7a, 0x93, 0xd7, 0x37, 0x32,
+            0x36, 0xfb, 0xdb, 0x20, 0xb8, 0x84,
+        ],
+    },
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60, 0x6b, 0x76, 0x81, 0x8c, 0x97, 0xa2, 0xad, 0xb8, 0xc3, 0xce,
+            0xd9, 0xe4, 0xef, 0xfa, 0x05, 0x10,
+        ],
+        msg: &[
+            0x23, 0x46, 0x69, 0x8c, 0xaf, 0xd2, 0xf5, 0x18, 0x3b, 0x5e, 0x81, 0xa4, 0xc7, 0xea,
+            0x0d, 0x30, 0x53, 0x76, 0x99, 0xbc, 0xdf, 0x02, 0x25, 0x48, 0x6b, 0x8e, 0xb1, 0xd4,
+            0xf7, 0x1a, 0x3d, 0x60, 0x83, 0xa6, 0xc9, 0xec, 0x0f, 0x32, 0x55, 0x78, 0x9b, 0xbe,
+            0xe1,
+        ],
+        mac: &[
+            0x9c, 0x6a, 0xee, 0x64, 0x1a, 0x33, 0xb6, 0x9b, 0x45, 0x76, 0x86, 0x8e, 0x6c, 0xe4,
+            0x6b, 0x0d, 0x5e, 0xec, 0x31, 0x61, 0x4d, 0x22, 0x0f, 0xa5, 0x55, 0x6f, 0xff, 0x20,
+            0x1e, 0x9c, 0x1e, 0x8d, 0xdb, 0xb1, 0x1c, 0x97, 0x62, 0xa5, 0x84, 0xa6, 0x37, 0x48,
+            0x6c, 0xa1, 0x74, 0x49, 0xa7, 0xe9,
+        ],
+    },
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60, 0x6b, 0x76, 0x81, 0x8c, 0x97, 0xa2, 0xad, 0xb8, 0xc3, 0xce,
+            0xd9, 0xe4, 0xef, 0xfa, 0x05, 0x10,
+        ],
+        msg: &[
+            0x23, 0x46, 0x69, 0x8c, 0xaf, 0xd2, 0xf5, 0x18, 0x3b, 0x5e, 0x81, 0xa4, 0xc7, 0xea,
+            0x0d, 0x30, 0x53, 0x76, 0x99, 0xbc, 0xdf, 0x02, 0x25, 0x48, 0x6b, 0x8e, 0xb1, 0xd4,
+            0xf7, 0x1a, 0x3d, 0x60, 0x83, 0xa6, 0xc9, 0xec, 0x0f, 0x32, 0x55, 0x78, 0x9b, 0xbe,
+            0xe1, 0x04, 0x27, 0x4a, 0x6d, 0x90, 0xb3, 0xd6, 0xf9, 0x1c, 0x3f, 0x62, 0x85, 0xa8,
+            0xcb, 0xee, 0x11, 0x34, 0x57, 0x7a, 0x9d, 0xc0,
+        ],
+        mac: &[
+            0x33, 0x52, 0x67, 0x2a, 0xb9, 0x6e, 0xf1, 0xb8, 0xd1, 0x4c, 0xc3, 0x2f, 0x40, 0x99,
+            0xf8, 0xbf, 0x4e, 0xf9, 0x16, 0xdc, 0xab, 0x56, 0x5c, 0x18, 0xc0, 0xd7, 0x56, 0xc6,
+            0x94, 0x34, 0x83, 0x6f, 0x46, 0x32, 0x16, 0x4e, 0x13, 0xe3, 0xef, 0xd5, 0xe0, 0x21,
+            0xdb, 0xb9, 0x1b, 0x90, 0x2e, 0xbf,
+        ],
+    },
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60, 0x6b, 0x76, 0x81, 0x8c, 0x97, 0xa2, 0xad, 0xb8, 0xc3, 0xce,
+            0xd9, 0xe4, 0xef, 0xfa, 0x05, 0x10,
+        ],
+        msg: &[
+            0x23, 0x46, 0x69, 0x8c, 0xaf, 0xd2, 0xf5, 0x18, 0x3b, 0x5e, 0x81, 0xa4, 0xc7, 0xea,
+            0x0d, 0x30, 0x53, 0x76, 0x99, 0xbc, 0xdf, 0x02, 0x25, 0x48, 0x6b, 0x8e, 0xb1, 0xd4,
+            0xf7, 0x1a, 0x3d, 0x60, 0x83, 0xa6, 0xc9, 0xec, 0x0f, 0x32, 0x55, 0x78, 0x9b, 0xbe,
+            0xe1, 0x04, 0x27, 0x4a, 0x6d, 0x90, 0xb3, 0xd6, 0xf9, 0x1c, 0x3f, 0x62, 0x85, 0xa8,
+            0xcb, 0xee, 0x11, 0x34, 0x57, 0x7a, 0x9d, 0xc0, 0xe3, 0x06, 0x29, 0x4c, 0x6f, 0x92,
+            0xb5, 0xd8, 0xfb, 0x1e, 0x41, 0x64, 0x87, 0xaa, 0xcd, 0xf0, 0x13, 0x36, 0x59, 0x7c,
+            0x9f, 0xc2, 0xe5, 0x08, 0x2b, 0x4e, 0x71, 0x94, 0xb7, 0xda, 0xfd, 0x20, 0x43, 0x66,
+            0x89, 0xac, 0xcf, 0xf2, 0x15, 0x38, 0x5b, 0x7e, 0xa1, 0xc4, 0xe7, 0x0a, 0x2d, 0x50,
+            0x73, 0x96, 0xb9, 0xdc, 0xff, 0x22, 0x45, 0x68, 0x8b, 0xae, 0xd1, 0xf4, 0x17, 0x3a,
+            0x5d, 0x80, 0xa3, 0xc6, 0xe9, 0x0c, 0x2f, 0x52, 0x75, 0x98, 0xbb, 0xde, 0x01, 0x24,
+            0x47, 0x6a, 0x8d, 0xb0, 0xd3, 0xf6, 0x19, 0x3c, 0x5f, 0x82, 0xa5, 0xc8, 0xeb, 0x0e,
+            0x31, 0x54, 0x77, 0x9a, 0xbd, 0xe0, 0x03, 0x26, 0x49, 0x6c, 0x8f, 0xb2, 0xd5, 0xf8,
+            0x1b, 0x3e, 0x61, 0x84, 0xa7, 0xca, 0xed, 0x10, 0x33, 0x56, 0x79, 0x9c, 0xbf, 0xe2,
+            0x05, 0x28, 0x4b, 0x6e, 0x91, 0xb4, 0xd7, 0xfa, 0x1d, 0x40, 0x63, 0x86, 0xa9, 0xcc,
+            0xef, 0x12, 0x35, 0x58,
+        ],
+        mac: &[
+            0x75, 0x38, 0xab, 0x97, 0x2e, 0x3d, 0x9e, 0x86, 0xaa, 0x13, 0x6e, 0x45, 0x32, 0x0d,
+            0xae, 0xd3, 0x0d, 0x7b, 0xf8, 0x6e, 0xf8, 0xd1, 0x7f, 0x13, 0xcc, 0x8d, 0xdc, 0xc6,
+            0x24, 0x13, 0x3e, 0xc1, 0xc7, 0x25, 0x00, 0x64, 0xee, 0x76, 0x6b, 0x4a, 0x3a, 0x61,
+            0x67, 0x22, 0x54, 0x17, 0x2c, 0x49,
+        ],
+    },
+];
+
+pub const HMAC_SHA512: &[HmacVector] = &[
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60, 0x6b, 0x76, 0x81, 0x8c, 0x97, 0xa2, 0xad, 0xb8, 0xc3, 0xce,
+            0xd9, 0xe4, 0xef, 0xfa, 0x05, 0x10, 0x1b, 0x26, 0x31, 0x3c, 0x47, 0x52, 0x5d, 0x68,
+            0x73, 0x7e, 0x89, 0x94, 0x9f, 0xaa, 0xb5, 0xc0,
+        ],
+        msg: &[],
+        mac: &[
+            0x21, 0x76, 0x01, 0x16, 0xec, 0x21, 0xa7, 0xcb, 0x6d, 0x80, 0x46, 0x72, 0x00, 0x6c,
+            0x52, 0x0a, 0x1f, 0x03, 0xfb, 0xd2, 0x69, 0x9c, 0x94, 0x97, 0x44, 0xa2, 0xdb, 0x3c,
+            0x28, 0x66, 0x8b, 0x5f, 0xac, 0xbb, 0xe8, 0xfe, 0x62, 0x0d, 0x20, 0xa6, 0xff, 0x99,
+            0xea, 0x58, 0xfe, 0xb9, 0x73, 0xbd, 0x53, 0x18, 0x35, 0xa6, 0x63, 0x7e, 0xdc, 0x8e,
+            0xba, 0x27, 0x0a, 0xa4, 0x2f, 0x38, 0x76, 0x47,
+        ],
+    },
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60, 0x6b, 0x76, 0x81, 0x8c, 0x97, 0xa2, 0xad, 0xb8, 0xc3, 0xce,
+            0xd9, 0xe4, 0xef, 0xfa, 0x05, 0x10, 0x1b, 0x26, 0x31, 0x3c, 0x47, 0x52, 0x5d, 0x68,
+            0x73, 0x7e, 0x89, 0x94, 0x9f, 0xaa, 0xb5, 0xc0,
+        ],
+        msg: &[
+            0x23, 0x46, 0x69, 0x8c, 0xaf, 0xd2, 0xf5, 0x18, 0x3b, 0x5e, 0x81, 0xa4, 0xc7, 0xea,
+            0x0d, 0x30, 0x53, 0x76, 0x99, 0xbc, 0xdf, 0x02, 0x25, 0x48, 0x6b, 0x8e, 0xb1, 0xd4,
+            0xf7, 0x1a, 0x3d, 0x60, 0x83, 0xa6, 0xc9, 0xec, 0x0f, 0x32, 0x55, 0x78, 0x9b, 0xbe,
+            0xe1,
+        ],
+        mac: &[
+            0x73, 0x27, 0xab, 0xc2, 0x2a, 0xe6, 0xf4, 0x32, 0x85, 0x65, 0x93, 0xa7, 0xb1, 0xa3,
+            0x66, 0x3f, 0x8a, 0xe7, 0xcf, 0x31, 0xca, 0x35, 0xcd, 0x79, 0x61, 0x51, 0xe8, 0x2c,
+            0x28, 0x06, 0xef, 0xf0, 0xd1, 0xe2, 0xb1, 0xd8, 0x89, 0x09, 0x28, 0x3a, 0xb6, 0x72,
+            0xb2, 0x08, 0x46, 0x5a, 0x19, 0xd0, 0x8e, 0x2f, 0xfa, 0x53, 0x71, 0x5d, 0xaa, 0x35,
+            0x2e, 0x07, 0xd9, 0xb6, 0xa5, 0x3b, 0xa7, 0xd1,
+        ],
+    },
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60, 0x6b, 0x76, 0x81, 0x8c, 0x97, 0xa2, 0xad, 0xb8, 0xc3, 0xce,
+            0xd9, 0xe4, 0xef, 0xfa, 0x05, 0x10, 0x1b, 0x26, 0x31, 0x3c, 0x47, 0x52, 0x5d, 0x68,
+            0x73, 0x7e, 0x89, 0x94, 0x9f, 0xaa, 0xb5, 0xc0,
+        ],
+        msg: &[
+            0x23, 0x46, 0x69, 0x8c, 0xaf, 0xd2, 0xf5, 0x18, 0x3b, 0x5e, 0x81, 0xa4, 0xc7, 0xea,
+            0x0d, 0x30, 0x53, 0x76, 0x99, 0xbc, 0xdf, 0x02, 0x25, 0x48, 0x6b, 0x8e, 0xb1, 0xd4,
+            0xf7, 0x1a, 0x3d, 0x60, 0x83, 0xa6, 0xc9, 0xec, 0x0f, 0x32, 0x55, 0x78, 0x9b, 0xbe,
+            0xe1, 0x04, 0x27, 0x4a, 0x6d, 0x90, 0xb3, 0xd6, 0xf9, 0x1c, 0x3f, 0x62, 0x85, 0xa8,
+            0xcb, 0xee, 0x11, 0x34, 0x57, 0x7a, 0x9d, 0xc0,
+        ],
+        mac: &[
+            0x96, 0x0b, 0xf2, 0x81, 0x96, 0xe7, 0x84, 0xed, 0x90, 0x1d, 0xb6, 0x3f, 0xcb, 0x29,
+            0x2b, 0x62, 0x62, 0x2c, 0x47, 0xc1, 0x85, 0x84, 0xff, 0x87, 0xdb, 0xbc, 0xce, 0x43,
+            0x49, 0xb5, 0xb8, 0xb3, 0x53, 0xf6, 0x65, 0xdc, 0xd5, 0xf2, 0xbf, 0xaa, 0x51, 0x2e,
+            0x2d, 0x73, 0x90, 0xa1, 0x32, 0xd4, 0x9d, 0xdf, 0xbc, 0x4b, 0xb1, 0x65, 0xd4, 0x0d,
+            0x31, 0x15, 0xc3, 0xc2, 0x6f, 0x43, 0x7a, 0x20,
+        ],
+    },
+    HmacVector {
+        key: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2, 0xfd, 0x08, 0x13, 0x1e, 0x29, 0x34,
+            0x3f, 0x4a, 0x55, 0x60, 0x6b, 0x76, 0x81, 0x8c, 0x97, 0xa2, 0xad, 0xb8, 0xc3, 0xce,
+            0xd9, 0xe4, 0xef, 0xfa, 0x05, 0x10, 0x1b, 0x26, 0x31, 0x3c, 0x47, 0x52, 0x5d, 0x68,
+            0x73, 0x7e, 0x89, 0x94, 0x9f, 0xaa, 0xb5, 0xc0,
+        ],
+        msg: &[
+            0x23, 0x46, 0x69, 0x8c, 0xaf, 0xd2, 0xf5, 0x18, 0x3b, 0x5e, 0x81, 0xa4, 0xc7, 0xea,
+            0x0d, 0x30, 0x53, 0x76, 0x99, 0xbc, 0xdf, 0x02, 0x25, 0x48, 0x6b, 0x8e, 0xb1, 0xd4,
+            0xf7, 0x1a, 0x3d, 0x60, 0x83, 0xa6, 0xc9, 0xec, 0x0f, 0x32, 0x55, 0x78, 0x9b, 0xbe,
+            0xe1, 0x04, 0x27, 0x4a, 0x6d, 0x90, 0xb3, 0xd6, 0xf9, 0x1c, 0x3f, 0x62, 0x85, 0xa8,
+            0xcb, 0xee, 0x11, 0x34, 0x57, 0x7a, 0x9d, 0xc0, 0xe3, 0x06, 0x29, 0x4c, 0x6f, 0x92,
+            0xb5, 0xd8, 0xfb, 0x1e, 0x41, 0x64, 0x87, 0xaa, 0xcd, 0xf0, 0x13, 0x36, 0x59, 0x7c,
+            0x9f, 0xc2, 0xe5, 0x08, 0x2b, 0x4e, 0x71, 0x94, 0xb7, 0xda, 0xfd, 0x20, 0x43, 0x66,
+            0x89, 0xac, 0xcf, 0xf2, 0x15, 0x38, 0x5b, 0x7e, 0xa1, 0xc4, 0xe7, 0x0a, 0x2d, 0x50,
+            0x73, 0x96, 0xb9, 0xdc, 0xff, 0x22, 0x45, 0x68, 0x8b, 0xae, 0xd1, 0xf4, 0x17, 0x3a,
+            0x5d, 0x80, 0xa3, 0xc6, 0xe9, 0x0c, 0x2f, 0x52, 0x75, 0x98, 0xbb, 0xde, 0x01, 0x24,
+            0x47, 0x6a, 0x8d, 0xb0, 0xd3, 0xf6, 0x19, 0x3c, 0x5f, 0x82, 0xa5, 0xc8, 0xeb, 0x0e,
+            0x31, 0x54, 0x77, 0x9a, 0xbd, 0xe0, 0x03, 0x26, 0x49, 0x6c, 0x8f, 0xb2, 0xd5, 0xf8,
+            0x1b, 0x3e, 0x61, 0x84, 0xa7, 0xca, 0xed, 0x10, 0x33, 0x56, 0x79, 0x9c, 0xbf, 0xe2,
+            0x05, 0x28, 0x4b, 0x6e, 0x91, 0xb4, 0xd7, 0xfa, 0x1d, 0x40, 0x63, 0x86, 0xa9, 0xcc,
+            0xef, 0x12, 0x35, 0x58,
+        ],
+        mac: &[
+            0xb1, 0x5a, 0x0a, 0x08, 0xbb, 0x4b, 0x51, 0x91, 0xcb, 0xf5, 0x61, 0x0d, 0x65, 0x77,
+            0xd8, 0x5a, 0x06, 0xa1, 0x99, 0xc2, 0x7e, 0x7f, 0x3b, 0xd6, 0x81, 0x64, 0x87, 0xe2,
+            0xe3, 0x9a, 0x6e, 0x82, 0xa2, 0xc4, 0xed, 0x85, 0xd4, 0xfd, 0x0e, 0xd8, 0x2f, 0x47,
+            0x79, 0xb6, 0x9f, 0x98, 0xaf, 0xa3, 0x20, 0xb0, 0xd3, 0xbe, 0xfb, 0x36, 0x3d, 0x60,
+            0x30, 0x89, 0x74, 0xee, 0x9f, 0xad, 0x28, 0xab,
+        ],
+    },
+];
+
+pub const HKDF_SHA256: &[HkdfVector] = &[
+    HkdfVector {
+        ikm: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2,
+        ],
+        salt: &[
+            0x30, 0x61, 0x92, 0xc3, 0xf4, 0x25, 0x56, 0x87, 0xb8, 0xe9, 0x1a, 0x4b, 0x7c,
+        ],
+        info: &[0xf0, 0xe1, 0xd2, 0xc3, 0xb4, 0xa5, 0x96, 0x87, 0x78, 0x69],
+        okm: &[
+            0xb9, 0x31, 0xf6, 0xab, 0x1c, 0x5c, 0x87, 0xdf, 0x15, 0x29, 0xf4, 0x24, 0x07, 0x18,
+            0xfc, 0xd5, 0x10, 0xed, 0x34, 0xc0, 0xcc, 0xec, 0xd6, 0xab, 0x1d, 0xf8, 0x63, 0x75,
+            0xbf, 0x9f, 0xfd, 0x84,
+        ],
+    },
+    HkdfVector {
+        ikm: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2,
+        ],
+        salt: &[
+            0x30, 0x61, 0x92, 0xc3, 0xf4, 0x25, 0x56, 0x87, 0xb8, 0xe9, 0x1a, 0x4b, 0x7c,
+        ],
+        info: &[0xf0, 0xe1, 0xd2, 0xc3, 0xb4, 0xa5, 0x96, 0x87, 0x78, 0x69],
+        okm: &[
+            0xb9, 0x31, 0xf6, 0xab, 0x1c, 0x5c, 0x87, 0xdf, 0x15, 0x29, 0xf4, 0x24, 0x07, 0x18,
+            0xfc, 0xd5, 0x10, 0xed, 0x34, 0xc0, 0xcc, 0xec, 0xd6, 0xab, 0x1d, 0xf8, 0x63, 0x75,
+            0xbf, 0x9f, 0xfd, 0x84, 0x83, 0x21, 0xf1, 0x49, 0x2e, 0xd5, 0xf6, 0xfa, 0x98, 0xdf,
+        ],
+    },
+    HkdfVector {
+        ikm: &[
+            0x0b, 0x16, 0x21, 0x2c, 0x37, 0x42, 0x4d, 0x58, 0x63, 0x6e, 0x79, 0x84, 0x8f, 0x9a,
+            0xa5, 0xb0, 0xbb, 0xc6, 0xd1, 0xdc, 0xe7, 0xf2,
+        ],
+        salt: &[
+            0x30, 0x61, 0x92, 0xc3, 0xf4, 0x25, 0x56, 0x87, 0xb8, 0xe9, 0x1a, 0x4b, 0x7c,
+        ],
+        info: &[0xf0, 0xe1, 0xd2, 0xc3, 0xb4, 0xa5, 0x96, 0x87, 0x78, 0x69],
+        okm: &[
+            0xb9, 0x31, 0xf6, 0xab, 0x1c, 0x5c, 0x87, 0xdf, 0x15, 0x29, 0xf4, 0x24, 0x07, 0x18,
+            0xfc, 0xd5, 0x10, 0xed, 0x34, 0xc0, 0xcc, 0xec, 0xd6, 0xab, 0x1d, 0xf8, 0x63, 0x75,
+            0xbf, 0x9f, 0xfd, 0x84, 0x83, 0x21, 0xf1, 0x49, 0x2e, 0xd5, 0xf6, 0xfa, 0x98, 0xdf,
+            0x7e, 0xfd, 0x6b, 0x20, 0x63, 0xb2, 0x27, 0x5c, 0x54, 0xc1, 0xe4, 0xcf, 0x08, 0x66,
+            0x99, 0x0a, 0xc0, 0xfb, 0x57, 0x79, 0x5f, 0x38, 0xd9, 0xcc, 0xd3, 0x2b, 0x12, 0xc9,
+            0xe3, 0xb3, 0x79, 0x56, 0xe9, 0x0f, 0x33, 0xcb, 0x70, 0x9a, 0x5a, 0x34,
+        ],
+    },
+];
@@ -1,5 +1,7 @@
 // Licensed under the Apache-2.0 license
 
+use crate::common::read_down_counter;
+use crate::syscon::{self, ClockConsumer, ClockId};
 use core::fmt;
 use core::marker::PhantomData;
 use embedded_hal_old::timer::{Cancel, CountDown, Periodic};
@@ -21,6 +23,10 @@ pub enum TimerError {
 
 const MAX_TIMEOUT_MS: u32 = 4_294_967;
 const MATCH_DISABLE: u32 = 0xffff_ffff;
+/// Time two back-to-back counter reads may drift apart by
+const COUNTER_READ_DRIFT_US: u32 = 2;
+/// Read pairs tried before a counter read gives up settling
+const COUNTER_READ_ATTEMPTS: usize = 4;
 
 /// Trait to abstract timer register base + index
 pub trait TimerInstance {
@@ -54,6 +60,10 @@ pub struct TimerController<T: TimerInstance> {
     _marker: PhantomData<T>,
 }
 
+// The references only reach the timer's own registers, which the PAC's
+// `Send` peripheral handles already let any context use
+unsafe impl<T: TimerInstance + Send> Send for TimerController<T> {}
+
 impl<T: TimerInstance> fmt::Debug for TimerController<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str("TimerController")
@@ -80,13 +90,31 @@ impl<T: TimerInstance> TimerController<T> {
         self.cr.timer000().read().bits()
     }
 
-    /// Stop the timer and clear reload
+    /// Current counter value, read until two back-to-back reads agree so a
+    /// read racing the count or a reload is not returned
+    #[must_use]
+    pub fn current(&self) -> u32 {
+        read_down_counter(
+            || self.cr.timer000().read().bits(),
+            self.tick_per_us * COUNTER_READ_DRIFT_US,
+            COUNTER_READ_ATTEMPTS,
+        )
+    }
+
+    /// Time left until the running count reaches zero
+    #[must_use]
+    pub fn remaining(&self) -> MicroSeconds {
+        MicroSeconds::from_ticks(self.current() / self.tick_per_us)
+    }
+
+    /// Stop the timer and clear reload and a pending expiry
     pub fn stop(&mut self) {
         let index = T::index();
         self.gr
             .timerg03c()
             .write(|w| unsafe { w.bits(1 << (4 * index)) });
         self.cr.timer004().write(|w| unsafe { w.bits(0) });
+        self.gr.timerg034().write(|w| unsafe { w.bits(1 << index) });
     }
 
     /// Handle timer interrupt (user calls this in IRQ handler)
@@ -109,6 +137,34 @@ impl<T: TimerInstance> TimerController<T> {
     }
 }
 
+/// Timers count APB clock ticks, so the tick rate follows PCLK changes.
+/// A running countdown keeps its old reload value until restarted.
+impl<T: TimerInstance> ClockConsumer for TimerController<T> {
+    fn clock_id(&self) -> ClockId {
+        ClockId::ClkPCLK
+    }
+
+    fn reconfigure_for_clock(&mut self, freq_hz: u32) -> Result<(), syscon::Error> {
+        let tick_per_us = freq_hz / 1_000_000;
+        if tick_per_us == 0 {
+            return Err(syscon::Error::InvalidClockFrequency);
+        }
+        self.tick_per_us = tick_per_us;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rt")]
+impl crate::interrupts::IrqHandler for TimerController<ast1060_pac::Timer> {
+    fn irq_number(&self) -> ast1060_pac::Interrupt {
+        ast1060_pac::Interrupt::timer
+    }
+
+    fn on_irq(&mut self) {
+        self.handle_interrupt();
+    }
+}
+
 impl<T: TimerInstance> CountDown for TimerController<T> {
     type Time = MicroSeconds;
     type Error = TimerError;
@@ -164,3 +220,81 @@ impl<T: TimerInstance> Cancel for TimerController<T> {
 }
 
 impl<T: TimerInstance> Periodic for TimerController<T> {}
+
+/// Non-blocking one-shot delay on a hardware timer, for loops that poll
+/// it between other work.
+///
+/// `start` arms the timer and `wait` returns `WouldBlock` until the time
+/// has passed. Delays longer than one timer count are run as several
+/// laps. The timer's interrupt must not be handled while the delay owns
+/// it, as the handler would clear the expiry `wait` looks for.
+pub struct NbDelay<T: TimerInstance> {
+    timer: TimerController<T>,
+    /// Microseconds still to run after the current lap
+    left_us: u32,
+    running: bool,
+}
+
+impl<T: TimerInstance> NbDelay<T> {
+    #[must_use]
+    pub fn new(timer: TimerController<T>) -> Self {
+        Self {
+            timer,
+            left_us: 0,
+            running: false,
+        }
+    }
+
+    /// Starts a delay of `duration`, replacing one still running
+    pub fn start(&mut self, duration: MicroSeconds) -> Result<(), TimerError> {
+        self.timer.stop();
+        self.left_us = duration.ticks();
+        self.running = false;
+        if self.left_us > 0 {
+            self.next_lap()?;
+            self.running = true;
+        }
+        Ok(())
+    }
+
+    /// `Ok` once the delay has passed, `WouldBlock` before. An expired or
+    /// cancelled delay stays `Ok` until the next `start`.
+    pub fn wait(&mut self) -> nb::Result<(), TimerError> {
+        if !self.running {
+            return Ok(());
+        }
+        self.timer.try_wait()?;
+        if self.left_us == 0 {
+            self.cancel();
+            return Ok(());
+        }
+        self.next_lap()?;
+        Err(nb::Error::WouldBlock)
+    }
+
+    pub fn cancel(&mut self) {
+        self.timer.stop();
+        self.left_us = 0;
+        self.running = false;
+    }
+
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Stops the delay and gives the timer back
+    #[must_use]
+    pub fn free(mut self) -> TimerController<T> {
+        self.cancel();
+        self.timer
+    }
+
+    /// Arms the longest lap the timer can count at the current tick rate
+    fn next_lap(&mut self) -> Result<(), TimerError> {
+        let max = (u32::MAX / self.timer.tick_per_us.max(1)).min(MAX_TIMEOUT_MS * 1000 - 1);
+        let lap = self.left_us.min(max);
+        self.left_us -= lap;
+        self.timer.try_start(MicroSeconds::from_ticks(lap))
+    }
+}
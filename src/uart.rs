@@ -5,6 +5,8 @@ use embedded_hal::delay::DelayNs;
 use embedded_io::ErrorKind;
 use embedded_io::ErrorType;
 
+pub mod early;
+
 #[derive(Debug)]
 pub enum Uart16550Error {
     Overrun,
@@ -56,6 +58,15 @@ pub enum WordLength {
     Eight,
 }
 
+// Modem control register (UART010) and its loopback bit, as on the 16550
+const UART_MCR_OFFSET: usize = 0x10;
+const UART_MCR_LOOP: u32 = 1 << 4;
+
+/// Divisor latch value for `baud` from a UART clock of `clock` Hz
+fn baud_divisor(clock: u32, baud: u32) -> u16 {
+    u16::try_from((clock / 13) / (16 * baud)).unwrap()
+}
+
 pub struct UartController<'a> {
     uart: Uart,
     delay: &'a mut dyn DelayNs,
@@ -81,8 +92,7 @@ impl UartController<'_> {
     /// ```
     pub unsafe fn init(&self, config: &Config) {
         // Calculate baud divisor
-        let raw = (config.clock / 13) / (16 * config.baud_rate);
-        let baud_divisor = u16::try_from(raw).unwrap();
+        let baud_divisor = baud_divisor(config.clock, config.baud_rate);
 
         // Enable DLAB to access divisor latch registers
         self.uart.uartlcr().write(|w| w.dlab().set_bit());
@@ -163,6 +173,26 @@ impl UartController<'_> {
 
         Ok(())
     }
+
+    /// Enables only the received data interrupt, for `UartRxIrq`; the THR
+    /// empty interrupt `init` also enables would fire continuously
+    pub fn enable_rx_interrupt(&mut self) {
+        self.uart.uartier().write(|w| w.erbfi().set_bit());
+    }
+
+    /// Routes transmitted bytes back to the receiver instead of the pins
+    pub fn set_loopback(&mut self, enable: bool) {
+        let mcr = (Uart::ptr() as usize + UART_MCR_OFFSET) as *mut u32;
+        unsafe {
+            let value = core::ptr::read_volatile(mcr);
+            let value = if enable {
+                value | UART_MCR_LOOP
+            } else {
+                value & !UART_MCR_LOOP
+            };
+            core::ptr::write_volatile(mcr, value);
+        }
+    }
 }
 
 impl<'a> UartController<'a> {
@@ -230,3 +260,36 @@ impl embedded_io::Write for UartController<'_> {
         Ok(())
     }
 }
+
+/// Receive interrupt handler that moves bytes from the RX FIFO into a queue,
+/// for the application to drain through the queue's consumer
+///
+/// Register it after [`UartController::enable_rx_interrupt`]. Bytes that
+/// arrive while the queue is full are dropped.
+#[cfg(feature = "rt")]
+pub struct UartRxIrq<const N: usize> {
+    rx: heapless::spsc::Producer<'static, u8, N>,
+}
+
+#[cfg(feature = "rt")]
+impl<const N: usize> UartRxIrq<N> {
+    #[must_use]
+    pub fn new(rx: heapless::spsc::Producer<'static, u8, N>) -> Self {
+        Self { rx }
+    }
+}
+
+#[cfg(feature = "rt")]
+impl<const N: usize> crate::interrupts::IrqHandler for UartRxIrq<N> {
+    fn irq_number(&self) -> ast1060_pac::Interrupt {
+        ast1060_pac::Interrupt::uart
+    }
+
+    fn on_irq(&mut self) {
+        // the interrupt clears once the FIFO is empty
+        let uart = unsafe { &*Uart::ptr() };
+        while uart.uartlsr().read().dr().bit_is_set() {
+            let _ = self.rx.enqueue(uart.uartrbr().read().uartrbr().bits());
+        }
+    }
+}
@@ -0,0 +1,72 @@
+// Licensed under the Apache-2.0 license
+
+//! Polled console output for before [`UartController`](super::UartController)
+//! is set up
+//!
+//! Assumes the ROM left the console UART clocked and pinmuxed, and only
+//! programs the divisor and line format. Output is written straight to THR
+//! with no buffering, so it is slow but works from `pre_init` and the panic
+//! handler.
+
+use super::baud_divisor;
+use ast1060_pac::Uart;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+fn regs() -> &'static ast1060_pac::uart::RegisterBlock {
+    unsafe { &*Uart::ptr() }
+}
+
+/// Program the divisor for `baud` from a UART clock of `clock` Hz, 8N1 with
+/// the FIFOs enabled
+///
+/// # Safety
+///
+/// Writes the console UART registers behind the back of any
+/// `UartController`. Only call this before `main`, or after the controller
+/// can no longer run (the panic handler).
+pub unsafe fn early_init(baud: u32, clock: u32) {
+    let uart = regs();
+    let [dll, dlh] = baud_divisor(clock, baud).to_le_bytes();
+    uart.uartlcr().write(|w| w.dlab().set_bit());
+    uart.uartdll()
+        .write(|w| w.the_lsbof_the_bd_divisor_latch().bits(dll));
+    uart.uartdlh()
+        .write(|w| w.the_msbof_the_bd_divisor_latch().bits(dlh));
+    uart.uartlcr().write(|w| w.cls().bits(0b11));
+    uart.uartfcr().write(|w| {
+        w.enbl_uartfifo().set_bit();
+        w.rx_fiforst().set_bit();
+        w.tx_fiforst().set_bit()
+    });
+}
+
+/// Write `s`, polling THR for each byte
+///
+/// # Safety
+///
+/// Same as [`early_init`].
+pub unsafe fn early_print(s: &str) {
+    for byte in s.bytes() {
+        putc(byte);
+    }
+}
+
+/// Write `value` as `0x` followed by eight hex digits
+///
+/// # Safety
+///
+/// Same as [`early_init`].
+pub unsafe fn early_hex(value: u32) {
+    putc(b'0');
+    putc(b'x');
+    for shift in (0..8).rev() {
+        putc(HEX_DIGITS[((value >> (shift * 4)) & 0xf) as usize]);
+    }
+}
+
+unsafe fn putc(byte: u8) {
+    let uart = regs();
+    while uart.uartlsr().read().thre().bit_is_clear() {}
+    uart.uartthr().write(|w| w.bits(u32::from(byte)));
+}
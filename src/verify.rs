@@ -0,0 +1,84 @@
+// Licensed under the Apache-2.0 license
+
+//! Measurement of flash regions for secure boot
+//!
+//! [`measure_region`] streams a range of erase sectors through the hash
+//! engine, feeding the watchdog between sectors, and returns the digest
+//! with the number of bytes measured.
+
+use crate::hace_controller::HaceController;
+use crate::hash_owned::{IntoHashAlgo, OwnedDigestContext};
+use crate::spi::norflash::SPI_NOR_SECTOR_SIZE;
+use crate::spi::norflashblockdevice::BlockAddrUsize;
+use embedded_hal_old::watchdog::Watchdog;
+use openprot_hal_blocking::digest::owned::{DigestInit, DigestOp};
+use openprot_hal_blocking::digest::DigestAlgorithm;
+use proposed_traits::block_device::{BlockDevice, BlockRange};
+
+/// Bytes read from flash and hashed per step
+const MEASURE_CHUNK: usize = SPI_NOR_SECTOR_SIZE;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MeasureError<E> {
+    /// The range does not fit in the device
+    OutOfBounds,
+    /// The watchdog could not be fed between chunks
+    Watchdog,
+    Flash(E),
+}
+
+/// Hashes the erase sectors in `range` with `algo`.
+///
+/// The region is read in sector-sized chunks and the watchdog is fed after
+/// each one, so large partitions can be measured under a running watchdog.
+/// Returns the digest, the number of bytes measured and the controller; on
+/// error the digest is abandoned and the controller handed back.
+pub fn measure_region<D, W, A>(
+    nor: &mut D,
+    wdt: &mut W,
+    hace: HaceController,
+    range: BlockRange<BlockAddrUsize>,
+    algo: A,
+) -> Result<
+    (
+        <OwnedDigestContext<A> as DigestOp>::Output,
+        u64,
+        HaceController,
+    ),
+    (MeasureError<D::Error>, HaceController),
+>
+where
+    D: BlockDevice<Address = BlockAddrUsize>,
+    W: Watchdog,
+    A: DigestAlgorithm + IntoHashAlgo,
+    HaceController: DigestInit<A, Context = OwnedDigestContext<A>>,
+    OwnedDigestContext<A>: DigestOp<Controller = HaceController>,
+{
+    let start = range.start.to_byte_offset();
+    let end = range
+        .count
+        .checked_mul(nor.erase_size())
+        .and_then(|len| len.checked_add(start))
+        .filter(|&end| end <= nor.capacity());
+    let Some(end) = end else {
+        return Err((MeasureError::OutOfBounds, hace));
+    };
+
+    let mut ctx = hace.init(algo).unwrap();
+    let mut chunk = [0u8; MEASURE_CHUNK];
+    let mut addr = start;
+    while addr < end {
+        let chunk = &mut chunk[..MEASURE_CHUNK.min(end - addr)];
+        if let Err(e) = nor.read(BlockAddrUsize(addr), chunk) {
+            return Err((MeasureError::Flash(e), ctx.cancel()));
+        }
+        ctx = ctx.update(chunk).unwrap();
+        if wdt.try_feed().is_err() {
+            return Err((MeasureError::Watchdog, ctx.cancel()));
+        }
+        addr += chunk.len();
+    }
+
+    let (digest, hace) = ctx.finalize().unwrap();
+    Ok((digest, u64::try_from(end - start).unwrap(), hace))
+}
@@ -0,0 +1,320 @@
+// Licensed under the Apache-2.0 license
+
+//! What firmware is running
+//!
+//! [`FIRMWARE`] is fixed at build time: the crate version, the commit and
+//! build time that `build.rs` passes in, and the cargo features the image
+//! was built with. Setting `ASPEED_GIT_HASH` and `SOURCE_DATE_EPOCH` makes
+//! the build reproducible; otherwise `build.rs` asks git and the clock, and
+//! falls back to zeros.
+//!
+//! [`FirmwareInfo::to_regs`] lays it out as [`VERSION_REGS`] little-endian
+//! words for the mailbox version registers:
+//!
+//! | word | contents                         |
+//! |------|----------------------------------|
+//! | 0    | major (high half), minor         |
+//! | 1    | patch (high half), [`FORMAT`]    |
+//! | 2    | feature bitmap, see [`feature`]  |
+//! | 3, 4 | build timestamp, low word first  |
+//! | 5..  | git commit hash, 20 bytes        |
+
+use crate::uart::UartController;
+use embedded_io::Write;
+
+/// Layout version of [`FirmwareInfo::to_regs`]
+pub const FORMAT: u16 = 1;
+pub const GIT_HASH_LEN: usize = 20;
+/// Words of the serialized form
+pub const VERSION_REGS: usize = 5 + GIT_HASH_LEN / 4;
+/// Version registers of the mailbox; the serialized form must fit
+pub const VERSION_REG_BUDGET: usize = 16;
+
+/// Bits of [`FirmwareInfo::features`]
+pub mod feature {
+    pub const CACHE: u32 = 1 << 0;
+    pub const SHA384: u32 = 1 << 1;
+    pub const SHA512: u32 = 1 << 2;
+    pub const HMAC: u32 = 1 << 3;
+    pub const RSA: u32 = 1 << 4;
+    pub const ECDSA: u32 = 1 << 5;
+    pub const I2C_TARGET: u32 = 1 << 6;
+    pub const RT: u32 = 1 << 7;
+    pub const SPI_DMA: u32 = 1 << 8;
+    pub const SPI_DMA_WRITE: u32 = 1 << 9;
+    pub const SPI_MONITOR: u32 = 1 << 10;
+    pub const TEST_UTILS: u32 = 1 << 11;
+    pub const HASH_CALIBRATION: u32 = 1 << 12;
+
+    /// Names as in `Cargo.toml`, by bit
+    pub const NAMES: [(u32, &str); 13] = [
+        (CACHE, "cache"),
+        (SHA384, "sha384"),
+        (SHA512, "sha512"),
+        (HMAC, "hmac"),
+        (RSA, "rsa"),
+        (ECDSA, "ecdsa"),
+        (I2C_TARGET, "i2c_target"),
+        (RT, "rt"),
+        (SPI_DMA, "spi_dma"),
+        (SPI_DMA_WRITE, "spi_dma_write"),
+        (SPI_MONITOR, "spi_monitor"),
+        (TEST_UTILS, "test-utils"),
+        (HASH_CALIBRATION, "hash-calibration"),
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Semver {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirmwareInfo {
+    pub semver: Semver,
+    /// All zeros when unknown
+    pub git_hash: [u8; GIT_HASH_LEN],
+    /// Seconds since the Unix epoch; 0 when unknown
+    pub build_timestamp: u64,
+    pub features: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionError {
+    /// The registers use a layout this build does not know
+    Format(u16),
+}
+
+/// This image
+pub const FIRMWARE: FirmwareInfo = FirmwareInfo {
+    semver: Semver {
+        major: parse_u16(env!("CARGO_PKG_VERSION_MAJOR")),
+        minor: parse_u16(env!("CARGO_PKG_VERSION_MINOR")),
+        patch: parse_u16(env!("CARGO_PKG_VERSION_PATCH")),
+    },
+    git_hash: parse_hash(env!("ASPEED_GIT_HASH")),
+    build_timestamp: parse_u64(env!("ASPEED_BUILD_TIMESTAMP")),
+    features: enabled_features(),
+};
+
+const fn enabled_features() -> u32 {
+    let mut bits = 0;
+    let enabled = [
+        (feature::CACHE, cfg!(feature = "cache")),
+        (feature::SHA384, cfg!(feature = "sha384")),
+        (feature::SHA512, cfg!(feature = "sha512")),
+        (feature::HMAC, cfg!(feature = "hmac")),
+        (feature::RSA, cfg!(feature = "rsa")),
+        (feature::ECDSA, cfg!(feature = "ecdsa")),
+        (feature::I2C_TARGET, cfg!(feature = "i2c_target")),
+        (feature::RT, cfg!(feature = "rt")),
+        (feature::SPI_DMA, cfg!(feature = "spi_dma")),
+        (feature::SPI_DMA_WRITE, cfg!(feature = "spi_dma_write")),
+        (feature::SPI_MONITOR, cfg!(feature = "spi_monitor")),
+        (feature::TEST_UTILS, cfg!(feature = "test-utils")),
+        (
+            feature::HASH_CALIBRATION,
+            cfg!(feature = "hash-calibration"),
+        ),
+    ];
+    let mut i = 0;
+    while i < enabled.len() {
+        if enabled[i].1 {
+            bits |= enabled[i].0;
+        }
+        i += 1;
+    }
+    bits
+}
+
+/// Decimal digits, saturating; anything else reads as 0
+const fn parse_u64(s: &str) -> u64 {
+    let bytes = s.as_bytes();
+    let mut value = 0u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() {
+            return 0;
+        }
+        value = value
+            .saturating_mul(10)
+            .saturating_add((bytes[i] - b'0') as u64);
+        i += 1;
+    }
+    value
+}
+
+const fn parse_u16(s: &str) -> u16 {
+    let value = parse_u64(s);
+    let bytes = value.to_le_bytes();
+    if value > u16::MAX as u64 {
+        u16::MAX
+    } else {
+        u16::from_le_bytes([bytes[0], bytes[1]])
+    }
+}
+
+const fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// 40 hex digits; anything else reads as all zeros
+const fn parse_hash(s: &str) -> [u8; GIT_HASH_LEN] {
+    let bytes = s.as_bytes();
+    let mut hash = [0u8; GIT_HASH_LEN];
+    if bytes.len() != 2 * GIT_HASH_LEN {
+        return hash;
+    }
+    let mut i = 0;
+    while i < GIT_HASH_LEN {
+        match (hex_digit(bytes[2 * i]), hex_digit(bytes[2 * i + 1])) {
+            (Some(hi), Some(lo)) => hash[i] = hi << 4 | lo,
+            _ => return [0; GIT_HASH_LEN],
+        }
+        i += 1;
+    }
+    hash
+}
+
+/// Low and high half of a register
+fn halves(reg: u32) -> [u16; 2] {
+    let [a, b, c, d] = reg.to_le_bytes();
+    [u16::from_le_bytes([a, b]), u16::from_le_bytes([c, d])]
+}
+
+impl FirmwareInfo {
+    /// Values for the mailbox version registers
+    #[must_use]
+    pub fn to_regs(&self) -> [u32; VERSION_REGS] {
+        let mut regs = [0u32; VERSION_REGS];
+        regs[0] = u32::from(self.semver.major) << 16 | u32::from(self.semver.minor);
+        regs[1] = u32::from(self.semver.patch) << 16 | u32::from(FORMAT);
+        regs[2] = self.features;
+        let bytes = self
+            .build_timestamp
+            .to_le_bytes()
+            .into_iter()
+            .chain(self.git_hash);
+        for (i, b) in bytes.enumerate() {
+            regs[3 + i / 4] |= u32::from(b) << (8 * (i % 4));
+        }
+        regs
+    }
+
+    pub fn from_regs(regs: &[u32; VERSION_REGS]) -> Result<Self, VersionError> {
+        let [minor, major] = halves(regs[0]);
+        let [format, patch] = halves(regs[1]);
+        if format != FORMAT {
+            return Err(VersionError::Format(format));
+        }
+        let mut git_hash = [0u8; GIT_HASH_LEN];
+        for (chunk, reg) in git_hash.chunks_exact_mut(4).zip(&regs[5..]) {
+            chunk.copy_from_slice(&reg.to_le_bytes());
+        }
+        Ok(Self {
+            semver: Semver {
+                major,
+                minor,
+                patch,
+            },
+            git_hash,
+            build_timestamp: u64::from(regs[4]) << 32 | u64::from(regs[3]),
+            features: regs[2],
+        })
+    }
+
+    /// Prints the version, commit, build time and features, for the boot
+    /// banner
+    pub fn print(&self, uart: &mut UartController<'_>) {
+        let Semver {
+            major,
+            minor,
+            patch,
+        } = self.semver;
+        write!(uart, "\rfirmware {major}.{minor}.{patch} (").unwrap();
+        for b in &self.git_hash[..4] {
+            write!(uart, "{b:02x}").unwrap();
+        }
+        writeln!(uart, ", built {})\r", self.build_timestamp).unwrap();
+        write!(uart, "features:").unwrap();
+        for (bit, name) in feature::NAMES {
+            if self.features & bit != 0 {
+                write!(uart, " {name}").unwrap();
+            }
+        }
+        writeln!(uart, "\r").unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        feature, parse_hash, parse_u16, FirmwareInfo, Semver, VersionError, FIRMWARE, FORMAT,
+        GIT_HASH_LEN, VERSION_REGS, VERSION_REG_BUDGET,
+    };
+
+    #[test]
+    fn test_regs_round_trip() {
+        let mut git_hash = [0u8; GIT_HASH_LEN];
+        for (b, v) in git_hash.iter_mut().zip((0..=255u8).rev()) {
+            *b = v;
+        }
+        let info = FirmwareInfo {
+            semver: Semver {
+                major: 1,
+                minor: 0xfffe,
+                patch: 300,
+            },
+            git_hash,
+            build_timestamp: 0x1_2345_6789,
+            features: feature::SHA384 | feature::I2C_TARGET,
+        };
+        let regs = info.to_regs();
+        assert_eq!(FirmwareInfo::from_regs(&regs), Ok(info));
+        assert_eq!(FirmwareInfo::from_regs(&FIRMWARE.to_regs()), Ok(FIRMWARE));
+
+        let mut bad = regs;
+        bad[1] = bad[1] & !0xffff | 7;
+        assert_eq!(FirmwareInfo::from_regs(&bad), Err(VersionError::Format(7)));
+    }
+
+    #[test]
+    fn test_register_budget() {
+        const { assert!(VERSION_REGS <= VERSION_REG_BUDGET) };
+        assert_eq!(FIRMWARE.to_regs()[1] & 0xffff, u32::from(FORMAT));
+        assert_eq!(feature::NAMES.len(), 13);
+        assert_eq!(
+            feature::NAMES.iter().fold(0, |bits, (bit, _)| bits | bit),
+            (1 << 13) - 1
+        );
+    }
+
+    #[test]
+    fn test_build_values() {
+        assert_eq!(
+            FIRMWARE.semver.major,
+            parse_u16(env!("CARGO_PKG_VERSION_MAJOR"))
+        );
+        assert_eq!(
+            FIRMWARE.features & feature::SHA384 != 0,
+            cfg!(feature = "sha384")
+        );
+        assert_eq!(parse_u16("70000"), u16::MAX);
+        assert_eq!(parse_u16("1x"), 0);
+        let hash = parse_hash("00112233445566778899aabbccddeeff0123ABCD");
+        assert_eq!(hash[..2], [0x00, 0x11]);
+        assert_eq!(hash[GIT_HASH_LEN - 2..], [0xab, 0xcd]);
+        assert_eq!(parse_hash("0011"), [0; GIT_HASH_LEN]);
+        assert_eq!(
+            parse_hash("g0112233445566778899aabbccddeeff0123abcd"),
+            [0; GIT_HASH_LEN]
+        );
+    }
+}
@@ -1,14 +1,20 @@
 // Licensed under the Apache-2.0 license
 
-use core::cmp::min;
+use crate::common::read_down_counter;
+use crate::syscon::{self, ClockConsumer, ClockId};
 use core::fmt;
 use core::marker::PhantomData;
 use embedded_hal_old::watchdog::{Disable, Enable, Watchdog};
+use fugit::MicrosDurationU32 as MicroSeconds;
 use fugit::MillisDurationU32 as MilliSeconds;
 
 #[derive(Debug)]
 pub enum WdtError {
     Unknown,
+    /// The clock source runs slower than the 1 MHz the counter needs
+    InvalidClock,
+    /// The timeout does not fit the 32-bit counter at the selected clock
+    TimeoutTooLong,
 }
 
 //abstracts register base access for different instances
@@ -44,9 +50,34 @@ impl WdtInstance for ast1060_pac::Wdt3 {
     }
 }
 
+/// What a watchdog timeout resets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WdtResetScope {
+    /// The whole chip, as on power-on
+    FullChip,
+    /// Only the ARM CPU; peripherals keep running
+    CpuOnly,
+    /// The SoC modules whose bits are set in the reset mask (WDT01C)
+    SocOnly(u32),
+}
+
+/// Clock the watchdog counter runs from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WdtClock {
+    /// Fixed 1 MHz reference from the crystal; keeps counting at the same
+    /// rate across PLL reconfiguration
+    OneMhz,
+    /// APB clock at the given rate in Hz; finer resolution, but follows
+    /// PCLK changes
+    Pclk(u32),
+}
+
 //generic
 pub struct WdtController<WDT: WdtInstance> {
     wdt: &'static ast1060_pac::wdt::RegisterBlock,
+    reset_scope: Option<WdtResetScope>,
+    clock: WdtClock,
+    tick_per_us: u32,
     _marker: PhantomData<WDT>,
 }
 
@@ -57,8 +88,19 @@ impl<WDT: WdtInstance> fmt::Debug for WdtController<WDT> {
 }
 
 const WDT_RATE_1MHZ: u32 = 1_000_000;
-const MAX_TIMEOUT_MS: u32 = 4_294_967;
+/// Microseconds two back-to-back counter reads may drift apart by
+const COUNTER_READ_DRIFT: u32 = 16;
+/// Read pairs tried before a counter read gives up settling
+const COUNTER_READ_ATTEMPTS: usize = 4;
+// WDT00C[4] counter clock select. Left clear for the 1 MHz reference, the
+// value the driver has always written; set only when PCLK is asked for
+const CLOCK_SEL_PCLK: u32 = 1 << 4;
 const RESTART_MAGIC: u16 = 0x4755;
+// WDT00C[6:5] reset system mode
+const RESET_MODE_SHIFT: u32 = 5;
+const RESET_MODE_SOC: u32 = 0b00;
+const RESET_MODE_FULL_CHIP: u32 = 0b01;
+const RESET_MODE_CPU: u32 = 0b10;
 
 impl<WDT: WdtInstance> Default for WdtController<WDT> {
     fn default() -> Self {
@@ -73,45 +115,110 @@ impl<WDT: WdtInstance> WdtController<WDT> {
         let wdt = unsafe { &*WDT::ptr() };
         Self {
             wdt,
+            reset_scope: None,
+            clock: WdtClock::OneMhz,
+            tick_per_us: 1,
             _marker: PhantomData,
         }
     }
 
-    /// Sets the watchdog timer timout period.
-    fn setup(&self, timeout_ms: MilliSeconds) {
-        assert!(
-            timeout_ms.to_millis() < MAX_TIMEOUT_MS,
-            "Watchdog timeout too high"
-        );
+    /// Selects the counter clock, taking effect on the next `start`.
+    ///
+    /// Timeouts are converted to counts at the selected rate, so a PCLK
+    /// source shortens the longest timeout accordingly.
+    pub fn set_clock_source(&mut self, clock: WdtClock) -> Result<(), WdtError> {
+        let tick_per_us = match clock {
+            WdtClock::OneMhz => 1,
+            WdtClock::Pclk(freq_hz) => freq_hz / WDT_RATE_1MHZ,
+        };
+        if tick_per_us == 0 {
+            return Err(WdtError::InvalidClock);
+        }
+        self.clock = clock;
+        self.tick_per_us = tick_per_us;
+        Ok(())
+    }
 
-        let actual = min(timeout_ms.to_millis(), MAX_TIMEOUT_MS);
+    /// Selects what a timeout resets, taking effect on the next `start`.
+    ///
+    /// Without a scope the hardware default (SoC reset with the reset
+    /// mask left as is) is used.
+    pub fn set_reset_scope(&mut self, scope: WdtResetScope) {
+        self.reset_scope = Some(scope);
+    }
 
-        self.wdt.wdt004().write(|w| unsafe {
-            w.counter_reload_value_reg()
-                .bits(actual / 1000 * WDT_RATE_1MHZ)
-        });
+    /// Sets the watchdog timer timout period.
+    fn setup(&self, timeout_ms: MilliSeconds) -> Result<(), WdtError> {
+        let counts = timeout_counts(timeout_ms.to_millis(), self.tick_per_us)
+            .ok_or(WdtError::TimeoutTooLong)?;
+
+        self.wdt
+            .wdt004()
+            .write(|w| unsafe { w.counter_reload_value_reg().bits(counts) });
 
         self.wdt
             .wdt008()
             .write(|w| unsafe { w.restart_reg().bits(RESTART_MAGIC) });
+        Ok(())
     }
 
-    pub fn start(&self, period: MilliSeconds) {
-        self.setup(period);
+    /// Starts the watchdog; fails with [`WdtError::TimeoutTooLong`] if
+    /// `period` does not fit the counter at the selected clock
+    pub fn start(&self, period: MilliSeconds) -> Result<(), WdtError> {
+        self.setup(period)?;
         self.wdt
             .wdt014()
             .write(|w| w.clear_timeout_boot_code_sel_and_intsts().set_bit());
 
+        let mode = match self.reset_scope {
+            None => RESET_MODE_SOC,
+            Some(WdtResetScope::FullChip) => RESET_MODE_FULL_CHIP,
+            Some(WdtResetScope::CpuOnly) => RESET_MODE_CPU,
+            Some(WdtResetScope::SocOnly(mask)) => {
+                self.wdt.wdt01c().write(|w| unsafe { w.bits(mask) });
+                RESET_MODE_SOC
+            }
+        };
+
+        let clock = match self.clock {
+            WdtClock::OneMhz => 0,
+            WdtClock::Pclk(_) => CLOCK_SEL_PCLK,
+        };
         self.wdt.wdt00c().write(|w| {
+            unsafe { w.bits((mode << RESET_MODE_SHIFT) | clock) };
             w.rst_sys_after_timeout().set_bit();
             w.wdtenbl_sig().set_bit()
         });
+        Ok(())
     }
 
     pub fn stop(&self) {
         self.wdt.wdt00c().write(|w| w.wdtenbl_sig().clear_bit());
     }
 
+    /// Returns `true` if the last reset was caused by this watchdog timing out.
+    ///
+    /// Must be read before `start`, which clears the timeout status.
+    #[must_use]
+    pub fn reset_by_timeout(&self) -> bool {
+        self.wdt.wdt010().read().bits() & 1 != 0
+    }
+
+    /// Time left before the watchdog fires, for deciding whether there is
+    /// room to finish an operation before the next feed.
+    ///
+    /// The count is converted at the selected clock rate; a read that
+    /// lands mid-update is retried.
+    #[must_use]
+    pub fn remaining(&self) -> MicroSeconds {
+        let count = read_down_counter(
+            || self.wdt.wdt000().read().bits(),
+            COUNTER_READ_DRIFT * self.tick_per_us,
+            COUNTER_READ_ATTEMPTS,
+        );
+        MicroSeconds::from_ticks(count / self.tick_per_us)
+    }
+
     pub fn feed(&mut self) {
         self.wdt
             .wdt008()
@@ -119,6 +226,28 @@ impl<WDT: WdtInstance> WdtController<WDT> {
     }
 }
 
+/// Counter reload value for `timeout_ms` at `tick_per_us`, if it fits
+fn timeout_counts(timeout_ms: u32, tick_per_us: u32) -> Option<u32> {
+    u32::try_from(u64::from(timeout_ms) * 1000 * u64::from(tick_per_us)).ok()
+}
+
+/// With a PCLK source the tick rate follows PCLK changes; a running
+/// watchdog keeps its old reload value until restarted. The 1 MHz source
+/// is unaffected.
+impl<WDT: WdtInstance> ClockConsumer for WdtController<WDT> {
+    fn clock_id(&self) -> ClockId {
+        ClockId::ClkPCLK
+    }
+
+    fn reconfigure_for_clock(&mut self, freq_hz: u32) -> Result<(), syscon::Error> {
+        if let WdtClock::Pclk(_) = self.clock {
+            self.set_clock_source(WdtClock::Pclk(freq_hz))
+                .map_err(|_| syscon::Error::InvalidClockFrequency)?;
+        }
+        Ok(())
+    }
+}
+
 impl<WDT: WdtInstance> Disable for WdtController<WDT> {
     type Error = WdtError;
     type Target = WdtController<WDT>;
@@ -135,7 +264,7 @@ impl<WDT: WdtInstance> Enable for WdtController<WDT> {
     type Time = MilliSeconds;
 
     fn try_start<T: Into<Self::Time>>(self, period: T) -> Result<Self::Target, Self::Error> {
-        self.start(period.into());
+        self.start(period.into())?;
         Ok(self)
     }
 }
@@ -148,3 +277,18 @@ impl<WDT: WdtInstance> Watchdog for WdtController<WDT> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_counts() {
+        assert_eq!(timeout_counts(1500, 1), Some(1_500_000));
+        assert_eq!(timeout_counts(4_294_967, 1), Some(4_294_967_000));
+        assert_eq!(timeout_counts(4_294_968, 1), None);
+        assert_eq!(timeout_counts(1000, 50), Some(50_000_000));
+        // 50 MHz overflows the 32-bit counter past ~85 s
+        assert_eq!(timeout_counts(86_000, 50), None);
+    }
+}
@@ -200,6 +200,21 @@ def run_qemu_with_timeout(binary_path, machine, capture_output, output_file, tim
         print_error(f"Error running QEMU: {e}")
         return False
 
+def check_early_console(output_file):
+    """The pre_init early console line must come before the main banner"""
+    with open(output_file, errors="replace") as f:
+        log = f.read()
+    early = log.find("early console up")
+    banner = log.find("Hello, world!!")
+    if early == -1:
+        print_error("Early console output missing")
+        return False
+    if banner != -1 and early > banner:
+        print_error("Early console output after the main banner")
+        return False
+    print_step("Early console output precedes the main banner")
+    return True
+
 def main():
     parser = argparse.ArgumentParser(description="ASPEED QEMU Test Script")
     parser.add_argument("--release", action="store_true",
@@ -262,6 +277,7 @@ def main():
     if capture_output and output_file and os.path.isfile(output_file):
         print_step(f"Output captured in: {output_file}")
         print(f"To view the output: cat {output_file}")
+        success = check_early_console(output_file) and success
     
     if success:
         print_step("QEMU test completed successfully!")
@@ -3,8 +3,32 @@
 use anyhow::{Context, Result};
 use std::process::Command;
 
+/// Feature selection passed through to `cargo bloat`
+#[derive(Default)]
+pub struct FeatureArgs {
+    pub features: Vec<String>,
+    pub no_default_features: bool,
+}
+
+impl FeatureArgs {
+    fn apply(&self, cmd: &mut Command) {
+        if self.no_default_features {
+            cmd.arg("--no-default-features");
+        }
+        if !self.features.is_empty() {
+            cmd.arg("--features");
+            cmd.arg(self.features.join(","));
+        }
+    }
+}
+
 /// Run cargo bloat analysis and generate size report
-pub fn analyze_bloat(release: bool, target: &str, format: BloatFormat) -> Result<()> {
+pub fn analyze_bloat(
+    release: bool,
+    target: &str,
+    features: &FeatureArgs,
+    format: BloatFormat,
+) -> Result<()> {
     println!("Running binary size analysis...");
 
     let mut cmd = Command::new("cargo");
@@ -15,6 +39,7 @@ pub fn analyze_bloat(release: bool, target: &str, format: BloatFormat) -> Result
     }
 
     cmd.args(["--target", target]);
+    features.apply(&mut cmd);
 
     match format {
         BloatFormat::Table => {
@@ -44,7 +69,12 @@ pub fn analyze_bloat(release: bool, target: &str, format: BloatFormat) -> Result
 }
 
 /// Generate detailed bloat report with multiple views
-pub fn generate_report(release: bool, target: &str, output_dir: &str) -> Result<()> {
+pub fn generate_report(
+    release: bool,
+    target: &str,
+    features: &FeatureArgs,
+    output_dir: &str,
+) -> Result<()> {
     println!("Generating comprehensive binary size report...");
 
     // Create output directory
@@ -69,6 +99,7 @@ pub fn generate_report(release: bool, target: &str, output_dir: &str) -> Result<
         }
 
         cmd.args(["--target", target]);
+        features.apply(&mut cmd);
         cmd.arg(flag);
 
         let output = cmd
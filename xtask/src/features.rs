@@ -0,0 +1,70 @@
+// Licensed under the Apache-2.0 license
+
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+static PROJECT_ROOT: Lazy<PathBuf> = Lazy::new(|| {
+    Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf()
+});
+
+/// Feature sets built with `--no-default-features`. The first is the
+/// smallest image (SHA-256 only); each crypto feature is also built alone
+/// so a missing `cfg` on a shared item shows up here.
+const FEATURE_SETS: &[&str] = &[
+    "cache",
+    "cache,sha384",
+    "cache,sha512",
+    "cache,hmac",
+    "cache,hmac,sha384",
+    "cache,ecdsa",
+    "cache,rsa",
+    "cache,ecdsa,rsa",
+    "sha384,sha512,hmac,rsa,ecdsa",
+    "cache,sha384,sha512,hmac,rsa,ecdsa,rt,i2c_target,test-utils",
+];
+
+/// Build every entry of [`FEATURE_SETS`] and report which ones fail
+pub fn check_matrix(target: &str) -> Result<()> {
+    println!("Building {} feature sets...", FEATURE_SETS.len());
+
+    let mut failed = Vec::new();
+    for features in FEATURE_SETS {
+        println!("--- features: {features}");
+        let status = Command::new("cargo")
+            .current_dir(&*PROJECT_ROOT)
+            .args([
+                "build",
+                "--workspace",
+                "--exclude",
+                "xtask",
+                "--target",
+                target,
+                "--no-default-features",
+                "--features",
+                features,
+            ])
+            .status()?;
+        if !status.success() {
+            failed.push(*features);
+        }
+    }
+
+    if !failed.is_empty() {
+        for features in &failed {
+            println!("❌ {features}");
+        }
+        bail!(
+            "{} of {} feature sets failed to build",
+            failed.len(),
+            FEATURE_SETS.len()
+        );
+    }
+
+    println!("✅ All feature sets build");
+    Ok(())
+}
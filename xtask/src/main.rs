@@ -1,15 +1,21 @@
 // Licensed under the Apache-2.0 license
 
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 mod bloat;
 mod build;
 mod clippy;
 mod docs;
+mod features;
 mod format;
 mod header;
+mod telemetry;
 mod test;
+mod vectors;
+
+/// Where `gen-vectors` writes by default
+const VECTORS_PATH: &str = "src/tests/vectors_generated.rs";
 
 #[derive(Parser)]
 #[command(version, about = "ASPEED DDK development automation tasks", long_about = None)]
@@ -38,6 +44,13 @@ enum Commands {
     /// Run clippy on all targets
     Clippy,
 
+    /// Build the crate with each supported combination of crypto features
+    FeatureMatrix {
+        /// Target architecture
+        #[arg(long, default_value = "thumbv7em-none-eabihf")]
+        target: String,
+    },
+
     /// Build documentation
     Docs {
         /// Open documentation after building
@@ -83,6 +96,24 @@ enum Commands {
         output: PathBuf,
     },
 
+    /// Generate the hash, HMAC and HKDF test vector tables with host crypto
+    GenVectors {
+        /// Output file, relative to the project root
+        #[arg(long, default_value = VECTORS_PATH)]
+        out: PathBuf,
+
+        /// Only verify that the committed file is up to date
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Decode the telemetry frames in a UART capture
+    DecodeTelemetry {
+        /// Capture file; console text between frames is skipped
+        #[arg(long)]
+        input: PathBuf,
+    },
+
     /// Run functional tests on hardware
     HardwareTest {
         /// UART device path (e.g., /dev/ttyUSB0)
@@ -111,6 +142,15 @@ enum Commands {
         /// Output directory for reports
         #[arg(long, default_value = "target/bloat-reports")]
         output_dir: String,
+
+        /// Features to enable, e.g. `cache` with --no-default-features for
+        /// a SHA-256 only image
+        #[arg(long)]
+        features: Vec<String>,
+
+        /// Do not enable the default features
+        #[arg(long)]
+        no_default_features: bool,
     },
 }
 
@@ -124,6 +164,7 @@ fn main() -> anyhow::Result<()> {
             features,
         } => build::build(release, &target, &features),
         Commands::Clippy => clippy::clippy(),
+        Commands::FeatureMatrix { target } => features::check_matrix(&target),
         Commands::Docs { open } => docs::docs(open),
         Commands::Format { fix } => format::format(fix),
         Commands::HeaderCheck => header::check(),
@@ -131,6 +172,8 @@ fn main() -> anyhow::Result<()> {
         Commands::Test { unit, integration } => test::test(unit, integration),
         Commands::Precommit => precommit(),
         Commands::GenBootImage { input, output } => build::gen_boot_image(&input, &output),
+        Commands::GenVectors { out, check } => vectors::gen_vectors(&out, check),
+        Commands::DecodeTelemetry { input } => telemetry::decode_telemetry(&input),
         Commands::HardwareTest { uart, suite } => {
             test::hardware_test(uart.as_deref(), suite.as_deref())
         }
@@ -139,11 +182,17 @@ fn main() -> anyhow::Result<()> {
             target,
             report,
             output_dir,
+            features,
+            no_default_features,
         } => {
+            let features = bloat::FeatureArgs {
+                features,
+                no_default_features,
+            };
             if report {
-                bloat::generate_report(release, &target, &output_dir)
+                bloat::generate_report(release, &target, &features, &output_dir)
             } else {
-                bloat::analyze_bloat(release, &target, bloat::BloatFormat::Table)
+                bloat::analyze_bloat(release, &target, &features, bloat::BloatFormat::Table)
             }
         }
     }
@@ -161,12 +210,20 @@ fn precommit() -> anyhow::Result<()> {
     // Check license headers
     header::check()?;
 
+    // Check generated test vectors
+    vectors::gen_vectors(Path::new(VECTORS_PATH), true)?;
+
     // Run tests
     test::test(false, false)?;
 
     // Generate size analysis report (optional - skip if cargo-bloat not available)
     println!("Generating binary size report...");
-    match bloat::generate_report(true, "thumbv7em-none-eabihf", "target/bloat-reports") {
+    match bloat::generate_report(
+        true,
+        "thumbv7em-none-eabihf",
+        &bloat::FeatureArgs::default(),
+        "target/bloat-reports",
+    ) {
         Ok(_) => println!("📊 Size analysis report generated"),
         Err(e) => {
             println!("⚠️  Size analysis skipped: {}", e);
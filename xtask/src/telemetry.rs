@@ -0,0 +1,169 @@
+// Licensed under the Apache-2.0 license
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Must match `aspeed_ddk::telemetry`
+const SYNC: [u8; 2] = [0xa5, 0x5a];
+
+/// `boot_trace::Milestone` names, in declaration order
+const MILESTONES: &[&str] = &[
+    "start",
+    "uart up",
+    "clock init",
+    "hace post done",
+    "hmac tests",
+    "ecdsa tests",
+    "rsa tests",
+    "gpio tests",
+    "i2c tests",
+    "wdt tests",
+    "timer tests",
+    "host policy tests",
+    "interrupt tests",
+    "spi tests",
+    "done",
+];
+
+/// CRC-16/CCITT-FALSE, as computed by the firmware
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xffffu16;
+    for &b in data {
+        crc ^= u16::from(b) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+fn u32s(payload: &[u8]) -> Vec<u32> {
+    payload
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+fn describe(kind: u8, payload: &[u8]) -> String {
+    match (kind, payload.len()) {
+        (1, len) if len >= 2 && (len - 2) % 5 == 0 => {
+            let dropped = u16::from_le_bytes([payload[0], payload[1]]);
+            let mut text = format!("boot trace ({dropped} dropped)");
+            for record in payload[2..].chunks_exact(5) {
+                let name = MILESTONES
+                    .get(usize::from(record[0]))
+                    .copied()
+                    .unwrap_or("?");
+                let delta = u32::from_le_bytes([record[1], record[2], record[3], record[4]]);
+                text += &format!("\n  {name:<20} +{delta} cycles");
+            }
+            text
+        }
+        (2, 24) => {
+            let w = u32s(payload);
+            format!(
+                "latency: {} samples, {} overflow, min {} ns, p50 {} ns, p99 {} ns, max {} ns",
+                w[0], w[1], w[2], w[3], w[4], w[5]
+            )
+        }
+        (3, 16) => {
+            let w = u32s(payload);
+            format!(
+                "test summary: {} passed, {} failed, {} suites, {} failed",
+                w[0], w[1], w[2], w[3]
+            )
+        }
+        _ => format!("type {kind}, {} bytes: {payload:02x?}", payload.len()),
+    }
+}
+
+/// Finds and checks the frames in `data`; bytes between frames, such as
+/// console text, are skipped
+fn decode_all(data: &[u8]) -> (Vec<String>, usize) {
+    let mut frames = Vec::new();
+    let mut bad = 0;
+    let mut pos = 0;
+    while let Some(start) = data[pos..].windows(2).position(|w| w == SYNC) {
+        let at = pos + start;
+        let len = data.get(at + 3).map_or(0, |&len| usize::from(len));
+        let frame = data.get(at..at + 4 + len + 2);
+        if let Some(frame) = frame.filter(|f| {
+            let crc = u16::from_le_bytes([f[f.len() - 2], f[f.len() - 1]]);
+            crc16(&f[2..f.len() - 2]) == crc
+        }) {
+            frames.push(format!(
+                "@{at:#x} {}",
+                describe(frame[2], &frame[4..frame.len() - 2])
+            ));
+            pos = at + frame.len();
+        } else {
+            // a sync pattern inside text, or a damaged or truncated frame
+            bad += 1;
+            pos = at + 1;
+        }
+    }
+    (frames, bad)
+}
+
+pub fn decode_telemetry(input: &Path) -> Result<()> {
+    let data = fs::read(input).with_context(|| format!("reading {}", input.display()))?;
+    let (frames, bad) = decode_all(&data);
+    for frame in &frames {
+        println!("{frame}");
+    }
+    println!("{} frames, {} bad CRCs", frames.len(), bad);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(kind: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = SYNC.to_vec();
+        frame.push(kind);
+        frame.push(payload.len().try_into().unwrap());
+        frame.extend_from_slice(payload);
+        let crc = crc16(&frame[2..]);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        frame
+    }
+
+    #[test]
+    fn crc_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+    }
+
+    #[test]
+    fn frames_between_console_text() {
+        let mut data = b"booting\r\n".to_vec();
+        let mut trace = vec![0, 0, 1];
+        trace.extend_from_slice(&250u32.to_le_bytes());
+        data.extend(frame(1, &trace));
+        data.extend_from_slice(b"\xa5\x5a garbage\r\n");
+        let summary: Vec<u8> = [5u32, 1, 3, 1]
+            .iter()
+            .flat_map(|w| w.to_le_bytes())
+            .collect();
+        data.extend(frame(3, &summary));
+
+        let (frames, bad) = decode_all(&data);
+        assert_eq!(bad, 1);
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0].contains("uart up"));
+        assert!(frames[0].contains("+250 cycles"));
+        assert!(frames[1].ends_with("5 passed, 1 failed, 3 suites, 1 failed"));
+    }
+
+    #[test]
+    fn damaged_frame_is_counted() {
+        let mut data = frame(2, &[0; 24]);
+        data[6] ^= 1;
+        assert_eq!(decode_all(&data), (Vec::new(), 1));
+    }
+}
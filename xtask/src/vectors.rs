@@ -0,0 +1,224 @@
+// Licensed under the Apache-2.0 license
+
+use anyhow::{bail, Context, Result};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+static PROJECT_ROOT: Lazy<PathBuf> = Lazy::new(|| {
+    Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf()
+});
+
+const HEADER: &str = "// Licensed under the Apache-2.0 license
+
+// @generated by `cargo xtask gen-vectors`. Do not edit; change the tables in
+// xtask/src/vectors.rs and regenerate.
+";
+
+/// Message lengths around the padding and block boundaries of the 64-byte
+/// (SHA-256) and 128-byte (SHA-384/512) blocks
+const HASH_LENS: &[usize] = &[0, 3, 55, 56, 63, 64, 65, 111, 112, 127, 128, 129, 200];
+
+/// HMAC message lengths; keys have the fixed size of each `hmac` algorithm
+const HMAC_LENS: &[usize] = &[0, 43, 64, 200];
+
+/// HKDF-SHA-256 output lengths: one block, a partial second block and the
+/// RFC 5869 case 1 length
+const HKDF_LENS: &[usize] = &[32, 42, 82];
+
+/// Deterministic test data; `seed` tells messages, keys and salts apart
+fn pattern(len: usize, seed: u8) -> Vec<u8> {
+    (0..len)
+        .map(|i| (i as u8).wrapping_mul(seed | 1).wrapping_add(seed))
+        .collect()
+}
+
+fn bytes(out: &mut String, name: &str, data: &[u8]) {
+    write!(out, "{name}: &[").unwrap();
+    for b in data {
+        write!(out, "0x{b:02x}, ").unwrap();
+    }
+    out.push_str("],\n");
+}
+
+fn hash_table<D: Digest>(out: &mut String, name: &str) {
+    writeln!(out, "\npub const {name}: &[HashVector] = &[").unwrap();
+    for &len in HASH_LENS {
+        let msg = pattern(len, 0x11);
+        out.push_str("HashVector {\n");
+        bytes(out, "msg", &msg);
+        bytes(out, "digest", &D::digest(&msg));
+        out.push_str("},\n");
+    }
+    out.push_str("];\n");
+}
+
+fn hmac_table<M: Mac + hmac::digest::KeyInit>(out: &mut String, name: &str, key_len: usize) {
+    let key = pattern(key_len, 0x0b);
+    writeln!(out, "\npub const {name}: &[HmacVector] = &[").unwrap();
+    for &len in HMAC_LENS {
+        let msg = pattern(len, 0x23);
+        let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(&key).unwrap();
+        mac.update(&msg);
+        out.push_str("HmacVector {\n");
+        bytes(out, "key", &key);
+        bytes(out, "msg", &msg);
+        bytes(out, "mac", &mac.finalize().into_bytes());
+        out.push_str("},\n");
+    }
+    out.push_str("];\n");
+}
+
+fn hkdf_table(out: &mut String) {
+    let ikm = pattern(22, 0x0b);
+    let salt = pattern(13, 0x30);
+    let info = pattern(10, 0xf0);
+    let hk = Hkdf::<Sha256>::new(Some(&salt), &ikm);
+    out.push_str("\npub const HKDF_SHA256: &[HkdfVector] = &[\n");
+    for &len in HKDF_LENS {
+        let mut okm = vec![0u8; len];
+        hk.expand(&info, &mut okm).unwrap();
+        out.push_str("HkdfVector {\n");
+        bytes(out, "ikm", &ikm);
+        bytes(out, "salt", &salt);
+        bytes(out, "info", &info);
+        bytes(out, "okm", &okm);
+        out.push_str("},\n");
+    }
+    out.push_str("];\n");
+}
+
+/// The generated file, before formatting
+fn render() -> String {
+    let mut out = String::from(HEADER);
+    out.push_str(
+        "
+pub struct HashVector {
+    pub msg: &'static [u8],
+    pub digest: &'static [u8],
+}
+
+pub struct HmacVector {
+    pub key: &'static [u8],
+    pub msg: &'static [u8],
+    pub mac: &'static [u8],
+}
+
+pub struct HkdfVector {
+    pub ikm: &'static [u8],
+    pub salt: &'static [u8],
+    pub info: &'static [u8],
+    pub okm: &'static [u8],
+}
+",
+    );
+    hash_table::<Sha256>(&mut out, "SHA256");
+    hash_table::<Sha384>(&mut out, "SHA384");
+    hash_table::<Sha512>(&mut out, "SHA512");
+    hmac_table::<Hmac<Sha256>>(&mut out, "HMAC_SHA256", 32);
+    hmac_table::<Hmac<Sha384>>(&mut out, "HMAC_SHA384", 48);
+    hmac_table::<Hmac<Sha512>>(&mut out, "HMAC_SHA512", 64);
+    hkdf_table(&mut out);
+    out
+}
+
+/// Runs `source` through rustfmt so the output passes `cargo xtask format`
+fn rustfmt(source: &str) -> Result<String> {
+    let mut child = Command::new("rustfmt")
+        .current_dir(&*PROJECT_ROOT)
+        .args(["--edition", "2021", "--emit", "stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("failed to run rustfmt")?;
+    child.stdin.take().unwrap().write_all(source.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("rustfmt rejected the generated vectors");
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Fails if `path` does not hold exactly `expected`
+fn check_file(path: &Path, expected: &str) -> Result<()> {
+    let current =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    if current != expected {
+        bail!(
+            "{} is out of date. Run 'cargo xtask gen-vectors' to regenerate it.",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+pub fn gen_vectors(out: &Path, check: bool) -> Result<()> {
+    let path = PROJECT_ROOT.join(out);
+    let generated = rustfmt(&render())?;
+
+    if check {
+        println!("Checking {}...", out.display());
+        check_file(&path, &generated)?;
+        println!("✅ Test vectors are up to date");
+    } else {
+        fs::write(&path, generated)?;
+        println!("✅ Wrote {}", out.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_source_parses() {
+        let file = syn::parse_file(&render()).unwrap();
+        let consts = file
+            .items
+            .iter()
+            .filter(|item| matches!(item, syn::Item::Const(_)))
+            .count();
+        assert_eq!(consts, 7);
+    }
+
+    #[test]
+    fn generated_source_has_header() {
+        assert!(render().starts_with("// Licensed under the Apache-2.0 license\n"));
+        assert!(render().contains("@generated"));
+    }
+
+    #[test]
+    fn sha256_matches_fips_example() {
+        // FIPS 180-4 example "abc", to catch a broken hash backend
+        let digest = Sha256::digest(b"abc");
+        assert_eq!(digest[..4], [0xba, 0x78, 0x16, 0xbf]);
+    }
+
+    #[test]
+    fn check_detects_mismatch() {
+        let expected = render();
+        let path = std::env::temp_dir().join(format!("vectors-{}.rs", std::process::id()));
+
+        fs::write(&path, &expected).unwrap();
+        assert!(check_file(&path, &expected).is_ok());
+
+        // flip one nibble of the first digest
+        let mut tampered = expected.clone().into_bytes();
+        let at = expected.find("digest: &[0x").unwrap() + "digest: &[0x".len();
+        tampered[at] = if tampered[at] == b'0' { b'1' } else { b'0' };
+        fs::write(&path, tampered).unwrap();
+        assert!(check_file(&path, &expected).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}